@@ -0,0 +1,126 @@
+"use strict";
+
+const state = { key: sessionStorage.getItem("p-mo-admin-key") || "", collection: null, nextOffset: null };
+
+async function api(path, options = {}) {
+  const response = await fetch("/ui/api" + path, {
+    ...options,
+    headers: { "x-api-key": state.key, ...(options.headers || {}) },
+  });
+  const body = await response.json();
+  if (!response.ok) {
+    throw new Error(body.error || response.statusText);
+  }
+  return body;
+}
+
+function setStatus(message) {
+  document.getElementById("status").textContent = message || "";
+}
+
+async function loadCollections() {
+  try {
+    const body = await api("/collections");
+    const list = document.getElementById("collections");
+    list.innerHTML = "";
+    for (const name of body.collections) {
+      const item = document.createElement("li");
+      item.textContent = name;
+      item.onclick = () => selectCollection(name, item);
+      list.appendChild(item);
+    }
+    setStatus("");
+  } catch (e) {
+    setStatus(e.message);
+  }
+}
+
+function selectCollection(name, item) {
+  document.querySelectorAll("#collections li").forEach((li) => li.classList.remove("selected"));
+  item.classList.add("selected");
+  state.collection = name;
+  browse(null);
+}
+
+function renderEntries(entries, append) {
+  const body = document.getElementById("entries");
+  if (!append) {
+    body.innerHTML = "";
+  }
+  for (const entry of entries) {
+    const row = document.createElement("tr");
+    const id = document.createElement("td");
+    id.textContent = entry.id;
+    const content = document.createElement("td");
+    content.className = "content";
+    content.textContent = entry.content;
+    content.onclick = () => {
+      document.getElementById("details").textContent = JSON.stringify(entry, null, 2);
+    };
+    const score = document.createElement("td");
+    score.textContent = entry.score === undefined ? "" : entry.score.toFixed(3);
+    const actions = document.createElement("td");
+    const remove = document.createElement("button");
+    remove.textContent = "Delete";
+    remove.onclick = () => deleteEntry(entry.id, row);
+    actions.appendChild(remove);
+    row.append(id, content, score, actions);
+    body.appendChild(row);
+  }
+}
+
+async function browse(offset) {
+  if (!state.collection) return;
+  const params = new URLSearchParams({ limit: "50" });
+  if (offset) params.set("offset", offset);
+  try {
+    const body = await api(`/collections/${encodeURIComponent(state.collection)}/entries?${params}`);
+    renderEntries(body.entries, Boolean(offset));
+    state.nextOffset = body.next_offset;
+    document.getElementById("next-page").hidden = !state.nextOffset;
+    setStatus("");
+  } catch (e) {
+    setStatus(e.message);
+  }
+}
+
+async function search(query) {
+  if (!state.collection) return;
+  const params = new URLSearchParams({ q: query, limit: "20" });
+  try {
+    const body = await api(`/collections/${encodeURIComponent(state.collection)}/search?${params}`);
+    renderEntries(body.results, false);
+    document.getElementById("next-page").hidden = true;
+    setStatus("");
+  } catch (e) {
+    setStatus(e.message);
+  }
+}
+
+async function deleteEntry(id, row) {
+  if (!confirm(`Delete entry ${id}?`)) return;
+  try {
+    await api(`/collections/${encodeURIComponent(state.collection)}/entries/${encodeURIComponent(id)}`, { method: "DELETE" });
+    row.remove();
+  } catch (e) {
+    setStatus(e.message);
+  }
+}
+
+document.getElementById("api-key").value = state.key;
+document.getElementById("key-form").onsubmit = (event) => {
+  event.preventDefault();
+  state.key = document.getElementById("api-key").value;
+  sessionStorage.setItem("p-mo-admin-key", state.key);
+  loadCollections();
+};
+document.getElementById("search-form").onsubmit = (event) => {
+  event.preventDefault();
+  search(document.getElementById("query").value);
+};
+document.getElementById("browse").onclick = () => browse(null);
+document.getElementById("next-page").onclick = () => browse(state.nextOffset);
+
+if (state.key) {
+  loadCollections();
+}
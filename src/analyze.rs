@@ -0,0 +1,384 @@
+//! Chunking analysis.
+//!
+//! Larger chunks mean fewer embeddings to compute and store; smaller ones
+//! keep each embedding about one thing. [`analyze`] chunks a sample of a
+//! corpus with each candidate strategy and measures the trade-off. Given an
+//! eval set and an embedding provider it also scores retrieval over the
+//! sample, so the recommendation follows real queries rather than lengths.
+
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use thiserror::Error;
+use tracing::debug;
+
+use crate::eval::{evaluate, EvalError, EvalSet};
+use crate::sources::files::collect_files;
+use crate::sources::{extract, FileType};
+use crate::text_processing::{count_tokens, ChunkingStrategy, EmbeddingError, EmbeddingProvider, TextProcessor, TokenizerConfig};
+use crate::vector_store::cosine_similarity;
+
+/// Documents read from a corpus when no sample size is given
+pub const DEFAULT_SAMPLE: usize = 50;
+
+/// Mean chunk length preferred when there is no eval set to decide by
+pub const TARGET_CHUNK_TOKENS: usize = 256;
+
+/// Longest chunk a recommendation may produce without an eval set, so no
+/// chunk is truncated by the embedding model
+pub const MAX_CHUNK_TOKENS: usize = 512;
+
+#[derive(Debug, Error)]
+pub enum AnalyzeError {
+    #[error("No readable documents to analyze")]
+    NoDocuments,
+    
+    #[error("No chunking strategies to compare")]
+    NoCandidates,
+    
+    #[error("Embedding failed: {0}")]
+    Embedding(#[from] EmbeddingError),
+    
+    #[error(transparent)]
+    Eval(#[from] EvalError),
+}
+
+/// The strategies tried when none are given
+pub fn default_candidates() -> Vec<ChunkingStrategy> {
+    vec![
+        ChunkingStrategy::FixedSize(128),
+        ChunkingStrategy::FixedSize(256),
+        ChunkingStrategy::FixedSize(512),
+        ChunkingStrategy::Paragraph,
+        ChunkingStrategy::Semantic,
+    ]
+}
+
+/// One document of the sample
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleDocument {
+    /// The document's path, which eval cases name in `expected`
+    pub id: String,
+    pub text: String,
+}
+
+/// Read up to `sample` documents spread evenly over the files under `paths`.
+///
+/// Files of unknown types and files that cannot be read are skipped.
+pub fn sample_documents(paths: &[PathBuf], sample: usize) -> Vec<SampleDocument> {
+    let (files, _) = collect_files(paths);
+    let files: Vec<PathBuf> = files.into_iter()
+        .filter(|path| FileType::from_key(&path.to_string_lossy()).is_some())
+        .collect();
+    let picked: Vec<&PathBuf> = if files.len() <= sample {
+        files.iter().collect()
+    } else {
+        (0..sample).map(|i| &files[i * files.len() / sample]).collect()
+    };
+    
+    picked.into_iter()
+        .filter_map(|path| {
+            let key = path.to_string_lossy();
+            let file_type = FileType::from_key(&key)?;
+            let bytes = fs::read(path)
+                .map_err(|e| debug!(path = %path.display(), error = %e, "Skipping unreadable file"))
+                .ok()?;
+            let pieces = extract(&key, file_type, &bytes)
+                .map_err(|e| debug!(path = %path.display(), error = %e, "Skipping file without text"))
+                .ok()?;
+            let text = pieces.into_iter().map(|piece| piece.content).collect::<Vec<_>>().join("\n\n");
+            (!text.trim().is_empty()).then(|| SampleDocument { id: path.display().to_string(), text })
+        })
+        .collect()
+}
+
+/// Retrieval quality of one strategy over the sample
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RetrievalScores {
+    pub recall: f64,
+    pub mrr: f64,
+    pub ndcg: f64,
+}
+
+/// What one strategy makes of the sample
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CandidateReport {
+    pub strategy: ChunkingStrategy,
+    /// Chunks produced, each needing one embedding
+    pub embeddings: usize,
+    pub mean_tokens: f64,
+    pub max_tokens: usize,
+    /// Scores over the eval set, when one was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retrieval: Option<RetrievalScores>,
+}
+
+/// The measurements of every candidate and the one recommended
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Analysis {
+    /// Documents in the sample
+    pub documents: usize,
+    pub candidates: Vec<CandidateReport>,
+    pub recommended: ChunkingStrategy,
+    /// Why the recommended strategy was chosen
+    pub reason: String,
+}
+
+impl Analysis {
+    /// Render the candidates as a Markdown table followed by the recommendation
+    pub fn render_markdown(&self) -> String {
+        let mut out = format!(
+            "Sampled {} documents\n\n| strategy | embeddings | mean tokens | max tokens | recall | MRR | NDCG |\n|---|---|---|---|---|---|---|\n",
+            self.documents
+        );
+        for candidate in &self.candidates {
+            let scores = match candidate.retrieval {
+                Some(scores) => format!("{:.4} | {:.4} | {:.4}", scores.recall, scores.mrr, scores.ndcg),
+                None => "- | - | -".to_string(),
+            };
+            let marker = if candidate.strategy == self.recommended { " *" } else { "" };
+            out.push_str(&format!(
+                "| {}{} | {} | {:.1} | {} | {} |\n",
+                candidate.strategy, marker, candidate.embeddings, candidate.mean_tokens, candidate.max_tokens, scores
+            ));
+        }
+        out.push_str(&format!("\nRecommended: {} ({})\n", self.recommended, self.reason));
+        out
+    }
+}
+
+/// An eval set to score candidates by, with the provider embedding chunks and queries
+pub struct Retrieval<'a> {
+    pub set: &'a EvalSet,
+    pub provider: &'a dyn EmbeddingProvider,
+    /// Number of documents scored per query
+    pub k: usize,
+}
+
+struct Chunk {
+    document: usize,
+    content: String,
+}
+
+/// Chunk `documents` with each candidate, measure the results and recommend one
+pub fn analyze(
+    documents: &[SampleDocument],
+    candidates: &[ChunkingStrategy],
+    retrieval: Option<Retrieval<'_>>,
+) -> Result<Analysis, AnalyzeError> {
+    if documents.is_empty() {
+        return Err(AnalyzeError::NoDocuments);
+    }
+    if candidates.is_empty() {
+        return Err(AnalyzeError::NoCandidates);
+    }
+    
+    // Queries are the same for every candidate, so embed them once
+    let queries: Vec<String> = retrieval.as_ref()
+        .map(|retrieval| retrieval.set.cases.iter().map(|case| case.query.clone()).collect())
+        .unwrap_or_default();
+    let query_embeddings = match &retrieval {
+        Some(retrieval) if !queries.is_empty() => retrieval.provider.generate_embeddings(&queries)?,
+        _ => Vec::new(),
+    };
+    
+    let mut reports = Vec::with_capacity(candidates.len());
+    for &strategy in candidates {
+        let chunks = chunk_documents(documents, strategy);
+        let tokens: Vec<usize> = chunks.iter().map(|chunk| count_tokens(&chunk.content)).collect();
+        let mean_tokens = if tokens.is_empty() { 0.0 } else { tokens.iter().sum::<usize>() as f64 / tokens.len() as f64 };
+        
+        let scores = match &retrieval {
+            Some(retrieval) => Some(score(documents, &chunks, &queries, &query_embeddings, retrieval)?),
+            None => None,
+        };
+        reports.push(CandidateReport {
+            strategy,
+            embeddings: chunks.len(),
+            mean_tokens,
+            max_tokens: tokens.iter().copied().max().unwrap_or(0),
+            retrieval: scores,
+        });
+    }
+    
+    let (recommended, reason) = recommend(&reports, retrieval.as_ref().map(|retrieval| retrieval.k));
+    Ok(Analysis { documents: documents.len(), candidates: reports, recommended, reason })
+}
+
+fn chunk_documents(documents: &[SampleDocument], strategy: ChunkingStrategy) -> Vec<Chunk> {
+    let processor = TextProcessor::new(TokenizerConfig::default(), strategy);
+    documents.iter()
+        .enumerate()
+        .flat_map(|(document, sample)| {
+            processor.chunk(&sample.text)
+                .into_iter()
+                .map(|chunk| chunk.content.trim().to_string())
+                .filter(|content| !content.is_empty())
+                .map(move |content| Chunk { document, content })
+        })
+        .collect()
+}
+
+/// Score the eval set against the chunks, counting a document as retrieved
+/// at the rank of its best chunk
+fn score(
+    documents: &[SampleDocument],
+    chunks: &[Chunk],
+    queries: &[String],
+    query_embeddings: &[Vec<f32>],
+    retrieval: &Retrieval<'_>,
+) -> Result<RetrievalScores, AnalyzeError> {
+    let contents: Vec<String> = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+    let embeddings = if contents.is_empty() { Vec::new() } else { retrieval.provider.generate_embeddings(&contents)? };
+    
+    let report = evaluate(retrieval.set, Some("sample"), retrieval.k, |query, _, k| {
+        let position = queries.iter().position(|candidate| candidate == query).ok_or_else(|| "unknown query".to_string())?;
+        let query = &query_embeddings[position];
+        
+        let mut ranked: Vec<(f32, usize)> = embeddings.iter()
+            .zip(chunks)
+            .map(|(embedding, chunk)| (cosine_similarity(query, embedding), chunk.document))
+            .collect();
+        ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+        
+        let mut seen = HashSet::new();
+        Ok(ranked.into_iter()
+            .filter(|(_, document)| seen.insert(*document))
+            .take(k)
+            .map(|(_, document)| documents[document].id.clone())
+            .collect())
+    })?;
+    Ok(RetrievalScores { recall: report.recall, mrr: report.mrr, ndcg: report.ndcg })
+}
+
+/// Pick the best NDCG when retrieval was scored, and otherwise the mean
+/// length closest to [`TARGET_CHUNK_TOKENS`] among strategies keeping every
+/// chunk within [`MAX_CHUNK_TOKENS`]. Ties go to the fewer embeddings.
+fn recommend(reports: &[CandidateReport], k: Option<usize>) -> (ChunkingStrategy, String) {
+    let fewer_embeddings = |a: &CandidateReport, b: &CandidateReport| b.embeddings.cmp(&a.embeddings);
+    
+    if let Some(k) = k {
+        let best = reports.iter()
+            .max_by(|a, b| {
+                let (a_scores, b_scores) = (a.retrieval.unwrap(), b.retrieval.unwrap());
+                a_scores.ndcg.partial_cmp(&b_scores.ndcg).unwrap_or(Ordering::Equal)
+                    .then(a_scores.recall.partial_cmp(&b_scores.recall).unwrap_or(Ordering::Equal))
+                    .then(fewer_embeddings(a, b))
+            })
+            .unwrap();
+        let ndcg = best.retrieval.unwrap().ndcg;
+        return (best.strategy, format!("best NDCG@{} on the eval set: {:.4}", k, ndcg));
+    }
+    
+    let fitting: Vec<&CandidateReport> = reports.iter().filter(|report| report.max_tokens <= MAX_CHUNK_TOKENS).collect();
+    let pool = if fitting.is_empty() { reports.iter().collect() } else { fitting };
+    let distance = |report: &CandidateReport| (report.mean_tokens - TARGET_CHUNK_TOKENS as f64).abs();
+    let best = pool.into_iter()
+        .max_by(|a, b| {
+            distance(b).partial_cmp(&distance(a)).unwrap_or(Ordering::Equal).then(fewer_embeddings(a, b))
+        })
+        .unwrap();
+    (best.strategy, format!(
+        "mean chunk length {:.1} tokens is closest to {} with no chunk over {}",
+        best.mean_tokens, TARGET_CHUNK_TOKENS, MAX_CHUNK_TOKENS
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::EvalCase;
+    
+    /// Embeds text as counts of its words hashed into a few buckets
+    struct BagOfWords;
+    
+    impl EmbeddingProvider for BagOfWords {
+        fn generate_embedding(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            let mut embedding = vec![0.0; 64];
+            for word in text.split_whitespace() {
+                let word = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+                let bucket = word.bytes().fold(7usize, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as usize)) % 64;
+                embedding[bucket] += 1.0;
+            }
+            Ok(embedding)
+        }
+        
+        fn generate_embeddings(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+            texts.iter().map(|text| self.generate_embedding(text)).collect()
+        }
+        
+        fn embedding_dim(&self) -> usize {
+            64
+        }
+    }
+    
+    fn document(id: &str, paragraph: &str, paragraphs: usize) -> SampleDocument {
+        SampleDocument { id: id.to_string(), text: vec![paragraph; paragraphs].join("\n\n") }
+    }
+    
+    #[test]
+    fn test_lengths_decide_without_an_eval_set() {
+        let paragraph = "Deployments roll out region by region and pause when error rates climb. ".repeat(8);
+        let documents = vec![document("deploys.md", &paragraph, 12)];
+        
+        let analysis = analyze(&documents, &default_candidates(), None).unwrap();
+        assert_eq!(analysis.documents, 1);
+        assert_eq!(analysis.candidates.len(), 5);
+        
+        let embeddings = |strategy| analysis.candidates.iter().find(|c| c.strategy == strategy).unwrap().embeddings;
+        assert!(embeddings(ChunkingStrategy::FixedSize(128)) > embeddings(ChunkingStrategy::FixedSize(512)));
+        assert!(analysis.candidates.iter().all(|candidate| candidate.retrieval.is_none()));
+        
+        let recommended = analysis.candidates.iter().find(|c| c.strategy == analysis.recommended).unwrap();
+        assert!(recommended.max_tokens <= MAX_CHUNK_TOKENS);
+        assert!(analysis.render_markdown().contains(&format!("Recommended: {}", analysis.recommended)));
+    }
+    
+    #[test]
+    fn test_eval_set_scores_retrieval() {
+        let documents = vec![
+            document("deploys.md", "Deployments roll out region by region and pause when error rates climb.", 3),
+            document("billing.md", "Invoices are issued monthly and unpaid invoices are retried after three days.", 3),
+        ];
+        let set = EvalSet {
+            collection: None,
+            cases: vec![
+                EvalCase { query: "when do deployments pause".to_string(), expected: vec!["deploys.md".to_string()], collection: None },
+                EvalCase { query: "unpaid invoices retried".to_string(), expected: vec!["billing.md".to_string()], collection: None },
+            ],
+        };
+        
+        let retrieval = Retrieval { set: &set, provider: &BagOfWords, k: 1 };
+        let analysis = analyze(&documents, &[ChunkingStrategy::Paragraph, ChunkingStrategy::FixedSize(512)], Some(retrieval)).unwrap();
+        let scores = analysis.candidates[0].retrieval.unwrap();
+        assert_eq!(scores.recall, 1.0);
+        assert!(analysis.reason.contains("NDCG@1"));
+    }
+    
+    #[test]
+    fn test_nothing_to_analyze() {
+        assert!(matches!(analyze(&[], &default_candidates(), None), Err(AnalyzeError::NoDocuments)));
+        let documents = vec![document("a.md", "text", 1)];
+        assert!(matches!(analyze(&documents, &[], None), Err(AnalyzeError::NoCandidates)));
+    }
+    
+    #[test]
+    fn test_sample_spreads_over_the_corpus() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for i in 0..6 {
+            fs::write(dir.path().join(format!("{}.md", i)), format!("Note number {}", i)).unwrap();
+        }
+        fs::write(dir.path().join("image.png"), b"\x89PNG").unwrap();
+        
+        let all = sample_documents(&[dir.path().to_path_buf()], 10);
+        assert_eq!(all.len(), 6);
+        
+        let sample = sample_documents(&[dir.path().to_path_buf()], 3);
+        let ids: Vec<String> = sample.iter()
+            .map(|document| PathBuf::from(&document.id).file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(ids, vec!["0.md", "2.md", "4.md"]);
+    }
+}
@@ -0,0 +1,120 @@
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+use super::{store_error_status, ApiState};
+use crate::attachments::{Attachment, AttachmentError, Attachments};
+use crate::vector_store::EntryId;
+
+/// Routes for uploading attachments, linking them to entries and downloading them
+pub fn router(state: ApiState) -> Router {
+    // Leave room above the attachment limit so oversized uploads get a clear error
+    let body_limit = state.attachments().map_or(0, |attachments| attachments.max_bytes()) + 64 * 1024;
+    Router::new()
+        .route("/api/attachments", post(upload))
+        .route("/api/attachments/:hash", get(download))
+        .route("/api/collections/:collection/entries/:id/attachments", post(upload_to_entry))
+        .layer(DefaultBodyLimit::max(body_limit))
+        .with_state(state)
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadParams {
+    /// Name shown for the attachment, e.g. `diagram.png`
+    filename: Option<String>,
+}
+
+async fn upload(State(state): State<ApiState>, Query(params): Query<UploadParams>, headers: HeaderMap, body: Bytes) -> Response {
+    let Some(attachments) = state.attachments() else { return not_enabled() };
+    match store(attachments, &params, &headers, &body).await {
+        Ok(attachment) => created(&attachment),
+        Err(e) => attachment_error(&e),
+    }
+}
+
+async fn upload_to_entry(
+    State(state): State<ApiState>,
+    Path((collection, id)): Path<(String, String)>,
+    Query(params): Query<UploadParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(attachments) = state.attachments() else { return not_enabled() };
+    let id = match EntryId::parse(&id) {
+        Ok(id) => id,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    // Check the entry first so nothing is stored for a missing one
+    match state.vector_store().exists(&collection, id.as_str()).await {
+        Ok(true) => {},
+        Ok(false) => return error(StatusCode::NOT_FOUND, format!("Entry {} not found in collection {}", id, collection)),
+        Err(e) => return error(store_error_status(&e), e.to_string()),
+    }
+    
+    let attachment = match store(attachments, &params, &headers, &body).await {
+        Ok(attachment) => attachment,
+        Err(e) => return attachment_error(&e),
+    };
+    match attachments.attach(state.vector_store().as_ref(), &collection, id.as_str(), &attachment).await {
+        Ok(_) => created(&attachment),
+        Err(e) => attachment_error(&e),
+    }
+}
+
+async fn download(State(state): State<ApiState>, Path(hash): Path<String>) -> Response {
+    let Some(attachments) = state.attachments() else { return not_enabled() };
+    match attachments.download(&hash).await {
+        Ok(Some((bytes, content_type))) => (
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                // Content never changes under a hash
+                (header::CACHE_CONTROL, "public, max-age=31536000, immutable".to_string()),
+                (header::ETAG, format!("\"{}\"", hash)),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            ],
+            bytes,
+        ).into_response(),
+        Ok(None) => error(StatusCode::NOT_FOUND, format!("Attachment {} not found", hash)),
+        Err(e) => attachment_error(&e),
+    }
+}
+
+async fn store(attachments: &Arc<Attachments>, params: &UploadParams, headers: &HeaderMap, body: &[u8]) -> Result<Attachment, AttachmentError> {
+    let content_type = headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok());
+    attachments.upload(body, content_type, params.filename.as_deref()).await
+}
+
+pub(super) fn created(attachment: &Attachment) -> Response {
+    let mut body = json!(attachment);
+    body["url"] = json!(attachment.url());
+    (StatusCode::CREATED, Json(body)).into_response()
+}
+
+/// The status for an attachment failure: rejected uploads are 4xx, storage failures 5xx
+pub(super) fn attachment_error(e: &AttachmentError) -> Response {
+    let status = match e {
+        AttachmentError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        AttachmentError::UnsupportedType(_) | AttachmentError::TypeMismatch { .. } | AttachmentError::UnknownType => {
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        },
+        AttachmentError::Empty | AttachmentError::InvalidHash(_) => StatusCode::BAD_REQUEST,
+        AttachmentError::EntryNotFound { .. } => StatusCode::NOT_FOUND,
+        AttachmentError::Store(e) => store_error_status(e),
+        AttachmentError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    error(status, e.to_string())
+}
+
+fn not_enabled() -> Response {
+    error(StatusCode::NOT_FOUND, "Attachments are not enabled".to_string())
+}
+
+pub(super) fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
@@ -0,0 +1,63 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+use super::ApiState;
+use crate::events::ChangeCursor;
+
+/// Changes returned when a request gives no `limit`
+pub const DEFAULT_CHANGES_LIMIT: usize = 100;
+
+/// Most changes returned by one request
+pub const MAX_CHANGES_LIMIT: usize = 1000;
+
+/// Longest a request may block waiting for a change
+pub const MAX_CHANGES_WAIT: Duration = Duration::from_secs(60);
+
+/// Routes for `GET /api/changes`
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/changes", get(changes))
+        .with_state(state)
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangesParams {
+    /// Cursor of the last change seen, or `now` to only see what comes next
+    since: Option<String>,
+    limit: Option<usize>,
+    /// Seconds to wait for a change when there is none yet
+    wait: Option<u64>,
+}
+
+async fn changes(State(state): State<ApiState>, Query(params): Query<ChangesParams>) -> Response {
+    let Some(feed) = state.change_feed() else {
+        return error(StatusCode::NOT_FOUND, "The change feed is not enabled".to_string());
+    };
+    let since = match params.since.as_deref() {
+        None => None,
+        Some("now") => Some(feed.head()),
+        Some(cursor) => match cursor.parse::<ChangeCursor>() {
+            Ok(cursor) => Some(cursor),
+            Err(e) => return error(StatusCode::BAD_REQUEST, e.to_string()),
+        },
+    };
+    let limit = params.limit.unwrap_or(DEFAULT_CHANGES_LIMIT).clamp(1, MAX_CHANGES_LIMIT);
+    
+    let page = match params.wait {
+        Some(seconds) if seconds > 0 => {
+            feed.wait_since(since, limit, Duration::from_secs(seconds).min(MAX_CHANGES_WAIT)).await
+        },
+        _ => feed.since(since, limit),
+    };
+    Json(page).into_response()
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
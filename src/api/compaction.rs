@@ -0,0 +1,67 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::json;
+use tracing::info;
+
+use super::{store_error_status, ApiState};
+
+/// Routes for `POST /api/admin/compact`
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/admin/compact", post(compact))
+        .with_state(state)
+}
+
+/// Compact the embedded store and its write-ahead log now, reporting the space reclaimed
+async fn compact(State(state): State<ApiState>) -> Response {
+    let Some(compactor) = state.compactor() else {
+        return error(StatusCode::NOT_FOUND, "Compaction applies only to the embedded store".to_string());
+    };
+    match compactor.compact().await {
+        Ok(report) => {
+            info!(reclaimed_bytes = report.reclaimed_bytes(), "Compacted the store: {}", report);
+            let reclaimed_bytes = report.reclaimed_bytes();
+            Json(json!({ "compaction": report, "reclaimed_bytes": reclaimed_bytes })).into_response()
+        },
+        Err(e) => error(store_error_status(&e), e.to_string()),
+    }
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FsyncPolicy;
+    use crate::vector_store::{Compactor, InMemoryVectorStore, VectorStore, WalVectorStore};
+    use std::sync::Arc;
+    use std::time::Duration;
+    
+    #[tokio::test]
+    async fn test_compaction_reports_reclaimed_space() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("wal.log");
+        let store = Arc::new(InMemoryVectorStore::new());
+        let (wal, _) = WalVectorStore::open(store.clone(), &path, FsyncPolicy::Always, Duration::from_secs(1)).await.unwrap();
+        let wal = Arc::new(wal);
+        wal.create_collection("scratch", 2).await.unwrap();
+        wal.delete_collection("scratch").await.unwrap();
+        
+        let compactor = Arc::new(Compactor::new(store).with_wal(wal.clone()));
+        let response = compact(State(ApiState::new(wal).with_compactor(compactor))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["compaction"]["checkpoint"]["records_after"], 0);
+        assert_eq!(body["reclaimed_bytes"], body["compaction"]["checkpoint"]["bytes_before"]);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        
+        let response = compact(State(ApiState::new(Arc::new(InMemoryVectorStore::new())))).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,103 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::json;
+use std::collections::HashMap;
+
+use super::{store_error_status, ApiState};
+use crate::vector_store::{EntryId, MetadataFilter};
+
+/// Query parameter prefix of metadata equality conditions, e.g. `metadata.owner=sre`
+pub const METADATA_PARAM_PREFIX: &str = "metadata.";
+
+/// Routes for counting entries and checking that one exists
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/collections/:collection/count", get(count))
+        .route("/api/collections/:collection/entries/:id/exists", get(exists))
+        .with_state(state)
+}
+
+/// Build a filter from `ids` and `tags` (comma-separated) and `metadata.<key>` parameters
+pub fn filter_from_params(params: &HashMap<String, String>) -> Result<MetadataFilter, String> {
+    let list = |value: &str| -> Vec<String> {
+        value.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    
+    let mut filter = MetadataFilter::default();
+    for (key, value) in params {
+        match key.as_str() {
+            "ids" => {
+                filter.ids = list(value).iter()
+                    .map(|id| EntryId::parse(id).map(String::from).map_err(|e| format!("ids: {}", e)))
+                    .collect::<Result<_, String>>()?;
+            },
+            "tags" => filter.tags = list(value),
+            key => match key.strip_prefix(METADATA_PARAM_PREFIX) {
+                Some(field) if !field.is_empty() => {
+                    filter.metadata.insert(field.to_string(), value.clone());
+                },
+                _ => return Err(format!("unknown filter parameter '{}'", key)),
+            },
+        }
+    }
+    Ok(filter)
+}
+
+async fn count(
+    State(state): State<ApiState>,
+    Path(collection): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let filter = match filter_from_params(&params) {
+        Ok(filter) => filter,
+        Err(message) => return error(StatusCode::BAD_REQUEST, message),
+    };
+    
+    match state.vector_store().count(&collection, &filter).await {
+        Ok(count) => Json(json!({ "collection": collection, "count": count })).into_response(),
+        Err(e) => error(store_error_status(&e), e.to_string()),
+    }
+}
+
+async fn exists(State(state): State<ApiState>, Path((collection, id)): Path<(String, String)>) -> Response {
+    let id = match EntryId::parse(&id) {
+        Ok(id) => id,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    
+    match state.vector_store().exists(&collection, id.as_str()).await {
+        Ok(exists) => Json(json!({ "collection": collection, "id": id, "exists": exists })).into_response(),
+        Err(e) => error(store_error_status(&e), e.to_string()),
+    }
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_filter_from_params() {
+        let params = HashMap::from([
+            ("tags".to_string(), "ops, db".to_string()),
+            ("metadata.owner".to_string(), "sre".to_string()),
+        ]);
+        let filter = filter_from_params(&params).unwrap();
+        assert_eq!(filter.tags, vec!["ops", "db"]);
+        assert_eq!(filter.metadata["owner"], "sre");
+        assert!(filter.ids.is_empty());
+        
+        let params = HashMap::from([("owner".to_string(), "sre".to_string())]);
+        assert!(filter_from_params(&params).is_err());
+    }
+}
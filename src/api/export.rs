@@ -0,0 +1,307 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::attachments::{attachments_of, Attachment};
+use crate::text_processing::{stemmed_words, stemmer_for};
+use crate::vector_store::keywords::is_stopword;
+use crate::vector_store::{Document, SearchResult, LANGUAGE_KEY};
+
+/// Maximum number of characters of content included in a snippet
+pub const SNIPPET_CHARS: usize = 200;
+
+/// Marks put around highlighted words, as Markdown bold
+pub const HIGHLIGHT_MARK: &str = "**";
+
+/// File formats search results can be exported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Markdown,
+}
+
+impl ExportFormat {
+    /// The HTTP content type of an export in this format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+    
+    /// The name used for this format in query strings
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "markdown",
+        }
+    }
+}
+
+/// One search result flattened for export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportRow {
+    pub title: String,
+    pub snippet: String,
+    pub score: f32,
+    /// `score` normalized to 0-1 for the collection's distance metric
+    #[serde(default)]
+    pub relevance: f32,
+    pub tags: Vec<String>,
+    /// Where the entry lives, as `<collection>#<entry id>`
+    pub source: String,
+    /// Download links of the entry's attachments, relative to the API root
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+}
+
+impl ExportRow {
+    pub fn from_result(collection: &str, result: &SearchResult) -> Self {
+        let document = &result.document;
+        let title = document.title()
+            .map(|title| title.to_string())
+            .unwrap_or_else(|| fallback_title(&document.content));
+        
+        Self {
+            title,
+            snippet: snippet(&document.content, SNIPPET_CHARS),
+            score: result.score,
+            relevance: result.relevance(),
+            tags: document.tags(),
+            source: format!("{}#{}", collection, document.id),
+            attachments: attachments_of(document).iter().map(Attachment::url).collect(),
+        }
+    }
+    
+    /// Show the part of the document matching `query` in the snippet, with the matches highlighted
+    pub fn highlight(mut self, document: &Document, query: &str) -> Self {
+        let language = document.metadata.get(LANGUAGE_KEY).map(|language| language.as_str());
+        self.snippet = highlighted_snippet(&document.content, query, language, SNIPPET_CHARS);
+        self
+    }
+}
+
+/// Render rows in the requested format
+pub fn render(format: ExportFormat, rows: &[ExportRow]) -> String {
+    match format {
+        ExportFormat::Csv => render_csv(rows),
+        ExportFormat::Markdown => render_markdown(rows),
+    }
+}
+
+/// Render rows as RFC 4180 CSV with a header line
+pub fn render_csv(rows: &[ExportRow]) -> String {
+    let mut out = String::from("title,snippet,score,tags,source\r\n");
+    for row in rows {
+        let fields = [
+            csv_field(&row.title),
+            csv_field(&row.snippet),
+            format!("{:.4}", row.score),
+            csv_field(&row.tags.join("; ")),
+            csv_field(&row.source),
+        ];
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Render rows as a Markdown table
+pub fn render_markdown(rows: &[ExportRow]) -> String {
+    let mut out = String::from("| Title | Snippet | Score | Tags | Source |\n|---|---|---:|---|---|\n");
+    for row in rows {
+        out.push_str(&format!(
+            "| {} | {} | {:.4} | {} | `{}` |\n",
+            markdown_cell(&row.title),
+            markdown_cell(&row.snippet),
+            row.score,
+            markdown_cell(&row.tags.join(", ")),
+            row.source.replace('`', "'"),
+        ));
+    }
+    out
+}
+
+/// Collapse whitespace and truncate to at most `max_chars` characters
+pub fn snippet(content: &str, max_chars: usize) -> String {
+    let collapsed = content.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if collapsed.chars().count() <= max_chars {
+        return collapsed;
+    }
+    
+    let mut truncated: String = collapsed.chars().take(max_chars.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Byte ranges of the words of `text` sharing a stem with a word of `query`.
+///
+/// Both are stemmed for `language`, an ISO 639-3 code, so inflected forms
+/// match: "deploying" highlights "deployment". Stopwords in the query are
+/// not highlighted.
+pub fn highlight_ranges(text: &str, query: &str, language: Option<&str>) -> Vec<Range<usize>> {
+    let stemmer = stemmer_for(language);
+    let stems: HashSet<String> = stemmed_words(query, &stemmer).into_iter()
+        .filter(|word| !is_stopword(&query[word.span.clone()].to_lowercase()))
+        .map(|word| word.stem)
+        .collect();
+    stemmed_words(text, &stemmer).into_iter()
+        .filter(|word| stems.contains(&word.stem))
+        .map(|word| word.span)
+        .collect()
+}
+
+/// A snippet of `content` around its first match for `query`, matches marked with [`HIGHLIGHT_MARK`].
+///
+/// Like [`snippet`] the text is collapsed and at most `max_chars` long, not
+/// counting the marks; without a match it is the start of the content.
+pub fn highlighted_snippet(content: &str, query: &str, language: Option<&str>, max_chars: usize) -> String {
+    let collapsed = content.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let ranges = highlight_ranges(&collapsed, query, language);
+    let Some(first) = ranges.first() else {
+        return snippet(&collapsed, max_chars);
+    };
+    
+    // Start a few words before the first match so it reads in context
+    let chars: Vec<(usize, char)> = collapsed.char_indices().collect();
+    let first_char = chars.partition_point(|(index, _)| *index < first.start);
+    let mut start = first_char.saturating_sub(max_chars / 4);
+    if start > 0 {
+        start = (start..first_char).find(|&at| chars[at - 1].1 == ' ').unwrap_or(first_char);
+    }
+    let lead = if start > 0 { 1 } else { 0 };
+    let room = max_chars.saturating_sub(lead);
+    let end = if chars.len() - start > room { start + room.saturating_sub(1) } else { chars.len() };
+    
+    let byte = |at: usize| chars.get(at).map_or(collapsed.len(), |(index, _)| *index);
+    let (from, to) = (byte(start), byte(end));
+    let mut out = String::from(if lead > 0 { "…" } else { "" });
+    let mut at = from;
+    for range in ranges.iter().filter(|range| range.start >= from && range.end <= to) {
+        out.push_str(&collapsed[at..range.start]);
+        out.push_str(HIGHLIGHT_MARK);
+        out.push_str(&collapsed[range.clone()]);
+        out.push_str(HIGHLIGHT_MARK);
+        at = range.end;
+    }
+    out.push_str(&collapsed[at..to]);
+    if to < collapsed.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// Use the first non-empty line, without Markdown heading markers, as a title
+fn fallback_title(content: &str) -> String {
+    let line = content.lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or("");
+    snippet(line, 80)
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::{DistanceMetric, Document};
+    
+    fn result(content: &str, score: f32) -> SearchResult {
+        let document = Document {
+            id: "entry-1".to_string(),
+            content: content.to_string(),
+            embedding: vec![],
+            metadata: Default::default(),
+        };
+        SearchResult { document, score, metric: DistanceMetric::Cosine }
+    }
+    
+    #[test]
+    fn test_row_uses_stored_title_and_tags() {
+        let mut result = result("Body text", 0.5);
+        result.document = result.document
+            .with_title("Stored title")
+            .with_tags(&["a".to_string(), "b".to_string()]);
+        
+        let row = ExportRow::from_result("notes", &result);
+        assert_eq!(row.title, "Stored title");
+        assert_eq!(row.tags, vec!["a", "b"]);
+        assert_eq!(row.source, "notes#entry-1");
+    }
+    
+    #[test]
+    fn test_row_falls_back_to_first_heading() {
+        let row = ExportRow::from_result("notes", &result("\n# Heading\nbody", 0.5));
+        assert_eq!(row.title, "Heading");
+        assert_eq!(row.snippet, "# Heading body");
+    }
+    
+    #[test]
+    fn test_snippet_truncates() {
+        assert_eq!(snippet("a  b\nc", 10), "a b c");
+        assert_eq!(snippet("abcdefghij", 5), "abcd…");
+    }
+    
+    #[test]
+    fn test_highlight_matches_inflected_forms() {
+        let content = "Before the deployment, run the checks. Deployments roll out by region.";
+        assert_eq!(
+            highlighted_snippet(content, "deploying the service", Some("eng"), 200),
+            "Before the **deployment**, run the checks. **Deployments** roll out by region."
+        );
+        
+        let content = "Para desplegar el servicio, ejecute las pruebas.";
+        assert_eq!(
+            highlighted_snippet(content, "desplegando", Some("spa"), 200),
+            "Para **desplegar** el servicio, ejecute las pruebas."
+        );
+        
+        assert_eq!(highlighted_snippet("No match here", "deploy", None, 200), "No match here");
+    }
+    
+    #[test]
+    fn test_highlighted_snippet_starts_near_the_match() {
+        let filler = "filler ".repeat(40);
+        let content = format!("{} the backup runs nightly {}", filler, filler);
+        let snippet = highlighted_snippet(&content, "backups", Some("eng"), 40);
+        assert!(snippet.starts_with("…the **backup** runs"));
+        assert!(snippet.ends_with('…'));
+        assert_eq!(snippet.replace(HIGHLIGHT_MARK, "").chars().count(), 40);
+        
+        let mut result = result("We deployed twice", 1.0);
+        result.document.metadata.insert(LANGUAGE_KEY.to_string(), "eng".to_string());
+        let row = ExportRow::from_result("notes", &result).highlight(&result.document, "deploy");
+        assert_eq!(row.snippet, "We **deployed** twice");
+    }
+    
+    #[test]
+    fn test_render_csv_escapes_fields() {
+        let row = ExportRow::from_result("notes", &result("Hello, \"world\"", 0.25));
+        let csv = render(ExportFormat::Csv, &[row]);
+        
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(lines[0], "title,snippet,score,tags,source");
+        assert_eq!(lines[1], "\"Hello, \"\"world\"\"\",\"Hello, \"\"world\"\"\",0.2500,,notes#entry-1");
+    }
+    
+    #[test]
+    fn test_render_markdown_escapes_pipes() {
+        let row = ExportRow::from_result("notes", &result("a | b", 1.0));
+        let markdown = render(ExportFormat::Markdown, &[row]);
+        
+        assert!(markdown.starts_with("| Title | Snippet | Score | Tags | Source |"));
+        assert!(markdown.contains("| a \\| b | a \\| b | 1.0000 |  | `notes#entry-1` |"));
+    }
+}
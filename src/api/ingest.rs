@@ -0,0 +1,298 @@
+//! Streaming NDJSON ingestion with backpressure.
+//!
+//! `POST /api/ingest?collection=<id>` takes a body of one entry per line,
+//! `{"content": ..., "id"?, "title"?, "tags"?, "metadata"?}`, usually sent
+//! with chunked transfer encoding. Entries are stored as their lines arrive,
+//! and the response streams back an NDJSON frame acknowledging each line, a
+//! `progress` frame every `progress_every` acknowledged lines, and a final
+//! `done` frame with the totals.
+//!
+//! Each entry is added the way the MCP server's `add_knowledge_entry` tool
+//! adds one: sanitized, screened for PII, checked against the collection's
+//! schema and given a title alike. Ingestion therefore needs the API to
+//! have an MCP server.
+//!
+//! Neither side has to buffer the upload. The body is read only as fast as
+//! lines are stored, and a line is not stored until the acknowledgement of
+//! the one before it has been taken by the response stream, so a client
+//! that stops reading acknowledgements pauses its own upload. The server
+//! holds at most one partial line, of up to [`MAX_LINE_BYTES`].
+
+use axum::body::{self, Body, Bytes};
+use axum::extract::{Query, RawBody, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use hyper::body::{HttpBody, Sender};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::info;
+
+use super::ApiState;
+use crate::mcp::ProgmoMcpServer;
+use crate::text_processing::Metadata;
+
+/// Content type of the request and response bodies
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Acknowledged lines between progress frames when a request gives no `progress_every`
+pub const DEFAULT_PROGRESS_EVERY: usize = 100;
+
+/// Longest line accepted; longer ones are skipped and reported as failed
+pub const MAX_LINE_BYTES: usize = 1024 * 1024;
+
+/// Routes for `POST /api/ingest`
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/ingest", post(ingest))
+        .with_state(state)
+}
+
+#[derive(Debug, Deserialize)]
+struct IngestParams {
+    collection: String,
+    /// Acknowledged lines between progress frames
+    progress_every: Option<usize>,
+}
+
+/// One line of the request body
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IngestLine {
+    /// Id to store the entry under; a new UUID when absent
+    id: Option<String>,
+    title: Option<String>,
+    content: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    metadata: Metadata,
+}
+
+/// How far an ingestion has got
+#[derive(Debug, Default, Serialize)]
+struct IngestProgress {
+    /// Lines read so far, blank ones included, so acknowledgements carry the line number
+    lines: usize,
+    ingested: usize,
+    failed: usize,
+    /// Bytes of the body read so far
+    bytes: usize,
+}
+
+/// Store the entries of an NDJSON body as it streams in, streaming back one
+/// acknowledgement per line
+async fn ingest(State(state): State<ApiState>, Query(params): Query<IngestParams>, RawBody(body): RawBody) -> Response {
+    let Some(server) = state.mcp_server().cloned() else {
+        return error(StatusCode::NOT_FOUND, "No MCP server is configured".to_string());
+    };
+    let (frames, response_body) = Body::channel();
+    let progress_every = params.progress_every.unwrap_or(DEFAULT_PROGRESS_EVERY).max(1);
+    tokio::spawn(run(server, params.collection, body, frames, progress_every));
+    Response::builder()
+        .header(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)
+        .body(body::boxed(response_body))
+        .expect("a static content type is a valid header")
+}
+
+/// Read `body` line by line into `collection`, acknowledging on `frames`;
+/// stops early once the client stops listening
+async fn run(server: Arc<ProgmoMcpServer>, collection: String, mut body: Body, mut frames: Sender, progress_every: usize) {
+    let mut splitter = LineSplitter::new(MAX_LINE_BYTES);
+    let mut progress = IngestProgress::default();
+    let mut failure = None;
+    loop {
+        let (lines, end) = match body.data().await {
+            Some(Ok(chunk)) => {
+                progress.bytes += chunk.len();
+                (splitter.push(&chunk), false)
+            },
+            Some(Err(e)) => {
+                failure = Some(format!("Failed to read the request body: {}", e));
+                (Vec::new(), true)
+            },
+            None => (splitter.finish().into_iter().collect(), true),
+        };
+        for line in lines {
+            if !ingest_line(&server, &collection, line, &mut progress, &mut frames, progress_every).await {
+                return;
+            }
+        }
+        if end {
+            break;
+        }
+    }
+    
+    info!(
+        collection = %collection,
+        ingested = progress.ingested,
+        failed = progress.failed,
+        bytes = progress.bytes,
+        "Finished streamed ingestion"
+    );
+    let mut done = json!(progress);
+    done["complete"] = json!(failure.is_none());
+    if let Some(error) = failure {
+        done["error"] = json!(error);
+    }
+    send(&mut frames, json!({ "done": done })).await;
+}
+
+/// Store one line and acknowledge it, with a progress frame when one is due;
+/// false once the client has gone away
+async fn ingest_line(
+    server: &ProgmoMcpServer,
+    collection: &str,
+    line: Line,
+    progress: &mut IngestProgress,
+    frames: &mut Sender,
+    progress_every: usize,
+) -> bool {
+    progress.lines += 1;
+    let stored = match line {
+        Line::Complete(bytes) if bytes.iter().all(u8::is_ascii_whitespace) => return true,
+        Line::Complete(bytes) => store_line(server, collection, &bytes).await,
+        Line::TooLong => Err(format!("Line is longer than {} bytes", MAX_LINE_BYTES)),
+    };
+    let ack = match stored {
+        Ok(id) => {
+            progress.ingested += 1;
+            json!({ "line": progress.lines, "status": "ingested", "id": id })
+        },
+        Err(error) => {
+            progress.failed += 1;
+            json!({ "line": progress.lines, "status": "failed", "error": error })
+        },
+    };
+    if !send(frames, ack).await {
+        return false;
+    }
+    
+    if (progress.ingested + progress.failed).is_multiple_of(progress_every) {
+        return send(frames, json!({ "progress": progress })).await;
+    }
+    true
+}
+
+/// Parse the entry on one line and add it through `server`, returning its id
+async fn store_line(server: &ProgmoMcpServer, collection: &str, line: &[u8]) -> Result<String, String> {
+    let entry: IngestLine = serde_json::from_slice(line).map_err(|e| format!("Invalid entry: {}", e))?;
+    if entry.content.trim().is_empty() {
+        return Err("Invalid entry: content is empty".to_string());
+    }
+    let mut arguments = json!({
+        "content": entry.content,
+        "tags": entry.tags,
+        "metadata": entry.metadata,
+    });
+    if let Some(id) = entry.id {
+        arguments["entry_id"] = json!(id);
+    }
+    if let Some(title) = entry.title {
+        arguments["title"] = json!(title);
+    }
+    server.add_entry(collection, &arguments).await
+}
+
+/// Write one frame; false once the client has gone away. Waits while the
+/// client is not reading, which is what holds back the upload.
+async fn send(frames: &mut Sender, frame: Value) -> bool {
+    let mut line = frame.to_string();
+    line.push('\n');
+    frames.send_data(Bytes::from(line)).await.is_ok()
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+/// A line of the body
+#[derive(Debug, PartialEq)]
+enum Line {
+    Complete(Vec<u8>),
+    /// A line over the limit, dropped as it was read
+    TooLong,
+}
+
+/// Splits chunks of a body into lines, holding only the partial line at the end
+#[derive(Debug)]
+struct LineSplitter {
+    partial: Vec<u8>,
+    max_bytes: usize,
+    /// Whether the partial line went over `max_bytes` and is being dropped
+    overflowed: bool,
+}
+
+impl LineSplitter {
+    fn new(max_bytes: usize) -> Self {
+        Self { partial: Vec::new(), max_bytes, overflowed: false }
+    }
+    
+    /// The lines `chunk` completes
+    fn push(&mut self, chunk: &[u8]) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut rest = chunk;
+        while let Some(end) = rest.iter().position(|&byte| byte == b'\n') {
+            self.append(&rest[..end]);
+            lines.push(self.take());
+            rest = &rest[end + 1..];
+        }
+        self.append(rest);
+        lines
+    }
+    
+    /// The last line, when the body does not end with a newline
+    fn finish(&mut self) -> Option<Line> {
+        if self.partial.is_empty() && !self.overflowed {
+            return None;
+        }
+        Some(self.take())
+    }
+    
+    fn append(&mut self, bytes: &[u8]) {
+        if self.overflowed {
+            return;
+        }
+        if self.partial.len() + bytes.len() > self.max_bytes {
+            self.overflowed = true;
+            self.partial = Vec::new();
+        } else {
+            self.partial.extend_from_slice(bytes);
+        }
+    }
+    
+    fn take(&mut self) -> Line {
+        let line = if self.overflowed { Line::TooLong } else { Line::Complete(std::mem::take(&mut self.partial)) };
+        self.overflowed = false;
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_lines_span_chunks() {
+        let mut splitter = LineSplitter::new(16);
+        assert_eq!(splitter.push(b"{\"a\""), []);
+        assert_eq!(splitter.push(b":1}\n\n{\"b\":"), [Line::Complete(b"{\"a\":1}".to_vec()), Line::Complete(Vec::new())]);
+        assert_eq!(splitter.push(b"2}"), []);
+        assert_eq!(splitter.finish(), Some(Line::Complete(b"{\"b\":2}".to_vec())));
+        assert_eq!(splitter.finish(), None);
+    }
+    
+    #[test]
+    fn test_long_lines_are_dropped_not_buffered() {
+        let mut splitter = LineSplitter::new(4);
+        assert_eq!(splitter.push(b"abc"), []);
+        assert_eq!(splitter.push(b"defgh"), []);
+        assert!(splitter.partial.is_empty());
+        assert_eq!(splitter.push(b"ij\nok\n"), [Line::TooLong, Line::Complete(b"ok".to_vec())]);
+        
+        splitter.push(b"toolong");
+        assert_eq!(splitter.finish(), Some(Line::TooLong));
+    }
+}
@@ -0,0 +1,261 @@
+//! Runtime API key management, and the authentication it backs.
+//!
+//! Once key management is enabled every route takes the admin API key or an
+//! active managed key with enough scope: `read` for GET requests, `write` for
+//! the rest, and `admin` for everything under `/api/admin`. A key limited to
+//! some collections is only accepted on requests naming one of them, by path
+//! (`/api/collections/:collection/...`) or by a `collection` query parameter.
+//! Opening the MCP WebSocket takes `read` on no collection in particular;
+//! each call made on it is then checked the same way.
+//!
+//! With an OIDC issuer configured, JWT bearer tokens are accepted too and
+//! their claims grant scopes and collections the same way. A token carrying
+//! a namespace claim has its requests pinned to that namespace.
+
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, HeaderValue, Method, Request, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get};
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tracing::debug;
+
+use super::websocket::MCP_WEBSOCKET_PATH;
+use super::ApiState;
+use crate::api_keys::{constant_time_eq, ApiKeyError, ApiKeyInfo, Grant, KeyOptions, Scope};
+use crate::context::{api_key_fingerprint, api_key_from_headers};
+use crate::oidc::{looks_like_jwt, TokenPrincipal};
+
+/// Header carrying the namespace a request operates in
+const NAMESPACE_HEADER: &str = "x-namespace";
+
+/// Audit source of key changes made through the REST API
+const AUDIT_SOURCE: &str = "rest";
+
+/// Routes for `/api/admin/keys`
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/admin/keys", get(list_keys).post(create_key))
+        .route("/api/admin/keys/:id", delete(revoke_key))
+        .with_state(state)
+}
+
+/// Who presented a request's credential
+#[derive(Debug, Clone, PartialEq)]
+enum Caller {
+    Admin,
+    Key(ApiKeyInfo),
+    Token(TokenPrincipal),
+}
+
+impl Caller {
+    fn allows(&self, scope: Scope, collection: Option<&str>) -> bool {
+        match self {
+            Caller::Admin => true,
+            Caller::Key(key) => key.allows(scope, collection),
+            Caller::Token(token) => token.allows(scope, collection),
+        }
+    }
+    
+    /// What the caller may do, for handlers that check more than the route does
+    fn grant(&self) -> Grant {
+        match self {
+            Caller::Admin => Grant { scope: Scope::Admin, collections: None },
+            Caller::Key(key) => Grant { scope: key.scope, collections: key.collections.clone() },
+            Caller::Token(token) => Grant { scope: token.scope, collections: token.collections.clone() },
+        }
+    }
+    
+    fn describe(&self) -> String {
+        match self {
+            Caller::Admin => "The admin API key".to_string(),
+            Caller::Key(key) => format!("API key {}", key.id),
+            Caller::Token(token) => format!("The token for {}", token.subject),
+        }
+    }
+}
+
+/// Identify the caller from the request's API key or bearer token, or say
+/// why it cannot be
+async fn authenticate(state: &ApiState, headers: &HeaderMap) -> Result<Caller, String> {
+    let Some(presented) = api_key_from_headers(headers) else {
+        return Err("Missing API key or bearer token".to_string());
+    };
+    if let Some(admin_key) = state.admin_key() {
+        if constant_time_eq(presented.as_bytes(), admin_key.as_bytes()) {
+            return Ok(Caller::Admin);
+        }
+    }
+    if let Some(oidc) = state.oidc().filter(|_| looks_like_jwt(&presented)) {
+        return oidc.validate(&presented).await.map(Caller::Token).map_err(|e| e.to_string());
+    }
+    state.api_keys()
+        .and_then(|keys| keys.verify(&presented))
+        .map(Caller::Key)
+        .ok_or_else(|| "Invalid, expired or revoked API key".to_string())
+}
+
+/// The scope a request needs, and the collection it names if any
+fn required_access(method: &Method, uri: &Uri) -> (Scope, Option<String>) {
+    let path = uri.path();
+    let scope = if path == "/api/admin" || path.starts_with("/api/admin/") {
+        Scope::Admin
+    } else if method == Method::GET || method == Method::HEAD {
+        Scope::Read
+    } else {
+        Scope::Write
+    };
+    let collection = match path.strip_prefix("/api/collections/") {
+        Some(rest) => rest.split('/').next().filter(|collection| !collection.is_empty()).map(str::to_string),
+        None => Query::<HashMap<String, String>>::try_from_uri(uri).ok()
+            .and_then(|Query(mut params)| params.remove("collection")),
+    };
+    (scope, collection)
+}
+
+/// Refuse requests without the admin key, an active managed key or a valid
+/// token whose scope covers them. Keys are checked against the live store, so
+/// revocations and expiries apply from the next request on. What the caller
+/// may do is left on the request as a [`Grant`] for handlers, such as the MCP
+/// WebSocket, that serve requests needing more than the route does.
+pub(crate) async fn require_api_key<B>(State(state): State<ApiState>, mut request: Request<B>, next: Next<B>) -> Response {
+    let caller = match authenticate(&state, request.headers()).await {
+        Ok(caller) => caller,
+        Err(message) => return error(StatusCode::UNAUTHORIZED, message),
+    };
+    let (scope, collection) = required_access(request.method(), request.uri());
+    // The MCP socket names no collection itself; each call on it is checked against the caller's grant
+    let allowed = match request.uri().path() {
+        MCP_WEBSOCKET_PATH => caller.grant().scope >= scope,
+        _ => caller.allows(scope, collection.as_deref()),
+    };
+    if !allowed {
+        let target = match &collection {
+            Some(collection) => format!("collection '{}'", collection),
+            None => "requests that name no collection".to_string(),
+        };
+        return error(StatusCode::FORBIDDEN, format!("{} does not grant {} access to {}", caller.describe(), scope, target));
+    }
+    
+    match &caller {
+        Caller::Admin => {},
+        Caller::Key(key) => debug!(key_id = %key.id, path = %request.uri().path(), "Authenticated with a managed API key"),
+        Caller::Token(token) => {
+            debug!(subject = %token.subject, path = %request.uri().path(), "Authenticated with a bearer token");
+            if let Some(namespace) = &token.namespace {
+                let requested = request.headers().get(NAMESPACE_HEADER).and_then(|value| value.to_str().ok());
+                if requested.is_some_and(|requested| requested != namespace) {
+                    return error(StatusCode::FORBIDDEN, format!("{} is limited to namespace '{}'", caller.describe(), namespace));
+                }
+                if let Ok(value) = HeaderValue::from_str(namespace) {
+                    request.headers_mut().insert(NAMESPACE_HEADER, value);
+                }
+            }
+        },
+    }
+    request.extensions_mut().insert(caller.grant());
+    next.run(request).await
+}
+
+async fn list_keys(State(state): State<ApiState>) -> Response {
+    let Some(keys) = state.api_keys() else {
+        return not_enabled();
+    };
+    Json(json!({ "keys": keys.list() })).into_response()
+}
+
+/// Create a key from a body of `name` plus optional `scope`, `collections`,
+/// and `expires_at` or `ttl`
+async fn create_key(State(state): State<ApiState>, headers: HeaderMap, Json(request): Json<Value>) -> Response {
+    let Some(keys) = state.api_keys() else {
+        return not_enabled();
+    };
+    let Some(name) = request.get("name").and_then(|name| name.as_str()) else {
+        return error(StatusCode::BAD_REQUEST, "Missing name".to_string());
+    };
+    let options = match KeyOptions::from_json(&request) {
+        Ok(options) => options,
+        Err(e) => return key_error(e),
+    };
+    match keys.create(name, options, &actor(&headers), AUDIT_SOURCE) {
+        Ok((info, key)) => (StatusCode::CREATED, Json(json!({ "key": info, "api_key": key }))).into_response(),
+        Err(e) => key_error(e),
+    }
+}
+
+async fn revoke_key(State(state): State<ApiState>, headers: HeaderMap, Path(id): Path<String>) -> Response {
+    let Some(keys) = state.api_keys() else {
+        return not_enabled();
+    };
+    match keys.revoke(&id, &actor(&headers), AUDIT_SOURCE) {
+        Ok(info) => Json(json!({ "key": info })).into_response(),
+        Err(e) => key_error(e),
+    }
+}
+
+/// The audit actor for a request: a fingerprint of its key, never the key itself
+fn actor(headers: &HeaderMap) -> String {
+    api_key_from_headers(headers)
+        .map(|key| format!("key:{}", api_key_fingerprint(&key)))
+        .unwrap_or_else(|| "anonymous".to_string())
+}
+
+fn key_error(e: ApiKeyError) -> Response {
+    let status = match e {
+        ApiKeyError::NotFound(_) => StatusCode::NOT_FOUND,
+        ApiKeyError::EmptyName | ApiKeyError::InvalidOptions(_) => StatusCode::BAD_REQUEST,
+        ApiKeyError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    error(status, e.to_string())
+}
+
+fn not_enabled() -> Response {
+    error(StatusCode::NOT_FOUND, "API key management is not enabled".to_string())
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys::ApiKeyStore;
+    use crate::vector_store::InMemoryVectorStore;
+    use axum::http::HeaderValue;
+    use std::sync::Arc;
+    
+    fn headers(key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", HeaderValue::from_str(key).unwrap());
+        headers
+    }
+    
+    #[tokio::test]
+    async fn test_authenticate() {
+        let keys = Arc::new(ApiKeyStore::new());
+        let state = ApiState::new(Arc::new(InMemoryVectorStore::new()))
+            .with_admin_key(Some("admin-secret".to_string()))
+            .with_api_keys(keys.clone());
+        let (info, key) = keys.create("ci", KeyOptions::default(), "admin", "test").unwrap();
+        
+        assert_eq!(authenticate(&state, &headers("admin-secret")).await, Ok(Caller::Admin));
+        assert_eq!(authenticate(&state, &headers(&key)).await, Ok(Caller::Key(info.clone())));
+        assert!(authenticate(&state, &HeaderMap::new()).await.is_err());
+        
+        keys.revoke(&info.id, "admin", "test").unwrap();
+        assert!(authenticate(&state, &headers(&key)).await.is_err());
+    }
+    
+    #[test]
+    fn test_required_access() {
+        let access = |method: Method, uri: &str| required_access(&method, &uri.parse().unwrap());
+        assert_eq!(access(Method::GET, "/api/search?q=x&collection=docs"), (Scope::Read, Some("docs".to_string())));
+        assert_eq!(access(Method::POST, "/api/collections/docs/entries/1/attachments"), (Scope::Write, Some("docs".to_string())));
+        assert_eq!(access(Method::DELETE, "/api/admin/keys/42"), (Scope::Admin, None));
+        assert_eq!(access(Method::POST, "/api/admin/compact"), (Scope::Admin, None));
+        assert_eq!(access(Method::GET, "/api/changes"), (Scope::Read, None));
+    }
+}
@@ -0,0 +1,63 @@
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use super::websocket::MCP_WEBSOCKET_PATH;
+use super::ApiState;
+use crate::api_keys::Scope;
+use crate::mcp::manifest::MANIFEST_PATH;
+
+/// Path prefix of the REST routes, as the manifest reports it
+const API_PATH: &str = "/api";
+
+/// Routes for the MCP discovery document; they never require credentials
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route(MANIFEST_PATH, get(manifest))
+        .with_state(state)
+}
+
+/// The MCP server's manifest, with the REST API and the credentials it takes
+async fn manifest(State(state): State<ApiState>) -> Response {
+    let Some(server) = state.mcp_server() else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "No MCP server is configured" }))).into_response();
+    };
+    let mut manifest = server.manifest();
+    if let Some(transports) = manifest["transports"].as_array_mut() {
+        transports.push(json!({
+            "type": "websocket",
+            "path": MCP_WEBSOCKET_PATH,
+            "framing": "message",
+            "description": "One JSON-RPC message per text frame; requests run concurrently and closing the socket cancels them",
+        }));
+    }
+    manifest["http"] = json!({
+        "api": API_PATH,
+        "auth": auth(&state),
+    });
+    ([(header::CACHE_CONTROL, "public, max-age=60")], Json(manifest)).into_response()
+}
+
+/// What the REST routes accept as credentials, and whether they need any
+fn auth(state: &ApiState) -> Value {
+    let mut schemes = Vec::new();
+    if state.api_keys().is_some() {
+        schemes.push(json!({ "type": "apiKey", "in": "header", "name": "x-api-key" }));
+        schemes.push(json!({ "type": "http", "scheme": "bearer", "description": "An API key sent as a bearer token" }));
+    }
+    if let Some(oidc) = state.oidc() {
+        schemes.push(json!({
+            "type": "openIdConnect",
+            "issuer": oidc.issuer(),
+            "openIdConnectUrl": format!("{}/.well-known/openid-configuration", oidc.issuer().trim_end_matches('/')),
+        }));
+    }
+    json!({
+        "required": !schemes.is_empty(),
+        "schemes": schemes,
+        "scopes": [Scope::Read, Scope::Write, Scope::Admin],
+    })
+}
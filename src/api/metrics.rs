@@ -0,0 +1,132 @@
+use axum::extract::State;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use std::fmt::Write;
+
+use super::ApiState;
+use crate::text_processing::{FallbackMetrics, ProviderMetrics, QueryCacheMetrics};
+use crate::vector_store::PoolMetrics;
+
+/// Content type of the Prometheus text exposition format
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Routes for `GET /metrics`
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(state)
+}
+
+async fn metrics(State(state): State<ApiState>) -> Response {
+    let mut body = state.vector_store().pool_metrics()
+        .map(|metrics| render_pool_metrics(&metrics))
+        .unwrap_or_default();
+    if let Some(metrics) = state.embedding_metrics() {
+        body.push_str(&render_embedding_metrics(&metrics));
+    }
+    if let Some(metrics) = state.query_cache_metrics() {
+        body.push_str(&render_query_cache_metrics(&metrics));
+    }
+    ([(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)], body).into_response()
+}
+
+/// Render pool metrics in the Prometheus text format
+pub fn render_pool_metrics(metrics: &PoolMetrics) -> String {
+    let series: [(&str, &str, &str, u64); 8] = [
+        ("pmo_pool_max_size", "gauge", "Most connections the pool will open", metrics.max_size as u64),
+        ("pmo_pool_size", "gauge", "Connections currently open", metrics.size as u64),
+        ("pmo_pool_available", "gauge", "Open connections not checked out", metrics.available as u64),
+        ("pmo_pool_waiting", "gauge", "Requests waiting for a connection", metrics.waiting as u64),
+        ("pmo_pool_checkouts_total", "counter", "Connections handed out", metrics.checkouts),
+        ("pmo_pool_wait_milliseconds_total", "counter", "Time spent waiting for connections", metrics.wait_time_ms),
+        ("pmo_pool_wait_timeouts_total", "counter", "Checkouts that gave up waiting for a free connection", metrics.wait_timeouts),
+        ("pmo_pool_create_timeouts_total", "counter", "Connections that could not be opened in time", metrics.create_timeouts),
+    ];
+    
+    let mut out = String::new();
+    for (name, kind, help, value) in series {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+    out
+}
+
+/// Render the counters of an embedding provider chain in the Prometheus text format
+pub fn render_embedding_metrics(metrics: &FallbackMetrics) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# HELP pmo_embedding_fallbacks_total Embeddings made by a fallback provider because an earlier one failed");
+    let _ = writeln!(out, "# TYPE pmo_embedding_fallbacks_total counter");
+    let _ = writeln!(out, "pmo_embedding_fallbacks_total {}", metrics.fallbacks);
+    
+    type Series = (&'static str, &'static str, fn(&ProviderMetrics) -> u64);
+    let series: [Series; 2] = [
+        ("pmo_embedding_provider_served_total", "Embedding calls answered by each provider", |provider| provider.served),
+        ("pmo_embedding_provider_failures_total", "Embedding calls each provider failed", |provider| provider.failures),
+    ];
+    for (name, help, value) in series {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        for provider in &metrics.providers {
+            let _ = writeln!(out, "{}{{provider=\"{}\"}} {}", name, provider.name.replace('\\', "\\\\").replace('"', "\\\""), value(provider));
+        }
+    }
+    out
+}
+
+/// Render the counters of the query embedding cache in the Prometheus text format
+pub fn render_query_cache_metrics(metrics: &QueryCacheMetrics) -> String {
+    let series: [(&str, &str, &str, String); 4] = [
+        ("pmo_query_cache_hits_total", "counter", "Query embeddings reused from the cache", metrics.hits.to_string()),
+        ("pmo_query_cache_misses_total", "counter", "Queries embedded because the cache had no fresh embedding", metrics.misses.to_string()),
+        ("pmo_query_cache_entries", "gauge", "Query embeddings held", metrics.entries.to_string()),
+        ("pmo_query_cache_hit_ratio", "gauge", "Share of lookups answered from the cache", metrics.hit_rate().to_string()),
+    ];
+    
+    let mut out = String::new();
+    for (name, kind, help, value) in series {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_render_pool_metrics() {
+        let metrics = PoolMetrics { max_size: 10, size: 4, available: 1, wait_timeouts: 2, ..PoolMetrics::default() };
+        let text = render_pool_metrics(&metrics);
+        assert!(text.contains("# TYPE pmo_pool_size gauge\npmo_pool_size 4\n"));
+        assert!(text.contains("pmo_pool_wait_timeouts_total 2\n"));
+        assert_eq!(text.lines().filter(|line| !line.starts_with('#')).count(), 8);
+    }
+    
+    #[test]
+    fn test_render_embedding_metrics() {
+        let metrics = FallbackMetrics {
+            fallbacks: 3,
+            providers: vec![
+                ProviderMetrics { name: "local".to_string(), served: 10, failures: 3 },
+                ProviderMetrics { name: "remote".to_string(), served: 3, failures: 0 },
+            ],
+        };
+        let text = render_embedding_metrics(&metrics);
+        assert!(text.contains("pmo_embedding_fallbacks_total 3\n"));
+        assert!(text.contains("pmo_embedding_provider_failures_total{provider=\"local\"} 3\n"));
+        assert!(text.contains("pmo_embedding_provider_served_total{provider=\"remote\"} 3\n"));
+    }
+    
+    #[test]
+    fn test_render_query_cache_metrics() {
+        let metrics = QueryCacheMetrics { hits: 3, misses: 1, entries: 1 };
+        let text = render_query_cache_metrics(&metrics);
+        assert!(text.contains("pmo_query_cache_hits_total 3\n"));
+        assert!(text.contains("# TYPE pmo_query_cache_hit_ratio gauge\npmo_query_cache_hit_ratio 0.75\n"));
+    }
+}
@@ -1 +1,266 @@
+pub mod attachments;
+pub mod changes;
+pub mod compaction;
+pub mod entries;
+pub mod export;
+pub mod ingest;
+pub mod keys;
+pub mod manifest;
+pub mod metrics;
 pub mod models;
+pub mod search;
+pub mod uploads;
+pub mod websocket;
+
+use axum::http::StatusCode;
+use axum::middleware;
+use axum::Router;
+use std::sync::Arc;
+
+use crate::api_keys::ApiKeyStore;
+use crate::attachments::Attachments;
+use crate::events::ChangeFeed;
+use crate::logging::RetrievalLog;
+use crate::mcp::{ProgmoMcpServer, DEFAULT_EMBEDDING_DIM};
+use crate::oidc::OidcValidator;
+use crate::text_processing::{EmbeddingError, EmbeddingProvider, FallbackMetrics, QueryCacheMetrics, QueryEmbeddingCache};
+use crate::uploads::UploadSessions;
+use crate::vector_store::{l2_normalize, CollectionRegistry, Compactor, VectorStore, VectorStoreError};
+
+pub use export::{ExportFormat, ExportRow};
+
+/// Shared state for the REST handlers
+#[derive(Clone)]
+pub struct ApiState {
+    vector_store: Arc<dyn VectorStore>,
+    embedding_provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    embedding_dim: usize,
+    normalize_embeddings: bool,
+    change_feed: Option<Arc<ChangeFeed>>,
+    attachments: Option<Arc<Attachments>>,
+    uploads: Option<Arc<UploadSessions>>,
+    registry: Arc<CollectionRegistry>,
+    api_keys: Option<Arc<ApiKeyStore>>,
+    admin_key: Option<String>,
+    oidc: Option<Arc<OidcValidator>>,
+    query_cache: Option<(Arc<QueryEmbeddingCache>, String)>,
+    retrieval_log: Option<Arc<RetrievalLog>>,
+    mcp_server: Option<Arc<ProgmoMcpServer>>,
+    compactor: Option<Arc<Compactor>>,
+}
+
+impl ApiState {
+    pub fn new(vector_store: Arc<dyn VectorStore>) -> Self {
+        Self {
+            vector_store,
+            embedding_provider: None,
+            embedding_dim: DEFAULT_EMBEDDING_DIM,
+            normalize_embeddings: false,
+            change_feed: None,
+            attachments: None,
+            uploads: None,
+            registry: Arc::new(CollectionRegistry::new()),
+            api_keys: None,
+            admin_key: None,
+            oidc: None,
+            query_cache: None,
+            retrieval_log: None,
+            mcp_server: None,
+            compactor: None,
+        }
+    }
+    
+    /// Embed queries with `provider` instead of placeholder vectors
+    pub fn with_embedding_provider(mut self, provider: Arc<dyn EmbeddingProvider + Send + Sync>) -> Self {
+        self.embedding_dim = provider.embedding_dim();
+        self.embedding_provider = Some(provider);
+        self
+    }
+    
+    /// Reuse the embeddings of recent queries from `cache`, made by the model named `model`
+    pub fn with_query_cache(mut self, cache: Arc<QueryEmbeddingCache>, model: &str) -> Self {
+        self.query_cache = Some((cache, model.to_string()));
+        self
+    }
+    
+    /// Log every search's results in `log` under a retrieval id returned
+    /// with them, as the MCP tools do
+    pub fn with_retrieval_log(mut self, log: Arc<RetrievalLog>) -> Self {
+        self.retrieval_log = Some(log);
+        self
+    }
+    
+    /// L2-normalize query embeddings, matching a server that normalizes entries
+    pub fn with_normalization(mut self, normalize: bool) -> Self {
+        self.normalize_embeddings = normalize;
+        self
+    }
+    
+    /// Serve `GET /api/changes` from `feed`, which should be fed by the
+    /// store, e.g. through an [`EventedVectorStore`](crate::vector_store::EventedVectorStore)
+    pub fn with_change_feed(mut self, feed: Arc<ChangeFeed>) -> Self {
+        self.change_feed = Some(feed);
+        self
+    }
+    
+    /// Serve attachment uploads and downloads from `attachments`
+    pub fn with_attachments(mut self, attachments: Arc<Attachments>) -> Self {
+        self.attachments = Some(attachments);
+        self
+    }
+    
+    /// Serve resumable uploads of attachments, keeping unfinished ones in
+    /// `uploads`; needs [`with_attachments`](Self::with_attachments) too
+    pub fn with_uploads(mut self, uploads: Arc<UploadSessions>) -> Self {
+        self.uploads = Some(uploads);
+        self
+    }
+    
+    /// Use the given collection registry, e.g. for collection TTLs
+    pub fn with_registry(mut self, registry: Arc<CollectionRegistry>) -> Self {
+        self.registry = registry;
+        self
+    }
+    
+    /// Require an API key on every route, checked against `keys`, and serve
+    /// `/api/admin/keys` for managing them
+    pub fn with_api_keys(mut self, keys: Arc<ApiKeyStore>) -> Self {
+        self.api_keys = Some(keys);
+        self
+    }
+    
+    /// Accept `key` everywhere, including for managing API keys
+    pub fn with_admin_key(mut self, key: Option<String>) -> Self {
+        self.admin_key = key;
+        self
+    }
+    
+    /// Require a key or a bearer token from `validator`'s issuer on every route
+    pub fn with_oidc(mut self, validator: Arc<OidcValidator>) -> Self {
+        self.oidc = Some(validator);
+        self
+    }
+    
+    /// Serve the manifest of `server` at `/.well-known/mcp.json`, for clients
+    /// discovering how to connect to it
+    pub fn with_mcp_server(mut self, server: Arc<ProgmoMcpServer>) -> Self {
+        self.mcp_server = Some(server);
+        self
+    }
+    
+    /// Serve `POST /api/admin/compact`, compacting the embedded store on demand
+    pub fn with_compactor(mut self, compactor: Arc<Compactor>) -> Self {
+        self.compactor = Some(compactor);
+        self
+    }
+    
+    pub fn vector_store(&self) -> &Arc<dyn VectorStore> {
+        &self.vector_store
+    }
+    
+    pub fn registry(&self) -> &Arc<CollectionRegistry> {
+        &self.registry
+    }
+    
+    pub fn change_feed(&self) -> Option<&Arc<ChangeFeed>> {
+        self.change_feed.as_ref()
+    }
+    
+    pub fn attachments(&self) -> Option<&Arc<Attachments>> {
+        self.attachments.as_ref()
+    }
+    
+    pub fn uploads(&self) -> Option<&Arc<UploadSessions>> {
+        self.uploads.as_ref()
+    }
+    
+    pub fn api_keys(&self) -> Option<&Arc<ApiKeyStore>> {
+        self.api_keys.as_ref()
+    }
+    
+    pub fn admin_key(&self) -> Option<&str> {
+        self.admin_key.as_deref()
+    }
+    
+    pub fn oidc(&self) -> Option<&Arc<OidcValidator>> {
+        self.oidc.as_ref()
+    }
+    
+    pub fn retrieval_log(&self) -> Option<&Arc<RetrievalLog>> {
+        self.retrieval_log.as_ref()
+    }
+    
+    pub fn mcp_server(&self) -> Option<&Arc<ProgmoMcpServer>> {
+        self.mcp_server.as_ref()
+    }
+    
+    pub fn compactor(&self) -> Option<&Arc<Compactor>> {
+        self.compactor.as_ref()
+    }
+    
+    /// Counters of the embedding provider chain, if one is configured
+    pub fn embedding_metrics(&self) -> Option<FallbackMetrics> {
+        self.embedding_provider.as_ref()?.fallback_metrics()
+    }
+    
+    /// Counters of the query embedding cache, if one is configured
+    pub fn query_cache_metrics(&self) -> Option<QueryCacheMetrics> {
+        self.query_cache.as_ref().map(|(cache, _)| cache.metrics())
+    }
+    
+    pub(crate) fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        let mut embedding = match (&self.embedding_provider, &self.query_cache) {
+            (Some(provider), Some((cache, model))) => cache.get_or_embed(model, text, |text| provider.generate_embedding(text))?,
+            (Some(provider), None) => provider.generate_embedding(text)?,
+            (None, _) => vec![0.0; self.embedding_dim],
+        };
+        if self.normalize_embeddings {
+            l2_normalize(&mut embedding);
+        }
+        Ok(embedding)
+    }
+}
+
+/// Every REST route backed by `state`, for mounting p-mo's API inside another
+/// axum application, e.g. `app.nest("/knowledge", p_mo::api::router(state))`.
+///
+/// Paths start with `/api` (plus `/metrics`, the MCP WebSocket at `/mcp` and
+/// `/.well-known/mcp.json`); the caller owns the listener and any other
+/// middleware in front of them. With [`ApiState::with_api_keys`] or
+/// [`ApiState::with_oidc`] every route but the MCP manifest requires an API
+/// key or bearer token.
+pub fn router(state: ApiState) -> Router {
+    let router = Router::new()
+        .merge(search::router(state.clone()))
+        .merge(entries::router(state.clone()))
+        .merge(ingest::router(state.clone()))
+        .merge(changes::router(state.clone()))
+        .merge(attachments::router(state.clone()))
+        .merge(uploads::router(state.clone()))
+        .merge(compaction::router(state.clone()))
+        .merge(metrics::router(state.clone()))
+        .merge(websocket::router(state.clone()));
+    // Discovery happens before a client has credentials
+    let public = manifest::router(state.clone());
+    if state.api_keys().is_none() && state.oidc().is_none() {
+        return router.merge(public);
+    }
+    router
+        .merge(keys::router(state.clone()))
+        .route_layer(middleware::from_fn_with_state(state, keys::require_api_key))
+        .merge(public)
+}
+
+/// The status for a vector store failure: bad arguments are 400, a missing collection 404, pool exhaustion is 503 so
+/// clients retry later, a collection whose schema drifted from the registry is 409 until it is reconciled, and a
+/// missed deadline is 504
+pub(crate) fn store_error_status(e: &VectorStoreError) -> StatusCode {
+    match e {
+        VectorStoreError::InvalidArgument(_) | VectorStoreError::DimensionMismatch { .. } => StatusCode::BAD_REQUEST,
+        VectorStoreError::CollectionNotFound(_) => StatusCode::NOT_FOUND,
+        VectorStoreError::PoolExhausted(_) => StatusCode::SERVICE_UNAVAILABLE,
+        VectorStoreError::SchemaMismatch { .. } => StatusCode::CONFLICT,
+        VectorStoreError::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
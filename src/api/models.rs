@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+
+use crate::vector_store::EntryId;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct KnowledgeEntry {
-    pub id: Option<Uuid>,
+    pub id: Option<EntryId>,
     pub title: String,
     pub content: String,
     pub tags: Vec<String>,
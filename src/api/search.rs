@@ -0,0 +1,105 @@
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::export::{self, ExportFormat, ExportRow};
+use super::{store_error_status, ApiState};
+use crate::context::RequestContext;
+use crate::logging::RetrievalRecord;
+use crate::vector_store::{deadline, search_unexpired, SearchQuery};
+
+/// Default number of results returned by a search
+pub const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+/// Largest number of results a single search may request
+pub const MAX_SEARCH_LIMIT: usize = 1000;
+
+/// Response header set on exported results cut short by the caller's deadline
+pub const PARTIAL_RESULTS_HEADER: &str = "x-partial-results";
+
+/// Response header carrying the retrieval id of exported results
+pub const RETRIEVAL_ID_HEADER: &str = "x-retrieval-id";
+
+/// Name searches are logged under in the retrieval log
+pub const SEARCH_RETRIEVAL_TOOL: &str = "api_search";
+
+#[derive(Debug, Deserialize)]
+pub struct SearchParams {
+    pub q: String,
+    pub collection: String,
+    pub limit: Option<usize>,
+    /// Export format; results are returned as JSON when omitted
+    pub format: Option<ExportFormat>,
+}
+
+/// Routes for `GET /api/search`
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/search", get(search))
+        .with_state(state)
+}
+
+/// Search a collection, within the deadline of an `x-request-timeout-ms` or
+/// `x-request-deadline` header if one was sent
+async fn search(State(state): State<ApiState>, headers: HeaderMap, Query(params): Query<SearchParams>) -> Response {
+    let ctx = RequestContext::from_headers(&headers);
+    if ctx.is_expired() {
+        return error(StatusCode::GATEWAY_TIMEOUT, "Deadline exceeded before the request started".to_string());
+    }
+    
+    let embedding = match state.embed(&params.q) {
+        Ok(embedding) => embedding,
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    
+    let limit = params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).min(MAX_SEARCH_LIMIT);
+    let store = state.vector_store();
+    let ttl = state.registry().ttl(&params.collection);
+    let search = search_unexpired(limit, ttl, chrono::Utc::now(), |fetch| {
+        store.search(&params.collection, SearchQuery { embedding: embedding.clone(), limit: fetch })
+    });
+    let (results, partial) = deadline::with_deadline(ctx.deadline, search).await;
+    let results = match results {
+        Ok(results) => results,
+        Err(e) => return error(store_error_status(&e), e.to_string()),
+    };
+    
+    let rows: Vec<ExportRow> = results.iter()
+        .map(|result| ExportRow::from_result(&params.collection, result).highlight(&result.document, &params.q))
+        .collect();
+    let metric = results.first().map(|result| result.metric.as_str());
+    let mut body = json!({ "results": rows, "metric": metric, "partial": partial });
+    let retrieval_id = state.retrieval_log().map(|log| {
+        let record = RetrievalRecord::new(SEARCH_RETRIEVAL_TOOL, &params.collection, &params.q, ctx.client_label(), body.clone());
+        let retrieval_id = record.retrieval_id.clone();
+        log.record(record);
+        retrieval_id
+    });
+    
+    match params.format {
+        Some(format) => {
+            let mut response = (
+                [(header::CONTENT_TYPE, format.content_type()), (header::HeaderName::from_static(PARTIAL_RESULTS_HEADER), if partial { "true" } else { "false" })],
+                export::render(format, &rows),
+            ).into_response();
+            if let Some(value) = retrieval_id.and_then(|id| HeaderValue::from_str(&id).ok()) {
+                response.headers_mut().insert(RETRIEVAL_ID_HEADER, value);
+            }
+            response
+        },
+        None => {
+            if let Some(retrieval_id) = retrieval_id {
+                body["retrieval_id"] = json!(retrieval_id);
+            }
+            Json(body).into_response()
+        },
+    }
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
@@ -0,0 +1,299 @@
+//! Resumable attachment uploads over HTTP, in the manner of tus.
+//!
+//! `POST /api/uploads` with an `Upload-Length` header starts a session and
+//! answers with its URL; the `Content-Type` of that request declares the
+//! type of the file. Each `PATCH` to the session URL appends its body at the
+//! offset given by an `Upload-Offset` header (or the start of a
+//! `Content-Range`), which must equal the bytes received so far. `GET` or
+//! `HEAD` reports the offset to resume from after a dropped connection, and
+//! `DELETE` abandons the upload.
+//!
+//! The request that completes an upload stores it as an attachment and
+//! answers like `POST /api/attachments`. A session started at
+//! `/api/collections/:collection/entries/:id/uploads` also attaches it to
+//! that entry; keys limited to some collections name the collection in a
+//! `collection` query parameter on the session's later requests.
+
+use axum::extract::{Path, Query, RawBody, State};
+use axum::http::header::{self, HeaderName};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use hyper::body::HttpBody;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::attachments::{attachment_error, created, error};
+use super::{store_error_status, ApiState};
+use crate::attachments::{AttachmentError, Attachments};
+use crate::uploads::{UploadError, UploadSession, UploadSessions};
+use crate::vector_store::EntryId;
+
+/// Header declaring the total size of an upload
+pub const UPLOAD_LENGTH_HEADER: &str = "upload-length";
+
+/// Header giving the offset a request's body starts at, or the offset reached
+pub const UPLOAD_OFFSET_HEADER: &str = "upload-offset";
+
+/// Path upload sessions are served under, relative to the API root
+pub const UPLOADS_PATH: &str = "/api/uploads";
+
+/// Routes for starting, resuming and abandoning uploads
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/api/uploads", post(start))
+        .route("/api/collections/:collection/entries/:id/uploads", post(start_for_entry))
+        .route("/api/uploads/:upload", get(status).patch(append).delete(cancel))
+        .with_state(state)
+}
+
+#[derive(Debug, Deserialize)]
+struct StartParams {
+    /// Name shown for the attachment, e.g. `manual.pdf`
+    filename: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionParams {
+    /// Collection of the session's entry, for keys limited to some collections
+    collection: Option<String>,
+}
+
+async fn start(State(state): State<ApiState>, Query(params): Query<StartParams>, headers: HeaderMap) -> Response {
+    create(&state, &params, &headers, None).await
+}
+
+async fn start_for_entry(
+    State(state): State<ApiState>,
+    Path((collection, id)): Path<(String, String)>,
+    Query(params): Query<StartParams>,
+    headers: HeaderMap,
+) -> Response {
+    let id = match EntryId::parse(&id) {
+        Ok(id) => id,
+        Err(e) => return error(StatusCode::BAD_REQUEST, e.to_string()),
+    };
+    // Check the entry first so no upload is sent for a missing one
+    match state.vector_store().exists(&collection, id.as_str()).await {
+        Ok(true) => {},
+        Ok(false) => return error(StatusCode::NOT_FOUND, format!("Entry {} not found in collection {}", id, collection)),
+        Err(e) => return error(store_error_status(&e), e.to_string()),
+    }
+    create(&state, &params, &headers, Some((collection.as_str(), id.as_str()))).await
+}
+
+async fn create(state: &ApiState, params: &StartParams, headers: &HeaderMap, entry: Option<(&str, &str)>) -> Response {
+    let (Some(uploads), Some(_)) = (state.uploads(), state.attachments()) else { return not_enabled() };
+    let Some(length) = header_u64(headers, UPLOAD_LENGTH_HEADER) else {
+        return error(StatusCode::BAD_REQUEST, "Missing or invalid Upload-Length header".to_string());
+    };
+    let content_type = headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok());
+    
+    match uploads.create(length, content_type, params.filename.as_deref(), entry).await {
+        Ok(session) => {
+            let url = upload_url(&session.id);
+            let mut response = (StatusCode::CREATED, offset_headers(&session, 0), Json(session_json(&session, 0))).into_response();
+            if let Ok(location) = url.parse() {
+                response.headers_mut().insert(header::LOCATION, location);
+            }
+            response
+        },
+        Err(e) => upload_error(&e),
+    }
+}
+
+async fn status(State(state): State<ApiState>, Path(upload): Path<String>, Query(params): Query<SessionParams>) -> Response {
+    let Some(uploads) = state.uploads() else { return not_enabled() };
+    match uploads.get(&upload).await {
+        Ok((session, _)) if !allows(&session, &params) => upload_error(&UploadError::NotFound(upload)),
+        Ok((session, offset)) => (offset_headers(&session, offset), Json(session_json(&session, offset))).into_response(),
+        Err(e) => upload_error(&e),
+    }
+}
+
+/// Append the body at the offset the request gives, completing the upload
+/// once every byte has arrived
+async fn append(
+    State(state): State<ApiState>,
+    Path(upload): Path<String>,
+    Query(params): Query<SessionParams>,
+    headers: HeaderMap,
+    RawBody(mut body): RawBody,
+) -> Response {
+    let (Some(uploads), Some(attachments)) = (state.uploads(), state.attachments()) else { return not_enabled() };
+    let offset = match request_offset(&headers) {
+        Ok(offset) => offset,
+        Err(message) => return error(StatusCode::BAD_REQUEST, message),
+    };
+    let mut writer = match uploads.resume(&upload, offset).await {
+        Ok(writer) => writer,
+        Err(e) => return upload_error(&e),
+    };
+    if !allows(writer.session(), &params) {
+        return upload_error(&UploadError::NotFound(upload));
+    }
+    if header_u64(&headers, header::CONTENT_LENGTH.as_str()).is_some_and(|length| length > writer.remaining()) {
+        return upload_error(&UploadError::PastEnd { length: writer.session().length });
+    }
+    
+    // Write the body as it arrives, so a dropped connection keeps what got through
+    let mut failure = None;
+    while let Some(chunk) = body.data().await {
+        let written = match chunk {
+            Ok(chunk) => writer.write(&chunk).await,
+            Err(e) => Err(UploadError::Interrupted(e.to_string())),
+        };
+        if let Err(e) = written {
+            failure = Some(e);
+            break;
+        }
+    }
+    let session = writer.session().clone();
+    let offset = match writer.finish().await {
+        Ok(offset) => offset,
+        Err(e) => return upload_error(&e),
+    };
+    if let Some(e) = failure {
+        return (offset_headers(&session, offset), upload_error(&e)).into_response();
+    }
+    if offset < session.length {
+        return (StatusCode::NO_CONTENT, offset_headers(&session, offset)).into_response();
+    }
+    
+    // Still holding the session, so a request racing this one cannot complete it twice
+    let response = complete(&state, uploads, attachments, &session).await;
+    drop(writer);
+    (offset_headers(&session, offset), response).into_response()
+}
+
+/// Store a fully received upload as an attachment, linked to the session's
+/// entry if it has one, and drop the session
+async fn complete(state: &ApiState, uploads: &UploadSessions, attachments: &Attachments, session: &UploadSession) -> Response {
+    // Stream the received file into storage; uploads may be far larger than a request body
+    let content = match uploads.content_path(&session.id) {
+        Ok(content) => content,
+        Err(e) => return upload_error(&e),
+    };
+    let attachment = match attachments.upload_file(&content, session.content_type.as_deref(), session.filename.as_deref()).await {
+        Ok(attachment) => attachment,
+        Err(e) => {
+            // Storage failures are worth retrying with an empty PATCH; rejected content is not
+            if !matches!(e, AttachmentError::Storage(_) | AttachmentError::Store(_)) {
+                let _ = uploads.remove(&session.id).await;
+            }
+            return attachment_error(&e);
+        },
+    };
+    if let (Some(collection), Some(entry_id)) = (&session.collection, &session.entry_id) {
+        if let Err(e) = attachments.attach(state.vector_store().as_ref(), collection, entry_id, &attachment).await {
+            return attachment_error(&e);
+        }
+    }
+    if let Err(e) = uploads.remove(&session.id).await {
+        return upload_error(&e);
+    }
+    created(&attachment)
+}
+
+async fn cancel(State(state): State<ApiState>, Path(upload): Path<String>, Query(params): Query<SessionParams>) -> Response {
+    let Some(uploads) = state.uploads() else { return not_enabled() };
+    match uploads.get(&upload).await {
+        Ok((session, _)) if !allows(&session, &params) => upload_error(&UploadError::NotFound(upload)),
+        Ok(_) => match uploads.remove(&upload).await {
+            Ok(()) => StatusCode::NO_CONTENT.into_response(),
+            Err(e) => upload_error(&e),
+        },
+        Err(e) => upload_error(&e),
+    }
+}
+
+/// Whether a request may touch `session`. Requests naming a collection, as
+/// collection-limited keys must, only reach sessions for that collection.
+fn allows(session: &UploadSession, params: &SessionParams) -> bool {
+    match &params.collection {
+        Some(collection) => session.collection.as_ref() == Some(collection),
+        None => true,
+    }
+}
+
+/// The offset a request's body starts at, from `Upload-Offset` or else the
+/// first byte of `Content-Range: bytes <first>-<last>/<length>`
+fn request_offset(headers: &HeaderMap) -> Result<u64, String> {
+    if let Some(value) = headers.get(UPLOAD_OFFSET_HEADER) {
+        return value.to_str().ok()
+            .and_then(|value| value.trim().parse().ok())
+            .ok_or_else(|| "Invalid Upload-Offset header".to_string());
+    }
+    let range = headers.get(header::CONTENT_RANGE)
+        .ok_or_else(|| "Missing Upload-Offset or Content-Range header".to_string())?;
+    range.to_str().ok()
+        .and_then(|range| range.trim().strip_prefix("bytes "))
+        .and_then(|range| range.split('-').next())
+        .and_then(|first| first.trim().parse().ok())
+        .ok_or_else(|| "Invalid Content-Range header; expected bytes <first>-<last>/<length>".to_string())
+}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+fn upload_url(id: &str) -> String {
+    format!("{}/{}", UPLOADS_PATH, id)
+}
+
+fn session_json(session: &UploadSession, offset: u64) -> Value {
+    let mut body = json!(session);
+    body["offset"] = json!(offset);
+    body["url"] = json!(upload_url(&session.id));
+    body
+}
+
+/// Headers reporting where an upload stands; never cached, as the offset moves
+fn offset_headers(session: &UploadSession, offset: u64) -> [(HeaderName, String); 3] {
+    [
+        (HeaderName::from_static(UPLOAD_OFFSET_HEADER), offset.to_string()),
+        (HeaderName::from_static(UPLOAD_LENGTH_HEADER), session.length.to_string()),
+        (header::CACHE_CONTROL, "no-store".to_string()),
+    ]
+}
+
+/// The status for an upload failure: a wrong offset is 409 so the client
+/// asks for the right one, storage failures are 5xx and the rest 4xx
+fn upload_error(e: &UploadError) -> Response {
+    let status = match e {
+        UploadError::NotFound(_) => StatusCode::NOT_FOUND,
+        UploadError::OffsetMismatch { .. } => StatusCode::CONFLICT,
+        UploadError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        UploadError::PastEnd { .. } | UploadError::Empty | UploadError::Interrupted(_) => StatusCode::BAD_REQUEST,
+        UploadError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    error(status, e.to_string())
+}
+
+fn not_enabled() -> Response {
+    error(StatusCode::NOT_FOUND, "Resumable uploads are not enabled".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    
+    #[test]
+    fn test_offset_from_either_header() {
+        let mut headers = HeaderMap::new();
+        assert!(request_offset(&headers).is_err());
+        
+        headers.insert(header::CONTENT_RANGE, HeaderValue::from_static("bytes 1024-2047/4096"));
+        assert_eq!(request_offset(&headers), Ok(1024));
+        headers.insert(UPLOAD_OFFSET_HEADER, HeaderValue::from_static("512"));
+        assert_eq!(request_offset(&headers), Ok(512));
+        
+        headers.insert(UPLOAD_OFFSET_HEADER, HeaderValue::from_static("-1"));
+        assert!(request_offset(&headers).is_err());
+        headers.remove(UPLOAD_OFFSET_HEADER);
+        headers.insert(header::CONTENT_RANGE, HeaderValue::from_static("items 0-1/2"));
+        assert!(request_offset(&headers).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+//! MCP over WebSocket.
+//!
+//! `GET /mcp` upgrades to a WebSocket carrying one JSON-RPC message per text
+//! frame, served like stdio: requests run concurrently, each response is sent
+//! as it completes, and closing the socket cancels whatever is still running.
+//! The upgrade request takes the same credentials as the REST routes. Its
+//! API key or token identifies the caller of every request on the socket,
+//! and each tool call and resource read must be within the credential's
+//! scope and collections, just as the REST route doing the same would.
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Extension, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use futures::{future, SinkExt, StreamExt};
+use serde_json::json;
+use std::io;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use super::ApiState;
+use crate::api_keys::Grant;
+use crate::context::RequestContext;
+use crate::mcp::transport;
+use crate::mcp::ProgmoMcpServer;
+
+/// Path MCP clients open a WebSocket on
+pub const MCP_WEBSOCKET_PATH: &str = "/mcp";
+
+/// Routes for `GET /mcp`
+pub fn router(state: ApiState) -> Router {
+    Router::new()
+        .route(MCP_WEBSOCKET_PATH, get(upgrade))
+        .with_state(state)
+}
+
+async fn upgrade(
+    State(state): State<ApiState>,
+    grant: Option<Extension<Grant>>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let Some(server) = state.mcp_server().cloned() else {
+        return error(StatusCode::NOT_FOUND, "No MCP server is configured".to_string());
+    };
+    // Deadlines belong to requests, which carry them in `_meta`, not to the socket
+    let mut ctx = RequestContext { deadline: None, ..RequestContext::from_headers(&headers) };
+    if let Some(Extension(grant)) = grant {
+        ctx = ctx.with_grant(grant);
+    }
+    upgrade.on_upgrade(move |socket| serve(server, socket, ctx))
+}
+
+/// Serve the requests on `socket` until the client closes it or goes away
+async fn serve(server: Arc<ProgmoMcpServer>, socket: WebSocket, ctx: RequestContext) {
+    let (mut sink, stream) = socket.split();
+    let (responses, mut outgoing) = mpsc::unbounded_channel::<String>();
+    let writer = tokio::spawn(async move {
+        while let Some(response) = outgoing.recv().await {
+            if sink.send(Message::Text(response)).await.is_err() {
+                break;
+            }
+        }
+    });
+    
+    // Pings are answered by the socket itself; a close frame ends the connection
+    let requests = stream
+        .take_while(|message| future::ready(!matches!(message, Ok(Message::Close(_)))))
+        .filter_map(|message| future::ready(match message {
+            Ok(Message::Text(text)) => Some(Ok(text)),
+            Ok(Message::Binary(bytes)) => Some(Ok(String::from_utf8_lossy(&bytes).into_owned())),
+            Ok(_) => None,
+            Err(e) => Some(Err(io::Error::other(e))),
+        }));
+    if let Err(e) = transport::serve_requests(server, requests, responses, ctx).await {
+        debug!("MCP WebSocket connection failed: {}", e);
+    }
+    writer.abort();
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
@@ -0,0 +1,432 @@
+//! API keys managed at runtime.
+//!
+//! Administrators create, list and revoke keys through the admin REST
+//! endpoints or MCP tools, without restarting the server. Only a SHA-256
+//! hash of each key is kept, in [`API_KEYS_FILE`] under the data directory;
+//! the key itself is shown once, when it is created. Authentication reads
+//! the live set, so a revoked key is refused on its next request, and every
+//! create and revoke is recorded in the config audit log.
+//!
+//! Each key carries a [`Scope`], optionally a list of the collections it may
+//! touch, and optionally an expiry, so e.g. a CI job can get a write key that
+//! lapses after an hour while an agent gets a read-only one.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::logging::{ConfigAuditLog, ConfigChange};
+
+/// File under the data directory holding the managed keys
+pub const API_KEYS_FILE: &str = "api_keys.json";
+
+/// Prefix of every generated key, so leaked keys are easy to recognise
+pub const KEY_PREFIX: &str = "pmo_";
+
+/// Characters of a key kept in clear to tell keys apart in listings
+const VISIBLE_CHARS: usize = 12;
+
+#[derive(Debug, Error)]
+pub enum ApiKeyError {
+    #[error("API key '{0}' not found")]
+    NotFound(String),
+    
+    #[error("API key name must not be empty")]
+    EmptyName,
+    
+    #[error("Invalid API key options: {0}")]
+    InvalidOptions(String),
+    
+    #[error("API key storage error: {0}")]
+    Storage(String),
+}
+
+/// What a key may do; each scope includes the ones before it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Search and read entries
+    Read,
+    /// Also add, change and delete entries; keys created before scopes existed have it
+    #[default]
+    Write,
+    /// Also manage API keys
+    Admin,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::Admin => "admin",
+        })
+    }
+}
+
+impl FromStr for Scope {
+    type Err = ApiKeyError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Scope::Read),
+            "write" => Ok(Scope::Write),
+            "admin" => Ok(Scope::Admin),
+            other => Err(ApiKeyError::InvalidOptions(format!("unknown scope '{}'; use read, write or admin", other))),
+        }
+    }
+}
+
+/// What a new key is allowed, and for how long
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyOptions {
+    pub scope: Scope,
+    /// The collections the key may touch; `None` allows all of them
+    pub collections: Option<Vec<String>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl KeyOptions {
+    /// Read `scope`, `collections` and either `expires_at` (RFC 3339) or
+    /// `ttl` (seconds from now) from a request's arguments
+    pub fn from_json(arguments: &Value) -> Result<Self, ApiKeyError> {
+        let invalid = |message: String| ApiKeyError::InvalidOptions(message);
+        let scope = match arguments.get("scope") {
+            None | Some(Value::Null) => Scope::default(),
+            Some(scope) => scope.as_str().ok_or_else(|| invalid("scope must be a string".to_string()))?.parse()?,
+        };
+        let collections = match arguments.get("collections") {
+            None | Some(Value::Null) => None,
+            Some(collections) => Some(serde_json::from_value::<Vec<String>>(collections.clone())
+                .map_err(|e| invalid(format!("collections: {}", e)))?),
+        };
+        let expires_at = match (arguments.get("expires_at"), arguments.get("ttl")) {
+            (Some(_), Some(_)) => return Err(invalid("give either expires_at or ttl, not both".to_string())),
+            (Some(expires_at), None) => {
+                let expires_at = expires_at.as_str().ok_or_else(|| invalid("expires_at must be a string".to_string()))?;
+                let expires_at = DateTime::parse_from_rfc3339(expires_at).map_err(|e| invalid(format!("expires_at: {}", e)))?;
+                Some(expires_at.with_timezone(&Utc))
+            },
+            (None, Some(ttl)) => {
+                let ttl = ttl.as_i64().filter(|ttl| *ttl > 0).ok_or_else(|| invalid("ttl must be a positive number of seconds".to_string()))?;
+                let expires_at = chrono::Duration::try_seconds(ttl).and_then(|ttl| Utc::now().checked_add_signed(ttl));
+                Some(expires_at.ok_or_else(|| invalid(format!("ttl {} is too large", ttl)))?)
+            },
+            (None, None) => None,
+        };
+        Ok(Self { scope, collections, expires_at })
+    }
+    
+    fn check(&self, now: DateTime<Utc>) -> Result<(), ApiKeyError> {
+        if self.collections.as_ref().is_some_and(|collections| collections.is_empty()) {
+            return Err(ApiKeyError::InvalidOptions("collections must not be empty; omit it to allow every collection".to_string()));
+        }
+        if self.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(ApiKeyError::InvalidOptions("expires_at must be in the future".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// A managed key as stored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub id: String,
+    pub name: String,
+    /// Hex SHA-256 of the key
+    pub hash: String,
+    /// The start of the key, for recognising it in listings
+    pub prefix: String,
+    #[serde(default)]
+    pub scope: Scope,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collections: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKeyRecord {
+    /// Whether the key is accepted at `now`: neither revoked nor expired
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+    
+    /// The record as shown to administrators, with the key masked and no hash
+    pub fn masked(&self) -> ApiKeyInfo {
+        ApiKeyInfo {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            key: format!("{}…", self.prefix),
+            scope: self.scope,
+            collections: self.collections.clone(),
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            revoked_at: self.revoked_at,
+            active: self.is_active_at(Utc::now()),
+        }
+    }
+}
+
+/// A managed key as listed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApiKeyInfo {
+    pub id: String,
+    pub name: String,
+    /// The key's first characters followed by an ellipsis
+    pub key: String,
+    pub scope: Scope,
+    pub collections: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub active: bool,
+}
+
+impl ApiKeyInfo {
+    /// Whether the key grants `scope`, on `collection` when the request names
+    /// one; a key limited to some collections may not make requests naming none
+    pub fn allows(&self, scope: Scope, collection: Option<&str>) -> bool {
+        grants(self.scope, self.collections.as_deref(), scope, collection)
+    }
+}
+
+/// What a verified credential allows, carried along with the requests it made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grant {
+    pub scope: Scope,
+    /// The collections the credential may touch; `None` allows all of them
+    pub collections: Option<Vec<String>>,
+}
+
+impl Grant {
+    /// Whether the grant covers `scope`, on `collection` when the request names one
+    pub fn allows(&self, scope: Scope, collection: Option<&str>) -> bool {
+        grants(self.scope, self.collections.as_deref(), scope, collection)
+    }
+}
+
+/// Whether a credential with scope `granted`, limited to `collections` if
+/// given, may make a request needing `scope` that names `collection`
+pub fn grants(granted: Scope, collections: Option<&[String]>, scope: Scope, collection: Option<&str>) -> bool {
+    granted >= scope && match (collections, collection) {
+        (None, _) => true,
+        (Some(allowed), Some(collection)) => allowed.iter().any(|allowed| allowed == collection),
+        (Some(_), None) => false,
+    }
+}
+
+/// The managed keys, optionally persisted to a file
+#[derive(Debug, Default)]
+pub struct ApiKeyStore {
+    path: Option<PathBuf>,
+    keys: RwLock<Vec<ApiKeyRecord>>,
+    audit_log: Option<Arc<ConfigAuditLog>>,
+}
+
+impl ApiKeyStore {
+    /// Create a store kept only in memory
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Load the keys in `path`, which is written back on every change; a
+    /// missing file is an empty store
+    pub fn load(path: &Path) -> Result<Self, ApiKeyError> {
+        let keys = match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| ApiKeyError::Storage(format!("Failed to parse {}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(ApiKeyError::Storage(format!("Failed to read {}: {}", path.display(), e))),
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            keys: RwLock::new(keys),
+            audit_log: None,
+        })
+    }
+    
+    /// Record key lifecycle events in `log`
+    pub fn with_audit_log(mut self, log: Arc<ConfigAuditLog>) -> Self {
+        self.audit_log = Some(log);
+        self
+    }
+    
+    /// Create a key named `name`, returning it in clear; it cannot be shown again
+    pub fn create(&self, name: &str, options: KeyOptions, actor: &str, source: &str) -> Result<(ApiKeyInfo, String), ApiKeyError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ApiKeyError::EmptyName);
+        }
+        let now = Utc::now();
+        options.check(now)?;
+        
+        let key = format!("{}{}{}", KEY_PREFIX, Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let record = ApiKeyRecord {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            hash: hash_key(&key),
+            prefix: key[..VISIBLE_CHARS].to_string(),
+            scope: options.scope,
+            collections: options.collections,
+            created_at: now,
+            expires_at: options.expires_at,
+            revoked_at: None,
+        };
+        let info = record.masked();
+        
+        let mut keys = self.keys.write().unwrap();
+        keys.push(record);
+        if let Err(e) = self.save(&keys) {
+            keys.pop();
+            return Err(e);
+        }
+        drop(keys);
+        
+        self.audit(&info.id, Value::Null, json!(info), actor, source);
+        Ok((info, key))
+    }
+    
+    /// Every key, revoked ones included, oldest first
+    pub fn list(&self) -> Vec<ApiKeyInfo> {
+        self.keys.read().unwrap().iter().map(ApiKeyRecord::masked).collect()
+    }
+    
+    /// Revoke the key `id`; requests presenting it are refused from now on
+    pub fn revoke(&self, id: &str, actor: &str, source: &str) -> Result<ApiKeyInfo, ApiKeyError> {
+        let mut keys = self.keys.write().unwrap();
+        let index = keys.iter().position(|record| record.id == id)
+            .ok_or_else(|| ApiKeyError::NotFound(id.to_string()))?;
+        let before = keys[index].masked();
+        if keys[index].revoked_at.is_some() {
+            return Ok(before);
+        }
+        
+        keys[index].revoked_at = Some(Utc::now());
+        if let Err(e) = self.save(&keys) {
+            keys[index].revoked_at = None;
+            return Err(e);
+        }
+        let after = keys[index].masked();
+        drop(keys);
+        
+        self.audit(id, json!(before), json!(after), actor, source);
+        Ok(after)
+    }
+    
+    /// The active key matching `key`, if any; revoked and expired keys never match
+    pub fn verify(&self, key: &str) -> Option<ApiKeyInfo> {
+        let hash = hash_key(key);
+        let now = Utc::now();
+        self.keys.read().unwrap()
+            .iter()
+            .find(|record| record.is_active_at(now) && constant_time_eq(record.hash.as_bytes(), hash.as_bytes()))
+            .map(ApiKeyRecord::masked)
+    }
+    
+    /// Write `keys` to the store's file, replacing it atomically
+    fn save(&self, keys: &[ApiKeyRecord]) -> Result<(), ApiKeyError> {
+        let Some(path) = &self.path else { return Ok(()) };
+        let failed = |e: String| ApiKeyError::Storage(format!("Failed to write {}: {}", path.display(), e));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| failed(e.to_string()))?;
+        }
+        let text = serde_json::to_string_pretty(keys).map_err(|e| failed(e.to_string()))?;
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, text).map_err(|e| failed(e.to_string()))?;
+        fs::rename(&temp, path).map_err(|e| failed(e.to_string()))
+    }
+    
+    fn audit(&self, id: &str, old_value: Value, new_value: Value, actor: &str, source: &str) {
+        if let Some(log) = &self.audit_log {
+            log.record(ConfigChange::new(format!("api_keys.{}", id), old_value, new_value, actor, source));
+        }
+    }
+}
+
+/// Hex SHA-256 of a key, as stored
+fn hash_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Compare secrets without short-circuiting on the first differing byte
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::ConfigHistoryQuery;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_create_verify_revoke() {
+        let audit_log = Arc::new(ConfigAuditLog::new(10));
+        let store = ApiKeyStore::new().with_audit_log(audit_log.clone());
+        
+        let (info, key) = store.create("ci", KeyOptions::default(), "admin", "test").unwrap();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert!(!info.key.contains(&key));
+        assert_eq!(store.verify(&key).unwrap().id, info.id);
+        assert!(store.verify("pmo_guess").is_none());
+        
+        store.revoke(&info.id, "admin", "test").unwrap();
+        assert!(store.verify(&key).is_none());
+        assert!(!store.list()[0].active);
+        assert!(matches!(store.revoke("missing", "admin", "test"), Err(ApiKeyError::NotFound(_))));
+        assert!(matches!(store.create("  ", KeyOptions::default(), "admin", "test"), Err(ApiKeyError::EmptyName)));
+        
+        let history = audit_log.history(&ConfigHistoryQuery::default());
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().all(|change| !change.new_value.to_string().contains(&key)));
+    }
+    
+    #[test]
+    fn test_scopes_and_expiry() {
+        let store = ApiKeyStore::new();
+        let options = KeyOptions::from_json(&json!({"scope": "read", "collections": ["docs"], "ttl": 3600})).unwrap();
+        let (info, key) = store.create("agent", options, "admin", "test").unwrap();
+        assert!(info.allows(Scope::Read, Some("docs")));
+        assert!(!info.allows(Scope::Write, Some("docs")));
+        assert!(!info.allows(Scope::Read, Some("notes")));
+        assert!(!info.allows(Scope::Read, None));
+        assert!(store.verify(&key).is_some());
+        
+        let (_, expired) = store.create("ci", KeyOptions::default(), "admin", "test").unwrap();
+        store.keys.write().unwrap()[1].expires_at = Some(Utc::now() - chrono::Duration::seconds(1));
+        assert!(store.verify(&expired).is_none());
+        assert!(!store.list()[1].active);
+        
+        assert!(KeyOptions::from_json(&json!({"scope": "root"})).is_err());
+        assert!(KeyOptions::from_json(&json!({"ttl": 60, "expires_at": "2030-01-01T00:00:00Z"})).is_err());
+        assert!(matches!(KeyOptions::from_json(&json!({"ttl": i64::MAX})), Err(ApiKeyError::InvalidOptions(_))));
+        let past = KeyOptions { expires_at: Some(Utc::now() - chrono::Duration::seconds(1)), ..Default::default() };
+        assert!(matches!(store.create("late", past, "admin", "test"), Err(ApiKeyError::InvalidOptions(_))));
+    }
+    
+    #[test]
+    fn test_keys_persist_hashed() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(API_KEYS_FILE);
+        
+        let (_, key) = ApiKeyStore::load(&path).unwrap().create("ci", KeyOptions::default(), "admin", "test").unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains(&key));
+        
+        let reloaded = ApiKeyStore::load(&path).unwrap();
+        assert!(reloaded.verify(&key).is_some());
+    }
+}
@@ -1,10 +1,13 @@
 use crate::cli::{Cli, Command, CliError};
 use crate::config::Config;
+use crate::progress::OutputMode;
 use std::path::PathBuf;
 
 pub struct App {
     cli: Cli,
     config: Option<Config>,
+    profile: Option<String>,
+    container: bool,
 }
 
 impl App {
@@ -12,22 +15,66 @@ impl App {
         Self {
             cli: Cli::new(),
             config: None,
+            profile: None,
+            container: false,
+        }
+    }
+    
+    /// Report the progress of long-running commands as `output` asks for
+    pub fn with_output(mut self, output: OutputMode) -> Self {
+        self.cli = self.cli.with_output(output);
+        self
+    }
+
+    /// Layer `profile` over the config file, instead of the profile named by `P_MO_PROFILE`
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.cli = self.cli.with_profile(profile.clone());
+        self.profile = profile;
+        self
+    }
+
+    /// Run the server on `start` until terminated; see [`Cli::with_serving`]
+    pub fn with_serving(mut self, serving: bool) -> Self {
+        self.cli = self.cli.with_serving(serving);
+        self
+    }
+
+    /// Run as a container's main process, whatever the config file says
+    pub fn with_container(mut self, container: bool) -> Self {
+        self.cli = self.cli.with_container(container);
+        self.container = container;
+        self
+    }
+
+    /// Whether `command` runs in container mode, from `--container` or the
+    /// `server.container` switch of the config it starts with
+    pub fn container_mode(&self, command: &Command) -> bool {
+        match command {
+            _ if self.container => true,
+            Command::Start { config_path: Some(path), .. } if path.exists() => {
+                Config::load_profile(path, self.profile.as_deref()).is_ok_and(|config| config.server.container)
+            },
+            _ => false,
         }
     }
 
     pub fn load_config(&mut self, config_path: &Option<PathBuf>) -> Result<(), CliError> {
         let config_path = config_path.clone().unwrap_or_else(Config::default_path);
-        self.config = Some(Config::load(&config_path).map_err(CliError::from)?);
+        self.config = Some(Config::load_profile(&config_path, self.profile.as_deref()).map_err(CliError::from)?);
         Ok(())
     }
 
     pub fn execute(&mut self, command: Command) -> Result<String, CliError> {
         match command {
             Command::Start { host, port, daemon, config_path } => {
-                // Try to load config if path is provided
-                if let Some(path) = &config_path {
+                // A container reads its settings from the environment, with or
+                // without a config file; otherwise try to load config if path is provided
+                if self.container {
+                    let path = config_path.clone().unwrap_or_else(Config::default_path);
+                    self.config = Some(Config::load_container(&path, self.profile.as_deref()).map_err(CliError::from)?);
+                } else if let Some(path) = &config_path {
                     if path.exists() {
-                        if let Ok(cfg) = Config::load(path) {
+                        if let Ok(cfg) = Config::load_profile(path, self.profile.as_deref()) {
                             self.config = Some(cfg);
                         }
                     }
@@ -42,7 +89,9 @@ impl App {
                     self.config.as_ref().map(|c| c.server.port)
                 });
                 
-                let daemon_mode = daemon || self.config.as_ref().map_or(false, |c| c.server.daemon);
+                // Containers run in the foreground under their runtime
+                let container = self.container || self.config.as_ref().is_some_and(|c| c.server.container);
+                let daemon_mode = !container && (daemon || self.config.as_ref().is_some_and(|c| c.server.daemon));
                 
                 self.cli.execute(Command::Start { 
                     host: config_host, 
@@ -0,0 +1,480 @@
+//! Binary attachments of knowledge entries.
+//!
+//! Attachments such as diagrams and screenshots are stored by the SHA-256
+//! hash of their content, on disk or in S3-compatible storage. Entries refer
+//! to them from their metadata, so vectors stay text-only and the same file
+//! attached twice is stored once.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::io::AsyncReadExt;
+
+use crate::config::{AttachmentsConfig, Config};
+use crate::sources::{Credentials, S3Client, SourceError};
+use crate::vector_store::{Document, VectorStore, VectorStoreError};
+
+/// Metadata key holding the JSON list of an entry's attachments
+pub const ATTACHMENTS_KEY: &str = "attachments";
+
+/// Path attachments are served from, relative to the API root
+pub const ATTACHMENTS_PATH: &str = "/api/attachments";
+
+/// Content type served when a stored attachment is not recognised
+const FALLBACK_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Leading bytes [`detect_content_type`] looks at
+const SNIFF_BYTES: usize = 1024;
+
+/// Bytes read at a time when hashing a file
+const READ_CHUNK_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Error)]
+pub enum AttachmentError {
+    #[error("Attachment is {size} bytes; the limit is {max} bytes")]
+    TooLarge { size: usize, max: usize },
+    
+    #[error("Attachment is empty")]
+    Empty,
+    
+    #[error("Content type {0} is not allowed for attachments")]
+    UnsupportedType(String),
+    
+    #[error("Attachment was declared as {declared} but its content is {detected}")]
+    TypeMismatch { declared: String, detected: String },
+    
+    #[error("Attachment content type is missing and could not be detected")]
+    UnknownType,
+    
+    #[error("Invalid attachment hash '{0}'")]
+    InvalidHash(String),
+    
+    #[error("Entry {id} not found in collection {collection}")]
+    EntryNotFound { collection: String, id: String },
+    
+    #[error("Attachment storage error: {0}")]
+    Storage(String),
+    
+    #[error("Vector store error: {0}")]
+    Store(#[from] VectorStoreError),
+}
+
+impl From<std::io::Error> for AttachmentError {
+    fn from(e: std::io::Error) -> Self {
+        AttachmentError::Storage(e.to_string())
+    }
+}
+
+impl From<SourceError> for AttachmentError {
+    fn from(e: SourceError) -> Self {
+        AttachmentError::Storage(e.to_string())
+    }
+}
+
+/// An attachment as referenced from an entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// Hex SHA-256 of the content, which also names it in storage
+    pub hash: String,
+    pub content_type: String,
+    pub size: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+}
+
+impl Attachment {
+    /// Where the attachment is downloaded from, relative to the API root
+    pub fn url(&self) -> String {
+        format!("{}/{}", ATTACHMENTS_PATH, self.hash)
+    }
+}
+
+/// The attachments listed in an entry's metadata
+pub fn attachments_of(document: &Document) -> Vec<Attachment> {
+    document.metadata.get(ATTACHMENTS_KEY)
+        .and_then(|attachments| serde_json::from_str(attachments).ok())
+        .unwrap_or_default()
+}
+
+/// Check that `hash` is a hex SHA-256, the only names attachments are stored under
+pub fn validate_hash(hash: &str) -> Result<(), AttachmentError> {
+    if hash.len() == 64 && hash.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f')) {
+        Ok(())
+    } else {
+        Err(AttachmentError::InvalidHash(hash.to_string()))
+    }
+}
+
+/// The content type of `bytes` going by their leading bytes, for the types attachments commonly have
+pub fn detect_content_type(bytes: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: [(&[u8], &str); 5] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+    ];
+    if let Some((_, content_type)) = SIGNATURES.iter().find(|(signature, _)| bytes.starts_with(signature)) {
+        return Some(content_type);
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(SNIFF_BYTES)]);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    if head.starts_with("<svg") || (head.starts_with("<?xml") && head.contains("<svg")) {
+        return Some("image/svg+xml");
+    }
+    None
+}
+
+/// The media type of a `Content-Type` value, without parameters
+fn media_type(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
+
+/// Storage for attachment content, keyed by hash
+#[async_trait]
+pub trait AttachmentStore: Send + Sync {
+    /// Store `bytes` under `hash`; storing the same hash again is harmless
+    async fn put(&self, hash: &str, bytes: &[u8], content_type: &str) -> Result<(), AttachmentError>;
+    
+    /// Store the content of the file at `path` under `hash` without reading it into memory
+    async fn put_file(&self, hash: &str, path: &Path, content_type: &str) -> Result<(), AttachmentError>;
+    
+    /// The content stored under `hash`, if any
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, AttachmentError>;
+}
+
+/// Attachments kept in a local directory, sharded by the first two hex digits
+#[derive(Debug, Clone)]
+pub struct DiskAttachmentStore {
+    root: PathBuf,
+}
+
+impl DiskAttachmentStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+    
+    /// A store in `root`, creating the directory now so a path that cannot
+    /// hold attachments is found before the first upload
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, AttachmentError> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)
+            .map_err(|e| AttachmentError::Storage(format!("Cannot use {} for attachments: {}", root.display(), e)))?;
+        Ok(Self { root })
+    }
+    
+    fn path(&self, hash: &str) -> PathBuf {
+        self.root.join(&hash[..2]).join(hash)
+    }
+}
+
+#[async_trait]
+impl AttachmentStore for DiskAttachmentStore {
+    async fn put(&self, hash: &str, bytes: &[u8], _content_type: &str) -> Result<(), AttachmentError> {
+        let path = self.path(hash);
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(());
+        }
+        tokio::fs::create_dir_all(path.parent().expect("sharded path has a parent")).await?;
+        // Write under a temporary name so a partial file is never served
+        let partial = path.with_extension(format!("partial-{}", uuid::Uuid::new_v4()));
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(())
+    }
+    
+    async fn put_file(&self, hash: &str, source: &Path, _content_type: &str) -> Result<(), AttachmentError> {
+        let path = self.path(hash);
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(());
+        }
+        tokio::fs::create_dir_all(path.parent().expect("sharded path has a parent")).await?;
+        let partial = path.with_extension(format!("partial-{}", uuid::Uuid::new_v4()));
+        tokio::fs::copy(source, &partial).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(())
+    }
+    
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, AttachmentError> {
+        match tokio::fs::read(self.path(hash)).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Attachments kept in an S3-compatible bucket
+pub struct BucketAttachmentStore {
+    client: S3Client,
+    prefix: String,
+}
+
+impl BucketAttachmentStore {
+    pub fn new(client: S3Client, prefix: &str) -> Self {
+        Self { client, prefix: prefix.to_string() }
+    }
+    
+    fn key(&self, hash: &str) -> String {
+        format!("{}{}", self.prefix, hash)
+    }
+}
+
+#[async_trait]
+impl AttachmentStore for BucketAttachmentStore {
+    async fn put(&self, hash: &str, bytes: &[u8], content_type: &str) -> Result<(), AttachmentError> {
+        Ok(self.client.put_object(&self.key(hash), bytes.to_vec(), content_type).await?)
+    }
+    
+    async fn put_file(&self, hash: &str, path: &Path, content_type: &str) -> Result<(), AttachmentError> {
+        let file = tokio::fs::File::open(path).await?;
+        let length = file.metadata().await?.len();
+        Ok(self.client.put_object_file(&self.key(hash), file, length, hash, content_type).await?)
+    }
+    
+    async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, AttachmentError> {
+        Ok(self.client.get_object(&self.key(hash)).await?)
+    }
+}
+
+/// Validates, stores and links attachments
+pub struct Attachments {
+    store: Arc<dyn AttachmentStore>,
+    max_bytes: usize,
+    allowed_types: Vec<String>,
+}
+
+impl Attachments {
+    pub fn new(store: Arc<dyn AttachmentStore>) -> Self {
+        let defaults = AttachmentsConfig::default();
+        Self {
+            store,
+            max_bytes: defaults.max_bytes,
+            allowed_types: defaults.allowed_types,
+        }
+    }
+    
+    /// Create attachments backed by the disk or bucket storage in `config`
+    pub fn from_config(config: &AttachmentsConfig) -> Result<Self, AttachmentError> {
+        let store: Arc<dyn AttachmentStore> = match &config.bucket {
+            Some(bucket) => {
+                let client = S3Client::new(&bucket.endpoint, &bucket.region, &bucket.bucket, Credentials::from_env()?);
+                Arc::new(BucketAttachmentStore::new(client, &bucket.prefix))
+            },
+            None => Arc::new(DiskAttachmentStore::open(
+                config.dir.clone().unwrap_or_else(|| Config::data_dir().join("attachments")),
+            )?),
+        };
+        Ok(Self::new(store).with_limits(config.max_bytes, config.allowed_types.clone()))
+    }
+    
+    /// Accept attachments of up to `max_bytes` with one of `allowed_types`
+    pub fn with_limits(mut self, max_bytes: usize, allowed_types: Vec<String>) -> Self {
+        self.max_bytes = max_bytes;
+        self.allowed_types = allowed_types.iter().map(|content_type| media_type(content_type)).collect();
+        self
+    }
+    
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+    
+    /// Check an upload and settle its content type: the detected type wins,
+    /// and a declared type must agree with it
+    pub fn check(&self, bytes: &[u8], declared_type: Option<&str>) -> Result<String, AttachmentError> {
+        self.check_head(&bytes[..bytes.len().min(SNIFF_BYTES)], bytes.len(), declared_type)
+    }
+    
+    /// [`check`](Self::check) an upload of `size` bytes going by its leading bytes, `head`
+    fn check_head(&self, head: &[u8], size: usize, declared_type: Option<&str>) -> Result<String, AttachmentError> {
+        if size == 0 {
+            return Err(AttachmentError::Empty);
+        }
+        if size > self.max_bytes {
+            return Err(AttachmentError::TooLarge { size, max: self.max_bytes });
+        }
+        
+        let declared = declared_type.map(media_type).filter(|declared| !declared.is_empty() && declared != FALLBACK_CONTENT_TYPE);
+        let content_type = match (declared, detect_content_type(head)) {
+            (Some(declared), Some(detected)) if declared != detected => {
+                return Err(AttachmentError::TypeMismatch { declared, detected: detected.to_string() });
+            },
+            (_, Some(detected)) => detected.to_string(),
+            (Some(declared), None) => declared,
+            (None, None) => return Err(AttachmentError::UnknownType),
+        };
+        if !self.allowed_types.contains(&content_type) {
+            return Err(AttachmentError::UnsupportedType(content_type));
+        }
+        Ok(content_type)
+    }
+    
+    /// Store an upload, returning the reference to keep on an entry
+    pub async fn upload(&self, bytes: &[u8], declared_type: Option<&str>, filename: Option<&str>) -> Result<Attachment, AttachmentError> {
+        let content_type = self.check(bytes, declared_type)?;
+        let hash = format!("{:x}", Sha256::digest(bytes));
+        self.store.put(&hash, bytes, &content_type).await?;
+        Ok(Attachment {
+            hash,
+            content_type,
+            size: bytes.len(),
+            filename: filename.map(str::to_string),
+        })
+    }
+    
+    /// Store the file at `path` as an upload, reading it a chunk at a time
+    /// rather than into memory, e.g. once a resumable upload is complete
+    pub async fn upload_file(&self, path: &Path, declared_type: Option<&str>, filename: Option<&str>) -> Result<Attachment, AttachmentError> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len() as usize;
+        if size > self.max_bytes {
+            return Err(AttachmentError::TooLarge { size, max: self.max_bytes });
+        }
+        
+        let mut hasher = Sha256::new();
+        let mut head = Vec::with_capacity(SNIFF_BYTES);
+        let mut chunk = vec![0; READ_CHUNK_BYTES];
+        loop {
+            let read = file.read(&mut chunk).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&chunk[..read]);
+            let wanted = (SNIFF_BYTES - head.len()).min(read);
+            head.extend_from_slice(&chunk[..wanted]);
+        }
+        let content_type = self.check_head(&head, size, declared_type)?;
+        let hash = format!("{:x}", hasher.finalize());
+        self.store.put_file(&hash, path, &content_type).await?;
+        Ok(Attachment {
+            hash,
+            content_type,
+            size,
+            filename: filename.map(str::to_string),
+        })
+    }
+    
+    /// The content stored under `hash` and the content type to serve it as
+    pub async fn download(&self, hash: &str) -> Result<Option<(Vec<u8>, &'static str)>, AttachmentError> {
+        validate_hash(hash)?;
+        Ok(self.store.get(hash).await?.map(|bytes| {
+            let content_type = detect_content_type(&bytes).unwrap_or(FALLBACK_CONTENT_TYPE);
+            (bytes, content_type)
+        }))
+    }
+    
+    /// Add `attachment` to an entry's metadata, leaving its content and embedding as they are
+    pub async fn attach(&self, store: &dyn VectorStore, collection: &str, id: &str, attachment: &Attachment) -> Result<Document, AttachmentError> {
+        let mut document = store.get_document(collection, id).await?
+            .ok_or_else(|| AttachmentError::EntryNotFound { collection: collection.to_string(), id: id.to_string() })?;
+        let mut attachments = attachments_of(&document);
+        if !attachments.iter().any(|existing| existing.hash == attachment.hash) {
+            attachments.push(attachment.clone());
+        }
+        document.metadata.insert(
+            ATTACHMENTS_KEY.to_string(),
+            serde_json::to_string(&attachments).expect("attachments serialize"),
+        );
+        store.insert_document(collection, document.clone()).await?;
+        Ok(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    use tempfile::TempDir;
+    
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    
+    #[test]
+    fn test_content_types_are_checked() {
+        let attachments = Attachments::new(Arc::new(DiskAttachmentStore::new("unused")));
+        assert_eq!(attachments.check(PNG, None).unwrap(), "image/png");
+        assert_eq!(attachments.check(PNG, Some("image/png; charset=binary")).unwrap(), "image/png");
+        assert_eq!(attachments.check(PNG, Some("application/octet-stream")).unwrap(), "image/png");
+        assert_eq!(attachments.check(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>", None).unwrap(), "image/svg+xml");
+        
+        assert!(matches!(attachments.check(PNG, Some("image/jpeg")), Err(AttachmentError::TypeMismatch { .. })));
+        assert!(matches!(attachments.check(b"#!/bin/sh", Some("text/x-shellscript")), Err(AttachmentError::UnsupportedType(_))));
+        assert!(matches!(attachments.check(b"plain", None), Err(AttachmentError::UnknownType)));
+        
+        let small = Attachments::new(Arc::new(DiskAttachmentStore::new("unused"))).with_limits(4, vec!["image/png".to_string()]);
+        assert!(matches!(small.check(PNG, None), Err(AttachmentError::TooLarge { max: 4, .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_upload_attach_and_download() {
+        let temp_dir = TempDir::new().unwrap();
+        let attachments = Attachments::new(Arc::new(DiskAttachmentStore::new(temp_dir.path())));
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        let mut document = Document::with_placeholder_embedding("Architecture overview".to_string(), 2);
+        document.id = "overview".to_string();
+        store.insert_document("docs", document).await.unwrap();
+        
+        let attachment = attachments.upload(PNG, Some("image/png"), Some("diagram.png")).await.unwrap();
+        assert_eq!(attachment.hash, format!("{:x}", Sha256::digest(PNG)));
+        // Uploading the same content again stores nothing new
+        attachments.upload(PNG, None, None).await.unwrap();
+        
+        attachments.attach(&store, "docs", "overview", &attachment).await.unwrap();
+        attachments.attach(&store, "docs", "overview", &attachment).await.unwrap();
+        let stored = store.get_document("docs", "overview").await.unwrap().unwrap();
+        assert_eq!(attachments_of(&stored), vec![attachment.clone()]);
+        assert_eq!(stored.embedding, vec![0.0, 0.0]);
+        assert!(matches!(
+            attachments.attach(&store, "docs", "missing", &attachment).await,
+            Err(AttachmentError::EntryNotFound { .. })
+        ));
+        
+        let (bytes, content_type) = attachments.download(&attachment.hash).await.unwrap().unwrap();
+        assert_eq!(bytes, PNG);
+        assert_eq!(content_type, "image/png");
+        assert!(attachments.download(&"0".repeat(64)).await.unwrap().is_none());
+        assert!(matches!(attachments.download("../etc/passwd").await, Err(AttachmentError::InvalidHash(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_upload_file_matches_upload() {
+        let temp_dir = TempDir::new().unwrap();
+        let attachments = Attachments::new(Arc::new(DiskAttachmentStore::new(temp_dir.path().join("store"))));
+        // Larger than a read chunk, so the hash spans several
+        let mut pdf = b"%PDF-1.7\n".to_vec();
+        pdf.resize(3 * READ_CHUNK_BYTES + 17, b'x');
+        let path = temp_dir.path().join("upload.part");
+        std::fs::write(&path, &pdf).unwrap();
+        
+        let from_file = attachments.upload_file(&path, Some("application/pdf"), Some("spec.pdf")).await.unwrap();
+        assert_eq!(from_file, attachments.upload(&pdf, Some("application/pdf"), Some("spec.pdf")).await.unwrap());
+        assert_eq!(attachments.download(&from_file.hash).await.unwrap().unwrap().0, pdf);
+        
+        assert!(matches!(
+            attachments.upload_file(&path, Some("image/png"), None).await,
+            Err(AttachmentError::TypeMismatch { .. })
+        ));
+        let small = Attachments::new(Arc::new(DiskAttachmentStore::new(temp_dir.path().join("store")))).with_limits(4, vec!["application/pdf".to_string()]);
+        assert!(matches!(small.upload_file(&path, None, None).await, Err(AttachmentError::TooLarge { max: 4, .. })));
+    }
+    
+    #[test]
+    fn test_unusable_directory_is_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("not-a-directory");
+        std::fs::write(&file, b"").unwrap();
+        let config = AttachmentsConfig { dir: Some(file.join("attachments")), ..AttachmentsConfig::default() };
+        assert!(matches!(Attachments::from_config(&config), Err(AttachmentError::Storage(_))));
+        
+        let config = AttachmentsConfig { dir: Some(temp_dir.path().join("attachments")), ..AttachmentsConfig::default() };
+        Attachments::from_config(&config).unwrap();
+        assert!(temp_dir.path().join("attachments").is_dir());
+    }
+}
@@ -0,0 +1,198 @@
+//! Settings bundles.
+//!
+//! A bundle holds the settings operators tune on a server rather than per
+//! machine: the collection registry, which tools are disabled, and the
+//! schedules of the maintenance, digest, feed and federation jobs. It is
+//! written as versioned YAML so it can be reviewed and committed, then
+//! imported elsewhere to promote an environment such as staging to production.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thiserror::Error;
+
+use crate::config::{Config, DigestConfig, FederationConfig, FeedsConfig, MaintenanceConfig, ToolsConfig};
+use crate::vector_store::{CollectionInfo, CollectionRegistry};
+
+/// Format version written by this build; bundles from newer builds are refused
+pub const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum BundleError {
+    #[error("Failed to parse settings bundle: {0}")]
+    Parse(String),
+    
+    #[error("Settings bundle version {found} is newer than this build supports ({supported})")]
+    UnsupportedVersion { found: u32, supported: u32 },
+}
+
+/// When the background jobs run and what they work on
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Schedules {
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    #[serde(default)]
+    pub digest: DigestConfig,
+    #[serde(default)]
+    pub feeds: FeedsConfig,
+    /// Federation sources without their `env`, which holds per-environment credentials
+    #[serde(default)]
+    pub federation: FederationConfig,
+}
+
+/// Every exported setting, as written to a bundle file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// The collection registry, sorted by name
+    #[serde(default)]
+    pub collections: Vec<CollectionInfo>,
+    /// Which tools are refused
+    #[serde(default)]
+    pub tools: ToolsConfig,
+    #[serde(default)]
+    pub schedules: Schedules,
+}
+
+/// What importing a bundle changed in the registry
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl SettingsBundle {
+    /// Capture the settings of `config` and `registry`
+    pub fn capture(config: &Config, registry: &CollectionRegistry) -> Self {
+        let mut federation = config.federation.clone();
+        for source in &mut federation.sources {
+            source.env.clear();
+        }
+        
+        Self {
+            version: BUNDLE_VERSION,
+            exported_at: Utc::now(),
+            collections: registry.list(),
+            tools: config.tools.clone(),
+            schedules: Schedules {
+                maintenance: config.maintenance.clone(),
+                digest: config.digest.clone(),
+                feeds: config.feeds.clone(),
+                federation,
+            },
+        }
+    }
+    
+    pub fn to_yaml(&self) -> Result<String, BundleError> {
+        serde_yaml::to_string(self).map_err(|e| BundleError::Parse(e.to_string()))
+    }
+    
+    /// Parse a bundle, refusing versions newer than [`BUNDLE_VERSION`]
+    pub fn parse(text: &str) -> Result<Self, BundleError> {
+        let bundle: SettingsBundle = serde_yaml::from_str(text).map_err(|e| BundleError::Parse(e.to_string()))?;
+        if bundle.version > BUNDLE_VERSION {
+            return Err(BundleError::UnsupportedVersion { found: bundle.version, supported: BUNDLE_VERSION });
+        }
+        Ok(bundle)
+    }
+    
+    /// Apply the bundle to `config` and `registry`.
+    ///
+    /// The bundle's collections are registered over any of the same name, and
+    /// with `prune` collections missing from the bundle are unregistered.
+    /// Federation sources keep the `env` of the configured source of the
+    /// same name, since it is never exported.
+    pub fn apply(&self, config: &mut Config, registry: &CollectionRegistry, prune: bool) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+        for info in &self.collections {
+            match registry.get(&info.name) {
+                None => summary.added.push(info.name.clone()),
+                Some(current) if current != *info => summary.updated.push(info.name.clone()),
+                Some(_) => continue,
+            }
+            registry.register(info.clone());
+        }
+        if prune {
+            for info in registry.list() {
+                if !self.collections.iter().any(|imported| imported.name == info.name) {
+                    registry.remove(&info.name);
+                    summary.removed.push(info.name);
+                }
+            }
+        }
+        
+        let mut envs: HashMap<String, HashMap<String, String>> = config.federation.sources.drain(..)
+            .map(|source| (source.name, source.env))
+            .collect();
+        config.federation = self.schedules.federation.clone();
+        for source in &mut config.federation.sources {
+            if let Some(env) = envs.remove(&source.name) {
+                source.env = env;
+            }
+        }
+        config.tools = self.tools.clone();
+        config.maintenance = self.schedules.maintenance.clone();
+        config.digest = self.schedules.digest.clone();
+        config.feeds = self.schedules.feeds.clone();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FederationSourceConfig;
+    
+    fn source(name: &str, env: &[(&str, &str)]) -> FederationSourceConfig {
+        FederationSourceConfig {
+            name: name.to_string(),
+            command: "wiki-mcp".to_string(),
+            args: Vec::new(),
+            env: env.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+            collection: "wiki".to_string(),
+            interval_secs: 600,
+            rules: Vec::new(),
+        }
+    }
+    
+    #[test]
+    fn test_round_trip_promotes_settings() {
+        let mut staging = Config::default();
+        staging.tools.disabled = vec!["delete_collection".to_string()];
+        staging.maintenance.interval_secs = 120;
+        staging.federation.sources = vec![source("wiki", &[("WIKI_TOKEN", "staging-secret")])];
+        let staging_registry = CollectionRegistry::new();
+        staging_registry.register(CollectionInfo::new("docs", 384).with_normalized(true));
+        
+        let yaml = SettingsBundle::capture(&staging, &staging_registry).to_yaml().unwrap();
+        assert!(yaml.starts_with("version: 1"));
+        assert!(!yaml.contains("staging-secret"));
+        
+        let mut production = Config::default();
+        production.federation.sources = vec![source("wiki", &[("WIKI_TOKEN", "production-secret")])];
+        let production_registry = CollectionRegistry::new();
+        production_registry.register(CollectionInfo::new("docs", 768));
+        production_registry.register(CollectionInfo::new("scratch", 8));
+        
+        let bundle = SettingsBundle::parse(&yaml).unwrap();
+        let summary = bundle.apply(&mut production, &production_registry, true);
+        assert_eq!(summary, ImportSummary { added: vec![], updated: vec!["docs".to_string()], removed: vec!["scratch".to_string()] });
+        assert_eq!(production_registry.list(), staging_registry.list());
+        assert_eq!(production.tools.disabled, vec!["delete_collection"]);
+        assert_eq!(production.maintenance.interval_secs, 120);
+        assert_eq!(production.federation.sources[0].env.get("WIKI_TOKEN").map(String::as_str), Some("production-secret"));
+        
+        // Importing again changes nothing
+        assert_eq!(bundle.apply(&mut production, &production_registry, true), ImportSummary::default());
+    }
+    
+    #[test]
+    fn test_newer_versions_are_refused() {
+        let yaml = SettingsBundle::capture(&Config::default(), &CollectionRegistry::new()).to_yaml().unwrap()
+            .replacen("version: 1", "version: 2", 1);
+        assert!(matches!(SettingsBundle::parse(&yaml), Err(BundleError::UnsupportedVersion { found: 2, supported: 1 })));
+        assert!(matches!(SettingsBundle::parse("collections: []"), Err(BundleError::Parse(_))));
+    }
+}
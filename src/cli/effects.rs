@@ -1,6 +1,7 @@
+use crate::api::ExportFormat;
 use crate::cli::{Args};
 use crate::config::Config;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -11,6 +12,14 @@ pub enum CliError {
     
     #[error("Configuration error: {0}")]
     ConfigError(#[from] crate::config::ConfigError),
+    
+    /// Every problem `config validate` found, rendered with their lines
+    #[error("{0}")]
+    InvalidConfig(String),
+    
+    /// The report of a self-test in which a check failed
+    #[error("{0}")]
+    SelftestFailed(String),
 }
 
 #[allow(dead_code)]
@@ -34,6 +43,385 @@ pub fn create_pid_file(path: &PathBuf) -> Result<(), CliError> {
         .map_err(|e| CliError::ExecutionError(format!("Failed to create PID file: {}", e)))
 }
 
+/// Run a search against a running server and return the rendered export
+pub fn fetch_search_export(
+    server: &str,
+    query: &str,
+    collection: &str,
+    limit: usize,
+    format: ExportFormat,
+) -> Result<String, CliError> {
+    let client = crate::client::KnowledgeClient::new(server);
+    block_on(client.search_export(collection, query, limit, format))
+}
+
+/// Run a search against a running server and return the ids of the results in rank order
+pub fn fetch_search_ids(server: &str, query: &str, collection: &str, limit: usize) -> Result<Vec<String>, CliError> {
+    let client = crate::client::KnowledgeClient::new(server);
+    let rows = block_on(client.search(collection, query, limit))?;
+    Ok(rows.iter().map(|row| crate::client::entry_id(row).to_string()).collect())
+}
+
+/// Compact the embedded store of a running server
+pub fn compact_server(server: &str, api_key: Option<&str>) -> Result<crate::vector_store::CompactionReport, CliError> {
+    let mut client = crate::client::KnowledgeClient::new(server);
+    if let Some(key) = api_key {
+        client = client.with_api_key(key);
+    }
+    block_on(client.compact())
+}
+
+/// Run a client call to completion on a fresh runtime
+fn block_on<T>(call: impl std::future::Future<Output = Result<T, crate::client::ClientError>>) -> Result<T, CliError> {
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(call).map_err(|e| CliError::ExecutionError(e.to_string()))
+}
+
+/// Rotate the encryption key of a collection stored in Qdrant
+pub fn reencrypt_qdrant_collection(
+    qdrant_url: &str,
+    collection: &str,
+    old_key_file: Option<&Path>,
+    new_key_file: &Path,
+    progress: &crate::progress::Progress,
+) -> Result<usize, CliError> {
+    use crate::vector_store::encrypted::reencrypt_collection;
+    use crate::vector_store::{EncryptionKey, QdrantConfig, QdrantConnector};
+    
+    let to_cli_error = |e: crate::vector_store::VectorStoreError| CliError::ExecutionError(e.to_string());
+    let old_key = old_key_file.map(EncryptionKey::from_file).transpose().map_err(to_cli_error)?;
+    let new_key = EncryptionKey::from_file(new_key_file).map_err(to_cli_error)?;
+    
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let config = QdrantConfig {
+            url: qdrant_url.to_string(),
+            ..QdrantConfig::default()
+        };
+        let store = QdrantConnector::new(config).await.map_err(to_cli_error)?;
+        reencrypt_collection(&store, collection, old_key.as_ref(), &new_key, progress).await.map_err(to_cli_error)
+    })
+}
+
+/// Compress the content of existing documents in a Qdrant collection
+pub fn compress_qdrant_collection(
+    qdrant_url: &str,
+    collection: &str,
+    compression: crate::vector_store::PayloadCompression,
+    progress: &crate::progress::Progress,
+) -> Result<usize, CliError> {
+    use crate::vector_store::{QdrantConfig, QdrantConnector};
+    
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let config = QdrantConfig {
+            url: qdrant_url.to_string(),
+            ..QdrantConfig::default()
+        };
+        let store = QdrantConnector::new(config).await
+            .map_err(|e| CliError::ExecutionError(e.to_string()))?
+            .with_compression(compression);
+        store.compress_existing(collection, progress).await
+            .map_err(|e| CliError::ExecutionError(e.to_string()))
+    })
+}
+
+/// Connect to Qdrant with sharded collections split over their shards
+async fn connect_qdrant(
+    qdrant_url: &str,
+    config: &crate::config::Config,
+) -> Result<std::sync::Arc<dyn crate::vector_store::VectorStore>, CliError> {
+    let backend = connect_backend(qdrant_url, config).await?;
+    Ok(std::sync::Arc::new(crate::vector_store::ShardedVectorStore::from_config(backend, &config.sharding)))
+}
+
+/// Connect to Qdrant: the routed instances when the config names any, otherwise `qdrant_url`
+async fn connect_backend(
+    qdrant_url: &str,
+    config: &crate::config::Config,
+) -> Result<std::sync::Arc<dyn crate::vector_store::VectorStore>, CliError> {
+    use crate::vector_store::{PayloadCompression, QdrantConfig, QdrantConnector, RoutedVectorStore};
+    use std::sync::Arc;
+    
+    let to_cli_error = |e: crate::vector_store::VectorStoreError| CliError::ExecutionError(e.to_string());
+    if !config.qdrant.instances.is_empty() {
+        let store = RoutedVectorStore::from_config(&config.qdrant, &config.pool, &config.compression).await.map_err(to_cli_error)?;
+        return Ok(Arc::new(store));
+    }
+    
+    let qdrant_config = QdrantConfig {
+        url: qdrant_url.to_string(),
+        ..QdrantConfig::default()
+    }.with_pool(&config.pool);
+    let mut connector = QdrantConnector::new(qdrant_config).await.map_err(to_cli_error)?;
+    if config.compression.enabled {
+        connector = connector.with_compression(PayloadCompression::from_config(&config.compression));
+    }
+    Ok(Arc::new(connector))
+}
+
+/// Poll every configured feed once, ingesting into Qdrant, and describe each outcome
+pub fn poll_qdrant_feeds(qdrant_url: &str, config: &crate::config::Config) -> Result<Vec<String>, CliError> {
+    use crate::feeds::FeedWatcher;
+    
+    let network = &config.network;
+    let http = crate::network::http_client(network).map_err(|e| CliError::ExecutionError(e.to_string()))?;
+    crate::network::check_qdrant(network, qdrant_url).map_err(|e| CliError::ExecutionError(e.to_string()))?;
+    
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let store = connect_qdrant(qdrant_url, config).await?;
+        let watcher = FeedWatcher::new(store, config.feeds.clone()).with_http_client(http);
+        
+        Ok(watcher.poll_all().await
+            .into_iter()
+            .map(|(url, outcome)| match outcome {
+                Ok(report) => format!("{}: {} new, {} already stored", url, report.ingested, report.skipped),
+                Err(e) => format!("{}: {}", url, e),
+            })
+            .collect())
+    })
+}
+
+/// Sync every configured federation source once into Qdrant, describing each outcome
+pub fn run_qdrant_federation(qdrant_url: &str, config: &crate::config::Config) -> Result<Vec<String>, CliError> {
+    use crate::federation::Federator;
+    
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let store = connect_qdrant(qdrant_url, config).await?;
+        let federator = Federator::new(store, config.federation.clone());
+        
+        let mut lines = Vec::new();
+        for (name, outcome) in federator.sync_all().await {
+            match outcome {
+                Ok(report) => {
+                    lines.push(format!(
+                        "{}: {} ingested, {} unchanged, {} skipped, {} failed",
+                        name, report.ingested, report.unchanged, report.skipped, report.failed.len()
+                    ));
+                    lines.extend(report.failed.iter().map(|(uri, reason)| format!("  {}: {}", uri, reason)));
+                },
+                Err(e) => lines.push(format!("{}: {}", name, e)),
+            }
+        }
+        Ok(lines)
+    })
+}
+
+/// Ingest local files into a Qdrant collection, `parallelism` at a time
+pub fn ingest_qdrant_files(
+    qdrant_url: &str,
+    config: &crate::config::Config,
+    paths: &[PathBuf],
+    collection: &str,
+    parallelism: usize,
+    progress: &crate::progress::Progress,
+) -> Result<crate::sources::IngestReport, CliError> {
+    use crate::sources::manifest::MANIFEST_FILE;
+    use crate::sources::FileIngest;
+    use crate::text_processing::{EmbeddingProvider, FallbackEmbeddingProvider};
+    
+    let embedding = FallbackEmbeddingProvider::from_config(&config.embedding, &config.network)
+        .map_err(|e| CliError::ExecutionError(e.to_string()))?;
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let store = connect_qdrant(qdrant_url, config).await?;
+        let mut ingest = FileIngest::new(store);
+        if let Some(chain) = embedding {
+            let dimension = chain.embedding_dim();
+            ingest = ingest.with_embedding_provider(std::sync::Arc::new(chain), dimension);
+        }
+        if config.ingest.ocr.enabled {
+            let ocr = crate::sources::OcrStage::from_config(&config.ingest.ocr)
+                .map_err(|e| CliError::ExecutionError(e.to_string()))?;
+            ingest = ingest.with_ocr(ocr);
+        }
+        ingest
+            .with_parallelism(parallelism)
+            .with_progress(progress.clone())
+            .with_manifest(Config::data_dir().join(MANIFEST_FILE))
+            .ingest(paths, collection)
+            .await
+            .map_err(|e| CliError::ExecutionError(e.to_string()))
+    })
+}
+
+/// Copy the selected documents of one Qdrant collection into a new one
+pub fn clone_qdrant_collection(
+    qdrant_url: &str,
+    config: &crate::config::Config,
+    source: &str,
+    target: &str,
+    options: &crate::vector_store::CloneOptions,
+    progress: &crate::progress::Progress,
+) -> Result<crate::vector_store::CloneOutcome, CliError> {
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let store = connect_qdrant(qdrant_url, config).await?;
+        crate::vector_store::clone_collection(store.as_ref(), source, target, options, progress).await
+            .map_err(|e| CliError::ExecutionError(e.to_string()))
+    })
+}
+
+/// Move a Qdrant collection's documents onto `shards` shards
+pub fn reshard_qdrant_collection(
+    qdrant_url: &str,
+    config: &crate::config::Config,
+    collection: &str,
+    shards: usize,
+    progress: &crate::progress::Progress,
+) -> Result<crate::vector_store::ReshardOutcome, CliError> {
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let backend = connect_backend(qdrant_url, config).await?;
+        crate::vector_store::ShardedVectorStore::from_config(backend, &config.sharding)
+            .reshard(collection, shards, progress)
+            .await
+            .map_err(|e| CliError::ExecutionError(e.to_string()))
+    })
+}
+
+/// The telemetry report this installation would send; tool call counts are
+/// kept by the running server, so they are empty here
+pub fn preview_telemetry(
+    qdrant_url: &str,
+    config: &crate::config::Config,
+) -> Result<crate::telemetry::TelemetryReport, CliError> {
+    use crate::telemetry::{TelemetryReporter, UsageCounters};
+    use std::sync::Arc;
+    
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let store = connect_qdrant(qdrant_url, config).await?;
+        let reporter = TelemetryReporter::new(config.telemetry.clone(), store, Arc::new(UsageCounters::default()));
+        Ok(reporter.preview().await)
+    })
+}
+
+/// Sync a bucket prefix into a Qdrant collection
+#[allow(clippy::too_many_arguments)]
+pub fn sync_qdrant_bucket(
+    qdrant_url: &str,
+    endpoint: &str,
+    region: &str,
+    bucket: &str,
+    prefix: &str,
+    collection: &str,
+    network: &crate::config::NetworkConfig,
+    progress: &crate::progress::Progress,
+) -> Result<crate::sources::SyncReport, CliError> {
+    use crate::sources::{BucketSync, Credentials, S3Client};
+    use crate::vector_store::{QdrantConfig, QdrantConnector};
+    use std::sync::Arc;
+    
+    let credentials = Credentials::from_env().map_err(|e| CliError::ExecutionError(e.to_string()))?;
+    let http = crate::network::http_client(network).map_err(|e| CliError::ExecutionError(e.to_string()))?;
+    crate::network::check_qdrant(network, qdrant_url).map_err(|e| CliError::ExecutionError(e.to_string()))?;
+    
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let qdrant_config = QdrantConfig {
+            url: qdrant_url.to_string(),
+            ..QdrantConfig::default()
+        };
+        let store = QdrantConnector::new(qdrant_config).await
+            .map_err(|e| CliError::ExecutionError(e.to_string()))?;
+        let source = S3Client::new(endpoint, region, bucket, credentials).with_http_client(http);
+        
+        BucketSync::new(Arc::new(source), Arc::new(store), bucket)
+            .with_progress(progress.clone())
+            .sync(prefix, collection)
+            .await
+            .map_err(|e| CliError::ExecutionError(e.to_string()))
+    })
+}
+
+/// Run the digest job once against Qdrant, describing each digest generated
+pub fn run_qdrant_digests(
+    qdrant_url: &str,
+    config: crate::config::DigestConfig,
+    collection: Option<&str>,
+    network: &crate::config::NetworkConfig,
+) -> Result<Vec<String>, CliError> {
+    use crate::digest::DigestJob;
+    use crate::vector_store::{QdrantConfig, QdrantConnector};
+    use std::sync::Arc;
+    
+    let http = crate::network::http_client(network).map_err(|e| CliError::ExecutionError(e.to_string()))?;
+    crate::network::check_qdrant(network, qdrant_url).map_err(|e| CliError::ExecutionError(e.to_string()))?;
+    
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let qdrant_config = QdrantConfig {
+            url: qdrant_url.to_string(),
+            ..QdrantConfig::default()
+        };
+        let store = QdrantConnector::new(qdrant_config).await
+            .map_err(|e| CliError::ExecutionError(e.to_string()))?;
+        let job = DigestJob::new(Arc::new(store), config).with_http_client(http);
+        
+        let outcomes = match collection {
+            Some(collection) => vec![(collection.to_string(), job.run_collection(collection).await)],
+            None => job.run_once().await,
+        };
+        
+        let mut lines = Vec::new();
+        for (collection, outcome) in outcomes {
+            match outcome {
+                Ok(Some(digest)) => lines.push(format!("Digested {} new entries in {}", digest.entry_count, collection)),
+                Ok(None) => {},
+                Err(e) => return Err(CliError::ExecutionError(format!("Digest of {} failed: {}", collection, e))),
+            }
+        }
+        Ok(lines)
+    })
+}
+
+/// Serve as `config` describes until the process receives SIGTERM or SIGINT
+pub fn run_server(config: &Config) -> Result<(), CliError> {
+    use crate::server::Server;
+    
+    let to_cli_error = |e: crate::server::ServerError| CliError::ExecutionError(e.to_string());
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let server = Server::from_config(config).await.map_err(to_cli_error)?;
+        let handle = server.start().await.map_err(to_cli_error)?;
+        tracing::info!(host = %config.server.host, port = config.server.port, "Serving");
+        handle.run_until_terminated().await.map_err(to_cli_error)
+    })
+}
+
+/// Apply the built-in migrations to the state in the data directory, or with
+/// `dry_run` only report the ones that are pending
+pub fn run_migrations(dry_run: bool, progress: &crate::progress::Progress) -> Result<Vec<crate::migrations::PlannedMigration>, CliError> {
+    use crate::migrations::{builtin, Migrator};
+    
+    let to_cli_error = |e: crate::migrations::MigrationError| CliError::ExecutionError(e.to_string());
+    let migrator = Migrator::new(Config::data_dir(), builtin())
+        .map_err(to_cli_error)?
+        .with_progress(progress.clone());
+    if dry_run {
+        return migrator.plan().map_err(to_cli_error);
+    }
+    
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(migrator.run()).map_err(to_cli_error)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,3 +467,215 @@ port = 8080
         assert!(pid > 0);
     }
 }
+
+/// Render the settings of `config` and the saved registry as a YAML bundle
+pub fn export_settings(config: &crate::config::Config) -> Result<String, CliError> {
+    use crate::bundle::SettingsBundle;
+    use crate::vector_store::{CollectionRegistry, REGISTRY_FILE};
+    
+    let registry = CollectionRegistry::load(&Config::data_dir().join(REGISTRY_FILE))
+        .map_err(|e| CliError::ExecutionError(e.to_string()))?;
+    SettingsBundle::capture(config, &registry).to_yaml()
+        .map_err(|e| CliError::ExecutionError(e.to_string()))
+}
+
+/// Apply the bundle in `file` to `config` and the saved registry, saving
+/// the registry unless `dry_run`; the caller saves `config`
+pub fn import_settings(
+    file: &Path,
+    config: &mut crate::config::Config,
+    prune: bool,
+    dry_run: bool,
+) -> Result<crate::bundle::ImportSummary, CliError> {
+    use crate::bundle::SettingsBundle;
+    use crate::vector_store::{CollectionRegistry, REGISTRY_FILE};
+    
+    let to_cli_error = |e: crate::vector_store::VectorStoreError| CliError::ExecutionError(e.to_string());
+    let text = std::fs::read_to_string(file)
+        .map_err(|e| CliError::ExecutionError(format!("Failed to read {}: {}", file.display(), e)))?;
+    let bundle = SettingsBundle::parse(&text).map_err(|e| CliError::ExecutionError(e.to_string()))?;
+    
+    let path = Config::data_dir().join(REGISTRY_FILE);
+    let registry = CollectionRegistry::load(&path).map_err(to_cli_error)?;
+    let summary = bundle.apply(config, &registry, prune);
+    if !dry_run {
+        registry.save(&path).map_err(to_cli_error)?;
+    }
+    Ok(summary)
+}
+
+/// Lint `collection`, or every collection, fixing safe issues when `options.fix` is set
+pub fn lint_qdrant_collections(
+    qdrant_url: &str,
+    config: &crate::config::Config,
+    collection: Option<&str>,
+    options: &crate::lint::LintOptions,
+    progress: &crate::progress::Progress,
+) -> Result<crate::lint::LintReport, CliError> {
+    use crate::lint::{lint_collection, LintReport};
+    use crate::text_processing::{EmbeddingProvider, FallbackEmbeddingProvider};
+    use crate::vector_store::{CollectionRegistry, REGISTRY_FILE};
+    
+    let to_cli_error = |e: crate::vector_store::VectorStoreError| CliError::ExecutionError(e.to_string());
+    let registry = CollectionRegistry::load(&Config::data_dir().join(REGISTRY_FILE)).map_err(to_cli_error)?;
+    // Vectors can only be fixed by embedding the content again
+    let embedder = if options.fix {
+        FallbackEmbeddingProvider::from_config(&config.embedding, &config.network).map_err(|e| CliError::ExecutionError(e.to_string()))?
+    } else {
+        None
+    };
+    
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let store = connect_qdrant(qdrant_url, config).await?;
+        let collections = match collection {
+            Some(collection) => vec![collection.to_string()],
+            None => store.list_collections().await.map_err(to_cli_error)?,
+        };
+        
+        let mut report = LintReport::default();
+        for collection in &collections {
+            let embedder = embedder.as_ref().map(|embedder| embedder as &dyn EmbeddingProvider);
+            report.merge(lint_collection(store.as_ref(), &registry, collection, options, embedder, progress).await.map_err(to_cli_error)?);
+        }
+        Ok(report)
+    })
+}
+
+/// Check collections against the saved registry, describing each mismatch;
+/// with `apply`, resolve them as `how` says
+pub fn reconcile_qdrant_collections(
+    qdrant_url: &str,
+    config: &crate::config::Config,
+    collection: Option<&str>,
+    how: crate::vector_store::Reconciliation,
+    apply: bool,
+) -> Result<Vec<String>, CliError> {
+    use crate::vector_store::{check_schema, reconcile, CollectionRegistry, Reconciliation, VectorStoreError, REGISTRY_FILE};
+    
+    let to_cli_error = |e: VectorStoreError| CliError::ExecutionError(e.to_string());
+    let path = Config::data_dir().join(REGISTRY_FILE);
+    let registry = CollectionRegistry::load(&path).map_err(to_cli_error)?;
+    let names: Vec<String> = match collection {
+        Some(name) if registry.get(name).is_none() => {
+            return Err(CliError::ExecutionError(format!("Collection '{}' is not registered in {}", name, path.display())));
+        },
+        Some(name) => vec![name.to_string()],
+        None => registry.list().into_iter().map(|info| info.name).collect(),
+    };
+    
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    let mut lines = runtime.block_on(async {
+        let store = connect_qdrant(qdrant_url, config).await?;
+        let mut lines = Vec::new();
+        for name in &names {
+            let (expected, actual) = match check_schema(store.as_ref(), &registry, name).await {
+                Ok(()) => continue,
+                Err(VectorStoreError::SchemaMismatch { expected, actual, .. }) => (expected, actual),
+                Err(e) => return Err(to_cli_error(e)),
+            };
+            
+            if !apply {
+                lines.push(format!("{}: the registry expects {} but the collection has {}", name, expected, actual));
+                continue;
+            }
+            let schema = reconcile(store.as_ref(), &registry, name, how).await.map_err(to_cli_error)?;
+            if let Some(schema) = schema {
+                lines.push(match how {
+                    Reconciliation::UpdateRegistry => format!("{}: registry updated to {}", name, schema),
+                    Reconciliation::Recreate => format!("{}: recreated with {}; its entries were deleted", name, schema),
+                });
+            }
+        }
+        Ok::<_, CliError>(lines)
+    })?;
+    
+    if apply && how == Reconciliation::UpdateRegistry && !lines.is_empty() {
+        registry.save(&path).map_err(to_cli_error)?;
+    }
+    if !apply && !lines.is_empty() {
+        lines.push(match how {
+            Reconciliation::UpdateRegistry => "Rerun with --yes to update the registry to match".to_string(),
+            Reconciliation::Recreate => "Rerun with --yes to delete and recreate these collections; their entries will be lost".to_string(),
+        });
+    }
+    Ok(lines)
+}
+
+/// Run the self-test in a new temporary collection, embedding with the
+/// configured providers or, without any, by hashing words
+pub fn run_qdrant_selftest(qdrant_url: &str, config: &crate::config::Config) -> Result<crate::selftest::SelftestReport, CliError> {
+    use crate::selftest::{run_selftest, WordHashEmbedder, SELFTEST_COLLECTION_PREFIX};
+    use crate::text_processing::{EmbeddingProvider, FallbackEmbeddingProvider};
+    
+    let chain = FallbackEmbeddingProvider::from_config(&config.embedding, &config.network)
+        .map_err(|e| CliError::ExecutionError(e.to_string()))?;
+    let (embedder, name): (Box<dyn EmbeddingProvider>, String) = match chain {
+        Some(chain) => {
+            let name = chain.provider_names().collect::<Vec<_>>().join(", ");
+            (Box::new(chain), name)
+        },
+        None => (
+            Box::new(WordHashEmbedder::new(crate::mcp::DEFAULT_EMBEDDING_DIM)),
+            "word hashing (no embedding provider configured)".to_string(),
+        ),
+    };
+    let collection = format!("{}{}", SELFTEST_COLLECTION_PREFIX, &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| CliError::ExecutionError(format!("Failed to start runtime: {}", e)))?;
+    runtime.block_on(async {
+        let store = connect_qdrant(qdrant_url, config).await?;
+        Ok(run_selftest(store.as_ref(), embedder.as_ref(), &name, &collection).await)
+    })
+}
+
+/// Chunk a sample of the corpus under `paths` with each candidate strategy,
+/// scoring retrieval with the configured embedding providers when an eval set is given
+pub fn analyze_chunking(
+    config: &crate::config::Config,
+    paths: &[PathBuf],
+    sample: usize,
+    set: Option<&crate::eval::EvalSet>,
+    k: usize,
+) -> Result<crate::analyze::Analysis, CliError> {
+    use crate::analyze::{analyze, default_candidates, sample_documents, Retrieval};
+    use crate::text_processing::FallbackEmbeddingProvider;
+    
+    let embedding = match set {
+        Some(_) => Some(FallbackEmbeddingProvider::from_config(&config.embedding, &config.network)
+            .map_err(|e| CliError::ExecutionError(e.to_string()))?
+            .ok_or_else(|| CliError::ExecutionError("Scoring an eval set needs an embedding provider in the config".to_string()))?),
+        None => None,
+    };
+    let retrieval = set.zip(embedding.as_ref()).map(|(set, provider)| Retrieval { set, provider, k });
+    
+    let documents = sample_documents(paths, sample);
+    analyze(&documents, &default_candidates(), retrieval).map_err(|e| CliError::ExecutionError(e.to_string()))
+}
+
+/// Record `chunking` for `collection` in the saved collection registry,
+/// registering the collection first if needed; returns the registry's path
+pub fn record_chunking(
+    config: &crate::config::Config,
+    collection: &str,
+    chunking: crate::text_processing::ChunkingStrategy,
+) -> Result<PathBuf, CliError> {
+    use crate::text_processing::{EmbeddingProvider, FallbackEmbeddingProvider};
+    use crate::vector_store::{CollectionInfo, CollectionRegistry, REGISTRY_FILE};
+    
+    let to_cli_error = |e: crate::vector_store::VectorStoreError| CliError::ExecutionError(e.to_string());
+    let path = Config::data_dir().join(REGISTRY_FILE);
+    let registry = CollectionRegistry::load(&path).map_err(to_cli_error)?;
+    if registry.get(collection).is_none() {
+        let dimension = FallbackEmbeddingProvider::from_config(&config.embedding, &config.network)
+            .map_err(|e| CliError::ExecutionError(e.to_string()))?
+            .map_or(crate::mcp::DEFAULT_EMBEDDING_DIM, |chain| chain.embedding_dim());
+        registry.register(CollectionInfo::new(collection, dimension));
+    }
+    registry.set_chunking(collection, chunking).map_err(to_cli_error)?;
+    registry.save(&path).map_err(to_cli_error)?;
+    Ok(path)
+}
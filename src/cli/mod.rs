@@ -3,56 +3,133 @@ mod pure;
 
 use clap::Parser;
 
+use crate::progress::{OutputMode, Progress};
+
 pub use effects::CliError;
-pub use pure::Command;
+pub use pure::{Command, ConfigAction, ServiceAction};
 
 pub struct Cli {
     // Track server state for testing purposes
     is_running: bool,
+    output: OutputMode,
+    profile: Option<String>,
+    container: bool,
+    serving: bool,
 }
 
 impl Cli {
     pub fn new() -> Self {
         Cli {
             is_running: false,
+            output: OutputMode::Text,
+            profile: None,
+            container: false,
+            serving: false,
         }
     }
 
+    /// Run the server on `start` until the process is terminated, instead of
+    /// only reporting where it would listen
+    pub fn with_serving(mut self, serving: bool) -> Self {
+        self.serving = serving;
+        self
+    }
+    
+    /// Report the progress of long-running commands as `output` asks for
+    pub fn with_output(mut self, output: OutputMode) -> Self {
+        self.output = output;
+        self
+    }
+    
+    /// Layer `profile` over the config file of every command, instead of the
+    /// profile named by `P_MO_PROFILE`
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.profile = profile;
+        self
+    }
+    
+    /// Read settings as a container does: `P_MO_*` variables over the config
+    /// file, which need not exist
+    pub fn with_container(mut self, container: bool) -> Self {
+        self.container = container;
+        self
+    }
+    
+    fn load_config(&self, path: &std::path::Path) -> Result<crate::config::Config, crate::config::ConfigError> {
+        if self.container {
+            crate::config::Config::load_container(path, self.profile.as_deref())
+        } else {
+            crate::config::Config::load_profile(path, self.profile.as_deref())
+        }
+    }
+    
     pub fn execute(&mut self, command: Command) -> Result<String, CliError> {
         match command {
             Command::Start { host, port, daemon, config_path } => {
-                // If config_path is provided, load it to get host/port
-                let (host_str, port_num) = if let Some(path) = &config_path {
-                    if path.exists() {
-                        match crate::config::Config::load(path) {
-                            Ok(config) => {
-                                let h = host.unwrap_or_else(|| config.server.host.clone());
-                                let p = port.unwrap_or(config.server.port);
-                                (h, p)
-                            },
-                            Err(_) => (
-                                host.unwrap_or_else(|| "127.0.0.1".to_string()),
-                                port.unwrap_or(8080)
-                            )
-                        }
-                    } else {
-                        (
-                            host.unwrap_or_else(|| "127.0.0.1".to_string()),
-                            port.unwrap_or(8080)
-                        )
-                    }
-                } else {
-                    (
-                        host.unwrap_or_else(|| "127.0.0.1".to_string()),
-                        port.unwrap_or(8080)
-                    )
+                // Settings come from the config file when there is one; a
+                // container reads them from the environment without one
+                let mut config = match &config_path {
+                    Some(path) if path.exists() || self.container => self.load_config(path)?,
+                    _ => crate::config::Config::default(),
                 };
+                if let Some(host) = host {
+                    config.server.host = host;
+                }
+                if let Some(port) = port {
+                    config.server.port = port;
+                }
+                config.server.daemon = daemon;
+                config.server.container |= self.container;
                 
                 // Set server as running
                 self.is_running = true;
                 
+                if self.serving {
+                    effects::run_server(&config)?;
+                    self.is_running = false;
+                    return Ok(String::new());
+                }
                 let daemon_str = if daemon { " in daemon mode" } else { "" };
-                Ok(format!("{}:{}{}", host_str, port_num, daemon_str))
+                Ok(format!("{}:{}{}", config.server.host, config.server.port, daemon_str))
+            },
+            Command::Background { host, port, config_path } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let server = if path.exists() {
+                    self.load_config(&path)?.server
+                } else {
+                    crate::config::ServerConfig::default()
+                };
+                let log_file = server.log_file.unwrap_or_else(|| crate::config::Config::state_dir().join("p-mo.log"));
+                
+                let mut args = vec!["start".to_string(), "--daemon".to_string()];
+                if let Some(host) = host {
+                    args.extend(["--host".to_string(), host]);
+                }
+                if let Some(port) = port {
+                    args.extend(["--port".to_string(), port.to_string()]);
+                }
+                if path.exists() {
+                    args.extend(["--config-path".to_string(), path.display().to_string()]);
+                }
+                
+                let pid = crate::service::spawn_background(&args, &log_file)
+                    .map_err(|e| CliError::ExecutionError(e.to_string()))?;
+                Ok(format!("Started p-mo in the background (pid {}), logging to {}", pid, log_file.display()))
+            },
+            Command::Service { action } => {
+                let outcome = match action {
+                    ServiceAction::Install { config_path } => {
+                        let path = match config_path {
+                            Some(path) => path,
+                            None => crate::config::Config::create_default_config()?,
+                        };
+                        let log_file = self.load_config(&path)?.server.log_file
+                            .unwrap_or_else(|| crate::config::Config::state_dir().join("p-mo.log"));
+                        crate::service::install(&path, &log_file)
+                    },
+                    ServiceAction::Uninstall => crate::service::uninstall(),
+                };
+                outcome.map_err(|e| CliError::ExecutionError(e.to_string()))
             },
             Command::Stop => {
                 self.is_running = false;
@@ -73,6 +150,418 @@ impl Cli {
                 config.save(&path)?;
                 
                 Ok("Created default configuration".to_string())
+            },
+            Command::Config { action: ConfigAction::Validate { config_path } } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let content = if self.container && !path.exists() {
+                    String::new()
+                } else {
+                    std::fs::read_to_string(&path).map_err(crate::config::ConfigError::from)?
+                };
+                let profile = crate::config::active_profile(self.profile.as_deref());
+                let problems = crate::config::diagnose(&content, profile.as_deref(), self.container);
+                if problems.is_empty() {
+                    return match profile {
+                        Some(profile) => Ok(format!("{} is valid with profile {}", path.display(), profile)),
+                        None => Ok(format!("{} is valid", path.display())),
+                    };
+                }
+                Err(CliError::InvalidConfig(format!(
+                    "{} problem(s) in {}:\n{}",
+                    problems.len(), path.display(), crate::config::render_problems(&path, &content, &problems)
+                )))
+            },
+            Command::Search { query, collection, limit, format, output, server } => {
+                let export = effects::fetch_search_export(&server, &query, &collection, limit, format)?;
+                
+                match output {
+                    Some(path) => {
+                        std::fs::write(&path, export).map_err(|e| {
+                            CliError::ExecutionError(format!("Failed to write {}: {}", path.display(), e))
+                        })?;
+                        Ok(format!("Exported results to {}", path.display()))
+                    },
+                    None => Ok(export.trim_end().to_string()),
+                }
+            },
+            Command::Reencrypt { collection, old_key_file, new_key_file, qdrant_url } => {
+                let progress = Progress::new(self.output, "reencrypt");
+                let rewritten = effects::reencrypt_qdrant_collection(&qdrant_url, &collection, old_key_file.as_deref(), &new_key_file, &progress)?;
+                progress.finish();
+                Ok(format!("Re-encrypted {} documents in {}", rewritten, collection))
+            },
+            Command::CompressPayloads { collection, threshold_bytes, level, qdrant_url } => {
+                let compression = crate::vector_store::PayloadCompression { threshold_bytes, level };
+                let progress = Progress::new(self.output, "compress-payloads");
+                let rewritten = effects::compress_qdrant_collection(&qdrant_url, &collection, compression, &progress)?;
+                progress.finish();
+                Ok(format!("Compressed {} documents in {}", rewritten, collection))
+            },
+            Command::CloneCollection { source, target, tags, metadata, sample, seed, dry_run, config_path, qdrant_url } => {
+                let metadata = metadata.iter()
+                    .map(|pair| match pair.split_once('=') {
+                        Some((key, value)) => Ok((key.to_string(), value.to_string())),
+                        None => Err(CliError::ExecutionError(format!("Invalid --metadata {} (expected KEY=VALUE)", pair))),
+                    })
+                    .collect::<Result<_, _>>()?;
+                let options = crate::vector_store::CloneOptions {
+                    filter: crate::vector_store::MetadataFilter { tags, metadata, ..Default::default() },
+                    sample,
+                    seed,
+                    dry_run,
+                };
+                
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let config = if path.exists() {
+                    self.load_config(&path)?
+                } else {
+                    crate::config::Config::default()
+                };
+                let progress = Progress::new(self.output, "clone-collection");
+                let outcome = effects::clone_qdrant_collection(&qdrant_url, &config, &source, &target, &options, &progress)?;
+                progress.finish();
+                
+                Ok(if dry_run {
+                    format!("Would copy {} of {} matching entries from {} to {}", outcome.copied, outcome.matched, source, target)
+                } else {
+                    format!("Copied {} of {} matching entries from {} to {}", outcome.copied, outcome.matched, source, target)
+                })
+            },
+            Command::Reshard { collection, shards, config_path, qdrant_url } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let config = if path.exists() {
+                    self.load_config(&path)?
+                } else {
+                    crate::config::Config::default()
+                };
+                let progress = Progress::new(self.output, "reshard");
+                let outcome = effects::reshard_qdrant_collection(&qdrant_url, &config, &collection, shards, &progress)?;
+                progress.finish();
+                
+                let mut message = if outcome.from == outcome.to {
+                    format!("{} already has {} shard(s)", collection, outcome.to)
+                } else {
+                    format!("Moved {} entries of {} from {} to {} shard(s)", outcome.moved, collection, outcome.from, outcome.to)
+                };
+                if config.sharding.collections.get(&collection).is_some_and(|configured| *configured != shards) {
+                    message.push_str(&format!("; sharding.collections.{} in the config still says {}", collection, config.sharding.collections[&collection]));
+                }
+                Ok(message)
+            },
+            Command::Telemetry { config_path, qdrant_url } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let config = if path.exists() {
+                    self.load_config(&path)?
+                } else {
+                    crate::config::Config::default()
+                };
+                let report = effects::preview_telemetry(&qdrant_url, &config)?;
+                let status = match (&config.telemetry.enabled, &config.telemetry.endpoint) {
+                    (false, _) => "Telemetry is disabled; nothing is sent. If enabled, this report would be sent:".to_string(),
+                    (true, None) => "Telemetry is enabled but has no endpoint; nothing is sent. The report would be:".to_string(),
+                    (true, Some(endpoint)) => format!("Telemetry is enabled; this report is sent to {}:", endpoint),
+                };
+                let report = serde_json::to_string_pretty(&report)
+                    .map_err(|e| CliError::ExecutionError(e.to_string()))?;
+                Ok(format!("{}\n{}", status, report))
+            },
+            Command::Eval { file, collection, k, json, server, compare, config_path } => {
+                let to_cli_error = |e: crate::eval::EvalError| CliError::ExecutionError(e.to_string());
+                let set = crate::eval::EvalSet::load(&file).map_err(to_cli_error)?;
+                
+                // Run the set against a server, optionally forcing every case onto one collection
+                let run = |server: &str, forced_collection: Option<&str>| {
+                    crate::eval::evaluate(&set, collection.as_deref(), k, |query, case_collection, k| {
+                        let target = forced_collection.unwrap_or(case_collection);
+                        effects::fetch_search_ids(server, query, target, k).map_err(|e| e.to_string())
+                    })
+                    .map_err(to_cli_error)
+                };
+                
+                let output = match compare {
+                    Some(names) => {
+                        let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                        let configs = self.load_config(&path)?.eval.configs;
+                        let lookup = |name: &str| configs.get(name).cloned().ok_or_else(|| {
+                            CliError::ExecutionError(format!("No retrieval config named '{}' in {}", name, path.display()))
+                        });
+                        let (a, b) = (lookup(&names[0])?, lookup(&names[1])?);
+                        
+                        let report_a = run(a.server.as_deref().unwrap_or(&server), a.collection.as_deref())?;
+                        let report_b = run(b.server.as_deref().unwrap_or(&server), b.collection.as_deref())?;
+                        let comparison = crate::eval::compare_reports(&names[0], report_a, &names[1], report_b);
+                        
+                        if json {
+                            serde_json::to_string_pretty(&comparison)
+                        } else {
+                            Ok(comparison.render_markdown())
+                        }
+                    },
+                    None => {
+                        let report = run(&server, None)?;
+                        if json {
+                            serde_json::to_string_pretty(&report)
+                        } else {
+                            Ok(report.render_markdown())
+                        }
+                    },
+                };
+                
+                output
+                    .map(|output| output.trim_end().to_string())
+                    .map_err(|e| CliError::ExecutionError(e.to_string()))
+            },
+            Command::Analyze { paths, collection, eval, k, sample, json, dry_run, config_path } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let config = if path.exists() {
+                    self.load_config(&path)?
+                } else {
+                    crate::config::Config::default()
+                };
+                let set = eval.as_deref()
+                    .map(crate::eval::EvalSet::load)
+                    .transpose()
+                    .map_err(|e| CliError::ExecutionError(e.to_string()))?;
+                
+                let analysis = effects::analyze_chunking(&config, &paths, sample, set.as_ref(), k)?;
+                
+                let mut output = if json {
+                    serde_json::to_string_pretty(&analysis).map_err(|e| CliError::ExecutionError(e.to_string()))?
+                } else {
+                    analysis.render_markdown().trim_end().to_string()
+                };
+                if !dry_run {
+                    let registry_path = effects::record_chunking(&config, &collection, analysis.recommended)?;
+                    if !json {
+                        output.push_str(&format!("\nRecorded {} for '{}' in {}", analysis.recommended, collection, registry_path.display()));
+                    }
+                }
+                Ok(output)
+            },
+            Command::PollFeeds { config_path, qdrant_url } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let config = self.load_config(&path)?;
+                if config.feeds.feeds.is_empty() {
+                    return Ok(format!("No feeds configured in {}", path.display()));
+                }
+                
+                let lines = effects::poll_qdrant_feeds(&qdrant_url, &config)?;
+                Ok(lines.join("\n"))
+            },
+            Command::Federate { source, config_path, qdrant_url } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let mut config = self.load_config(&path)?;
+                if let Some(name) = &source {
+                    config.federation.sources.retain(|candidate| candidate.name == *name);
+                    if config.federation.sources.is_empty() {
+                        return Err(CliError::ExecutionError(format!("No federation source named {} in {}", name, path.display())));
+                    }
+                }
+                if config.federation.sources.is_empty() {
+                    return Ok(format!("No federation sources configured in {}", path.display()));
+                }
+                
+                let lines = effects::run_qdrant_federation(&qdrant_url, &config)?;
+                Ok(lines.join("\n"))
+            },
+            Command::Ingest { mut paths, collection, parallelism, errors_file, retry, config_path, qdrant_url } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let config = if path.exists() {
+                    self.load_config(&path)?
+                } else {
+                    crate::config::Config::default()
+                };
+                if let Some(retry) = &retry {
+                    paths.extend(crate::sources::files::read_error_file(retry)
+                        .map_err(|e| CliError::ExecutionError(format!("Failed to read {}: {}", retry.display(), e)))?);
+                }
+                if paths.is_empty() {
+                    return Ok("Nothing to ingest".to_string());
+                }
+                
+                let parallelism = parallelism.unwrap_or(config.ingest.parallelism);
+                let progress = Progress::new(self.output, "ingest");
+                let report = effects::ingest_qdrant_files(&qdrant_url, &config, &paths, &collection, parallelism, &progress)?;
+                progress.finish();
+                
+                let mut lines = vec![format!(
+                    "{} ingested ({} entries), {} unchanged, {} removed, {} failed, {} unsupported",
+                    report.succeeded.len(), report.entries(), report.unchanged.len(), report.removed.len(),
+                    report.failed.len(), report.unsupported.len()
+                )];
+                lines.extend(report.succeeded.iter().map(|(path, entries)| format!("  ok {} ({} entries)", path.display(), entries)));
+                lines.extend(report.removed.iter().map(|path| format!("  removed {}", path.display())));
+                lines.extend(report.failed.iter().map(|failure| format!("  failed {}: {}", failure.path.display(), failure.reason)));
+                if !report.failed.is_empty() {
+                    let errors_file = errors_file.unwrap_or_else(|| crate::config::Config::state_dir().join("ingest-errors.jsonl"));
+                    report.write_error_file(&errors_file)
+                        .map_err(|e| CliError::ExecutionError(format!("Failed to write {}: {}", errors_file.display(), e)))?;
+                    lines.push(format!("Failures written to {}; ingest just those with --retry {}", errors_file.display(), errors_file.display()));
+                }
+                Ok(lines.join("\n"))
+            },
+            Command::SyncBucket { bucket, prefix, collection, provider, endpoint, region, config_path, qdrant_url } => {
+                let endpoint = match (endpoint, provider.as_str()) {
+                    (Some(endpoint), _) => endpoint,
+                    (None, "s3") => crate::sources::S3_ENDPOINT.to_string(),
+                    (None, "gcs") => crate::sources::GCS_ENDPOINT.to_string(),
+                    (None, other) => return Err(CliError::ExecutionError(format!("Unknown provider: {} (expected s3 or gcs)", other))),
+                };
+                
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let network = if path.exists() {
+                    self.load_config(&path)?.network
+                } else {
+                    crate::config::NetworkConfig::default()
+                };
+                
+                let progress = Progress::new(self.output, "sync-bucket");
+                let report = effects::sync_qdrant_bucket(&qdrant_url, &endpoint, &region, &bucket, &prefix, &collection, &network, &progress)?;
+                progress.finish();
+                let mut lines = vec![format!(
+                    "{} ingested ({} entries), {} unchanged, {} unsupported, {} failed",
+                    report.ingested, report.entries, report.unchanged, report.unsupported, report.failed.len()
+                )];
+                lines.extend(report.failed.iter().map(|(key, reason)| format!("  {}: {}", key, reason)));
+                Ok(lines.join("\n"))
+            },
+            Command::Digest { collection, config_path, qdrant_url } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let config = if path.exists() {
+                    self.load_config(&path)?
+                } else {
+                    crate::config::Config::default()
+                };
+                
+                let lines = effects::run_qdrant_digests(&qdrant_url, config.digest, collection.as_deref(), &config.network)?;
+                if lines.is_empty() {
+                    Ok("No new entries to digest".to_string())
+                } else {
+                    Ok(lines.join("\n"))
+                }
+            },
+            Command::ExportSettings { output, config_path } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let config = if path.exists() {
+                    crate::config::Config::load_as_written(&path)?
+                } else {
+                    crate::config::Config::default()
+                };
+                let yaml = effects::export_settings(&config)?;
+                
+                match output {
+                    Some(output) => {
+                        std::fs::write(&output, yaml).map_err(|e| {
+                            CliError::ExecutionError(format!("Failed to write {}: {}", output.display(), e))
+                        })?;
+                        Ok(format!("Exported settings to {}", output.display()))
+                    },
+                    None => Ok(yaml.trim_end().to_string()),
+                }
+            },
+            Command::ImportSettings { file, prune, dry_run, config_path } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let mut config = if path.exists() {
+                    crate::config::Config::load_as_written(&path)?
+                } else {
+                    crate::config::Config::default()
+                };
+                let summary = effects::import_settings(&file, &mut config, prune, dry_run)?;
+                if !dry_run {
+                    config.save(&path)?;
+                }
+                
+                let verb = if dry_run { "Would import" } else { "Imported" };
+                let mut lines = vec![format!(
+                    "{} settings from {}: {} collections added, {} updated, {} removed",
+                    verb, file.display(), summary.added.len(), summary.updated.len(), summary.removed.len()
+                )];
+                lines.extend(summary.added.iter().map(|name| format!("  added {}", name)));
+                lines.extend(summary.updated.iter().map(|name| format!("  updated {}", name)));
+                lines.extend(summary.removed.iter().map(|name| format!("  removed {}", name)));
+                Ok(lines.join("\n"))
+            },
+            Command::Lint { collection, fix, max_chunk_chars, json, config_path, qdrant_url } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let config = if path.exists() {
+                    self.load_config(&path)?
+                } else {
+                    crate::config::Config::default()
+                };
+                let options = crate::lint::LintOptions { max_chunk_chars, fix, normalize: config.vectors.normalize };
+                
+                let progress = Progress::new(self.output, "lint");
+                let report = effects::lint_qdrant_collections(&qdrant_url, &config, collection.as_deref(), &options, &progress)?;
+                progress.finish();
+                
+                if json {
+                    serde_json::to_string_pretty(&report).map_err(|e| CliError::ExecutionError(e.to_string()))
+                } else {
+                    Ok(report.render_text().trim_end().to_string())
+                }
+            },
+            Command::Reconcile { collection, recreate, yes, config_path, qdrant_url } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let config = if path.exists() {
+                    self.load_config(&path)?
+                } else {
+                    crate::config::Config::default()
+                };
+                let how = if recreate {
+                    crate::vector_store::Reconciliation::Recreate
+                } else {
+                    crate::vector_store::Reconciliation::UpdateRegistry
+                };
+                
+                let lines = effects::reconcile_qdrant_collections(&qdrant_url, &config, collection.as_deref(), how, yes)?;
+                if lines.is_empty() {
+                    Ok("Every collection matches the registry".to_string())
+                } else {
+                    Ok(lines.join("\n"))
+                }
+            },
+            Command::Selftest { json, config_path, qdrant_url } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let config = if path.exists() {
+                    self.load_config(&path)?
+                } else {
+                    crate::config::Config::default()
+                };
+                let report = effects::run_qdrant_selftest(&qdrant_url, &config)?;
+                
+                let output = if json {
+                    serde_json::to_string_pretty(&report).map_err(|e| CliError::ExecutionError(e.to_string()))?
+                } else {
+                    report.render_text().trim_end().to_string()
+                };
+                if report.passed() {
+                    Ok(output)
+                } else {
+                    Err(CliError::SelftestFailed(output))
+                }
+            },
+            Command::Migrate { dry_run } => {
+                let progress = Progress::new(self.output, "migrate");
+                let migrations = effects::run_migrations(dry_run, &progress)?;
+                progress.finish();
+                let verb = if dry_run { "Would apply" } else { "Applied" };
+                if migrations.is_empty() {
+                    Ok("Schema is up to date".to_string())
+                } else {
+                    let mut lines = vec![format!("{} {} migration(s):", verb, migrations.len())];
+                    lines.extend(migrations.iter().map(|m| format!("  {}: {}", m.version, m.description)));
+                    Ok(lines.join("\n"))
+                }
+            }
+            Command::Compact { api_key, config_path, server } => {
+                let path = config_path.unwrap_or_else(crate::config::Config::default_path);
+                let api_key = match api_key {
+                    Some(key) => Some(key),
+                    None if path.exists() => self.load_config(&path)?.admin.api_key,
+                    None => None,
+                };
+                let report = effects::compact_server(&server, api_key.as_deref())?;
+                Ok(format!("Compacted the store: {}", report))
             }
         }
     }
@@ -81,6 +570,18 @@ impl Cli {
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
+    /// How to report progress: bars on a terminal ("text") or periodic JSON lines on stderr ("json")
+    #[arg(long, value_enum, default_value_t = OutputMode::Text)]
+    output: OutputMode,
+    
+    /// Configuration profile to layer over the base settings; defaults to P_MO_PROFILE
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    
+    /// Run as a container's main process: settings from P_MO_* variables, JSON logs on stdout, no PID file
+    #[arg(long, global = true)]
+    container: bool,
+    
     #[command(subcommand)]
     command: Command,
 }
@@ -89,7 +590,19 @@ impl Args {
     pub fn parse() -> Self {
         <Self as Parser>::parse()
     }
-
+    
+    pub fn output_mode(&self) -> OutputMode {
+        self.output
+    }
+    
+    pub fn profile(&self) -> Option<String> {
+        self.profile.clone()
+    }
+    
+    pub fn container(&self) -> bool {
+        self.container
+    }
+    
     pub fn get_command(self) -> Command {
         self.command
     }
@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use crate::api::ExportFormat;
+
 #[derive(clap::Subcommand, Debug)]
 pub enum Command {
     /// Start the server
@@ -21,6 +23,27 @@ pub enum Command {
         config_path: Option<PathBuf>,
     },
 
+    /// Start the server as a detached background process (works without fork, including on Windows)
+    Background {
+        /// Host address to bind to
+        #[arg(short, long)]
+        host: Option<String>,
+
+        /// Port to listen on
+        #[arg(short, long)]
+        port: Option<u16>,
+
+        /// Path to config file
+        #[arg(short, long)]
+        config_path: Option<PathBuf>,
+    },
+
+    /// Register or remove p-mo as a login service (launchd on macOS, Task Scheduler on Windows)
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+
     /// Stop the server
     Stop,
 
@@ -33,6 +56,454 @@ pub enum Command {
         #[arg(short, long)]
         config_path: Option<PathBuf>,
     },
+
+    /// Inspect the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Search a collection on a running server and export the results
+    Search {
+        /// Text to search for
+        query: String,
+
+        /// Collection to search
+        #[arg(short, long)]
+        collection: String,
+
+        /// Maximum number of results
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+
+        /// Export format
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+
+        /// Write the export to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Base URL of the server
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        server: String,
+    },
+
+    /// Re-encrypt a collection's stored documents under a new key
+    Reencrypt {
+        /// Collection to rewrite
+        #[arg(short, long)]
+        collection: String,
+
+        /// File with the current base64 key; omit if the collection is unencrypted
+        #[arg(long)]
+        old_key_file: Option<PathBuf>,
+
+        /// File with the new base64 key
+        #[arg(long)]
+        new_key_file: PathBuf,
+
+        /// URL of the Qdrant instance holding the collection
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Compress the stored content of existing entries in a collection
+    CompressPayloads {
+        /// Collection to rewrite
+        #[arg(short, long)]
+        collection: String,
+
+        /// Content shorter than this many bytes is left uncompressed
+        #[arg(long, default_value_t = 4096)]
+        threshold_bytes: usize,
+
+        /// zstd compression level
+        #[arg(long, default_value_t = 3)]
+        level: i32,
+
+        /// URL of the Qdrant instance holding the collection
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Copy a collection, or a filtered subset or random sample of it, into a new collection
+    CloneCollection {
+        /// Collection to copy from
+        source: String,
+
+        /// New collection to copy into
+        target: String,
+
+        /// Only copy entries carrying all of these tags (comma separated)
+        #[arg(long, value_delimiter = ',')]
+        tags: Vec<String>,
+
+        /// Only copy entries whose metadata has these values, as key=value
+        #[arg(long = "metadata", value_name = "KEY=VALUE")]
+        metadata: Vec<String>,
+
+        /// Copy a random sample of this many of the matching entries
+        #[arg(long)]
+        sample: Option<usize>,
+
+        /// Seed for a reproducible sample
+        #[arg(long, requires = "sample")]
+        seed: Option<u64>,
+
+        /// Report how many entries would be copied without copying them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Path to config file with the Qdrant settings
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// URL of the Qdrant instance holding the collections
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Move a collection's entries onto a different number of shards
+    Reshard {
+        /// Collection to reshard
+        collection: String,
+
+        /// Number of shards to spread the collection over; 1 stores it unsharded
+        #[arg(long)]
+        shards: usize,
+
+        /// Path to config file with the Qdrant settings
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// URL of the Qdrant instance holding the collection
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Show whether usage telemetry is enabled and print exactly the report that would be sent
+    Telemetry {
+        /// Path to config file with the telemetry settings
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// URL of the Qdrant instance the report describes
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Measure retrieval quality against a file of queries and expected entries
+    Eval {
+        /// YAML or JSON file of {query, expected} cases
+        file: PathBuf,
+
+        /// Collection searched by cases that do not name one
+        #[arg(short, long)]
+        collection: Option<String>,
+
+        /// Number of results scored per query
+        #[arg(short, default_value_t = 10)]
+        k: usize,
+
+        /// Print the report as JSON instead of Markdown
+        #[arg(long)]
+        json: bool,
+
+        /// Base URL of the server
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        server: String,
+
+        /// Compare two retrieval configurations named in the config's [eval.configs]
+        #[arg(long, num_args = 2, value_names = ["A", "B"])]
+        compare: Option<Vec<String>>,
+
+        /// Path to config file with the named retrieval configurations
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+    },
+
+    /// Compare chunking strategies on a sample of a corpus and record the best in the collection registry
+    Analyze {
+        /// Files or directories of the corpus
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Collection whose chunking settings are recorded
+        #[arg(short, long)]
+        collection: String,
+
+        /// YAML or JSON file of {query, expected} cases, naming documents by path
+        #[arg(long)]
+        eval: Option<PathBuf>,
+
+        /// Number of documents scored per eval query
+        #[arg(short, default_value_t = 10)]
+        k: usize,
+
+        /// Maximum number of documents to sample
+        #[arg(long, default_value_t = crate::analyze::DEFAULT_SAMPLE)]
+        sample: usize,
+
+        /// Print the analysis as JSON instead of Markdown
+        #[arg(long)]
+        json: bool,
+
+        /// Report the recommendation without recording it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Path to config file with the embedding settings
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+    },
+
+    /// Poll the configured RSS/Atom feeds once and ingest new items
+    PollFeeds {
+        /// Path to config file with the feed settings
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// URL of the Qdrant instance holding the collections
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Sync the configured federation sources (other MCP servers) once
+    Federate {
+        /// Sync only the source with this name
+        #[arg(short, long)]
+        source: Option<String>,
+
+        /// Path to config file with the federation sources
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// URL of the Qdrant instance holding the collections
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Sync local files and directories (md, txt, html, pdf, jsonl) into a collection,
+    /// skipping unchanged files and removing the entries of deleted ones
+    Ingest {
+        /// Files and directories to ingest; directories are read recursively
+        #[arg(required_unless_present = "retry")]
+        paths: Vec<PathBuf>,
+
+        /// Collection the files are stored in
+        #[arg(short, long)]
+        collection: String,
+
+        /// Files ingested at once; defaults to the config's [ingest] parallelism
+        #[arg(long)]
+        parallelism: Option<usize>,
+
+        /// Where to write the failed files as JSON lines; defaults to ingest-errors.jsonl in the state directory
+        #[arg(long)]
+        errors_file: Option<PathBuf>,
+
+        /// Ingest again the files listed in an errors file from an earlier run
+        #[arg(long)]
+        retry: Option<PathBuf>,
+
+        /// Path to config file with the ingest settings
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// URL of the Qdrant instance holding the collections
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Ingest new and changed objects from an S3 or GCS bucket
+    SyncBucket {
+        /// Bucket to read from
+        bucket: String,
+
+        /// Only ingest objects whose key starts with this prefix
+        #[arg(long, default_value = "")]
+        prefix: String,
+
+        /// Collection the objects are stored in
+        #[arg(short, long)]
+        collection: String,
+
+        /// Storage service: "s3" or "gcs" (credentials come from AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY)
+        #[arg(long, default_value = "s3")]
+        provider: String,
+
+        /// Endpoint of an S3-compatible service, overriding the provider's
+        #[arg(long)]
+        endpoint: Option<String>,
+
+        /// Region the bucket is in
+        #[arg(long, default_value = "us-east-1")]
+        region: String,
+
+        /// Path to config file with the network settings
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// URL of the Qdrant instance holding the collections
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Generate digests of the entries added since the last digest
+    Digest {
+        /// Digest only this collection; defaults to the collections in the config
+        #[arg(short, long)]
+        collection: Option<String>,
+
+        /// Path to config file with the digest settings
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// URL of the Qdrant instance holding the collections
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Export the collection registry, disabled tools and job schedules to a YAML settings bundle
+    ExportSettings {
+        /// Write the bundle to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Path to config file
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+    },
+
+    /// Import a settings bundle exported from another environment, rewriting the config file and registry
+    ImportSettings {
+        /// Bundle written by export-settings
+        file: PathBuf,
+
+        /// Unregister collections the bundle does not list
+        #[arg(long)]
+        prune: bool,
+
+        /// Report what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Path to config file
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+    },
+
+    /// Check collections for missing titles and tags, placeholder or mis-sized vectors, orphaned chunks and overlong chunks
+    Lint {
+        /// Collection to lint; defaults to every collection
+        #[arg(short, long)]
+        collection: Option<String>,
+
+        /// Apply the safe fixes: generate missing titles and re-embed placeholder or mis-sized vectors
+        #[arg(long)]
+        fix: bool,
+
+        /// Report chunks longer than this many characters
+        #[arg(long, default_value_t = crate::lint::DEFAULT_MAX_CHUNK_CHARS)]
+        max_chunk_chars: usize,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Path to config file with the embedding settings
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// URL of the Qdrant instance holding the collections
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Check collections' vector size and distance against the registry and repair any drift
+    Reconcile {
+        /// Collection to check; defaults to every registered collection
+        collection: Option<String>,
+
+        /// Delete a drifted collection, with all its entries, and create it again as the registry
+        /// expects, instead of updating the registry to match the collection
+        #[arg(long)]
+        recreate: bool,
+
+        /// Apply the changes; without this the drift is only reported
+        #[arg(long)]
+        yes: bool,
+
+        /// Path to config file
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// URL of the Qdrant instance holding the collections
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Check a deployment end to end: ingest built-in entries into a temporary collection,
+    /// search, filter, update and delete them, and print a pass/fail matrix
+    Selftest {
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Path to config file with the embedding settings
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// URL of the Qdrant instance to test
+        #[arg(long, default_value = "http://localhost:6333")]
+        qdrant_url: String,
+    },
+
+    /// Upgrade p-mo's stored state to the schema this build expects
+    Migrate {
+        /// List the pending migrations without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Compact a running server's embedded store: checkpoint its write-ahead log and vacuum dead data
+    Compact {
+        /// Admin API key; defaults to the config's [admin] api_key
+        #[arg(long)]
+        api_key: Option<String>,
+
+        /// Path to config file with the admin key
+        #[arg(long)]
+        config_path: Option<PathBuf>,
+
+        /// Base URL of the server
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        server: String,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ServiceAction {
+    /// Start the server at login
+    Install {
+        /// Path to config file the service starts with
+        #[arg(short, long)]
+        config_path: Option<PathBuf>,
+    },
+
+    /// Stop starting the server at login
+    Uninstall,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Check the config file for unknown keys, bad values and conflicting settings, listing every problem with its line
+    Validate {
+        /// Path to config file
+        #[arg(short, long)]
+        config_path: Option<PathBuf>,
+    },
 }
 
 #[cfg(test)]
@@ -0,0 +1,223 @@
+//! A typed async client for a running p-mo server.
+//!
+//! Wraps the REST endpoints so other Rust tools (and our own CLI) do not
+//! have to build URLs and decode JSON by hand. Collection management goes
+//! through the admin endpoints and needs the admin API key.
+
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::api::entries::METADATA_PARAM_PREFIX;
+use crate::api::{ExportFormat, ExportRow};
+use crate::vector_store::{CompactionReport, MetadataFilter};
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Failed to reach {url}: {message}")]
+    Request { url: String, message: String },
+    
+    #[error("Server returned {status}: {message}")]
+    Status { status: u16, message: String },
+    
+    #[error("Unexpected response: {0}")]
+    Decode(String),
+}
+
+/// An entry to add to the knowledge base
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NewEntry {
+    pub title: String,
+    pub content: String,
+    pub tags: Vec<String>,
+}
+
+/// An entry as returned by the server
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Entry {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Client for the knowledge endpoints of one server
+#[derive(Debug, Clone)]
+pub struct KnowledgeClient {
+    base_url: String,
+    api_key: Option<String>,
+    http: reqwest::Client,
+}
+
+impl KnowledgeClient {
+    /// A client for the server at `base_url`, e.g. "http://127.0.0.1:8080"
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: None,
+            http: reqwest::Client::new(),
+        }
+    }
+    
+    /// Send `key` with every request, as required by the admin endpoints
+    pub fn with_api_key(mut self, key: &str) -> Self {
+        self.api_key = Some(key.to_string());
+        self
+    }
+    
+    /// Send requests with `http`, e.g. one built by `network::http_client`
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+    
+    fn request(&self, method: Method, path: &str) -> (String, RequestBuilder) {
+        let url = format!("{}{}", self.base_url, path);
+        let mut request = self.http.request(method, &url);
+        if let Some(key) = &self.api_key {
+            request = request.header("x-api-key", key);
+        }
+        (url, request)
+    }
+    
+    async fn send(&self, url: String, request: RequestBuilder) -> Result<Response, ClientError> {
+        let response = request.send().await
+            .map_err(|e| ClientError::Request { url, message: e.to_string() })?;
+        
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        
+        // Error bodies are {"error": "..."}; fall back to the raw text
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&body).ok()
+            .and_then(|value| value["error"].as_str().map(|message| message.to_string()))
+            .unwrap_or(body);
+        Err(ClientError::Status { status: status.as_u16(), message })
+    }
+    
+    async fn json<T: DeserializeOwned>(&self, url: String, request: RequestBuilder) -> Result<T, ClientError> {
+        self.send(url, request).await?
+            .json()
+            .await
+            .map_err(|e| ClientError::Decode(e.to_string()))
+    }
+    
+    /// Add an entry, returning its id
+    pub async fn add(&self, entry: &NewEntry) -> Result<String, ClientError> {
+        let (url, request) = self.request(Method::POST, "/api/knowledge");
+        self.json(url, request.json(entry)).await
+    }
+    
+    /// Fetch an entry by id
+    pub async fn get(&self, id: &str) -> Result<Entry, ClientError> {
+        let (url, request) = self.request(Method::GET, &format!("/api/knowledge/{}", id));
+        self.json(url, request).await
+    }
+    
+    /// The best `limit` matches for `query` in `collection`
+    pub async fn search(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<ExportRow>, ClientError> {
+        #[derive(Deserialize)]
+        struct SearchResponse {
+            results: Vec<ExportRow>,
+        }
+        
+        let limit = limit.to_string();
+        let (url, request) = self.request(Method::GET, "/api/search");
+        let request = request.query(&[("q", query), ("collection", collection), ("limit", limit.as_str())]);
+        let response: SearchResponse = self.json(url, request).await?;
+        Ok(response.results)
+    }
+    
+    /// The results of a search rendered by the server in `format`
+    pub async fn search_export(&self, collection: &str, query: &str, limit: usize, format: ExportFormat) -> Result<String, ClientError> {
+        let limit = limit.to_string();
+        let (url, request) = self.request(Method::GET, "/api/search");
+        let request = request.query(&[
+            ("q", query),
+            ("collection", collection),
+            ("limit", limit.as_str()),
+            ("format", format.as_str()),
+        ]);
+        
+        self.send(url, request).await?
+            .text()
+            .await
+            .map_err(|e| ClientError::Decode(e.to_string()))
+    }
+    
+    /// Number of entries in `collection` matching `filter`
+    pub async fn count(&self, collection: &str, filter: &MetadataFilter) -> Result<usize, ClientError> {
+        #[derive(Deserialize)]
+        struct CountResponse {
+            count: usize,
+        }
+        
+        let mut params: Vec<(String, String)> = Vec::new();
+        if !filter.ids.is_empty() {
+            params.push(("ids".to_string(), filter.ids.join(",")));
+        }
+        if !filter.tags.is_empty() {
+            params.push(("tags".to_string(), filter.tags.join(",")));
+        }
+        for (key, value) in &filter.metadata {
+            params.push((format!("{}{}", METADATA_PARAM_PREFIX, key), value.clone()));
+        }
+        
+        let (url, request) = self.request(Method::GET, &format!("/api/collections/{}/count", collection));
+        let response: CountResponse = self.json(url, request.query(&params)).await?;
+        Ok(response.count)
+    }
+    
+    /// Whether `collection` holds an entry with this id
+    pub async fn exists(&self, collection: &str, id: &str) -> Result<bool, ClientError> {
+        #[derive(Deserialize)]
+        struct ExistsResponse {
+            exists: bool,
+        }
+        
+        let (url, request) = self.request(Method::GET, &format!("/api/collections/{}/entries/{}/exists", collection, id));
+        let response: ExistsResponse = self.json(url, request).await?;
+        Ok(response.exists)
+    }
+    
+    /// Names of the server's collections (admin)
+    pub async fn collections(&self) -> Result<Vec<String>, ClientError> {
+        #[derive(Deserialize)]
+        struct CollectionsResponse {
+            collections: Vec<String>,
+        }
+        
+        let (url, request) = self.request(Method::GET, "/ui/api/collections");
+        let response: CollectionsResponse = self.json(url, request).await?;
+        Ok(response.collections)
+    }
+    
+    /// Delete an entry from a collection (admin)
+    pub async fn delete(&self, collection: &str, id: &str) -> Result<(), ClientError> {
+        let (url, request) = self.request(Method::DELETE, &format!("/ui/api/collections/{}/entries/{}", collection, id));
+        self.send(url, request).await.map(|_| ())
+    }
+    
+    /// Compact the server's embedded store (admin)
+    pub async fn compact(&self) -> Result<CompactionReport, ClientError> {
+        #[derive(Deserialize)]
+        struct CompactionResponse {
+            compaction: CompactionReport,
+        }
+        
+        let (url, request) = self.request(Method::POST, "/api/admin/compact");
+        let response: CompactionResponse = self.json(url, request).await?;
+        Ok(response.compaction)
+    }
+}
+
+/// The entry id in a search result's `source` (`<collection>#<entry id>`)
+pub fn entry_id(row: &ExportRow) -> &str {
+    row.source.rsplit_once('#').map(|(_, id)| id).unwrap_or(&row.source)
+}
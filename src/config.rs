@@ -1,68 +1,1774 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+mod container;
+mod interpolate;
+mod profiles;
+mod validate;
+
+pub use container::{ENV_PREFIX, VECTOR_STORE_URL_ENV};
+pub use profiles::{active_profile, PROFILE_ENV};
+pub use validate::{diagnose, render as render_problems, ConfigProblem};
+
 #[derive(Debug, Error)]
 pub enum ConfigError {
     #[error("Failed to read config file: {0}")]
     ReadError(#[from] std::io::Error),
     
-    #[error("Failed to parse config file: {0}")]
-    ParseError(#[from] toml::de::Error),
+    #[error("Failed to parse config file: {0}")]
+    ParseError(#[from] toml::de::Error),
+    
+    #[error("Failed to write config file: {0}")]
+    WriteError(String),
+    
+    #[error("Invalid configuration: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    Invalid(Vec<ConfigProblem>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default = "default_server_config")]
+    pub server: ServerConfig,
+    
+    #[serde(default)]
+    pub slow_query: SlowQueryConfig,
+    
+    #[serde(default)]
+    pub admin: AdminConfig,
+    
+    #[serde(default)]
+    pub oidc: OidcConfig,
+    
+    #[serde(default)]
+    pub audit: AuditConfig,
+    
+    #[serde(default)]
+    pub retrieval_log: RetrievalLogConfig,
+    
+    #[serde(default)]
+    pub language: LanguageConfig,
+    
+    #[serde(default)]
+    pub pii: PiiConfig,
+    
+    #[serde(default)]
+    pub safe_mode: SafeModeConfig,
+    
+    #[serde(default)]
+    pub injection_scoring: InjectionScoringConfig,
+    
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
+    
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    
+    #[serde(default)]
+    pub models: ModelsConfig,
+    
+    #[serde(default)]
+    pub memory: MemoryConfig,
+    
+    #[serde(default)]
+    pub digest: DigestConfig,
+    
+    #[serde(default)]
+    pub tools: ToolsConfig,
+    
+    #[serde(default)]
+    pub vectors: VectorsConfig,
+    
+    #[serde(default)]
+    pub pool: PoolConfig,
+    
+    #[serde(default)]
+    pub qdrant: QdrantRoutingConfig,
+    
+    #[serde(default)]
+    pub eval: EvalConfig,
+    
+    #[serde(default)]
+    pub feeds: FeedsConfig,
+    
+    #[serde(default)]
+    pub network: NetworkConfig,
+    
+    #[serde(default)]
+    pub federation: FederationConfig,
+    
+    #[serde(default)]
+    pub gateway: GatewayConfig,
+    
+    #[serde(default)]
+    pub ingest: IngestConfig,
+    
+    #[serde(default)]
+    pub embedding: EmbeddingProvidersConfig,
+    
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    
+    #[serde(default)]
+    pub attachments: AttachmentsConfig,
+    
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    
+    #[serde(default)]
+    pub wal: WalConfig,
+    
+    #[serde(default)]
+    pub sharding: ShardingConfig,
+    
+    #[serde(default)]
+    pub reranker: RerankerConfig,
+    
+    #[serde(default)]
+    pub query_cache: QueryCacheConfig,
+    
+    #[serde(default)]
+    pub changes: ChangesConfig,
+    
+    /// Settings layered over the rest by profile name; see `p_mo::config::active_profile`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, toml::Table>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            server: ServerConfig::default(),
+            slow_query: SlowQueryConfig::default(),
+            admin: AdminConfig::default(),
+            oidc: OidcConfig::default(),
+            audit: AuditConfig::default(),
+            retrieval_log: RetrievalLogConfig::default(),
+            language: LanguageConfig::default(),
+            pii: PiiConfig::default(),
+            safe_mode: SafeModeConfig::default(),
+            injection_scoring: InjectionScoringConfig::default(),
+            encryption: EncryptionConfig::default(),
+            compression: CompressionConfig::default(),
+            models: ModelsConfig::default(),
+            memory: MemoryConfig::default(),
+            digest: DigestConfig::default(),
+            tools: ToolsConfig::default(),
+            vectors: VectorsConfig::default(),
+            pool: PoolConfig::default(),
+            qdrant: QdrantRoutingConfig::default(),
+            eval: EvalConfig::default(),
+            feeds: FeedsConfig::default(),
+            network: NetworkConfig::default(),
+            federation: FederationConfig::default(),
+            gateway: GatewayConfig::default(),
+            ingest: IngestConfig::default(),
+            embedding: EmbeddingProvidersConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            attachments: AttachmentsConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            wal: WalConfig::default(),
+            sharding: ShardingConfig::default(),
+            reranker: RerankerConfig::default(),
+            query_cache: QueryCacheConfig::default(),
+            changes: ChangesConfig::default(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    #[serde(default = "default_host")]
+    pub host: String,
+    
+    #[serde(default = "default_port")]
+    pub port: u16,
+    
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    
+    #[serde(default)]
+    pub daemon: bool,
+    
+    #[serde(default = "default_pid_file")]
+    pub pid_file: Option<PathBuf>,
+    
+    #[serde(default = "default_log_file")]
+    pub log_file: Option<PathBuf>,
+    
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    
+    /// Serve the REST API and the MCP WebSocket on a Unix domain socket
+    /// instead of `host`/`port`
+    #[serde(default)]
+    pub unix_socket: Option<UnixSocketConfig>,
+    
+    /// CORS, security headers and request size limits of the HTTP server
+    #[serde(default)]
+    pub http: HttpConfig,
+    
+    /// Run as a container's main process: JSON logs on stdout, settings from
+    /// `P_MO_*` variables, no PID or log file and a prompt exit on SIGTERM
+    #[serde(default)]
+    pub container: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: default_host(),
+            port: default_port(),
+            timeout_secs: default_timeout_secs(),
+            daemon: false,
+            pid_file: default_pid_file(),
+            log_file: default_log_file(),
+            logging: LoggingConfig::default(),
+            unix_socket: None,
+            http: HttpConfig::default(),
+            container: false,
+        }
+    }
+}
+
+/// A Unix domain socket the server listens on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnixSocketConfig {
+    pub path: PathBuf,
+    
+    /// Permission bits of the socket file; only users who can write to it can connect
+    #[serde(default = "default_unix_socket_mode")]
+    pub mode: u32,
+}
+
+fn default_unix_socket_mode() -> u32 {
+    0o600
+}
+
+/// Browser access to the HTTP server: which other origins may call it, the
+/// headers that keep its pages from being framed or sniffed, and how large a
+/// buffered request body may be
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Origins allowed to make cross-origin requests, e.g.
+    /// `https://notes.example.com`, or `*` for any; empty disables CORS
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+    
+    /// Methods cross-origin requests may use
+    #[serde(default = "default_cors_methods")]
+    pub cors_methods: Vec<String>,
+    
+    /// Request headers cross-origin requests may send
+    #[serde(default = "default_cors_headers")]
+    pub cors_headers: Vec<String>,
+    
+    /// Response headers cross-origin scripts may read
+    #[serde(default = "default_cors_expose_headers")]
+    pub cors_expose_headers: Vec<String>,
+    
+    /// Let browsers send cookies and credentials cross-origin; needs explicit origins
+    #[serde(default)]
+    pub cors_allow_credentials: bool,
+    
+    /// How long browsers may cache a preflight answer
+    #[serde(default = "default_cors_max_age_secs")]
+    pub cors_max_age_secs: u64,
+    
+    /// Send `X-Content-Type-Options`, `X-Frame-Options`, `Referrer-Policy`
+    /// and `Content-Security-Policy` on every response
+    #[serde(default = "default_security_headers")]
+    pub security_headers: bool,
+    
+    #[serde(default = "default_content_security_policy")]
+    pub content_security_policy: String,
+    
+    /// Send `Strict-Transport-Security` with this max-age; only set it when
+    /// clients reach the server over HTTPS
+    #[serde(default)]
+    pub hsts_max_age_secs: Option<u64>,
+    
+    /// Largest JSON or form body a request may send. Attachments have their
+    /// own limit, and streamed ingestion and uploads are bounded per line and
+    /// per session instead.
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            cors_origins: Vec::new(),
+            cors_methods: default_cors_methods(),
+            cors_headers: default_cors_headers(),
+            cors_expose_headers: default_cors_expose_headers(),
+            cors_allow_credentials: false,
+            cors_max_age_secs: default_cors_max_age_secs(),
+            security_headers: default_security_headers(),
+            content_security_policy: default_content_security_policy(),
+            hsts_max_age_secs: None,
+            max_body_bytes: default_max_body_bytes(),
+        }
+    }
+}
+
+fn default_cors_methods() -> Vec<String> {
+    ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"].map(String::from).to_vec()
+}
+
+fn default_cors_headers() -> Vec<String> {
+    ["content-type", "authorization", "x-api-key", "x-session-id", "x-namespace", "upload-length", "upload-offset", "content-range"]
+        .map(String::from)
+        .to_vec()
+}
+
+fn default_cors_expose_headers() -> Vec<String> {
+    ["location", "upload-length", "upload-offset"].map(String::from).to_vec()
+}
+
+fn default_cors_max_age_secs() -> u64 {
+    600
+}
+
+fn default_security_headers() -> bool {
+    true
+}
+
+fn default_content_security_policy() -> String {
+    "default-src 'self'; frame-ancestors 'none'; base-uri 'none'; form-action 'self'".to_string()
+}
+
+fn default_max_body_bytes() -> usize {
+    2 * 1024 * 1024
+}
+
+/// Rotation and retention of the daemon log file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Rotate once the log reaches this size; 0 disables size-based rotation
+    #[serde(default = "default_log_max_file_bytes")]
+    pub max_file_bytes: u64,
+    
+    /// Rotate once the log has been open this long
+    #[serde(default)]
+    pub rotate_every_secs: Option<u64>,
+    
+    /// Number of rotated logs to keep
+    #[serde(default = "default_log_max_files")]
+    pub max_files: usize,
+    
+    /// Gzip rotated logs
+    #[serde(default = "default_log_compress")]
+    pub compress: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: default_log_max_file_bytes(),
+            rotate_every_secs: None,
+            max_files: default_log_max_files(),
+            compress: default_log_compress(),
+        }
+    }
+}
+
+fn default_log_max_file_bytes() -> u64 {
+    50 * 1024 * 1024
+}
+
+fn default_log_max_files() -> usize {
+    7
+}
+
+fn default_log_compress() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlowQueryConfig {
+    /// Operations at or above this latency are logged
+    #[serde(default = "default_slow_query_threshold_ms")]
+    pub threshold_ms: u64,
+    
+    /// Dedicated file for slow query records
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    
+    /// Rotate the slow query log once it reaches this size
+    #[serde(default = "default_slow_query_max_file_bytes")]
+    pub max_file_bytes: u64,
+    
+    /// Number of rotated slow query logs to keep
+    #[serde(default = "default_slow_query_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for SlowQueryConfig {
+    fn default() -> Self {
+        Self {
+            threshold_ms: default_slow_query_threshold_ms(),
+            log_file: None,
+            max_file_bytes: default_slow_query_max_file_bytes(),
+            max_files: default_slow_query_max_files(),
+        }
+    }
+}
+
+fn default_slow_query_threshold_ms() -> u64 {
+    500
+}
+
+fn default_slow_query_max_file_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_slow_query_max_files() -> usize {
+    5
+}
+
+/// Where changes to runtime settings are recorded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuditConfig {
+    /// File the changes are appended to as JSON lines; kept in memory only when unset
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    
+    /// Most changes kept for get_config_history
+    #[serde(default = "default_audit_max_entries")]
+    pub max_entries: usize,
+    
+    /// Rotate the audit file once it reaches this size
+    #[serde(default = "default_slow_query_max_file_bytes")]
+    pub max_file_bytes: u64,
+    
+    /// Number of rotated audit files to keep
+    #[serde(default = "default_slow_query_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            max_entries: default_audit_max_entries(),
+            max_file_bytes: default_slow_query_max_file_bytes(),
+            max_files: default_slow_query_max_files(),
+        }
+    }
+}
+
+fn default_audit_max_entries() -> usize {
+    1000
+}
+
+/// Where the results of searches are kept for later lookup by retrieval id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetrievalLogConfig {
+    /// File the retrievals are appended to as JSON lines; kept in memory only when unset
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    
+    /// Most retrievals kept for get_retrieval
+    #[serde(default = "default_retrieval_max_entries")]
+    pub max_entries: usize,
+    
+    /// Rotate the retrieval file once it reaches this size
+    #[serde(default = "default_slow_query_max_file_bytes")]
+    pub max_file_bytes: u64,
+    
+    /// Number of rotated retrieval files to keep
+    #[serde(default = "default_slow_query_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for RetrievalLogConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            max_entries: default_retrieval_max_entries(),
+            max_file_bytes: default_slow_query_max_file_bytes(),
+            max_files: default_slow_query_max_files(),
+        }
+    }
+}
+
+fn default_retrieval_max_entries() -> usize {
+    10_000
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdminConfig {
+    /// API key required by the admin UI; the UI is disabled when unset
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+/// Accepting JWT bearer tokens from an OIDC issuer alongside API keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OidcConfig {
+    /// The issuer tokens must come from, e.g. `https://login.example.com/realms/eng`;
+    /// tokens are not accepted when unset
+    #[serde(default)]
+    pub issuer: Option<String>,
+    
+    /// The audience tokens must be issued for, e.g. p-mo's client id;
+    /// required with `issuer`, or tokens the issuer made for any of its other
+    /// clients would be accepted
+    #[serde(default)]
+    pub audience: Option<String>,
+    
+    /// Where the issuer's signing keys are published; discovered from the
+    /// issuer's `/.well-known/openid-configuration` when unset
+    #[serde(default)]
+    pub jwks_url: Option<String>,
+    
+    /// How long fetched signing keys are used before they are fetched again
+    #[serde(default = "default_jwks_cache_secs")]
+    pub jwks_cache_secs: u64,
+    
+    /// Clock skew tolerated when checking expiry and not-before times
+    #[serde(default = "default_oidc_leeway_secs")]
+    pub leeway_secs: u64,
+    
+    /// Claim holding the token's scopes, as a space-separated string or an
+    /// array; dots reach into nested claims, e.g. `realm_access.roles`
+    #[serde(default = "default_scope_claim")]
+    pub scope_claim: String,
+    
+    /// Scope claim values and the p-mo scope each grants; when empty the
+    /// values `read`, `write` and `admin` grant themselves
+    #[serde(default)]
+    pub scope_map: HashMap<String, crate::api_keys::Scope>,
+    
+    /// Claim listing the collections the token may touch; every collection
+    /// when the token lacks it
+    #[serde(default = "default_collections_claim")]
+    pub collections_claim: String,
+    
+    /// Claim naming the namespace the token's requests operate in
+    #[serde(default = "default_namespace_claim")]
+    pub namespace_claim: String,
+}
+
+impl Default for OidcConfig {
+    fn default() -> Self {
+        Self {
+            issuer: None,
+            audience: None,
+            jwks_url: None,
+            jwks_cache_secs: default_jwks_cache_secs(),
+            leeway_secs: default_oidc_leeway_secs(),
+            scope_claim: default_scope_claim(),
+            scope_map: HashMap::new(),
+            collections_claim: default_collections_claim(),
+            namespace_claim: default_namespace_claim(),
+        }
+    }
+}
+
+fn default_jwks_cache_secs() -> u64 {
+    3600
+}
+
+fn default_oidc_leeway_secs() -> u64 {
+    60
+}
+
+fn default_scope_claim() -> String {
+    "scope".to_string()
+}
+
+fn default_collections_claim() -> String {
+    "collections".to_string()
+}
+
+fn default_namespace_claim() -> String {
+    "namespace".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageConfig {
+    /// Detect the language of new entries and store it in metadata
+    #[serde(default = "default_language_detect")]
+    pub detect: bool,
+    
+    /// Detections below this confidence are ignored
+    #[serde(default = "default_language_min_confidence")]
+    pub min_confidence: f64,
+    
+    /// Route entries and queries to language-specific collections
+    #[serde(default)]
+    pub route: bool,
+    
+    /// Name of a routed collection; `{collection}` and `{lang}` are substituted
+    #[serde(default = "default_language_collection_template")]
+    pub collection_template: String,
+    
+    /// ISO 639-3 codes to route; all languages are routed when empty
+    #[serde(default)]
+    pub languages: Vec<String>,
+    
+    /// Explicit targets keyed by `<lang>` or `<collection>.<lang>`
+    #[serde(default)]
+    pub overrides: HashMap<String, String>,
+}
+
+impl Default for LanguageConfig {
+    fn default() -> Self {
+        Self {
+            detect: default_language_detect(),
+            min_confidence: default_language_min_confidence(),
+            route: false,
+            collection_template: default_language_collection_template(),
+            languages: Vec::new(),
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+/// What to do with an entry that contains PII
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PiiAction {
+    /// Reject the insert
+    Block,
+    /// Replace the detected spans before storing
+    Mask,
+    /// Store unchanged but mark the entry as sensitive
+    #[default]
+    Tag,
+    /// Skip the check
+    Allow,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PiiConfig {
+    /// Scan new entries for PII
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Action for collections without an override
+    #[serde(default)]
+    pub action: PiiAction,
+    
+    /// Per-collection actions
+    #[serde(default)]
+    pub collections: HashMap<String, PiiAction>,
+}
+
+/// Safe mode for collections that take untrusted content
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SafeModeConfig {
+    /// Sanitize new entries and flag likely prompt injection
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Collections safe mode covers; every collection when empty
+    #[serde(default)]
+    pub collections: Vec<String>,
+}
+
+/// Scoring retrieved entries for prompt injection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InjectionScoringConfig {
+    /// Annotate search and get_context results with an injection_risk score
+    /// unless a call sets `injection_scoring` itself
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EncryptionConfig {
+    /// Environment variable holding a base64 key; takes precedence over `key_file`
+    #[serde(default = "default_encryption_key_env")]
+    pub key_env: String,
+    
+    /// File containing a base64 key; encryption is disabled when no key is found
+    #[serde(default)]
+    pub key_file: Option<PathBuf>,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            key_env: default_encryption_key_env(),
+            key_file: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CompressionConfig {
+    /// Compress stored content with zstd
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Content shorter than this many bytes is stored uncompressed
+    #[serde(default = "default_compression_threshold_bytes")]
+    pub threshold_bytes: usize,
+    
+    /// zstd compression level
+    #[serde(default = "default_compression_level")]
+    pub level: i32,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_bytes: default_compression_threshold_bytes(),
+            level: default_compression_level(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelsConfig {
+    /// Model assumed by get_context when a request does not name one
+    #[serde(default = "default_model_name")]
+    pub default_model: String,
+    
+    /// Additional models, or overrides of the built-in ones, keyed by name
+    #[serde(default)]
+    pub overrides: HashMap<String, crate::text_processing::ModelInfo>,
+}
+
+impl Default for ModelsConfig {
+    fn default() -> Self {
+        Self {
+            default_model: default_model_name(),
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FederationConfig {
+    /// Sync the sources on their schedules while the server is up
+    #[serde(default)]
+    pub enabled: bool,
+    
+    #[serde(default)]
+    pub sources: Vec<FederationSourceConfig>,
+}
+
+/// Another MCP server whose resources are ingested
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FederationSourceConfig {
+    /// Identifies the source in entry metadata and logs
+    pub name: String,
+    
+    /// Command starting the server, which speaks MCP on stdio
+    pub command: String,
+    
+    #[serde(default)]
+    pub args: Vec<String>,
+    
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    
+    /// Collection for resources no rule sends elsewhere
+    pub collection: String,
+    
+    /// Time between syncs
+    #[serde(default = "default_federation_interval_secs")]
+    pub interval_secs: u64,
+    
+    /// Checked in order; the first rule matching a resource URI applies
+    #[serde(default)]
+    pub rules: Vec<MappingRule>,
+}
+
+/// Routes resources whose URI matches `pattern`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MappingRule {
+    /// Regular expression matched against the resource URI
+    pub pattern: String,
+    
+    /// Collection to store matching resources in, instead of the source's
+    #[serde(default)]
+    pub collection: Option<String>,
+    
+    /// Tags added to matching resources
+    #[serde(default)]
+    pub tags: Vec<String>,
+    
+    /// Do not ingest matching resources
+    #[serde(default)]
+    pub skip: bool,
+}
+
+fn default_federation_interval_secs() -> u64 {
+    60 * 60
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GatewayConfig {
+    /// MCP servers whose tools are offered alongside p-mo's own
+    #[serde(default)]
+    pub downstreams: Vec<DownstreamConfig>,
+}
+
+/// A downstream MCP server; its tools are listed as `<name>.<tool>`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DownstreamConfig {
+    pub name: String,
+    
+    /// Command starting the server, which speaks MCP on stdio
+    pub command: String,
+    
+    #[serde(default)]
+    pub args: Vec<String>,
+    
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    
+    /// Variables passed to the server from p-mo's environment, keeping
+    /// credentials out of the config: `{ GITHUB_TOKEN = "PMO_GITHUB_TOKEN" }`
+    #[serde(default)]
+    pub env_from: HashMap<String, String>,
+    
+    /// Tools to expose; all of the server's tools when empty
+    #[serde(default)]
+    pub tools: Vec<String>,
+    
+    /// Longest a forwarded call may take
+    #[serde(default = "default_downstream_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_downstream_timeout_secs() -> u64 {
+    30
+}
+
+/// Outbound connection settings for Qdrant, webhooks, feeds and object storage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Proxy for outbound HTTP and HTTPS requests, e.g. "http://proxy.corp:3128"
+    #[serde(default)]
+    pub proxy: Option<String>,
+    
+    /// Hosts reached directly; an entry also matches its subdomains
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+    
+    /// PEM files of extra root certificates to trust, such as a private CA
+    #[serde(default)]
+    pub ca_certificates: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeedsConfig {
+    /// Poll the feeds while the server is up
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Time between polls
+    #[serde(default = "default_feeds_interval_secs")]
+    pub interval_secs: u64,
+    
+    #[serde(default)]
+    pub feeds: Vec<FeedConfig>,
+}
+
+impl Default for FeedsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_feeds_interval_secs(),
+            feeds: Vec::new(),
+        }
+    }
+}
+
+/// An RSS or Atom feed and the collection its items go to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeedConfig {
+    pub url: String,
+    pub collection: String,
+    
+    /// Tags added to every item of the feed
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_feeds_interval_secs() -> u64 {
+    60 * 60
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EvalConfig {
+    /// Named retrieval configurations that `p-mo eval --compare` can run
+    #[serde(default)]
+    pub configs: HashMap<String, RetrievalConfig>,
+}
+
+/// Where to run an eval set's searches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetrievalConfig {
+    /// Base URL of the server to search; defaults to the command's `--server`
+    #[serde(default)]
+    pub server: Option<String>,
+    
+    /// Collection to search instead of the eval set's, e.g. one built with other chunking
+    #[serde(default)]
+    pub collection: Option<String>,
+}
+
+/// How the embedded backend holds vectors in memory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorStorage {
+    #[default]
+    F32,
+    /// Half precision, halving memory at a small cost in accuracy
+    F16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VectorsConfig {
+    /// L2-normalize embeddings at insert and query time and search by dot product
+    #[serde(default)]
+    pub normalize: bool,
+    
+    /// Vector storage format of the embedded backend
+    #[serde(default)]
+    pub storage: VectorStorage,
+    
+    /// What to do with an all-zero embedding at insert; NaN and infinite values are always rejected
+    #[serde(default)]
+    pub placeholder: PlaceholderAction,
+    
+    /// Content longer than this many bytes is split into chunk entries when
+    /// added, using the collection's chunking strategy; 0 stores it whole
+    #[serde(default = "default_auto_chunk_bytes")]
+    pub auto_chunk_bytes: usize,
+    
+    /// Chunked entries are also stored with an extractive summary of this many
+    /// sentences, matched first by two-stage and hierarchical searches; 0
+    /// stores summaries only for adds that ask
+    #[serde(default)]
+    pub summary_sentences: usize,
+}
+
+impl Default for VectorsConfig {
+    fn default() -> Self {
+        Self {
+            normalize: false,
+            storage: VectorStorage::default(),
+            placeholder: PlaceholderAction::default(),
+            auto_chunk_bytes: default_auto_chunk_bytes(),
+            summary_sentences: 0,
+        }
+    }
+}
+
+fn default_auto_chunk_bytes() -> usize {
+    crate::mcp::DEFAULT_AUTO_CHUNK_BYTES
+}
+
+/// What to do when an entry would be stored with an all-zero embedding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceholderAction {
+    /// Store it and log a warning, as when no embedding provider is configured
+    #[default]
+    Warn,
+    /// Reject the insert
+    Reject,
+}
+
+/// Sizing and timeouts of the Qdrant connection pool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PoolConfig {
+    /// Most connections open at once
+    #[serde(default = "default_pool_max_connections")]
+    pub max_connections: usize,
+    
+    /// How long a request waits for a free connection before failing as pool exhaustion
+    #[serde(default = "default_pool_wait_timeout_ms")]
+    pub wait_timeout_ms: u64,
+    
+    /// How long opening a new connection may take
+    #[serde(default = "default_pool_create_timeout_ms")]
+    pub create_timeout_ms: u64,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: default_pool_max_connections(),
+            wait_timeout_ms: default_pool_wait_timeout_ms(),
+            create_timeout_ms: default_pool_create_timeout_ms(),
+        }
+    }
+}
+
+fn default_pool_max_connections() -> usize {
+    10
+}
+
+fn default_pool_wait_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_pool_create_timeout_ms() -> u64 {
+    5000
+}
+
+/// Settings of `p-mo ingest`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IngestConfig {
+    /// Files read, embedded and stored at once
+    #[serde(default = "default_ingest_parallelism")]
+    pub parallelism: usize,
+    
+    #[serde(default)]
+    pub ocr: OcrConfig,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            parallelism: default_ingest_parallelism(),
+            ocr: OcrConfig::default(),
+        }
+    }
+}
+
+fn default_ingest_parallelism() -> usize {
+    4
+}
+
+/// Text recognition for images and image-only PDFs; needs the `ocr` feature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OcrConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Tesseract language codes, e.g. `eng` or `eng+deu`
+    #[serde(default = "default_ocr_language")]
+    pub language: String,
+    
+    /// Directory holding the Tesseract language data; Tesseract's default when unset
+    #[serde(default)]
+    pub datapath: Option<PathBuf>,
+    
+    /// Pages recognized with a lower mean confidence (0-100) are skipped
+    #[serde(default = "default_ocr_min_confidence")]
+    pub min_confidence: f32,
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            language: default_ocr_language(),
+            datapath: None,
+            min_confidence: default_ocr_min_confidence(),
+        }
+    }
+}
+
+fn default_ocr_language() -> String {
+    "eng".to_string()
+}
+
+fn default_ocr_min_confidence() -> f32 {
+    60.0
+}
+
+/// Opt-in anonymous usage reports; see `p_mo::telemetry` for what they contain.
+///
+/// Written either as `telemetry = false` or as a `[telemetry]` table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "TelemetrySetting")]
+pub struct TelemetryConfig {
+    /// Off unless explicitly turned on
+    pub enabled: bool,
+    
+    /// Where reports are sent; nothing is sent without one
+    pub endpoint: Option<String>,
+    
+    /// How often a report is sent
+    pub interval_secs: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: None,
+            interval_secs: default_telemetry_interval_secs(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+enum TelemetrySetting {
+    Enabled(bool),
+    Table {
+        #[serde(default)]
+        enabled: bool,
+        #[serde(default)]
+        endpoint: Option<String>,
+        #[serde(default = "default_telemetry_interval_secs")]
+        interval_secs: u64,
+    },
+}
+
+impl From<TelemetrySetting> for TelemetryConfig {
+    fn from(setting: TelemetrySetting) -> Self {
+        match setting {
+            TelemetrySetting::Enabled(enabled) => Self { enabled, ..Self::default() },
+            TelemetrySetting::Table { enabled, endpoint, interval_secs } => Self { enabled, endpoint, interval_secs },
+        }
+    }
+}
+
+fn default_telemetry_interval_secs() -> u64 {
+    24 * 60 * 60
+}
+
+/// Where binary attachments of entries are stored and what may be uploaded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttachmentsConfig {
+    /// Directory attachments are stored in; defaults to `attachments` in the data directory
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+    
+    /// Store attachments in S3-compatible storage instead of on disk
+    #[serde(default)]
+    pub bucket: Option<AttachmentBucketConfig>,
+    
+    /// Largest attachment accepted, in bytes
+    #[serde(default = "default_attachment_max_bytes")]
+    pub max_bytes: usize,
+    
+    /// Content types that may be uploaded
+    #[serde(default = "default_attachment_types")]
+    pub allowed_types: Vec<String>,
+    
+    /// Directory unfinished resumable uploads are kept in; defaults to `uploads` in the data directory
+    #[serde(default)]
+    pub upload_dir: Option<PathBuf>,
+    
+    /// Seconds an unfinished resumable upload is kept after it started
+    #[serde(default = "default_upload_ttl_secs")]
+    pub upload_ttl_secs: u64,
+}
+
+impl Default for AttachmentsConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            bucket: None,
+            max_bytes: default_attachment_max_bytes(),
+            allowed_types: default_attachment_types(),
+            upload_dir: None,
+            upload_ttl_secs: default_upload_ttl_secs(),
+        }
+    }
+}
+
+/// A bucket holding attachments; credentials are read from the environment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttachmentBucketConfig {
+    #[serde(default = "default_attachment_bucket_endpoint")]
+    pub endpoint: String,
+    
+    #[serde(default = "default_attachment_bucket_region")]
+    pub region: String,
+    
+    pub bucket: String,
+    
+    /// Prepended to the key of every attachment
+    #[serde(default)]
+    pub prefix: String,
+}
+
+fn default_attachment_max_bytes() -> usize {
+    5 * 1024 * 1024
+}
+
+fn default_attachment_types() -> Vec<String> {
+    ["image/png", "image/jpeg", "image/gif", "image/webp", "image/svg+xml", "application/pdf"]
+        .iter()
+        .map(|content_type| content_type.to_string())
+        .collect()
+}
+
+fn default_upload_ttl_secs() -> u64 {
+    crate::uploads::DEFAULT_UPLOAD_TTL_SECS as u64
+}
+
+fn default_attachment_bucket_endpoint() -> String {
+    crate::sources::S3_ENDPOINT.to_string()
+}
+
+fn default_attachment_bucket_region() -> String {
+    "us-east-1".to_string()
+}
+
+/// Periodic upkeep run by the server, such as purging expired entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaintenanceConfig {
+    #[serde(default = "default_maintenance_enabled")]
+    pub enabled: bool,
     
-    #[error("Failed to write config file: {0}")]
-    WriteError(String),
+    /// Time between maintenance runs
+    #[serde(default = "default_maintenance_interval_secs")]
+    pub interval_secs: u64,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_maintenance_enabled(),
+            interval_secs: default_maintenance_interval_secs(),
+        }
+    }
 }
 
+fn default_maintenance_enabled() -> bool {
+    true
+}
+
+fn default_maintenance_interval_secs() -> u64 {
+    15 * 60
+}
+
+/// Write-ahead log keeping the embedded vector store across restarts and crashes
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Config {
-    #[serde(default = "default_server_config")]
-    pub server: ServerConfig,
+#[serde(deny_unknown_fields)]
+pub struct WalConfig {
+    /// Log every change to the embedded store before acknowledging it, and
+    /// replay the log at startup
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// The log file; `wal.log` in the data directory when unset
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    
+    /// When logged changes are flushed to disk
+    #[serde(default)]
+    pub fsync: FsyncPolicy,
+    
+    /// Time between flushes with `fsync = "interval"`
+    #[serde(default = "default_wal_fsync_interval_ms")]
+    pub fsync_interval_ms: u64,
+    
+    /// Rewrite the log as a checkpoint of the live store once it grows past
+    /// this size, and past twice its size after the last checkpoint; 0 lets
+    /// it grow until checkpointed by hand
+    #[serde(default = "default_wal_checkpoint_bytes")]
+    pub checkpoint_bytes: u64,
 }
 
-impl Default for Config {
+impl Default for WalConfig {
     fn default() -> Self {
         Self {
-            server: ServerConfig::default(),
+            enabled: false,
+            path: None,
+            fsync: FsyncPolicy::default(),
+            fsync_interval_ms: default_wal_fsync_interval_ms(),
+            checkpoint_bytes: default_wal_checkpoint_bytes(),
         }
     }
 }
 
+fn default_wal_fsync_interval_ms() -> u64 {
+    1000
+}
+
+fn default_wal_checkpoint_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+/// The feed of entry and collection changes served on `GET /api/changes`
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ServerConfig {
-    #[serde(default = "default_host")]
-    pub host: String,
+#[serde(deny_unknown_fields)]
+pub struct ChangesConfig {
+    /// Record every change made through the server
+    #[serde(default = "default_changes_enabled")]
+    pub enabled: bool,
     
-    #[serde(default = "default_port")]
-    pub port: u16,
+    /// Changes kept for readers to catch up on; one further behind resyncs
+    #[serde(default = "default_changes_capacity")]
+    pub capacity: usize,
+}
+
+impl Default for ChangesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            capacity: default_changes_capacity(),
+        }
+    }
+}
+
+fn default_changes_enabled() -> bool {
+    true
+}
+
+fn default_changes_capacity() -> usize {
+    crate::events::DEFAULT_FEED_CAPACITY
+}
+
+/// When the write-ahead log is flushed to disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FsyncPolicy {
+    /// Before every change is acknowledged; nothing acknowledged is lost
+    #[default]
+    Always,
+    /// Every `fsync_interval_ms`; a crash loses at most that much
+    Interval,
+}
+
+/// Collections split over several backend collections, for knowledge bases
+/// larger than one backend collection can hold
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShardingConfig {
+    /// Shard count of each sharded collection, used when it is created; an
+    /// existing collection keeps its layout until `p-mo reshard` changes it
+    #[serde(default)]
+    pub collections: BTreeMap<String, usize>,
+}
+
+/// The cross-encoder that reorders the results of collections with reranking enabled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RerankerConfig {
+    /// Directory with the model's `config.json`, `vocab.txt` and
+    /// `rust_model.ot`; no reranking when unset
+    #[serde(default)]
+    pub model_path: Option<PathBuf>,
     
-    #[serde(default = "default_timeout_secs")]
-    pub timeout_secs: u64,
+    #[serde(default)]
+    pub use_gpu: bool,
+    
+    /// Tokens of each query and passage pair the model reads; the rest is cut
+    #[serde(default = "default_reranker_max_length")]
+    pub max_length: usize,
     
+    /// Pairs scored in one pass of the model
+    #[serde(default = "default_reranker_batch_size")]
+    pub batch_size: usize,
+}
+
+impl Default for RerankerConfig {
+    fn default() -> Self {
+        Self {
+            model_path: None,
+            use_gpu: false,
+            max_length: default_reranker_max_length(),
+            batch_size: default_reranker_batch_size(),
+        }
+    }
+}
+
+fn default_reranker_max_length() -> usize {
+    512
+}
+
+fn default_reranker_batch_size() -> usize {
+    32
+}
+
+/// Reusing the embeddings of recent search queries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueryCacheConfig {
+    /// Keep query embeddings so repeated searches skip the embedding model
     #[serde(default)]
-    pub daemon: bool,
+    pub enabled: bool,
     
-    #[serde(default = "default_pid_file")]
-    pub pid_file: Option<PathBuf>,
+    /// How long an embedding is reused after it was made
+    #[serde(default = "default_query_cache_ttl_secs")]
+    pub ttl_secs: u64,
     
-    #[serde(default = "default_log_file")]
-    pub log_file: Option<PathBuf>,
+    /// Most query embeddings kept; the oldest go first
+    #[serde(default = "default_query_cache_max_entries")]
+    pub max_entries: usize,
 }
 
-impl Default for ServerConfig {
+impl Default for QueryCacheConfig {
     fn default() -> Self {
         Self {
-            host: default_host(),
-            port: default_port(),
-            timeout_secs: default_timeout_secs(),
-            daemon: false,
-            pid_file: default_pid_file(),
-            log_file: default_log_file(),
+            enabled: false,
+            ttl_secs: default_query_cache_ttl_secs(),
+            max_entries: default_query_cache_max_entries(),
+        }
+    }
+}
+
+fn default_query_cache_ttl_secs() -> u64 {
+    300
+}
+
+fn default_query_cache_max_entries() -> usize {
+    1024
+}
+
+/// The embedding providers to use, in order of preference
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmbeddingProvidersConfig {
+    /// Tried in order; when one fails the next embeds the text instead.
+    /// All must produce the same dimension. None means placeholder embeddings.
+    #[serde(default)]
+    pub providers: Vec<EmbeddingProviderConfig>,
+}
+
+/// One embedding provider of the fallback chain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum EmbeddingProviderConfig {
+    /// A sentence embedding model run in-process
+    Local {
+        /// Recorded on the entries this provider embeds
+        name: String,
+        
+        #[serde(default = "default_local_model")]
+        model: crate::text_processing::EmbeddingModelType,
+        
+        /// Directory with the model files; downloaded when unset
+        #[serde(default)]
+        model_path: Option<PathBuf>,
+        
+        dimension: usize,
+        
+        #[serde(default)]
+        use_gpu: bool,
+    },
+    /// An OpenAI-compatible embeddings endpoint
+    Remote {
+        /// Recorded on the entries this provider embeds
+        name: String,
+        
+        /// URL of the embeddings endpoint, e.g. https://api.openai.com/v1/embeddings
+        url: String,
+        
+        model: String,
+        
+        /// Environment variable holding the API key, if the endpoint needs one
+        #[serde(default)]
+        api_key_env: Option<String>,
+        
+        dimension: usize,
+        
+        #[serde(default = "default_remote_timeout_secs")]
+        timeout_secs: u64,
+        
+        /// Most calls to make per minute; unlimited when unset
+        #[serde(default)]
+        requests_per_minute: Option<u32>,
+        
+        /// How long a request waits for concurrent ones to join its call; 0 sends each alone
+        #[serde(default = "default_coalesce_window_ms")]
+        coalesce_window_ms: u64,
+        
+        /// Most texts sent in one call
+        #[serde(default = "default_max_batch_size")]
+        max_batch_size: usize,
+    },
+}
+
+impl EmbeddingProviderConfig {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Local { name, .. } | Self::Remote { name, .. } => name,
+        }
+    }
+}
+
+fn default_local_model() -> crate::text_processing::EmbeddingModelType {
+    crate::text_processing::EmbeddingModelType::MiniLM
+}
+
+fn default_remote_timeout_secs() -> u64 {
+    30
+}
+
+fn default_coalesce_window_ms() -> u64 {
+    10
+}
+
+fn default_max_batch_size() -> usize {
+    64
+}
+
+/// Qdrant deployments and which collections live on each
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QdrantRoutingConfig {
+    /// Named Qdrant deployments; none means the single `--qdrant-url` instance
+    #[serde(default)]
+    pub instances: Vec<QdrantInstanceConfig>,
+    
+    /// Checked in order; the first rule matching a collection name picks its instance
+    #[serde(default)]
+    pub routes: Vec<CollectionRoute>,
+    
+    /// Instance for collections no rule matches; the first instance when unset
+    #[serde(default)]
+    pub default_instance: Option<String>,
+}
+
+/// A Qdrant deployment collections can be routed to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QdrantInstanceConfig {
+    pub name: String,
+    
+    pub url: String,
+    
+    /// Environment variable holding the instance's API key, keeping it out of the config
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    
+    /// Read replicas; searches and lookups go here while writes go to `url`
+    #[serde(default)]
+    pub read_urls: Vec<String>,
+    
+    /// How a read replica is picked for each request
+    #[serde(default)]
+    pub read_selection: ReadSelection,
+}
+
+/// How reads are spread over replicas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadSelection {
+    /// Take turns
+    #[default]
+    RoundRobin,
+    /// Prefer the replica that has been answering fastest
+    LeastLatency,
+}
+
+/// Sends collections whose name matches `pattern` to `instance`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CollectionRoute {
+    /// Regular expression matched against the collection name
+    pub pattern: String,
+    
+    /// Name of the instance holding matching collections
+    pub instance: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolsConfig {
+    /// Tools to hide from ListTools and refuse to run
+    #[serde(default)]
+    pub disabled: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DigestConfig {
+    /// Run the digest job while the server is up
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Time between digest runs
+    #[serde(default = "default_digest_interval_secs")]
+    pub interval_secs: u64,
+    
+    /// Sentences in each digest summary
+    #[serde(default = "default_digest_max_sentences")]
+    pub max_sentences: usize,
+    
+    /// Slack-compatible webhook that receives every digest
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    
+    /// Collections to digest, keyed by name
+    #[serde(default)]
+    pub collections: HashMap<String, CollectionDigestConfig>,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_digest_interval_secs(),
+            max_sentences: default_digest_max_sentences(),
+            webhook_url: None,
+            collections: HashMap::new(),
+        }
+    }
+}
+
+/// Digest settings for one collection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CollectionDigestConfig {
+    #[serde(default = "default_collection_digest_enabled")]
+    pub enabled: bool,
+    
+    /// Webhook for this collection's digests, replacing the global one
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    
+    /// Sentences in this collection's summaries, replacing the global setting
+    #[serde(default)]
+    pub max_sentences: Option<usize>,
+}
+
+impl Default for CollectionDigestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            webhook_url: None,
+            max_sentences: None,
+        }
+    }
+}
+
+fn default_digest_interval_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_digest_max_sentences() -> usize {
+    5
+}
+
+fn default_collection_digest_enabled() -> bool {
+    true
+}
+
+/// Who a memory belongs to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryScope {
+    /// The current MCP session, falling back to the user without one
+    #[default]
+    Session,
+    /// The authenticated client
+    User,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MemoryConfig {
+    /// Default owner of memories
+    #[serde(default)]
+    pub scope: MemoryScope,
+    
+    /// Prefix of memory collection names
+    #[serde(default = "default_memory_collection_prefix")]
+    pub collection_prefix: String,
+    
+    /// Age at which a memory's recall weight halves
+    #[serde(default = "default_memory_half_life_secs")]
+    pub half_life_secs: u64,
+    
+    /// Lifetime of memories written without a ttl; unset means they never expire
+    #[serde(default)]
+    pub default_ttl_secs: Option<u64>,
+    
+    /// Minimum similarity for forget-by-query to delete a memory
+    #[serde(default = "default_memory_forget_threshold")]
+    pub forget_threshold: f32,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            scope: MemoryScope::default(),
+            collection_prefix: default_memory_collection_prefix(),
+            half_life_secs: default_memory_half_life_secs(),
+            default_ttl_secs: None,
+            forget_threshold: default_memory_forget_threshold(),
         }
     }
 }
 
+fn default_memory_collection_prefix() -> String {
+    "memory".to_string()
+}
+
+fn default_memory_half_life_secs() -> u64 {
+    3 * 24 * 60 * 60
+}
+
+fn default_memory_forget_threshold() -> f32 {
+    0.8
+}
+
+fn default_model_name() -> String {
+    crate::text_processing::models::DEFAULT_MODEL.to_string()
+}
+
+fn default_compression_threshold_bytes() -> usize {
+    4096
+}
+
+fn default_compression_level() -> i32 {
+    3
+}
+
+fn default_encryption_key_env() -> String {
+    "P_MO_ENCRYPTION_KEY".to_string()
+}
+
+fn default_language_detect() -> bool {
+    true
+}
+
+fn default_language_min_confidence() -> f64 {
+    0.5
+}
+
+fn default_language_collection_template() -> String {
+    "{collection}_{lang}".to_string()
+}
+
 fn default_host() -> String {
     "127.0.0.1".to_string()
 }
@@ -76,11 +1782,11 @@ fn default_timeout_secs() -> u64 {
 }
 
 fn default_pid_file() -> Option<PathBuf> {
-    Some(PathBuf::from("/tmp/p-mo.pid"))
+    Some(Config::state_dir().join("p-mo.pid"))
 }
 
 fn default_log_file() -> Option<PathBuf> {
-    Some(PathBuf::from("/tmp/p-mo.log"))
+    Some(Config::state_dir().join("p-mo.log"))
 }
 
 fn default_server_config() -> ServerConfig {
@@ -88,9 +1794,43 @@ fn default_server_config() -> ServerConfig {
 }
 
 impl Config {
+    /// Load the config file at `path` with the profile named by `P_MO_PROFILE`,
+    /// if any, resolving its `${VAR}` references from the environment
     pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::load_profile(path, None)
+    }
+    
+    /// Load the config file at `path` with `profile` layered over it, or else
+    /// the profile named by `P_MO_PROFILE`
+    pub fn load_profile(path: &Path, profile: Option<&str>) -> Result<Self, ConfigError> {
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content, active_profile(profile).as_deref(), Some(&interpolate::env_var), false)
+    }
+    
+    /// Load the settings of a container: the config file at `path` if one is
+    /// mounted there, with `profile` and then the `P_MO_*` variables over it
+    pub fn load_container(path: &Path, profile: Option<&str>) -> Result<Self, ConfigError> {
+        let content = if path.exists() { fs::read_to_string(path)? } else { String::new() };
+        Self::parse(&content, active_profile(profile).as_deref(), Some(&interpolate::env_var), true)
+    }
+    
+    /// Load the config file at `path` as written, without a profile or
+    /// resolving `${VAR}` references, for commands that write the config back
+    /// or copy settings elsewhere
+    pub fn load_as_written(path: &Path) -> Result<Self, ConfigError> {
         let content = fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+        Self::parse(&content, None, None, false)
+    }
+    
+    fn parse(content: &str, profile: Option<&str>, lookup: Option<interpolate::Lookup>, container: bool) -> Result<Self, ConfigError> {
+        // Parsed as written first, so type errors point at the file's lines
+        let config: Config = toml::from_str(content)?;
+        let config = resolve_layers(config, content, profile, lookup, container)
+            .map_err(|problems| ConfigError::Invalid(validate::with_lines(problems, content)))?;
+        let problems = config.validate();
+        if !problems.is_empty() {
+            return Err(ConfigError::Invalid(validate::with_lines(problems, content)));
+        }
         Ok(config)
     }
     
@@ -109,11 +1849,29 @@ impl Config {
             .join("config.toml")
     }
     
+    /// Per-user directory for the PID and log files of a background server
+    ///
+    /// `~/.local/state/p-mo` on Linux, `~/Library/Application Support/p-mo`
+    /// on macOS and `%LOCALAPPDATA%\p-mo` on Windows.
+    pub fn state_dir() -> PathBuf {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .unwrap_or_else(std::env::temp_dir)
+            .join("p-mo")
+    }
+    
+    /// Per-user directory for data such as the embedded vector store
+    pub fn data_dir() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("p-mo")
+    }
+    
     pub fn ensure_config_dir() -> Result<PathBuf, ConfigError> {
         let config_dir = dirs::config_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join("p-mo");
-            
+        
         if !config_dir.exists() {
             fs::create_dir_all(&config_dir)
                 .map_err(|e| ConfigError::WriteError(format!("Failed to create config directory: {}", e)))?;
@@ -134,3 +1892,33 @@ impl Config {
         Ok(config_path)
     }
 }
+
+/// Layer `profile` over `config`, parsed as written from the file `content`,
+/// then in container mode the `P_MO_*` variables, and resolve its `${VAR}`
+/// references with `lookup`; without a lookup the environment is not read
+fn resolve_layers(config: Config, content: &str, profile: Option<&str>, lookup: Option<interpolate::Lookup>, container: bool) -> Result<Config, Vec<ConfigProblem>> {
+    let interpolating = lookup.is_some() && content.contains("${");
+    let container = lookup.is_some() && (container || config.server.container);
+    if profile.is_none() && !interpolating && !container {
+        return Ok(config);
+    }
+    
+    let mut document: toml::Value = toml::from_str(content).map_err(|e| vec![ConfigProblem::new("", e.message())])?;
+    if let Some(profile) = profile {
+        profiles::apply(&mut document, profile).map_err(|problem| vec![problem])?;
+    }
+    // A profile may switch container mode on as well
+    if lookup.is_some() && (container || container::switched_on(&document)) {
+        let problems = container::apply_env(&mut document, &container::env_vars());
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+    }
+    if let Some(lookup) = lookup {
+        let problems = interpolate::interpolate_document(&mut document, lookup);
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+    }
+    document.try_into().map_err(|e: toml::de::Error| vec![ConfigProblem::new("", e.message())])
+}
@@ -0,0 +1,181 @@
+//! Settings from `P_MO_*` environment variables, for running in a container.
+//!
+//! In container mode any setting can come from the environment instead of a
+//! mounted file: the variable is the setting's path in upper case behind
+//! `P_MO_`, with `_` for `.`, so `P_MO_SERVER_PORT` sets `server.port` and
+//! `P_MO_SLOW_QUERY_THRESHOLD_MS` sets `slow_query.threshold_ms`. Variables
+//! override the file and the active profile.
+//!
+//! A value is read as the type of the setting it replaces; settings without a
+//! default take a TOML value (`true`, `30`, `["a", "b"]`, `"0042"` for digits
+//! meant as text), else the text as a string. `P_MO_VECTOR_STORE_URL` stands
+//! for a single Qdrant instance named `default`. Variables whose first word
+//! names no section are left alone, so `${P_MO_...}` references keep working.
+
+use toml::{Table, Value};
+
+use super::profiles::PROFILE_ENV;
+use super::validate::ConfigProblem;
+use super::Config;
+
+/// Prefix of the variables holding settings
+pub const ENV_PREFIX: &str = "P_MO_";
+
+/// Variable holding the URL of the vector store, as a Qdrant instance named `default`
+pub const VECTOR_STORE_URL_ENV: &str = "P_MO_VECTOR_STORE_URL";
+
+/// The `P_MO_*` variables of the process, in name order
+pub fn env_vars() -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+        .collect();
+    vars.sort();
+    vars
+}
+
+/// Whether the config file `document` sets `server.container`
+pub fn switched_on(document: &Value) -> bool {
+    document.get("server").and_then(|server| server.get("container")).and_then(Value::as_bool) == Some(true)
+}
+
+/// Set the settings named by `vars` in the config file `document`, returning
+/// the values that do not fit their setting
+pub fn apply_env(document: &mut Value, vars: &[(String, String)]) -> Vec<ConfigProblem> {
+    let defaults = match Value::try_from(Config::default()) {
+        Ok(Value::Table(defaults)) => defaults,
+        _ => Table::new(),
+    };
+    let mut problems = Vec::new();
+    for (name, raw) in vars {
+        if name == VECTOR_STORE_URL_ENV {
+            let mut instance = Table::new();
+            instance.insert("name".to_string(), Value::String("default".to_string()));
+            instance.insert("url".to_string(), Value::String(raw.clone()));
+            set(document, &["qdrant".to_string(), "instances".to_string()], Value::Array(vec![Value::Table(instance)]));
+            continue;
+        }
+        let Some(rest) = name.strip_prefix(ENV_PREFIX).filter(|_| name != PROFILE_ENV) else {
+            continue;
+        };
+        let words: Vec<String> = rest.to_lowercase().split('_').map(str::to_string).collect();
+        let Some(path) = setting_path(&defaults, &words, true) else {
+            continue;
+        };
+        match parse(raw, default_at(&defaults, &path)) {
+            Ok(value) => set(document, &path, value),
+            Err(message) => problems.push(ConfigProblem::new(path.join("."), format!("{} {}", name, message))),
+        }
+    }
+    problems
+}
+
+/// The path of the setting `words` names: the longest runs of words naming
+/// tables of `table`, then the rest as the key. `None` at the top when the
+/// words name no section.
+fn setting_path(table: &Table, words: &[String], top: bool) -> Option<Vec<String>> {
+    // At least one word is left for the key
+    for end in (1..words.len()).rev() {
+        let name = words[..end].join("_");
+        if let Some(Value::Table(child)) = table.get(&name) {
+            let mut path = vec![name];
+            path.extend(setting_path(child, &words[end..], false)?);
+            return Some(path);
+        }
+    }
+    if top { None } else { Some(vec![words.join("_")]) }
+}
+
+fn default_at<'a>(defaults: &'a Table, path: &[String]) -> Option<&'a Value> {
+    let (key, tables) = path.split_last()?;
+    let mut table = defaults;
+    for name in tables {
+        table = table.get(name)?.as_table()?;
+    }
+    table.get(key)
+}
+
+/// `raw` as a value of the same type as `default`
+fn parse(raw: &str, default: Option<&Value>) -> Result<Value, String> {
+    match default {
+        Some(Value::String(_)) => Ok(Value::String(raw.to_string())),
+        Some(Value::Boolean(_)) => match raw.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" => Ok(Value::Boolean(true)),
+            "false" | "0" | "no" => Ok(Value::Boolean(false)),
+            _ => Err(format!("is not true or false: '{}'", raw)),
+        },
+        Some(Value::Integer(_)) => raw.trim().parse().map(Value::Integer)
+            .map_err(|_| format!("is not a whole number: '{}'", raw)),
+        Some(Value::Float(_)) => raw.trim().parse().map(Value::Float)
+            .map_err(|_| format!("is not a number: '{}'", raw)),
+        _ => Ok(toml::from_str::<Table>(&format!("value = {}", raw))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| Value::String(raw.to_string()))),
+    }
+}
+
+/// Set `path` of `document` to `value`, adding the tables on the way
+fn set(document: &mut Value, path: &[String], value: Value) {
+    let Some((key, tables)) = path.split_last() else {
+        return;
+    };
+    let mut current = document;
+    for name in tables {
+        let Value::Table(table) = current else {
+            return;
+        };
+        let child = table.entry(name.clone()).or_insert_with(|| Value::Table(Table::new()));
+        if !child.is_table() {
+            *child = Value::Table(Table::new());
+        }
+        current = child;
+    }
+    if let Value::Table(table) = current {
+        table.insert(key.clone(), value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+    
+    #[test]
+    fn test_env_vars_override_the_file() {
+        let mut document: Value = toml::from_str("[server]\nport = 8080\n").unwrap();
+        let problems = apply_env(&mut document, &vars(&[
+            ("P_MO_SERVER_PORT", "9000"),
+            ("P_MO_SERVER_HOST", "0.0.0.0"),
+            ("P_MO_SERVER_LOGGING_MAX_FILES", "3"),
+            ("P_MO_SLOW_QUERY_THRESHOLD_MS", "250"),
+            ("P_MO_VECTOR_STORE_URL", "http://qdrant:6334"),
+            ("P_MO_PROFILE", "prod"),
+            ("P_MO_ADMIN_API_KEY", "s3cret"),
+            ("P_MO_UNRELATED", "ignored"),
+        ]));
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(document["server"]["port"].as_integer(), Some(9000));
+        assert_eq!(document["server"]["host"].as_str(), Some("0.0.0.0"));
+        assert_eq!(document["server"]["logging"]["max_files"].as_integer(), Some(3));
+        assert_eq!(document["slow_query"]["threshold_ms"].as_integer(), Some(250));
+        assert_eq!(document["qdrant"]["instances"][0]["url"].as_str(), Some("http://qdrant:6334"));
+        assert_eq!(document["admin"]["api_key"].as_str(), Some("s3cret"));
+        assert!(document.get("unrelated").is_none());
+        
+        // Typos under a known section are still refused as unknown keys
+        apply_env(&mut document, &vars(&[("P_MO_SERVER_PROT", "9000")]));
+        assert!(document.try_into::<Config>().is_err());
+    }
+    
+    #[test]
+    fn test_env_values_must_fit_their_setting() {
+        let mut document = Value::Table(Table::new());
+        let problems = apply_env(&mut document, &vars(&[("P_MO_SERVER_PORT", "eighty"), ("P_MO_SERVER_DAEMON", "maybe")]));
+        let paths: Vec<&str> = problems.iter().map(|problem| problem.path.as_str()).collect();
+        assert_eq!(paths, ["server.port", "server.daemon"]);
+        assert!(problems[0].message.starts_with("P_MO_SERVER_PORT is not a whole number"));
+    }
+}
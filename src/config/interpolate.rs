@@ -0,0 +1,115 @@
+//! `${VAR}` references in config values, resolved from the environment.
+//!
+//! Any string value may hold `${VAR}`, replaced by the variable's value, or
+//! `${VAR:-default}`, which falls back to `default` when the variable is unset
+//! or empty; `$${` writes a literal `${`. API keys, webhook URLs and other
+//! secrets can so stay out of a config file that is committed.
+
+use toml::Value;
+
+use super::validate::ConfigProblem;
+
+/// Reads an environment variable; tests substitute their own
+pub type Lookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// Read `name` from the process environment
+pub fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Resolve the references in every string of the config file `document`,
+/// returning those that cannot be
+pub fn interpolate_document(document: &mut Value, lookup: Lookup) -> Vec<ConfigProblem> {
+    let mut problems = Vec::new();
+    interpolate(document, String::new(), lookup, &mut problems);
+    problems
+}
+
+/// Resolve the references in every string of `value`, which sits at `path`
+fn interpolate(value: &mut Value, path: String, lookup: Lookup, problems: &mut Vec<ConfigProblem>) {
+    match value {
+        Value::String(text) if text.contains('$') => match resolve(text, lookup) {
+            Ok(resolved) => *text = resolved,
+            Err(message) => problems.push(ConfigProblem::new(path, message)),
+        },
+        Value::Array(values) => {
+            for (index, value) in values.iter_mut().enumerate() {
+                interpolate(value, format!("{}[{}]", path, index), lookup, problems);
+            }
+        },
+        Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                interpolate(value, path, lookup, problems);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// `text` with its references replaced, or why one cannot be
+fn resolve(text: &str, lookup: Lookup) -> Result<String, String> {
+    let mut resolved = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        resolved.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(escaped) = after.strip_prefix("${") {
+            resolved.push_str("${");
+            rest = escaped;
+        } else if let Some(reference) = after.strip_prefix('{') {
+            let end = reference.find('}').ok_or_else(|| "has a ${ without its closing }".to_string())?;
+            let (name, default) = match reference[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&reference[..end], None),
+            };
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("'{}' is not an environment variable name", name));
+            }
+            match lookup(name).filter(|value| !value.is_empty()).or_else(|| default.map(str::to_string)) {
+                Some(value) => resolved.push_str(&value),
+                None => return Err(format!("environment variable {} is not set and the reference gives no default", name)),
+            }
+            rest = &reference[end + 1..];
+        } else {
+            resolved.push('$');
+            rest = after;
+        }
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "PMO_ADMIN_KEY" => Some("s3cret".to_string()),
+            "PMO_EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+    
+    #[test]
+    fn test_resolve_references() {
+        assert_eq!(resolve("${PMO_ADMIN_KEY}", &lookup), Ok("s3cret".to_string()));
+        assert_eq!(resolve("http://${PMO_HOST:-localhost}:6334", &lookup), Ok("http://localhost:6334".to_string()));
+        assert_eq!(resolve("${PMO_EMPTY:-fallback}", &lookup), Ok("fallback".to_string()));
+        assert_eq!(resolve("^docs-.*$ and $${literal}", &lookup), Ok("^docs-.*$ and ${literal}".to_string()));
+        assert!(resolve("${PMO_MISSING}", &lookup).unwrap_err().contains("PMO_MISSING is not set"));
+        assert!(resolve("${PMO_ADMIN_KEY", &lookup).is_err());
+        assert!(resolve("${not a name}", &lookup).is_err());
+    }
+    
+    #[test]
+    fn test_interpolate_document() {
+        let content = "[admin]\napi_key = \"${PMO_ADMIN_KEY}\"\n\n[[qdrant.instances]]\nname = \"cloud\"\nurl = \"${PMO_QDRANT_URL}\"\n";
+        let mut document: Value = toml::from_str(content).unwrap();
+        let problems = interpolate_document(&mut document, &lookup);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].path, "qdrant.instances[0].url");
+        assert_eq!(document["admin"]["api_key"].as_str(), Some("s3cret"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Profiles layering settings over the base config, e.g. for dev and prod.
+//!
+//! A profile is a `[profiles.<name>]` table holding only the settings it
+//! changes, nested like the rest of the file: `[profiles.prod.server]` sets
+//! `server.*` for `prod`. A profile may name another as `inherits` to build
+//! on its settings. The active profile comes from `--profile` or
+//! `P_MO_PROFILE`; without one the base settings apply unchanged.
+//!
+//! Layers merge in a fixed order, from the base through each ancestor to the
+//! selected profile: tables merge key by key, and any other value, arrays
+//! included, replaces the one beneath it.
+
+use toml::{Table, Value};
+
+use super::validate::ConfigProblem;
+
+/// Environment variable selecting the profile when `--profile` is not given
+pub const PROFILE_ENV: &str = "P_MO_PROFILE";
+
+/// Key of a profile naming the profile it builds on
+const INHERITS_KEY: &str = "inherits";
+
+/// The profile to use: `requested`, else the one named by `P_MO_PROFILE`
+pub fn active_profile(requested: Option<&str>) -> Option<String> {
+    requested
+        .map(str::to_string)
+        .or_else(|| std::env::var(PROFILE_ENV).ok())
+        .filter(|profile| !profile.is_empty())
+}
+
+/// Merge the layers of `profile` over the base settings of the config file `document`
+pub fn apply(document: &mut Value, profile: &str) -> Result<(), ConfigProblem> {
+    let profiles = document.get("profiles").and_then(Value::as_table).cloned().unwrap_or_default();
+    
+    // The selected profile first, then each profile it inherits from
+    let mut chain: Vec<(&str, &Table)> = Vec::new();
+    let mut name = profile;
+    loop {
+        if chain.iter().any(|(seen, _)| *seen == name) {
+            let cycle: Vec<&str> = chain.iter().map(|(seen, _)| *seen).chain([name]).collect();
+            return Err(ConfigProblem::new(inherits_path(&chain), format!("profiles inherit from each other in a cycle: {}", cycle.join(" -> "))));
+        }
+        let Some(layer) = profiles.get(name).and_then(Value::as_table) else {
+            let path = if chain.is_empty() { "profiles".to_string() } else { inherits_path(&chain) };
+            return Err(ConfigProblem::new(path, format!("no profile named '{}' is defined", name)));
+        };
+        chain.push((name, layer));
+        match layer.get(INHERITS_KEY) {
+            None => break,
+            Some(Value::String(parent)) => name = parent.as_str(),
+            Some(_) => return Err(ConfigProblem::new(inherits_path(&chain), "must be the name of a profile")),
+        }
+    }
+    
+    if let Value::Table(base) = document {
+        for (_, layer) in chain.into_iter().rev() {
+            let mut layer = layer.clone();
+            layer.remove(INHERITS_KEY);
+            layer.remove("profiles");
+            merge(base, layer);
+        }
+    }
+    Ok(())
+}
+
+fn inherits_path(chain: &[(&str, &Table)]) -> String {
+    chain.last().map(|(name, _)| format!("profiles.{}.{}", name, INHERITS_KEY)).unwrap_or_default()
+}
+
+/// Merge `layer` into `base`: tables key by key, anything else replaced
+fn merge(base: &mut Table, layer: Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(layer)) => merge(base, layer),
+            (_, value) => {
+                base.insert(key, value);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const CONTENT: &str = r#"
+[server]
+host = "127.0.0.1"
+port = 8080
+
+[[qdrant.instances]]
+name = "local"
+url = "http://localhost:6334"
+
+[profiles.staging.server]
+host = "0.0.0.0"
+
+[profiles.prod]
+inherits = "staging"
+
+[profiles.prod.server]
+port = 443
+
+[[profiles.prod.qdrant.instances]]
+name = "cloud"
+url = "https://qdrant.example.com:6334"
+"#;
+
+    #[test]
+    fn test_profiles_merge_through_inheritance() {
+        let mut document: Value = toml::from_str(CONTENT).unwrap();
+        apply(&mut document, "prod").unwrap();
+        assert_eq!(document["server"]["host"].as_str(), Some("0.0.0.0"));
+        assert_eq!(document["server"]["port"].as_integer(), Some(443));
+        // Arrays are replaced, not appended to
+        let instances = document["qdrant"]["instances"].as_array().unwrap();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0]["name"].as_str(), Some("cloud"));
+    }
+    
+    #[test]
+    fn test_unknown_and_cyclic_profiles() {
+        let mut document: Value = toml::from_str(CONTENT).unwrap();
+        assert_eq!(apply(&mut document, "dev").unwrap_err().path, "profiles");
+        
+        let mut document: Value = toml::from_str("[profiles.a]\ninherits = \"b\"\n[profiles.b]\ninherits = \"a\"\n").unwrap();
+        let problem = apply(&mut document, "a").unwrap_err();
+        assert_eq!(problem.path, "profiles.b.inherits");
+        assert!(problem.message.contains("a -> b -> a"));
+    }
+}
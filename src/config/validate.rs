@@ -0,0 +1,452 @@
+//! Finding everything wrong with a config file at once.
+//!
+//! Unknown keys and mistyped values are refused when the file is parsed,
+//! `${VAR}` references must resolve, and [`Config::validate`] then checks
+//! settings against each other. Loading stops
+//! at the first parse error; [`diagnose`] keeps going so `p-mo config validate`
+//! can list every problem with the line it is on.
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use super::interpolate::env_var;
+use super::{resolve_layers, Config, EmbeddingProviderConfig, FsyncPolicy, VectorStorage};
+use crate::network;
+use crate::vector_store::sharded::SHARD_SEPARATOR;
+
+/// Parse errors reported before giving up on a file
+const MAX_PARSE_PROBLEMS: usize = 50;
+
+/// Something wrong with a configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// Dotted path of the setting, e.g. `qdrant.routes[0].instance`; empty when unknown
+    pub path: String,
+    pub message: String,
+    /// Line of the setting in the file, counting from 1, when known
+    pub line: Option<usize>,
+}
+
+impl ConfigProblem {
+    pub(super) fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+            line: None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Config {
+    /// Check settings against each other; no problems means the config is usable
+    pub fn validate(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        
+        let server = &self.server;
+        if server.host.is_empty() {
+            problems.push(ConfigProblem::new("server.host", "must not be empty"));
+        }
+        if server.timeout_secs == 0 {
+            problems.push(ConfigProblem::new("server.timeout_secs", "must be greater than 0"));
+        }
+        if server.daemon && server.pid_file.as_ref().is_none_or(|pid_file| pid_file.as_os_str().is_empty()) {
+            problems.push(ConfigProblem::new("server.pid_file", "daemon mode needs a pid_file to record the server's process id in"));
+        }
+        if let Some(socket) = &server.unix_socket {
+            if socket.mode > 0o777 {
+                problems.push(ConfigProblem::new("server.unix_socket.mode", format!("{:o} is not a permission mode; use e.g. 0o600", socket.mode)));
+            }
+        }
+        
+        let http = &server.http;
+        for (index, origin) in http.cors_origins.iter().enumerate() {
+            if !is_cors_origin(origin) {
+                problems.push(ConfigProblem::new(
+                    format!("server.http.cors_origins[{}]", index),
+                    format!("'{}' is not an origin; use `*` or e.g. https://notes.example.com, without a path", origin),
+                ));
+            }
+        }
+        for (index, method) in http.cors_methods.iter().enumerate() {
+            if Method::from_bytes(method.as_bytes()).is_err() {
+                problems.push(ConfigProblem::new(format!("server.http.cors_methods[{}]", index), format!("'{}' is not an HTTP method", method)));
+            }
+        }
+        let header_lists = [("cors_headers", &http.cors_headers), ("cors_expose_headers", &http.cors_expose_headers)];
+        for (setting, names) in header_lists {
+            for (index, name) in names.iter().enumerate() {
+                if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                    problems.push(ConfigProblem::new(format!("server.http.{}[{}]", setting, index), format!("'{}' is not a header name", name)));
+                }
+            }
+        }
+        if HeaderValue::from_str(&http.content_security_policy).is_err() {
+            problems.push(ConfigProblem::new("server.http.content_security_policy", "must be printable ASCII on one line"));
+        }
+        if http.max_body_bytes == 0 {
+            problems.push(ConfigProblem::new("server.http.max_body_bytes", "must be greater than 0"));
+        }
+        
+        let oidc = &self.oidc;
+        if oidc.issuer.is_none() {
+            let unused = [("audience", oidc.audience.is_some()), ("jwks_url", oidc.jwks_url.is_some()), ("scope_map", !oidc.scope_map.is_empty())];
+            for (setting, _) in unused.iter().filter(|(_, set)| *set) {
+                problems.push(ConfigProblem::new(format!("oidc.{}", setting), "has no effect without oidc.issuer"));
+            }
+        } else if oidc.audience.as_deref().is_none_or(str::is_empty) {
+            problems.push(ConfigProblem::new(
+                "oidc.audience",
+                "is required with oidc.issuer; without it tokens the issuer made for its other clients would be accepted",
+            ));
+        }
+        
+        if self.telemetry.enabled && self.telemetry.endpoint.is_none() {
+            problems.push(ConfigProblem::new("telemetry.endpoint", "telemetry is enabled, but no reports are sent without an endpoint"));
+        }
+        
+        if self.vectors.storage != VectorStorage::F32 && !self.qdrant.instances.is_empty() {
+            problems.push(ConfigProblem::new("vectors.storage", "only applies to the embedded backend, but [[qdrant.instances]] sends collections to Qdrant"));
+        }
+        if self.wal.enabled && !self.qdrant.instances.is_empty() {
+            problems.push(ConfigProblem::new("wal.enabled", "only logs the embedded backend, but [[qdrant.instances]] sends collections to Qdrant"));
+        }
+        
+        let qdrant = &self.qdrant;
+        let instances = unique_names(&mut problems, "qdrant.instances", qdrant.instances.iter().map(|instance| instance.name.as_str()));
+        for (index, route) in qdrant.routes.iter().enumerate() {
+            check_pattern(&mut problems, &format!("qdrant.routes[{}].pattern", index), &route.pattern);
+            if !instances.contains(route.instance.as_str()) {
+                problems.push(ConfigProblem::new(format!("qdrant.routes[{}].instance", index), format!("no [[qdrant.instances]] is named '{}'", route.instance)));
+            }
+        }
+        if let Some(default_instance) = &qdrant.default_instance {
+            if !instances.contains(default_instance.as_str()) {
+                problems.push(ConfigProblem::new("qdrant.default_instance", format!("no [[qdrant.instances]] is named '{}'", default_instance)));
+            }
+        }
+        for (index, instance) in qdrant.instances.iter().enumerate() {
+            for (setting, reason) in network::unsupported_for_qdrant(&self.network, &instance.url) {
+                problems.push(ConfigProblem::new(setting, format!("cannot apply to qdrant.instances[{}]: {}", index, reason)));
+            }
+        }
+        
+        let providers = &self.embedding.providers;
+        unique_names(&mut problems, "embedding.providers", providers.iter().map(|provider| provider.name()));
+        if let Some(first) = providers.first() {
+            for (index, provider) in providers.iter().enumerate().skip(1) {
+                if dimension(provider) != dimension(first) {
+                    problems.push(ConfigProblem::new(
+                        format!("embedding.providers[{}].dimension", index),
+                        format!("is {} but embedding.providers[0] produces {}; fallbacks must produce the same dimension", dimension(provider), dimension(first)),
+                    ));
+                }
+            }
+        }
+        
+        unique_names(&mut problems, "federation.sources", self.federation.sources.iter().map(|source| source.name.as_str()));
+        for (source_index, source) in self.federation.sources.iter().enumerate() {
+            for (rule_index, rule) in source.rules.iter().enumerate() {
+                check_pattern(&mut problems, &format!("federation.sources[{}].rules[{}].pattern", source_index, rule_index), &rule.pattern);
+            }
+        }
+        unique_names(&mut problems, "gateway.downstreams", self.gateway.downstreams.iter().map(|downstream| downstream.name.as_str()));
+        
+        if self.attachments.bucket.is_some() && self.attachments.dir.is_some() {
+            problems.push(ConfigProblem::new("attachments.dir", "is unused when attachments.bucket is set"));
+        }
+        if !(0.0..=1.0).contains(&self.memory.forget_threshold) {
+            problems.push(ConfigProblem::new("memory.forget_threshold", "must be between 0 and 1"));
+        }
+        if !(0.0..=1.0).contains(&self.language.min_confidence) {
+            problems.push(ConfigProblem::new("language.min_confidence", "must be between 0 and 1"));
+        }
+        if self.ingest.parallelism == 0 {
+            problems.push(ConfigProblem::new("ingest.parallelism", "must be greater than 0"));
+        }
+        if self.pool.max_connections == 0 {
+            problems.push(ConfigProblem::new("pool.max_connections", "must be greater than 0"));
+        }
+        if self.wal.fsync == FsyncPolicy::Interval && self.wal.fsync_interval_ms == 0 {
+            problems.push(ConfigProblem::new("wal.fsync_interval_ms", "must be greater than 0 with fsync = \"interval\""));
+        }
+        if self.reranker.max_length == 0 {
+            problems.push(ConfigProblem::new("reranker.max_length", "must be greater than 0"));
+        }
+        if self.reranker.batch_size == 0 {
+            problems.push(ConfigProblem::new("reranker.batch_size", "must be greater than 0"));
+        }
+        if self.query_cache.enabled && self.query_cache.max_entries == 0 {
+            problems.push(ConfigProblem::new("query_cache.max_entries", "must be greater than 0 when the cache is enabled"));
+        }
+        for (collection, shards) in &self.sharding.collections {
+            let path = format!("sharding.collections.{}", collection);
+            if *shards == 0 {
+                problems.push(ConfigProblem::new(path, "must be at least 1"));
+            } else if collection.contains(SHARD_SEPARATOR) {
+                problems.push(ConfigProblem::new(path, format!("collection names must not contain '{}'", SHARD_SEPARATOR)));
+            }
+        }
+        
+        problems
+    }
+}
+
+/// Every problem with the config file `content`, with `profile` layered over
+/// it when given and in `container` mode the `P_MO_*` variables, each with its
+/// line when known.
+///
+/// After a parse error the offending line, or the whole table when the error
+/// is on its header, is blanked and the file parsed again, so one run reports
+/// every unknown key and bad value rather than only the first.
+pub fn diagnose(content: &str, profile: Option<&str>, container: bool) -> Vec<ConfigProblem> {
+    let mut lines: Vec<&str> = content.lines().collect();
+    let paths = line_paths(&lines);
+    let mut problems = Vec::new();
+    loop {
+        let text = lines.join("\n");
+        let error = match toml::from_str::<Config>(&text) {
+            Ok(config) => {
+                match resolve_layers(config, &text, profile, Some(&env_var), container) {
+                    Ok(config) => problems.extend(with_lines(config.validate(), content)),
+                    Err(unresolved) => problems.extend(with_lines(unresolved, content)),
+                }
+                return problems;
+            },
+            Err(error) => error,
+        };
+        
+        let line = error.span().map(|span| text[..span.start].matches('\n').count());
+        problems.push(ConfigProblem {
+            path: line.and_then(|line| paths.get(line).cloned().flatten()).unwrap_or_default(),
+            message: error.message().to_string(),
+            line: line.map(|line| line + 1),
+        });
+        match line {
+            Some(line) if !lines[line].trim().is_empty() && problems.len() < MAX_PARSE_PROBLEMS => blank(&mut lines, line),
+            _ => return problems,
+        }
+    }
+}
+
+/// `problems` with the lines of their settings in the file `content`
+pub fn with_lines(problems: Vec<ConfigProblem>, content: &str) -> Vec<ConfigProblem> {
+    let lines: Vec<&str> = content.lines().collect();
+    let paths = line_paths(&lines);
+    problems.into_iter()
+        .map(|problem| ConfigProblem {
+            line: locate(&paths, &problem.path),
+            ..problem
+        })
+        .collect()
+}
+
+/// Render `problems` found in the file at `path` as `file:line: ...` with the
+/// offending line quoted under each
+pub fn render(path: &Path, content: &str, problems: &[ConfigProblem]) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut rendered = Vec::new();
+    for problem in problems {
+        let location = match problem.line {
+            Some(line) => format!("{}:{}", path.display(), line),
+            None => path.display().to_string(),
+        };
+        let setting = if problem.path.is_empty() { String::new() } else { format!("{}: ", problem.path) };
+        rendered.push(format!("{}: {}{}", location, setting, problem.message));
+        if let Some(source) = problem.line.and_then(|line| lines.get(line - 1)) {
+            rendered.push(format!("    | {}", source.trim_end()));
+        }
+    }
+    rendered.join("\n")
+}
+
+fn dimension(provider: &EmbeddingProviderConfig) -> usize {
+    match provider {
+        EmbeddingProviderConfig::Local { dimension, .. } | EmbeddingProviderConfig::Remote { dimension, .. } => *dimension,
+    }
+}
+
+/// The names of the entries of `list`, reporting any given twice
+fn unique_names<'a>(problems: &mut Vec<ConfigProblem>, list: &str, names: impl Iterator<Item = &'a str>) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    for (index, name) in names.enumerate() {
+        if !seen.insert(name) {
+            problems.push(ConfigProblem::new(format!("{}[{}].name", list, index), format!("'{}' is already the name of an earlier entry", name)));
+        }
+    }
+    seen
+}
+
+fn check_pattern(problems: &mut Vec<ConfigProblem>, path: &str, pattern: &str) {
+    if let Err(e) = Regex::new(pattern) {
+        problems.push(ConfigProblem::new(path, format!("is not a valid regular expression: {}", e)));
+    }
+}
+
+/// Whether `origin` is `*` or a scheme and host, with an optional port and nothing after
+fn is_cors_origin(origin: &str) -> bool {
+    if origin == "*" {
+        return true;
+    }
+    let Some((scheme, host)) = origin.trim_end_matches('/').split_once("://") else { return false };
+    !scheme.is_empty()
+        && !host.is_empty()
+        && !host.contains(['/', '?', '#', ' '])
+        && HeaderValue::from_str(origin).is_ok()
+}
+
+/// Blank line `index`, and the rest of its table when it is a table header,
+/// keeping the line count so later errors point at the right lines
+fn blank(lines: &mut [&str], index: usize) {
+    let is_header = lines[index].trim_start().starts_with('[');
+    lines[index] = "";
+    if is_header {
+        for line in lines[index + 1..].iter_mut().take_while(|line| !line.trim_start().starts_with('[')) {
+            *line = "";
+        }
+    }
+}
+
+/// The dotted path of the table header or setting on each line, e.g.
+/// `federation.sources[1].rules[0].pattern`; `None` for other lines
+fn line_paths(lines: &[&str]) -> Vec<Option<String>> {
+    let mut table = String::new();
+    // Elements seen so far of each array of tables, keyed by its resolved path
+    let mut arrays: HashMap<String, usize> = HashMap::new();
+    let resolve = |arrays: &HashMap<String, usize>, keys: &[String]| {
+        let mut path = String::new();
+        for key in keys {
+            path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+            if let Some(count) = arrays.get(&path) {
+                path = format!("{}[{}]", path, count - 1);
+            }
+        }
+        path
+    };
+    
+    lines.iter()
+        .map(|line| {
+            let line = line.trim();
+            if let Some(header) = line.strip_prefix("[[").and_then(|rest| rest.split("]]").next()) {
+                let keys = split_keys(header);
+                let (last, parents) = keys.split_last()?;
+                let parent = resolve(&arrays, parents);
+                let array = if parent.is_empty() { last.clone() } else { format!("{}.{}", parent, last) };
+                let count = arrays.entry(array.clone()).or_insert(0);
+                *count += 1;
+                table = format!("{}[{}]", array, *count - 1);
+                Some(table.clone())
+            } else if let Some(header) = line.strip_prefix('[').and_then(|rest| rest.split(']').next()) {
+                table = resolve(&arrays, &split_keys(header));
+                Some(table.clone())
+            } else if line.starts_with('#') {
+                None
+            } else {
+                let (key, _) = line.split_once('=')?;
+                let key = split_keys(key).join(".");
+                Some(if table.is_empty() { key } else { format!("{}.{}", table, key) })
+            }
+        })
+        .collect()
+}
+
+fn split_keys(keys: &str) -> Vec<String> {
+    keys.split('.')
+        .map(|key| key.trim().trim_matches(|c| c == '"' || c == '\'').to_string())
+        .collect()
+}
+
+/// The line of the setting at `path`, or else of the closest table holding it
+fn locate(paths: &[Option<String>], path: &str) -> Option<usize> {
+    let mut path = path;
+    loop {
+        if let Some(index) = paths.iter().position(|line_path| line_path.as_deref() == Some(path)) {
+            return Some(index + 1);
+        }
+        path = &path[..path.rfind(['.', '['])?];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_diagnose_reports_every_problem() {
+        let content = "\
+[server]
+host = \"0.0.0.0\"
+prot = 9000
+daemon = true
+pid_file = \"\"
+
+[[qdrant.instances]]
+name = \"local\"
+url = \"http://localhost:6334\"
+
+[[qdrant.routes]]
+pattern = \"^docs-\"
+instance = \"cloud\"
+
+[telemtry]
+enabled = true
+";
+        let problems = diagnose(content, None, false);
+        let found: Vec<(&str, Option<usize>)> = problems.iter().map(|problem| (problem.path.as_str(), problem.line)).collect();
+        assert_eq!(found, vec![
+            ("server.prot", Some(3)),
+            ("telemtry", Some(15)),
+            ("server.pid_file", Some(5)),
+            ("qdrant.routes[0].instance", Some(13)),
+        ]);
+        assert!(problems[0].message.contains("unknown field `prot`"));
+        
+        let rendered = render(Path::new("config.toml"), content, &problems);
+        assert!(rendered.contains("config.toml:13: qdrant.routes[0].instance: no [[qdrant.instances]] is named 'cloud'\n    | instance = \"cloud\""));
+    }
+    
+    #[test]
+    fn test_line_paths_of_nested_arrays() {
+        let lines = ["[[federation.sources]]", "name = \"a\"", "[[federation.sources]]", "[[federation.sources.rules]]", "pattern = \"x\"", "[federation.sources.env]"];
+        let paths = line_paths(&lines);
+        assert_eq!(paths[4].as_deref(), Some("federation.sources[1].rules[0].pattern"));
+        assert_eq!(paths[5].as_deref(), Some("federation.sources[1].env"));
+        assert_eq!(locate(&paths, "federation.sources[0].command"), Some(1));
+        assert!(Config::default().validate().is_empty());
+    }
+    
+    #[test]
+    fn test_oidc_issuer_needs_an_audience() {
+        let mut config = Config::default();
+        config.oidc.issuer = Some("https://login.example.com".to_string());
+        let paths: Vec<String> = config.validate().into_iter().map(|problem| problem.path).collect();
+        assert_eq!(paths, ["oidc.audience"]);
+        
+        config.oidc.audience = Some("p-mo".to_string());
+        assert!(config.validate().is_empty());
+    }
+    
+    #[test]
+    fn test_cors_origins() {
+        assert!(is_cors_origin("*"));
+        assert!(is_cors_origin("https://notes.example.com"));
+        assert!(is_cors_origin("http://localhost:5173/"));
+        assert!(!is_cors_origin("notes.example.com"));
+        assert!(!is_cors_origin("https://notes.example.com/app"));
+        assert!(!is_cors_origin("https://"));
+    }
+}
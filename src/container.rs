@@ -0,0 +1,33 @@
+//! Running as the main process of a container.
+//!
+//! Selected with `--container` or `server.container = true`. Settings come
+//! from `P_MO_*` variables over an optional mounted config file, logs go to
+//! stdout as JSON lines, no PID or log file is written, and SIGTERM stops the
+//! server within [`SHUTDOWN_GRACE`] so the runtime never has to kill it.
+//! `/health` answers 503 once shutdown begins, for readiness probes.
+
+use std::io;
+use std::time::Duration;
+
+/// Longest in-flight requests may take to finish once the server is told to stop
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Wait for SIGTERM or SIGINT, returning the name of the signal received
+#[cfg(unix)]
+pub async fn terminated() -> io::Result<&'static str> {
+    use tokio::signal::unix::{signal, SignalKind};
+    
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    tokio::select! {
+        _ = terminate.recv() => Ok("SIGTERM"),
+        _ = interrupt.recv() => Ok("SIGINT"),
+    }
+}
+
+/// Wait for Ctrl-C, returning the name of the signal received
+#[cfg(not(unix))]
+pub async fn terminated() -> io::Result<&'static str> {
+    tokio::signal::ctrl_c().await?;
+    Ok("Ctrl-C")
+}
@@ -0,0 +1,347 @@
+//! Per-request context shared by every transport.
+//!
+//! A `RequestContext` records who is making a request so logging, quotas,
+//! access control and auditing all agree on the caller's identity.
+
+use axum::http::HeaderMap;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info_span, Span};
+use uuid::Uuid;
+
+use crate::api_keys::Grant;
+
+/// Name used for callers that did not identify themselves
+pub const ANONYMOUS_CLIENT: &str = "anonymous";
+
+/// How the caller's identity was established
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentitySource {
+    /// The caller did not identify itself
+    Anonymous,
+    /// Derived from an API key presented by the transport
+    ApiKey,
+    /// Self-reported through MCP `clientInfo`
+    ClientInfo,
+}
+
+/// Identity and limits of a single request
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    /// Unique id for this request, used to correlate log lines
+    pub request_id: String,
+    /// The calling client, if known
+    pub client_id: Option<String>,
+    /// How `client_id` was established
+    pub identity_source: IdentitySource,
+    /// The client session the request belongs to
+    pub session_id: Option<String>,
+    /// The namespace the request operates in
+    pub namespace: Option<String>,
+    /// When the caller stops waiting for a response
+    pub deadline: Option<Instant>,
+    /// Cancelled when the caller goes away, e.g. its connection drops
+    pub cancellation: CancellationToken,
+    /// What the credential the transport verified allows; `None` when the
+    /// transport checks none, as stdio does
+    pub grant: Option<Grant>,
+}
+
+impl Default for RequestContext {
+    fn default() -> Self {
+        Self::anonymous()
+    }
+}
+
+impl RequestContext {
+    /// Create a context for an unidentified caller
+    pub fn anonymous() -> Self {
+        Self {
+            request_id: Uuid::new_v4().to_string(),
+            client_id: None,
+            identity_source: IdentitySource::Anonymous,
+            session_id: None,
+            namespace: None,
+            deadline: None,
+            cancellation: CancellationToken::new(),
+            grant: None,
+        }
+    }
+    
+    /// Identify the caller by API key, without retaining the key itself
+    pub fn from_api_key(api_key: &str) -> Self {
+        Self {
+            client_id: Some(format!("key:{}", api_key_fingerprint(api_key))),
+            identity_source: IdentitySource::ApiKey,
+            ..Self::anonymous()
+        }
+    }
+    
+    /// Build a context from the metadata carried in an MCP request.
+    ///
+    /// Reads `clientInfo` from the params (as sent with `initialize`) or from
+    /// `params._meta`, plus optional `_meta.sessionId`, `_meta.namespace` and
+    /// a deadline as `_meta.timeoutMs` or an RFC 3339 `_meta.deadline`.
+    pub fn from_mcp_request(request: &Value) -> Self {
+        let params = request.get("params");
+        let meta = params.and_then(|p| p.get("_meta"));
+        let mut ctx = Self::anonymous();
+        
+        let client_info = params.and_then(|p| p.get("clientInfo"))
+            .or_else(|| meta.and_then(|m| m.get("clientInfo")));
+        if let Some(name) = client_info.and_then(|info| info.get("name")).and_then(|n| n.as_str()) {
+            let version = client_info.and_then(|info| info.get("version")).and_then(|v| v.as_str());
+            ctx.client_id = Some(match version {
+                Some(version) => format!("{}/{}", name, version),
+                None => name.to_string(),
+            });
+            ctx.identity_source = IdentitySource::ClientInfo;
+        }
+        
+        ctx.session_id = meta.and_then(|m| m.get("sessionId")).and_then(|s| s.as_str()).map(|s| s.to_string());
+        ctx.namespace = meta.and_then(|m| m.get("namespace")).and_then(|s| s.as_str()).map(|s| s.to_string());
+        ctx.deadline = deadline_hint(
+            meta.and_then(|m| m.get("timeoutMs")).and_then(|t| t.as_u64()),
+            meta.and_then(|m| m.get("deadline")).and_then(|d| d.as_str()),
+        );
+        ctx
+    }
+    
+    /// Build a context from HTTP headers (`x-api-key` or `Authorization: Bearer`,
+    /// `x-session-id`, `x-namespace`, and `x-request-timeout-ms` or an RFC 3339
+    /// `x-request-deadline`)
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut ctx = match api_key_from_headers(headers) {
+            Some(key) => Self::from_api_key(&key),
+            None => Self::anonymous(),
+        };
+        ctx.session_id = header_value(headers, "x-session-id");
+        ctx.namespace = header_value(headers, "x-namespace");
+        ctx.deadline = deadline_hint(
+            header_value(headers, "x-request-timeout-ms").and_then(|t| t.parse().ok()),
+            header_value(headers, "x-request-deadline").as_deref(),
+        );
+        ctx
+    }
+    
+    /// Fill in anything this context lacks from `other`, keeping existing values.
+    ///
+    /// Transport-level identity (API keys) takes precedence over self-reported
+    /// identity in the request body, and the transport's cancellation is kept.
+    pub fn merge(mut self, other: RequestContext) -> Self {
+        if self.client_id.is_none() {
+            self.client_id = other.client_id;
+            self.identity_source = other.identity_source;
+        }
+        self.session_id = self.session_id.or(other.session_id);
+        self.namespace = self.namespace.or(other.namespace);
+        self.deadline = self.deadline.or(other.deadline);
+        self.grant = self.grant.or(other.grant);
+        self
+    }
+    
+    pub fn with_client_id(mut self, client_id: &str, source: IdentitySource) -> Self {
+        self.client_id = Some(client_id.to_string());
+        self.identity_source = source;
+        self
+    }
+    
+    pub fn with_session_id(mut self, session_id: &str) -> Self {
+        self.session_id = Some(session_id.to_string());
+        self
+    }
+    
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+    
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+    
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+    
+    /// Limit the request to what `grant` allows
+    pub fn with_grant(mut self, grant: Grant) -> Self {
+        self.grant = Some(grant);
+        self
+    }
+    
+    /// Abandon the request's work once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+    
+    /// The client id, or `anonymous`
+    pub fn client_label(&self) -> &str {
+        self.client_id.as_deref().unwrap_or(ANONYMOUS_CLIENT)
+    }
+    
+    /// Time left before the deadline, if there is one
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+    
+    /// Whether the deadline has passed
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_some_and(|remaining| remaining.is_zero())
+    }
+    
+    /// Whether the caller has gone away
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+    
+    /// A span carrying the request's identity, for use as a parent of its work
+    pub fn span(&self) -> Span {
+        info_span!(
+            "request",
+            request_id = %self.request_id,
+            client_id = %self.client_label(),
+            session_id = %self.session_id.as_deref().unwrap_or(""),
+            namespace = %self.namespace.as_deref().unwrap_or("")
+        )
+    }
+}
+
+/// The deadline a caller asked for, as a timeout in milliseconds or an RFC
+/// 3339 time; when both are given the earlier wins
+fn deadline_hint(timeout_ms: Option<u64>, deadline: Option<&str>) -> Option<Instant> {
+    let now = Instant::now();
+    let from_timeout = timeout_ms.map(|ms| now + Duration::from_millis(ms));
+    let from_deadline = deadline
+        .and_then(|deadline| chrono::DateTime::parse_from_rfc3339(deadline).ok())
+        .map(|deadline| {
+            // A deadline already past leaves no time at all
+            let left = (deadline.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or(Duration::ZERO);
+            now + left
+        });
+    match (from_timeout, from_deadline) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// The API key presented in `x-api-key` or an `Authorization: Bearer` header
+pub fn api_key_from_headers(headers: &HeaderMap) -> Option<String> {
+    header_value(headers, "x-api-key")
+        .or_else(|| {
+            header_value(headers, "authorization")
+                .and_then(|auth| auth.strip_prefix("Bearer ").map(|key| key.trim().to_string()))
+        })
+        .filter(|key| !key.is_empty())
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers.get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_string())
+}
+
+/// A short, stable, non-reversible identifier for an API key
+pub fn api_key_fingerprint(api_key: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(api_key.as_bytes()));
+    digest[..12].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[test]
+    fn test_from_mcp_request_client_info() {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "method": "CallTool",
+            "params": {
+                "_meta": {
+                    "clientInfo": {"name": "cline", "version": "3.1"},
+                    "sessionId": "session-1",
+                    "namespace": "team-a"
+                }
+            }
+        });
+        
+        let ctx = RequestContext::from_mcp_request(&request);
+        assert_eq!(ctx.client_label(), "cline/3.1");
+        assert_eq!(ctx.identity_source, IdentitySource::ClientInfo);
+        assert_eq!(ctx.session_id.as_deref(), Some("session-1"));
+        assert_eq!(ctx.namespace.as_deref(), Some("team-a"));
+    }
+    
+    #[test]
+    fn test_from_mcp_request_anonymous() {
+        let ctx = RequestContext::from_mcp_request(&json!({"method": "CallTool"}));
+        assert_eq!(ctx.client_label(), ANONYMOUS_CLIENT);
+        assert_eq!(ctx.identity_source, IdentitySource::Anonymous);
+    }
+    
+    #[test]
+    fn test_from_headers_uses_api_key_fingerprint() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer secret-key".parse().unwrap());
+        headers.insert("x-session-id", "abc".parse().unwrap());
+        
+        let ctx = RequestContext::from_headers(&headers);
+        assert_eq!(ctx.identity_source, IdentitySource::ApiKey);
+        assert!(!ctx.client_label().contains("secret-key"));
+        assert_eq!(ctx.client_id, RequestContext::from_api_key("secret-key").client_id);
+        assert_eq!(ctx.session_id.as_deref(), Some("abc"));
+    }
+    
+    #[test]
+    fn test_merge_prefers_transport_identity() {
+        let transport = RequestContext::from_api_key("key");
+        let body = RequestContext::anonymous()
+            .with_client_id("cline", IdentitySource::ClientInfo)
+            .with_session_id("s1");
+        
+        let merged = transport.clone().merge(body);
+        assert_eq!(merged.client_id, transport.client_id);
+        assert_eq!(merged.identity_source, IdentitySource::ApiKey);
+        assert_eq!(merged.session_id.as_deref(), Some("s1"));
+    }
+    
+    #[test]
+    fn test_deadline() {
+        let ctx = RequestContext::anonymous().with_timeout(Duration::ZERO);
+        assert!(ctx.is_expired());
+        
+        let ctx = RequestContext::anonymous().with_timeout(Duration::from_secs(60));
+        assert!(!ctx.is_expired());
+        assert!(RequestContext::anonymous().remaining().is_none());
+    }
+    
+    #[test]
+    fn test_deadline_hints() {
+        let request = json!({"params": {"_meta": {"timeoutMs": 1500}}});
+        let remaining = RequestContext::from_mcp_request(&request).remaining().unwrap();
+        assert!(remaining <= Duration::from_millis(1500) && remaining > Duration::from_millis(1000));
+        
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-deadline", "2001-01-01T00:00:00Z".parse().unwrap());
+        headers.insert("x-request-timeout-ms", "60000".parse().unwrap());
+        assert!(RequestContext::from_headers(&headers).is_expired());
+        assert!(RequestContext::from_headers(&HeaderMap::new()).deadline.is_none());
+    }
+    
+    #[test]
+    fn test_merge_keeps_transport_cancellation() {
+        let connection = CancellationToken::new();
+        let ctx = RequestContext::anonymous()
+            .with_cancellation(connection.child_token())
+            .merge(RequestContext::anonymous());
+        assert!(!ctx.is_cancelled());
+        
+        connection.cancel();
+        assert!(ctx.is_cancelled());
+    }
+}
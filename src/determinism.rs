@@ -0,0 +1,196 @@
+//! Seeded ids and a mock clock for reproducible responses.
+//!
+//! The server normally takes ids from `Uuid::new_v4`, times from the system
+//! clock and sampling seeds from the time of day, so the same requests
+//! answer differently on every run. A [`Determinism::seeded`] source draws
+//! all three from one seeded generator and a clock that starts at a fixed
+//! instant and ticks a fixed step on every reading. Golden files of MCP
+//! responses then match across runs and platforms, as long as requests are
+//! sent one at a time.
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use uuid::{Builder, Uuid};
+
+use crate::vector_store::EntryId;
+
+/// Where a seeded clock starts: 2024-01-01T00:00:00Z, in seconds since the Unix epoch
+pub const DEFAULT_EPOCH_SECS: i64 = 1_704_067_200;
+
+/// How far a seeded clock moves on each reading
+pub const DEFAULT_TICK_MILLIS: i64 = 1000;
+
+/// A source of the current time
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system clock
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that starts at a fixed instant and moves `tick` forward on every
+/// reading, so successive readings differ and keep their order
+#[derive(Debug)]
+pub struct MockClock {
+    next: Mutex<DateTime<Utc>>,
+    tick: Duration,
+}
+
+impl MockClock {
+    pub fn new(start: DateTime<Utc>, tick: Duration) -> Self {
+        Self { next: Mutex::new(start), tick }
+    }
+    
+    /// Move the clock forward by `by`, e.g. past an entry's expiry
+    pub fn advance(&self, by: Duration) {
+        *self.next.lock().unwrap() += by;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        let mut next = self.next.lock().unwrap();
+        let now = *next;
+        *next += self.tick;
+        now
+    }
+}
+
+/// A SplitMix64 generator, the one clone sampling uses
+#[derive(Debug)]
+pub struct SeededRng(Mutex<u64>);
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self(Mutex::new(seed))
+    }
+    
+    pub fn next_u64(&self) -> u64 {
+        let mut state = self.0.lock().unwrap();
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    
+    /// A version 4 UUID made from the next 128 bits
+    pub fn uuid(&self) -> Uuid {
+        let bits = (u128::from(self.next_u64()) << 64) | u128::from(self.next_u64());
+        Builder::from_random_bytes(bits.to_be_bytes()).into_uuid()
+    }
+}
+
+/// Where the server takes its ids, timestamps and sampling seeds from
+#[derive(Clone)]
+pub struct Determinism {
+    clock: Arc<dyn Clock>,
+    /// `None` draws ids and seeds from the operating system
+    rng: Option<Arc<SeededRng>>,
+}
+
+impl Determinism {
+    /// Random ids and the system clock, as in production
+    pub fn system() -> Self {
+        Self { clock: Arc::new(SystemClock), rng: None }
+    }
+    
+    /// Ids and seeds from `seed`, and a [`MockClock`] starting at
+    /// [`DEFAULT_EPOCH_SECS`] and ticking [`DEFAULT_TICK_MILLIS`]
+    pub fn seeded(seed: u64) -> Self {
+        let start = Utc.timestamp_opt(DEFAULT_EPOCH_SECS, 0).unwrap();
+        Self {
+            clock: Arc::new(MockClock::new(start, Duration::milliseconds(DEFAULT_TICK_MILLIS))),
+            rng: Some(Arc::new(SeededRng::new(seed))),
+        }
+    }
+    
+    /// Read the time from `clock` instead
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Whether ids and seeds come from a seed rather than the operating system
+    pub fn is_seeded(&self) -> bool {
+        self.rng.is_some()
+    }
+    
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+    
+    /// A fresh version 4 UUID
+    pub fn uuid(&self) -> Uuid {
+        match &self.rng {
+            Some(rng) => rng.uuid(),
+            None => Uuid::new_v4(),
+        }
+    }
+    
+    /// A fresh entry id
+    pub fn entry_id(&self) -> EntryId {
+        EntryId::from(self.uuid())
+    }
+    
+    /// The seed to sample with: `given` when there is one, else the next from
+    /// the generator; `None` leaves the sampler to pick one itself
+    pub fn sample_seed(&self, given: Option<u64>) -> Option<u64> {
+        given.or_else(|| self.rng.as_ref().map(|rng| rng.next_u64()))
+    }
+}
+
+impl Default for Determinism {
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+impl fmt::Debug for Determinism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Determinism").field("seeded", &self.is_seeded()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let (a, b) = (Determinism::seeded(7), Determinism::seeded(7));
+        for _ in 0..3 {
+            assert_eq!(a.uuid(), b.uuid());
+            assert_eq!(a.now(), b.now());
+        }
+        assert_ne!(Determinism::seeded(8).uuid(), Determinism::seeded(7).uuid());
+        
+        let uuid = Determinism::seeded(7).uuid();
+        assert_eq!(uuid.get_version_num(), 4);
+        assert!(EntryId::parse(&uuid.to_string()).unwrap().is_uuid());
+    }
+    
+    #[test]
+    fn test_mock_clock_ticks_and_advances() {
+        let clock = Determinism::seeded(1);
+        assert_eq!(clock.now().to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert_eq!(clock.now().to_rfc3339(), "2024-01-01T00:00:01+00:00");
+        
+        let mock = Arc::new(MockClock::new(Utc.timestamp_opt(0, 0).unwrap(), Duration::zero()));
+        let clock = Determinism::seeded(1).with_clock(mock.clone());
+        mock.advance(Duration::days(1));
+        assert_eq!(clock.now(), Utc.timestamp_opt(86_400, 0).unwrap());
+        
+        assert_eq!(Determinism::system().sample_seed(None), None);
+        assert_eq!(Determinism::system().sample_seed(Some(3)), Some(3));
+        assert!(Determinism::seeded(1).sample_seed(None).is_some());
+    }
+}
@@ -0,0 +1,318 @@
+//! Periodic digests of what was added to each collection.
+//!
+//! A digest summarizes the entries created since the previous digest and is
+//! stored back into the collection as an entry tagged `digest`, so the time
+//! it covers up to survives restarts without any extra state.
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::config::DigestConfig;
+use crate::text_processing::{summarize_text, EmbeddingError, EmbeddingProvider};
+use crate::vector_store::{Document, VectorStore, VectorStoreError, CREATED_AT_KEY, EMBEDDING_PROVIDER_KEY};
+
+/// Tag carried by every digest entry
+pub const DIGEST_TAG: &str = "digest";
+
+/// Metadata key holding the creation time up to which a digest covers entries
+pub const DIGEST_UNTIL_KEY: &str = "digest_until";
+
+/// Metadata key holding how many entries a digest summarizes
+pub const DIGEST_ENTRIES_KEY: &str = "digest_entries";
+
+/// Page size used while reading a collection
+const DIGEST_PAGE_SIZE: usize = 256;
+
+/// Embedding dimension used for digest entries when no provider is configured
+const DEFAULT_DIGEST_EMBEDDING_DIM: usize = 384;
+
+#[derive(Debug, Error)]
+pub enum DigestError {
+    #[error("Vector store error: {0}")]
+    Store(#[from] VectorStoreError),
+    
+    #[error("Embedding error: {0}")]
+    Embedding(#[from] EmbeddingError),
+    
+    #[error("Webhook delivery failed: {0}")]
+    Webhook(String),
+}
+
+/// A digest of the entries added to a collection over a period
+#[derive(Debug, Clone, PartialEq)]
+pub struct Digest {
+    pub collection: String,
+    /// End of the previous digest, or `None` for a collection's first digest
+    pub since: Option<DateTime<Utc>>,
+    /// Creation time of the newest entry covered
+    pub until: DateTime<Utc>,
+    pub entry_count: usize,
+    pub title: String,
+    /// The stored digest text: a summary followed by the titles of the entries
+    pub content: String,
+}
+
+fn created_at(document: &Document) -> Option<DateTime<Utc>> {
+    document.metadata.get(CREATED_AT_KEY)
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn is_digest(document: &Document) -> bool {
+    document.tags().iter().any(|tag| tag == DIGEST_TAG)
+}
+
+/// Summarize `entries` created after `since`; `None` when there is nothing new.
+///
+/// Entries without a creation time predate digests and are never included.
+pub fn build_digest(collection: &str, entries: &[Document], since: Option<DateTime<Utc>>, max_sentences: usize) -> Option<Digest> {
+    let mut new_entries: Vec<(&Document, DateTime<Utc>)> = entries.iter()
+        .filter(|document| !is_digest(document))
+        .filter_map(|document| created_at(document).map(|created| (document, created)))
+        .filter(|(_, created)| since.is_none_or(|since| *created > since))
+        .collect();
+    if new_entries.is_empty() {
+        return None;
+    }
+    new_entries.sort_by_key(|(_, created)| *created);
+    
+    let until = new_entries.last().map(|(_, created)| *created)?;
+    let joined = new_entries.iter()
+        .map(|(document, _)| document.content.trim())
+        .collect::<Vec<&str>>()
+        .join("\n\n");
+    let summary = summarize_text(&joined, max_sentences);
+    
+    let mut content = format!("{} new entries in {}.\n\n{}\n", new_entries.len(), collection, summary);
+    for (document, _) in &new_entries {
+        content.push_str(&format!("\n- {}", document.title().unwrap_or(&document.id)));
+    }
+    
+    Some(Digest {
+        collection: collection.to_string(),
+        since,
+        until,
+        entry_count: new_entries.len(),
+        title: format!("Digest for {} up to {}", collection, until.format("%Y-%m-%d %H:%M UTC")),
+        content,
+    })
+}
+
+/// A Slack-compatible incoming webhook payload for a digest
+pub fn slack_payload(digest: &Digest) -> Value {
+    json!({
+        "text": format!("*{}*\n{}", digest.title, digest.content)
+    })
+}
+
+/// Generates digests for the configured collections
+pub struct DigestJob {
+    store: Arc<dyn VectorStore>,
+    config: DigestConfig,
+    embedding_provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    embedding_dim: usize,
+    http: reqwest::Client,
+}
+
+impl DigestJob {
+    pub fn new(store: Arc<dyn VectorStore>, config: DigestConfig) -> Self {
+        Self {
+            store,
+            config,
+            embedding_provider: None,
+            embedding_dim: DEFAULT_DIGEST_EMBEDDING_DIM,
+            http: reqwest::Client::new(),
+        }
+    }
+    
+    /// Embed digest entries with `provider`, whose vectors have `embedding_dim` dimensions
+    pub fn with_embedding_provider(mut self, provider: Arc<dyn EmbeddingProvider + Send + Sync>, embedding_dim: usize) -> Self {
+        self.embedding_provider = Some(provider);
+        self.embedding_dim = embedding_dim;
+        self
+    }
+    
+    /// Send requests with `http`, e.g. one built by `network::http_client`
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+    
+    /// Generate, store and deliver the digest of one collection
+    pub async fn run_collection(&self, collection: &str) -> Result<Option<Digest>, DigestError> {
+        let settings = self.config.collections.get(collection);
+        let max_sentences = settings
+            .and_then(|settings| settings.max_sentences)
+            .unwrap_or(self.config.max_sentences);
+        
+        let mut entries = Vec::new();
+        let mut offset = None;
+        loop {
+            let page = self.store.list_documents(collection, offset, DIGEST_PAGE_SIZE).await?;
+            entries.extend(page.documents);
+            offset = page.next_offset;
+            if offset.is_none() {
+                break;
+            }
+        }
+        
+        let since = entries.iter()
+            .filter(|document| is_digest(document))
+            .filter_map(|document| document.metadata.get(DIGEST_UNTIL_KEY))
+            .filter_map(|until| DateTime::parse_from_rfc3339(until).ok())
+            .map(|until| until.with_timezone(&Utc))
+            .max();
+        
+        let digest = match build_digest(collection, &entries, since, max_sentences) {
+            Some(digest) => digest,
+            None => return Ok(None),
+        };
+        
+        let (embedding, embedding_provider) = match &self.embedding_provider {
+            Some(provider) => provider.generate_attributed(&digest.content)?,
+            None => (vec![0.0; self.embedding_dim], None),
+        };
+        let mut document = Document {
+            id: uuid::Uuid::new_v4().to_string(),
+            content: digest.content.clone(),
+            embedding,
+            metadata: Default::default(),
+        }
+        .with_title(&digest.title)
+        .with_tags(&[DIGEST_TAG.to_string()]);
+        document.metadata.insert(CREATED_AT_KEY.to_string(), Utc::now().to_rfc3339());
+        if let Some(provider) = embedding_provider {
+            document.metadata.insert(EMBEDDING_PROVIDER_KEY.to_string(), provider);
+        }
+        document.metadata.insert(DIGEST_UNTIL_KEY.to_string(), digest.until.to_rfc3339());
+        document.metadata.insert(DIGEST_ENTRIES_KEY.to_string(), digest.entry_count.to_string());
+        self.store.insert_document(collection, document).await?;
+        
+        let webhook_url = settings
+            .and_then(|settings| settings.webhook_url.as_ref())
+            .or(self.config.webhook_url.as_ref());
+        if let Some(url) = webhook_url {
+            self.post_webhook(url, &digest).await?;
+        }
+        
+        Ok(Some(digest))
+    }
+    
+    async fn post_webhook(&self, url: &str, digest: &Digest) -> Result<(), DigestError> {
+        let response = self.http.post(url)
+            .timeout(Duration::from_secs(10))
+            .json(&slack_payload(digest))
+            .send()
+            .await
+            .map_err(|e| DigestError::Webhook(e.to_string()))?;
+        
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(DigestError::Webhook(format!("{} responded with {}", url, response.status())))
+        }
+    }
+    
+    /// Run every enabled collection once, returning each collection's outcome
+    pub async fn run_once(&self) -> Vec<(String, Result<Option<Digest>, DigestError>)> {
+        let mut collections: Vec<&String> = self.config.collections.iter()
+            .filter(|(_, settings)| settings.enabled)
+            .map(|(collection, _)| collection)
+            .collect();
+        collections.sort();
+        
+        let mut outcomes = Vec::with_capacity(collections.len());
+        for collection in collections {
+            outcomes.push((collection.clone(), self.run_collection(collection).await));
+        }
+        outcomes
+    }
+    
+    /// Run the job every `interval_secs` until the task is aborted
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(self.config.interval_secs.max(1)));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            
+            loop {
+                ticker.tick().await;
+                for (collection, outcome) in self.run_once().await {
+                    match outcome {
+                        Ok(Some(digest)) => info!(collection = %collection, entries = digest.entry_count, "Generated digest"),
+                        Ok(None) => {},
+                        Err(e) => warn!(collection = %collection, "Failed to generate digest: {}", e),
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CollectionDigestConfig;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    fn entry(id: &str, title: &str, content: &str, created_at: &str) -> Document {
+        let mut document = Document {
+            id: id.to_string(),
+            content: content.to_string(),
+            embedding: vec![0.0; 4],
+            metadata: Default::default(),
+        }
+        .with_title(title);
+        document.metadata.insert(CREATED_AT_KEY.to_string(), created_at.to_string());
+        document
+    }
+    
+    #[test]
+    fn test_build_digest_skips_old_and_untimed_entries() {
+        let since = DateTime::parse_from_rfc3339("2026-03-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let mut untimed = entry("c", "Untimed", "No timestamp.", "");
+        untimed.metadata.remove(CREATED_AT_KEY);
+        let entries = vec![
+            entry("a", "Old", "Old news.", "2026-02-01T00:00:00Z"),
+            entry("b", "Rollout", "The rollout finished.", "2026-03-02T00:00:00Z"),
+            untimed,
+        ];
+        
+        let digest = build_digest("ops", &entries, Some(since), 3).unwrap();
+        assert_eq!(digest.entry_count, 1);
+        assert!(digest.content.contains("- Rollout"));
+        assert!(!digest.content.contains("Old"));
+        assert!(slack_payload(&digest)["text"].as_str().unwrap().starts_with("*Digest for ops"));
+        
+        assert!(build_digest("ops", &entries[..1], Some(since), 3).is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_digest_covers_only_new_entries() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("ops", 4).await.unwrap();
+        store.insert_document("ops", entry("a", "Deploy", "Deployed version two.", "2026-03-01T00:00:00Z")).await.unwrap();
+        
+        let mut config = DigestConfig::default();
+        config.collections.insert("ops".to_string(), CollectionDigestConfig::default());
+        let job = DigestJob::new(store.clone(), config).with_embedding_provider(
+            Arc::new(crate::text_processing::embedding::MockEmbeddingGenerator::new(4)),
+            4,
+        );
+        
+        let first = job.run_collection("ops").await.unwrap().unwrap();
+        assert_eq!(first.entry_count, 1);
+        assert!(first.since.is_none());
+        assert!(job.run_collection("ops").await.unwrap().is_none());
+        
+        store.insert_document("ops", entry("b", "Rollback", "Rolled back version two.", "2026-03-02T00:00:00Z")).await.unwrap();
+        let outcomes = job.run_once().await;
+        let second = outcomes[0].1.as_ref().unwrap().as_ref().unwrap();
+        assert_eq!(second.entry_count, 1);
+        assert_eq!(second.since, Some(first.until));
+        assert!(second.content.contains("- Rollback"));
+    }
+}
@@ -0,0 +1,374 @@
+//! Retrieval quality evaluation.
+//!
+//! An eval set pairs queries with the ids of the entries a good search should
+//! return. Running it reports recall@k, MRR and NDCG@k, so chunking strategies
+//! and embedding models can be compared on the same questions.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EvalError {
+    #[error("Failed to read eval set: {0}")]
+    Io(#[from] std::io::Error),
+    
+    #[error("Failed to parse eval set: {0}")]
+    Parse(String),
+    
+    #[error("Eval case '{0}' has no collection and no default collection was given")]
+    MissingCollection(String),
+    
+    #[error("Search failed for '{query}': {message}")]
+    Search { query: String, message: String },
+}
+
+/// One query and the entries it should find
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalCase {
+    pub query: String,
+    #[serde(alias = "expected_entry_ids")]
+    pub expected: Vec<String>,
+    /// Collection to search, overriding the set's
+    #[serde(default)]
+    pub collection: Option<String>,
+}
+
+/// A named group of cases
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalSet {
+    /// Collection searched by cases that do not name one
+    #[serde(default)]
+    pub collection: Option<String>,
+    pub cases: Vec<EvalCase>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EvalFile {
+    Set(EvalSet),
+    Cases(Vec<EvalCase>),
+}
+
+impl EvalSet {
+    /// Parse an eval set from YAML or JSON, either a list of cases or `{collection, cases}`
+    pub fn parse(text: &str, yaml: bool) -> Result<Self, EvalError> {
+        let file: EvalFile = if yaml {
+            serde_yaml::from_str(text).map_err(|e| EvalError::Parse(e.to_string()))?
+        } else {
+            serde_json::from_str(text).map_err(|e| EvalError::Parse(e.to_string()))?
+        };
+        
+        Ok(match file {
+            EvalFile::Set(set) => set,
+            EvalFile::Cases(cases) => EvalSet { collection: None, cases },
+        })
+    }
+    
+    /// Load an eval set, treating `.yaml` and `.yml` files as YAML and anything else as JSON
+    pub fn load(path: &Path) -> Result<Self, EvalError> {
+        let text = fs::read_to_string(path)?;
+        let yaml = matches!(path.extension().and_then(|ext| ext.to_str()), Some("yaml") | Some("yml"));
+        Self::parse(&text, yaml)
+    }
+}
+
+/// Fraction of the expected entries found in the first `k` results
+pub fn recall_at_k(retrieved: &[String], expected: &[String], k: usize) -> f64 {
+    if expected.is_empty() {
+        return 0.0;
+    }
+    let top: HashSet<&String> = retrieved.iter().take(k).collect();
+    let found = expected.iter().filter(|id| top.contains(id)).count();
+    found as f64 / expected.len() as f64
+}
+
+/// One over the rank of the first relevant result, or 0 if none was retrieved
+pub fn reciprocal_rank(retrieved: &[String], expected: &[String]) -> f64 {
+    retrieved.iter()
+        .position(|id| expected.contains(id))
+        .map(|rank| 1.0 / (rank + 1) as f64)
+        .unwrap_or(0.0)
+}
+
+/// Normalized discounted cumulative gain over the first `k` results, with binary relevance
+pub fn ndcg_at_k(retrieved: &[String], expected: &[String], k: usize) -> f64 {
+    let gain = |rank: usize| 1.0 / ((rank + 2) as f64).log2();
+    
+    let dcg: f64 = retrieved.iter()
+        .take(k)
+        .enumerate()
+        .filter(|(_, id)| expected.contains(id))
+        .map(|(rank, _)| gain(rank))
+        .sum();
+    let ideal: f64 = (0..expected.len().min(k)).map(gain).sum();
+    
+    if ideal == 0.0 { 0.0 } else { dcg / ideal }
+}
+
+/// Metrics for one case
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaseReport {
+    pub query: String,
+    pub collection: String,
+    pub expected: Vec<String>,
+    pub retrieved: Vec<String>,
+    pub recall: f64,
+    pub reciprocal_rank: f64,
+    pub ndcg: f64,
+}
+
+/// Metrics for a whole eval set
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvalReport {
+    pub k: usize,
+    pub recall: f64,
+    pub mrr: f64,
+    pub ndcg: f64,
+    pub cases: Vec<CaseReport>,
+}
+
+impl EvalReport {
+    /// Render the report as a Markdown summary followed by a per-query table
+    pub fn render_markdown(&self) -> String {
+        let mut out = format!(
+            "| metric | value |\n|---|---|\n| recall@{k} | {:.4} |\n| MRR | {:.4} |\n| NDCG@{k} | {:.4} |\n| queries | {} |\n\n",
+            self.recall, self.mrr, self.ndcg, self.cases.len(), k = self.k
+        );
+        out.push_str(&format!("| query | collection | recall@{k} | RR | NDCG@{k} |\n|---|---|---|---|---|\n", k = self.k));
+        for case in &self.cases {
+            out.push_str(&format!(
+                "| {} | {} | {:.4} | {:.4} | {:.4} |\n",
+                case.query.replace('|', "\\|"), case.collection, case.recall, case.reciprocal_rank, case.ndcg
+            ));
+        }
+        out
+    }
+}
+
+/// Run every case through `search` and score the results.
+///
+/// `search` receives the query, the collection and `k`, and returns the ids of
+/// the entries found in rank order.
+pub fn evaluate<F>(set: &EvalSet, default_collection: Option<&str>, k: usize, mut search: F) -> Result<EvalReport, EvalError>
+where
+    F: FnMut(&str, &str, usize) -> Result<Vec<String>, String>,
+{
+    let mut cases = Vec::with_capacity(set.cases.len());
+    for case in &set.cases {
+        let collection = case.collection.as_deref()
+            .or(set.collection.as_deref())
+            .or(default_collection)
+            .ok_or_else(|| EvalError::MissingCollection(case.query.clone()))?;
+        
+        let retrieved = search(&case.query, collection, k).map_err(|message| EvalError::Search {
+            query: case.query.clone(),
+            message,
+        })?;
+        
+        cases.push(CaseReport {
+            query: case.query.clone(),
+            collection: collection.to_string(),
+            expected: case.expected.clone(),
+            recall: recall_at_k(&retrieved, &case.expected, k),
+            reciprocal_rank: reciprocal_rank(&retrieved, &case.expected),
+            ndcg: ndcg_at_k(&retrieved, &case.expected, k),
+            retrieved,
+        });
+    }
+    
+    let mean = |metric: fn(&CaseReport) -> f64| {
+        if cases.is_empty() { 0.0 } else { cases.iter().map(metric).sum::<f64>() / cases.len() as f64 }
+    };
+    
+    Ok(EvalReport {
+        k,
+        recall: mean(|case| case.recall),
+        mrr: mean(|case| case.reciprocal_rank),
+        ndcg: mean(|case| case.ndcg),
+        cases,
+    })
+}
+
+/// How one query fared under two configurations
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaseDiff {
+    pub query: String,
+    pub recall_delta: f64,
+    pub reciprocal_rank_delta: f64,
+    pub ndcg_delta: f64,
+    /// Expected entries only the second configuration retrieved
+    pub gained: Vec<String>,
+    /// Expected entries only the first configuration retrieved
+    pub lost: Vec<String>,
+}
+
+/// Two reports of the same eval set and the differences between them
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comparison {
+    pub a_name: String,
+    pub b_name: String,
+    pub a: EvalReport,
+    pub b: EvalReport,
+    pub recall_delta: f64,
+    pub mrr_delta: f64,
+    pub ndcg_delta: f64,
+    pub cases: Vec<CaseDiff>,
+}
+
+/// Compare two reports of the same eval set; deltas are `b - a`
+pub fn compare_reports(a_name: &str, a: EvalReport, b_name: &str, b: EvalReport) -> Comparison {
+    let found = |case: &CaseReport| -> HashSet<String> {
+        case.retrieved.iter()
+            .take(a.k)
+            .filter(|id| case.expected.contains(id))
+            .cloned()
+            .collect()
+    };
+    
+    let cases = a.cases.iter()
+        .zip(&b.cases)
+        .map(|(case_a, case_b)| {
+            let (found_a, found_b) = (found(case_a), found(case_b));
+            let mut gained: Vec<String> = found_b.difference(&found_a).cloned().collect();
+            let mut lost: Vec<String> = found_a.difference(&found_b).cloned().collect();
+            gained.sort();
+            lost.sort();
+            
+            CaseDiff {
+                query: case_a.query.clone(),
+                recall_delta: case_b.recall - case_a.recall,
+                reciprocal_rank_delta: case_b.reciprocal_rank - case_a.reciprocal_rank,
+                ndcg_delta: case_b.ndcg - case_a.ndcg,
+                gained,
+                lost,
+            }
+        })
+        .collect();
+    
+    Comparison {
+        a_name: a_name.to_string(),
+        b_name: b_name.to_string(),
+        recall_delta: b.recall - a.recall,
+        mrr_delta: b.mrr - a.mrr,
+        ndcg_delta: b.ndcg - a.ndcg,
+        a,
+        b,
+        cases,
+    }
+}
+
+impl Comparison {
+    /// Render aggregate deltas, then one row per query whose results changed
+    pub fn render_markdown(&self) -> String {
+        let k = self.a.k;
+        let mut out = format!("| metric | {} | {} | delta |\n|---|---|---|---|\n", self.a_name, self.b_name);
+        for (metric, a, b, delta) in [
+            (format!("recall@{}", k), self.a.recall, self.b.recall, self.recall_delta),
+            ("MRR".to_string(), self.a.mrr, self.b.mrr, self.mrr_delta),
+            (format!("NDCG@{}", k), self.a.ndcg, self.b.ndcg, self.ndcg_delta),
+        ] {
+            out.push_str(&format!("| {} | {:.4} | {:.4} | {:+.4} |\n", metric, a, b, delta));
+        }
+        
+        let changed: Vec<&CaseDiff> = self.cases.iter()
+            .filter(|case| case.recall_delta != 0.0 || case.reciprocal_rank_delta != 0.0 || case.ndcg_delta != 0.0)
+            .collect();
+        out.push_str(&format!("\n{} of {} queries changed\n", changed.len(), self.cases.len()));
+        if !changed.is_empty() {
+            out.push_str(&format!("\n| query | recall@{k} | RR | NDCG@{k} | gained | lost |\n|---|---|---|---|---|---|\n", k = k));
+            for case in changed {
+                out.push_str(&format!(
+                    "| {} | {:+.4} | {:+.4} | {:+.4} | {} | {} |\n",
+                    case.query.replace('|', "\\|"),
+                    case.recall_delta,
+                    case.reciprocal_rank_delta,
+                    case.ndcg_delta,
+                    case.gained.join(", "),
+                    case.lost.join(", ")
+                ));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+    
+    #[test]
+    fn test_metrics() {
+        let retrieved = ids(&["x", "a", "y", "b"]);
+        let expected = ids(&["a", "b"]);
+        
+        assert_eq!(recall_at_k(&retrieved, &expected, 2), 0.5);
+        assert_eq!(recall_at_k(&retrieved, &expected, 4), 1.0);
+        assert_eq!(reciprocal_rank(&retrieved, &expected), 0.5);
+        assert_eq!(ndcg_at_k(&ids(&["a", "b"]), &expected, 2), 1.0);
+        assert!(ndcg_at_k(&retrieved, &expected, 4) < 1.0);
+        assert_eq!(reciprocal_rank(&ids(&["x"]), &expected), 0.0);
+    }
+    
+    #[test]
+    fn test_parse_formats() {
+        let yaml = "collection: docs\ncases:\n  - query: rotate keys\n    expected: [a]\n";
+        let set = EvalSet::parse(yaml, true).unwrap();
+        assert_eq!(set.collection.as_deref(), Some("docs"));
+        assert_eq!(set.cases[0].expected, ids(&["a"]));
+        
+        let json = r#"[{"query": "rotate keys", "expected_entry_ids": ["a"], "collection": "ops"}]"#;
+        let set = EvalSet::parse(json, false).unwrap();
+        assert_eq!(set.cases[0].collection.as_deref(), Some("ops"));
+    }
+    
+    #[test]
+    fn test_evaluate() {
+        let set = EvalSet {
+            collection: None,
+            cases: vec![
+                EvalCase { query: "one".to_string(), expected: ids(&["a"]), collection: None },
+                EvalCase { query: "two".to_string(), expected: ids(&["b"]), collection: None },
+            ],
+        };
+        
+        let report = evaluate(&set, Some("docs"), 2, |query, collection, _| {
+            assert_eq!(collection, "docs");
+            Ok(if query == "one" { ids(&["a", "c"]) } else { ids(&["c", "d"]) })
+        }).unwrap();
+        
+        assert_eq!(report.recall, 0.5);
+        assert_eq!(report.mrr, 0.5);
+        assert!(report.render_markdown().contains("| recall@2 | 0.5000 |"));
+        
+        assert!(matches!(evaluate(&set, None, 2, |_, _, _| Ok(Vec::new())), Err(EvalError::MissingCollection(_))));
+    }
+    
+    #[test]
+    fn test_compare_reports() {
+        let set = EvalSet {
+            collection: Some("docs".to_string()),
+            cases: vec![
+                EvalCase { query: "one".to_string(), expected: ids(&["a"]), collection: None },
+                EvalCase { query: "two".to_string(), expected: ids(&["b"]), collection: None },
+            ],
+        };
+        let baseline = evaluate(&set, None, 2, |query, _, _| Ok(if query == "one" { ids(&["a"]) } else { ids(&["c"]) })).unwrap();
+        let candidate = evaluate(&set, None, 2, |_, _, _| Ok(ids(&["c", "b"]))).unwrap();
+        
+        let comparison = compare_reports("baseline", baseline, "candidate", candidate);
+        assert_eq!(comparison.recall_delta, 0.0);
+        assert_eq!(comparison.cases[0].lost, ids(&["a"]));
+        assert_eq!(comparison.cases[1].gained, ids(&["b"]));
+        
+        let markdown = comparison.render_markdown();
+        assert!(markdown.contains("| MRR | 0.5000 | 0.2500 | -0.2500 |"));
+        assert!(markdown.contains("2 of 2 queries changed"));
+    }
+}
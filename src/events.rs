@@ -0,0 +1,267 @@
+//! Change events for knowledge entries and collections.
+//!
+//! A [`ChangeFeed`] keeps the most recent changes in memory, each with a
+//! cursor. Consumers read the changes after the last cursor they saw and may
+//! wait for new ones to arrive. Cursors carry the epoch of the feed that
+//! issued them, so after a restart (or once a cursor's events have been
+//! dropped) the feed reports a reset and the consumer knows to resync.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::watch;
+
+use crate::config::ChangesConfig;
+
+/// Changes kept by a feed when none is given
+pub const DEFAULT_FEED_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Invalid change cursor '{0}'")]
+pub struct InvalidCursor(pub String);
+
+/// What happened to an entry or collection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// An entry was inserted or replaced
+    Upserted,
+    Deleted,
+    /// Metadata of the entries matching a filter was patched
+    MetadataPatched,
+    CollectionCreated,
+    CollectionDeleted,
+}
+
+/// A position in a change feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangeCursor {
+    epoch: u64,
+    sequence: u64,
+}
+
+impl fmt::Display for ChangeCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:x}-{}", self.epoch, self.sequence)
+    }
+}
+
+impl FromStr for ChangeCursor {
+    type Err = InvalidCursor;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidCursor(s.to_string());
+        let (epoch, sequence) = s.split_once('-').ok_or_else(invalid)?;
+        Ok(Self {
+            epoch: u64::from_str_radix(epoch, 16).map_err(|_| invalid())?,
+            sequence: sequence.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// One change
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangeEvent {
+    /// Resume after this change by passing it as `since`
+    pub cursor: String,
+    #[serde(skip)]
+    sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    pub kind: ChangeKind,
+    pub collection: String,
+    /// The entry that changed; `None` for collection and filter-wide changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+/// Changes after a cursor
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangePage {
+    pub events: Vec<ChangeEvent>,
+    /// Where the next read should start
+    pub cursor: String,
+    /// The cursor read from was issued by an earlier run or its changes are
+    /// no longer kept; `events` starts from the oldest change kept, and
+    /// anything before it must be resynced
+    pub reset: bool,
+}
+
+struct FeedState {
+    events: VecDeque<ChangeEvent>,
+    next_sequence: u64,
+}
+
+/// Recent changes, in the order they were made
+pub struct ChangeFeed {
+    epoch: u64,
+    capacity: usize,
+    state: Mutex<FeedState>,
+    latest: watch::Sender<u64>,
+}
+
+impl fmt::Debug for ChangeFeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangeFeed")
+            .field("epoch", &self.epoch)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl Default for ChangeFeed {
+    fn default() -> Self {
+        Self::new(DEFAULT_FEED_CAPACITY)
+    }
+}
+
+impl ChangeFeed {
+    /// Create a feed keeping the latest `capacity` changes
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            epoch: Utc::now().timestamp_millis() as u64,
+            capacity: capacity.max(1),
+            state: Mutex::new(FeedState { events: VecDeque::new(), next_sequence: 1 }),
+            latest: watch::channel(0).0,
+        }
+    }
+    
+    /// The feed `config` asks for; `None` when disabled
+    pub fn from_config(config: &ChangesConfig) -> Option<Self> {
+        config.enabled.then(|| Self::new(config.capacity))
+    }
+
+    fn cursor(&self, sequence: u64) -> ChangeCursor {
+        ChangeCursor { epoch: self.epoch, sequence }
+    }
+    
+    /// Record a change and wake any waiting readers
+    pub fn record(&self, kind: ChangeKind, collection: &str, id: Option<&str>) {
+        let mut state = self.state.lock().unwrap();
+        let sequence = state.next_sequence;
+        state.next_sequence += 1;
+        state.events.push_back(ChangeEvent {
+            cursor: self.cursor(sequence).to_string(),
+            sequence,
+            timestamp: Utc::now(),
+            kind,
+            collection: collection.to_string(),
+            id: id.map(str::to_string),
+        });
+        if state.events.len() > self.capacity {
+            state.events.pop_front();
+        }
+        drop(state);
+        self.latest.send_replace(sequence);
+    }
+    
+    /// The cursor after the latest change, for readers only interested in what comes next
+    pub fn head(&self) -> ChangeCursor {
+        self.cursor(self.state.lock().unwrap().next_sequence - 1)
+    }
+    
+    /// Up to `limit` changes after `since`, or from the oldest change kept without one
+    pub fn since(&self, since: Option<ChangeCursor>, limit: usize) -> ChangePage {
+        let state = self.state.lock().unwrap();
+        let oldest = state.events.front().map_or(state.next_sequence, |event| event.sequence);
+        let (after, reset) = match since {
+            None => (0, false),
+            Some(cursor) if cursor.epoch != self.epoch || cursor.sequence >= state.next_sequence => (0, true),
+            // Changes between the cursor and the oldest one kept were dropped
+            Some(cursor) if cursor.sequence + 1 < oldest => (0, true),
+            Some(cursor) => (cursor.sequence, false),
+        };
+        
+        let events: Vec<ChangeEvent> = state.events.iter()
+            .filter(|event| event.sequence > after)
+            .take(limit)
+            .cloned()
+            .collect();
+        let last = events.last().map_or(after.max(oldest.saturating_sub(1)), |event| event.sequence);
+        ChangePage { events, cursor: self.cursor(last).to_string(), reset }
+    }
+    
+    /// Like [`since`](Self::since), but wait up to `timeout` for a change when there is none yet
+    pub async fn wait_since(&self, since: Option<ChangeCursor>, limit: usize, timeout: Duration) -> ChangePage {
+        let mut latest = self.latest.subscribe();
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            latest.borrow_and_update();
+            let page = self.since(since, limit);
+            if !page.events.is_empty() || page.reset {
+                return page;
+            }
+            match tokio::time::timeout_at(deadline, latest.changed()).await {
+                Ok(Ok(())) => continue,
+                _ => return page,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    
+    #[test]
+    fn test_reads_resume_from_a_cursor() {
+        let feed = ChangeFeed::new(10);
+        feed.record(ChangeKind::CollectionCreated, "docs", None);
+        feed.record(ChangeKind::Upserted, "docs", Some("a"));
+        
+        let first = feed.since(None, 1);
+        assert_eq!(first.events.len(), 1);
+        assert_eq!(first.events[0].kind, ChangeKind::CollectionCreated);
+        
+        let cursor: ChangeCursor = first.cursor.parse().unwrap();
+        let second = feed.since(Some(cursor), 10);
+        assert_eq!(second.events.len(), 1);
+        assert_eq!(second.events[0].id.as_deref(), Some("a"));
+        assert!(!second.reset);
+        
+        let caught_up = feed.since(Some(second.cursor.parse().unwrap()), 10);
+        assert!(caught_up.events.is_empty());
+        assert_eq!(caught_up.cursor, second.cursor);
+        assert_eq!(feed.head().to_string(), second.cursor);
+    }
+    
+    #[test]
+    fn test_stale_cursors_reset() {
+        let feed = ChangeFeed::new(2);
+        let start = feed.head();
+        for id in ["a", "b", "c"] {
+            feed.record(ChangeKind::Upserted, "docs", Some(id));
+        }
+        
+        let page = feed.since(Some(start), 10);
+        assert!(page.reset);
+        assert_eq!(page.events.iter().map(|event| event.id.as_deref().unwrap()).collect::<Vec<_>>(), vec!["b", "c"]);
+        
+        let other_run = ChangeCursor { epoch: feed.epoch + 1, sequence: 1 };
+        assert!(feed.since(Some(other_run), 10).reset);
+        assert_eq!("nonsense".parse::<ChangeCursor>(), Err(InvalidCursor("nonsense".to_string())));
+    }
+    
+    #[tokio::test]
+    async fn test_wait_returns_when_a_change_arrives() {
+        let feed = Arc::new(ChangeFeed::new(10));
+        let head = feed.head();
+        
+        let empty = feed.wait_since(Some(head), 10, Duration::from_millis(20)).await;
+        assert!(empty.events.is_empty());
+        
+        let writer = feed.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            writer.record(ChangeKind::Deleted, "docs", Some("a"));
+        });
+        let page = feed.wait_since(Some(head), 10, Duration::from_secs(5)).await;
+        assert_eq!(page.events.len(), 1);
+        assert_eq!(page.events[0].kind, ChangeKind::Deleted);
+    }
+}
@@ -0,0 +1,189 @@
+//! A minimal MCP client speaking JSON-RPC over a child process's stdio.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+use super::{FederationError, Resource, ResourceContent, ResourceSource};
+
+/// MCP protocol revision announced during initialization
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// How long to wait for any single response
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The result of response `line` if it answers request `id`
+///
+/// Returns `None` for notifications, server requests and other responses.
+pub fn response_for(line: &str, id: u64) -> Option<Result<Value, FederationError>> {
+    let message: Value = serde_json::from_str(line).ok()?;
+    if message.get("id").and_then(Value::as_u64) != Some(id) || message.get("method").is_some() {
+        return None;
+    }
+    
+    Some(match message.get("error") {
+        Some(error) => Err(FederationError::Rpc(
+            error["message"].as_str().unwrap_or("unknown error").to_string(),
+        )),
+        None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+    })
+}
+
+/// A connection to an MCP server that requests can be sent over
+#[async_trait]
+pub trait McpTransport: Send {
+    /// Send a request and wait for its result
+    async fn call(&mut self, method: &str, params: Value) -> Result<Value, FederationError>;
+}
+
+/// An MCP server running as a child process
+pub struct StdioMcpClient {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    next_id: u64,
+}
+
+impl StdioMcpClient {
+    /// Start `command` and complete the MCP initialization handshake
+    pub async fn spawn(command: &str, args: &[String], env: &HashMap<String, String>) -> Result<Self, FederationError> {
+        let mut child = Command::new(command)
+            .args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| FederationError::Spawn(format!("{}: {}", command, e)))?;
+        
+        let stdin = child.stdin.take().ok_or_else(|| FederationError::Spawn("stdin unavailable".to_string()))?;
+        let stdout = child.stdout.take().ok_or_else(|| FederationError::Spawn("stdout unavailable".to_string()))?;
+        let mut client = Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+            next_id: 1,
+        };
+        
+        client.request("initialize", json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "p-mo", "version": env!("CARGO_PKG_VERSION") },
+        })).await?;
+        client.send(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await?;
+        
+        Ok(client)
+    }
+    
+    async fn send(&mut self, message: &Value) -> Result<(), FederationError> {
+        let mut line = message.to_string();
+        line.push('\n');
+        self.stdin.write_all(line.as_bytes()).await?;
+        self.stdin.flush().await?;
+        Ok(())
+    }
+    
+    /// Send a request and wait for its response, skipping unrelated messages
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value, FederationError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })).await?;
+        
+        loop {
+            let line = tokio::time::timeout(RESPONSE_TIMEOUT, self.stdout.next_line())
+                .await
+                .map_err(|_| FederationError::Rpc(format!("Timed out waiting for {}", method)))??;
+            match line {
+                Some(line) => {
+                    if let Some(result) = response_for(&line, id) {
+                        return result;
+                    }
+                },
+                None => return Err(FederationError::Rpc("Server closed its output".to_string())),
+            }
+        }
+    }
+    
+    /// Stop the server process
+    pub async fn close(mut self) {
+        let _ = self.child.kill().await;
+    }
+}
+
+#[async_trait]
+impl McpTransport for StdioMcpClient {
+    async fn call(&mut self, method: &str, params: Value) -> Result<Value, FederationError> {
+        self.request(method, params).await
+    }
+}
+
+#[async_trait]
+impl ResourceSource for StdioMcpClient {
+    async fn list_resources(&mut self) -> Result<Vec<Resource>, FederationError> {
+        let mut resources = Vec::new();
+        let mut cursor: Option<String> = None;
+        
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request("resources/list", params).await?;
+            
+            for resource in result["resources"].as_array().into_iter().flatten() {
+                if let Some(uri) = resource["uri"].as_str() {
+                    resources.push(Resource {
+                        uri: uri.to_string(),
+                        name: resource["name"].as_str().map(|name| name.to_string()),
+                        mime_type: resource["mimeType"].as_str().map(|mime| mime.to_string()),
+                    });
+                }
+            }
+            
+            match result["nextCursor"].as_str() {
+                Some(next) if !next.is_empty() => cursor = Some(next.to_string()),
+                _ => break,
+            }
+        }
+        
+        Ok(resources)
+    }
+    
+    async fn read_resource(&mut self, uri: &str) -> Result<Vec<ResourceContent>, FederationError> {
+        let result = self.request("resources/read", json!({ "uri": uri })).await?;
+        
+        Ok(result["contents"].as_array()
+            .into_iter()
+            .flatten()
+            .map(|content| ResourceContent {
+                mime_type: content["mimeType"].as_str().map(|mime| mime.to_string()),
+                text: content["text"].as_str().map(|text| text.to_string()),
+                blob: content["blob"].as_str().map(|blob| blob.to_string()),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_response_for() {
+        let ok = r#"{"jsonrpc":"2.0","id":2,"result":{"resources":[]}}"#;
+        assert_eq!(response_for(ok, 2).unwrap().unwrap(), json!({"resources": []}));
+        assert!(response_for(ok, 3).is_none());
+        
+        let error = r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"Method not found"}}"#;
+        assert!(matches!(response_for(error, 2), Some(Err(FederationError::Rpc(message))) if message == "Method not found"));
+        
+        let notification = r#"{"jsonrpc":"2.0","method":"notifications/message","params":{}}"#;
+        assert!(response_for(notification, 2).is_none());
+        assert!(response_for("not json", 2).is_none());
+    }
+}
@@ -0,0 +1,398 @@
+//! Federation: pulling documents from other MCP servers.
+//!
+//! Each configured source is an MCP server started as a child process. Its
+//! resources are listed, read and stored in collections chosen by the
+//! source's mapping rules. Entries keep a hash of their content so a later
+//! sync only rewrites resources that changed.
+
+pub mod client;
+
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use chrono::Utc;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::config::{FederationConfig, FederationSourceConfig, MappingRule};
+use crate::feeds::html_to_text;
+use crate::text_processing::{EmbeddingError, EmbeddingProvider};
+use crate::vector_store::{Document, VectorStore, VectorStoreError, CREATED_AT_KEY, EMBEDDING_PROVIDER_KEY, SOURCE_KEY};
+
+pub use client::{McpTransport, StdioMcpClient};
+
+/// Metadata key holding the name of the federation source an entry came from
+pub const FEDERATION_SOURCE_KEY: &str = "federation_source";
+
+/// Metadata key holding the hex SHA-256 of a federated entry's content
+pub const RESOURCE_HASH_KEY: &str = "resource_hash";
+
+/// Embedding dimension used for resources when no provider is configured
+const DEFAULT_FEDERATION_EMBEDDING_DIM: usize = 384;
+
+#[derive(Debug, Error)]
+pub enum FederationError {
+    #[error("Failed to start MCP server {0}")]
+    Spawn(String),
+    
+    #[error("MCP error: {0}")]
+    Rpc(String),
+    
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    
+    #[error("Invalid mapping rule '{pattern}': {message}")]
+    Rule { pattern: String, message: String },
+    
+    #[error("Vector store error: {0}")]
+    Store(#[from] VectorStoreError),
+    
+    #[error("Embedding error: {0}")]
+    Embedding(#[from] EmbeddingError),
+}
+
+/// A resource advertised by an MCP server
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resource {
+    pub uri: String,
+    pub name: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+/// One part of a read resource: either text or base64 data
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceContent {
+    pub mime_type: Option<String>,
+    pub text: Option<String>,
+    pub blob: Option<String>,
+}
+
+/// Something that can list and read MCP resources
+#[async_trait]
+pub trait ResourceSource: Send {
+    async fn list_resources(&mut self) -> Result<Vec<Resource>, FederationError>;
+    
+    async fn read_resource(&mut self, uri: &str) -> Result<Vec<ResourceContent>, FederationError>;
+}
+
+/// Plain text of a resource's contents, or `None` if none of it is text
+pub fn resource_text(contents: &[ResourceContent]) -> Option<String> {
+    let parts: Vec<String> = contents.iter()
+        .filter_map(|content| {
+            let mime = content.mime_type.as_deref().unwrap_or("text/plain");
+            let text = match (&content.text, &content.blob) {
+                (Some(text), _) => text.clone(),
+                (None, Some(blob)) if is_textual(mime) => String::from_utf8(BASE64.decode(blob).ok()?).ok()?,
+                _ => return None,
+            };
+            Some(if mime.starts_with("text/html") { html_to_text(&text) } else { text })
+        })
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+        .collect();
+    
+    (!parts.is_empty()).then(|| parts.join("\n\n"))
+}
+
+fn is_textual(mime: &str) -> bool {
+    mime.starts_with("text/") || mime.ends_with("json") || mime.ends_with("xml") || mime.ends_with("yaml")
+}
+
+/// Where a resource goes, as decided by a source's mapping rules
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub collection: String,
+    pub tags: Vec<String>,
+}
+
+/// Mapping rules compiled for matching
+pub struct CompiledRules {
+    default_collection: String,
+    rules: Vec<(Regex, MappingRule)>,
+}
+
+impl CompiledRules {
+    pub fn new(source: &FederationSourceConfig) -> Result<Self, FederationError> {
+        let rules = source.rules.iter()
+            .map(|rule| {
+                Regex::new(&rule.pattern)
+                    .map(|regex| (regex, rule.clone()))
+                    .map_err(|e| FederationError::Rule { pattern: rule.pattern.clone(), message: e.to_string() })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        
+        Ok(Self { default_collection: source.collection.clone(), rules })
+    }
+    
+    /// The mapping of the first rule matching `uri`; `None` if that rule skips it
+    ///
+    /// Resources no rule matches go to the source's collection untagged.
+    pub fn map(&self, uri: &str) -> Option<Mapping> {
+        match self.rules.iter().find(|(regex, _)| regex.is_match(uri)) {
+            Some((_, rule)) if rule.skip => None,
+            Some((_, rule)) => Some(Mapping {
+                collection: rule.collection.clone().unwrap_or_else(|| self.default_collection.clone()),
+                tags: rule.tags.clone(),
+            }),
+            None => Some(Mapping { collection: self.default_collection.clone(), tags: Vec::new() }),
+        }
+    }
+}
+
+/// Id under which a resource is stored, stable across syncs
+pub fn resource_document_id(source: &str, uri: &str) -> String {
+    let digest = Sha256::digest(format!("{}\n{}", source, uri).as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Uuid::from_bytes(bytes).to_string()
+}
+
+fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Outcome of syncing one source
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FederationReport {
+    pub ingested: usize,
+    /// Resources whose content matches the stored entry
+    pub unchanged: usize,
+    /// Resources skipped by a rule or holding no text
+    pub skipped: usize,
+    /// Resources that could not be read, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+/// Syncs the configured federation sources into collections
+pub struct Federator {
+    store: Arc<dyn VectorStore>,
+    config: FederationConfig,
+    embedding_provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    embedding_dim: usize,
+}
+
+impl Federator {
+    pub fn new(store: Arc<dyn VectorStore>, config: FederationConfig) -> Self {
+        Self {
+            store,
+            config,
+            embedding_provider: None,
+            embedding_dim: DEFAULT_FEDERATION_EMBEDDING_DIM,
+        }
+    }
+    
+    /// Embed resources with `provider`, whose vectors have `embedding_dim` dimensions
+    pub fn with_embedding_provider(mut self, provider: Arc<dyn EmbeddingProvider + Send + Sync>, embedding_dim: usize) -> Self {
+        self.embedding_provider = Some(provider);
+        self.embedding_dim = embedding_dim;
+        self
+    }
+    
+    async fn ensure_collection(&self, collection: &str) -> Result<(), FederationError> {
+        if !self.store.list_collections().await?.iter().any(|name| name == collection) {
+            self.store.create_collection(collection, self.embedding_dim).await?;
+        }
+        Ok(())
+    }
+    
+    /// Ingest the new and changed resources of `resources`
+    pub async fn sync_resources(
+        &self,
+        source: &FederationSourceConfig,
+        resources: &mut dyn ResourceSource,
+    ) -> Result<FederationReport, FederationError> {
+        let rules = CompiledRules::new(source)?;
+        let mut report = FederationReport::default();
+        
+        for resource in resources.list_resources().await? {
+            let Some(mapping) = rules.map(&resource.uri) else {
+                report.skipped += 1;
+                continue;
+            };
+            let contents = match resources.read_resource(&resource.uri).await {
+                Ok(contents) => contents,
+                Err(e) => {
+                    report.failed.push((resource.uri.clone(), e.to_string()));
+                    continue;
+                },
+            };
+            let Some(content) = resource_text(&contents) else {
+                report.skipped += 1;
+                continue;
+            };
+            
+            self.ensure_collection(&mapping.collection).await?;
+            let id = resource_document_id(&source.name, &resource.uri);
+            let hash = content_hash(&content);
+            let stored = self.store.get_document(&mapping.collection, &id).await?;
+            if stored.is_some_and(|document| document.metadata.get(RESOURCE_HASH_KEY) == Some(&hash)) {
+                report.unchanged += 1;
+                continue;
+            }
+            
+            let (embedding, embedding_provider) = match &self.embedding_provider {
+                Some(provider) => provider.generate_attributed(&content)?,
+                None => (vec![0.0; self.embedding_dim], None),
+            };
+            let title = resource.name.clone()
+                .unwrap_or_else(|| resource.uri.rsplit('/').next().unwrap_or(&resource.uri).to_string());
+            let mut document = Document {
+                id,
+                content,
+                embedding,
+                metadata: Default::default(),
+            }
+            .with_title(&title)
+            .with_tags(&mapping.tags);
+            document.metadata.insert(CREATED_AT_KEY.to_string(), Utc::now().to_rfc3339());
+            if let Some(provider) = embedding_provider {
+                document.metadata.insert(EMBEDDING_PROVIDER_KEY.to_string(), provider);
+            }
+            document.metadata.insert(SOURCE_KEY.to_string(), resource.uri.clone());
+            document.metadata.insert(FEDERATION_SOURCE_KEY.to_string(), source.name.clone());
+            document.metadata.insert(RESOURCE_HASH_KEY.to_string(), hash);
+            
+            self.store.insert_document(&mapping.collection, document).await?;
+            report.ingested += 1;
+        }
+        
+        Ok(report)
+    }
+    
+    /// Start a source's MCP server, sync it and stop the server
+    pub async fn sync_source(&self, source: &FederationSourceConfig) -> Result<FederationReport, FederationError> {
+        let mut client = StdioMcpClient::spawn(&source.command, &source.args, &source.env).await?;
+        let report = self.sync_resources(source, &mut client).await;
+        client.close().await;
+        report
+    }
+    
+    /// Sync every configured source once, returning each source's outcome
+    pub async fn sync_all(&self) -> Vec<(String, Result<FederationReport, FederationError>)> {
+        let mut outcomes = Vec::with_capacity(self.config.sources.len());
+        for source in &self.config.sources {
+            outcomes.push((source.name.clone(), self.sync_source(source).await));
+        }
+        outcomes
+    }
+    
+    /// Sync each source on its own schedule until the task is aborted
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        let federator = Arc::new(self);
+        tokio::spawn(async move {
+            // Dropping the set when this task is aborted aborts every source's loop
+            let mut loops = tokio::task::JoinSet::new();
+            for index in 0..federator.config.sources.len() {
+                let federator = federator.clone();
+                loops.spawn(async move {
+                    let source = &federator.config.sources[index];
+                    let mut ticker = tokio::time::interval(Duration::from_secs(source.interval_secs.max(1)));
+                    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                    
+                    loop {
+                        ticker.tick().await;
+                        match federator.sync_source(source).await {
+                            Ok(report) if report.ingested > 0 => info!(source = %source.name, ingested = report.ingested, "Ingested federated resources"),
+                            Ok(_) => {},
+                            Err(e) => warn!(source = %source.name, "Failed to sync federation source: {}", e),
+                        }
+                    }
+                });
+            }
+            while loops.join_next().await.is_some() {}
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    use std::collections::HashMap;
+    
+    struct FakeServer {
+        resources: HashMap<String, String>,
+    }
+    
+    #[async_trait]
+    impl ResourceSource for FakeServer {
+        async fn list_resources(&mut self) -> Result<Vec<Resource>, FederationError> {
+            let mut uris: Vec<&String> = self.resources.keys().collect();
+            uris.sort();
+            Ok(uris.into_iter()
+                .map(|uri| Resource { uri: uri.clone(), name: None, mime_type: Some("text/markdown".to_string()) })
+                .collect())
+        }
+        
+        async fn read_resource(&mut self, uri: &str) -> Result<Vec<ResourceContent>, FederationError> {
+            Ok(vec![ResourceContent {
+                mime_type: Some("text/markdown".to_string()),
+                text: self.resources.get(uri).cloned(),
+                blob: None,
+            }])
+        }
+    }
+    
+    fn source() -> FederationSourceConfig {
+        FederationSourceConfig {
+            name: "files".to_string(),
+            command: "mcp-server-filesystem".to_string(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            collection: "federated".to_string(),
+            interval_secs: 3600,
+            rules: vec![
+                MappingRule { pattern: r"/drafts/".to_string(), collection: None, tags: Vec::new(), skip: true },
+                MappingRule { pattern: r"^file:///docs/adr/".to_string(), collection: Some("adrs".to_string()), tags: vec!["adr".to_string()], skip: false },
+            ],
+        }
+    }
+    
+    #[test]
+    fn test_mapping_rules() {
+        let rules = CompiledRules::new(&source()).unwrap();
+        assert_eq!(rules.map("file:///docs/drafts/x.md"), None);
+        assert_eq!(rules.map("file:///docs/adr/0001.md"), Some(Mapping { collection: "adrs".to_string(), tags: vec!["adr".to_string()] }));
+        assert_eq!(rules.map("file:///docs/readme.md").unwrap().collection, "federated");
+    }
+    
+    #[test]
+    fn test_resource_text_decodes_textual_blobs() {
+        let contents = vec![
+            ResourceContent { mime_type: Some("text/html".to_string()), text: Some("<p>Hello</p>".to_string()), blob: None },
+            ResourceContent { mime_type: Some("application/json".to_string()), text: None, blob: Some(BASE64.encode("{\"a\":1}")) },
+            ResourceContent { mime_type: Some("image/png".to_string()), text: None, blob: Some(BASE64.encode("png")) },
+        ];
+        assert_eq!(resource_text(&contents).unwrap(), "Hello\n\n{\"a\":1}");
+        assert_eq!(resource_text(&contents[2..]), None);
+    }
+    
+    #[tokio::test]
+    async fn test_sync_maps_and_skips_unchanged() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        let federator = Federator::new(store.clone(), FederationConfig::default());
+        let mut server = FakeServer {
+            resources: HashMap::from([
+                ("file:///docs/adr/0001.md".to_string(), "Use Qdrant.".to_string()),
+                ("file:///docs/drafts/idea.md".to_string(), "Half-baked.".to_string()),
+                ("file:///docs/readme.md".to_string(), "Read me.".to_string()),
+            ]),
+        };
+        
+        let report = federator.sync_resources(&source(), &mut server).await.unwrap();
+        assert_eq!((report.ingested, report.unchanged, report.skipped), (2, 0, 1));
+        
+        let adr = store.get_document("adrs", &resource_document_id("files", "file:///docs/adr/0001.md")).await.unwrap().unwrap();
+        assert_eq!(adr.tags(), vec!["adr"]);
+        assert_eq!(adr.metadata[FEDERATION_SOURCE_KEY], "files");
+        
+        server.resources.insert("file:///docs/readme.md".to_string(), "Read me, updated.".to_string());
+        let report = federator.sync_resources(&source(), &mut server).await.unwrap();
+        assert_eq!((report.ingested, report.unchanged), (1, 1));
+    }
+}
@@ -0,0 +1,307 @@
+//! Background ingestion of RSS and Atom feeds.
+//!
+//! Each configured feed is polled on a schedule and its new items are stored
+//! in the feed's collection. Items are stored under ids derived from their
+//! GUID (or link), so an item already stored is recognised without keeping
+//! any state beyond the collection itself.
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::config::{FeedConfig, FeedsConfig};
+use crate::text_processing::{EmbeddingError, EmbeddingProvider};
+use crate::vector_store::{Document, VectorStore, VectorStoreError, CREATED_AT_KEY, EMBEDDING_PROVIDER_KEY, SOURCE_KEY};
+
+/// Metadata key holding when a feed item was published (RFC 3339)
+pub const PUBLISHED_AT_KEY: &str = "published_at";
+
+/// Metadata key holding the URL of the feed an item came from
+pub const FEED_URL_KEY: &str = "feed_url";
+
+/// Metadata key holding a feed item's GUID
+pub const FEED_ITEM_ID_KEY: &str = "feed_item_id";
+
+/// Embedding dimension used for feed items when no provider is configured
+const DEFAULT_FEED_EMBEDDING_DIM: usize = 384;
+
+#[derive(Debug, Error)]
+pub enum FeedError {
+    #[error("Failed to fetch {url}: {message}")]
+    Fetch { url: String, message: String },
+    
+    #[error("Failed to parse feed: {0}")]
+    Parse(String),
+    
+    #[error("Vector store error: {0}")]
+    Store(#[from] VectorStoreError),
+    
+    #[error("Embedding error: {0}")]
+    Embedding(#[from] EmbeddingError),
+}
+
+/// One item of a feed, reduced to what is stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedItem {
+    /// The GUID, or the link when the feed gives no GUID
+    pub key: String,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    /// Plain text of the item's content, or of its summary
+    pub content: String,
+}
+
+/// Strip tags and decode the common entities of an HTML fragment
+pub fn html_to_text(html: &str) -> String {
+    lazy_static! {
+        static ref BLOCK_TAG: Regex = Regex::new(r"(?i)<\s*(br|/p|/div|/li|/h[1-6])\s*/?>").unwrap();
+        static ref TAG: Regex = Regex::new(r"(?s)<[^>]*>").unwrap();
+        static ref BLANK_LINES: Regex = Regex::new(r"\n\s*\n+").unwrap();
+    }
+    
+    let text = BLOCK_TAG.replace_all(html, "\n\n");
+    let text = TAG.replace_all(&text, "");
+    let text = text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    BLANK_LINES.replace_all(text.trim(), "\n\n").to_string()
+}
+
+/// Parse an RSS or Atom document into items
+pub fn parse_feed(body: &[u8]) -> Result<Vec<FeedItem>, FeedError> {
+    let feed = feed_rs::parser::parse(body).map_err(|e| FeedError::Parse(e.to_string()))?;
+    
+    Ok(feed.entries.into_iter()
+        .filter_map(|entry| {
+            let link = entry.links.first().map(|link| link.href.clone());
+            let key = if entry.id.is_empty() { link.clone()? } else { entry.id };
+            let html = entry.content
+                .and_then(|content| content.body)
+                .or_else(|| entry.summary.map(|summary| summary.content))
+                .unwrap_or_default();
+            
+            Some(FeedItem {
+                key,
+                title: entry.title.map(|title| html_to_text(&title.content)),
+                link,
+                published: entry.published.or(entry.updated),
+                content: html_to_text(&html),
+            })
+        })
+        .collect())
+}
+
+/// Id under which an item is stored, stable across polls
+pub fn item_document_id(feed_url: &str, key: &str) -> String {
+    let digest = Sha256::digest(format!("{}\n{}", feed_url, key).as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Uuid::from_bytes(bytes).to_string()
+}
+
+/// Outcome of polling one feed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PollReport {
+    pub ingested: usize,
+    /// Items already stored by an earlier poll
+    pub skipped: usize,
+}
+
+/// Polls the configured feeds and ingests their new items
+pub struct FeedWatcher {
+    store: Arc<dyn VectorStore>,
+    config: FeedsConfig,
+    embedding_provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    embedding_dim: usize,
+    http: reqwest::Client,
+}
+
+impl FeedWatcher {
+    pub fn new(store: Arc<dyn VectorStore>, config: FeedsConfig) -> Self {
+        Self {
+            store,
+            config,
+            embedding_provider: None,
+            embedding_dim: DEFAULT_FEED_EMBEDDING_DIM,
+            http: reqwest::Client::new(),
+        }
+    }
+    
+    /// Embed items with `provider`, whose vectors have `embedding_dim` dimensions
+    pub fn with_embedding_provider(mut self, provider: Arc<dyn EmbeddingProvider + Send + Sync>, embedding_dim: usize) -> Self {
+        self.embedding_provider = Some(provider);
+        self.embedding_dim = embedding_dim;
+        self
+    }
+    
+    /// Send requests with `http`, e.g. one built by `network::http_client`
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+    
+    /// Store the items not already in the feed's collection
+    pub async fn ingest_items(&self, feed: &FeedConfig, items: Vec<FeedItem>) -> Result<PollReport, FeedError> {
+        let mut report = PollReport::default();
+        
+        for item in items {
+            let id = item_document_id(&feed.url, &item.key);
+            if item.content.is_empty() || self.store.get_document(&feed.collection, &id).await?.is_some() {
+                report.skipped += 1;
+                continue;
+            }
+            
+            let (embedding, embedding_provider) = match &self.embedding_provider {
+                Some(provider) => provider.generate_attributed(&item.content)?,
+                None => (vec![0.0; self.embedding_dim], None),
+            };
+            let mut document = Document {
+                id,
+                content: item.content,
+                embedding,
+                metadata: Default::default(),
+            }
+            .with_tags(&feed.tags);
+            if let Some(title) = &item.title {
+                document = document.with_title(title);
+            }
+            document.metadata.insert(CREATED_AT_KEY.to_string(), Utc::now().to_rfc3339());
+            if let Some(provider) = embedding_provider {
+                document.metadata.insert(EMBEDDING_PROVIDER_KEY.to_string(), provider);
+            }
+            document.metadata.insert(FEED_URL_KEY.to_string(), feed.url.clone());
+            document.metadata.insert(FEED_ITEM_ID_KEY.to_string(), item.key);
+            if let Some(link) = item.link {
+                document.metadata.insert(SOURCE_KEY.to_string(), link);
+            }
+            if let Some(published) = item.published {
+                document.metadata.insert(PUBLISHED_AT_KEY.to_string(), published.to_rfc3339());
+            }
+            
+            self.store.insert_document(&feed.collection, document).await?;
+            report.ingested += 1;
+        }
+        
+        Ok(report)
+    }
+    
+    /// Fetch one feed and ingest its new items
+    pub async fn poll_feed(&self, feed: &FeedConfig) -> Result<PollReport, FeedError> {
+        let fetch_error = |message: String| FeedError::Fetch { url: feed.url.clone(), message };
+        let response = self.http.get(&feed.url)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| fetch_error(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(fetch_error(format!("status {}", response.status())));
+        }
+        let body = response.bytes().await.map_err(|e| fetch_error(e.to_string()))?;
+        
+        self.ingest_items(feed, parse_feed(&body)?).await
+    }
+    
+    /// Poll every configured feed once, returning each feed's outcome
+    pub async fn poll_all(&self) -> Vec<(String, Result<PollReport, FeedError>)> {
+        let mut outcomes = Vec::with_capacity(self.config.feeds.len());
+        for feed in &self.config.feeds {
+            outcomes.push((feed.url.clone(), self.poll_feed(feed).await));
+        }
+        outcomes
+    }
+    
+    /// Poll every `interval_secs` until the task is aborted
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(self.config.interval_secs.max(1)));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            
+            loop {
+                ticker.tick().await;
+                for (url, outcome) in self.poll_all().await {
+                    match outcome {
+                        Ok(report) if report.ingested > 0 => info!(feed = %url, ingested = report.ingested, "Ingested feed items"),
+                        Ok(_) => {},
+                        Err(e) => warn!(feed = %url, "Failed to poll feed: {}", e),
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    const RSS: &str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Blog</title>
+<item><title>Release 1.2</title><link>https://example.com/1.2</link><guid>release-1.2</guid>
+<pubDate>Mon, 02 Mar 2026 10:00:00 GMT</pubDate><description>&lt;p&gt;Faster &amp;amp; smaller.&lt;/p&gt;</description></item>
+<item><title>No guid</title><link>https://example.com/no-guid</link><description>Plain text.</description></item>
+</channel></rss>"#;
+    
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom"><title>News</title><id>urn:news</id><updated>2026-03-01T00:00:00Z</updated>
+<entry><title>Outage report</title><id>urn:news:1</id><link href="https://example.com/outage"/>
+<updated>2026-03-01T00:00:00Z</updated><content type="html">&lt;p&gt;Root cause found.&lt;/p&gt;</content></entry>
+</feed>"#;
+    
+    fn feed() -> FeedConfig {
+        FeedConfig {
+            url: "https://example.com/feed.xml".to_string(),
+            collection: "news".to_string(),
+            tags: vec!["feed".to_string()],
+        }
+    }
+    
+    #[test]
+    fn test_parse_rss_and_atom() {
+        let items = parse_feed(RSS.as_bytes()).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title.as_deref(), Some("Release 1.2"));
+        assert_eq!(items[0].content, "Faster & smaller.");
+        assert!(items[0].published.is_some());
+        assert!(!items[1].key.is_empty());
+        
+        let items = parse_feed(ATOM.as_bytes()).unwrap();
+        assert_eq!(items[0].key, "urn:news:1");
+        assert_eq!(items[0].link.as_deref(), Some("https://example.com/outage"));
+        assert_eq!(items[0].content, "Root cause found.");
+    }
+    
+    #[test]
+    fn test_html_to_text() {
+        assert_eq!(html_to_text("<p>One</p><p>Two &lt;3</p>"), "One\n\nTwo <3");
+    }
+    
+    #[tokio::test]
+    async fn test_ingest_deduplicates_items() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("news", 384).await.unwrap();
+        let watcher = FeedWatcher::new(store.clone(), FeedsConfig::default());
+        let items = parse_feed(RSS.as_bytes()).unwrap();
+        
+        let report = watcher.ingest_items(&feed(), items.clone()).await.unwrap();
+        assert_eq!(report, PollReport { ingested: 2, skipped: 0 });
+        
+        let report = watcher.ingest_items(&feed(), items).await.unwrap();
+        assert_eq!(report, PollReport { ingested: 0, skipped: 2 });
+        
+        let stored = store.get_document("news", &item_document_id(&feed().url, "release-1.2")).await.unwrap().unwrap();
+        assert_eq!(stored.metadata[SOURCE_KEY], "https://example.com/1.2");
+        assert!(stored.metadata[PUBLISHED_AT_KEY].starts_with("2026-03-02T10:00:00"));
+        assert_eq!(stored.tags(), vec!["feed"]);
+    }
+}
@@ -0,0 +1,214 @@
+//! Browser-facing policy of the HTTP server, from `[server.http]`.
+//!
+//! Cross-origin requests from the configured origins get CORS headers, and
+//! their preflight `OPTIONS` requests are answered here, before any API key
+//! check, since browsers send preflights without credentials. Every response
+//! gets the security headers unless a handler set its own, and buffered
+//! request bodies are capped at `max_body_bytes`.
+
+use axum::extract::{DefaultBodyLimit, State};
+use axum::http::header::{self, HeaderName};
+use axum::http::{HeaderMap, HeaderValue, Method, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::config::HttpConfig;
+
+/// CORS and security headers to add to responses, with the body size limit
+#[derive(Debug, Clone)]
+pub struct HttpPolicy {
+    /// Allowed origins, without trailing slashes
+    origins: Vec<String>,
+    any_origin: bool,
+    allow_methods: Option<HeaderValue>,
+    allow_headers: Option<HeaderValue>,
+    expose_headers: Option<HeaderValue>,
+    allow_credentials: bool,
+    max_age: HeaderValue,
+    security_headers: Vec<(HeaderName, HeaderValue)>,
+    max_body_bytes: usize,
+}
+
+impl Default for HttpPolicy {
+    fn default() -> Self {
+        Self::from_config(&HttpConfig::default())
+    }
+}
+
+impl HttpPolicy {
+    /// The policy `config` describes; header values that are not valid in a
+    /// header are left out, as `p-mo config validate` reports them
+    pub fn from_config(config: &HttpConfig) -> Self {
+        let mut security_headers = Vec::new();
+        if config.security_headers {
+            security_headers.extend([
+                (header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+                (header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")),
+                (header::REFERRER_POLICY, HeaderValue::from_static("no-referrer")),
+            ]);
+            if let Ok(policy) = HeaderValue::from_str(&config.content_security_policy) {
+                if !policy.is_empty() {
+                    security_headers.push((header::CONTENT_SECURITY_POLICY, policy));
+                }
+            }
+        }
+        if let Some(max_age) = config.hsts_max_age_secs {
+            let value = HeaderValue::from_str(&format!("max-age={}", max_age)).expect("numbers are valid header values");
+            security_headers.push((header::STRICT_TRANSPORT_SECURITY, value));
+        }
+        
+        Self {
+            origins: config.cors_origins.iter()
+                .filter(|origin| origin.as_str() != "*")
+                .map(|origin| origin.trim_end_matches('/').to_string())
+                .collect(),
+            any_origin: config.cors_origins.iter().any(|origin| origin == "*"),
+            allow_methods: header_list(&config.cors_methods),
+            allow_headers: header_list(&config.cors_headers),
+            expose_headers: header_list(&config.cors_expose_headers),
+            allow_credentials: config.cors_allow_credentials,
+            max_age: HeaderValue::from(config.cors_max_age_secs),
+            security_headers,
+            max_body_bytes: config.max_body_bytes,
+        }
+    }
+    
+    /// `app` behind this policy
+    pub fn apply(&self, app: Router) -> Router {
+        app.layer(middleware::from_fn_with_state(Arc::new(self.clone()), enforce))
+            .layer(DefaultBodyLimit::max(self.max_body_bytes))
+    }
+    
+    /// The `Access-Control-Allow-Origin` answer to a request from `origin`:
+    /// the origin itself when it is listed, `*` when any origin is allowed
+    fn allowed_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        let listed = origin.to_str()
+            .is_ok_and(|origin| self.origins.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin)));
+        if listed {
+            Some(origin.clone())
+        } else if self.any_origin {
+            Some(HeaderValue::from_static("*"))
+        } else {
+            None
+        }
+    }
+    
+    /// Headers letting the browser show a response to `allow_origin`'s scripts
+    fn add_cors_headers(&self, headers: &mut HeaderMap, allow_origin: HeaderValue, preflight: bool) {
+        // Credentials are never allowed for the wildcard, which browsers would refuse anyway
+        if self.allow_credentials && allow_origin != "*" {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        headers.append(header::VARY, HeaderValue::from_static("origin"));
+        
+        if !preflight {
+            if let Some(expose) = &self.expose_headers {
+                headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, expose.clone());
+            }
+            return;
+        }
+        headers.insert(header::ACCESS_CONTROL_MAX_AGE, self.max_age.clone());
+        if let Some(methods) = &self.allow_methods {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods.clone());
+        }
+        if let Some(allowed) = &self.allow_headers {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allowed.clone());
+        }
+    }
+    
+    fn add_security_headers(&self, headers: &mut HeaderMap) {
+        for (name, value) in &self.security_headers {
+            headers.entry(name).or_insert_with(|| value.clone());
+        }
+    }
+}
+
+/// Answer preflights and add the policy's headers to every other response
+async fn enforce<B>(State(policy): State<Arc<HttpPolicy>>, request: Request<B>, next: Next<B>) -> Response {
+    let allow_origin = request.headers().get(header::ORIGIN).and_then(|origin| policy.allowed_origin(origin));
+    let preflight = request.method() == Method::OPTIONS
+        && request.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+    
+    let mut response = match (preflight, allow_origin) {
+        (true, Some(allow_origin)) => {
+            let mut response = StatusCode::NO_CONTENT.into_response();
+            policy.add_cors_headers(response.headers_mut(), allow_origin, true);
+            response
+        },
+        (true, None) => (StatusCode::FORBIDDEN, Json(json!({ "error": "Cross-origin requests from this origin are not allowed" }))).into_response(),
+        (false, allow_origin) => {
+            let mut response = next.run(request).await;
+            if let Some(allow_origin) = allow_origin {
+                policy.add_cors_headers(response.headers_mut(), allow_origin, false);
+            }
+            response
+        },
+    };
+    policy.add_security_headers(response.headers_mut());
+    response
+}
+
+/// `values` as one comma-separated header value, or none if there are none
+fn header_list(values: &[String]) -> Option<HeaderValue> {
+    if values.is_empty() {
+        return None;
+    }
+    HeaderValue::from_str(&values.join(", ")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn policy(origins: &[&str], allow_credentials: bool) -> HttpPolicy {
+        HttpPolicy::from_config(&HttpConfig {
+            cors_origins: origins.iter().map(|origin| origin.to_string()).collect(),
+            cors_allow_credentials: allow_credentials,
+            ..HttpConfig::default()
+        })
+    }
+    
+    #[test]
+    fn test_allowed_origins() {
+        let origin = HeaderValue::from_static("https://notes.example.com");
+        let other = HeaderValue::from_static("https://evil.example.com");
+        assert_eq!(HttpPolicy::default().allowed_origin(&origin), None);
+        
+        let listed = policy(&["https://notes.example.com/"], false);
+        assert_eq!(listed.allowed_origin(&origin), Some(origin.clone()));
+        assert_eq!(listed.allowed_origin(&other), None);
+        
+        let any = policy(&["*", "https://notes.example.com"], true);
+        assert_eq!(any.allowed_origin(&other), Some(HeaderValue::from_static("*")));
+        
+        // Only listed origins are trusted with credentials
+        let mut headers = HeaderMap::new();
+        any.add_cors_headers(&mut headers, HeaderValue::from_static("*"), false);
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+        any.add_cors_headers(&mut headers, origin.clone(), true);
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], origin);
+        assert!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS].to_str().unwrap().contains("x-api-key"));
+    }
+    
+    #[test]
+    fn test_security_headers_leave_handler_values() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("SAMEORIGIN"));
+        HttpPolicy::default().add_security_headers(&mut headers);
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "SAMEORIGIN");
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert!(headers.contains_key(header::CONTENT_SECURITY_POLICY));
+        assert!(!headers.contains_key(header::STRICT_TRANSPORT_SECURITY));
+        
+        let quiet = HttpPolicy::from_config(&HttpConfig { security_headers: false, hsts_max_age_secs: Some(3600), ..HttpConfig::default() });
+        let mut headers = HeaderMap::new();
+        quiet.add_security_headers(&mut headers);
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[header::STRICT_TRANSPORT_SECURITY], "max-age=3600");
+    }
+}
@@ -0,0 +1,242 @@
+//! Long-running server-side jobs, such as reindexing a collection.
+//!
+//! A job is started by a tool call that returns at once with the job's URI,
+//! e.g. `jobs://reindex/<id>`. Clients read that resource to poll the job's
+//! progress as structured JSON, or pass `?wait=<secs>` to block until the
+//! job next moves, instead of scraping logs to learn whether it finished.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+/// Finished jobs kept for clients to read; older ones are forgotten first
+pub const MAX_FINISHED_JOBS: usize = 100;
+
+/// Scheme of job resource URIs
+pub const JOB_URI_SCHEME: &str = "jobs://";
+
+/// Where a job is in its life
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A snapshot of a job, as returned by its resource
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobStatus {
+    pub id: String,
+    /// What the job does, e.g. `reindex`
+    pub kind: String,
+    pub uri: String,
+    /// What the job works on, e.g. a collection
+    pub target: String,
+    pub state: JobState,
+    /// Units of work done so far
+    pub done: u64,
+    /// `None` until the job knows how much work there is
+    pub total: Option<u64>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// What a completed job produced
+    pub result: Option<Value>,
+    /// Why a failed job stopped
+    pub error: Option<String>,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        self.state != JobState::Running
+    }
+    
+    /// Share of the work done, from 0 to 1, once the total is known
+    pub fn fraction(&self) -> Option<f64> {
+        self.total.map(|total| if total == 0 { 1.0 } else { self.done.min(total) as f64 / total as f64 })
+    }
+}
+
+/// The running side of a job, through which it reports progress
+#[derive(Clone)]
+pub struct JobHandle {
+    status: Arc<watch::Sender<JobStatus>>,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> String {
+        self.status.borrow().id.clone()
+    }
+    
+    pub fn uri(&self) -> String {
+        self.status.borrow().uri.clone()
+    }
+    
+    pub fn status(&self) -> JobStatus {
+        self.status.borrow().clone()
+    }
+    
+    pub fn set_total(&self, total: u64) {
+        self.update(|status| status.total = Some(total));
+    }
+    
+    pub fn inc(&self, delta: u64) {
+        self.update(|status| status.done += delta);
+    }
+    
+    /// Mark the job completed with `result`
+    pub fn complete(&self, result: Value) {
+        self.update(|status| {
+            status.state = JobState::Completed;
+            status.finished_at = Some(Utc::now());
+            status.result = Some(result);
+        });
+    }
+    
+    /// Mark the job failed because of `error`
+    pub fn fail(&self, error: impl Into<String>) {
+        let error = error.into();
+        self.update(|status| {
+            status.state = JobState::Failed;
+            status.finished_at = Some(Utc::now());
+            status.error = Some(error);
+        });
+    }
+    
+    fn update(&self, change: impl FnOnce(&mut JobStatus)) {
+        self.status.send_modify(|status| {
+            change(status);
+            status.updated_at = Utc::now();
+        });
+    }
+}
+
+/// The jobs the server has started, running and recently finished
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: Mutex<Vec<Arc<watch::Sender<JobStatus>>>>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Register a new running job of `kind` working on `target`
+    pub fn start(&self, kind: &str, target: &str) -> JobHandle {
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let (sender, _) = watch::channel(JobStatus {
+            uri: format!("{}{}/{}", JOB_URI_SCHEME, kind, id),
+            id,
+            kind: kind.to_string(),
+            target: target.to_string(),
+            state: JobState::Running,
+            done: 0,
+            total: None,
+            started_at: now,
+            updated_at: now,
+            finished_at: None,
+            result: None,
+            error: None,
+        });
+        let status = Arc::new(sender);
+        
+        let mut jobs = self.jobs.lock().unwrap();
+        let finished = jobs.iter().filter(|job| job.borrow().is_finished()).count();
+        if finished >= MAX_FINISHED_JOBS {
+            // Jobs are kept in start order, so the first finished one is the oldest
+            if let Some(oldest) = jobs.iter().position(|job| job.borrow().is_finished()) {
+                jobs.remove(oldest);
+            }
+        }
+        jobs.push(status.clone());
+        JobHandle { status }
+    }
+    
+    /// The job of `kind` with `id`
+    pub fn get(&self, kind: &str, id: &str) -> Option<JobStatus> {
+        self.find(kind, id).map(|job| job.borrow().clone())
+    }
+    
+    /// Every known job of `kind`, oldest first
+    pub fn list(&self, kind: &str) -> Vec<JobStatus> {
+        self.jobs.lock().unwrap()
+            .iter()
+            .map(|job| job.borrow().clone())
+            .filter(|status| status.kind == kind)
+            .collect()
+    }
+    
+    /// The job of `kind` with `id` once it next changes, or after `timeout`
+    /// if it does not; at once if it has already finished
+    pub async fn wait(&self, kind: &str, id: &str, timeout: Duration) -> Option<JobStatus> {
+        let job = self.find(kind, id)?;
+        let mut changes = job.subscribe();
+        if !changes.borrow().is_finished() {
+            let _ = tokio::time::timeout(timeout, changes.changed()).await;
+        }
+        let status = changes.borrow().clone();
+        Some(status)
+    }
+    
+    fn find(&self, kind: &str, id: &str) -> Option<Arc<watch::Sender<JobStatus>>> {
+        self.jobs.lock().unwrap()
+            .iter()
+            .find(|job| {
+                let status = job.borrow();
+                status.kind == kind && status.id == id
+            })
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    #[tokio::test]
+    async fn test_job_lifecycle() {
+        let registry = JobRegistry::new();
+        let job = registry.start("reindex", "docs");
+        assert_eq!(job.uri(), format!("jobs://reindex/{}", job.id()));
+        
+        job.set_total(4);
+        job.inc(1);
+        let status = registry.get("reindex", &job.id()).unwrap();
+        assert_eq!(status.state, JobState::Running);
+        assert_eq!(status.fraction(), Some(0.25));
+        assert!(registry.get("export", &job.id()).is_none());
+        
+        let waiter = {
+            let job = job.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                job.complete(json!({"reindexed": 4}));
+            })
+        };
+        let status = registry.wait("reindex", &job.id(), Duration::from_secs(5)).await.unwrap();
+        waiter.await.unwrap();
+        assert_eq!(status.state, JobState::Completed);
+        assert_eq!(status.result, Some(json!({"reindexed": 4})));
+        assert_eq!(registry.list("reindex").len(), 1);
+    }
+    
+    #[test]
+    fn test_finished_jobs_are_pruned() {
+        let registry = JobRegistry::new();
+        let running = registry.start("reindex", "docs");
+        for _ in 0..MAX_FINISHED_JOBS + 5 {
+            registry.start("reindex", "docs").fail("boom");
+        }
+        let jobs = registry.list("reindex");
+        assert!(jobs.iter().filter(|job| job.is_finished()).count() <= MAX_FINISHED_JOBS);
+        assert!(jobs.iter().any(|job| job.id == running.id()));
+    }
+}
@@ -1,11 +1,43 @@
 pub mod server;
 pub mod cli;
+pub mod client;
 pub mod api;
+pub mod api_keys;
 pub mod vector_store;
 pub mod config;
+pub mod analyze;
 pub mod app;
+pub mod attachments;
+pub mod bundle;
 pub mod mcp;
+pub mod migrations;
 pub mod text_processing;
+pub mod logging;
+pub mod maintenance;
+pub mod otel;
+pub mod progress;
+pub mod container;
+pub mod context;
+pub mod determinism;
+pub mod digest;
+pub mod eval;
+pub mod events;
+pub mod feeds;
+pub mod federation;
+pub mod http_policy;
+pub mod jobs;
+pub mod lint;
+pub mod network;
+pub mod oidc;
+pub mod selftest;
+pub mod service;
+pub mod systemd;
+#[cfg(unix)]
+pub mod unix_socket;
+pub mod uploads;
+pub mod sources;
+pub mod telemetry;
+pub mod ui;
 
 pub use server::Server;
 pub use cli::{Cli, Args};
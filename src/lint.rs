@@ -0,0 +1,360 @@
+//! Knowledge base linting.
+//!
+//! `p-mo lint` walks every document of a collection looking for problems
+//! that quietly hurt retrieval: entries without titles or tags, placeholder
+//! embeddings that were never replaced, vectors of the wrong dimension,
+//! chunks whose entry is gone, and chunks too long to embed well. Safe fixes
+//! only fill in what is missing, generating titles and re-embedding content,
+//! and never delete anything.
+
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::progress::Progress;
+use crate::text_processing::{generate_title, EmbeddingProvider};
+use crate::vector_store::chunks::{chunk_id, CHUNK_INDEX_KEY, PARENT_ID_KEY};
+use crate::vector_store::filter::FILTER_PAGE_SIZE;
+use crate::vector_store::{embedding_defect, l2_normalize, CollectionRegistry, Document, EmbeddingDefect, VectorStore, VectorStoreError, TITLE_GENERATED_KEY, TITLE_KEY};
+
+/// Chunks longer than this many characters are reported by default
+pub const DEFAULT_MAX_CHUNK_CHARS: usize = 4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// The problems `p-mo lint` looks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintCheck {
+    MissingTitle,
+    MissingTags,
+    /// An all-zero embedding, left behind when no embedding provider was configured
+    ZeroVector,
+    /// An embedding with NaN or infinite components
+    NonFiniteVector,
+    DimensionMismatch,
+    /// A chunk that its entry no longer reaches, so it only turns up in searches
+    OrphanedChunk,
+    LongChunk,
+}
+
+impl LintCheck {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LintCheck::MissingTitle => "missing_title",
+            LintCheck::MissingTags => "missing_tags",
+            LintCheck::ZeroVector => "zero_vector",
+            LintCheck::NonFiniteVector => "non_finite_vector",
+            LintCheck::DimensionMismatch => "dimension_mismatch",
+            LintCheck::OrphanedChunk => "orphaned_chunk",
+            LintCheck::LongChunk => "long_chunk",
+        }
+    }
+    
+    pub fn severity(&self) -> Severity {
+        match self {
+            LintCheck::MissingTags => Severity::Info,
+            LintCheck::MissingTitle | LintCheck::OrphanedChunk | LintCheck::LongChunk => Severity::Warning,
+            LintCheck::ZeroVector | LintCheck::NonFiniteVector | LintCheck::DimensionMismatch => Severity::Error,
+        }
+    }
+}
+
+/// One problem with one document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LintIssue {
+    pub collection: String,
+    /// Id of the document; for chunks, the chunk's own id
+    pub id: String,
+    pub check: LintCheck,
+    pub severity: Severity,
+    pub message: String,
+    /// Whether `--fix` repaired it
+    pub fixed: bool,
+}
+
+/// What to check and whether to fix it
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    pub max_chunk_chars: usize,
+    /// Apply the safe fixes
+    pub fix: bool,
+    /// Whether re-embedded vectors are L2-normalized, as the server does with `[vectors] normalize`
+    pub normalize: bool,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            max_chunk_chars: DEFAULT_MAX_CHUNK_CHARS,
+            fix: false,
+            normalize: false,
+        }
+    }
+}
+
+/// Everything one lint run found
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LintReport {
+    /// Documents checked
+    pub scanned: usize,
+    pub issues: Vec<LintIssue>,
+}
+
+impl LintReport {
+    /// Issues of `severity` that are still unfixed
+    pub fn open(&self, severity: Severity) -> usize {
+        self.issues.iter().filter(|issue| issue.severity == severity && !issue.fixed).count()
+    }
+    
+    pub fn fixed(&self) -> usize {
+        self.issues.iter().filter(|issue| issue.fixed).count()
+    }
+    
+    /// Combine the reports of several collections
+    pub fn merge(&mut self, other: LintReport) {
+        self.scanned += other.scanned;
+        self.issues.extend(other.issues);
+    }
+    
+    /// A line per issue, most severe first, then a summary
+    pub fn render_text(&self) -> String {
+        let mut issues: Vec<&LintIssue> = self.issues.iter().collect();
+        issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+        
+        let mut out = String::new();
+        for issue in issues {
+            let fixed = if issue.fixed { " (fixed)" } else { "" };
+            out.push_str(&format!(
+                "{:<7} {} {}/{}: {}{}\n",
+                format!("{:?}", issue.severity).to_lowercase(), issue.check.as_str(), issue.collection, issue.id, issue.message, fixed
+            ));
+        }
+        out.push_str(&format!(
+            "Checked {} documents: {} errors, {} warnings, {} info, {} fixed\n",
+            self.scanned, self.open(Severity::Error), self.open(Severity::Warning), self.open(Severity::Info), self.fixed()
+        ));
+        out
+    }
+}
+
+/// Where a chunk sits in its entry
+struct ChunkRef {
+    id: String,
+    parent: String,
+    index: usize,
+}
+
+/// Lint every document of `collection`.
+///
+/// Vector sizes are checked against the registry, or the collection's own
+/// schema when it is unregistered. Zero and mis-sized vectors are fixed only
+/// when `embedder` produces vectors of the expected size.
+pub async fn lint_collection(
+    store: &dyn VectorStore,
+    registry: &CollectionRegistry,
+    collection: &str,
+    options: &LintOptions,
+    embedder: Option<&dyn EmbeddingProvider>,
+    progress: &Progress,
+) -> Result<LintReport, VectorStoreError> {
+    let expected_size = match registry.get(collection) {
+        Some(info) => Some(info.vector_size),
+        None => store.collection_schema(collection).await?.map(|schema| schema.vector_size),
+    };
+    let embedder = embedder.filter(|embedder| expected_size.is_none_or(|size| embedder.embedding_dim() == size));
+    
+    let mut report = LintReport::default();
+    let mut ids = HashSet::new();
+    let mut chunks = Vec::new();
+    let mut offset = None;
+    loop {
+        let page = store.list_documents(collection, offset, FILTER_PAGE_SIZE).await?;
+        progress.inc(page.documents.len() as u64);
+        for mut document in page.documents {
+            report.scanned += 1;
+            let found = lint_document(collection, &mut document, expected_size, options, embedder);
+            if options.fix && found.iter().any(|issue| issue.fixed) {
+                store.insert_document(collection, document.clone()).await?;
+            }
+            report.issues.extend(found);
+            
+            let index = document.metadata.get(CHUNK_INDEX_KEY).and_then(|index| index.parse::<usize>().ok());
+            if let (Some(parent), Some(index)) = (document.metadata.get(PARENT_ID_KEY), index) {
+                chunks.push(ChunkRef { id: document.id.clone(), parent: parent.clone(), index });
+            }
+            ids.insert(document.id);
+        }
+        
+        match page.next_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+    
+    // A chunk is reachable only if every chunk before it in its entry exists
+    for chunk in chunks.iter().filter(|chunk| chunk.index > 0) {
+        if let Some(missing) = (0..chunk.index).find(|&index| !ids.contains(&chunk_id(&chunk.parent, index))) {
+            report.issues.push(issue(collection, &chunk.id, LintCheck::OrphanedChunk, format!(
+                "chunk {} of entry '{}' cannot be reached because chunk {} is missing",
+                chunk.index, chunk.parent, missing
+            ), false));
+        }
+    }
+    Ok(report)
+}
+
+fn issue(collection: &str, id: &str, check: LintCheck, message: String, fixed: bool) -> LintIssue {
+    LintIssue {
+        collection: collection.to_string(),
+        id: id.to_string(),
+        check,
+        severity: check.severity(),
+        message,
+        fixed,
+    }
+}
+
+/// Check one document, fixing it in place when `options.fix` allows
+fn lint_document(
+    collection: &str,
+    document: &mut Document,
+    expected_size: Option<usize>,
+    options: &LintOptions,
+    embedder: Option<&dyn EmbeddingProvider>,
+) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    
+    // Titles and tags are copied onto every chunk, so only the first chunk is checked
+    let is_entry = document.metadata.get(CHUNK_INDEX_KEY).is_none_or(|index| index == "0");
+    if is_entry && document.title().is_none_or(|title| title.trim().is_empty()) {
+        let title = if options.fix { generate_title(&document.content) } else { None };
+        let fixed = title.is_some();
+        if let Some(title) = title {
+            document.metadata.insert(TITLE_KEY.to_string(), title);
+            document.metadata.insert(TITLE_GENERATED_KEY.to_string(), "true".to_string());
+        }
+        issues.push(issue(collection, &document.id, LintCheck::MissingTitle, "entry has no title".to_string(), fixed));
+    }
+    if is_entry && document.tags().is_empty() {
+        issues.push(issue(collection, &document.id, LintCheck::MissingTags, "entry has no tags".to_string(), false));
+    }
+    
+    let problem = match embedding_defect(&document.embedding) {
+        Some(EmbeddingDefect::Zero) => Some((LintCheck::ZeroVector, "embedding is all zeros, a placeholder that was never replaced".to_string())),
+        Some(EmbeddingDefect::NonFinite) => Some((LintCheck::NonFiniteVector, EmbeddingDefect::NonFinite.description().to_string())),
+        None => expected_size
+            .filter(|size| *size != document.embedding.len())
+            .map(|size| (LintCheck::DimensionMismatch, format!("embedding has {} dimensions but the collection expects {}", document.embedding.len(), size))),
+    };
+    if let Some((check, message)) = problem {
+        let embedding = match embedder {
+            // A provider that itself returns placeholders fixes nothing
+            Some(embedder) if options.fix => embedder.generate_embedding(&document.content).ok()
+                .filter(|embedding| embedding_defect(embedding).is_none()),
+            _ => None,
+        };
+        let fixed = embedding.is_some();
+        if let Some(mut embedding) = embedding {
+            if options.normalize {
+                l2_normalize(&mut embedding);
+            }
+            document.embedding = embedding;
+        }
+        issues.push(issue(collection, &document.id, check, message, fixed));
+    }
+    
+    let chars = document.content.chars().count();
+    if chars > options.max_chunk_chars {
+        issues.push(issue(collection, &document.id, LintCheck::LongChunk, format!(
+            "chunk is {} characters, over the limit of {}; split the entry so each part embeds well",
+            chars, options.max_chunk_chars
+        ), false));
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_processing::embedding::MockEmbeddingGenerator;
+    use crate::vector_store::{CollectionInfo, InMemoryVectorStore};
+    
+    fn document(id: &str, content: &str, embedding: Vec<f32>) -> Document {
+        Document {
+            id: id.to_string(),
+            content: content.to_string(),
+            embedding,
+            metadata: Default::default(),
+        }
+    }
+    
+    async fn seeded() -> (InMemoryVectorStore, CollectionRegistry) {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 4).await.unwrap();
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("docs", 4));
+        
+        let tidy = document("tidy", "Deploys happen on Tuesdays.", vec![0.5, 0.5, 0.5, 0.5])
+            .with_title("Deploys")
+            .with_tags(&["ops".to_string()]);
+        store.insert_document("docs", tidy).await.unwrap();
+        store.insert_document("docs", document("placeholder", "Rotate keys every month.", vec![0.0; 4])).await.unwrap();
+        
+        let mut orphan = document(&chunk_id("gone", 1), "A paragraph whose entry was deleted.", vec![0.1, 0.2, 0.3, 0.4])
+            .with_title("Gone")
+            .with_tags(&["ops".to_string()]);
+        orphan.metadata.insert(PARENT_ID_KEY.to_string(), "gone".to_string());
+        orphan.metadata.insert(CHUNK_INDEX_KEY.to_string(), "1".to_string());
+        store.insert_document("docs", orphan).await.unwrap();
+        (store, registry)
+    }
+    
+    #[tokio::test]
+    async fn test_lint_reports_with_severities() {
+        let (store, registry) = seeded().await;
+        let options = LintOptions { max_chunk_chars: 30, ..Default::default() };
+        let report = lint_collection(&store, &registry, "docs", &options, None, &Progress::hidden()).await.unwrap();
+        
+        assert_eq!(report.scanned, 3);
+        let mut found: Vec<(&str, LintCheck)> = report.issues.iter().map(|issue| (issue.id.as_str(), issue.check)).collect();
+        found.sort_by_key(|(id, check)| (id.to_string(), check.as_str()));
+        let orphan_id = chunk_id("gone", 1);
+        let mut expected = vec![
+            ("placeholder", LintCheck::MissingTags),
+            ("placeholder", LintCheck::MissingTitle),
+            ("placeholder", LintCheck::ZeroVector),
+            (orphan_id.as_str(), LintCheck::LongChunk),
+            (orphan_id.as_str(), LintCheck::OrphanedChunk),
+        ];
+        expected.sort_by_key(|(id, check)| (id.to_string(), check.as_str()));
+        assert_eq!(found, expected);
+        assert_eq!(report.open(Severity::Error), 1);
+        assert!(report.render_text().starts_with("error   zero_vector docs/placeholder"));
+    }
+    
+    #[tokio::test]
+    async fn test_fix_repairs_only_safe_issues() {
+        let (store, registry) = seeded().await;
+        let options = LintOptions { fix: true, ..Default::default() };
+        let embedder = MockEmbeddingGenerator::new(4);
+        let report = lint_collection(&store, &registry, "docs", &options, Some(&embedder), &Progress::hidden()).await.unwrap();
+        assert_eq!(report.fixed(), 2);
+        
+        let fixed = store.get_document("docs", "placeholder").await.unwrap().unwrap();
+        assert!(fixed.title().is_some());
+        assert!(fixed.embedding.iter().any(|x| *x != 0.0));
+        // Orphans are left for an operator to delete
+        assert!(store.get_document("docs", &chunk_id("gone", 1)).await.unwrap().is_some());
+        
+        let again = lint_collection(&store, &registry, "docs", &options, Some(&embedder), &Progress::hidden()).await.unwrap();
+        let checks: Vec<LintCheck> = again.issues.iter().map(|issue| issue.check).collect();
+        assert_eq!(checks.len(), 2);
+        assert!(checks.contains(&LintCheck::MissingTags) && checks.contains(&LintCheck::OrphanedChunk));
+    }
+}
@@ -0,0 +1,181 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Mutex;
+use tracing::{error, info};
+
+use super::RotatingFile;
+use crate::config::AuditConfig;
+
+/// A runtime setting that changed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigChange {
+    pub timestamp: DateTime<Utc>,
+    /// Dotted path of the setting, e.g. `collections.docs.search`
+    pub setting: String,
+    /// `null` when the setting was unset
+    pub old_value: Value,
+    /// `null` when the setting was cleared
+    pub new_value: Value,
+    /// Who made the change, e.g. a client id
+    pub actor: String,
+    /// What made the change, e.g. the tool that was called
+    pub source: String,
+}
+
+impl ConfigChange {
+    pub fn new(setting: impl Into<String>, old_value: Value, new_value: Value, actor: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            setting: setting.into(),
+            old_value,
+            new_value,
+            actor: actor.into(),
+            source: source.into(),
+        }
+    }
+}
+
+/// Which changes a history lookup returns
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigHistoryQuery {
+    /// Only settings at or under this dotted path
+    pub setting: Option<String>,
+    pub actor: Option<String>,
+    /// Only changes made at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Most changes to return, newest first
+    pub limit: Option<usize>,
+}
+
+impl ConfigHistoryQuery {
+    fn matches(&self, change: &ConfigChange) -> bool {
+        let setting_matches = self.setting.as_deref().is_none_or(|prefix| {
+            change.setting == prefix || change.setting.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('.'))
+        });
+        setting_matches
+            && self.actor.as_deref().is_none_or(|actor| change.actor == actor)
+            && self.since.is_none_or(|since| change.timestamp >= since)
+    }
+}
+
+/// Keeps the most recent configuration changes for lookup and, optionally,
+/// appends every change as a JSON line to a rotating file
+#[derive(Debug)]
+pub struct ConfigAuditLog {
+    max_entries: usize,
+    changes: Mutex<VecDeque<ConfigChange>>,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl ConfigAuditLog {
+    /// Create an audit log holding up to `max_entries` changes in memory
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            changes: Mutex::new(VecDeque::new()),
+            file: None,
+        }
+    }
+    
+    /// Create an audit log from configuration, reloading the changes already in its file
+    pub fn from_config(config: &AuditConfig) -> io::Result<Self> {
+        let log = Self::new(config.max_entries);
+        let Some(path) = &config.file else { return Ok(log) };
+        
+        if let Ok(content) = std::fs::read_to_string(path) {
+            let mut changes = log.changes.lock().unwrap();
+            for change in content.lines().filter_map(|line| serde_json::from_str::<ConfigChange>(line).ok()) {
+                changes.push_back(change);
+                if changes.len() > log.max_entries {
+                    changes.pop_front();
+                }
+            }
+        }
+        Ok(log.with_file(RotatingFile::open(path, config.max_file_bytes, config.max_files)?))
+    }
+    
+    /// Also write changes as JSON lines to `file`
+    pub fn with_file(mut self, file: RotatingFile) -> Self {
+        self.file = Some(Mutex::new(file));
+        self
+    }
+    
+    /// Record a change, logging it through tracing as well
+    pub fn record(&self, change: ConfigChange) {
+        let line = serde_json::to_string(&change).unwrap_or_default();
+        info!(target: "p_mo::audit", "{}", line);
+        
+        if let Some(file) = &self.file {
+            if let Err(e) = file.lock().unwrap().write_line(&line) {
+                error!("Failed to write config audit log: {}", e);
+            }
+        }
+        
+        let mut changes = self.changes.lock().unwrap();
+        changes.push_back(change);
+        if changes.len() > self.max_entries {
+            changes.pop_front();
+        }
+    }
+    
+    /// Changes matching `query`, newest first
+    pub fn history(&self, query: &ConfigHistoryQuery) -> Vec<ConfigChange> {
+        self.changes.lock().unwrap()
+            .iter()
+            .rev()
+            .filter(|change| query.matches(change))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_history_filters_newest_first() {
+        let log = ConfigAuditLog::new(10);
+        log.record(ConfigChange::new("collections.docs.search", json!(null), json!({"limit": 5}), "key:alice", "update_collection_settings"));
+        log.record(ConfigChange::new("collections.docs2.search", json!(null), json!({"limit": 3}), "key:bob", "update_collection_settings"));
+        log.record(ConfigChange::new("collections.docs.search", json!({"limit": 5}), json!({"limit": 8}), "key:bob", "update_collection_settings"));
+        
+        let docs = log.history(&ConfigHistoryQuery { setting: Some("collections.docs".to_string()), ..Default::default() });
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].new_value, json!({"limit": 8}));
+        
+        let bob = log.history(&ConfigHistoryQuery { actor: Some("key:bob".to_string()), limit: Some(1), ..Default::default() });
+        assert_eq!(bob.len(), 1);
+        assert_eq!(bob[0].setting, "collections.docs.search");
+        
+        let future = ConfigHistoryQuery { since: Some(Utc::now() + chrono::Duration::hours(1)), ..Default::default() };
+        assert!(log.history(&future).is_empty());
+    }
+    
+    #[test]
+    fn test_changes_survive_a_restart() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = AuditConfig {
+            file: Some(temp_dir.path().join("config-audit.log")),
+            max_entries: 2,
+            ..AuditConfig::default()
+        };
+        
+        let log = ConfigAuditLog::from_config(&config).unwrap();
+        for limit in 1..=3 {
+            log.record(ConfigChange::new("collections.docs.search", json!(null), json!({"limit": limit}), "anonymous", "test"));
+        }
+        drop(log);
+        
+        let reopened = ConfigAuditLog::from_config(&config).unwrap();
+        let history = reopened.history(&ConfigHistoryQuery::default());
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].new_value, json!({"limit": 3}));
+    }
+}
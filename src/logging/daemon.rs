@@ -0,0 +1,107 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use tracing::{error, info, Subscriber};
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::fmt::{self, MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::{SubscriberInitExt, TryInitError};
+use tracing_subscriber::Layer as _;
+
+use super::rotation::LogWriter;
+
+/// The daemon's log file once one is installed; log lines go to stdout until then
+static LOG_FILE: RwLock<Option<LogWriter>> = RwLock::new(None);
+
+/// Whether the global subscriber carries the log layers of [`try_init_global`]
+static LOG_LAYERS_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+fn logging_to_file() -> bool {
+    LOG_FILE.read().is_ok_and(|file| file.is_some())
+}
+
+/// Writes to the installed log file; only asked for writers while there is one
+#[derive(Debug, Clone, Copy)]
+struct LogFile;
+
+impl<'a> MakeWriter<'a> for LogFile {
+    type Writer = Box<dyn io::Write + 'a>;
+    
+    fn make_writer(&'a self) -> Self::Writer {
+        match LOG_FILE.read().ok().and_then(|file| file.clone()) {
+            Some(writer) => Box::new(writer),
+            None => Box::new(io::sink()),
+        }
+    }
+}
+
+/// Install `subscriber` as the global subscriber, with layers logging to
+/// stdout, as text or JSON lines, until [`install_global_writer`] gives them
+/// a log file and to that file from then on
+pub fn try_init_global<S>(subscriber: S, json_logs: bool) -> Result<(), TryInitError>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync + 'static,
+{
+    let text = (!json_logs).then(|| fmt::layer().with_filter(filter_fn(|_| !logging_to_file())));
+    let json = json_logs.then(|| fmt::layer().json().with_filter(filter_fn(|_| !logging_to_file())));
+    let file = fmt::layer()
+        .with_ansi(false)
+        .with_writer(LogFile)
+        .with_filter(filter_fn(|_| logging_to_file()));
+    
+    subscriber.with(text).with(json).with(file).try_init()?;
+    LOG_LAYERS_INSTALLED.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Route tracing output to `writer` instead of stdout, installing the global
+/// subscriber if none is yet; returns false if a subscriber not installed by
+/// [`try_init_global`] was already set, which keeps the output
+pub fn install_global_writer(writer: LogWriter) -> bool {
+    if let Ok(mut file) = LOG_FILE.write() {
+        *file = Some(writer);
+    }
+    LOG_LAYERS_INSTALLED.load(Ordering::SeqCst) || try_init_global(tracing_subscriber::registry(), false).is_ok()
+}
+
+/// Reopen the log file whenever the process receives SIGUSR1.
+///
+/// This is what logrotate's `postrotate` hook expects: it moves the file
+/// away and signals the daemon to start writing a fresh one.
+#[cfg(unix)]
+pub fn spawn_reopen_on_sigusr1(writer: LogWriter) -> io::Result<tokio::task::JoinHandle<()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+    
+    let mut signals = signal(SignalKind::user_defined1())?;
+    Ok(tokio::spawn(async move {
+        while signals.recv().await.is_some() {
+            match writer.reopen() {
+                Ok(()) => info!("Reopened log file after SIGUSR1"),
+                Err(e) => error!("Failed to reopen log file: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::RotatingFile;
+    
+    #[test]
+    fn test_log_lines_follow_the_log_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("p-mo.log");
+        try_init_global(tracing_subscriber::registry(), false).unwrap();
+        
+        // The global subscriber is taken, so the file is reached through its layers
+        let writer = LogWriter::new(RotatingFile::open(&path, 1024 * 1024, 1).unwrap());
+        assert!(install_global_writer(writer));
+        info!("written to the log file");
+        
+        let logged = std::fs::read_to_string(&path).unwrap();
+        assert!(logged.contains("written to the log file"), "{}", logged);
+        assert!(!logged.contains('\u{1b}'), "log file lines carry no ANSI escapes");
+    }
+}
@@ -0,0 +1,10 @@
+pub mod audit;
+pub mod daemon;
+pub mod retrieval;
+pub mod rotation;
+pub mod slow_query;
+
+pub use audit::{ConfigAuditLog, ConfigChange, ConfigHistoryQuery};
+pub use retrieval::{RetrievalLog, RetrievalRecord};
+pub use rotation::{LogWriter, RotatingFile};
+pub use slow_query::{redact_params, SlowQueryEntry, SlowQueryLog, StageTimer, StageTiming};
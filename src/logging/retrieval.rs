@@ -0,0 +1,149 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Mutex;
+use tracing::error;
+
+use super::RotatingFile;
+use crate::config::RetrievalLogConfig;
+
+/// What a search or get_context call returned, kept under its retrieval id
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetrievalRecord {
+    pub retrieval_id: String,
+    pub timestamp: DateTime<Utc>,
+    /// The tool that made the retrieval, e.g. `search_knowledge`
+    pub tool: String,
+    pub collection: String,
+    pub query: String,
+    /// Who made the call, e.g. a client id
+    pub actor: String,
+    /// The tool result exactly as returned to the caller
+    pub result: Value,
+}
+
+impl RetrievalRecord {
+    /// A record of `result` under a new retrieval id
+    pub fn new(tool: &str, collection: &str, query: &str, actor: &str, result: Value) -> Self {
+        Self {
+            retrieval_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            tool: tool.to_string(),
+            collection: collection.to_string(),
+            query: query.to_string(),
+            actor: actor.to_string(),
+            result,
+        }
+    }
+}
+
+/// Keeps the most recent retrievals for lookup by id and, optionally,
+/// appends every retrieval as a JSON line to a rotating file
+#[derive(Debug)]
+pub struct RetrievalLog {
+    max_entries: usize,
+    records: Mutex<VecDeque<RetrievalRecord>>,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl RetrievalLog {
+    /// Create a log holding up to `max_entries` retrievals in memory
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            records: Mutex::new(VecDeque::new()),
+            file: None,
+        }
+    }
+    
+    /// Create a retrieval log from configuration, reloading the retrievals already in its file
+    pub fn from_config(config: &RetrievalLogConfig) -> io::Result<Self> {
+        let log = Self::new(config.max_entries);
+        let Some(path) = &config.file else { return Ok(log) };
+        
+        if let Ok(content) = std::fs::read_to_string(path) {
+            let mut records = log.records.lock().unwrap();
+            for record in content.lines().filter_map(|line| serde_json::from_str::<RetrievalRecord>(line).ok()) {
+                records.push_back(record);
+                if records.len() > log.max_entries {
+                    records.pop_front();
+                }
+            }
+        }
+        Ok(log.with_file(RotatingFile::open(path, config.max_file_bytes, config.max_files)?))
+    }
+    
+    /// Also write retrievals as JSON lines to `file`
+    pub fn with_file(mut self, file: RotatingFile) -> Self {
+        self.file = Some(Mutex::new(file));
+        self
+    }
+    
+    /// Record a retrieval
+    pub fn record(&self, record: RetrievalRecord) {
+        if let Some(file) = &self.file {
+            let line = serde_json::to_string(&record).unwrap_or_default();
+            if let Err(e) = file.lock().unwrap().write_line(&line) {
+                error!("Failed to write retrieval log: {}", e);
+            }
+        }
+        
+        let mut records = self.records.lock().unwrap();
+        records.push_back(record);
+        if records.len() > self.max_entries {
+            records.pop_front();
+        }
+    }
+    
+    /// The retrieval recorded under `retrieval_id`, if it is still kept
+    pub fn get(&self, retrieval_id: &str) -> Option<RetrievalRecord> {
+        self.records.lock().unwrap()
+            .iter()
+            .rev()
+            .find(|record| record.retrieval_id == retrieval_id)
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_oldest_retrievals_are_dropped() {
+        let log = RetrievalLog::new(2);
+        let ids: Vec<String> = (0..3)
+            .map(|i| {
+                let record = RetrievalRecord::new("search_knowledge", "docs", &format!("query {}", i), "anonymous", json!({"i": i}));
+                let id = record.retrieval_id.clone();
+                log.record(record);
+                id
+            })
+            .collect();
+        
+        assert!(log.get(&ids[0]).is_none());
+        assert_eq!(log.get(&ids[2]).unwrap().result, json!({"i": 2}));
+        assert!(log.get("unknown").is_none());
+    }
+    
+    #[test]
+    fn test_retrievals_survive_a_restart() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = RetrievalLogConfig {
+            file: Some(temp_dir.path().join("retrievals.log")),
+            ..RetrievalLogConfig::default()
+        };
+        
+        let log = RetrievalLog::from_config(&config).unwrap();
+        let record = RetrievalRecord::new("get_context", "docs", "deploys", "key:alice", json!({"context": {"entries": []}}));
+        log.record(record.clone());
+        drop(log);
+        
+        let reopened = RetrievalLog::from_config(&config).unwrap();
+        assert_eq!(reopened.get(&record.retrieval_id), Some(record));
+    }
+}
@@ -0,0 +1,315 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::LoggingConfig;
+
+/// An append-only log file that rotates once it grows past a size limit or age.
+///
+/// Rotated files are named `<path>.1` (newest) through `<path>.<max_files>`
+/// (oldest), with a `.gz` suffix when compression is enabled; anything older
+/// is deleted.
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    max_age: Option<Duration>,
+    compress: bool,
+    file: File,
+    size: u64,
+    opened_at: Instant,
+}
+
+impl RotatingFile {
+    /// Open (or create) the log file at `path`.
+    ///
+    /// A `max_bytes` of 0 disables size-based rotation.
+    pub fn open(path: &Path, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        
+        let file = open_append(path)?;
+        let size = file.metadata()?.len();
+        
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            max_files,
+            max_age: None,
+            compress: false,
+            file,
+            size,
+            opened_at: Instant::now(),
+        })
+    }
+    
+    /// Open the log file at `path` using the daemon logging settings
+    pub fn from_config(path: &Path, config: &LoggingConfig) -> io::Result<Self> {
+        let file = Self::open(path, config.max_file_bytes, config.max_files)?
+            .with_compression(config.compress);
+        
+        Ok(match config.rotate_every_secs {
+            Some(secs) => file.with_max_age(Duration::from_secs(secs)),
+            None => file,
+        })
+    }
+    
+    /// Also rotate once the active file has been open for `max_age`
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+    
+    /// Gzip rotated files
+    pub fn with_compression(mut self, compress: bool) -> Self {
+        self.compress = compress;
+        self
+    }
+    
+    /// Get the path of the active log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// Append a line, rotating first if it would exceed the size or age limit
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let bytes = line.len() as u64 + 1;
+        if self.needs_rotation(bytes) {
+            self.rotate()?;
+        }
+        
+        writeln!(self.file, "{}", line)?;
+        self.size += bytes;
+        Ok(())
+    }
+    
+    /// Rotate the active file now
+    pub fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = self.archive_path(self.max_files);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+            
+            for index in (1..self.max_files).rev() {
+                let from = self.archive_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.archive_path(index + 1))?;
+                }
+            }
+            
+            let newest = rotated_path(&self.path, 1);
+            fs::rename(&self.path, &newest)?;
+            if self.compress {
+                compress_file(&newest)?;
+            }
+        }
+        
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        self.opened_at = Instant::now();
+        Ok(())
+    }
+    
+    /// Reopen the file at the configured path.
+    ///
+    /// Used after an external tool such as logrotate has moved the file away.
+    pub fn reopen(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file = open_append(&self.path)?;
+        self.size = self.file.metadata()?.len();
+        self.opened_at = Instant::now();
+        Ok(())
+    }
+    
+    fn needs_rotation(&self, incoming: u64) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        
+        let too_big = self.max_bytes > 0 && self.size + incoming > self.max_bytes;
+        let too_old = self.max_age.is_some_and(|age| self.opened_at.elapsed() >= age);
+        too_big || too_old
+    }
+    
+    fn archive_path(&self, index: usize) -> PathBuf {
+        let path = rotated_path(&self.path, index);
+        if self.compress {
+            gz_path(&path)
+        } else {
+            path
+        }
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.needs_rotation(buf.len() as u64) {
+            self.rotate()?;
+        }
+        
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+    
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Get the path of the `index`-th rotated file for `path`
+pub fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// A cloneable handle to a shared rotating file, usable as a tracing writer
+#[derive(Debug, Clone)]
+pub struct LogWriter {
+    file: Arc<Mutex<RotatingFile>>,
+}
+
+impl LogWriter {
+    pub fn new(file: RotatingFile) -> Self {
+        Self {
+            file: Arc::new(Mutex::new(file)),
+        }
+    }
+    
+    /// Reopen the underlying file
+    pub fn reopen(&self) -> io::Result<()> {
+        self.file.lock().unwrap().reopen()
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.lock().unwrap().write(buf)
+    }
+    
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.lock().unwrap().flush()
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn gz_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".gz");
+    PathBuf::from(name)
+}
+
+/// Gzip `path` into `<path>.gz` and remove the original
+fn compress_file(path: &Path) -> io::Result<PathBuf> {
+    let target = gz_path(path);
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(File::create(&target)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_rotates_when_size_exceeded() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("slow.log");
+        
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        file.write_line("first line").unwrap();
+        file.write_line("second line").unwrap();
+        file.write_line("third line").unwrap();
+        
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third line\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "second line\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 2)).unwrap(), "first line\n");
+    }
+    
+    #[test]
+    fn test_retention_drops_oldest() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("slow.log");
+        
+        let mut file = RotatingFile::open(&path, 1, 1).unwrap();
+        file.write_line("one").unwrap();
+        file.write_line("two").unwrap();
+        file.write_line("three").unwrap();
+        
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "two\n");
+        assert!(!rotated_path(&path, 2).exists());
+    }
+    
+    #[test]
+    fn test_compresses_rotated_files() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("daemon.log");
+        
+        let mut file = RotatingFile::open(&path, 1, 3).unwrap().with_compression(true);
+        file.write_line("old").unwrap();
+        file.write_line("new").unwrap();
+        
+        let archive = gz_path(&rotated_path(&path, 1));
+        assert!(archive.exists());
+        assert!(!rotated_path(&path, 1).exists());
+        
+        let mut decoded = String::new();
+        GzDecoder::new(File::open(&archive).unwrap()).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, "old\n");
+    }
+    
+    #[test]
+    fn test_rotates_by_age() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("daemon.log");
+        
+        let mut file = RotatingFile::open(&path, 0, 2).unwrap().with_max_age(Duration::ZERO);
+        file.write_line("first").unwrap();
+        file.write_line("second").unwrap();
+        
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "first\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+    }
+    
+    #[test]
+    fn test_reopen_after_external_move() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("daemon.log");
+        let moved = temp_dir.path().join("daemon.log.moved");
+        
+        let writer = LogWriter::new(RotatingFile::open(&path, 0, 1).unwrap());
+        let mut handle = writer.clone();
+        handle.write_all(b"before\n").unwrap();
+        
+        fs::rename(&path, &moved).unwrap();
+        writer.reopen().unwrap();
+        handle.write_all(b"after\n").unwrap();
+        
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "before\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "after\n");
+    }
+}
@@ -0,0 +1,263 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
+
+use super::RotatingFile;
+use crate::config::SlowQueryConfig;
+use crate::vector_store::BackendStage;
+
+/// Name of the stage that covers the call into the vector store backend
+pub const BACKEND_STAGE: &str = "vector_store";
+
+/// Argument keys whose values are replaced before logging
+const REDACTED_KEYS: [&str; 4] = ["content", "query", "text", "embedding"];
+
+/// Time spent in a single stage of an operation
+#[derive(Debug, Clone, Serialize)]
+pub struct StageTiming {
+    pub name: String,
+    pub duration_ms: f64,
+}
+
+/// A single slow-query log record
+#[derive(Debug, Clone, Serialize)]
+pub struct SlowQueryEntry {
+    pub timestamp: DateTime<Utc>,
+    pub operation: String,
+    pub collection: Option<String>,
+    /// The client that issued the operation, if known
+    pub client_id: Option<String>,
+    /// Operation parameters with content redacted
+    pub params: Value,
+    pub stages: Vec<StageTiming>,
+    pub backend_latency_ms: Option<f64>,
+    /// Where the backend spent its time, when it reports a breakdown
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub backend_stages: Vec<BackendStage>,
+    pub total_ms: f64,
+}
+
+/// Records how long each stage of an operation takes
+#[derive(Debug)]
+pub struct StageTimer {
+    started: Instant,
+    last: Instant,
+    stages: Vec<StageTiming>,
+    backend_stages: Vec<BackendStage>,
+}
+
+impl StageTimer {
+    /// Start timing an operation
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            last: now,
+            stages: Vec::new(),
+            backend_stages: Vec::new(),
+        }
+    }
+    
+    /// Close the current stage, attributing the time since the previous mark to `name`
+    pub fn stage(&mut self, name: &str) {
+        let now = Instant::now();
+        self.stages.push(StageTiming {
+            name: name.to_string(),
+            duration_ms: duration_ms(now - self.last),
+        });
+        self.last = now;
+    }
+    
+    /// Add the breakdown the backend reported for its part of the operation
+    pub fn backend(&mut self, stages: Vec<BackendStage>) {
+        self.backend_stages.extend(stages);
+    }
+    
+    /// The stages so far and the backend's breakdown, for a caller that asked to see them
+    pub fn explain(&self) -> Value {
+        json!({
+            "stages": self.stages,
+            "backend_stages": self.backend_stages,
+            "slowest_backend_stage": crate::vector_store::slowest_stage(&self.backend_stages),
+            "total_ms": duration_ms(self.elapsed())
+        })
+    }
+    
+    /// Total time elapsed since the timer started
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+    
+    /// Build a log entry from the recorded stages
+    pub fn finish(self, operation: &str, collection: Option<&str>, params: &Value) -> SlowQueryEntry {
+        let backend_latency_ms = self.stages.iter()
+            .filter(|stage| stage.name == BACKEND_STAGE)
+            .map(|stage| stage.duration_ms)
+            .reduce(|a, b| a + b);
+        
+        SlowQueryEntry {
+            timestamp: Utc::now(),
+            operation: operation.to_string(),
+            collection: collection.map(|c| c.to_string()),
+            client_id: None,
+            params: redact_params(params),
+            total_ms: duration_ms(self.elapsed()),
+            stages: self.stages,
+            backend_latency_ms,
+            backend_stages: self.backend_stages,
+        }
+    }
+}
+
+/// Logs operations that exceed a latency threshold to tracing and, optionally,
+/// a dedicated rotating file
+#[derive(Debug)]
+pub struct SlowQueryLog {
+    threshold: Duration,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl SlowQueryLog {
+    /// Create a slow-query log that only reports through tracing
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold,
+            file: None,
+        }
+    }
+    
+    /// Create a slow-query log from configuration
+    pub fn from_config(config: &SlowQueryConfig) -> io::Result<Self> {
+        let log = Self::new(Duration::from_millis(config.threshold_ms));
+        match &config.log_file {
+            Some(path) => Ok(log.with_file(RotatingFile::open(path, config.max_file_bytes, config.max_files)?)),
+            None => Ok(log),
+        }
+    }
+    
+    /// Also write slow queries as JSON lines to `file`
+    pub fn with_file(mut self, file: RotatingFile) -> Self {
+        self.file = Some(Mutex::new(file));
+        self
+    }
+    
+    /// Get the latency threshold
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+    
+    /// Check whether an operation that took `elapsed` counts as slow
+    pub fn is_slow(&self, elapsed: Duration) -> bool {
+        elapsed >= self.threshold
+    }
+    
+    /// Record an entry if it exceeded the threshold; returns whether it was logged
+    pub fn record(&self, entry: &SlowQueryEntry) -> bool {
+        if entry.total_ms < duration_ms(self.threshold) {
+            return false;
+        }
+        
+        let line = serde_json::to_string(entry).unwrap_or_default();
+        warn!(target: "p_mo::slow_query", "{}", line);
+        
+        if let Some(file) = &self.file {
+            let mut file = file.lock().unwrap();
+            if let Err(e) = file.write_line(&line) {
+                error!("Failed to write slow query log: {}", e);
+            }
+        }
+        
+        true
+    }
+}
+
+/// Replace content-bearing values so logs never contain stored text or vectors
+pub fn redact_params(params: &Value) -> Value {
+    match params {
+        Value::Object(map) => {
+            let redacted = map.iter()
+                .map(|(key, value)| {
+                    let value = if REDACTED_KEYS.contains(&key.as_str()) {
+                        redact_value(value)
+                    } else {
+                        redact_params(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect();
+            Value::Object(redacted)
+        },
+        Value::Array(items) => Value::Array(items.iter().map(redact_params).collect()),
+        other => other.clone(),
+    }
+}
+
+fn redact_value(value: &Value) -> Value {
+    match value {
+        Value::String(s) => json!(format!("<redacted {} chars>", s.chars().count())),
+        Value::Array(items) => json!(format!("<redacted {} items>", items.len())),
+        Value::Null => Value::Null,
+        _ => json!("<redacted>"),
+    }
+}
+
+fn duration_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_redact_params() {
+        let params = json!({
+            "collection_id": "docs",
+            "content": "secret text",
+            "embedding": [0.1, 0.2],
+            "nested": {"query": "hello"}
+        });
+        
+        let redacted = redact_params(&params);
+        assert_eq!(redacted["collection_id"], "docs");
+        assert_eq!(redacted["content"], "<redacted 11 chars>");
+        assert_eq!(redacted["embedding"], "<redacted 2 items>");
+        assert_eq!(redacted["nested"]["query"], "<redacted 5 chars>");
+    }
+    
+    #[test]
+    fn test_stage_timer_reports_backend_latency() {
+        let mut timer = StageTimer::start();
+        timer.stage("embed");
+        std::thread::sleep(Duration::from_millis(5));
+        timer.stage(BACKEND_STAGE);
+        
+        let entry = timer.finish("search_knowledge", Some("docs"), &json!({"query": "q"}));
+        assert_eq!(entry.stages.len(), 2);
+        assert!(entry.backend_latency_ms.unwrap() >= 5.0);
+        assert!(entry.total_ms >= entry.backend_latency_ms.unwrap());
+    }
+    
+    #[test]
+    fn test_record_respects_threshold() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("slow.log");
+        
+        let log = SlowQueryLog::new(Duration::from_secs(60))
+            .with_file(RotatingFile::open(&path, 0, 1).unwrap());
+        let entry = StageTimer::start().finish("search_knowledge", None, &json!({}));
+        assert!(!log.record(&entry));
+        
+        let log = SlowQueryLog::new(Duration::ZERO)
+            .with_file(RotatingFile::open(&path, 0, 1).unwrap());
+        assert!(log.record(&entry));
+        
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains("\"operation\":\"search_knowledge\""));
+    }
+}
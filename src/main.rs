@@ -1,15 +1,23 @@
 use p_mo::app::App;
 use p_mo::cli::{Args, CliError};
-use tracing_subscriber;
+use p_mo::otel::{self, OtelConfig};
 
 fn run() -> Result<(), CliError> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
-    
     let args = Args::parse();
-    let mut app = App::new();
+    let mut app = App::new()
+        .with_serving(true)
+        .with_output(args.output_mode())
+        .with_profile(args.profile())
+        .with_container(args.container());
+    let command = args.get_command();
+    
+    // Initialize logging, as JSON lines in a container, exporting traces when
+    // an OTLP endpoint is configured
+    let otel_config = OtelConfig::from_env().with_json_logs(app.container_mode(&command));
+    let _otel_guard = otel::init_tracing(&otel_config)
+        .map_err(|e| CliError::ExecutionError(e.to_string()))?;
     
-    let result = app.execute(args.get_command())?;
+    let result = app.execute(command)?;
     if !result.is_empty() {
         println!("{}", result);
     }
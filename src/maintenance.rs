@@ -0,0 +1,183 @@
+//! Periodic upkeep of the vector store.
+//!
+//! The scheduler wakes every `[maintenance] interval_secs` and purges the
+//! entries that have expired, either by their own `expires_at` or by their
+//! collection's TTL. Search already leaves expired entries out, so a purge
+//! only reclaims space and never changes what callers see. Over the embedded
+//! store, each run then compacts it: it checkpoints the write-ahead log, if
+//! enabled and changed, dropping the records of replaced, deleted and purged
+//! entries, and vacuums the dead data they left in memory.
+
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::config::MaintenanceConfig;
+use crate::vector_store::{purge_expired, CollectionRegistry, CompactionReport, Compactor, VectorStore, VectorStoreError};
+
+/// What one maintenance run did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// Expired entries deleted from each collection that had any
+    pub purged: BTreeMap<String, usize>,
+    /// Collections that could not be purged, with the reason
+    pub failed: BTreeMap<String, String>,
+    /// The compaction, or why it failed; `None` without the embedded store
+    /// or when it found nothing to do
+    pub compaction: Option<Result<CompactionReport, String>>,
+}
+
+/// Runs maintenance every interval while the server is up
+#[derive(Clone)]
+pub struct MaintenanceScheduler {
+    config: MaintenanceConfig,
+    store: Arc<dyn VectorStore>,
+    registry: Arc<CollectionRegistry>,
+    compactor: Option<Arc<Compactor>>,
+}
+
+impl MaintenanceScheduler {
+    pub fn new(config: MaintenanceConfig, store: Arc<dyn VectorStore>, registry: Arc<CollectionRegistry>) -> Self {
+        Self { config, store, registry, compactor: None }
+    }
+    
+    /// Compact with `compactor` after purging, checkpointing the log only if
+    /// anything was logged since the last checkpoint
+    pub fn with_compactor(mut self, compactor: Arc<Compactor>) -> Self {
+        self.compactor = Some(compactor);
+        self
+    }
+    
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.config.interval_secs.max(1))
+    }
+    
+    /// Purge the expired entries of every collection once, then compact
+    pub async fn run_once(&self) -> Result<MaintenanceReport, VectorStoreError> {
+        let now = Utc::now();
+        let mut report = MaintenanceReport::default();
+        for collection in self.store.list_collections().await? {
+            let ttl = self.registry.ttl(&collection);
+            match purge_expired(self.store.as_ref(), &collection, ttl, now).await {
+                Ok(purged) if purged.is_empty() => {},
+                Ok(purged) => {
+                    report.purged.insert(collection, purged.len());
+                },
+                Err(e) => {
+                    report.failed.insert(collection, e.to_string());
+                },
+            }
+        }
+        if let Some(compactor) = &self.compactor {
+            report.compaction = match compactor.compact_changed().await {
+                Ok(compaction) if compaction.is_empty() => None,
+                Ok(compaction) => Some(Ok(compaction)),
+                Err(e) => Some(Err(e.to_string())),
+            };
+        }
+        Ok(report)
+    }
+    
+    /// Run every interval in the background; `None` when maintenance is disabled
+    pub fn spawn(self) -> Option<JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+        
+        Some(tokio::spawn(async move {
+            let interval = self.interval();
+            loop {
+                tokio::time::sleep(interval).await;
+                match self.run_once().await {
+                    Ok(report) => {
+                        for (collection, purged) in &report.purged {
+                            info!(collection = %collection, purged, "Purged expired entries");
+                        }
+                        for (collection, reason) in &report.failed {
+                            warn!(collection = %collection, "Failed to purge expired entries: {}", reason);
+                        }
+                        match &report.compaction {
+                            Some(Ok(compaction)) => {
+                                info!(reclaimed_bytes = compaction.reclaimed_bytes(), "Compacted the store: {}", compaction);
+                            },
+                            Some(Err(reason)) => warn!("Failed to compact the store: {}", reason),
+                            None => {},
+                        }
+                    },
+                    Err(e) => warn!("Maintenance run failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FsyncPolicy;
+    use crate::vector_store::{CollectionInfo, Document, InMemoryVectorStore, WalVectorStore, CREATED_AT_KEY};
+    
+    #[tokio::test]
+    async fn test_run_purges_by_collection_ttl() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        let registry = Arc::new(CollectionRegistry::new());
+        let day_old = (Utc::now() - chrono::Duration::days(1)).to_rfc3339();
+        for collection in ["scratch", "docs"] {
+            store.create_collection(collection, 2).await.unwrap();
+            registry.register(CollectionInfo::new(collection, 2));
+            let mut document = Document::with_placeholder_embedding("note".to_string(), 2);
+            document.metadata.insert(CREATED_AT_KEY.to_string(), day_old.clone());
+            store.insert_document(collection, document).await.unwrap();
+        }
+        registry.set_ttl("scratch", Some(3600)).unwrap();
+        
+        let scheduler = MaintenanceScheduler::new(MaintenanceConfig::default(), store.clone(), registry);
+        let report = scheduler.run_once().await.unwrap();
+        assert_eq!(report.purged, BTreeMap::from([("scratch".to_string(), 1)]));
+        assert!(report.failed.is_empty());
+        
+        assert_eq!(store.count("scratch", &Default::default()).await.unwrap(), 0);
+        assert_eq!(store.count("docs", &Default::default()).await.unwrap(), 1);
+        assert_eq!(scheduler.run_once().await.unwrap(), MaintenanceReport::default());
+    }
+    
+    #[tokio::test]
+    async fn test_run_compacts_the_store() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("wal.log");
+        let embedded = Arc::new(InMemoryVectorStore::new());
+        let (wal, _) = WalVectorStore::open(embedded.clone(), &path, FsyncPolicy::Always, Duration::from_secs(1)).await.unwrap();
+        let wal = Arc::new(wal);
+        let registry = Arc::new(CollectionRegistry::new());
+        wal.create_collection("scratch", 2).await.unwrap();
+        registry.register(CollectionInfo::new("scratch", 2));
+        registry.set_ttl("scratch", Some(3600)).unwrap();
+        let mut document = Document::with_placeholder_embedding("note".to_string(), 2);
+        document.metadata.insert(CREATED_AT_KEY.to_string(), (Utc::now() - chrono::Duration::days(1)).to_rfc3339());
+        wal.insert_document("scratch", document).await.unwrap();
+        
+        let compactor = Arc::new(Compactor::new(embedded).with_wal(wal.clone()));
+        let scheduler = MaintenanceScheduler::new(MaintenanceConfig::default(), wal, registry).with_compactor(compactor);
+        let report = scheduler.run_once().await.unwrap();
+        assert_eq!(report.purged, BTreeMap::from([("scratch".to_string(), 1)]));
+        // The insert and the delete purging it leave only the collection
+        let compaction = report.compaction.unwrap().unwrap();
+        assert_eq!(compaction.vacuum.indexes_rebuilt, 1);
+        let checkpoint = compaction.checkpoint.unwrap();
+        assert_eq!((checkpoint.records_before, checkpoint.records_after), (3, 1));
+        assert!(checkpoint.reclaimed_bytes() > 0);
+        assert_eq!(checkpoint.bytes_after, std::fs::metadata(&path).unwrap().len());
+        
+        assert_eq!(scheduler.run_once().await.unwrap(), MaintenanceReport::default());
+    }
+    
+    #[test]
+    fn test_disabled_scheduler_does_not_spawn() {
+        let config = MaintenanceConfig { enabled: false, ..Default::default() };
+        let scheduler = MaintenanceScheduler::new(config, Arc::new(InMemoryVectorStore::new()), Arc::new(CollectionRegistry::new()));
+        assert!(scheduler.spawn().is_none());
+    }
+}
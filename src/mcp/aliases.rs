@@ -0,0 +1,193 @@
+//! Deprecated tool names and argument spellings that keep working.
+//!
+//! When a tool is renamed or its arguments change, the old name stays in
+//! [`ALIASES`] with the catalog version that deprecated it. A call to the old
+//! name is migrated, arguments renamed and new required ones defaulted, then
+//! handled by the tool it now names; the result carries a deprecation notice
+//! in its `_meta` and the call is logged, so agent configs written against
+//! an older catalog keep working while their owners find out.
+//!
+//! ListTools lists aliases, with their notices, to clients that ask with
+//! `includeDeprecated` or say with `toolsVersion` that they were written
+//! against a catalog from before the rename.
+
+use serde_json::{json, Value};
+
+use super::tools::tool_spec;
+
+/// Version of the tool catalog, raised whenever a tool is renamed or its
+/// arguments change in a way an alias has to cover
+pub const TOOLS_VERSION: u32 = 2;
+
+/// An old tool name and how to turn its calls into calls of the current tool
+pub struct ToolAlias {
+    /// The deprecated name
+    pub name: &'static str,
+    /// The tool now handling its calls
+    pub tool: &'static str,
+    /// Catalog version that deprecated the name
+    pub deprecated_in: u32,
+    /// Arguments renamed since, as (old, new)
+    pub renamed_arguments: &'static [(&'static str, &'static str)],
+    /// Builds values for arguments the old name did not take, used when a call leaves them out
+    pub defaults: fn() -> Value,
+}
+
+impl ToolAlias {
+    /// `arguments` of a call to the old name, as the current tool takes them.
+    /// An argument given under both its old and new name keeps the new one.
+    pub fn migrate(&self, arguments: &Value) -> Value {
+        let Some(given) = arguments.as_object() else { return arguments.clone() };
+        let mut migrated = given.clone();
+        for (old, new) in self.renamed_arguments {
+            if let Some(value) = migrated.remove(*old) {
+                migrated.entry(*new).or_insert(value);
+            }
+        }
+        if let Value::Object(defaults) = (self.defaults)() {
+            for (name, value) in defaults {
+                migrated.entry(name).or_insert(value);
+            }
+        }
+        Value::Object(migrated)
+    }
+    
+    pub fn notice(&self) -> String {
+        format!("Tool '{}' is deprecated; call '{}' instead", self.name, self.tool)
+    }
+    
+    /// The notice as attached to results and ListTools entries
+    pub fn to_json(&self) -> Value {
+        let renamed: serde_json::Map<String, Value> = self.renamed_arguments.iter()
+            .map(|(old, new)| (old.to_string(), json!(new)))
+            .collect();
+        json!({
+            "alias": self.name,
+            "replacedBy": self.tool,
+            "deprecatedIn": self.deprecated_in,
+            "renamedArguments": renamed,
+            "message": self.notice(),
+        })
+    }
+}
+
+fn no_defaults() -> Value {
+    json!({})
+}
+
+/// Every deprecated name, in the order they were deprecated
+pub const ALIASES: &[ToolAlias] = &[
+    ToolAlias {
+        name: "add_entry",
+        tool: "add_knowledge_entry",
+        deprecated_in: 2,
+        renamed_arguments: &[("collection", "collection_id"), ("id", "entry_id")],
+        defaults: no_defaults,
+    },
+    ToolAlias {
+        name: "search",
+        tool: "search_knowledge",
+        deprecated_in: 2,
+        renamed_arguments: &[("collection", "collection_id"), ("top_k", "limit")],
+        defaults: no_defaults,
+    },
+    ToolAlias {
+        name: "search_memories",
+        tool: "recall",
+        deprecated_in: 2,
+        renamed_arguments: &[("top_k", "k")],
+        // The old name searched all of a user's memories, never just the session's
+        defaults: || json!({"scope": "user"}),
+    },
+];
+
+/// How ListTools describes `alias`: the tool it names, under the old name
+/// and with the deprecation notice
+pub fn listing(alias: &ToolAlias) -> Option<Value> {
+    let tool = tool_spec(alias.tool)?;
+    let mut listed = tool.to_json();
+    listed["name"] = json!(alias.name);
+    listed["description"] = json!(format!("Deprecated: {}. {}", alias.notice(), tool.description));
+    listed["deprecation"] = alias.to_json();
+    Some(listed)
+}
+
+/// `response` to a call of `alias` with the deprecation notice in its result's `_meta`
+pub fn with_notice(response: String, alias: &ToolAlias) -> String {
+    let Ok(mut parsed) = serde_json::from_str::<Value>(&response) else { return response };
+    match parsed.get_mut("result").and_then(Value::as_object_mut) {
+        Some(result) => {
+            let meta = result.entry("_meta").or_insert_with(|| json!({}));
+            meta["deprecation"] = alias.to_json();
+            parsed.to_string()
+        },
+        None => response,
+    }
+}
+
+/// Look up a deprecated name
+pub fn alias(name: &str) -> Option<&'static ToolAlias> {
+    ALIASES.iter().find(|alias| alias.name == name)
+}
+
+/// The aliases a client written against catalog `version` may still call by name
+pub fn aliases_since(version: u32) -> impl Iterator<Item = &'static ToolAlias> {
+    ALIASES.iter().filter(move |alias| alias.deprecated_in > version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_migrate_renames_and_defaults() {
+        let search = alias("search_memories").unwrap();
+        let migrated = search.migrate(&json!({"query": "deploys", "top_k": 3}));
+        assert_eq!(migrated, json!({"query": "deploys", "k": 3, "scope": "user"}));
+        
+        // New spellings win over old ones, and given values over defaults
+        let migrated = search.migrate(&json!({"query": "deploys", "top_k": 3, "k": 5, "scope": "session"}));
+        assert_eq!(migrated, json!({"query": "deploys", "k": 5, "scope": "session"}));
+    }
+    
+    #[test]
+    fn test_aliases_name_current_tools() {
+        for alias in ALIASES {
+            assert!(tool_spec(alias.tool).is_some(), "alias {} names unknown tool {}", alias.name, alias.tool);
+            assert!(tool_spec(alias.name).is_none(), "alias {} shadows a tool", alias.name);
+            assert!(alias.deprecated_in <= TOOLS_VERSION);
+        }
+        assert_eq!(aliases_since(TOOLS_VERSION).count(), 0);
+        assert_eq!(aliases_since(1).count(), ALIASES.len());
+    }
+    
+    #[tokio::test]
+    async fn test_deprecated_names_are_served_and_listed() {
+        use crate::mcp::{ProgmoMcpServer, ServerConfig};
+        use crate::text_processing::embedding::MockEmbeddingGenerator;
+        use crate::vector_store::{CollectionInfo, InMemoryVectorStore, VectorStore};
+        use std::sync::Arc;
+        
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("docs", 8).await.unwrap();
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store)
+            .with_embedding_provider(Arc::new(MockEmbeddingGenerator::new(8)))
+            .unwrap();
+        server.registry().register(CollectionInfo::new("docs", 8));
+        
+        let call = json!({"jsonrpc": "2.0", "id": 1, "method": "CallTool", "params": {"name": "search", "arguments": {"query": "rotation", "collection": "docs", "top_k": 3}}});
+        let response: Value = serde_json::from_str(&server.handle_request(&call.to_string()).await).unwrap();
+        assert!(response["error"].is_null(), "{}", response);
+        assert_eq!(response["result"]["_meta"]["deprecation"]["replacedBy"], "search_knowledge");
+        
+        let list = |params: Value| json!({"jsonrpc": "2.0", "id": 2, "method": "ListTools", "params": params}).to_string();
+        let current: Value = serde_json::from_str(&server.handle_request(&list(json!({}))).await).unwrap();
+        assert!(current["result"]["toolsVersion"].is_null());
+        assert!(!current["result"]["tools"].as_array().unwrap().iter().any(|tool| tool["name"] == "search"));
+        
+        let old: Value = serde_json::from_str(&server.handle_request(&list(json!({"toolsVersion": 1}))).await).unwrap();
+        assert_eq!(old["result"]["toolsVersion"], TOOLS_VERSION);
+        let search = old["result"]["tools"].as_array().unwrap().iter().find(|tool| tool["name"] == "search").unwrap();
+        assert_eq!(search["deprecation"]["renamedArguments"]["top_k"], "limit");
+    }
+}
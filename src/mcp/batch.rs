@@ -0,0 +1,282 @@
+//! Adding many entries in one call, with an outcome per entry
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+use super::{document_bytes, error_response, is_dry_run, missing_argument, store_error_response, PreparedEntry, ProgmoMcpServer, ToolError};
+use crate::context::RequestContext;
+use crate::vector_store::batch::ROLLED_BACK;
+use crate::vector_store::{BatchItem, Document};
+
+/// Largest number of entries a single add_knowledge_entries call accepts
+pub const MAX_BATCH_ENTRIES: usize = 100;
+
+/// Where one entry of a batch ended up
+enum ItemOutcome {
+    Inserted(Value),
+    Failed(ToolError),
+    /// Not written because an atomic batch was abandoned before any write
+    Skipped,
+    /// Written, then undone because another entry of an atomic batch failed
+    RolledBack,
+}
+
+impl ItemOutcome {
+    fn to_json(&self, index: usize, id: Option<&str>) -> Value {
+        let mut item = json!({ "index": index, "id": id });
+        match self {
+            ItemOutcome::Inserted(entry) => {
+                item["status"] = json!("inserted");
+                item["entry"] = entry.clone();
+            },
+            ItemOutcome::Failed(error) => {
+                item["status"] = json!("failed");
+                item["error"] = error.to_json();
+            },
+            ItemOutcome::Skipped => item["status"] = json!("skipped"),
+            ItemOutcome::RolledBack => item["status"] = json!("rolled_back"),
+        }
+        item
+    }
+}
+
+impl ProgmoMcpServer {
+    /// Handle an add_knowledge_entries tool call.
+    ///
+    /// Each entry is validated and embedded as add_knowledge_entry would and
+    /// reported on its own, so a caller can resend only the failures. With
+    /// `atomic` nothing is kept unless every entry is stored.
+    pub(super) async fn handle_add_knowledge_entries(&self, ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let collection_id = match arguments.get("collection_id").and_then(|collection_id| collection_id.as_str()) {
+            Some(collection_id) => collection_id,
+            None => return missing_argument(id, "collection_id"),
+        };
+        let entries = match arguments.get("entries").and_then(|entries| entries.as_array()) {
+            Some(entries) if !entries.is_empty() => entries,
+            _ => return error_response(id, -32602, "Invalid params: entries must be a non-empty array".to_string()),
+        };
+        if entries.len() > MAX_BATCH_ENTRIES {
+            return error_response(id, -32602, format!("Invalid params: at most {} entries per call", MAX_BATCH_ENTRIES));
+        }
+        let atomic = arguments.get("atomic").and_then(|atomic| atomic.as_bool()).unwrap_or(false);
+        
+        let mut prepared = Vec::with_capacity(entries.len());
+        for (index, entry) in entries.iter().enumerate() {
+            prepared.push(if entry.is_object() {
+                self.prepare_entry(collection_id, entry).await
+            } else {
+                Err(ToolError::invalid(format!("Invalid params: entries[{}] must be an object", index)))
+            });
+        }
+        let ids: Vec<Option<String>> = prepared.iter()
+            .map(|entry| entry.as_ref().ok().map(|entry| entry.documents[0].id.clone()))
+            .collect();
+        
+        if is_dry_run(arguments) {
+            let items: Vec<Value> = prepared.iter().enumerate()
+                .map(|(index, entry)| match entry {
+                    Ok(entry) => json!({ "index": index, "id": entry.documents[0].id, "status": "planned", "entry": entry.summary() }),
+                    Err(error) => ItemOutcome::Failed(error.clone()).to_json(index, None),
+                })
+                .collect();
+            let documents: Vec<&Document> = prepared.iter().flatten().flat_map(|entry| &entry.documents).collect();
+            let planned: Vec<&str> = documents.iter().map(|document| document.id.as_str()).collect();
+            let bytes: usize = documents.iter().map(|document| document_bytes(document)).sum();
+            return json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "content": [
+                        {
+                            "type": "text",
+                            "text": format!("Dry run: would add {} of {} entries", ids.iter().flatten().count(), entries.len())
+                        }
+                    ],
+                    "plan": {
+                        "dry_run": true,
+                        "action": "add",
+                        "collection_id": collection_id,
+                        "atomic": atomic,
+                        "ids": planned,
+                        "chunks": planned.len(),
+                        "bytes": bytes
+                    },
+                    "items": items
+                }
+            }).to_string();
+        }
+        
+        let mut outcomes: Vec<Option<ItemOutcome>> = (0..entries.len()).map(|_| None).collect();
+        let mut groups: BTreeMap<String, Vec<(usize, PreparedEntry)>> = BTreeMap::new();
+        for (index, entry) in prepared.into_iter().enumerate() {
+            match entry {
+                Ok(entry) => groups.entry(entry.collection_id.clone()).or_default().push((index, entry)),
+                Err(error) => outcomes[index] = Some(ItemOutcome::Failed(error)),
+            }
+        }
+        
+        // An atomic batch with an invalid entry is abandoned before anything is written
+        if atomic && outcomes.iter().any(|outcome| outcome.is_some()) {
+            let outcomes = outcomes.into_iter().map(|outcome| outcome.unwrap_or(ItemOutcome::Skipped)).collect();
+            return batch_response(id, collection_id, atomic, &ids, outcomes);
+        }
+        if atomic && groups.len() > 1 {
+            let collections: Vec<&str> = groups.keys().map(|collection| collection.as_str()).collect();
+            return error_response(id, -32602, format!(
+                "Invalid params: an atomic batch must be stored in one collection, but its entries were routed to {}",
+                collections.join(", ")
+            ));
+        }
+        
+        for (collection, group) in groups {
+            // Chunked entries contribute a document per chunk; remember whose each one is
+            let mut summaries = BTreeMap::new();
+            let mut owners = Vec::new();
+            let mut documents = Vec::new();
+            for (index, entry) in group {
+                summaries.insert(index, entry.summary());
+                owners.extend(std::iter::repeat_n(index, entry.documents.len()));
+                documents.extend(entry.documents);
+            }
+            
+            match self.vector_store.batch_insert(&collection, documents, atomic).await {
+                Ok(results) => {
+                    let mut by_entry: BTreeMap<usize, Vec<BatchItem>> = BTreeMap::new();
+                    for (index, result) in owners.into_iter().zip(results) {
+                        by_entry.entry(index).or_default().push(result);
+                    }
+                    for (index, results) in by_entry {
+                        let summary = summaries.remove(&index).unwrap_or_default();
+                        outcomes[index] = Some(self.entry_outcome(&collection, summary, results).await);
+                    }
+                },
+                // Only a failed rollback fails an atomic batch as a whole
+                Err(e) if atomic => return store_error_response(id, &e),
+                Err(e) => {
+                    for index in summaries.into_keys() {
+                        outcomes[index] = Some(ItemOutcome::Failed(ToolError::store(&e)));
+                    }
+                },
+            }
+        }
+        
+        let outcomes: Vec<ItemOutcome> = outcomes.into_iter().map(|outcome| outcome.unwrap_or(ItemOutcome::Skipped)).collect();
+        let inserted = outcomes.iter().filter(|outcome| matches!(outcome, ItemOutcome::Inserted(_))).count();
+        info!(
+            request_id = %ctx.request_id,
+            client_id = %ctx.client_label(),
+            collection = %collection_id,
+            inserted = inserted,
+            failed = outcomes.len() - inserted,
+            atomic = atomic,
+            "Added knowledge entries"
+        );
+        batch_response(id, collection_id, atomic, &ids, outcomes)
+    }
+    
+    /// The outcome of an entry from the results of its documents. An entry
+    /// is only stored whole, so the chunks kept when others failed are removed.
+    async fn entry_outcome(&self, collection: &str, summary: Value, results: Vec<BatchItem>) -> ItemOutcome {
+        let error = match results.iter().find_map(|result| result.error.as_deref().filter(|error| *error != ROLLED_BACK)) {
+            Some(error) => error.to_string(),
+            None if results.iter().all(BatchItem::is_inserted) => return ItemOutcome::Inserted(summary),
+            None => return ItemOutcome::RolledBack,
+        };
+        for result in results.iter().filter(|result| result.is_inserted()) {
+            if let Err(e) = self.vector_store.delete_document(collection, &result.id).await {
+                warn!(collection = %collection, chunk_id = %result.id, "Failed to remove chunk of a partly stored entry: {}", e);
+            }
+        }
+        ItemOutcome::Failed(ToolError::new(-32603, format!("Internal error: {}", error)))
+    }
+}
+
+/// Build the response to a batch: counts plus the outcome of every entry in request order
+fn batch_response(id: &Value, collection_id: &str, atomic: bool, ids: &[Option<String>], outcomes: Vec<ItemOutcome>) -> String {
+    let inserted = outcomes.iter().filter(|outcome| matches!(outcome, ItemOutcome::Inserted(_))).count();
+    let items: Vec<Value> = outcomes.iter().enumerate()
+        .map(|(index, outcome)| outcome.to_json(index, ids[index].as_deref()))
+        .collect();
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "result": {
+            "content": [
+                {
+                    "type": "text",
+                    "text": format!("Added {} of {} entries to {}", inserted, outcomes.len(), collection_id)
+                }
+            ],
+            "collection_id": collection_id,
+            "atomic": atomic,
+            "inserted": inserted,
+            "failed": outcomes.len() - inserted,
+            "items": items
+        }
+    }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::ServerConfig;
+    use crate::vector_store::{InMemoryVectorStore, VectorStore};
+    use std::sync::Arc;
+    
+    fn batch_request(entries: Value, atomic: bool) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "id": "1",
+            "method": "CallTool",
+            "params": {
+                "name": "add_knowledge_entries",
+                "arguments": {"collection_id": "notes", "entries": entries, "atomic": atomic}
+            }
+        }).to_string()
+    }
+    
+    async fn setup() -> (Arc<InMemoryVectorStore>, ProgmoMcpServer) {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("notes", 384).await.unwrap();
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store.clone());
+        (store, server)
+    }
+    
+    #[tokio::test]
+    async fn test_reports_each_entry() {
+        let (store, server) = setup().await;
+        let entries = json!([
+            {"entry_id": "first", "title": "First", "content": "kept"},
+            {"entry_id": "second", "title": "Second"},
+            {"entry_id": "third", "title": "Third", "content": "also kept"}
+        ]);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&batch_request(entries, false)).await).unwrap();
+        let result = &response["result"];
+        assert_eq!(result["inserted"], 2);
+        assert_eq!(result["failed"], 1);
+        assert_eq!(result["items"][0]["status"], "inserted");
+        assert_eq!(result["items"][1]["status"], "failed");
+        assert_eq!(result["items"][1]["error"]["code"], -32602);
+        assert_eq!(result["items"][2]["status"], "inserted");
+        assert!(store.exists("notes", "first").await.unwrap());
+        assert!(store.exists("notes", "third").await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_atomic_batch_writes_nothing_on_failure() {
+        let (store, server) = setup().await;
+        let entries = json!([
+            {"entry_id": "first", "title": "First", "content": "kept"},
+            {"entry_id": "second", "title": "Second"}
+        ]);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&batch_request(entries, true)).await).unwrap();
+        let result = &response["result"];
+        assert_eq!(result["inserted"], 0);
+        assert_eq!(result["items"][0]["status"], "skipped");
+        assert_eq!(result["items"][1]["status"], "failed");
+        assert!(!store.exists("notes", "first").await.unwrap());
+    }
+}
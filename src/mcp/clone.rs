@@ -0,0 +1,123 @@
+//! Copying collections into new ones, e.g. to build staging datasets
+
+use serde_json::{json, Value};
+
+use super::{error_response, is_dry_run, missing_argument, store_error_response, ProgmoMcpServer};
+use crate::context::RequestContext;
+use crate::progress::Progress;
+use crate::vector_store::{clone_collection, CloneOptions, CollectionInfo, MetadataFilter};
+
+impl ProgmoMcpServer {
+    /// Handle a clone_collection tool call.
+    ///
+    /// Copies the entries of `source_collection` matching `filter`, or a
+    /// random `sample_size` of them, into the new `target_collection`. A
+    /// registered source's settings are registered for the target too.
+    pub(super) async fn handle_clone_collection(&self, _ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let source = match arguments.get("source_collection").and_then(|source| source.as_str()) {
+            Some(source) => source,
+            None => return missing_argument(id, "source_collection"),
+        };
+        let target = match arguments.get("target_collection").and_then(|target| target.as_str()) {
+            Some(target) if !target.trim().is_empty() => target,
+            _ => return missing_argument(id, "target_collection"),
+        };
+        let filter = match arguments.get("filter").map(MetadataFilter::from_json).unwrap_or(Ok(MetadataFilter::default())) {
+            Ok(filter) => filter,
+            Err(message) => return error_response(id, -32602, format!("Invalid params: {}", message)),
+        };
+        let sample = match arguments.get("sample_size") {
+            None | Some(Value::Null) => None,
+            Some(sample) => match sample.as_u64() {
+                Some(sample) if sample > 0 => Some(sample as usize),
+                _ => return error_response(id, -32602, "Invalid params: sample_size must be a positive integer".to_string()),
+            },
+        };
+        let seed = match arguments.get("seed") {
+            None | Some(Value::Null) => None,
+            Some(seed) => match seed.as_u64() {
+                Some(seed) => Some(seed),
+                None => return error_response(id, -32602, "Invalid params: seed must be a non-negative integer".to_string()),
+            },
+        };
+        
+        let dry_run = is_dry_run(arguments);
+        let seed = self.determinism.sample_seed(seed);
+        let options = CloneOptions { filter, sample, seed, dry_run };
+        let outcome = match clone_collection(self.vector_store.as_ref(), source, target, &options, &Progress::hidden()).await {
+            Ok(outcome) => outcome,
+            Err(e) => return store_error_response(id, &e),
+        };
+        if !dry_run && outcome.copied > 0 {
+            if let Some(info) = self.registry.get(source) {
+                self.registry.register(CollectionInfo { name: target.to_string(), ..info });
+            }
+        }
+        
+        let text = if dry_run {
+            format!("Dry run: would copy {} of {} matching entries from {} to {}", outcome.copied, outcome.matched, source, target)
+        } else {
+            format!("Copied {} of {} matching entries from {} to {}", outcome.copied, outcome.matched, source, target)
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": text
+                    }
+                ],
+                "matched": outcome.matched,
+                "copied": outcome.copied,
+                "dry_run": dry_run
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::ServerConfig;
+    use crate::vector_store::{Document, InMemoryVectorStore, VectorStore};
+    use std::sync::Arc;
+    
+    fn call(id: u64, arguments: Value) -> String {
+        json!({"jsonrpc": "2.0", "id": id, "method": "CallTool", "params": {"name": "clone_collection", "arguments": arguments}}).to_string()
+    }
+    
+    #[tokio::test]
+    async fn test_clone_collection_tool() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("prod", 2).await.unwrap();
+        for (id, tag) in [("a", "ops"), ("b", "web"), ("c", "ops")] {
+            let mut document = Document::with_placeholder_embedding(id.to_string(), 2).with_tags(&[tag.to_string()]);
+            document.id = id.to_string();
+            store.insert_document("prod", document).await.unwrap();
+        }
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store.clone());
+        server.registry.register(CollectionInfo::new("prod", 2));
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(1, json!({
+            "source_collection": "prod",
+            "target_collection": "staging",
+            "filter": {"tags": ["ops"]}
+        }))).await).unwrap();
+        assert_eq!(response["result"]["copied"], 2);
+        assert!(store.exists("staging", "c").await.unwrap());
+        assert!(!store.exists("staging", "b").await.unwrap());
+        assert_eq!(server.registry.get("staging").unwrap().vector_size, 2);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(2, json!({
+            "source_collection": "prod", "target_collection": "sample", "sample_size": 0
+        }))).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(3, json!({
+            "source_collection": "prod", "target_collection": "staging"
+        }))).await).unwrap();
+        assert!(response["error"]["message"].as_str().unwrap().contains("already exists"));
+    }
+}
@@ -0,0 +1,173 @@
+//! Structured tool result content: JSON parts with a schema and links to knowledge:// resources
+
+use serde_json::{json, Value};
+
+/// Prefix of the resource URI of a single entry
+pub const ENTRY_URI_PREFIX: &str = "knowledge://collections/";
+
+/// How a tool call wants its result content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentFormat {
+    /// A single text part, readable by every client
+    #[default]
+    Text,
+    /// The text part followed by a typed JSON part and a resource link per entry
+    Structured,
+}
+
+impl ContentFormat {
+    /// Read the optional `content_format` argument of a tool call
+    pub fn from_arguments(arguments: &Value) -> Result<Self, String> {
+        match arguments.get("content_format").and_then(|format| format.as_str()) {
+            None | Some("text") => Ok(Self::Text),
+            Some("structured") => Ok(Self::Structured),
+            Some(other) => Err(format!(
+                "Invalid params: unknown content_format '{}'; expected 'text' or 'structured'", other
+            )),
+        }
+    }
+}
+
+/// The JSON schema property tools accepting `content_format` advertise
+pub fn content_format_property() -> Value {
+    json!({
+        "type": "string",
+        "enum": ["text", "structured"],
+        "description": "'structured' adds a json part and resource links to the text result"
+    })
+}
+
+/// The resource URI of an entry, readable with ReadResource
+pub fn entry_uri(collection_id: &str, entry_id: &str) -> String {
+    format!("{}{}/entries/{}", ENTRY_URI_PREFIX, collection_id, entry_id)
+}
+
+/// Split an entry resource URI into its collection and entry id
+pub fn parse_entry_uri(uri: &str) -> Option<(&str, &str)> {
+    let rest = uri.strip_prefix(ENTRY_URI_PREFIX)?;
+    let (collection_id, entry_id) = rest.split_once("/entries/")?;
+    if collection_id.is_empty() || entry_id.is_empty() {
+        return None;
+    }
+    Some((collection_id, entry_id))
+}
+
+/// A plain text content part
+pub fn text_part(text: impl Into<String>) -> Value {
+    json!({ "type": "text", "text": text.into() })
+}
+
+/// A JSON content part, described by `schema`
+pub fn json_part(value: Value, schema: Value) -> Value {
+    json!({ "type": "json", "json": value, "schema": schema })
+}
+
+/// A link to an entry that clients can resolve with ReadResource
+pub fn resource_link(collection_id: &str, entry_id: &str, title: Option<&str>) -> Value {
+    let mut link = json!({
+        "type": "resource_link",
+        "uri": entry_uri(collection_id, entry_id),
+        "name": title.unwrap_or(entry_id),
+        "mimeType": "text/plain"
+    });
+    if let Some(title) = title {
+        link["title"] = json!(title);
+    }
+    link
+}
+
+/// Schema of the json part of search_knowledge results
+pub fn search_results_schema() -> Value {
+    json!({
+        "type": "array",
+        "items": {
+            "type": "object",
+            "properties": {
+                "id": {"type": "string"},
+                "title": {"type": ["string", "null"]},
+                "content": {"type": "string"},
+                "score": {"type": "number"},
+                "relevance": {"type": "number", "minimum": 0, "maximum": 1},
+                "uri": {"type": "string"},
+                "injection_risk": {"type": "number"},
+                "injection_signals": {"type": "array", "items": {"type": "string"}},
+                "attachments": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "url": {"type": "string"},
+                            "content_type": {"type": "string"},
+                            "filename": {"type": ["string", "null"]}
+                        },
+                        "required": ["url", "content_type"]
+                    }
+                }
+            },
+            "required": ["id", "content", "score", "uri"]
+        }
+    })
+}
+
+/// Schema of the json part of get_context results
+pub fn context_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "model": {"type": "string"},
+            "budget": {"type": "integer"},
+            "tokens_used": {"type": "integer"},
+            "omitted": {"type": "integer"},
+            "entries": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "string"},
+                        "score": {"type": "number"},
+                        "relevance": {"type": "number", "minimum": 0, "maximum": 1},
+                        "tokens": {"type": "integer"},
+                        "uri": {"type": "string"},
+                        "injection_risk": {"type": "number"},
+                        "injection_signals": {"type": "array", "items": {"type": "string"}}
+                    },
+                    "required": ["id", "score", "tokens", "uri"]
+                }
+            }
+        },
+        "required": ["model", "budget", "tokens_used", "omitted", "entries"]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_content_format_from_arguments() {
+        assert_eq!(ContentFormat::from_arguments(&json!({})), Ok(ContentFormat::Text));
+        assert_eq!(ContentFormat::from_arguments(&json!({"content_format": "structured"})), Ok(ContentFormat::Structured));
+        assert!(ContentFormat::from_arguments(&json!({"content_format": "html"})).is_err());
+    }
+    
+    #[test]
+    fn test_entry_uri_round_trip() {
+        let uri = entry_uri("notes", "abc-123");
+        assert_eq!(uri, "knowledge://collections/notes/entries/abc-123");
+        assert_eq!(parse_entry_uri(&uri), Some(("notes", "abc-123")));
+        assert_eq!(parse_entry_uri("knowledge://collections/notes"), None);
+        assert_eq!(parse_entry_uri("knowledge://collections//entries/x"), None);
+    }
+    
+    #[test]
+    fn test_resource_link_falls_back_to_id() {
+        let link = resource_link("notes", "abc", None);
+        assert_eq!(link["type"], "resource_link");
+        assert_eq!(link["name"], "abc");
+        assert!(link.get("title").is_none());
+        
+        let titled = resource_link("notes", "abc", Some("Rotating keys"));
+        assert_eq!(titled["name"], "Rotating keys");
+        assert_eq!(titled["title"], "Rotating keys");
+    }
+}
@@ -0,0 +1,194 @@
+//! Ingestion of conversation transcripts as searchable context
+
+use serde_json::{json, Value};
+use tracing::info;
+
+use super::{error_response, invalid_response, missing_argument, store_error_code, store_error_message, ErrorKind, ProgmoMcpServer, ToolError};
+use crate::config::PiiAction;
+use crate::context::RequestContext;
+use crate::text_processing::conversation::DEFAULT_CONVERSATION_CHUNK_CHARS;
+use crate::text_processing::pii::{detect_pii, mask_pii, pii_kinds};
+use crate::text_processing::{chunk_conversation, ConversationMessage};
+use crate::vector_store::{Document, CONVERSATION_ID_KEY, CREATED_AT_KEY, PII_KINDS_KEY, SENSITIVE_KEY, SPEAKER_KEY, TIMESTAMP_KEY, TURN_END_KEY, TURN_INDEX_KEY};
+
+impl ProgmoMcpServer {
+    /// Handle an ingest_conversation tool call
+    pub(super) async fn handle_ingest_conversation(&self, ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let collection_id = match arguments.get("collection_id").and_then(|collection_id| collection_id.as_str()) {
+            Some(collection_id) => collection_id,
+            None => return missing_argument(id, "collection_id"),
+        };
+        
+        let messages: Vec<ConversationMessage> = match arguments.get("messages").map(|messages| serde_json::from_value(messages.clone())) {
+            Some(Ok(messages)) => messages,
+            Some(Err(e)) => return error_response(id, -32602, format!("Invalid params: messages must be an array of {{role, content, timestamp}}: {}", e)),
+            None => return missing_argument(id, "messages"),
+        };
+        
+        let conversation_id = arguments.get("conversation_id")
+            .and_then(|conversation_id| conversation_id.as_str())
+            .map(|conversation_id| conversation_id.to_string())
+            .unwrap_or_else(|| self.determinism.uuid().to_string());
+        
+        let max_chars = arguments.get("max_chunk_chars")
+            .and_then(|max_chars| max_chars.as_u64())
+            .map(|max_chars| max_chars as usize)
+            .unwrap_or(DEFAULT_CONVERSATION_CHUNK_CHARS);
+        
+        let chunks = chunk_conversation(&messages, max_chars);
+        if chunks.is_empty() {
+            return error_response(id, -32602, "Invalid params: conversation has no non-empty messages".to_string());
+        }
+        
+        // Prepare every chunk before inserting any, so a rejected chunk leaves nothing behind
+        let pii_action = self.pii_policy.action_for(collection_id);
+        let created_at = self.determinism.now().to_rfc3339();
+        let mut documents = Vec::with_capacity(chunks.len());
+        for chunk in &chunks {
+            let mut content = chunk.content.clone();
+            let mut document_metadata = vec![
+                (CREATED_AT_KEY, created_at.clone()),
+                (CONVERSATION_ID_KEY, conversation_id.clone()),
+                (SPEAKER_KEY, chunk.speakers.join(",")),
+                (TURN_INDEX_KEY, chunk.turn_start.to_string()),
+                (TURN_END_KEY, chunk.turn_end.to_string()),
+            ];
+            if let Some(timestamp) = &chunk.started_at {
+                document_metadata.push((TIMESTAMP_KEY, timestamp.clone()));
+            }
+            
+            if let Some(action) = pii_action {
+                let matches = detect_pii(&content);
+                if !matches.is_empty() {
+                    let kinds: Vec<&str> = pii_kinds(&matches).iter().map(|kind| kind.as_str()).collect();
+                    match action {
+                        PiiAction::Block => return invalid_response(id, ErrorKind::PiiDetected, format!("Invalid params: turns {}-{} contain PII ({})", chunk.turn_start, chunk.turn_end, kinds.join(", "))),
+                        PiiAction::Mask => content = mask_pii(&content, &matches),
+                        PiiAction::Allow => {},
+                        PiiAction::Tag => {
+                            document_metadata.push((SENSITIVE_KEY, "true".to_string()));
+                            document_metadata.push((PII_KINDS_KEY, kinds.join(",")));
+                        },
+                    }
+                }
+            }
+            
+            let embedding = match self.embed(&content) {
+                Ok(embedding) => embedding,
+                Err(e) => return error_response(id, -32603, format!("Internal error: {}", e)),
+            };
+            if let Err(e) = self.validate_embedding(collection_id, &embedding) {
+                return e.response(id);
+            }
+            if let Err(e) = self.check_stored_embedding(collection_id, &embedding) {
+                return e.response(id);
+            }
+            
+            let mut document = Document {
+                id: self.determinism.uuid().to_string(),
+                content,
+                embedding,
+                metadata: Default::default(),
+            }
+            .with_title(&format!("Conversation {} turns {}-{}", conversation_id, chunk.turn_start, chunk.turn_end));
+            document.metadata.extend(document_metadata.into_iter().map(|(key, value)| (key.to_string(), value)));
+            documents.push(document);
+        }
+        
+        if super::is_dry_run(arguments) {
+            let ids: Vec<String> = documents.iter().map(|document| document.id.clone()).collect();
+            let bytes = documents.iter().map(super::document_bytes).sum();
+            return super::plan_response(id, "add", collection_id, &ids, chunks.len(), bytes);
+        }
+        
+        let mut entries = Vec::with_capacity(documents.len());
+        for (document, chunk) in documents.into_iter().zip(&chunks) {
+            let entry_id = document.id.clone();
+            if let Err(e) = self.vector_store.insert_document(collection_id, document).await {
+                let stored = format!("{} ({} of {} chunks stored)", store_error_message(&e), entries.len(), chunks.len());
+                return ToolError::new(store_error_code(&e), stored).with_kind(ErrorKind::of_store_error(&e)).response(id);
+            }
+            entries.push(json!({
+                "id": entry_id,
+                "turn_start": chunk.turn_start,
+                "turn_end": chunk.turn_end,
+                "speakers": chunk.speakers,
+            }));
+        }
+        
+        info!(
+            request_id = %ctx.request_id,
+            client_id = %ctx.client_label(),
+            collection = %collection_id,
+            conversation_id = %conversation_id,
+            chunks = entries.len(),
+            "Ingested conversation"
+        );
+        
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": format!("Ingested {} turns as {} entries for conversation {}", messages.len(), entries.len(), conversation_id)
+                    }
+                ],
+                "conversation": {
+                    "id": conversation_id,
+                    "collection_id": collection_id,
+                    "entries": entries
+                }
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mcp::{ProgmoMcpServer, ServerConfig};
+    use crate::vector_store::{InMemoryVectorStore, VectorStore, CONVERSATION_ID_KEY, SPEAKER_KEY, TURN_INDEX_KEY};
+    use serde_json::Value;
+    use std::sync::Arc;
+    
+    #[tokio::test]
+    async fn test_ingest_conversation() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("sessions", 384).await.unwrap();
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store.clone());
+        
+        let request = r#"{"jsonrpc":"2.0","id":"1","method":"CallTool","params":{"name":"ingest_conversation","arguments":{"collection_id":"sessions","conversation_id":"conv-1","max_chunk_chars":80,"messages":[{"role":"user","content":"Why is the build failing?","timestamp":"2026-01-05T10:00:00Z"},{"role":"assistant","content":"The lockfile is stale."},{"role":"user","content":"Regenerating it fixed the build, thanks for the help."}]}}}"#;
+        let response: Value = serde_json::from_str(&server.handle_request(request).await).unwrap();
+        let entries = response["result"]["conversation"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["turn_end"], 1);
+        
+        let stored = store.get_document("sessions", entries[0]["id"].as_str().unwrap()).await.unwrap().unwrap();
+        assert_eq!(stored.metadata[CONVERSATION_ID_KEY], "conv-1");
+        assert_eq!(stored.metadata[SPEAKER_KEY], "user,assistant");
+        assert_eq!(stored.metadata[TURN_INDEX_KEY], "0");
+        assert_eq!(stored.metadata["timestamp"], "2026-01-05T10:00:00Z");
+    }
+    
+    #[tokio::test]
+    async fn test_ingest_conversation_dry_run() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("sessions", 384).await.unwrap();
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store.clone());
+        
+        let request = r#"{"jsonrpc":"2.0","id":"3","method":"CallTool","params":{"name":"ingest_conversation","arguments":{"collection_id":"sessions","dry_run":true,"messages":[{"role":"user","content":"hello"},{"role":"assistant","content":"hi"}]}}}"#;
+        let response: Value = serde_json::from_str(&server.handle_request(request).await).unwrap();
+        assert_eq!(response["result"]["plan"]["chunks"], 1);
+        assert!(store.list_documents("sessions", None, 10).await.unwrap().documents.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_ingest_conversation_rejects_bad_messages() {
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, Arc::new(InMemoryVectorStore::new()));
+        
+        let request = r#"{"jsonrpc":"2.0","id":"2","method":"CallTool","params":{"name":"ingest_conversation","arguments":{"collection_id":"sessions","messages":[{"content":"no role"}]}}}"#;
+        let response: Value = serde_json::from_str(&server.handle_request(request).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+    }
+}
@@ -0,0 +1,112 @@
+//! Cheap presence and size checks that never fetch documents
+
+use serde_json::{json, Value};
+
+use super::{entry_id_argument, error_response, missing_argument, store_error_response, ProgmoMcpServer};
+use crate::context::RequestContext;
+use crate::vector_store::MetadataFilter;
+
+impl ProgmoMcpServer {
+    /// Handle a count_entries tool call; without a filter every entry is counted
+    pub(super) async fn handle_count_entries(&self, _ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let collection_id = match arguments.get("collection_id").and_then(|collection_id| collection_id.as_str()) {
+            Some(collection_id) => collection_id,
+            None => return missing_argument(id, "collection_id"),
+        };
+        let filter = match arguments.get("filter").map(MetadataFilter::from_json).unwrap_or(Ok(MetadataFilter::default())) {
+            Ok(filter) => filter,
+            Err(message) => return error_response(id, -32602, format!("Invalid params: {}", message)),
+        };
+        
+        let count = match self.vector_store.count(collection_id, &filter).await {
+            Ok(count) => count,
+            Err(e) => return store_error_response(id, &e),
+        };
+        
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": count.to_string()
+                    }
+                ],
+                "count": count
+            }
+        }).to_string()
+    }
+    
+    /// Handle an entry_exists tool call
+    pub(super) async fn handle_entry_exists(&self, _ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let collection_id = match arguments.get("collection_id").and_then(|collection_id| collection_id.as_str()) {
+            Some(collection_id) => collection_id,
+            None => return missing_argument(id, "collection_id"),
+        };
+        let entry_id = match entry_id_argument(arguments, "entry_id") {
+            Ok(Some(entry_id)) => entry_id,
+            Ok(None) => return missing_argument(id, "entry_id"),
+            Err(message) => return error_response(id, -32602, message),
+        };
+        
+        let exists = match self.vector_store.exists(collection_id, entry_id.as_str()).await {
+            Ok(exists) => exists,
+            Err(e) => return store_error_response(id, &e),
+        };
+        
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": exists.to_string()
+                    }
+                ],
+                "exists": exists
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::ServerConfig;
+    use crate::vector_store::{Document, InMemoryVectorStore, VectorStore};
+    use std::sync::Arc;
+    
+    fn call(id: u64, name: &str, arguments: Value) -> String {
+        json!({"jsonrpc": "2.0", "id": id, "method": "CallTool", "params": {"name": name, "arguments": arguments}}).to_string()
+    }
+    
+    #[tokio::test]
+    async fn test_count_and_exists_tools() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("docs", 2).await.unwrap();
+        for (id, tag) in [("a", "ops"), ("b", "web")] {
+            let mut document = Document::with_placeholder_embedding(id.to_string(), 2).with_tags(&[tag.to_string()]);
+            document.id = id.to_string();
+            store.insert_document("docs", document).await.unwrap();
+        }
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(1, "count_entries", json!({"collection_id": "docs"}))).await).unwrap();
+        assert_eq!(response["result"]["count"], 2);
+        let response: Value = serde_json::from_str(&server.handle_request(&call(2, "count_entries", json!({
+            "collection_id": "docs", "filter": {"tags": ["ops"]}
+        }))).await).unwrap();
+        assert_eq!(response["result"]["count"], 1);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(3, "entry_exists", json!({"collection_id": "docs", "entry_id": "a"}))).await).unwrap();
+        assert_eq!(response["result"]["exists"], true);
+        let response: Value = serde_json::from_str(&server.handle_request(&call(4, "entry_exists", json!({"collection_id": "docs", "entry_id": "z"}))).await).unwrap();
+        assert_eq!(response["result"]["exists"], false);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(5, "count_entries", json!({"collection_id": "missing"}))).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+        assert_eq!(response["error"]["data"]["error_code"], "collection_not_found");
+    }
+}
@@ -0,0 +1,181 @@
+//! Experimental vector arithmetic over a collection's embedding space
+
+use serde_json::{json, Value};
+
+use super::{error_response, missing_argument, store_error_response, ErrorKind, ProgmoMcpServer, ToolError};
+use crate::context::RequestContext;
+use crate::vector_store::{l2_normalize, EntryId, SearchQuery};
+
+/// Mean of equally sized vectors; `None` for no vectors or mismatched sizes
+pub fn average(vectors: &[Vec<f32>]) -> Option<Vec<f32>> {
+    let first = vectors.first()?;
+    if vectors.iter().any(|vector| vector.len() != first.len()) {
+        return None;
+    }
+    
+    let mut mean = vec![0.0; first.len()];
+    for vector in vectors {
+        for (sum, x) in mean.iter_mut().zip(vector) {
+            *sum += x;
+        }
+    }
+    for x in mean.iter_mut() {
+        *x /= vectors.len() as f32;
+    }
+    Some(mean)
+}
+
+/// `a - b + c`, the vector completing "b is to a as c is to ?"
+pub fn analogy(a: &[f32], b: &[f32], c: &[f32]) -> Option<Vec<f32>> {
+    if a.len() != b.len() || a.len() != c.len() {
+        return None;
+    }
+    Some(a.iter().zip(b).zip(c).map(|((a, b), c)| a - b + c).collect())
+}
+
+impl ProgmoMcpServer {
+    /// Resolve an operand: a string or `{"entry_id"}` names a stored entry, `{"text"}` is embedded
+    async fn operand_vector(&self, collection_id: &str, operand: &Value) -> Result<(Vec<f32>, Option<String>), ToolError> {
+        let entry_id = operand.as_str().or_else(|| operand.get("entry_id").and_then(|entry_id| entry_id.as_str()));
+        if let Some(entry_id) = entry_id {
+            let entry_id = match EntryId::parse(entry_id) {
+                Ok(entry_id) => entry_id,
+                Err(e) => return Err(ToolError::invalid(format!("Invalid params: {}", e))),
+            };
+            return match self.vector_store.get_document(collection_id, entry_id.as_str()).await {
+                Ok(Some(document)) => Ok((document.embedding, Some(document.id))),
+                Ok(None) => Err(ToolError::invalid(format!("Invalid params: entry not found: {}", entry_id)).with_kind(ErrorKind::EntryNotFound)),
+                Err(e) => Err(ToolError::store(&e)),
+            };
+        }
+        
+        match operand.get("text").and_then(|text| text.as_str()) {
+            Some(text) => self.embed(text)
+                .map(|embedding| (embedding, None))
+                .map_err(|e| ToolError::new(-32603, format!("Internal error: {}", e))),
+            None => Err(ToolError::invalid("Invalid params: operands must be an entry id, {\"entry_id\"} or {\"text\"}".to_string())),
+        }
+    }
+    
+    /// Handle an explore_embedding tool call.
+    ///
+    /// Computes the average of the `operands`, or `a - b + c`, and returns the
+    /// entries nearest to the result. Operand entries are left out of the
+    /// results unless `include_operands` is set.
+    pub(super) async fn handle_explore_embedding(&self, _ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let collection_id = match arguments.get("collection_id").and_then(|collection_id| collection_id.as_str()) {
+            Some(collection_id) => collection_id,
+            None => return missing_argument(id, "collection_id"),
+        };
+        let operation = arguments.get("operation").and_then(|operation| operation.as_str()).unwrap_or("average");
+        let limit = arguments.get("limit").and_then(|limit| limit.as_u64()).unwrap_or(10) as usize;
+        let include_operands = arguments.get("include_operands").and_then(|include| include.as_bool()).unwrap_or(false);
+        
+        let operands: Vec<&Value> = match operation {
+            "average" => match arguments.get("operands").and_then(|operands| operands.as_array()) {
+                Some(operands) if !operands.is_empty() => operands.iter().collect(),
+                _ => return error_response(id, -32602, "Invalid params: average needs a non-empty operands array".to_string()),
+            },
+            "analogy" => match (arguments.get("a"), arguments.get("b"), arguments.get("c")) {
+                (Some(a), Some(b), Some(c)) => vec![a, b, c],
+                _ => return error_response(id, -32602, "Invalid params: analogy needs a, b and c".to_string()),
+            },
+            other => return error_response(id, -32602, format!("Invalid params: unknown operation '{}'", other)),
+        };
+        
+        let mut vectors = Vec::with_capacity(operands.len());
+        let mut operand_ids = Vec::new();
+        for operand in operands {
+            match self.operand_vector(collection_id, operand).await {
+                Ok((vector, entry_id)) => {
+                    vectors.push(vector);
+                    operand_ids.extend(entry_id);
+                },
+                Err(e) => return e.response(id),
+            }
+        }
+        
+        let result = match operation {
+            "analogy" => analogy(&vectors[0], &vectors[1], &vectors[2]),
+            _ => average(&vectors),
+        };
+        let mut embedding = match result {
+            Some(embedding) => embedding,
+            None => return error_response(id, -32602, "Invalid params: operands have different dimensions".to_string()),
+        };
+        if self.normalize_embeddings {
+            l2_normalize(&mut embedding);
+        }
+        if let Err(e) = self.validate_embedding(collection_id, &embedding) {
+            return e.response(id);
+        }
+        
+        let search_limit = if include_operands { limit } else { limit + operand_ids.len() };
+        let results = match self.vector_store.search(collection_id, SearchQuery { embedding, limit: search_limit }).await {
+            Ok(results) => results,
+            Err(e) => return store_error_response(id, &e),
+        };
+        
+        let neighbours: Vec<Value> = results.into_iter()
+            .filter(|result| include_operands || !operand_ids.contains(&result.document.id))
+            .take(limit)
+            .map(|result| json!({
+                "id": result.document.id,
+                "title": result.document.title(),
+                "content": result.document.content,
+                "score": result.score,
+            }))
+            .collect();
+        
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": serde_json::to_string(&neighbours).unwrap()
+                    }
+                ],
+                "operation": operation
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::ServerConfig;
+    use crate::vector_store::{Document, InMemoryVectorStore, VectorStore};
+    use std::sync::Arc;
+    
+    #[test]
+    fn test_vector_operations() {
+        assert_eq!(average(&[vec![1.0, 3.0], vec![3.0, 5.0]]), Some(vec![2.0, 4.0]));
+        assert_eq!(average(&[vec![1.0], vec![1.0, 2.0]]), None);
+        assert_eq!(average(&[]), None);
+        assert_eq!(analogy(&[1.0, 1.0], &[1.0, 0.0], &[0.0, 0.0]), Some(vec![0.0, 1.0]));
+    }
+    
+    #[tokio::test]
+    async fn test_analogy_finds_nearest_entry() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("words", 3).await.unwrap();
+        for (id, embedding) in [("king", [1.0, 1.0, 0.0]), ("man", [1.0, 0.0, 0.0]), ("woman", [0.0, 0.0, 1.0]), ("queen", [0.0, 1.0, 1.0])] {
+            let document = Document {
+                id: id.to_string(),
+                content: id.to_string(),
+                embedding: embedding.to_vec(),
+                metadata: Default::default(),
+            };
+            store.insert_document("words", document).await.unwrap();
+        }
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store);
+        
+        let request = r#"{"jsonrpc":"2.0","id":"1","method":"CallTool","params":{"name":"explore_embedding","arguments":{"collection_id":"words","operation":"analogy","a":"king","b":"man","c":{"entry_id":"woman"},"limit":1}}}"#;
+        let response: Value = serde_json::from_str(&server.handle_request(request).await).unwrap();
+        let neighbours: Value = serde_json::from_str(response["result"]["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(neighbours[0]["id"], "queen");
+    }
+}
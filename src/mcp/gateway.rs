@@ -0,0 +1,243 @@
+//! Gateway mode: offering the tools of downstream MCP servers.
+//!
+//! Each downstream server's selected tools are listed under namespaced
+//! names (`github.search_issues`) next to p-mo's own, and calls to them are
+//! forwarded with the downstream's timeout, so agents need one connection.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use super::{error_response, ErrorKind, ProgmoMcpServer, ToolError};
+use crate::config::DownstreamConfig;
+use crate::context::RequestContext;
+use crate::federation::{FederationError, McpTransport, StdioMcpClient};
+
+/// A downstream tool as offered by the gateway
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewayTool {
+    /// `<downstream>.<tool>`
+    pub name: String,
+    /// Name of the tool on the downstream server
+    pub remote_name: String,
+    pub description: String,
+    pub input_schema: Value,
+    downstream: usize,
+}
+
+impl GatewayTool {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "inputSchema": self.input_schema,
+        })
+    }
+}
+
+struct Downstream {
+    config: DownstreamConfig,
+    /// `None` after a failure; the server is restarted on the next call
+    transport: Mutex<Option<Box<dyn McpTransport>>>,
+}
+
+/// The tools a downstream's `tools/list` result offers under `config`
+pub fn select_tools(config: &DownstreamConfig, downstream: usize, listed: &Value) -> Vec<GatewayTool> {
+    listed["tools"].as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tool| {
+            let remote_name = tool["name"].as_str()?;
+            if !config.tools.is_empty() && !config.tools.iter().any(|selected| selected == remote_name) {
+                return None;
+            }
+            Some(GatewayTool {
+                name: format!("{}.{}", config.name, remote_name),
+                remote_name: remote_name.to_string(),
+                description: tool["description"].as_str().unwrap_or_default().to_string(),
+                input_schema: tool.get("inputSchema").cloned().unwrap_or_else(|| json!({ "type": "object" })),
+                downstream,
+            })
+        })
+        .collect()
+}
+
+/// The environment a downstream server is started with
+fn downstream_env(config: &DownstreamConfig) -> HashMap<String, String> {
+    let mut env = config.env.clone();
+    for (name, source) in &config.env_from {
+        match std::env::var(source) {
+            Ok(value) => {
+                env.insert(name.clone(), value);
+            },
+            Err(_) => warn!(downstream = %config.name, variable = %source, "Environment variable for downstream is not set"),
+        }
+    }
+    env
+}
+
+async fn spawn_downstream(config: &DownstreamConfig) -> Result<Box<dyn McpTransport>, FederationError> {
+    let client = StdioMcpClient::spawn(&config.command, &config.args, &downstream_env(config)).await?;
+    Ok(Box::new(client))
+}
+
+/// The downstream servers and the tools mounted from them
+pub struct Gateway {
+    downstreams: Vec<Downstream>,
+    tools: Vec<GatewayTool>,
+}
+
+impl Gateway {
+    /// Start every downstream and collect its tools
+    ///
+    /// A downstream that fails to start is logged and left out, so one
+    /// broken server does not keep p-mo from starting.
+    pub async fn connect(configs: Vec<DownstreamConfig>) -> Self {
+        let mut connected = Vec::new();
+        for config in configs {
+            match spawn_downstream(&config).await {
+                Ok(transport) => connected.push((config, transport)),
+                Err(e) => warn!(downstream = %config.name, "Failed to start downstream MCP server: {}", e),
+            }
+        }
+        Self::from_transports(connected).await
+    }
+    
+    /// Build a gateway over already connected downstreams
+    pub async fn from_transports(connected: Vec<(DownstreamConfig, Box<dyn McpTransport>)>) -> Self {
+        let mut downstreams = Vec::new();
+        let mut tools = Vec::new();
+        
+        for (config, mut transport) in connected {
+            let timeout = Duration::from_secs(config.timeout_secs.max(1));
+            match tokio::time::timeout(timeout, transport.call("tools/list", json!({}))).await {
+                Ok(Ok(listed)) => {
+                    let selected = select_tools(&config, downstreams.len(), &listed);
+                    for missing in config.tools.iter().filter(|name| !selected.iter().any(|tool| tool.remote_name == **name)) {
+                        warn!(downstream = %config.name, tool = %missing, "Downstream does not offer configured tool");
+                    }
+                    tools.extend(selected);
+                    downstreams.push(Downstream { config, transport: Mutex::new(Some(transport)) });
+                },
+                Ok(Err(e)) => warn!(downstream = %config.name, "Failed to list downstream tools: {}", e),
+                Err(_) => warn!(downstream = %config.name, "Timed out listing downstream tools"),
+            }
+        }
+        
+        Self { downstreams, tools }
+    }
+    
+    pub fn tools(&self) -> &[GatewayTool] {
+        &self.tools
+    }
+    
+    pub fn tool(&self, name: &str) -> Option<&GatewayTool> {
+        self.tools.iter().find(|tool| tool.name == name)
+    }
+    
+    /// Forward a call to the tool's downstream, returning its `tools/call` result
+    pub async fn call(&self, tool: &GatewayTool, arguments: Value) -> Result<Value, FederationError> {
+        let downstream = &self.downstreams[tool.downstream];
+        let timeout = Duration::from_secs(downstream.config.timeout_secs.max(1));
+        let mut transport = downstream.transport.lock().await;
+        
+        if transport.is_none() {
+            *transport = Some(spawn_downstream(&downstream.config).await?);
+        }
+        let call = transport.as_mut().expect("transport was just connected")
+            .call("tools/call", json!({ "name": tool.remote_name, "arguments": arguments }));
+        let outcome = tokio::time::timeout(timeout, call).await;
+        
+        match outcome {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(e @ FederationError::Rpc(_))) => Err(e),
+            Ok(Err(e)) => {
+                // The connection is broken; start a fresh server next time
+                *transport = None;
+                Err(e)
+            },
+            Err(_) => Err(FederationError::Rpc(format!(
+                "{} timed out after {}s", tool.name, timeout.as_secs()
+            ))),
+        }
+    }
+}
+
+impl ProgmoMcpServer {
+    /// Forward a CallTool request for a downstream tool
+    pub(super) async fn handle_gateway_call(&self, _ctx: &RequestContext, id: &Value, tool: &GatewayTool, arguments: &Value) -> String {
+        let Some(gateway) = &self.gateway else {
+            return ToolError::new(-32601, format!("Tool not found: {}", tool.name)).with_kind(ErrorKind::ToolNotFound).response(id);
+        };
+        
+        match gateway.call(tool, arguments.clone()).await {
+            Ok(result) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": result
+            }).to_string(),
+            Err(e) => error_response(id, -32603, format!("Downstream call to {} failed: {}", tool.name, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    
+    struct FakeDownstream;
+    
+    #[async_trait]
+    impl McpTransport for FakeDownstream {
+        async fn call(&mut self, method: &str, params: Value) -> Result<Value, FederationError> {
+            match method {
+                "tools/list" => Ok(json!({ "tools": [
+                    { "name": "search_issues", "description": "Search issues", "inputSchema": { "type": "object" } },
+                    { "name": "delete_repo", "description": "Delete a repository" },
+                ]})),
+                "tools/call" => Ok(json!({
+                    "content": [{ "type": "text", "text": format!("{} {}", params["name"].as_str().unwrap_or_default(), params["arguments"]["q"].as_str().unwrap_or_default()) }]
+                })),
+                _ => Err(FederationError::Rpc(format!("Method not found: {}", method))),
+            }
+        }
+    }
+    
+    fn config() -> DownstreamConfig {
+        DownstreamConfig {
+            name: "github".to_string(),
+            command: "github-mcp-server".to_string(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            env_from: HashMap::new(),
+            tools: vec!["search_issues".to_string()],
+            timeout_secs: 5,
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_gateway_mounts_selected_tools() {
+        let gateway = Gateway::from_transports(vec![(config(), Box::new(FakeDownstream))]).await;
+        
+        let names: Vec<&str> = gateway.tools().iter().map(|tool| tool.name.as_str()).collect();
+        assert_eq!(names, vec!["github.search_issues"]);
+        
+        let tool = gateway.tool("github.search_issues").unwrap();
+        let result = gateway.call(tool, json!({ "q": "is:open" })).await.unwrap();
+        assert_eq!(result["content"][0]["text"], "search_issues is:open");
+    }
+    
+    #[test]
+    fn test_select_tools_without_filter() {
+        let listed = json!({ "tools": [{ "name": "a" }, { "name": "b" }] });
+        let config = DownstreamConfig { tools: Vec::new(), ..config() };
+        
+        let tools = select_tools(&config, 0, &listed);
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[1].name, "github.b");
+        assert_eq!(tools[1].input_schema, json!({ "type": "object" }));
+    }
+}
@@ -0,0 +1,164 @@
+//! Remediation hints for tool errors.
+//!
+//! Every error a tool returns carries, next to its JSON-RPC code and
+//! message, a stable `error_code` naming the kind of failure and, where
+//! there is one, a `hint` telling the caller what to do next. Agents that
+//! read the hint recover on their own instead of retrying the same call.
+
+use super::tools::CAPABILITY_DISABLED;
+use super::{ACCESS_DENIED, DEADLINE_EXCEEDED, POOL_EXHAUSTED, REQUEST_CANCELLED, SCHEMA_MISMATCH};
+use crate::vector_store::VectorStoreError;
+
+/// The kind of a tool failure, which decides its `error_code` and hint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    ParseError,
+    InvalidRequest,
+    MethodNotFound,
+    ToolNotFound,
+    ToolDisabled,
+    /// A required argument, named, was left out
+    MissingArgument(String),
+    InvalidParams,
+    CollectionNotFound,
+    EntryNotFound,
+    ApiKeyNotFound,
+    UnknownResource,
+    DimensionMismatch,
+    InvalidEmbedding,
+    PiiDetected,
+    SchemaValidation,
+    PoolExhausted,
+    SchemaMismatch,
+    DeadlineExceeded,
+    Cancelled,
+    AccessDenied,
+    BackendUnavailable,
+    Internal,
+    Other,
+}
+
+impl ErrorKind {
+    /// The kind a JSON-RPC code implies when nothing more is known
+    pub fn from_code(code: i64) -> Self {
+        match code {
+            POOL_EXHAUSTED => Self::PoolExhausted,
+            SCHEMA_MISMATCH => Self::SchemaMismatch,
+            DEADLINE_EXCEEDED => Self::DeadlineExceeded,
+            REQUEST_CANCELLED => Self::Cancelled,
+            ACCESS_DENIED => Self::AccessDenied,
+            CAPABILITY_DISABLED => Self::ToolDisabled,
+            -32700 => Self::ParseError,
+            -32600 => Self::InvalidRequest,
+            -32601 => Self::MethodNotFound,
+            -32602 => Self::InvalidParams,
+            -32603 => Self::Internal,
+            _ => Self::Other,
+        }
+    }
+    
+    /// The kind of a vector store failure
+    pub fn of_store_error(e: &VectorStoreError) -> Self {
+        match e {
+            VectorStoreError::CollectionNotFound(_) => Self::CollectionNotFound,
+            VectorStoreError::DimensionMismatch { .. } => Self::DimensionMismatch,
+            VectorStoreError::InvalidArgument(_) => Self::InvalidParams,
+            VectorStoreError::PoolExhausted(_) => Self::PoolExhausted,
+            VectorStoreError::SchemaMismatch { .. } => Self::SchemaMismatch,
+            VectorStoreError::DeadlineExceeded => Self::DeadlineExceeded,
+            VectorStoreError::ConnectionError(_) | VectorStoreError::TimeoutError(_) | VectorStoreError::PoolError(_) => Self::BackendUnavailable,
+            VectorStoreError::OperationFailed(_) | VectorStoreError::AuthenticationError(_) => Self::Internal,
+        }
+    }
+    
+    /// Machine-readable name of the kind, e.g. `collection_not_found`
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            Self::ParseError => "parse_error",
+            Self::InvalidRequest => "invalid_request",
+            Self::MethodNotFound => "method_not_found",
+            Self::ToolNotFound => "tool_not_found",
+            Self::ToolDisabled => "tool_disabled",
+            Self::MissingArgument(_) => "missing_argument",
+            Self::InvalidParams => "invalid_params",
+            Self::CollectionNotFound => "collection_not_found",
+            Self::EntryNotFound => "entry_not_found",
+            Self::ApiKeyNotFound => "api_key_not_found",
+            Self::UnknownResource => "unknown_resource",
+            Self::DimensionMismatch => "dimension_mismatch",
+            Self::InvalidEmbedding => "invalid_embedding",
+            Self::PiiDetected => "pii_detected",
+            Self::SchemaValidation => "schema_validation",
+            Self::PoolExhausted => "pool_exhausted",
+            Self::SchemaMismatch => "schema_mismatch",
+            Self::DeadlineExceeded => "deadline_exceeded",
+            Self::Cancelled => "cancelled",
+            Self::AccessDenied => "access_denied",
+            Self::BackendUnavailable => "backend_unavailable",
+            Self::Internal => "internal_error",
+            Self::Other => "error",
+        }
+    }
+    
+    /// What the caller can do about it, when there is anything
+    pub fn hint(&self) -> Option<String> {
+        let hint = match self {
+            Self::ParseError => "send the request as one valid JSON-RPC object",
+            Self::InvalidRequest => "send a JSON-RPC object with jsonrpc, id, method and params",
+            Self::MethodNotFound => "use ListTools, CallTool or ReadResource",
+            Self::ToolNotFound => "call ListTools for the names of the available tools",
+            Self::ToolDisabled => "call ListTools to see the tools enabled on this server",
+            Self::MissingArgument(argument) => return Some(format!("add `{}`; ListTools gives the input schema of every tool", argument)),
+            Self::InvalidParams => "check the arguments against the tool's input schema from ListTools",
+            Self::CollectionNotFound => {
+                "check collection_id for typos; a collection must be created by an operator before entries are added to or searched in it"
+            },
+            Self::EntryNotFound => "check entry_id and collection_id; search_knowledge returns the ids of stored entries",
+            Self::ApiKeyNotFound => "list_api_keys gives the ids of the managed keys",
+            Self::UnknownResource => {
+                "entry resources look like knowledge://collections/<collection>/entries/<id>, job resources like jobs://reindex/<id>"
+            },
+            Self::DimensionMismatch => "leave out embedding so the server embeds the text itself, or send one with the collection's dimension",
+            Self::InvalidEmbedding => "send an embedding with finite, non-zero values, or leave it out so the server embeds the text",
+            Self::PiiDetected => {
+                "remove or mask the personal data listed in data.findings and retry, or ask an operator to change the collection's PII policy"
+            },
+            Self::SchemaValidation => "fix the fields listed in data.field_errors; data.template shows a valid entry",
+            Self::PoolExhausted => "the server is busy; retry after a short delay",
+            Self::SchemaMismatch => {
+                "the collection no longer matches the server's registry and retrying will not help; an operator must run `p-mo reconcile`"
+            },
+            Self::DeadlineExceeded => "allow more time with _meta.timeoutMs, or ask for less with a smaller limit or a narrower filter",
+            Self::AccessDenied => "connect with an API key or token whose scope and collections cover the call",
+            Self::BackendUnavailable => "the vector store could not be reached; retry later",
+            Self::Internal => "retry once; if the error persists, the server logs have the details",
+            Self::Cancelled | Self::Other => return None,
+        };
+        Some(hint.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_kinds_from_codes_and_store_errors() {
+        assert_eq!(ErrorKind::from_code(POOL_EXHAUSTED).error_code(), "pool_exhausted");
+        assert_eq!(ErrorKind::from_code(-32602), ErrorKind::InvalidParams);
+        assert_eq!(ErrorKind::from_code(REQUEST_CANCELLED).hint(), None);
+        
+        let missing = ErrorKind::of_store_error(&VectorStoreError::CollectionNotFound("docs".to_string()));
+        assert_eq!(missing.error_code(), "collection_not_found");
+        let mismatch = VectorStoreError::DimensionMismatch { collection: "docs".to_string(), expected: 384, actual: 768 };
+        assert_eq!(ErrorKind::of_store_error(&mismatch), ErrorKind::DimensionMismatch);
+        assert_eq!(ErrorKind::of_store_error(&VectorStoreError::TimeoutError("slow".to_string())).error_code(), "backend_unavailable");
+    }
+    
+    #[test]
+    fn test_missing_argument_hint_names_it() {
+        let missing = ErrorKind::MissingArgument("collection_id".to_string());
+        assert_eq!(missing.error_code(), "missing_argument");
+        assert!(missing.hint().unwrap().contains("`collection_id`"));
+    }
+}
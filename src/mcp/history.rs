@@ -0,0 +1,106 @@
+//! Looking up changes made to runtime settings
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use super::{error_response, ProgmoMcpServer};
+use crate::context::RequestContext;
+use crate::logging::ConfigHistoryQuery;
+
+/// Changes returned when a call gives no `limit`
+const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+impl ProgmoMcpServer {
+    /// Handle a get_config_history tool call
+    pub(super) async fn handle_get_config_history(&self, _ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let string = |key: &str| arguments.get(key).and_then(|value| value.as_str()).map(str::to_string);
+        let since = match arguments.get("since").and_then(|since| since.as_str()) {
+            None => None,
+            Some(since) => match DateTime::parse_from_rfc3339(since) {
+                Ok(since) => Some(since.with_timezone(&Utc)),
+                Err(e) => return error_response(id, -32602, format!("Invalid params: since: {}", e)),
+            },
+        };
+        let limit = match arguments.get("limit") {
+            None | Some(Value::Null) => DEFAULT_HISTORY_LIMIT,
+            Some(limit) => match limit.as_u64() {
+                Some(limit) => limit as usize,
+                None => return error_response(id, -32602, "Invalid params: limit must be a non-negative integer".to_string()),
+            },
+        };
+        
+        let query = ConfigHistoryQuery {
+            setting: string("setting"),
+            actor: string("actor"),
+            since,
+            limit: Some(limit),
+        };
+        let changes = self.audit_log.history(&query);
+        let text = match changes.len() {
+            1 => "1 configuration change".to_string(),
+            count => format!("{} configuration changes", count),
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": text
+                    }
+                ],
+                "changes": changes
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::ServerConfig;
+    use crate::vector_store::{CollectionInfo, InMemoryVectorStore};
+    use std::sync::Arc;
+    
+    fn call(id: u64, name: &str, arguments: Value) -> String {
+        json!({"jsonrpc": "2.0", "id": id, "method": "CallTool", "params": {"name": name, "arguments": arguments}}).to_string()
+    }
+    
+    #[tokio::test]
+    async fn test_settings_changes_are_audited() {
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, Arc::new(InMemoryVectorStore::new()));
+        server.registry.register(CollectionInfo::new("docs", 2));
+        
+        server.handle_request_with_context(&call(1, "update_collection_settings", json!({
+            "collection_id": "docs", "limit": 5, "score_threshold": 0.3
+        })), RequestContext::from_api_key("secret")).await;
+        server.handle_request(&call(2, "update_collection_settings", json!({
+            "collection_id": "docs", "limit": 8, "dry_run": true
+        }))).await;
+        server.handle_request(&call(3, "update_collection_settings", json!({
+            "collection_id": "docs", "limit": 8
+        }))).await;
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(4, "get_config_history", json!({
+            "setting": "collections.docs.search.limit"
+        }))).await).unwrap();
+        let changes = response["result"]["changes"].as_array().unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0]["old_value"], 5);
+        assert_eq!(changes[0]["new_value"], 8);
+        assert_eq!(changes[0]["actor"], "anonymous");
+        assert!(changes[1]["old_value"].is_null());
+        assert!(changes[1]["actor"].as_str().unwrap().starts_with("key:"));
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(5, "get_config_history", json!({
+            "setting": "collections.docs", "limit": 10
+        }))).await).unwrap();
+        assert_eq!(response["result"]["changes"].as_array().unwrap().len(), 3);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(6, "get_config_history", json!({
+            "since": "yesterday"
+        }))).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+    }
+}
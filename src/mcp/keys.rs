@@ -0,0 +1,200 @@
+//! Creating, listing and revoking API keys at runtime
+
+use serde_json::{json, Value};
+use tracing::info;
+
+use super::{error_response, invalid_response, is_dry_run, missing_argument, ErrorKind, ProgmoMcpServer};
+use crate::api_keys::{ApiKeyError, ApiKeyStore, KeyOptions};
+use crate::context::RequestContext;
+
+impl ProgmoMcpServer {
+    /// The key store, or the error to return when key management is off
+    fn api_key_store(&self, id: &Value) -> Result<&ApiKeyStore, String> {
+        self.api_keys.as_deref()
+            .ok_or_else(|| error_response(id, -32603, "Internal error: API key management is not enabled on this server".to_string()))
+    }
+    
+    /// Handle a create_api_key tool call; the key is in the response and nowhere else
+    pub(super) async fn handle_create_api_key(&self, ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let store = match self.api_key_store(id) {
+            Ok(store) => store,
+            Err(response) => return response,
+        };
+        let name = match arguments.get("name").and_then(|name| name.as_str()) {
+            Some(name) if !name.trim().is_empty() => name,
+            _ => return missing_argument(id, "name"),
+        };
+        let options = match KeyOptions::from_json(arguments) {
+            Ok(options) => options,
+            Err(e) => return key_error_response(id, e),
+        };
+        
+        if is_dry_run(arguments) {
+            return json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "content": [
+                        {
+                            "type": "text",
+                            "text": format!("Dry run: would create API key {}", name.trim())
+                        }
+                    ],
+                    "plan": {
+                        "dry_run": true,
+                        "action": "create_api_key",
+                        "name": name.trim(),
+                        "scope": options.scope,
+                        "collections": options.collections,
+                        "expires_at": options.expires_at
+                    }
+                }
+            }).to_string();
+        }
+        
+        let (key, api_key) = match store.create(name, options, ctx.client_label(), "create_api_key") {
+            Ok(created) => created,
+            Err(e) => return key_error_response(id, e),
+        };
+        info!(request_id = %ctx.request_id, client_id = %ctx.client_label(), key_id = %key.id, "Created API key");
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": format!("Created {} API key {} ({}); store it now, it cannot be shown again", key.scope, key.name, key.id)
+                    }
+                ],
+                "key": key,
+                "api_key": api_key
+            }
+        }).to_string()
+    }
+    
+    /// Handle a list_api_keys tool call; keys are masked
+    pub(super) async fn handle_list_api_keys(&self, _ctx: &RequestContext, id: &Value, _arguments: &Value) -> String {
+        let store = match self.api_key_store(id) {
+            Ok(store) => store,
+            Err(response) => return response,
+        };
+        let keys = store.list();
+        let active = keys.iter().filter(|key| key.active).count();
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": format!("{} API keys, {} active", keys.len(), active)
+                    }
+                ],
+                "keys": keys
+            }
+        }).to_string()
+    }
+    
+    /// Handle a revoke_api_key tool call; the key is refused from its next request on
+    pub(super) async fn handle_revoke_api_key(&self, ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let store = match self.api_key_store(id) {
+            Ok(store) => store,
+            Err(response) => return response,
+        };
+        let key_id = match arguments.get("key_id").and_then(|key_id| key_id.as_str()) {
+            Some(key_id) => key_id,
+            None => return missing_argument(id, "key_id"),
+        };
+        
+        if is_dry_run(arguments) {
+            let Some(key) = store.list().into_iter().find(|key| key.id == key_id) else {
+                return key_error_response(id, ApiKeyError::NotFound(key_id.to_string()));
+            };
+            return json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "content": [
+                        {
+                            "type": "text",
+                            "text": format!("Dry run: would revoke API key {} ({})", key.name, key.id)
+                        }
+                    ],
+                    "plan": {"dry_run": true, "action": "revoke_api_key", "key": key}
+                }
+            }).to_string();
+        }
+        
+        let key = match store.revoke(key_id, ctx.client_label(), "revoke_api_key") {
+            Ok(key) => key,
+            Err(e) => return key_error_response(id, e),
+        };
+        info!(request_id = %ctx.request_id, client_id = %ctx.client_label(), key_id = %key.id, "Revoked API key");
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": format!("Revoked API key {} ({})", key.name, key.id)
+                    }
+                ],
+                "key": key
+            }
+        }).to_string()
+    }
+}
+
+fn key_error_response(id: &Value, e: ApiKeyError) -> String {
+    match e {
+        ApiKeyError::NotFound(_) => invalid_response(id, ErrorKind::ApiKeyNotFound, format!("Invalid params: {}", e)),
+        ApiKeyError::EmptyName | ApiKeyError::InvalidOptions(_) => error_response(id, -32602, format!("Invalid params: {}", e)),
+        ApiKeyError::Storage(_) => error_response(id, -32603, format!("Internal error: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys::Scope;
+    use crate::logging::ConfigAuditLog;
+    use crate::mcp::ServerConfig;
+    use crate::vector_store::InMemoryVectorStore;
+    use std::sync::Arc;
+    
+    fn call(id: u64, name: &str, arguments: Value) -> String {
+        json!({"jsonrpc": "2.0", "id": id, "method": "CallTool", "params": {"name": name, "arguments": arguments}}).to_string()
+    }
+    
+    #[tokio::test]
+    async fn test_key_lifecycle() {
+        let audit_log = Arc::new(ConfigAuditLog::new(10));
+        let keys = Arc::new(ApiKeyStore::new().with_audit_log(audit_log.clone()));
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, Arc::new(InMemoryVectorStore::new()))
+            .with_audit_log(audit_log)
+            .with_api_keys(keys.clone());
+        
+        let created: Value = serde_json::from_str(&server.handle_request(&call(1, "create_api_key", json!({"name": "ci", "scope": "read", "ttl": 3600}))).await).unwrap();
+        let api_key = created["result"]["api_key"].as_str().unwrap().to_string();
+        let key_id = created["result"]["key"]["id"].as_str().unwrap().to_string();
+        assert_eq!(keys.verify(&api_key).unwrap().scope, Scope::Read);
+        
+        let listed = server.handle_request(&call(2, "list_api_keys", json!({}))).await;
+        assert!(!listed.contains(&api_key));
+        
+        server.handle_request(&call(3, "revoke_api_key", json!({"key_id": key_id}))).await;
+        assert!(keys.verify(&api_key).is_none());
+        
+        let history: Value = serde_json::from_str(&server.handle_request(&call(4, "get_config_history", json!({"setting": "api_keys"}))).await).unwrap();
+        assert_eq!(history["result"]["changes"].as_array().unwrap().len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_key_management_disabled() {
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, Arc::new(InMemoryVectorStore::new()));
+        let response: Value = serde_json::from_str(&server.handle_request(&call(1, "list_api_keys", json!({}))).await).unwrap();
+        assert!(response["error"]["message"].as_str().unwrap().contains("not enabled"));
+    }
+}
@@ -0,0 +1,98 @@
+//! The discovery document describing how to connect to this server.
+//!
+//! Clients and registries fetch it from [`MANIFEST_PATH`] instead of
+//! connecting first: it names the server and its version, the transports
+//! and methods it speaks, every enabled tool with its schemas and the URI
+//! schemes of its resources. The HTTP layer adds what it knows, such as the
+//! credentials its routes require.
+
+use serde_json::{json, Value};
+
+use super::aliases::{ToolAlias, ALIASES, TOOLS_VERSION};
+use super::content::ENTRY_URI_PREFIX;
+use super::ProgmoMcpServer;
+use crate::jobs::JOB_URI_SCHEME;
+
+/// Where the manifest is served over HTTP
+pub const MANIFEST_PATH: &str = "/.well-known/mcp.json";
+
+/// Version of the manifest's own layout, raised when fields change meaning
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// JSON-RPC methods the server answers
+const METHODS: &[&str] = &["ListTools", "CallTool", "ReadResource"];
+
+impl ProgmoMcpServer {
+    /// The manifest of this server, listing only the tools it would list to a client
+    pub fn manifest(&self) -> Value {
+        json!({
+            "manifestVersion": MANIFEST_VERSION,
+            "name": self.name(),
+            "version": self.version(),
+            "protocol": {
+                "jsonrpc": "2.0",
+                "methods": METHODS,
+            },
+            "transports": [{
+                "type": "stdio",
+                "framing": "ndjson",
+                "description": "One JSON-RPC message per line on stdin and stdout; requests run concurrently",
+            }],
+            "toolsVersion": TOOLS_VERSION,
+            "tools": self.listed_tools(),
+            "deprecatedTools": ALIASES.iter().map(ToolAlias::to_json).collect::<Vec<_>>(),
+            "resources": [
+                {
+                    "scheme": scheme(ENTRY_URI_PREFIX),
+                    "uriTemplates": [
+                        format!("{}{{collection}}", ENTRY_URI_PREFIX),
+                        format!("{}{{collection}}/entries/{{id}}{{?format}}", ENTRY_URI_PREFIX),
+                    ],
+                    "mimeTypes": ["application/json", "text/plain", "text/markdown"],
+                },
+                {
+                    "scheme": scheme(JOB_URI_SCHEME),
+                    "uriTemplates": [
+                        format!("{}{{kind}}", JOB_URI_SCHEME),
+                        format!("{}{{kind}}/{{id}}{{?wait}}", JOB_URI_SCHEME),
+                    ],
+                    "mimeTypes": ["application/json"],
+                },
+            ],
+        })
+    }
+}
+
+/// `knowledge` of `knowledge://collections/`
+fn scheme(prefix: &str) -> &str {
+    prefix.split("://").next().unwrap_or(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::ToolPolicy;
+    use crate::mcp::ServerConfig;
+    use crate::vector_store::InMemoryVectorStore;
+    use std::sync::Arc;
+    
+    #[test]
+    fn test_manifest_lists_enabled_tools_and_schemes() {
+        let mut policy = ToolPolicy::default();
+        policy.disable("forget");
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, Arc::new(InMemoryVectorStore::new()))
+            .with_tool_policy(policy);
+        let manifest = server.manifest();
+        
+        assert_eq!(manifest["name"], "test");
+        assert_eq!(manifest["version"], "0.1.0");
+        assert_eq!(manifest["transports"][0]["type"], "stdio");
+        let tools = manifest["tools"].as_array().unwrap();
+        assert!(tools.iter().any(|tool| tool["name"] == "recall" && tool["inputSchema"].is_object()));
+        assert!(!tools.iter().any(|tool| tool["name"] == "forget"));
+        
+        let schemes: Vec<&str> = manifest["resources"].as_array().unwrap().iter().map(|resource| resource["scheme"].as_str().unwrap()).collect();
+        assert_eq!(schemes, ["knowledge", "jobs"]);
+        assert_eq!(manifest["resources"][0]["uriTemplates"][1], "knowledge://collections/{collection}/entries/{id}{?format}");
+    }
+}
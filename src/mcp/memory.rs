@@ -0,0 +1,370 @@
+//! Agent memory tools: remember, recall and forget.
+//!
+//! Memories live in per-session or per-user collections, separate from the
+//! curated knowledge collections, and fade with age unless marked important.
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use super::{document_bytes, entry_id_argument, error_response, is_dry_run, missing_argument, plan_response, store_error_response, ProgmoMcpServer, ToolError};
+use crate::config::MemoryScope;
+use crate::context::RequestContext;
+use crate::vector_store::{expiry_after, is_expired, Document, SearchQuery, SearchResult, VectorStoreError, EXPIRES_AT_KEY};
+
+/// Metadata key holding when a memory was written (RFC 3339)
+pub const CREATED_AT_KEY: &str = "memory_created_at";
+
+/// Metadata key holding a memory's importance between 0 and 1
+pub const IMPORTANCE_KEY: &str = "memory_importance";
+
+/// Importance of memories written without one
+pub const DEFAULT_IMPORTANCE: f32 = 0.5;
+
+/// Name of the memory collection for an owner, restricted to safe characters
+pub fn memory_collection(prefix: &str, scope: &str, owner: &str) -> String {
+    let owner: String = owner.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("{}_{}_{}", prefix, scope, owner)
+}
+
+/// Exponential decay: 1 for a new memory, 0.5 after one half-life
+pub fn recency_weight(age: ChronoDuration, half_life: ChronoDuration) -> f32 {
+    let half_life_secs = half_life.num_seconds().max(1) as f32;
+    let age_secs = age.num_seconds().max(0) as f32;
+    0.5f32.powf(age_secs / half_life_secs)
+}
+
+/// Combine similarity, recency and importance into a recall score.
+///
+/// Importance scales the score between half and full strength, so an
+/// unimportant memory is never ranked out entirely by importance alone.
+pub fn recall_score(similarity: f32, age: ChronoDuration, half_life: ChronoDuration, importance: f32) -> f32 {
+    similarity * recency_weight(age, half_life) * (0.5 + importance.clamp(0.0, 1.0) / 2.0)
+}
+
+fn parse_time(document: &Document, key: &str) -> Option<DateTime<Utc>> {
+    document.metadata.get(key)
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn importance(document: &Document) -> f32 {
+    document.metadata.get(IMPORTANCE_KEY)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_IMPORTANCE)
+}
+
+fn text_response(id: &Value, text: String) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "result": {
+            "content": [
+                {
+                    "type": "text",
+                    "text": text
+                }
+            ]
+        }
+    }).to_string()
+}
+
+impl ProgmoMcpServer {
+    /// Resolve the memory collection for a call from its `scope` argument and context
+    fn memory_collection_for(&self, ctx: &RequestContext, arguments: &Value) -> Result<String, String> {
+        let scope = match arguments.get("scope").and_then(|scope| scope.as_str()) {
+            Some("session") => MemoryScope::Session,
+            Some("user") => MemoryScope::User,
+            Some(other) => return Err(format!("Invalid params: unknown scope '{}'", other)),
+            None => self.memory_config.scope,
+        };
+        
+        let prefix = &self.memory_config.collection_prefix;
+        match (scope, &ctx.session_id) {
+            (MemoryScope::Session, Some(session_id)) => Ok(memory_collection(prefix, "session", session_id)),
+            _ => Ok(memory_collection(prefix, "user", ctx.client_label())),
+        }
+    }
+    
+    /// Insert a memory, creating the collection on first use
+    async fn insert_memory(&self, collection: &str, document: Document) -> Result<(), VectorStoreError> {
+        match self.vector_store.insert_document(collection, document.clone()).await {
+            Ok(()) => Ok(()),
+            Err(_) => {
+                self.vector_store.create_collection(collection, self.embedding_dim).await?;
+                self.vector_store.insert_document(collection, document).await
+            }
+        }
+    }
+    
+    /// Search a memory collection; a collection that does not exist yet holds no memories
+    async fn search_memories(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<SearchResult>, ToolError> {
+        let embedding = self.embed(query).map_err(|e| ToolError::new(-32603, format!("Internal error: {}", e)))?;
+        match self.vector_store.search(collection, SearchQuery { embedding, limit }).await {
+            Ok(results) => Ok(results),
+            Err(e) => match self.vector_store.list_collections().await {
+                Ok(collections) if !collections.iter().any(|name| name == collection) => Ok(Vec::new()),
+                _ => Err(ToolError::store(&e)),
+            },
+        }
+    }
+    
+    /// Handle a remember tool call
+    pub(super) async fn handle_remember(&self, ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let content = match arguments.get("content").and_then(|content| content.as_str()) {
+            Some(content) if !content.trim().is_empty() => content,
+            _ => return missing_argument(id, "content"),
+        };
+        
+        let importance = arguments.get("importance")
+            .and_then(|importance| importance.as_f64())
+            .map(|importance| importance as f32)
+            .unwrap_or(DEFAULT_IMPORTANCE);
+        if !(0.0..=1.0).contains(&importance) {
+            return error_response(id, -32602, "Invalid params: importance must be between 0 and 1".to_string());
+        }
+        
+        let ttl_secs = arguments.get("ttl")
+            .and_then(|ttl| ttl.as_u64())
+            .or(self.memory_config.default_ttl_secs);
+        
+        let collection = match self.memory_collection_for(ctx, arguments) {
+            Ok(collection) => collection,
+            Err(message) => return error_response(id, -32602, message),
+        };
+        
+        let embedding = match self.embed(content) {
+            Ok(embedding) => embedding,
+            Err(e) => return error_response(id, -32603, format!("Internal error: {}", e)),
+        };
+        if let Err(e) = self.check_stored_embedding(&collection, &embedding) {
+            return e.response(id);
+        }
+        
+        let now = self.determinism.now();
+        let mut document = Document {
+            id: self.determinism.uuid().to_string(),
+            content: content.to_string(),
+            embedding,
+            metadata: Default::default(),
+        };
+        document.metadata.insert(CREATED_AT_KEY.to_string(), now.to_rfc3339());
+        document.metadata.insert(IMPORTANCE_KEY.to_string(), importance.to_string());
+        let expires_at = match ttl_secs.map(|ttl_secs| (ttl_secs, expiry_after(now, ttl_secs))) {
+            Some((ttl_secs, None)) => return error_response(id, -32602, format!("Invalid params: ttl {} is too large", ttl_secs)),
+            Some((_, expires_at)) => expires_at,
+            None => None,
+        };
+        if let Some(expires_at) = expires_at {
+            document.metadata.insert(EXPIRES_AT_KEY.to_string(), expires_at.to_rfc3339());
+        }
+        
+        let memory_id = document.id.clone();
+        if is_dry_run(arguments) {
+            return plan_response(id, "add", &collection, &[memory_id], 1, document_bytes(&document));
+        }
+        
+        if let Err(e) = self.insert_memory(&collection, document).await {
+            return store_error_response(id, &e);
+        }
+        
+        text_response(id, json!({
+            "id": memory_id,
+            "collection_id": collection,
+            "created_at": now.to_rfc3339(),
+            "expires_at": expires_at.map(|expires_at| expires_at.to_rfc3339()),
+            "importance": importance,
+        }).to_string())
+    }
+    
+    /// Handle a recall tool call, ranking memories by similarity, recency and importance
+    pub(super) async fn handle_recall(&self, ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let query = match arguments.get("query").and_then(|query| query.as_str()) {
+            Some(query) => query,
+            None => return missing_argument(id, "query"),
+        };
+        
+        let k = arguments.get("k")
+            .and_then(|k| k.as_u64())
+            .unwrap_or(5) as usize;
+        
+        let collection = match self.memory_collection_for(ctx, arguments) {
+            Ok(collection) => collection,
+            Err(message) => return error_response(id, -32602, message),
+        };
+        
+        // Over-fetch so that re-ranking by recency can surface older close matches
+        let candidates = match self.search_memories(&collection, query, k.saturating_mul(4).max(k)).await {
+            Ok(candidates) => candidates,
+            Err(e) => return e.response(id),
+        };
+        
+        let now = self.determinism.now();
+        let half_life = ChronoDuration::seconds(self.memory_config.half_life_secs as i64);
+        let mut memories = Vec::new();
+        for result in candidates {
+            if is_expired(&result.document, None, now) {
+                if let Err(e) = self.vector_store.delete_document(&collection, &result.document.id).await {
+                    warn!("Failed to delete expired memory {}: {}", result.document.id, e);
+                }
+                continue;
+            }
+            
+            let created_at = parse_time(&result.document, CREATED_AT_KEY).unwrap_or(now);
+            let importance = importance(&result.document);
+            let score = recall_score(result.score, now - created_at, half_life, importance);
+            memories.push((score, result, created_at, importance));
+        }
+        
+        memories.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        memories.truncate(k);
+        
+        let memories: Vec<Value> = memories.into_iter()
+            .map(|(score, result, created_at, importance)| json!({
+                "id": result.document.id,
+                "content": result.document.content,
+                "score": score,
+                "similarity": result.score,
+                "created_at": created_at.to_rfc3339(),
+                "importance": importance,
+            }))
+            .collect();
+        
+        text_response(id, serde_json::to_string(&memories).unwrap())
+    }
+    
+    /// Handle a forget tool call, deleting a memory by id or all memories matching a query
+    pub(super) async fn handle_forget(&self, ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let collection = match self.memory_collection_for(ctx, arguments) {
+            Ok(collection) => collection,
+            Err(message) => return error_response(id, -32602, message),
+        };
+        
+        let memory_id = match entry_id_argument(arguments, "id") {
+            Ok(memory_id) => memory_id,
+            Err(message) => return error_response(id, -32602, message),
+        };
+        let to_delete: Vec<String> = if let Some(memory_id) = memory_id {
+            vec![memory_id.into()]
+        } else if let Some(query) = arguments.get("query").and_then(|query| query.as_str()) {
+            let threshold = arguments.get("threshold")
+                .and_then(|threshold| threshold.as_f64())
+                .unwrap_or(self.memory_config.forget_threshold as f64) as f32;
+            let limit = arguments.get("limit")
+                .and_then(|limit| limit.as_u64())
+                .unwrap_or(20) as usize;
+            
+            match self.search_memories(&collection, query, limit).await {
+                Ok(results) => results.into_iter()
+                    .filter(|result| result.score >= threshold)
+                    .map(|result| result.document.id)
+                    .collect(),
+                Err(e) => return e.response(id),
+            }
+        } else {
+            return error_response(id, -32602, "Invalid params: provide id or query".to_string());
+        };
+        
+        if is_dry_run(arguments) {
+            return plan_response(id, "delete", &collection, &to_delete, 0, 0);
+        }
+        
+        for memory_id in &to_delete {
+            if let Err(e) = self.vector_store.delete_document(&collection, memory_id).await {
+                return store_error_response(id, &e);
+            }
+        }
+        
+        text_response(id, json!({ "deleted": to_delete }).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::ServerConfig;
+    use crate::text_processing::embedding::MockEmbeddingGenerator;
+    use crate::vector_store::{InMemoryVectorStore, VectorStore};
+    use std::sync::Arc;
+    
+    fn server(store: Arc<InMemoryVectorStore>) -> ProgmoMcpServer {
+        ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store)
+            .with_embedding_provider(Arc::new(MockEmbeddingGenerator::new(8)))
+            .unwrap()
+    }
+    
+    fn call(name: &str, arguments: Value) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "id": "1",
+            "method": "CallTool",
+            "params": {"name": name, "arguments": arguments, "_meta": {"sessionId": "s-1"}}
+        }).to_string()
+    }
+    
+    async fn result_text(server: &ProgmoMcpServer, request: String) -> Value {
+        let response: Value = serde_json::from_str(&server.handle_request(&request).await).unwrap();
+        serde_json::from_str(response["result"]["content"][0]["text"].as_str().unwrap()).unwrap()
+    }
+    
+    #[test]
+    fn test_decay_and_scoring() {
+        let half_life = ChronoDuration::hours(1);
+        assert_eq!(recency_weight(ChronoDuration::zero(), half_life), 1.0);
+        assert!((recency_weight(ChronoDuration::hours(1), half_life) - 0.5).abs() < 1e-6);
+        assert!(recall_score(1.0, ChronoDuration::zero(), half_life, 1.0) > recall_score(1.0, ChronoDuration::zero(), half_life, 0.0));
+        assert_eq!(memory_collection("memory", "user", "key:ab/cd"), "memory_user_key_ab_cd");
+    }
+    
+    #[tokio::test]
+    async fn test_remember_recall_forget() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        let server = server(store.clone());
+        
+        assert_eq!(result_text(&server, call("recall", json!({"query": "anything"}))).await, json!([]));
+        
+        let minor = result_text(&server, call("remember", json!({"content": "likes tea", "importance": 0.1}))).await;
+        let major = result_text(&server, call("remember", json!({"content": "deploys on Fridays", "importance": 0.9}))).await;
+        assert_eq!(major["collection_id"], "memory_session_s-1");
+        
+        let recalled = result_text(&server, call("recall", json!({"query": "habits", "k": 2}))).await;
+        assert_eq!(recalled[0]["id"], major["id"]);
+        assert_eq!(recalled[1]["id"], minor["id"]);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call("forget", json!({"query": "habits", "threshold": 0.0, "dry_run": true}))).await).unwrap();
+        assert_eq!(response["result"]["plan"]["ids"].as_array().unwrap().len(), 2);
+        
+        let forgotten = result_text(&server, call("forget", json!({"id": minor["id"]}))).await;
+        assert_eq!(forgotten["deleted"][0], minor["id"]);
+        let recalled = result_text(&server, call("recall", json!({"query": "habits"}))).await;
+        assert_eq!(recalled.as_array().unwrap().len(), 1);
+        
+        // Knowledge collections are untouched by memory tools
+        assert_eq!(store.list_collections().await.unwrap(), vec!["memory_session_s-1"]);
+    }
+    
+    #[tokio::test]
+    async fn test_expired_memories_are_not_recalled() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        let server = server(store.clone());
+        
+        let mut document = Document::with_placeholder_embedding("old news".to_string(), 8);
+        document.embedding = vec![1.0; 8];
+        document.metadata.insert(EXPIRES_AT_KEY.to_string(), (Utc::now() - ChronoDuration::seconds(1)).to_rfc3339());
+        store.create_collection("memory_session_s-1", 8).await.unwrap();
+        store.insert_document("memory_session_s-1", document).await.unwrap();
+        
+        let recalled = result_text(&server, call("recall", json!({"query": "news"}))).await;
+        assert_eq!(recalled, json!([]));
+        assert!(store.list_documents("memory_session_s-1", None, 10).await.unwrap().documents.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_remember_rejects_an_unrepresentable_ttl() {
+        let server = server(Arc::new(InMemoryVectorStore::new()));
+        let response: Value = serde_json::from_str(&server.handle_request(&call("remember", json!({"content": "forever", "ttl": u64::MAX}))).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::vector_store::{Document, SearchQuery, SearchResult, VectorStore, VectorStoreError};
+use crate::vector_store::{DistanceMetric, Document, SearchQuery, SearchResult, VectorStore, VectorStoreError};
 use async_trait::async_trait;
 
 /// Mock implementation of the EmbeddedQdrantConnector for testing
@@ -35,11 +35,13 @@ impl VectorStore for MockQdrantConnector {
             id: "test-id".to_string(),
             content: "Test document".to_string(),
             embedding: vec![0.0; 384],
+            metadata: Default::default(),
         };
         
         let result = SearchResult {
             document: doc,
             score: 0.95,
+            metric: DistanceMetric::Cosine,
         };
         
         Ok(vec![result])
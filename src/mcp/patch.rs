@@ -0,0 +1,126 @@
+//! Bulk metadata changes to the entries matching a filter
+
+use serde_json::{json, Value};
+
+use super::{error_response, is_dry_run, missing_argument, store_error_response, ProgmoMcpServer};
+use crate::context::RequestContext;
+use crate::vector_store::{patch::rewrite_metadata, MetadataFilter, MetadataPatch};
+
+impl ProgmoMcpServer {
+    /// Handle a patch_metadata tool call.
+    ///
+    /// Applies a JSON merge patch, and optionally tag renames, to the metadata
+    /// of every entry matching `filter`. Content and embeddings are untouched.
+    pub(super) async fn handle_patch_metadata(&self, _ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let collection_id = match arguments.get("collection_id").and_then(|collection_id| collection_id.as_str()) {
+            Some(collection_id) => collection_id,
+            None => return missing_argument(id, "collection_id"),
+        };
+        
+        let filter = match arguments.get("filter").map(MetadataFilter::from_json) {
+            Some(Ok(filter)) if !filter.is_empty() => filter,
+            Some(Ok(_)) => return error_response(id, -32602, "Invalid params: filter needs at least one condition".to_string()),
+            Some(Err(message)) => return error_response(id, -32602, format!("Invalid params: {}", message)),
+            None => return missing_argument(id, "filter"),
+        };
+        
+        let mut patch = match arguments.get("patch").map(MetadataPatch::from_merge_patch).unwrap_or(Ok(MetadataPatch::default())) {
+            Ok(patch) => patch,
+            Err(message) => return error_response(id, -32602, format!("Invalid params: {}", message)),
+        };
+        if let Some(renames) = arguments.get("rename_tags") {
+            let renames = match renames.as_object() {
+                Some(renames) => renames,
+                None => return error_response(id, -32602, "Invalid params: rename_tags must be an object".to_string()),
+            };
+            for (from, to) in renames {
+                match to.as_str() {
+                    Some(to) if !to.trim().is_empty() && !to.contains(',') => patch = patch.with_tag_rename(from, to.trim()),
+                    _ => return error_response(id, -32602, format!("Invalid params: rename_tags.{} must be a tag name", from)),
+                }
+            }
+        }
+        if patch.is_empty() {
+            return error_response(id, -32602, "Invalid params: nothing to change; give patch or rename_tags".to_string());
+        }
+        
+        let dry_run = is_dry_run(arguments);
+        let outcome = if dry_run {
+            rewrite_metadata(self.vector_store.as_ref(), collection_id, &filter, &patch, true).await
+        } else {
+            self.vector_store.patch_metadata(collection_id, &filter, &patch).await
+        };
+        let outcome = match outcome {
+            Ok(outcome) => outcome,
+            Err(e) => return store_error_response(id, &e),
+        };
+        
+        let text = if dry_run {
+            format!("Dry run: would patch {} of {} matching entries in {}", outcome.updated, outcome.matched, collection_id)
+        } else {
+            format!("Patched {} of {} matching entries in {}", outcome.updated, outcome.matched, collection_id)
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": text
+                    }
+                ],
+                "matched": outcome.matched,
+                "updated": outcome.updated,
+                "dry_run": dry_run
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::ServerConfig;
+    use crate::vector_store::{Document, InMemoryVectorStore, VectorStore};
+    use std::sync::Arc;
+    
+    fn call(id: u64, arguments: Value) -> String {
+        json!({"jsonrpc": "2.0", "id": id, "method": "CallTool", "params": {"name": "patch_metadata", "arguments": arguments}}).to_string()
+    }
+    
+    #[tokio::test]
+    async fn test_patch_metadata_tool() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("docs", 2).await.unwrap();
+        for (id, tags) in [("a", vec!["ops".to_string()]), ("b", vec!["web".to_string()])] {
+            let mut document = Document::with_placeholder_embedding(id.to_string(), 2).with_tags(&tags);
+            document.id = id.to_string();
+            store.insert_document("docs", document).await.unwrap();
+        }
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store.clone());
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(1, json!({
+            "collection_id": "docs",
+            "filter": {"tags": ["ops"]},
+            "patch": {"owner": "sre"},
+            "rename_tags": {"ops": "operations"}
+        }))).await).unwrap();
+        assert_eq!(response["result"]["matched"], 1);
+        assert_eq!(response["result"]["updated"], 1);
+        let patched = store.get_document("docs", "a").await.unwrap().unwrap();
+        assert_eq!(patched.metadata["owner"], "sre");
+        assert_eq!(patched.tags(), vec!["operations"]);
+        assert!(!store.get_document("docs", "b").await.unwrap().unwrap().metadata.contains_key("owner"));
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(2, json!({
+            "collection_id": "docs", "filter": {}, "patch": {"owner": "sre"}
+        }))).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(3, json!({
+            "collection_id": "docs", "filter": {"ids": ["b"]}
+        }))).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+    }
+}
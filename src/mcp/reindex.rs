@@ -0,0 +1,279 @@
+//! Re-embedding a whole collection in the background, tracked as a job
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::{error_response, invalid_response, is_dry_run, missing_argument, store_error_response, ErrorKind, ProgmoMcpServer};
+use crate::context::RequestContext;
+use crate::jobs::{JobState, JOB_URI_SCHEME};
+use crate::progress::Progress;
+use crate::text_processing::EmbeddingProvider;
+use crate::vector_store::{embedding_defect, l2_normalize, CollectionRegistry, MetadataFilter, VectorStore, VectorStoreError, EMBEDDING_PROVIDER_KEY};
+
+/// Kind of the jobs started by reindex_collection, as in `jobs://reindex/<id>`
+pub const REINDEX_JOB: &str = "reindex";
+
+/// Longest a job resource read may wait for the job to move
+pub const MAX_JOB_WAIT: Duration = Duration::from_secs(60);
+
+/// Entries read from the store at a time
+const PAGE_SIZE: usize = 256;
+
+/// Failed entries named in a reindex report; the rest are only counted
+const MAX_REPORTED_FAILURES: usize = 20;
+
+/// The outcome of reindexing a collection
+#[derive(Debug, Default, Serialize)]
+pub struct ReindexReport {
+    pub reindexed: usize,
+    pub failed: usize,
+    /// The first failed entries, with why each failed
+    pub failures: Vec<Value>,
+}
+
+/// Re-embed every entry of `collection` from its content with `provider`.
+///
+/// An entry that cannot be embedded is left as it was and reported; only a
+/// failing store stops the run.
+pub async fn reindex_collection(
+    store: &dyn VectorStore,
+    registry: &CollectionRegistry,
+    provider: &(dyn EmbeddingProvider + Send + Sync),
+    normalize: bool,
+    collection: &str,
+    progress: &Progress,
+) -> Result<ReindexReport, VectorStoreError> {
+    if let Ok(total) = store.count(collection, &MetadataFilter::default()).await {
+        progress.set_total(total as u64);
+    }
+    let mut report = ReindexReport::default();
+    let mut offset = None;
+    loop {
+        let page = store.list_documents(collection, offset, PAGE_SIZE).await?;
+        
+        for mut document in page.documents {
+            progress.inc(1);
+            let embedded = provider.generate_attributed(&document.content)
+                .map_err(|e| e.to_string())
+                .and_then(|(mut embedding, provider)| {
+                    if normalize {
+                        l2_normalize(&mut embedding);
+                    }
+                    registry.validate_dimension(collection, embedding.len()).map_err(|e| e.to_string())?;
+                    match embedding_defect(&embedding) {
+                        Some(defect) => Err(defect.description().to_string()),
+                        None => Ok((embedding, provider)),
+                    }
+                });
+            match embedded {
+                Ok((embedding, provider)) => {
+                    document.embedding = embedding;
+                    match provider {
+                        Some(provider) => document.metadata.insert(EMBEDDING_PROVIDER_KEY.to_string(), provider),
+                        None => document.metadata.remove(EMBEDDING_PROVIDER_KEY),
+                    };
+                    store.insert_document(collection, document).await?;
+                    report.reindexed += 1;
+                },
+                Err(error) => {
+                    warn!(collection = %collection, entry_id = %document.id, "Failed to reindex entry: {}", error);
+                    if report.failures.len() < MAX_REPORTED_FAILURES {
+                        report.failures.push(json!({ "entry_id": document.id, "error": error }));
+                    }
+                    report.failed += 1;
+                },
+            }
+        }
+        
+        match page.next_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+    progress.finish();
+    Ok(report)
+}
+
+impl ProgmoMcpServer {
+    /// Handle a reindex_collection tool call.
+    ///
+    /// Starts re-embedding the collection in the background and returns at
+    /// once with the job's resource URI, which clients read for progress.
+    pub(super) async fn handle_reindex_collection(&self, ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let collection_id = match arguments.get("collection_id").and_then(|collection_id| collection_id.as_str()) {
+            Some(collection_id) => collection_id.to_string(),
+            None => return missing_argument(id, "collection_id"),
+        };
+        let Some(provider) = self.embedding_provider.clone() else {
+            return error_response(id, -32602, "Invalid params: reindexing needs an embedding provider, and none is configured".to_string());
+        };
+        if let Err(e) = self.registry.validate_dimension(&collection_id, provider.embedding_dim()) {
+            return error_response(id, -32602, format!("Invalid params: {}", e));
+        }
+        if let Some(running) = self.jobs.list(REINDEX_JOB).into_iter().find(|job| job.target == collection_id && job.state == JobState::Running) {
+            return error_response(id, -32602, format!("Invalid params: {} is already being reindexed by {}", collection_id, running.uri));
+        }
+        let entries = match self.vector_store.count(&collection_id, &MetadataFilter::default()).await {
+            Ok(entries) => entries,
+            Err(e) => return store_error_response(id, &e),
+        };
+        
+        if is_dry_run(arguments) {
+            return json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "content": [
+                        {
+                            "type": "text",
+                            "text": format!("Dry run: would reindex {} entries of {}", entries, collection_id)
+                        }
+                    ],
+                    "plan": {"dry_run": true, "action": "reindex", "collection_id": collection_id, "entries": entries}
+                }
+            }).to_string();
+        }
+        
+        let job = self.jobs.start(REINDEX_JOB, &collection_id);
+        let status = job.status();
+        info!(request_id = %ctx.request_id, client_id = %ctx.client_label(), collection = %collection_id, job = %status.uri, "Started reindex");
+        
+        let store = self.vector_store.clone();
+        let registry = self.registry.clone();
+        let normalize = self.normalize_embeddings;
+        tokio::spawn(async move {
+            let progress = Progress::job(job.clone(), REINDEX_JOB);
+            match reindex_collection(store.as_ref(), &registry, provider.as_ref(), normalize, &collection_id, &progress).await {
+                Ok(report) => {
+                    info!(collection = %collection_id, reindexed = report.reindexed, failed = report.failed, "Reindex finished");
+                    job.complete(json!(report));
+                },
+                Err(e) => {
+                    warn!(collection = %collection_id, "Reindex failed: {}", e);
+                    job.fail(e.to_string());
+                },
+            }
+        });
+        
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": format!("Started reindexing {} entries of {}; read {} for progress", entries, status.target, status.uri)
+                    }
+                ],
+                "job": status
+            }
+        }).to_string()
+    }
+    
+    /// Read a `jobs://<kind>` resource, listing jobs of that kind, or a
+    /// `jobs://<kind>/<id>` one; `?wait=<secs>` waits for the job to move first
+    pub(super) async fn read_job_resource(&self, id: &Value, uri: &str) -> String {
+        let (path, query) = match uri.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (uri, None),
+        };
+        let wait = query.into_iter()
+            .flat_map(|query| query.split('&'))
+            .find_map(|pair| pair.strip_prefix("wait="))
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .map(|secs| Duration::from_secs(secs).min(MAX_JOB_WAIT));
+        
+        let contents = match path.trim_start_matches(JOB_URI_SCHEME).split_once('/') {
+            None => {
+                let kind = path.trim_start_matches(JOB_URI_SCHEME);
+                serde_json::to_string(&self.jobs.list(kind))
+            },
+            Some((kind, job_id)) => {
+                let status = match wait {
+                    Some(wait) => self.jobs.wait(kind, job_id, wait).await,
+                    None => self.jobs.get(kind, job_id),
+                };
+                match status {
+                    Some(status) => serde_json::to_string(&status),
+                    None => return invalid_response(id, ErrorKind::UnknownResource, format!("Unknown resource: {}", uri)),
+                }
+            },
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "contents": [
+                    {
+                        "uri": uri,
+                        "mimeType": "application/json",
+                        "text": contents.unwrap_or_default()
+                    }
+                ]
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::ServerConfig;
+    use crate::text_processing::embedding::MockEmbeddingGenerator;
+    use crate::vector_store::{Document, InMemoryVectorStore};
+    use std::sync::Arc;
+    
+    fn request(id: u64, method: &str, params: Value) -> String {
+        json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}).to_string()
+    }
+    
+    #[tokio::test]
+    async fn test_reindex_job_reports_progress() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("docs", 4).await.unwrap();
+        for (index, content) in ["alpha", "beta", "gamma"].iter().enumerate() {
+            let mut document = Document::with_placeholder_embedding(content.to_string(), 4);
+            document.id = format!("doc-{}", index);
+            store.insert_document("docs", document).await.unwrap();
+        }
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store.clone())
+            .with_embedding_provider(Arc::new(MockEmbeddingGenerator::new(4)))
+            .unwrap();
+        
+        let started: Value = serde_json::from_str(&server.handle_request(&request(1, "CallTool", json!({
+            "name": "reindex_collection", "arguments": {"collection_id": "docs"}
+        }))).await).unwrap();
+        let uri = started["result"]["job"]["uri"].as_str().unwrap().to_string();
+        assert!(uri.starts_with("jobs://reindex/"));
+        
+        let mut status = Value::Null;
+        for attempt in 0..50 {
+            let read: Value = serde_json::from_str(&server.handle_request(&request(2 + attempt, "ReadResource", json!({
+                "uri": format!("{}?wait=1", uri)
+            }))).await).unwrap();
+            status = serde_json::from_str(read["result"]["contents"][0]["text"].as_str().unwrap()).unwrap();
+            if status["state"] != "running" {
+                break;
+            }
+        }
+        assert_eq!(status["state"], "completed");
+        assert_eq!(status["done"], 3);
+        assert_eq!(status["total"], 3);
+        assert_eq!(status["result"]["reindexed"], 3);
+        assert!(store.get_document("docs", "doc-0").await.unwrap().unwrap().embedding.iter().any(|value| *value != 0.0));
+        
+        let listed: Value = serde_json::from_str(&server.handle_request(&request(99, "ReadResource", json!({"uri": "jobs://reindex"}))).await).unwrap();
+        assert!(listed["result"]["contents"][0]["text"].as_str().unwrap().contains(&uri));
+    }
+    
+    #[tokio::test]
+    async fn test_reindex_needs_a_provider() {
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, Arc::new(InMemoryVectorStore::new()));
+        let response: Value = serde_json::from_str(&server.handle_request(&request(1, "CallTool", json!({
+            "name": "reindex_collection", "arguments": {"collection_id": "docs"}
+        }))).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+    }
+}
@@ -0,0 +1,234 @@
+//! Rendered views of entries for ReadResource.
+//!
+//! `knowledge://collections/X/entries/Y` returns the entry's stored text.
+//! Adding `?format=markdown` or `?format=json` returns the whole entry
+//! instead, reassembled from its chunks with its title, tags and the
+//! sources it was ingested from, so clients can show a readable page
+//! without putting one together themselves.
+
+use serde_json::{json, Value};
+
+use super::{invalid_response, store_error_response, ErrorKind, ProgmoMcpServer};
+use crate::attachments::attachments_of;
+use crate::feeds::FEED_URL_KEY;
+use crate::federation::FEDERATION_SOURCE_KEY;
+use crate::sources::{LINE_KEY, OBJECT_KEY_KEY, PAGE_KEY};
+use crate::vector_store::chunks::load_chunks;
+use crate::vector_store::{Document, EntryId, SOURCE_KEY};
+
+/// How an entry resource is rendered, chosen with the `format` query parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EntryFormat {
+    /// The stored text of the entry's first chunk
+    #[default]
+    Text,
+    /// A Markdown page with title, tags, content and sources
+    Markdown,
+    /// The same fields as JSON
+    Json,
+}
+
+impl EntryFormat {
+    /// Read the format from a resource URI's query string; other parameters are ignored
+    pub fn from_query(query: Option<&str>) -> Result<Self, String> {
+        let format = query.into_iter()
+            .flat_map(|query| query.split('&'))
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == "format")
+            .map(|(_, value)| value);
+        match format {
+            None | Some("text") => Ok(Self::Text),
+            Some("markdown") => Ok(Self::Markdown),
+            Some("json") => Ok(Self::Json),
+            Some(other) => Err(format!("unknown format '{}'; expected 'text', 'markdown' or 'json'", other)),
+        }
+    }
+    
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Text => "text/plain",
+            Self::Markdown => "text/markdown",
+            Self::Json => "application/json",
+        }
+    }
+}
+
+/// Where the chunks of an entry came from, first seen first, without repeats
+pub fn citations(chunks: &[Document]) -> Vec<String> {
+    let mut citations: Vec<String> = Vec::new();
+    let mut cite = |citation: String| {
+        if !citations.contains(&citation) {
+            citations.push(citation);
+        }
+    };
+    
+    for chunk in chunks {
+        let metadata = &chunk.metadata;
+        if let Some(source) = metadata.get(SOURCE_KEY).or_else(|| metadata.get(OBJECT_KEY_KEY)) {
+            cite(match (metadata.get(PAGE_KEY), metadata.get(LINE_KEY)) {
+                (Some(page), _) => format!("{}, page {}", source, page),
+                (None, Some(line)) => format!("{}, line {}", source, line),
+                (None, None) => source.clone(),
+            });
+        }
+        if let Some(feed_url) = metadata.get(FEED_URL_KEY) {
+            cite(feed_url.clone());
+        }
+        if let Some(source) = metadata.get(FEDERATION_SOURCE_KEY) {
+            cite(format!("federated from {}", source));
+        }
+        for attachment in attachments_of(chunk) {
+            let name = attachment.filename.clone().unwrap_or_else(|| attachment.hash.clone());
+            cite(format!("[{}]({})", name, attachment.url()));
+        }
+    }
+    citations
+}
+
+/// The content of an entry, its chunks joined back into paragraphs
+fn assemble(chunks: &[Document]) -> String {
+    chunks.iter()
+        .map(|chunk| chunk.content.trim())
+        .collect::<Vec<&str>>()
+        .join("\n\n")
+}
+
+/// Render an entry, given its chunks in order, as a Markdown page
+pub fn render_markdown(entry_id: &str, chunks: &[Document]) -> String {
+    let first = &chunks[0];
+    let mut page = format!("# {}\n\n", first.title().unwrap_or(entry_id));
+    
+    let tags = first.tags();
+    if !tags.is_empty() {
+        let tags: Vec<String> = tags.iter().map(|tag| format!("`{}`", tag)).collect();
+        page.push_str(&format!("Tags: {}\n\n", tags.join(", ")));
+    }
+    
+    page.push_str(&assemble(chunks));
+    page.push('\n');
+    
+    let citations = citations(chunks);
+    if !citations.is_empty() {
+        page.push_str("\n## Sources\n\n");
+        for (number, citation) in citations.iter().enumerate() {
+            page.push_str(&format!("{}. {}\n", number + 1, citation));
+        }
+    }
+    page
+}
+
+/// Render an entry, given its chunks in order, as JSON
+pub fn render_json(entry_id: &str, chunks: &[Document]) -> Value {
+    let first = &chunks[0];
+    json!({
+        "id": entry_id,
+        "title": first.title(),
+        "tags": first.tags(),
+        "content": assemble(chunks),
+        "citations": citations(chunks),
+        "chunks": chunks.len()
+    })
+}
+
+impl ProgmoMcpServer {
+    /// Read an entry resource, rendered as its URI's query asks
+    pub(super) async fn read_entry_resource(&self, id: &Value, uri: &str, collection_id: &str, entry_id: &str, query: Option<&str>) -> String {
+        let entry_id = match EntryId::parse(entry_id) {
+            Ok(entry_id) => entry_id,
+            Err(e) => return invalid_response(id, ErrorKind::UnknownResource, format!("Invalid URI: {}: {}", uri, e)),
+        };
+        let format = match EntryFormat::from_query(query) {
+            Ok(format) => format,
+            Err(message) => return invalid_response(id, ErrorKind::UnknownResource, format!("Invalid URI: {}: {}", uri, message)),
+        };
+        
+        let chunks = match format {
+            EntryFormat::Text => self.vector_store.get_document(collection_id, entry_id.as_str()).await
+                .map(|document| document.into_iter().collect::<Vec<Document>>()),
+            EntryFormat::Markdown | EntryFormat::Json => load_chunks(self.vector_store.as_ref(), collection_id, entry_id.as_str()).await,
+        };
+        let chunks = match chunks {
+            Ok(chunks) if chunks.is_empty() => return invalid_response(id, ErrorKind::UnknownResource, format!("Unknown resource: {}", uri)),
+            Ok(chunks) => chunks,
+            Err(e) => return store_error_response(id, &e),
+        };
+        
+        let text = match format {
+            EntryFormat::Text => chunks[0].content.clone(),
+            EntryFormat::Markdown => render_markdown(entry_id.as_str(), &chunks),
+            EntryFormat::Json => render_json(entry_id.as_str(), &chunks).to_string(),
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "contents": [
+                    {
+                        "uri": uri,
+                        "mimeType": format.mime_type(),
+                        "text": text
+                    }
+                ]
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::ServerConfig;
+    use crate::vector_store::chunks::{chunk_id, PARENT_ID_KEY};
+    use crate::vector_store::{InMemoryVectorStore, VectorStore};
+    use std::sync::Arc;
+    
+    fn chunk(entry_id: &str, index: usize, content: &str, source: &str) -> Document {
+        let mut document = Document::with_placeholder_embedding(content.to_string(), 2)
+            .with_title("Key rotation")
+            .with_tags(&["ops".to_string(), "security".to_string()]);
+        document.id = chunk_id(entry_id, index);
+        document.metadata.insert(PARENT_ID_KEY.to_string(), entry_id.to_string());
+        document.metadata.insert(SOURCE_KEY.to_string(), source.to_string());
+        document
+    }
+    
+    async fn read(server: &ProgmoMcpServer, uri: &str) -> Value {
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "ReadResource", "params": {"uri": uri}}).to_string();
+        serde_json::from_str(&server.handle_request(&request).await).unwrap()
+    }
+    
+    #[test]
+    fn test_format_from_query() {
+        assert_eq!(EntryFormat::from_query(None), Ok(EntryFormat::Text));
+        assert_eq!(EntryFormat::from_query(Some("lang=en&format=markdown")), Ok(EntryFormat::Markdown));
+        assert!(EntryFormat::from_query(Some("format=pdf")).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_entries_render_from_all_chunks() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("docs", 2).await.unwrap();
+        store.insert_document("docs", chunk("rotation", 0, "Rotate keys monthly.", "runbooks/keys.md")).await.unwrap();
+        store.insert_document("docs", chunk("rotation", 1, "Revoke the old key a day later.", "runbooks/keys.md")).await.unwrap();
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store);
+        
+        let plain = read(&server, "knowledge://collections/docs/entries/rotation").await;
+        assert_eq!(plain["result"]["contents"][0]["text"], "Rotate keys monthly.");
+        
+        let markdown = read(&server, "knowledge://collections/docs/entries/rotation?format=markdown").await;
+        let contents = &markdown["result"]["contents"][0];
+        assert_eq!(contents["mimeType"], "text/markdown");
+        assert_eq!(contents["uri"], "knowledge://collections/docs/entries/rotation?format=markdown");
+        assert_eq!(contents["text"], "# Key rotation\n\nTags: `ops`, `security`\n\nRotate keys monthly.\n\nRevoke the old key a day later.\n\n## Sources\n\n1. runbooks/keys.md\n");
+        
+        let rendered = read(&server, "knowledge://collections/docs/entries/rotation?format=json").await;
+        let entry: Value = serde_json::from_str(rendered["result"]["contents"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(entry["chunks"], 2);
+        assert_eq!(entry["citations"], json!(["runbooks/keys.md"]));
+        
+        let unknown = read(&server, "knowledge://collections/docs/entries/rotation?format=pdf").await;
+        assert_eq!(unknown["error"]["code"], -32602);
+        let missing = read(&server, "knowledge://collections/docs/entries/missing?format=markdown").await;
+        assert_eq!(missing["error"]["code"], -32602);
+    }
+}
@@ -0,0 +1,102 @@
+//! Retrieval provenance.
+//!
+//! Every search_knowledge and get_context result carries a `retrieval_id`,
+//! and the result is logged under that id, so get_retrieval can later show
+//! exactly what a caller was given, even after the collection has changed.
+
+use serde_json::{json, Value};
+
+use super::{error_response, missing_argument, ProgmoMcpServer};
+use crate::context::RequestContext;
+use crate::logging::RetrievalRecord;
+
+impl ProgmoMcpServer {
+    /// Stamp `result` with a new retrieval id and log it under that id
+    pub(super) fn watermark(&self, ctx: &RequestContext, tool: &str, collection_id: &str, query: &str, mut result: Value) -> Value {
+        let mut record = RetrievalRecord::new(tool, collection_id, query, ctx.client_label(), result.clone());
+        record.retrieval_id = self.determinism.uuid().to_string();
+        record.timestamp = self.determinism.now();
+        result["retrieval_id"] = json!(record.retrieval_id);
+        self.retrieval_log.record(record);
+        result
+    }
+    
+    /// Handle a get_retrieval tool call
+    pub(super) async fn handle_get_retrieval(&self, _ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let retrieval_id = match arguments.get("retrieval_id").and_then(|retrieval_id| retrieval_id.as_str()) {
+            Some(retrieval_id) => retrieval_id,
+            None => return missing_argument(id, "retrieval_id"),
+        };
+        let record = match self.retrieval_log.get(retrieval_id) {
+            Some(record) => record,
+            None => return error_response(id, -32602, format!("Invalid params: unknown retrieval '{}'", retrieval_id)),
+        };
+        
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": format!("{} of '{}' in {} at {}", record.tool, record.query, record.collection, record.timestamp.to_rfc3339())
+                    }
+                ],
+                "retrieval": record
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::ServerConfig;
+    use crate::text_processing::embedding::MockEmbeddingGenerator;
+    use crate::vector_store::{CollectionInfo, InMemoryVectorStore, VectorStore};
+    use std::sync::Arc;
+    
+    fn call(id: u64, name: &str, arguments: Value) -> String {
+        json!({"jsonrpc": "2.0", "id": id, "method": "CallTool", "params": {"name": name, "arguments": arguments}}).to_string()
+    }
+    
+    async fn response(server: &ProgmoMcpServer, request: String) -> Value {
+        serde_json::from_str(&server.handle_request(&request).await).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_retrieval_returns_what_was_returned_earlier() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("docs", 8).await.unwrap();
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store.clone())
+            .with_embedding_provider(Arc::new(MockEmbeddingGenerator::new(8)))
+            .unwrap();
+        server.registry().register(CollectionInfo::new("docs", 8));
+        
+        response(&server, call(1, "add_knowledge_entry", json!({
+            "collection_id": "docs", "entry_id": "deploys", "title": "Deploys", "content": "deploys happen on tuesdays"
+        }))).await;
+        let searched = response(&server, call(2, "search_knowledge", json!({"collection_id": "docs", "query": "when are deploys"}))).await;
+        let search_id = searched["result"]["retrieval_id"].as_str().unwrap().to_string();
+        let context = response(&server, call(3, "get_context", json!({"collection_id": "docs", "query": "when are deploys"}))).await;
+        let context_id = context["result"]["retrieval_id"].as_str().unwrap().to_string();
+        assert_ne!(search_id, context_id);
+        
+        // Later changes to the collection do not change the recorded result
+        store.delete_document("docs", "deploys").await.unwrap();
+        
+        let retrieval = response(&server, call(4, "get_retrieval", json!({"retrieval_id": search_id}))).await;
+        let record = &retrieval["result"]["retrieval"];
+        assert_eq!(record["tool"], "search_knowledge");
+        assert_eq!(record["collection"], "docs");
+        assert_eq!(record["query"], "when are deploys");
+        assert_eq!(record["result"]["content"], searched["result"]["content"]);
+        assert!(record["result"]["content"][0]["text"].as_str().unwrap().contains("deploys"));
+        
+        let retrieval = response(&server, call(5, "get_retrieval", json!({"retrieval_id": context_id}))).await;
+        assert_eq!(retrieval["result"]["retrieval"]["result"]["context"], context["result"]["context"]);
+        
+        let unknown = response(&server, call(6, "get_retrieval", json!({"retrieval_id": "nope"}))).await;
+        assert_eq!(unknown["error"]["code"], -32602);
+    }
+}
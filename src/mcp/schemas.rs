@@ -0,0 +1,357 @@
+//! Schemas of tool results, resource contents and errors, checked in debug builds.
+//!
+//! Every tool documents the shape of its result as `outputSchema` in
+//! ListTools. Debug and test builds validate each response the server sends
+//! against the schema of its tool, of resource contents or of errors, and
+//! panic on a mismatch: a result that drifts from its documentation fails the
+//! first test that produces it instead of surprising clients. Release builds
+//! skip the check.
+//!
+//! The validator covers the part of JSON Schema the server's schemas use:
+//! `type`, `properties`, `required`, `additionalProperties`, `items`,
+//! `minItems`, `enum`, `const`, `minimum`, `maximum` and `anyOf`/`oneOf`.
+//! Other keywords are ignored.
+
+use serde_json::{json, Value};
+
+use super::tools::ToolSpec;
+
+/// Schema of a tool result: content parts plus `properties`, nothing else
+pub fn tool_result(properties: Value, required: &[&str]) -> Value {
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties,
+        "required": ["content"],
+        "additionalProperties": false,
+    });
+    schema["properties"]["content"] = content_schema();
+    if let Some(keys) = schema["required"].as_array_mut() {
+        keys.extend(required.iter().map(|key| json!(key)));
+    }
+    schema
+}
+
+/// Schema of what a mutating tool returns for a dry run: a plan of what
+/// would change, and for batches the planned items
+pub fn dry_run_result() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "content": content_schema(),
+            "plan": {
+                "type": "object",
+                "properties": {
+                    "dry_run": {"const": true},
+                    "action": {"type": "string"},
+                },
+                "required": ["dry_run", "action"],
+            },
+            "items": {"type": "array", "items": {"type": "object"}},
+        },
+        "required": ["content", "plan"],
+        "additionalProperties": false,
+    })
+}
+
+/// Schema of the content parts of a tool result
+fn content_schema() -> Value {
+    json!({
+        "type": "array",
+        "items": {
+            "anyOf": [
+                {
+                    "type": "object",
+                    "properties": {"type": {"const": "text"}, "text": {"type": "string"}},
+                    "required": ["type", "text"],
+                    "additionalProperties": false,
+                },
+                {
+                    "type": "object",
+                    "properties": {"type": {"const": "json"}, "json": {}, "schema": {"type": "object"}},
+                    "required": ["type", "json", "schema"],
+                    "additionalProperties": false,
+                },
+                {
+                    "type": "object",
+                    "properties": {
+                        "type": {"const": "resource_link"},
+                        "uri": {"type": "string"},
+                        "name": {"type": "string"},
+                        "mimeType": {"type": "string"},
+                        "title": {"type": "string"},
+                    },
+                    "required": ["type", "uri", "name"],
+                    "additionalProperties": false,
+                },
+            ],
+        },
+    })
+}
+
+/// Schema of a ReadResource result
+pub fn resource_result() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "contents": {
+                "type": "array",
+                "minItems": 1,
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "uri": {"type": "string"},
+                        "mimeType": {"enum": ["application/json", "text/plain", "text/markdown"]},
+                        "text": {"type": "string"},
+                    },
+                    "required": ["uri", "mimeType", "text"],
+                    "additionalProperties": false,
+                },
+            },
+        },
+        "required": ["contents"],
+        "additionalProperties": false,
+    })
+}
+
+/// Schema of a JSON-RPC error; `data` may carry details beyond the code and hint
+pub fn error_object() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "code": {"type": "integer"},
+            "message": {"type": "string"},
+            "data": {
+                "type": "object",
+                "properties": {
+                    "error_code": {"type": "string"},
+                    "hint": {"type": "string"},
+                },
+                "required": ["error_code"],
+            },
+        },
+        "required": ["code", "message", "data"],
+        "additionalProperties": false,
+    })
+}
+
+/// Where `value` breaks `schema`, one message per violation; empty when it conforms
+pub fn violations(schema: &Value, value: &Value) -> Vec<String> {
+    let mut found = Vec::new();
+    check(schema, value, "$", &mut found);
+    found
+}
+
+fn check(schema: &Value, value: &Value, path: &str, found: &mut Vec<String>) {
+    if let Some(types) = schema.get("type") {
+        let allowed: Vec<&str> = match types {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.iter().any(|name| has_type(value, name)) {
+            found.push(format!("{}: expected {}, got {}", path, allowed.join(" or "), type_name(value)));
+            return;
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            found.push(format!("{}: expected {}, got {}", path, expected, value));
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            found.push(format!("{}: {} is not one of {}", path, value, Value::Array(options.clone())));
+        }
+    }
+    if let Some(options) = schema.get("anyOf").or_else(|| schema.get("oneOf")).and_then(Value::as_array) {
+        if !options.iter().any(|option| violations(option, value).is_empty()) {
+            found.push(format!("{}: {} matches none of the allowed shapes", path, value));
+        }
+    }
+    
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+            if number < minimum {
+                found.push(format!("{}: {} is below the minimum of {}", path, number, minimum));
+            }
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+            if number > maximum {
+                found.push(format!("{}: {} is above the maximum of {}", path, number, maximum));
+            }
+        }
+    }
+    
+    if let Value::Object(fields) = value {
+        for key in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+            if !fields.contains_key(key) {
+                found.push(format!("{}: missing {}", path, key));
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, field) in fields {
+            let field_path = format!("{}.{}", path, key);
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(property) => check(property, field, &field_path, found),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => found.push(format!("{}: not in the schema", field_path)),
+                    Some(additional @ Value::Object(_)) => check(additional, field, &field_path, found),
+                    _ => {},
+                },
+            }
+        }
+    }
+    
+    if let Value::Array(items) = value {
+        if let Some(min_items) = schema.get("minItems").and_then(Value::as_u64) {
+            if (items.len() as u64) < min_items {
+                found.push(format!("{}: {} items, at least {} expected", path, items.len(), min_items));
+            }
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                check(item_schema, item, &format!("{}[{}]", path, index), found);
+            }
+        }
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Panic if the response to a call of `tool` breaks its documented schema;
+/// does nothing in release builds
+pub fn debug_check_tool_response(tool: &ToolSpec, response: &str) {
+    if cfg!(debug_assertions) {
+        check_response(&format!("tool {}", tool.name), &tool.result_schema(), response);
+    }
+}
+
+/// Panic if the response to a ReadResource of `uri` breaks the resource
+/// schema; does nothing in release builds
+pub fn debug_check_resource_response(uri: &str, response: &str) {
+    if cfg!(debug_assertions) {
+        check_response(&format!("resource {}", uri), &resource_result(), response);
+    }
+}
+
+fn check_response(what: &str, result_schema: &Value, response: &str) {
+    let response: Value = match serde_json::from_str(response) {
+        Ok(response) => response,
+        Err(e) => panic!("{} sent a response that is not JSON: {}", what, e),
+    };
+    
+    let mut found = Vec::new();
+    if response.get("jsonrpc") != Some(&json!("2.0")) {
+        found.push("$.jsonrpc: expected \"2.0\"".to_string());
+    }
+    match (response.get("result"), response.get("error")) {
+        (Some(result), None) => {
+            check(result_schema, result, "$.result", &mut found);
+            check_embedded(result, &mut found);
+        },
+        (None, Some(error)) => check(&error_object(), error, "$.error", &mut found),
+        _ => found.push("$: expected exactly one of result and error".to_string()),
+    }
+    assert!(found.is_empty(), "{} sent a response that breaks its schema:\n  {}\nresponse: {}", what, found.join("\n  "), response);
+}
+
+/// Check what the outer schemas leave opaque: json content parts against the
+/// schema they carry, and JSON resource text for being JSON
+fn check_embedded(result: &Value, found: &mut Vec<String>) {
+    for (index, part) in result["content"].as_array().into_iter().flatten().enumerate() {
+        if part["type"] == "json" {
+            check(&part["schema"], &part["json"], &format!("$.result.content[{}].json", index), found);
+        }
+    }
+    for (index, contents) in result["contents"].as_array().into_iter().flatten().enumerate() {
+        if contents["mimeType"] == "application/json" {
+            let text = contents["text"].as_str().unwrap_or_default();
+            if serde_json::from_str::<Value>(text).is_err() {
+                found.push(format!("$.result.contents[{}].text: not JSON", index));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::{tool_spec, TOOLS};
+    
+    #[test]
+    fn test_violations_name_the_path() {
+        let schema = tool_result(json!({
+            "count": {"type": "integer", "minimum": 0},
+            "state": {"enum": ["running", "done"]},
+        }), &["count"]);
+        let conforming = json!({"content": [{"type": "text", "text": "3"}], "count": 3, "state": "done"});
+        assert!(violations(&schema, &conforming).is_empty());
+        
+        let broken = json!({"content": [{"type": "image"}], "count": -1.5, "state": "lost", "extra": true});
+        let found = violations(&schema, &broken);
+        assert!(found.iter().any(|v| v.starts_with("$.content[0]:")), "{:?}", found);
+        assert!(found.iter().any(|v| v == "$.count: expected integer, got number"), "{:?}", found);
+        assert!(found.iter().any(|v| v.starts_with("$.state:")), "{:?}", found);
+        assert!(found.iter().any(|v| v == "$.extra: not in the schema"), "{:?}", found);
+        
+        let missing = violations(&schema, &json!({"content": []}));
+        assert_eq!(missing, ["$: missing count"]);
+    }
+    
+    #[test]
+    fn test_mutating_tools_also_allow_plans() {
+        let add = tool_spec("add_knowledge_entry").unwrap().result_schema();
+        let plan = json!({
+            "content": [{"type": "text", "text": "Dry run"}],
+            "plan": {"dry_run": true, "action": "add", "ids": ["a"]}
+        });
+        assert!(violations(&add, &plan).is_empty());
+        
+        let count = tool_spec("count_entries").unwrap().result_schema();
+        assert!(!violations(&count, &plan).is_empty());
+        
+        for tool in TOOLS {
+            assert_eq!(tool.to_json()["outputSchema"], tool.result_schema(), "{}", tool.name);
+        }
+    }
+    
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "breaks its schema")]
+    fn test_mismatched_response_panics() {
+        let response = json!({"jsonrpc": "2.0", "id": 1, "result": {"content": [], "count": "three"}});
+        debug_check_tool_response(tool_spec("count_entries").unwrap(), &response.to_string());
+    }
+    
+    #[test]
+    fn test_errors_and_resources_are_checked() {
+        let error = json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32602, "message": "Invalid params", "data": {"error_code": "invalid_params", "field_errors": []}}});
+        debug_check_tool_response(tool_spec("count_entries").unwrap(), &error.to_string());
+        
+        let resource = json!({"jsonrpc": "2.0", "id": 1, "result": {"contents": [{"uri": "knowledge://collections/docs", "mimeType": "application/json", "text": "[\"docs\"]"}]}});
+        debug_check_resource_response("knowledge://collections/docs", &resource.to_string());
+    }
+}
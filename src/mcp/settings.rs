@@ -0,0 +1,197 @@
+//! Tuning a collection's default search parameters and entry TTL at runtime
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use super::{error_response, invalid_response, is_dry_run, missing_argument, ErrorKind, ProgmoMcpServer};
+use crate::context::RequestContext;
+use crate::logging::ConfigChange;
+use crate::vector_store::{check_ttl, SearchDefaults};
+
+/// Apply one setting from the arguments: absent keeps `current`, `null` clears it
+fn merge_setting<T: DeserializeOwned>(arguments: &Value, key: &str, current: Option<T>) -> Result<Option<T>, String> {
+    match arguments.get(key) {
+        None => Ok(current),
+        Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value.clone())
+            .map(Some)
+            .map_err(|e| format!("Invalid params: {}: {}", key, e)),
+    }
+}
+
+/// One audit record per search setting that differs between `old` and `new`
+pub fn search_defaults_changes(collection: &str, old: &SearchDefaults, new: &SearchDefaults, actor: &str) -> Vec<ConfigChange> {
+    let old = serde_json::to_value(old).unwrap_or_default();
+    let new = serde_json::to_value(new).unwrap_or_default();
+    let mut keys: Vec<&String> = old.as_object().into_iter().chain(new.as_object()).flat_map(|fields| fields.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|key| old.get(key.as_str()) != new.get(key.as_str()))
+        .map(|key| ConfigChange::new(
+            format!("collections.{}.search.{}", collection, key),
+            old.get(key.as_str()).cloned().unwrap_or(Value::Null),
+            new.get(key.as_str()).cloned().unwrap_or(Value::Null),
+            actor,
+            "update_collection_settings",
+        ))
+        .collect()
+}
+
+/// The collection's defaults with the settings given in `arguments` applied
+pub fn merge_search_defaults(current: SearchDefaults, arguments: &Value) -> Result<SearchDefaults, String> {
+    Ok(SearchDefaults {
+        limit: merge_setting(arguments, "limit", current.limit)?,
+        score_threshold: merge_setting(arguments, "score_threshold", current.score_threshold)?,
+        hybrid: merge_setting(arguments, "hybrid", current.hybrid)?,
+        rerank: merge_setting(arguments, "rerank", current.rerank)?,
+    })
+}
+
+impl ProgmoMcpServer {
+    /// Handle an update_collection_settings tool call
+    pub(super) async fn handle_update_collection_settings(&self, ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let collection_id = match arguments.get("collection_id").and_then(|collection_id| collection_id.as_str()) {
+            Some(collection_id) => collection_id,
+            None => return missing_argument(id, "collection_id"),
+        };
+        let (current, current_ttl) = match self.registry.get(collection_id) {
+            Some(info) => (info.search, info.ttl_secs),
+            None => return invalid_response(id, ErrorKind::CollectionNotFound, format!("Invalid params: collection '{}' is not registered", collection_id)),
+        };
+        
+        let settings = match merge_search_defaults(current.clone(), arguments) {
+            Ok(settings) => settings,
+            Err(message) => return error_response(id, -32602, message),
+        };
+        let ttl_secs = match merge_setting(arguments, "ttl_secs", current_ttl) {
+            Ok(ttl_secs) => ttl_secs,
+            Err(message) => return error_response(id, -32602, message),
+        };
+        if let Some(Err(message)) = ttl_secs.map(check_ttl) {
+            return error_response(id, -32602, format!("Invalid params: ttl_secs: {}", message));
+        }
+        
+        let dry_run = is_dry_run(arguments);
+        let outcome = if dry_run {
+            settings.check()
+        } else {
+            self.registry.set_search_defaults(collection_id, settings.clone())
+                .and_then(|()| self.registry.set_ttl(collection_id, ttl_secs))
+        };
+        if let Err(e) = outcome {
+            return error_response(id, -32602, format!("Invalid params: {}", e));
+        }
+        if !dry_run {
+            for change in search_defaults_changes(collection_id, &current, &settings, ctx.client_label()) {
+                self.audit_log.record(change);
+            }
+            if ttl_secs != current_ttl {
+                self.audit_log.record(ConfigChange::new(
+                    format!("collections.{}.ttl_secs", collection_id),
+                    json!(current_ttl),
+                    json!(ttl_secs),
+                    ctx.client_label(),
+                    "update_collection_settings",
+                ));
+            }
+        }
+        
+        let text = if dry_run {
+            format!("Dry run: would update search settings of {}", collection_id)
+        } else {
+            format!("Updated search settings of {}", collection_id)
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": text
+                    }
+                ],
+                "collection_id": collection_id,
+                "settings": settings,
+                "ttl_secs": ttl_secs,
+                "dry_run": dry_run
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::ServerConfig;
+    use crate::text_processing::embedding::MockEmbeddingGenerator;
+    use crate::vector_store::{CollectionInfo, CollectionRegistry, Document, HybridWeights, InMemoryVectorStore, VectorStore};
+    use std::sync::Arc;
+    
+    fn call(id: u64, name: &str, arguments: Value) -> String {
+        json!({"jsonrpc": "2.0", "id": id, "method": "CallTool", "params": {"name": name, "arguments": arguments}}).to_string()
+    }
+    
+    #[test]
+    fn test_merge_search_defaults() {
+        let current = SearchDefaults { limit: Some(5), score_threshold: Some(0.2), ..Default::default() };
+        let merged = merge_search_defaults(current, &json!({
+            "score_threshold": null,
+            "hybrid": {"vector": 0.6, "keyword": 0.4}
+        })).unwrap();
+        assert_eq!(merged.limit, Some(5));
+        assert_eq!(merged.score_threshold, None);
+        assert_eq!(merged.hybrid, Some(HybridWeights { vector: 0.6, keyword: 0.4 }));
+        
+        assert!(merge_search_defaults(SearchDefaults::default(), &json!({"limit": "ten"})).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_settings_apply_when_arguments_are_omitted() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("docs", 2).await.unwrap();
+        for (id, embedding) in [("near", vec![1.0, 0.0]), ("far", vec![0.0, 1.0]), ("middle", vec![0.8, 0.6])] {
+            store.insert_document("docs", Document {
+                id: id.to_string(),
+                content: id.to_string(),
+                embedding,
+                metadata: Default::default(),
+            }).await.unwrap();
+        }
+        let registry = Arc::new(CollectionRegistry::new());
+        registry.register(CollectionInfo::new("docs", 2));
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store)
+            .with_registry(registry.clone())
+            .with_embedding_provider(Arc::new(MockEmbeddingGenerator::new(2)))
+            .unwrap();
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(1, "update_collection_settings", json!({
+            "collection_id": "docs", "limit": 2, "dry_run": true
+        }))).await).unwrap();
+        assert_eq!(response["result"]["settings"]["limit"], 2);
+        assert_eq!(registry.search_defaults("docs"), SearchDefaults::default());
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(2, "update_collection_settings", json!({
+            "collection_id": "docs", "limit": 2
+        }))).await).unwrap();
+        assert_eq!(response["result"]["dry_run"], false);
+        assert_eq!(registry.search_defaults("docs").limit, Some(2));
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(3, "search_knowledge", json!({
+            "collection_id": "docs", "query": "anything"
+        }))).await).unwrap();
+        let results: Vec<Value> = serde_json::from_str(response["result"]["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(results.len(), 2);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(4, "update_collection_settings", json!({
+            "collection_id": "unknown", "limit": 2
+        }))).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&call(5, "update_collection_settings", json!({
+            "collection_id": "docs", "hybrid": {"vector": 0, "keyword": 0}
+        }))).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+    }
+}
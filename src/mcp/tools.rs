@@ -0,0 +1,641 @@
+//! The catalog of tools the server offers, and per-tool enablement
+
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+use super::aliases::alias;
+use super::schemas::{dry_run_result, tool_result};
+use crate::api_keys::Scope;
+use crate::config::ToolsConfig;
+
+/// Error code returned when a disabled tool is called
+pub const CAPABILITY_DISABLED: i64 = -32001;
+
+/// Description of one tool, as returned by ListTools
+pub struct ToolSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Whether the tool changes stored data; such tools accept `dry_run`
+    pub mutating: bool,
+    /// Builds the JSON schema of the tool's arguments
+    pub input_schema: fn() -> Value,
+    /// Builds the JSON schema of the tool's result, not counting dry runs
+    pub output_schema: fn() -> Value,
+}
+
+impl ToolSpec {
+    pub fn to_json(&self) -> Value {
+        let mut schema = (self.input_schema)();
+        if self.mutating {
+            schema["properties"]["dry_run"] = json!({
+                "type": "boolean",
+                "description": "Validate and report what would change without persisting anything"
+            });
+        }
+        
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "inputSchema": schema,
+            "outputSchema": self.result_schema(),
+        })
+    }
+    
+    /// The JSON schema of every result the tool returns; a mutating tool
+    /// returns a plan instead when called with `dry_run`
+    pub fn result_schema(&self) -> Value {
+        let schema = (self.output_schema)();
+        if self.mutating {
+            json!({ "anyOf": [schema, dry_run_result()] })
+        } else {
+            schema
+        }
+    }
+}
+
+fn object_schema(properties: Value, required: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// Schema of an entry filter; every given condition must hold
+fn filter_schema() -> Value {
+    object_schema(json!({
+        "ids": {"type": "array", "items": {"type": "string"}},
+        "tags": {"type": "array", "items": {"type": "string"}},
+        "metadata": {"type": "object", "additionalProperties": {"type": ["string", "number", "boolean"]}},
+    }), &[])
+}
+
+/// Schema of what an add call reports about each entry it stores
+fn entry_summary_schema() -> Value {
+    object_schema(json!({
+        "id": {"type": "string"},
+        "ids": {"type": "array", "items": {"type": "string"}},
+        "chunks": {"type": "integer"},
+        "summary_id": {"type": ["string", "null"]},
+        "collection_id": {"type": "string"},
+        "title": {"type": "string"},
+        "title_generated": {"type": "boolean"},
+        "language": {"type": ["string", "null"]},
+        "expires_at": {"type": ["string", "null"]},
+        "injection_signals": {"type": "array", "items": {"type": "string"}},
+        "placeholder_embedding": {"type": "boolean"},
+    }), &["id", "ids", "chunks", "collection_id", "title"])
+}
+
+/// Schema of how many of an entry's chunks an update kept, re-embedded and dropped
+fn chunk_counts_schema() -> Value {
+    object_schema(json!({
+        "total": {"type": "integer"},
+        "reused": {"type": "integer"},
+        "recomputed": {"type": "integer"},
+        "removed": {"type": "integer"},
+    }), &["total", "reused", "recomputed", "removed"])
+}
+
+/// Every tool the server implements
+pub const TOOLS: &[ToolSpec] = &[
+    ToolSpec {
+        name: "add_knowledge_entry",
+        description: "Add an entry to a knowledge collection; long content is split into chunks stored under the entry id",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "entry_id": {"type": "string"},
+            "title": {"type": "string"},
+            "content": {"type": "string"},
+            "tags": {"type": "array", "items": {"type": "string"}},
+            "metadata": {"type": "object"},
+            "ttl": {"type": "integer", "minimum": 1, "description": "Seconds until the entry expires"},
+            "untrusted": {"type": "boolean", "description": "Sanitize the entry and flag likely prompt injection, as safe mode does"},
+            "embedding": {"type": "array", "items": {"type": "number"}, "description": "Precomputed embedding of the content, used instead of the server's model"},
+            "summarize": {"type": "boolean", "description": "Also store an extractive summary of chunked content, matched by two_stage and hierarchical searches"},
+        }), &["collection_id", "content"]),
+        output_schema: || tool_result(json!({"entry": entry_summary_schema()}), &["entry"]),
+    },
+    ToolSpec {
+        name: "add_knowledge_entries",
+        description: "Add several entries to a knowledge collection, reporting each entry's outcome so failures can be retried alone",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "entries": {
+                "type": "array",
+                "maxItems": super::batch::MAX_BATCH_ENTRIES,
+                "items": {
+                    "type": "object",
+                    "description": "An entry with the fields add_knowledge_entry takes, except collection_id"
+                }
+            },
+            "atomic": {"type": "boolean", "description": "Store every entry or none; by default valid entries are stored even when others fail"},
+        }), &["collection_id", "entries"]),
+        output_schema: || tool_result(json!({
+            "collection_id": {"type": "string"},
+            "atomic": {"type": "boolean"},
+            "inserted": {"type": "integer"},
+            "failed": {"type": "integer"},
+            "items": {"type": "array", "items": object_schema(json!({
+                "index": {"type": "integer"},
+                "id": {"type": ["string", "null"]},
+                "status": {"enum": ["inserted", "failed", "skipped", "rolled_back"]},
+                "entry": entry_summary_schema(),
+                "error": {"type": "object"},
+            }), &["index", "id", "status"])},
+        }), &["collection_id", "atomic", "inserted", "failed", "items"]),
+    },
+    ToolSpec {
+        name: "update_knowledge_entry",
+        description: "Replace an entry's content, re-embedding only the paragraphs that changed",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "entry_id": {"type": "string"},
+            "content": {"type": "string"},
+            "title": {"type": "string"},
+            "tags": {"type": "array", "items": {"type": "string"}},
+        }), &["collection_id", "entry_id", "content"]),
+        output_schema: || tool_result(json!({
+            "entry": object_schema(json!({
+                "id": {"type": "string"},
+                "collection_id": {"type": "string"},
+                "title": {"type": "string"},
+            }), &["id", "collection_id", "title"]),
+            "chunks": chunk_counts_schema(),
+        }), &["entry", "chunks"]),
+    },
+    ToolSpec {
+        name: "search_knowledge",
+        description: "Search a knowledge collection",
+        mutating: false,
+        input_schema: || object_schema(json!({
+            "query": {"type": "string"},
+            "collection_id": {"type": "string"},
+            "limit": {"type": "integer"},
+            "language": {"type": "string"},
+            "score_threshold": {"type": "number"},
+            "content_format": super::content::content_format_property(),
+            "injection_scoring": {"type": "boolean", "description": "Annotate results with an injection_risk score and the signals behind it"},
+            "embedding": {"type": "array", "items": {"type": "number"}, "description": "Precomputed embedding of the query, used instead of the server's model"},
+            "explain": {"type": "boolean", "description": "Include a timing breakdown of the search, down to the backend's pool, network and deserialize stages"},
+            "strategy": {"type": "string", "enum": ["chunks", "two_stage", "hierarchical"], "description": "two_stage and hierarchical match whole entries by their summaries first; two_stage then scores every chunk of the best entries, hierarchical searches only their chunks"},
+            "entries": {"type": "integer", "minimum": 1, "description": "Entries whose chunks a two_stage or hierarchical search looks at"},
+        }), &["query", "collection_id"]),
+        output_schema: || tool_result(json!({
+            "metric": {"type": ["string", "null"]},
+            "partial": {"type": "boolean"},
+            "reranked": {"type": "boolean"},
+            "explain": {"type": "object"},
+            "retrieval_id": {"type": "string"},
+        }), &["metric", "partial", "reranked", "retrieval_id"]),
+    },
+    ToolSpec {
+        name: "scan_entry",
+        description: "Report entries that contain personally identifiable information",
+        mutating: false,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "entry_id": {"type": "string"},
+            "limit": {"type": "integer"},
+        }), &["collection_id"]),
+        output_schema: || tool_result(json!({}), &[]),
+    },
+    ToolSpec {
+        name: "scan_secrets",
+        description: "Report entries that contain credentials or other secrets",
+        mutating: false,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "entry_id": {"type": "string"},
+            "limit": {"type": "integer"},
+        }), &["collection_id"]),
+        output_schema: || tool_result(json!({}), &[]),
+    },
+    ToolSpec {
+        name: "scan_placeholders",
+        description: "Report entries stored with all-zero or non-finite embeddings, optionally re-embedding them from their content",
+        mutating: false,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "limit": {"type": "integer"},
+            "reembed": {"type": "boolean", "description": "Regenerate the flagged embeddings with the server's embedding provider"},
+        }), &["collection_id"]),
+        output_schema: || tool_result(json!({}), &[]),
+    },
+    ToolSpec {
+        name: "get_context",
+        description: "Pack the best matching entries into a model's token budget",
+        mutating: false,
+        input_schema: || object_schema(json!({
+            "query": {"type": "string"},
+            "collection_id": {"type": "string"},
+            "model": {"type": "string"},
+            "reserve_tokens": {"type": "integer"},
+            "limit": {"type": "integer"},
+            "language": {"type": "string"},
+            "score_threshold": {"type": "number"},
+            "content_format": super::content::content_format_property(),
+            "injection_scoring": {"type": "boolean", "description": "Annotate results with an injection_risk score and the signals behind it"},
+            "embedding": {"type": "array", "items": {"type": "number"}, "description": "Precomputed embedding of the query, used instead of the server's model"},
+            "explain": {"type": "boolean", "description": "Include a timing breakdown of the search, down to the backend's pool, network and deserialize stages"},
+            "strategy": {"type": "string", "enum": ["chunks", "two_stage", "hierarchical"], "description": "two_stage and hierarchical match whole entries by their summaries first; two_stage then scores every chunk of the best entries, hierarchical searches only their chunks"},
+            "entries": {"type": "integer", "minimum": 1, "description": "Entries whose chunks a two_stage or hierarchical search looks at"},
+        }), &["query", "collection_id"]),
+        output_schema: || tool_result(json!({
+            "context": object_schema(json!({
+                "model": {"type": "string"},
+                "context_window": {"type": "integer"},
+                "reserve_tokens": {"type": "integer"},
+                "budget": {"type": "integer"},
+                "tokens_used": {"type": "integer"},
+                "entries": {"type": "array", "items": {"type": "object"}},
+                "omitted": {"type": "integer"},
+                "metric": {"type": ["string", "null"]},
+            }), &["model", "context_window", "reserve_tokens", "budget", "tokens_used", "entries", "omitted", "metric"]),
+            "partial": {"type": "boolean"},
+            "explain": {"type": "object"},
+            "retrieval_id": {"type": "string"},
+        }), &["context", "partial", "retrieval_id"]),
+    },
+    ToolSpec {
+        name: "explore_embedding",
+        description: "Experimental: find the entries nearest to an average of entries or an a - b + c analogy",
+        mutating: false,
+        input_schema: || {
+            let operand = json!({
+                "oneOf": [
+                    {"type": "string", "description": "Entry id"},
+                    object_schema(json!({"entry_id": {"type": "string"}}), &["entry_id"]),
+                    object_schema(json!({"text": {"type": "string"}}), &["text"]),
+                ]
+            });
+            object_schema(json!({
+                "collection_id": {"type": "string"},
+                "operation": {"type": "string", "enum": ["average", "analogy"]},
+                "operands": {"type": "array", "items": operand},
+                "a": operand,
+                "b": operand,
+                "c": operand,
+                "limit": {"type": "integer"},
+                "include_operands": {"type": "boolean"},
+            }), &["collection_id"])
+        },
+        output_schema: || tool_result(json!({"operation": {"type": "string"}}), &["operation"]),
+    },
+    ToolSpec {
+        name: "ingest_conversation",
+        description: "Store a conversation transcript as searchable chunks of turns",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "conversation_id": {"type": "string"},
+            "max_chunk_chars": {"type": "integer"},
+            "messages": {
+                "type": "array",
+                "items": object_schema(json!({
+                    "role": {"type": "string"},
+                    "content": {"type": "string"},
+                    "timestamp": {"type": "string"},
+                }), &["role", "content"]),
+            },
+        }), &["collection_id", "messages"]),
+        output_schema: || tool_result(json!({
+            "conversation": object_schema(json!({
+                "id": {"type": "string"},
+                "collection_id": {"type": "string"},
+                "entries": {"type": "array"},
+            }), &["id", "collection_id", "entries"]),
+        }), &["conversation"]),
+    },
+    ToolSpec {
+        name: "remember",
+        description: "Store a memory for the current session or user",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "content": {"type": "string"},
+            "ttl": {"type": "integer", "description": "Seconds until the memory expires"},
+            "importance": {"type": "number", "minimum": 0, "maximum": 1},
+            "scope": {"type": "string", "enum": ["session", "user"]},
+        }), &["content"]),
+        output_schema: || tool_result(json!({}), &[]),
+    },
+    ToolSpec {
+        name: "recall",
+        description: "Find memories, favouring recent and important ones",
+        mutating: false,
+        input_schema: || object_schema(json!({
+            "query": {"type": "string"},
+            "k": {"type": "integer"},
+            "scope": {"type": "string", "enum": ["session", "user"]},
+        }), &["query"]),
+        output_schema: || tool_result(json!({}), &[]),
+    },
+    ToolSpec {
+        name: "forget",
+        description: "Delete a memory by id, or every memory matching a query",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "id": {"type": "string"},
+            "query": {"type": "string"},
+            "threshold": {"type": "number"},
+            "limit": {"type": "integer"},
+            "scope": {"type": "string", "enum": ["session", "user"]},
+        }), &[]),
+        output_schema: || tool_result(json!({}), &[]),
+    },
+    ToolSpec {
+        name: "count_entries",
+        description: "Count the entries of a collection, optionally only those matching a filter",
+        mutating: false,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "filter": filter_schema(),
+        }), &["collection_id"]),
+        output_schema: || tool_result(json!({"count": {"type": "integer", "minimum": 0}}), &["count"]),
+    },
+    ToolSpec {
+        name: "entry_exists",
+        description: "Check whether an entry exists without fetching it",
+        mutating: false,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "entry_id": {"type": "string"},
+        }), &["collection_id", "entry_id"]),
+        output_schema: || tool_result(json!({"exists": {"type": "boolean"}}), &["exists"]),
+    },
+    ToolSpec {
+        name: "patch_metadata",
+        description: "Apply a JSON merge patch to the metadata of every entry matching a filter",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "filter": filter_schema(),
+            "patch": {"type": "object", "description": "null removes a field; tags takes an array"},
+            "rename_tags": {"type": "object", "additionalProperties": {"type": "string"}},
+        }), &["collection_id", "filter"]),
+        output_schema: || tool_result(json!({
+            "matched": {"type": "integer"},
+            "updated": {"type": "integer"},
+            "dry_run": {"type": "boolean"},
+        }), &["matched", "updated", "dry_run"]),
+    },
+    ToolSpec {
+        name: "update_collection_settings",
+        description: "Set a registered collection's default search parameters and entry TTL; null clears a setting",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "limit": {"type": ["integer", "null"], "minimum": 1},
+            "score_threshold": {"type": ["number", "null"]},
+            "hybrid": {
+                "type": ["object", "null"],
+                "properties": {
+                    "vector": {"type": "number", "minimum": 0},
+                    "keyword": {"type": "number", "minimum": 0},
+                },
+                "required": ["vector", "keyword"],
+            },
+            "rerank": {
+                "type": ["object", "null"],
+                "properties": {
+                    "enabled": {"type": "boolean"},
+                    "candidates": {"type": "integer", "minimum": 1},
+                },
+                "required": ["enabled"],
+            },
+            "ttl_secs": {"type": ["integer", "null"], "minimum": 1, "description": "Lifetime of entries without an expiry of their own"},
+        }), &["collection_id"]),
+        output_schema: || tool_result(json!({
+            "collection_id": {"type": "string"},
+            "settings": {"type": "object"},
+            "ttl_secs": {"type": ["integer", "null"]},
+            "dry_run": {"type": "boolean"},
+        }), &["collection_id", "settings", "ttl_secs", "dry_run"]),
+    },
+    ToolSpec {
+        name: "get_config_history",
+        description: "List recent changes to runtime settings with their old and new values, who made them and when, newest first",
+        mutating: false,
+        input_schema: || object_schema(json!({
+            "setting": {"type": "string", "description": "Only settings at or under this dotted path, e.g. collections.docs"},
+            "actor": {"type": "string"},
+            "since": {"type": "string", "format": "date-time"},
+            "limit": {"type": "integer", "minimum": 0},
+        }), &[]),
+        output_schema: || tool_result(json!({
+            "changes": {"type": "array", "items": {"type": "object"}},
+        }), &["changes"]),
+    },
+    ToolSpec {
+        name: "clone_collection",
+        description: "Copy a collection's entries, optionally a filtered subset or a random sample, into a new collection",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "source_collection": {"type": "string"},
+            "target_collection": {"type": "string"},
+            "filter": filter_schema(),
+            "sample_size": {"type": "integer", "minimum": 1},
+            "seed": {"type": "integer", "minimum": 0, "description": "Makes the sample reproducible"},
+        }), &["source_collection", "target_collection"]),
+        output_schema: || tool_result(json!({
+            "matched": {"type": "integer"},
+            "copied": {"type": "integer"},
+            "dry_run": {"type": "boolean"},
+        }), &["matched", "copied", "dry_run"]),
+    },
+    ToolSpec {
+        name: "extend_ttl",
+        description: "Set an entry to expire a number of seconds from now, extending or shortening its lifetime",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+            "entry_id": {"type": "string"},
+            "ttl": {"type": "integer", "minimum": 1, "description": "Seconds from now until the entry expires"},
+        }), &["collection_id", "entry_id", "ttl"]),
+        output_schema: || tool_result(json!({
+            "entry_id": {"type": "string"},
+            "collection_id": {"type": "string"},
+            "expires_at": {"type": "string"},
+        }), &["entry_id", "collection_id", "expires_at"]),
+    },
+    ToolSpec {
+        name: "get_retrieval",
+        description: "Fetch exactly what an earlier search_knowledge or get_context call returned, by its retrieval_id",
+        mutating: false,
+        input_schema: || object_schema(json!({
+            "retrieval_id": {"type": "string", "description": "The retrieval_id of the earlier result"},
+        }), &["retrieval_id"]),
+        output_schema: || tool_result(json!({
+            "retrieval": object_schema(json!({
+                "retrieval_id": {"type": "string"},
+                "timestamp": {"type": "string"},
+                "tool": {"type": "string"},
+                "collection": {"type": "string"},
+                "query": {"type": "string"},
+                "actor": {"type": "string"},
+                "result": {"type": "object"},
+            }), &["retrieval_id", "timestamp", "tool", "collection", "query", "actor", "result"]),
+        }), &["retrieval"]),
+    },
+    ToolSpec {
+        name: "create_api_key",
+        description: "Create an API key for the REST API with a scope, optional collections and an optional expiry; the key is returned once and only its hash is kept",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "name": {"type": "string", "description": "What the key is for, e.g. the client using it"},
+            "scope": {"type": "string", "enum": ["read", "write", "admin"], "description": "read searches, write also changes entries, admin also manages keys; defaults to write"},
+            "collections": {"type": "array", "items": {"type": "string"}, "minItems": 1, "description": "Only these collections; every collection when omitted"},
+            "expires_at": {"type": "string", "format": "date-time"},
+            "ttl": {"type": "integer", "minimum": 1, "description": "Seconds until the key expires, instead of expires_at"},
+        }), &["name"]),
+        output_schema: || tool_result(json!({
+            "key": {"type": "object"},
+            "api_key": {"type": "string", "description": "The secret; shown only once"},
+        }), &["key", "api_key"]),
+    },
+    ToolSpec {
+        name: "list_api_keys",
+        description: "List the managed API keys, masked, with when they were created and revoked",
+        mutating: false,
+        input_schema: || object_schema(json!({}), &[]),
+        output_schema: || tool_result(json!({
+            "keys": {"type": "array", "items": {"type": "object"}},
+        }), &["keys"]),
+    },
+    ToolSpec {
+        name: "revoke_api_key",
+        description: "Revoke an API key; requests presenting it are refused from then on",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "key_id": {"type": "string", "description": "The id of the key, as listed by list_api_keys"},
+        }), &["key_id"]),
+        output_schema: || tool_result(json!({"key": {"type": "object"}}), &["key"]),
+    },
+    ToolSpec {
+        name: "reindex_collection",
+        description: "Re-embed every entry of a collection in the background with the configured embedding provider; returns a jobs://reindex/<id> resource to read for progress, with ?wait=<secs> to wait for it to move",
+        mutating: true,
+        input_schema: || object_schema(json!({
+            "collection_id": {"type": "string"},
+        }), &["collection_id"]),
+        output_schema: || tool_result(json!({
+            "job": object_schema(json!({
+                "id": {"type": "string"},
+                "kind": {"type": "string"},
+                "uri": {"type": "string"},
+                "target": {"type": "string"},
+                "state": {"enum": ["running", "completed", "failed"]},
+                "done": {"type": "integer"},
+                "total": {"type": ["integer", "null"]},
+            }), &["id", "kind", "uri", "target", "state", "done"]),
+        }), &["job"]),
+    },
+];
+
+/// Tools that manage the server rather than the knowledge in it
+const ADMIN_TOOLS: &[&str] = &["create_api_key", "list_api_keys", "revoke_api_key", "get_config_history"];
+
+/// Arguments naming a collection a tool reads or changes
+const COLLECTION_ARGUMENTS: &[&str] = &["collection_id", "collection", "source_collection", "target_collection"];
+
+/// The scope an API key or token needs to call `tool`, as for the REST
+/// routes: admin to manage the server, write to change stored data and read
+/// for the rest
+pub fn required_scope(tool: &str) -> Scope {
+    if ADMIN_TOOLS.contains(&tool) {
+        Scope::Admin
+    } else if tool_spec(tool).is_some_and(|tool| tool.mutating) {
+        Scope::Write
+    } else {
+        Scope::Read
+    }
+}
+
+/// The collections a call's `arguments` name
+pub fn named_collections(arguments: &Value) -> Vec<&str> {
+    COLLECTION_ARGUMENTS.iter()
+        .filter_map(|argument| arguments.get(argument).and_then(Value::as_str))
+        .collect()
+}
+
+/// Look up a tool by name
+pub fn tool_spec(name: &str) -> Option<&'static ToolSpec> {
+    TOOLS.iter().find(|tool| tool.name == name)
+}
+
+/// Which tools may be listed and called
+#[derive(Debug, Clone, Default)]
+pub struct ToolPolicy {
+    disabled: HashSet<String>,
+}
+
+impl ToolPolicy {
+    pub fn from_config(config: &ToolsConfig) -> Self {
+        Self {
+            disabled: config.disabled.iter().cloned().collect(),
+        }
+    }
+    
+    /// Disable a tool by name
+    pub fn disable(&mut self, name: &str) {
+        self.disabled.insert(name.to_string());
+    }
+    
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.contains(name)
+    }
+    
+    /// Configured names that match no tool, most likely typos
+    pub fn unknown_tools(&self) -> Vec<&str> {
+        let mut unknown: Vec<&str> = self.disabled.iter()
+            .map(|name| name.as_str())
+            // Namespaced gateway tools are only known once downstreams connect
+            .filter(|name| tool_spec(name).is_none() && alias(name).is_none() && !name.contains('.'))
+            .collect();
+        unknown.sort();
+        unknown
+    }
+    
+    /// The enabled tools, in catalog order
+    pub fn enabled_tools(&self) -> impl Iterator<Item = &'static ToolSpec> + '_ {
+        TOOLS.iter().filter(move |tool| self.is_enabled(tool.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_policy_filters_tools() {
+        let config = ToolsConfig {
+            disabled: vec!["forget".to_string(), "delete_everything".to_string()],
+        };
+        let policy = ToolPolicy::from_config(&config);
+        
+        assert!(!policy.is_enabled("forget"));
+        assert!(policy.is_enabled("recall"));
+        assert_eq!(policy.enabled_tools().count(), TOOLS.len() - 1);
+        assert_eq!(policy.unknown_tools(), vec!["delete_everything"]);
+    }
+    
+    #[test]
+    fn test_mutating_tools_accept_dry_run() {
+        let add = tool_spec("add_knowledge_entry").unwrap().to_json();
+        assert_eq!(add["inputSchema"]["properties"]["dry_run"]["type"], "boolean");
+        
+        let search = tool_spec("search_knowledge").unwrap().to_json();
+        assert!(search["inputSchema"]["properties"].get("dry_run").is_none());
+        assert!(search["outputSchema"].get("anyOf").is_none());
+        assert!(add["outputSchema"]["anyOf"].is_array());
+    }
+    
+    #[test]
+    fn test_tool_names_are_unique() {
+        let names: HashSet<&str> = TOOLS.iter().map(|tool| tool.name).collect();
+        assert_eq!(names.len(), TOOLS.len());
+    }
+}
@@ -0,0 +1,136 @@
+//! JSON-RPC over a connection: newline-delimited on a byte stream such as
+//! stdio, or one message per item of any stream, such as WebSocket frames.
+//!
+//! Each connection owns a cancellation token. Every request on it runs with
+//! a child of that token, so when the peer disconnects (or the connection
+//! future is dropped) all of its outstanding work is abandoned.
+
+use futures::{Stream, StreamExt};
+use std::io;
+use std::pin::pin;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+
+use super::ProgmoMcpServer;
+use crate::context::RequestContext;
+
+/// Serve requests read from `reader`, one per line, writing responses to `writer`.
+///
+/// Requests run concurrently and responses are written as they complete.
+/// Returns when `reader` reaches end of input; any requests still running
+/// at that point are cancelled.
+pub async fn serve_connection<R, W>(server: Arc<ProgmoMcpServer>, reader: R, writer: W, ctx: RequestContext) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (responses, mut outgoing) = mpsc::unbounded_channel::<String>();
+    let writer_task = tokio::spawn(async move {
+        let mut writer = writer;
+        while let Some(response) = outgoing.recv().await {
+            writer.write_all(response.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await?;
+        }
+        Ok::<(), io::Error>(())
+    });
+    
+    let lines = futures::stream::try_unfold(BufReader::new(reader).lines(), |mut lines| async move {
+        Ok(lines.next_line().await?.map(|line| (line, lines)))
+    });
+    let served = serve_requests(server, lines, responses, ctx).await;
+    writer_task.abort();
+    served
+}
+
+/// Serve the requests arriving on `requests`, one message per item, sending
+/// each response to `responses` as it completes.
+///
+/// Returns when `requests` ends or fails; any requests still running at that
+/// point are cancelled, as they are if the returned future is dropped.
+pub async fn serve_requests<S>(server: Arc<ProgmoMcpServer>, requests: S, responses: mpsc::UnboundedSender<String>, ctx: RequestContext) -> io::Result<()>
+where
+    S: Stream<Item = io::Result<String>>,
+{
+    let connection = ctx.cancellation.child_token();
+    // Dropping the guard cancels the connection however this function exits
+    let _guard = connection.clone().drop_guard();
+    
+    let mut requests = pin!(requests);
+    while let Some(request) = requests.next().await {
+        let request = request?;
+        if request.trim().is_empty() {
+            continue;
+        }
+        
+        let request_ctx = RequestContext::anonymous()
+            .merge(ctx.clone())
+            .with_cancellation(connection.child_token());
+        let server = server.clone();
+        let responses = responses.clone();
+        tokio::spawn(async move {
+            let response = server.handle_request_with_context(&request, request_ctx).await;
+            if responses.send(response).is_err() {
+                debug!("Connection closed before the response was written");
+            }
+        });
+    }
+    
+    info!("Client disconnected; cancelling outstanding requests");
+    connection.cancel();
+    Ok(())
+}
+
+/// Serve requests on the process's stdin and stdout
+pub async fn serve_stdio(server: Arc<ProgmoMcpServer>) -> io::Result<()> {
+    serve_connection(server, tokio::io::stdin(), tokio::io::stdout(), RequestContext::anonymous()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::{ServerConfig, REQUEST_CANCELLED};
+    use crate::vector_store::InMemoryVectorStore;
+    use serde_json::Value;
+    use tokio_util::sync::CancellationToken;
+    
+    fn server() -> Arc<ProgmoMcpServer> {
+        let config = ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() };
+        Arc::new(ProgmoMcpServer::new(config, Arc::new(InMemoryVectorStore::new())))
+    }
+    
+    #[tokio::test]
+    async fn test_serve_connection_answers_each_line() {
+        let (client, server_side) = tokio::io::duplex(64 * 1024);
+        let (server_read, server_write) = tokio::io::split(server_side);
+        let serving = tokio::spawn(serve_connection(server(), server_read, server_write, RequestContext::anonymous()));
+        
+        let (mut client_read, mut client_write) = tokio::io::split(client);
+        client_write.write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ListTools\"}\n").await.unwrap();
+        
+        let mut reader = BufReader::new(&mut client_read);
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], 1);
+        assert!(response["result"]["tools"].is_array());
+        
+        // Closing our end is a disconnect, which ends the connection
+        client_write.shutdown().await.unwrap();
+        serving.await.unwrap().unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_cancelled_request_is_abandoned() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let ctx = RequestContext::anonymous().with_cancellation(cancellation);
+        
+        let request = r#"{"jsonrpc":"2.0","id":7,"method":"ListTools"}"#;
+        let response: Value = serde_json::from_str(&server().handle_request_with_context(request, ctx).await).unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["error"]["code"], REQUEST_CANCELLED);
+    }
+}
@@ -0,0 +1,155 @@
+//! Extending the lifetime of entries that would otherwise expire
+
+use serde_json::{json, Value};
+
+use super::{entry_id_argument, error_response, invalid_response, is_dry_run, missing_argument, plan_response, store_error_response, ttl_argument, ErrorKind, ProgmoMcpServer};
+use crate::context::RequestContext;
+use crate::vector_store::{expiry_after, is_expired, MetadataFilter, MetadataPatch, EXPIRES_AT_KEY};
+
+impl ProgmoMcpServer {
+    /// Handle an extend_ttl tool call, setting an entry to expire `ttl` seconds from now
+    pub(super) async fn handle_extend_ttl(&self, _ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let collection_id = match arguments.get("collection_id").and_then(|collection_id| collection_id.as_str()) {
+            Some(collection_id) => collection_id,
+            None => return missing_argument(id, "collection_id"),
+        };
+        let entry_id = match entry_id_argument(arguments, "entry_id") {
+            Ok(Some(entry_id)) => entry_id,
+            Ok(None) => return missing_argument(id, "entry_id"),
+            Err(message) => return error_response(id, -32602, message),
+        };
+        let ttl_secs = match ttl_argument(arguments) {
+            Ok(Some(ttl_secs)) => ttl_secs,
+            Ok(None) => return missing_argument(id, "ttl"),
+            Err(message) => return error_response(id, -32602, message),
+        };
+        
+        // An expired entry is already gone for searches, so it cannot be revived
+        let now = self.determinism.now();
+        match self.vector_store.get_document(collection_id, entry_id.as_str()).await {
+            Ok(Some(document)) if !is_expired(&document, self.registry.ttl(collection_id), now) => {},
+            Ok(_) => return invalid_response(id, ErrorKind::EntryNotFound, format!("Invalid params: entry '{}' not found", entry_id.as_str())),
+            Err(e) => return store_error_response(id, &e),
+        }
+        
+        let expires_at = match expiry_after(now, ttl_secs) {
+            Some(expires_at) => expires_at.to_rfc3339(),
+            None => return error_response(id, -32602, format!("Invalid params: ttl {} is too large", ttl_secs)),
+        };
+        if is_dry_run(arguments) {
+            return plan_response(id, "update", collection_id, &[entry_id.into()], 0, 0);
+        }
+        
+        let filter = MetadataFilter { ids: vec![entry_id.as_str().to_string()], ..Default::default() };
+        let patch = MetadataPatch::from_merge_patch(&json!({ EXPIRES_AT_KEY: expires_at })).unwrap();
+        if let Err(e) = self.vector_store.patch_metadata(collection_id, &filter, &patch).await {
+            return store_error_response(id, &e);
+        }
+        
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": format!("Entry {} now expires at {}", entry_id.as_str(), expires_at)
+                    }
+                ],
+                "entry_id": entry_id.as_str(),
+                "collection_id": collection_id,
+                "expires_at": expires_at
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as ChronoDuration, Utc};
+    use crate::mcp::ServerConfig;
+    use crate::text_processing::embedding::MockEmbeddingGenerator;
+    use crate::vector_store::{CollectionInfo, Document, InMemoryVectorStore, VectorStore};
+    use std::sync::Arc;
+    
+    fn call(id: u64, name: &str, arguments: Value) -> String {
+        json!({"jsonrpc": "2.0", "id": id, "method": "CallTool", "params": {"name": name, "arguments": arguments}}).to_string()
+    }
+    
+    async fn response(server: &ProgmoMcpServer, request: String) -> Value {
+        serde_json::from_str(&server.handle_request(&request).await).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_expired_entries_are_hidden_until_extended() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("scratch", 8).await.unwrap();
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store.clone())
+            .with_embedding_provider(Arc::new(MockEmbeddingGenerator::new(8)))
+            .unwrap();
+        server.registry().register(CollectionInfo::new("scratch", 8));
+        
+        let added = response(&server, call(1, "add_knowledge_entry", json!({
+            "collection_id": "scratch", "entry_id": "note", "title": "Note", "content": "standup moved to ten", "ttl": 60
+        }))).await;
+        assert!(added["result"]["entry"]["expires_at"].is_string());
+        let search = call(2, "search_knowledge", json!({"collection_id": "scratch", "query": "standup moved to ten"}));
+        assert!(response(&server, search.clone()).await["result"]["content"][0]["text"].as_str().unwrap().contains("note"));
+        
+        // Lapse the entry: it stays stored but no longer appears in results
+        let mut document = store.get_document("scratch", "note").await.unwrap().unwrap();
+        document.metadata.insert(EXPIRES_AT_KEY.to_string(), (Utc::now() - ChronoDuration::seconds(1)).to_rfc3339());
+        store.insert_document("scratch", document).await.unwrap();
+        assert_eq!(response(&server, search.clone()).await["result"]["content"][0]["text"], "[]");
+        
+        let lapsed = response(&server, call(3, "extend_ttl", json!({"collection_id": "scratch", "entry_id": "note", "ttl": 60}))).await;
+        assert_eq!(lapsed["error"]["code"], -32602);
+        
+        let mut document = store.get_document("scratch", "note").await.unwrap().unwrap();
+        document.metadata.insert(EXPIRES_AT_KEY.to_string(), (Utc::now() + ChronoDuration::seconds(5)).to_rfc3339());
+        store.insert_document("scratch", document).await.unwrap();
+        let extended = response(&server, call(4, "extend_ttl", json!({"collection_id": "scratch", "entry_id": "note", "ttl": 86400}))).await;
+        let expires_at = extended["result"]["expires_at"].as_str().unwrap().to_string();
+        let stored = store.get_document("scratch", "note").await.unwrap().unwrap();
+        assert_eq!(stored.metadata.get(EXPIRES_AT_KEY), Some(&expires_at));
+        
+        let invalid = response(&server, call(5, "extend_ttl", json!({"collection_id": "scratch", "entry_id": "note", "ttl": 0}))).await;
+        assert_eq!(invalid["error"]["code"], -32602);
+        
+        // Lifetimes past the bound are refused rather than overflowing the expiry time
+        for (call_id, name) in [(6, "extend_ttl"), (7, "add_knowledge_entry")] {
+            let too_long = response(&server, call(call_id, name, json!({
+                "collection_id": "scratch", "entry_id": "note", "title": "Note", "content": "standup moved to ten", "ttl": u64::MAX
+            }))).await;
+            assert_eq!(too_long["error"]["code"], -32602, "{}", name);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_collection_ttl_hides_old_entries() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("scratch", 8).await.unwrap();
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store.clone())
+            .with_embedding_provider(Arc::new(MockEmbeddingGenerator::new(8)))
+            .unwrap();
+        server.registry().register(CollectionInfo::new("scratch", 8));
+        
+        let mut document = Document::with_placeholder_embedding("old note".to_string(), 8);
+        document.id = "old".to_string();
+        document.metadata.insert(crate::vector_store::CREATED_AT_KEY.to_string(), (Utc::now() - ChronoDuration::days(2)).to_rfc3339());
+        store.insert_document("scratch", document).await.unwrap();
+        
+        let settings = response(&server, call(1, "update_collection_settings", json!({"collection_id": "scratch", "ttl_secs": 86400}))).await;
+        assert_eq!(settings["result"]["ttl_secs"], 86400);
+        let results = response(&server, call(2, "search_knowledge", json!({"collection_id": "scratch", "query": "old note"}))).await;
+        assert_eq!(results["result"]["content"][0]["text"], "[]");
+        
+        let too_long = response(&server, call(3, "update_collection_settings", json!({"collection_id": "scratch", "ttl_secs": u64::MAX}))).await;
+        assert_eq!(too_long["error"]["code"], -32602);
+        
+        response(&server, call(3, "update_collection_settings", json!({"collection_id": "scratch", "ttl_secs": null}))).await;
+        let results = response(&server, call(4, "search_knowledge", json!({"collection_id": "scratch", "query": "old note"}))).await;
+        assert!(results["result"]["content"][0]["text"].as_str().unwrap().contains("old"));
+    }
+}
@@ -0,0 +1,309 @@
+//! Updating stored entries without re-embedding unchanged chunks
+
+use serde_json::{json, Value};
+use tracing::info;
+
+use super::{document_bytes, entry_id_argument, error_response, invalid_response, is_dry_run, missing_argument, store_error_response, ErrorKind, ProgmoMcpServer, ToolError, DEFAULT_SUMMARY_SENTENCES};
+use crate::config::PiiAction;
+use crate::context::RequestContext;
+use crate::text_processing::pii::{detect_pii, mask_pii, pii_kinds};
+use crate::text_processing::{generate_title, summarize_text, ChunkingStrategy, TextProcessor, TokenizerConfig};
+use crate::vector_store::chunks::{chunk_id, load_chunks, plan_chunk_update, summary_document, summary_id, CHUNK_HASH_KEY, CHUNK_INDEX_KEY, PARENT_ID_KEY};
+use crate::vector_store::{Document, PII_KINDS_KEY, SENSITIVE_KEY, TITLE_GENERATED_KEY};
+
+impl ProgmoMcpServer {
+    /// Handle an update_knowledge_entry tool call.
+    ///
+    /// The new content is split into paragraphs and compared with the stored
+    /// chunks by hash; only changed or inserted chunks are embedded again.
+    pub(super) async fn handle_update_knowledge_entry(&self, ctx: &RequestContext, id: &Value, arguments: &Value) -> String {
+        let collection_id = match arguments.get("collection_id").and_then(|collection_id| collection_id.as_str()) {
+            Some(collection_id) => collection_id,
+            None => return missing_argument(id, "collection_id"),
+        };
+        let entry_id = match entry_id_argument(arguments, "entry_id") {
+            Ok(Some(entry_id)) => entry_id,
+            Ok(None) => return missing_argument(id, "entry_id"),
+            Err(message) => return error_response(id, -32602, message),
+        };
+        let entry_id = entry_id.as_str();
+        let content = match arguments.get("content").and_then(|content| content.as_str()) {
+            Some(content) => content,
+            None => return missing_argument(id, "content"),
+        };
+        
+        let stored = match load_chunks(self.vector_store.as_ref(), collection_id, entry_id).await {
+            Ok(stored) if stored.is_empty() => return invalid_response(id, ErrorKind::EntryNotFound, format!("Invalid params: entry not found: {}", entry_id)),
+            Ok(stored) => stored,
+            Err(e) => return store_error_response(id, &e),
+        };
+        
+        // Carry over the entry's metadata, dropping what is recomputed below
+        let mut metadata = stored[0].metadata.clone();
+        for key in [PARENT_ID_KEY, CHUNK_INDEX_KEY, CHUNK_HASH_KEY, SENSITIVE_KEY, PII_KINDS_KEY] {
+            metadata.remove(key);
+        }
+        
+        let provided_title = arguments.get("title")
+            .and_then(|title| title.as_str())
+            .map(|title| title.trim())
+            .filter(|title| !title.is_empty());
+        let title = match provided_title {
+            Some(title) => {
+                metadata.remove(TITLE_GENERATED_KEY);
+                title.to_string()
+            },
+            None => match stored[0].title().map(|title| title.to_string()).or_else(|| generate_title(content)) {
+                Some(title) => title,
+                None => {
+                    return ToolError::invalid("Invalid params: missing title and no title could be generated from the content".to_string())
+                        .with_kind(ErrorKind::MissingArgument("title".to_string()))
+                        .response(id);
+                },
+            },
+        };
+        
+        let tags = match arguments.get("tags").and_then(|tags| tags.as_array()) {
+            Some(tags) => tags.iter()
+                .filter_map(|tag| tag.as_str())
+                .map(|tag| tag.to_string())
+                .collect(),
+            None => stored[0].tags(),
+        };
+        
+        let masked_content;
+        let content = match self.pii_policy.action_for(collection_id) {
+            Some(action) => {
+                let matches = detect_pii(content);
+                if matches.is_empty() {
+                    content
+                } else {
+                    let kinds: Vec<&str> = pii_kinds(&matches).iter().map(|kind| kind.as_str()).collect();
+                    match action {
+                        PiiAction::Block => return invalid_response(id, ErrorKind::PiiDetected, format!("Invalid params: entry contains PII ({})", kinds.join(", "))),
+                        PiiAction::Mask => {
+                            masked_content = mask_pii(content, &matches);
+                            masked_content.as_str()
+                        },
+                        PiiAction::Allow => content,
+                        PiiAction::Tag => {
+                            metadata.insert(SENSITIVE_KEY.to_string(), "true".to_string());
+                            metadata.insert(PII_KINDS_KEY.to_string(), kinds.join(","));
+                            content
+                        },
+                    }
+                }
+            },
+            None => content,
+        };
+        
+        if let Err(field_errors) = self.registry.validate_entry(collection_id, &title, &tags, &metadata) {
+            return ToolError::invalid(format!("Invalid params: entry does not match the schema of collection '{}'", collection_id))
+                .with_kind(ErrorKind::SchemaValidation)
+                .with_data(json!({
+                    "field_errors": field_errors
+                }))
+                .response(id);
+        }
+        
+        let processor = TextProcessor::new(TokenizerConfig::default(), ChunkingStrategy::Paragraph);
+        let new_chunks: Vec<String> = processor.chunk(content).into_iter().map(|chunk| chunk.content).collect();
+        if new_chunks.is_empty() {
+            return error_response(id, -32602, "Invalid params: content is empty".to_string());
+        }
+        
+        let mut plan = plan_chunk_update(entry_id, &new_chunks, &stored);
+        let (reused, recomputed, removed) = (plan.reused(), plan.recomputed(), plan.removed.len());
+        
+        let mut documents = Vec::with_capacity(plan.chunks.len());
+        for chunk in plan.chunks {
+            let embedding = match chunk.embedding {
+                Some(embedding) => embedding,
+                None => match self.embed(&chunk.content) {
+                    Ok(embedding) => embedding,
+                    Err(e) => return error_response(id, -32603, format!("Internal error: {}", e)),
+                },
+            };
+            if let Err(e) = self.validate_embedding(collection_id, &embedding) {
+                return e.response(id);
+            }
+            if let Err(e) = self.check_stored_embedding(collection_id, &embedding) {
+                return e.response(id);
+            }
+            
+            let mut document = Document {
+                id: chunk_id(entry_id, chunk.index),
+                content: chunk.content,
+                embedding,
+                metadata: metadata.clone(),
+            }
+            .with_title(&title)
+            .with_tags(&tags);
+            document.metadata.insert(PARENT_ID_KEY.to_string(), entry_id.to_string());
+            document.metadata.insert(CHUNK_INDEX_KEY.to_string(), chunk.index.to_string());
+            document.metadata.insert(CHUNK_HASH_KEY.to_string(), chunk.hash);
+            documents.push(document);
+        }
+        
+        let chunk_counts = json!({
+            "total": documents.len(),
+            "reused": reused,
+            "recomputed": recomputed,
+            "removed": removed,
+        });
+        
+        // Entries stored with a summary keep one that matches the new content
+        let stored_summary = match self.vector_store.get_document(collection_id, &summary_id(entry_id)).await {
+            Ok(stored_summary) => stored_summary,
+            Err(e) => return store_error_response(id, &e),
+        };
+        match stored_summary {
+            Some(stored_summary) if documents.len() > 1 => {
+                let sentences = self.summary_sentences.unwrap_or(DEFAULT_SUMMARY_SENTENCES);
+                let summary = summarize_text(content, sentences);
+                let embedding = if summary == stored_summary.content {
+                    stored_summary.embedding
+                } else {
+                    match self.embed(&summary) {
+                        Ok(embedding) => embedding,
+                        Err(e) => return error_response(id, -32603, format!("Internal error: {}", e)),
+                    }
+                };
+                documents.push(summary_document(entry_id, &documents[0], summary, embedding));
+            },
+            // An entry short enough to be one chunk is matched directly
+            Some(stored_summary) => plan.removed.push(stored_summary.id),
+            None => {},
+        }
+        
+        if is_dry_run(arguments) {
+            let ids: Vec<&str> = documents.iter().map(|document| document.id.as_str()).collect();
+            return json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": {
+                    "content": [
+                        {
+                            "type": "text",
+                            "text": format!("Dry run: would update entry {} in {}", entry_id, collection_id)
+                        }
+                    ],
+                    "plan": {
+                        "dry_run": true,
+                        "action": "update",
+                        "collection_id": collection_id,
+                        "ids": ids,
+                        "deleted_ids": plan.removed,
+                        "chunks": chunk_counts,
+                        "bytes": documents.iter().map(document_bytes).sum::<usize>()
+                    }
+                }
+            }).to_string();
+        }
+        
+        for document in documents {
+            if let Err(e) = self.vector_store.insert_document(collection_id, document).await {
+                return store_error_response(id, &e);
+            }
+        }
+        for removed_id in &plan.removed {
+            if let Err(e) = self.vector_store.delete_document(collection_id, removed_id).await {
+                return store_error_response(id, &e);
+            }
+        }
+        
+        info!(
+            request_id = %ctx.request_id,
+            client_id = %ctx.client_label(),
+            collection = %collection_id,
+            entry_id = %entry_id,
+            reused = reused,
+            recomputed = recomputed,
+            removed = removed,
+            "Updated knowledge entry"
+        );
+        
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "content": [
+                    {
+                        "type": "text",
+                        "text": format!("Updated entry {}: {} chunks reused, {} recomputed, {} removed", entry_id, reused, recomputed, removed)
+                    }
+                ],
+                "entry": {
+                    "id": entry_id,
+                    "collection_id": collection_id,
+                    "title": title
+                },
+                "chunks": chunk_counts
+            }
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mcp::{ProgmoMcpServer, ServerConfig};
+    use crate::vector_store::chunks::{chunk_id, CHUNK_INDEX_KEY, PARENT_ID_KEY};
+    use crate::vector_store::{Document, InMemoryVectorStore, VectorStore};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    
+    fn update(content: &str, dry_run: bool) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "id": "1",
+            "method": "CallTool",
+            "params": {"name": "update_knowledge_entry", "arguments": {"collection_id": "docs", "entry_id": "entry-1", "content": content, "dry_run": dry_run}}
+        }).to_string()
+    }
+    
+    #[tokio::test]
+    async fn test_update_reembeds_only_changed_chunks() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("docs", 384).await.unwrap();
+        let document = Document {
+            id: "entry-1".to_string(),
+            content: "First paragraph.".to_string(),
+            embedding: vec![1.0; 384],
+            metadata: Default::default(),
+        }
+        .with_title("Notes");
+        store.insert_document("docs", document).await.unwrap();
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store.clone());
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&update("First paragraph.\n\nSecond paragraph.", false)).await).unwrap();
+        assert_eq!(response["result"]["chunks"]["reused"], 1);
+        assert_eq!(response["result"]["chunks"]["recomputed"], 1);
+        
+        let first = store.get_document("docs", "entry-1").await.unwrap().unwrap();
+        assert_eq!(first.embedding, vec![1.0; 384]);
+        assert_eq!(first.title(), Some("Notes"));
+        let second = store.get_document("docs", &chunk_id("entry-1", 1)).await.unwrap().unwrap();
+        assert_eq!(second.metadata[PARENT_ID_KEY], "entry-1");
+        assert_eq!(second.metadata[CHUNK_INDEX_KEY], "1");
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&update("Second paragraph.", true)).await).unwrap();
+        assert_eq!(response["result"]["plan"]["chunks"]["reused"], 1);
+        assert_eq!(response["result"]["plan"]["chunks"]["removed"], 1);
+        assert!(store.get_document("docs", &chunk_id("entry-1", 1)).await.unwrap().is_some());
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&update("Second paragraph.", false)).await).unwrap();
+        assert_eq!(response["result"]["chunks"]["removed"], 1);
+        assert!(store.get_document("docs", &chunk_id("entry-1", 1)).await.unwrap().is_none());
+        assert_eq!(store.get_document("docs", "entry-1").await.unwrap().unwrap().content, "Second paragraph.");
+    }
+    
+    #[tokio::test]
+    async fn test_update_missing_entry() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("docs", 384).await.unwrap();
+        let server = ProgmoMcpServer::new(ServerConfig { name: "test".to_string(), version: "0.1.0".to_string() }, store);
+        
+        let response: Value = serde_json::from_str(&server.handle_request(&update("Anything.", false)).await).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+    }
+}
@@ -0,0 +1,347 @@
+//! Versioned migrations of p-mo's internal schemas.
+//!
+//! The installed schema version is kept in a small JSON file. On startup the
+//! migrations newer than it run in version order; if one fails, the ones that
+//! already ran are rolled back in reverse and the recorded version is left
+//! where it started.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::{error, info};
+
+use crate::progress::Progress;
+use crate::vector_store::VectorStore;
+
+/// File name of the schema version record
+pub const SCHEMA_VERSION_FILE: &str = "schema_version.json";
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("Failed to read or write the schema version: {0}")]
+    Io(#[from] std::io::Error),
+    
+    #[error("Schema version file is corrupt: {0}")]
+    Corrupt(#[from] serde_json::Error),
+    
+    #[error("Migrations must have distinct, increasing versions; {0} is out of order")]
+    OutOfOrder(u32),
+    
+    #[error("Installed schema version {installed} is newer than this build knows ({latest}); upgrade p-mo")]
+    NewerSchema { installed: u32, latest: u32 },
+    
+    #[error("Migration {version} ({description}) failed: {message}; the run was rolled back")]
+    RolledBack { version: u32, description: String, message: String },
+    
+    #[error("Migration {version} ({description}) failed: {message}; rolling back also failed: {rollback}")]
+    RollbackFailed { version: u32, description: String, message: String, rollback: String },
+}
+
+/// What a migration may change
+#[derive(Clone)]
+pub struct MigrationContext {
+    /// Directory holding p-mo's persistent state
+    pub data_dir: PathBuf,
+    /// The vector store, for migrations that rewrite collections
+    pub vector_store: Option<Arc<dyn VectorStore>>,
+}
+
+/// One step from schema version `version - 1` to `version`
+#[async_trait]
+pub trait Migration: Send + Sync {
+    fn version(&self) -> u32;
+    
+    fn description(&self) -> &str;
+    
+    async fn up(&self, ctx: &MigrationContext) -> Result<(), String>;
+    
+    /// Undo `up`; called when a later migration in the same run fails
+    async fn down(&self, ctx: &MigrationContext) -> Result<(), String>;
+}
+
+/// A migration recorded as applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub description: String,
+    pub applied_at: DateTime<Utc>,
+}
+
+/// The contents of the schema version file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaVersion {
+    pub version: u32,
+    #[serde(default)]
+    pub applied: Vec<AppliedMigration>,
+}
+
+impl SchemaVersion {
+    /// Read the record, treating a missing file as version 0
+    pub fn load(path: &Path) -> Result<Self, MigrationError> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Write the record, replacing the file atomically
+    pub fn save(&self, path: &Path) -> Result<(), MigrationError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+/// A migration that would run, as reported by a dry run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedMigration {
+    pub version: u32,
+    pub description: String,
+}
+
+/// Applies pending migrations and records the installed version
+pub struct Migrator {
+    migrations: Vec<Box<dyn Migration>>,
+    ctx: MigrationContext,
+    version_file: PathBuf,
+    progress: Progress,
+}
+
+impl Migrator {
+    /// Migrate the state under `data_dir` with `migrations`, which must be in version order
+    pub fn new(data_dir: PathBuf, migrations: Vec<Box<dyn Migration>>) -> Result<Self, MigrationError> {
+        let mut previous = 0;
+        for migration in &migrations {
+            if migration.version() <= previous {
+                return Err(MigrationError::OutOfOrder(migration.version()));
+            }
+            previous = migration.version();
+        }
+        
+        Ok(Self {
+            migrations,
+            version_file: data_dir.join(SCHEMA_VERSION_FILE),
+            ctx: MigrationContext { data_dir, vector_store: None },
+            progress: Progress::hidden(),
+        })
+    }
+    
+    /// Let migrations rewrite collections in `store`
+    pub fn with_vector_store(mut self, store: Arc<dyn VectorStore>) -> Self {
+        self.ctx.vector_store = Some(store);
+        self
+    }
+    
+    /// Report each migration as it is applied
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+    
+    /// The newest schema version this build knows
+    pub fn latest_version(&self) -> u32 {
+        self.migrations.last().map(|migration| migration.version()).unwrap_or(0)
+    }
+    
+    /// The installed schema version
+    pub fn installed_version(&self) -> Result<u32, MigrationError> {
+        Ok(SchemaVersion::load(&self.version_file)?.version)
+    }
+    
+    /// The migrations a run would apply, without applying them
+    pub fn plan(&self) -> Result<Vec<PlannedMigration>, MigrationError> {
+        let installed = self.checked_installed_version()?;
+        Ok(self.pending(installed)
+            .map(|migration| PlannedMigration {
+                version: migration.version(),
+                description: migration.description().to_string(),
+            })
+            .collect())
+    }
+    
+    /// Apply every pending migration, returning the ones applied.
+    ///
+    /// On failure the migrations applied by this run are rolled back, newest
+    /// first, and the installed version is restored.
+    pub async fn run(&self) -> Result<Vec<PlannedMigration>, MigrationError> {
+        let installed = self.checked_installed_version()?;
+        let mut record = SchemaVersion::load(&self.version_file)?;
+        let starting = record.clone();
+        let mut applied: Vec<&dyn Migration> = Vec::new();
+        
+        self.progress.set_total(self.pending(installed).count() as u64);
+        for migration in self.pending(installed) {
+            info!(version = migration.version(), description = %migration.description(), "Applying migration");
+            if let Err(message) = migration.up(&self.ctx).await {
+                error!(version = migration.version(), error = %message, "Migration failed; rolling back");
+                return Err(self.roll_back(migration, message, &applied, &starting).await);
+            }
+            
+            applied.push(migration);
+            record.version = migration.version();
+            record.applied.push(AppliedMigration {
+                version: migration.version(),
+                description: migration.description().to_string(),
+                applied_at: Utc::now(),
+            });
+            record.save(&self.version_file)?;
+            self.progress.inc(1);
+        }
+        
+        Ok(applied.iter()
+            .map(|migration| PlannedMigration {
+                version: migration.version(),
+                description: migration.description().to_string(),
+            })
+            .collect())
+    }
+    
+    fn checked_installed_version(&self) -> Result<u32, MigrationError> {
+        let installed = self.installed_version()?;
+        let latest = self.latest_version();
+        if installed > latest {
+            return Err(MigrationError::NewerSchema { installed, latest });
+        }
+        Ok(installed)
+    }
+    
+    fn pending(&self, installed: u32) -> impl Iterator<Item = &dyn Migration> {
+        self.migrations.iter()
+            .map(|migration| migration.as_ref())
+            .filter(move |migration| migration.version() > installed)
+    }
+    
+    async fn roll_back(&self, failed: &dyn Migration, message: String, applied: &[&dyn Migration], starting: &SchemaVersion) -> MigrationError {
+        let mut rollback_errors = Vec::new();
+        for migration in applied.iter().rev() {
+            if let Err(e) = migration.down(&self.ctx).await {
+                rollback_errors.push(format!("{}: {}", migration.version(), e));
+            }
+        }
+        if let Err(e) = starting.save(&self.version_file) {
+            rollback_errors.push(format!("restoring the schema version: {}", e));
+        }
+        
+        if rollback_errors.is_empty() {
+            MigrationError::RolledBack {
+                version: failed.version(),
+                description: failed.description().to_string(),
+                message,
+            }
+        } else {
+            MigrationError::RollbackFailed {
+                version: failed.version(),
+                description: failed.description().to_string(),
+                message,
+                rollback: rollback_errors.join("; "),
+            }
+        }
+    }
+}
+
+/// The migrations shipped with this build, in version order.
+///
+/// None yet: internal state starts at schema version 0, and the first change
+/// to the registry, audit log or preference layouts adds version 1 here.
+pub fn builtin() -> Vec<Box<dyn Migration>> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+    
+    /// Records the steps it runs, failing `up` when asked to
+    struct Step {
+        version: u32,
+        fail: bool,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+    
+    #[async_trait]
+    impl Migration for Step {
+        fn version(&self) -> u32 {
+            self.version
+        }
+        
+        fn description(&self) -> &str {
+            "test step"
+        }
+        
+        async fn up(&self, _ctx: &MigrationContext) -> Result<(), String> {
+            if self.fail {
+                return Err("boom".to_string());
+            }
+            self.log.lock().unwrap().push(format!("up {}", self.version));
+            Ok(())
+        }
+        
+        async fn down(&self, _ctx: &MigrationContext) -> Result<(), String> {
+            self.log.lock().unwrap().push(format!("down {}", self.version));
+            Ok(())
+        }
+    }
+    
+    fn steps(log: &Arc<Mutex<Vec<String>>>, versions: &[(u32, bool)]) -> Vec<Box<dyn Migration>> {
+        versions.iter()
+            .map(|&(version, fail)| Box::new(Step { version, fail, log: log.clone() }) as Box<dyn Migration>)
+            .collect()
+    }
+    
+    #[tokio::test]
+    async fn test_run_applies_pending_in_order() {
+        let dir = TempDir::new().unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let migrator = Migrator::new(dir.path().to_path_buf(), steps(&log, &[(1, false), (2, false)])).unwrap();
+        
+        assert_eq!(migrator.plan().unwrap().len(), 2);
+        assert!(log.lock().unwrap().is_empty());
+        
+        assert_eq!(migrator.run().await.unwrap().len(), 2);
+        assert_eq!(*log.lock().unwrap(), vec!["up 1", "up 2"]);
+        assert_eq!(migrator.installed_version().unwrap(), 2);
+        
+        // Nothing is left to do on the next startup
+        assert!(migrator.plan().unwrap().is_empty());
+        assert!(migrator.run().await.unwrap().is_empty());
+        let record = SchemaVersion::load(&dir.path().join(SCHEMA_VERSION_FILE)).unwrap();
+        assert_eq!(record.applied.len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_failure_rolls_back_the_run() {
+        let dir = TempDir::new().unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let migrator = Migrator::new(dir.path().to_path_buf(), steps(&log, &[(1, false), (2, false), (3, true)])).unwrap();
+        
+        let err = migrator.run().await.unwrap_err();
+        assert!(matches!(err, MigrationError::RolledBack { version: 3, .. }));
+        assert_eq!(*log.lock().unwrap(), vec!["up 1", "up 2", "down 2", "down 1"]);
+        assert_eq!(migrator.installed_version().unwrap(), 0);
+    }
+    
+    #[test]
+    fn test_rejects_bad_orders_and_newer_schemas() {
+        let dir = TempDir::new().unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        assert!(matches!(
+            Migrator::new(dir.path().to_path_buf(), steps(&log, &[(2, false), (1, false)])),
+            Err(MigrationError::OutOfOrder(1))
+        ));
+        
+        SchemaVersion { version: 5, applied: Vec::new() }.save(&dir.path().join(SCHEMA_VERSION_FILE)).unwrap();
+        let migrator = Migrator::new(dir.path().to_path_buf(), builtin()).unwrap();
+        assert!(matches!(migrator.plan(), Err(MigrationError::NewerSchema { installed: 5, latest: 0 })));
+    }
+}
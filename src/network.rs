@@ -0,0 +1,185 @@
+//! Outbound HTTP clients built from the `[network]` config.
+//!
+//! Every client that talks to a remote service is built here so that a
+//! proxy or private CA configured once applies everywhere.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::config::NetworkConfig;
+
+#[derive(Debug, Error)]
+pub enum NetworkError {
+    #[error("Invalid proxy URL {url}: {message}")]
+    Proxy { url: String, message: String },
+    
+    #[error("Failed to read certificate {path}: {message}")]
+    Certificate { path: PathBuf, message: String },
+    
+    #[error("Failed to build HTTP client: {0}")]
+    Client(String),
+    
+    #[error("{setting} cannot apply to Qdrant at {url}: {reason}")]
+    Qdrant { url: String, setting: &'static str, reason: &'static str },
+}
+
+/// Whether requests to `url` skip the proxy because of `no_proxy`
+pub fn bypasses_proxy(config: &NetworkConfig, url: &str) -> bool {
+    let Some(host) = reqwest::Url::parse(url).ok().and_then(|url| url.host_str().map(|host| host.to_ascii_lowercase())) else {
+        return false;
+    };
+    
+    config.no_proxy.iter()
+        .map(|entry| entry.trim().trim_start_matches('.').to_ascii_lowercase())
+        .any(|entry| entry == "*" || host == entry || host.ends_with(&format!(".{}", entry)))
+}
+
+fn proxy(config: &NetworkConfig) -> Result<Option<reqwest::Proxy>, NetworkError> {
+    let Some(url) = &config.proxy else {
+        return Ok(None);
+    };
+    
+    let proxy = reqwest::Proxy::all(url)
+        .map_err(|e| NetworkError::Proxy { url: url.clone(), message: e.to_string() })?;
+    Ok(Some(proxy.no_proxy(reqwest::NoProxy::from_string(&config.no_proxy.join(",")))))
+}
+
+/// Read every certificate in a PEM file, which may hold a bundle
+fn read_certificates(path: &Path) -> Result<Vec<reqwest::Certificate>, NetworkError> {
+    let certificate_error = |message: String| NetworkError::Certificate { path: path.to_path_buf(), message };
+    let pem = fs::read_to_string(path).map_err(|e| certificate_error(e.to_string()))?;
+    
+    const END: &str = "-----END CERTIFICATE-----";
+    let certificates = pem.split_inclusive(END)
+        .filter(|block| block.contains(END))
+        .map(|block| reqwest::Certificate::from_pem(block.trim().as_bytes()).map_err(|e| certificate_error(e.to_string())))
+        .collect::<Result<Vec<_>, _>>()?;
+    
+    if certificates.is_empty() {
+        return Err(certificate_error("no PEM certificates found".to_string()));
+    }
+    Ok(certificates)
+}
+
+fn certificates(config: &NetworkConfig) -> Result<Vec<reqwest::Certificate>, NetworkError> {
+    let mut all = Vec::new();
+    for path in &config.ca_certificates {
+        all.extend(read_certificates(path)?);
+    }
+    Ok(all)
+}
+
+/// An async client using the configured proxy and extra root certificates
+pub fn http_client(config: &NetworkConfig) -> Result<reqwest::Client, NetworkError> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = proxy(config)? {
+        builder = builder.proxy(proxy);
+    }
+    for certificate in certificates(config)? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    builder.build().map_err(|e| NetworkError::Client(e.to_string()))
+}
+
+/// A blocking client using the configured proxy and extra root certificates.
+///
+/// The client is built on a thread of its own, since building one starts a
+/// runtime that panics on an async runtime's thread; it may be dropped anywhere.
+pub fn blocking_http_client(config: &NetworkConfig) -> Result<reqwest::blocking::Client, NetworkError> {
+    let mut builder = reqwest::blocking::Client::builder();
+    if let Some(proxy) = proxy(config)? {
+        builder = builder.proxy(proxy);
+    }
+    for certificate in certificates(config)? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    std::thread::scope(|scope| scope.spawn(|| builder.build()).join())
+        .map_err(|_| NetworkError::Client("building the client panicked".to_string()))?
+        .map_err(|e| NetworkError::Client(e.to_string()))
+}
+
+/// The settings that would be expected to reach Qdrant at `qdrant_url` but
+/// cannot, with why.
+///
+/// Qdrant is reached over gRPC, which does not go through HTTP proxies and
+/// only trusts the system roots.
+pub fn unsupported_for_qdrant(config: &NetworkConfig, qdrant_url: &str) -> Vec<(&'static str, &'static str)> {
+    let mut unsupported = Vec::new();
+    if config.proxy.is_some() && !bypasses_proxy(config, qdrant_url) {
+        unsupported.push(("network.proxy", "Qdrant is reached directly, not through the proxy; add its host to network.no_proxy"));
+    }
+    if !config.ca_certificates.is_empty() && qdrant_url.starts_with("https://") {
+        unsupported.push(("network.ca_certificates", "Qdrant only trusts the system roots; add the CA to them or to SSL_CERT_FILE"));
+    }
+    unsupported
+}
+
+/// Refuse to reach Qdrant at `qdrant_url` while settings meant for it would be ignored
+pub fn check_qdrant(config: &NetworkConfig, qdrant_url: &str) -> Result<(), NetworkError> {
+    match unsupported_for_qdrant(config, qdrant_url).first() {
+        Some((setting, reason)) => Err(NetworkError::Qdrant { url: qdrant_url.to_string(), setting, reason }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn network(no_proxy: &[&str]) -> NetworkConfig {
+        NetworkConfig {
+            proxy: Some("http://proxy.corp.example:3128".to_string()),
+            no_proxy: no_proxy.iter().map(|host| host.to_string()).collect(),
+            ca_certificates: Vec::new(),
+        }
+    }
+    
+    #[test]
+    fn test_bypasses_proxy() {
+        let config = network(&["localhost", ".internal.example"]);
+        assert!(bypasses_proxy(&config, "http://localhost:6333"));
+        assert!(bypasses_proxy(&config, "https://qdrant.internal.example"));
+        assert!(bypasses_proxy(&config, "https://internal.example/feed"));
+        assert!(!bypasses_proxy(&config, "https://notinternal.example"));
+        assert!(!bypasses_proxy(&config, "https://api.example.com"));
+        assert!(bypasses_proxy(&network(&["*"]), "https://api.example.com"));
+    }
+    
+    #[test]
+    fn test_http_client_with_proxy() {
+        assert!(http_client(&network(&["localhost"])).is_ok());
+        assert!(http_client(&NetworkConfig::default()).is_ok());
+        
+        let invalid = NetworkConfig {
+            proxy: Some("not a url".to_string()),
+            ..NetworkConfig::default()
+        };
+        assert!(matches!(http_client(&invalid), Err(NetworkError::Proxy { .. })));
+    }
+    
+    #[test]
+    fn test_qdrant_refuses_settings_it_ignores() {
+        assert!(check_qdrant(&network(&["localhost"]), "http://localhost:6334").is_ok());
+        assert!(matches!(
+            check_qdrant(&network(&[]), "http://qdrant.example:6334"),
+            Err(NetworkError::Qdrant { setting: "network.proxy", .. })
+        ));
+        
+        let config = NetworkConfig {
+            ca_certificates: vec![PathBuf::from("/etc/p-mo/ca.pem")],
+            ..NetworkConfig::default()
+        };
+        assert!(check_qdrant(&config, "http://qdrant.example:6334").is_ok());
+        assert_eq!(unsupported_for_qdrant(&config, "https://qdrant.example:6334")[0].0, "network.ca_certificates");
+    }
+    
+    #[test]
+    fn test_missing_certificate_is_an_error() {
+        let config = NetworkConfig {
+            ca_certificates: vec![PathBuf::from("/nonexistent/ca.pem")],
+            ..NetworkConfig::default()
+        };
+        assert!(matches!(http_client(&config), Err(NetworkError::Certificate { .. })));
+    }
+}
@@ -0,0 +1,56 @@
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::{runtime, trace as sdktrace, Resource};
+use std::collections::HashMap;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+
+use super::{OtelConfig, OtelError, OtelGuard, TraceParent};
+use crate::logging::daemon;
+
+pub(super) fn install(config: &OtelConfig, endpoint: &str) -> Result<OtelGuard, OtelError> {
+    // The batch exporters need a Tokio runtime; the CLI starts outside of one
+    let runtime = match tokio::runtime::Handle::try_current() {
+        Ok(_) => None,
+        Err(_) => Some(tokio::runtime::Runtime::new().map_err(|e| OtelError::InitError(e.to_string()))?),
+    };
+    let _enter = runtime.as_ref().map(|rt| rt.enter());
+    
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    
+    let resource = Resource::new(vec![KeyValue::new("service.name", config.service_name.clone())]);
+    
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+        .with_trace_config(sdktrace::config().with_resource(resource.clone()))
+        .install_batch(runtime::Tokio)
+        .map_err(|e| OtelError::InitError(e.to_string()))?;
+    
+    let meter_provider = opentelemetry_otlp::new_pipeline()
+        .metrics(runtime::Tokio)
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+        .with_resource(resource)
+        .build()
+        .map_err(|e| OtelError::InitError(e.to_string()))?;
+    global::set_meter_provider(meter_provider);
+    
+    // One subscriber exports spans and logs, to stdout or the daemon's log file
+    let subscriber = tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+    daemon::try_init_global(subscriber, config.json_logs)
+        .map_err(|e| OtelError::InitError(e.to_string()))?;
+    
+    drop(_enter);
+    Ok(OtelGuard { _runtime: runtime })
+}
+
+pub(super) fn set_parent(span: &Span, traceparent: &TraceParent) {
+    let mut carrier = HashMap::new();
+    carrier.insert("traceparent".to_string(), traceparent.to_string());
+    
+    let context = TraceContextPropagator::new().extract(&carrier);
+    span.set_parent(context);
+}
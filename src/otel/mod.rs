@@ -0,0 +1,133 @@
+//! Tracing setup and trace context propagation.
+//!
+//! With the `otel` feature enabled and `OTEL_EXPORTER_OTLP_ENDPOINT` set,
+//! spans and metrics are exported over OTLP so p-mo's work shows up in the
+//! calling agent's traces.
+
+mod pure;
+#[cfg(feature = "otel")]
+mod export;
+
+pub use pure::*;
+
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use thiserror::Error;
+use tracing::{info_span, Instrument, Span};
+
+use crate::logging::daemon;
+
+#[derive(Debug, Error)]
+pub enum OtelError {
+    #[error("Failed to initialize OpenTelemetry: {0}")]
+    InitError(String),
+}
+
+/// OTLP export settings, read from the standard OpenTelemetry environment variables
+#[derive(Debug, Clone)]
+pub struct OtelConfig {
+    /// The OTLP collector endpoint; export is disabled when unset
+    pub endpoint: Option<String>,
+    /// The service name reported with every span
+    pub service_name: String,
+    /// Log JSON lines instead of text, as container runtimes collect them
+    pub json_logs: bool,
+}
+
+impl Default for OtelConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            service_name: "p-mo".to_string(),
+            json_logs: false,
+        }
+    }
+}
+
+impl OtelConfig {
+    /// Read `OTEL_EXPORTER_OTLP_ENDPOINT` and `OTEL_SERVICE_NAME`
+    pub fn from_env() -> Self {
+        Self {
+            endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().filter(|e| !e.is_empty()),
+            service_name: std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "p-mo".to_string()),
+            json_logs: false,
+        }
+    }
+
+    /// Log JSON lines to stdout instead of text
+    pub fn with_json_logs(mut self, json_logs: bool) -> Self {
+        self.json_logs = json_logs;
+        self
+    }
+    
+    pub fn enabled(&self) -> bool {
+        self.endpoint.is_some()
+    }
+}
+
+/// Keeps exporters alive; dropping it flushes pending spans
+pub struct OtelGuard {
+    #[cfg(feature = "otel")]
+    _runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl Drop for OtelGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        opentelemetry::global::shutdown_tracer_provider();
+    }
+}
+
+/// Install the global tracing subscriber, adding OTLP export when configured
+#[cfg(feature = "otel")]
+pub fn init_tracing(config: &OtelConfig) -> Result<OtelGuard, OtelError> {
+    match &config.endpoint {
+        Some(endpoint) => export::install(config, endpoint),
+        None => {
+            init_fmt(config)?;
+            Ok(OtelGuard { _runtime: None })
+        }
+    }
+}
+
+/// Install the global tracing subscriber, adding OTLP export when configured
+#[cfg(not(feature = "otel"))]
+pub fn init_tracing(config: &OtelConfig) -> Result<OtelGuard, OtelError> {
+    init_fmt(config)?;
+    if config.enabled() {
+        tracing::warn!("OTEL_EXPORTER_OTLP_ENDPOINT is set but p-mo was built without the otel feature");
+    }
+    Ok(OtelGuard {})
+}
+
+/// Install the plain subscriber logging to stdout, as text or JSON lines, or
+/// to the daemon's log file once it has one
+fn init_fmt(config: &OtelConfig) -> Result<(), OtelError> {
+    daemon::try_init_global(tracing_subscriber::registry(), config.json_logs)
+        .map_err(|e| OtelError::InitError(e.to_string()))
+}
+
+/// Make `span` a child of the caller's remote span
+pub fn attach_remote_parent(span: &Span, traceparent: &TraceParent) {
+    #[cfg(feature = "otel")]
+    export::set_parent(span, traceparent);
+    
+    #[cfg(not(feature = "otel"))]
+    span.in_scope(|| tracing::debug!(traceparent = %traceparent, "Received remote trace parent"));
+}
+
+/// Axum middleware that opens a span per request, continuing any `traceparent` header
+pub async fn trace_http_request<B>(request: Request<B>, next: Next<B>) -> Response {
+    let span = info_span!("http.request", method = %request.method(), path = %request.uri().path());
+    
+    let traceparent = request.headers()
+        .get("traceparent")
+        .and_then(|value| value.to_str().ok())
+        .and_then(TraceParent::parse);
+    if let Some(traceparent) = &traceparent {
+        attach_remote_parent(&span, traceparent);
+    }
+    
+    next.run(request).instrument(span).await
+}
@@ -0,0 +1,111 @@
+use serde_json::Value;
+use std::fmt;
+
+/// A parsed W3C `traceparent` value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceParent {
+    pub version: u8,
+    pub trace_id: String,
+    pub parent_id: String,
+    pub flags: u8,
+}
+
+impl TraceParent {
+    /// Parse a `traceparent` header value such as
+    /// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`
+    pub fn parse(value: &str) -> Option<Self> {
+        let parts: Vec<&str> = value.trim().split('-').collect();
+        if parts.len() < 4 {
+            return None;
+        }
+        
+        let version = parse_hex_byte(parts[0])?;
+        // Version 0xff is forbidden, and version 00 must have exactly four fields
+        if version == 0xff || (version == 0 && parts.len() != 4) {
+            return None;
+        }
+        
+        let trace_id = parts[1];
+        let parent_id = parts[2];
+        if !is_hex_id(trace_id, 32) || !is_hex_id(parent_id, 16) {
+            return None;
+        }
+        
+        Some(Self {
+            version,
+            trace_id: trace_id.to_string(),
+            parent_id: parent_id.to_string(),
+            flags: parse_hex_byte(parts[3])?,
+        })
+    }
+    
+    /// Whether the caller sampled this trace
+    pub fn is_sampled(&self) -> bool {
+        self.flags & 0x01 == 0x01
+    }
+}
+
+impl fmt::Display for TraceParent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}-{}-{}-{:02x}", self.version, self.trace_id, self.parent_id, self.flags)
+    }
+}
+
+/// Extract a trace parent from MCP request metadata (`params._meta.traceparent`)
+pub fn traceparent_from_mcp_request(request: &Value) -> Option<TraceParent> {
+    request.get("params")
+        .and_then(|params| params.get("_meta"))
+        .and_then(|meta| meta.get("traceparent"))
+        .and_then(|value| value.as_str())
+        .and_then(TraceParent::parse)
+}
+
+fn parse_hex_byte(value: &str) -> Option<u8> {
+    if value.len() != 2 {
+        return None;
+    }
+    u8::from_str_radix(value, 16).ok()
+}
+
+fn is_hex_id(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        && value.chars().any(|c| c != '0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    const VALID: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+    
+    #[test]
+    fn test_parse_valid_traceparent() {
+        let parent = TraceParent::parse(VALID).unwrap();
+        assert_eq!(parent.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(parent.parent_id, "00f067aa0ba902b7");
+        assert!(parent.is_sampled());
+        assert_eq!(parent.to_string(), VALID);
+    }
+    
+    #[test]
+    fn test_parse_invalid_traceparent() {
+        assert!(TraceParent::parse("").is_none());
+        assert!(TraceParent::parse("ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").is_none());
+        assert!(TraceParent::parse("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none());
+        assert!(TraceParent::parse("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01").is_none());
+        assert!(TraceParent::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra").is_none());
+    }
+    
+    #[test]
+    fn test_traceparent_from_mcp_request() {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "method": "CallTool",
+            "params": {"_meta": {"traceparent": VALID}}
+        });
+        assert!(traceparent_from_mcp_request(&request).is_some());
+        assert!(traceparent_from_mcp_request(&json!({"params": {}})).is_none());
+    }
+}
@@ -0,0 +1,257 @@
+//! Progress of long-running operations.
+//!
+//! Operations report through a [`Progress`] handed to them by the CLI, which
+//! picks the display once: a bar on an interactive terminal, periodic JSON
+//! lines in `--output json` mode, or nothing. Progress goes to stderr so the
+//! command's result on stdout stays parseable. Operations the server runs in
+//! the background report to their [`JobHandle`] instead, for clients to poll.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::jobs::JobHandle;
+
+/// How often a JSON progress line is written at most
+pub const JSON_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How the CLI presents progress and results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputMode {
+    /// Human-readable output with progress bars on a terminal
+    #[default]
+    Text,
+    /// Machine-readable progress lines
+    Json,
+}
+
+/// One JSON progress line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProgressEvent<'a> {
+    pub task: &'a str,
+    pub done: u64,
+    /// `None` until the operation knows how much work there is
+    pub total: Option<u64>,
+    pub elapsed_ms: u64,
+    pub finished: bool,
+}
+
+enum Display {
+    Bar(ProgressBar),
+    Json {
+        writer: Mutex<Box<dyn Write + Send>>,
+        interval: Duration,
+        last: Mutex<Option<Instant>>,
+    },
+    Job(JobHandle),
+}
+
+struct Inner {
+    task: String,
+    done: AtomicU64,
+    /// 0 while unknown
+    total: AtomicU64,
+    started: Instant,
+    display: Display,
+}
+
+/// A handle for reporting progress; clones report to the same display
+#[derive(Clone, Default)]
+pub struct Progress {
+    inner: Option<Arc<Inner>>,
+}
+
+impl Progress {
+    /// Progress for `task` displayed the way `mode` asks for
+    pub fn new(mode: OutputMode, task: &str) -> Self {
+        match mode {
+            OutputMode::Text if std::io::stderr().is_terminal() => Self::bar(task),
+            OutputMode::Text => Self::hidden(),
+            OutputMode::Json => Self::json(Box::new(std::io::stderr()), task, JSON_PROGRESS_INTERVAL),
+        }
+    }
+    
+    /// Progress that is not displayed
+    pub fn hidden() -> Self {
+        Self { inner: None }
+    }
+    
+    fn bar(task: &str) -> Self {
+        let bar = ProgressBar::new_spinner();
+        bar.set_message(task.to_string());
+        bar.enable_steady_tick(Duration::from_millis(120));
+        Self::with_display(task, Display::Bar(bar))
+    }
+    
+    /// Write JSON progress lines to `writer`, at most one per `interval` before the last
+    pub fn json(writer: Box<dyn Write + Send>, task: &str, interval: Duration) -> Self {
+        Self::with_display(task, Display::Json {
+            writer: Mutex::new(writer),
+            interval,
+            last: Mutex::new(None),
+        })
+    }
+    
+    /// Report to a server-side job, whose resource clients poll
+    pub fn job(job: JobHandle, task: &str) -> Self {
+        Self::with_display(task, Display::Job(job))
+    }
+    
+    fn with_display(task: &str, display: Display) -> Self {
+        Self {
+            inner: Some(Arc::new(Inner {
+                task: task.to_string(),
+                done: AtomicU64::new(0),
+                total: AtomicU64::new(0),
+                started: Instant::now(),
+                display,
+            })),
+        }
+    }
+    
+    /// Set how many units of work the operation has
+    pub fn set_total(&self, total: u64) {
+        let Some(inner) = &self.inner else { return };
+        inner.total.store(total, Ordering::Relaxed);
+        match &inner.display {
+            Display::Bar(bar) => {
+                bar.set_length(total);
+                bar.set_style(
+                    ProgressStyle::with_template("{msg} [{bar:40}] {pos}/{len} ({eta})")
+                        .unwrap_or_else(|_| ProgressStyle::default_bar())
+                        .progress_chars("=> "),
+                );
+            },
+            Display::Job(job) => job.set_total(total),
+            Display::Json { .. } => {},
+        }
+        inner.emit(false);
+    }
+    
+    /// Record `delta` more units of work done
+    pub fn inc(&self, delta: u64) {
+        let Some(inner) = &self.inner else { return };
+        inner.done.fetch_add(delta, Ordering::Relaxed);
+        match &inner.display {
+            Display::Bar(bar) => bar.inc(delta),
+            Display::Job(job) => job.inc(delta),
+            Display::Json { .. } => {},
+        }
+        inner.emit(false);
+    }
+    
+    /// Units of work done so far
+    pub fn done(&self) -> u64 {
+        self.inner.as_ref().map(|inner| inner.done.load(Ordering::Relaxed)).unwrap_or(0)
+    }
+    
+    /// Mark the operation complete, clearing the bar or writing a final line;
+    /// a job is completed by whoever started it, with its result
+    pub fn finish(&self) {
+        let Some(inner) = &self.inner else { return };
+        match &inner.display {
+            Display::Bar(bar) => bar.finish_and_clear(),
+            Display::Json { .. } => inner.emit(true),
+            Display::Job(_) => {},
+        }
+    }
+}
+
+impl Inner {
+    fn emit(&self, finished: bool) {
+        let Display::Json { writer, interval, last } = &self.display else { return };
+        
+        let now = Instant::now();
+        {
+            let mut last = last.lock().unwrap();
+            if !finished && last.is_some_and(|last| now.duration_since(last) < *interval) {
+                return;
+            }
+            *last = Some(now);
+        }
+        
+        let total = self.total.load(Ordering::Relaxed);
+        let event = ProgressEvent {
+            task: &self.task,
+            done: self.done.load(Ordering::Relaxed),
+            total: (total > 0).then_some(total),
+            elapsed_ms: now.duration_since(self.started).as_millis() as u64,
+            finished,
+        };
+        if let Ok(line) = serde_json::to_string(&event) {
+            let _ = writeln!(writer.lock().unwrap(), "{}", line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// A writer tests can read back
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+    
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    
+    impl Captured {
+        fn lines(&self) -> Vec<serde_json::Value> {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+    
+    #[test]
+    fn test_json_lines_are_throttled() {
+        let captured = Captured::default();
+        let progress = Progress::json(Box::new(captured.clone()), "reencrypt", Duration::from_secs(3600));
+        progress.set_total(3);
+        progress.inc(1);
+        progress.inc(2);
+        progress.finish();
+        
+        let lines = captured.lines();
+        // The first report, then the final one; the ones between fall inside the interval
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["task"], "reencrypt");
+        assert_eq!(lines[0]["total"], 3);
+        assert_eq!(lines[1]["done"], 3);
+        assert_eq!(lines[1]["finished"], true);
+    }
+    
+    #[test]
+    fn test_json_total_unknown() {
+        let captured = Captured::default();
+        let progress = Progress::json(Box::new(captured.clone()), "sync", Duration::ZERO);
+        progress.inc(1);
+        let clone = progress.clone();
+        clone.inc(1);
+        
+        let lines = captured.lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0]["total"].is_null());
+        assert_eq!(progress.done(), 2);
+    }
+    
+    #[test]
+    fn test_hidden_is_a_no_op() {
+        let progress = Progress::hidden();
+        progress.set_total(10);
+        progress.inc(4);
+        progress.finish();
+        assert_eq!(progress.done(), 0);
+    }
+}
@@ -0,0 +1,336 @@
+//! End-to-end self-test of a deployment.
+//!
+//! `p-mo selftest` runs what an agent relies on against the configured
+//! backend and embedding provider: it creates a temporary collection, stores
+//! a few built-in entries, checks that searches rank them as expected,
+//! updates, filters and deletes entries, and removes the collection again.
+//! Every step is one row of the report, so a broken deployment shows which
+//! part of the pipeline failed.
+
+use serde::Serialize;
+use std::time::Instant;
+
+use crate::text_processing::{EmbeddingError, EmbeddingProvider};
+use crate::vector_store::{Document, MetadataFilter, SearchQuery, VectorStore};
+
+/// Start of the names of the temporary collections the self-test creates
+pub const SELFTEST_COLLECTION_PREFIX: &str = "p-mo-selftest-";
+
+/// Metadata field the built-in entries are filtered on
+const TOPIC_KEY: &str = "topic";
+
+/// A built-in entry
+struct SampleEntry {
+    id: &'static str,
+    title: &'static str,
+    topic: &'static str,
+    tags: &'static [&'static str],
+    content: &'static str,
+}
+
+const ENTRIES: &[SampleEntry] = &[
+    SampleEntry {
+        id: "selftest-backups",
+        title: "Nightly backups",
+        topic: "operations",
+        tags: &["storage"],
+        content: "Database backups run every night at two o'clock and are copied to cold storage for thirty days.",
+    },
+    SampleEntry {
+        id: "selftest-deploys",
+        title: "Deploying a release",
+        topic: "operations",
+        tags: &["release"],
+        content: "To deploy a release, tag the commit, wait for the pipeline to build the image, then roll it out region by region.",
+    },
+    SampleEntry {
+        id: "selftest-passwords",
+        title: "Password resets",
+        topic: "accounts",
+        tags: &["security"],
+        content: "Users reset a forgotten password from the sign-in page; the reset link in the email expires after one hour.",
+    },
+    SampleEntry {
+        id: "selftest-keys",
+        title: "API key rotation",
+        topic: "accounts",
+        tags: &["security"],
+        content: "API keys are rotated every ninety days by issuing a new key, updating the clients and revoking the old key.",
+    },
+];
+
+/// Searches and the entry each must rank first
+const RANKINGS: &[(&str, &str)] = &[
+    ("when do database backups run", "selftest-backups"),
+    ("how do I roll out a release", "selftest-deploys"),
+    ("reset a forgotten password", "selftest-passwords"),
+    ("rotate API keys", "selftest-keys"),
+];
+
+/// How a check went
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Pass,
+    Fail,
+    /// Not run because a step it needs failed
+    Skip,
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Fail => "FAIL",
+            Outcome::Skip => "skip",
+        }
+    }
+}
+
+/// One row of the report
+#[derive(Debug, Clone, Serialize)]
+pub struct SelftestCheck {
+    pub name: String,
+    pub outcome: Outcome,
+    pub detail: String,
+    pub millis: u64,
+}
+
+/// The outcome of every check of a self-test run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SelftestReport {
+    /// The temporary collection the run used
+    pub collection: String,
+    /// What embedded the entries and queries
+    pub embedder: String,
+    pub checks: Vec<SelftestCheck>,
+}
+
+impl SelftestReport {
+    /// Whether no check failed
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.outcome != Outcome::Fail)
+    }
+    
+    /// The checks as an aligned pass/fail matrix with a summary line
+    pub fn render_text(&self) -> String {
+        let width = self.checks.iter().map(|check| check.name.len()).max().unwrap_or(0).max("check".len());
+        let mut out = format!("Self-test of collection {} embedded with {}\n\n", self.collection, self.embedder);
+        out.push_str(&format!("{:<width$}  result  time     detail\n", "check", width = width));
+        for check in &self.checks {
+            out.push_str(&format!(
+                "{:<width$}  {:<6}  {:>5}ms  {}\n",
+                check.name, check.outcome.as_str(), check.millis, check.detail, width = width
+            ));
+        }
+        let count = |outcome: Outcome| self.checks.iter().filter(|check| check.outcome == outcome).count();
+        out.push_str(&format!(
+            "\n{} passed, {} failed, {} skipped\n",
+            count(Outcome::Pass), count(Outcome::Fail), count(Outcome::Skip)
+        ));
+        out
+    }
+    
+    /// Run `check` and record it, or record it skipped when `ready` is false;
+    /// returns whether it passed
+    async fn run<F>(&mut self, name: &str, ready: bool, check: F) -> bool
+    where
+        F: std::future::Future<Output = Result<String, String>>,
+    {
+        if !ready {
+            self.checks.push(SelftestCheck { name: name.to_string(), outcome: Outcome::Skip, detail: String::new(), millis: 0 });
+            return false;
+        }
+        let started = Instant::now();
+        let (outcome, detail) = match check.await {
+            Ok(detail) => (Outcome::Pass, detail),
+            Err(detail) => (Outcome::Fail, detail),
+        };
+        let millis = started.elapsed().as_millis() as u64;
+        self.checks.push(SelftestCheck { name: name.to_string(), outcome, detail, millis });
+        outcome == Outcome::Pass
+    }
+}
+
+/// Embeds text as counts of its words hashed into buckets.
+///
+/// Stands in for a model when none is configured, so the backend can still
+/// be checked; rankings then follow shared words rather than meaning.
+pub struct WordHashEmbedder {
+    dimension: usize,
+}
+
+impl WordHashEmbedder {
+    pub fn new(dimension: usize) -> Self {
+        Self { dimension: dimension.max(1) }
+    }
+}
+
+impl EmbeddingProvider for WordHashEmbedder {
+    fn generate_embedding(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        let mut embedding = vec![0.0; self.dimension];
+        for word in text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()) {
+            let word = word.to_lowercase();
+            let bucket = word.bytes().fold(7usize, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as usize)) % self.dimension;
+            embedding[bucket] += 1.0;
+        }
+        Ok(embedding)
+    }
+    
+    fn generate_embeddings(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        texts.iter().map(|text| self.generate_embedding(text)).collect()
+    }
+    
+    fn embedding_dim(&self) -> usize {
+        self.dimension
+    }
+}
+
+fn sample_document(entry: &SampleEntry, content: &str, embedder: &dyn EmbeddingProvider) -> Result<Document, String> {
+    let embedding = embedder.generate_embedding(content).map_err(|e| format!("embedding {}: {}", entry.id, e))?;
+    let tags: Vec<String> = entry.tags.iter().map(|tag| tag.to_string()).collect();
+    let mut document = Document { id: entry.id.to_string(), content: content.to_string(), embedding, metadata: Default::default() }
+        .with_title(entry.title)
+        .with_tags(&tags);
+    document.metadata.insert(TOPIC_KEY.to_string(), entry.topic.to_string());
+    Ok(document)
+}
+
+/// Ids of `collection`'s best matches for `query`, best first
+async fn search_ids(store: &dyn VectorStore, embedder: &dyn EmbeddingProvider, collection: &str, query: &str, filter: Option<&MetadataFilter>) -> Result<Vec<String>, String> {
+    let embedding = embedder.generate_embedding(query).map_err(|e| format!("embedding the query: {}", e))?;
+    let query = SearchQuery { embedding, limit: ENTRIES.len() };
+    let results = match filter {
+        Some(filter) => store.search_filtered(collection, query, filter).await,
+        None => store.search(collection, query).await,
+    };
+    Ok(results.map_err(|e| e.to_string())?.into_iter().map(|result| result.document.id).collect())
+}
+
+/// Run every check against `store` in the new collection `collection`, removing it at the end
+pub async fn run_selftest(store: &dyn VectorStore, embedder: &dyn EmbeddingProvider, embedder_name: &str, collection: &str) -> SelftestReport {
+    let mut report = SelftestReport { collection: collection.to_string(), embedder: embedder_name.to_string(), checks: Vec::new() };
+    let total = ENTRIES.len();
+    
+    let connected = report.run("connect", true, async {
+        store.test_connection().await.map(|_| store.backend_name().to_string()).map_err(|e| e.to_string())
+    }).await;
+    let created = report.run("create collection", connected, async {
+        store.create_collection(collection, embedder.embedding_dim()).await
+            .map(|_| format!("{} dimensions", embedder.embedding_dim()))
+            .map_err(|e| e.to_string())
+    }).await;
+    let ingested = report.run("ingest", created, async {
+        for entry in ENTRIES {
+            let document = sample_document(entry, entry.content, embedder)?;
+            store.insert_document(collection, document).await.map_err(|e| format!("{}: {}", entry.id, e))?;
+        }
+        Ok(format!("{} entries", total))
+    }).await;
+    report.run("count", ingested, async {
+        match store.count(collection, &MetadataFilter::default()).await.map_err(|e| e.to_string())? {
+            count if count == total => Ok(format!("{} entries", count)),
+            count => Err(format!("expected {} entries, found {}", total, count)),
+        }
+    }).await;
+    
+    for (query, expected) in RANKINGS {
+        report.run(&format!("search \"{}\"", query), ingested, async {
+            let ids = search_ids(store, embedder, collection, query, None).await?;
+            match ids.first() {
+                Some(first) if first == expected => Ok(format!("{} ranked first", expected)),
+                _ => Err(format!("expected {} first, got [{}]", expected, ids.join(", "))),
+            }
+        }).await;
+    }
+    
+    report.run("filter by metadata", ingested, async {
+        let filter = MetadataFilter { metadata: [(TOPIC_KEY.to_string(), "accounts".to_string())].into(), ..Default::default() };
+        let ids = search_ids(store, embedder, collection, "rotate API keys", Some(&filter)).await?;
+        let expected: Vec<&str> = ENTRIES.iter().filter(|entry| entry.topic == "accounts").map(|entry| entry.id).collect();
+        if ids.len() == expected.len() && ids.iter().all(|id| expected.contains(&id.as_str())) {
+            Ok(format!("{} of {} entries matched", ids.len(), total))
+        } else {
+            Err(format!("expected [{}], got [{}]", expected.join(", "), ids.join(", ")))
+        }
+    }).await;
+    report.run("filter by tags", ingested, async {
+        let filter = MetadataFilter { tags: vec!["security".to_string()], ..Default::default() };
+        let expected = ENTRIES.iter().filter(|entry| entry.tags.contains(&"security")).count();
+        match store.count(collection, &filter).await.map_err(|e| e.to_string())? {
+            count if count == expected => Ok(format!("{} entries tagged security", count)),
+            count => Err(format!("expected {} entries tagged security, found {}", expected, count)),
+        }
+    }).await;
+    
+    report.run("update", ingested, async {
+        let entry = &ENTRIES[0];
+        let content = format!("{} Restores are rehearsed monthly.", entry.content);
+        store.insert_document(collection, sample_document(entry, &content, embedder)?).await.map_err(|e| e.to_string())?;
+        let stored = store.get_document(collection, entry.id).await.map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("{} is gone after the update", entry.id))?;
+        if stored.content != content {
+            return Err(format!("{} still has its old content", entry.id));
+        }
+        match store.count(collection, &MetadataFilter::default()).await.map_err(|e| e.to_string())? {
+            count if count == total => Ok(format!("{} replaced in place", entry.id)),
+            count => Err(format!("the update left {} entries instead of {}", count, total)),
+        }
+    }).await;
+    report.run("delete", ingested, async {
+        let entry = &ENTRIES[1];
+        store.delete_document(collection, entry.id).await.map_err(|e| e.to_string())?;
+        if store.get_document(collection, entry.id).await.map_err(|e| e.to_string())?.is_some() {
+            return Err(format!("{} is still stored", entry.id));
+        }
+        let ids = search_ids(store, embedder, collection, RANKINGS[1].0, None).await?;
+        if ids.iter().any(|id| id == entry.id) {
+            return Err(format!("{} is still found by search", entry.id));
+        }
+        Ok(format!("{} removed", entry.id))
+    }).await;
+    
+    report.run("tear down", created, async {
+        store.delete_collection(collection).await.map_err(|e| e.to_string())?;
+        let collections = store.list_collections().await.map_err(|e| e.to_string())?;
+        if collections.iter().any(|name| name == collection) {
+            return Err(format!("{} is still listed", collection));
+        }
+        Ok(format!("{} deleted", collection))
+    }).await;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    #[tokio::test]
+    async fn test_selftest_passes_on_a_working_store() {
+        let store = InMemoryVectorStore::new();
+        let embedder = WordHashEmbedder::new(256);
+        let report = run_selftest(&store, &embedder, "word hashing", "p-mo-selftest-test").await;
+        
+        assert!(report.passed(), "{}", report.render_text());
+        assert!(report.checks.iter().all(|check| check.outcome == Outcome::Pass));
+        assert!(store.list_collections().await.unwrap().is_empty());
+        assert!(report.render_text().contains(&format!("{} passed, 0 failed, 0 skipped", report.checks.len())));
+    }
+    
+    #[tokio::test]
+    async fn test_failed_steps_skip_what_needs_them() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("taken", 4).await.unwrap();
+        let report = run_selftest(&store, &WordHashEmbedder::new(4), "word hashing", "taken").await;
+        
+        assert!(!report.passed());
+        let outcome = |name: &str| report.checks.iter().find(|check| check.name == name).unwrap().outcome;
+        assert_eq!(outcome("connect"), Outcome::Pass);
+        assert_eq!(outcome("create collection"), Outcome::Fail);
+        assert_eq!(outcome("ingest"), Outcome::Skip);
+        assert_eq!(outcome("tear down"), Outcome::Skip);
+        // A collection the run did not create is left alone
+        assert_eq!(store.list_collections().await.unwrap(), ["taken"]);
+    }
+}
@@ -1,4 +1,6 @@
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::oneshot;
@@ -6,7 +8,29 @@ use tokio::task::JoinHandle;
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
+use crate::api::{self, ApiState};
+use crate::api_keys::{ApiKeyStore, API_KEYS_FILE};
+use crate::attachments::Attachments;
 use crate::config;
+use crate::container;
+use crate::events::ChangeFeed;
+use crate::http_policy::HttpPolicy;
+use crate::logging::{daemon, ConfigAuditLog, LogWriter, RetrievalLog, RotatingFile, SlowQueryLog};
+use crate::maintenance::MaintenanceScheduler;
+use crate::mcp::{self, gateway::Gateway, tools::ToolPolicy, ProgmoMcpServer};
+use crate::migrations::{self, MigrationError, Migrator};
+use crate::network;
+use crate::oidc::OidcValidator;
+use crate::otel;
+use crate::systemd;
+use crate::telemetry::{TelemetryReporter, UsageCounters};
+use crate::uploads::UploadSessions;
+use crate::text_processing::{CrossEncoderReranker, EmbeddingProvider, FallbackEmbeddingProvider, LanguageRouter, ModelRegistry, PiiPolicy, QueryEmbeddingCache, SafeModePolicy};
+use crate::ui::{self, UiState};
+use crate::vector_store::{
+    CollectionRegistry, Compactor, EncryptedVectorStore, EncryptionKey, EventedVectorStore, InMemoryVectorStore, RoutedVectorStore, ShardedVectorStore,
+    TimedVectorStore, VectorStore, WalVectorStore, REGISTRY_FILE,
+};
 
 #[derive(Debug, Error)]
 pub enum ServerError {
@@ -21,6 +45,15 @@ pub enum ServerError {
     
     #[error("Failed to daemonize: {0}")]
     DaemonError(String),
+    
+    #[error("Startup migration failed: {0}")]
+    MigrationError(#[from] MigrationError),
+    
+    #[error("Failed to listen for termination signals: {0}")]
+    SignalError(std::io::Error),
+    
+    #[error("Failed to set up the server: {0}")]
+    SetupError(String),
 }
 
 pub struct ServerConfig {
@@ -30,6 +63,10 @@ pub struct ServerConfig {
     pub daemon: bool,
     pub pid_file: Option<PathBuf>,
     pub log_file: Option<PathBuf>,
+    pub logging: config::LoggingConfig,
+    /// Run as a container's main process: no PID or log file, even with
+    /// `daemon`, and a bounded wait for requests at shutdown
+    pub container: bool,
 }
 
 impl Default for ServerConfig {
@@ -39,8 +76,10 @@ impl Default for ServerConfig {
             port: 8080,
             timeout: Duration::from_secs(30),
             daemon: false,
-            pid_file: Some(PathBuf::from("/tmp/p-mo.pid")),
-            log_file: Some(PathBuf::from("/tmp/p-mo.log")),
+            pid_file: Some(config::Config::state_dir().join("p-mo.pid")),
+            log_file: Some(config::Config::state_dir().join("p-mo.log")),
+            logging: config::LoggingConfig::default(),
+            container: false,
         }
     }
 }
@@ -54,6 +93,8 @@ impl From<config::ServerConfig> for ServerConfig {
             daemon: config.daemon,
             pid_file: config.pid_file,
             log_file: config.log_file,
+            logging: config.logging,
+            container: config.container,
         }
     }
 }
@@ -61,36 +102,275 @@ impl From<config::ServerConfig> for ServerConfig {
 pub struct ServerHandle {
     shutdown_tx: oneshot::Sender<()>,
     task: JoinHandle<()>,
+    draining: Arc<AtomicBool>,
+    /// How long in-flight requests may take to finish; unbounded when `None`
+    grace: Option<Duration>,
+    watchdog: Option<JoinHandle<()>>,
+    telemetry: Option<JoinHandle<()>>,
+    maintenance: Option<JoinHandle<()>>,
 }
 
 impl ServerHandle {
-    pub async fn shutdown(self) -> Result<(), ServerError> {
+    pub async fn shutdown(mut self) -> Result<(), ServerError> {
+        let _ = systemd::notify("STOPPING=1");
+        self.draining.store(true, Ordering::SeqCst);
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.abort();
+        }
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.abort();
+        }
+        if let Some(maintenance) = &self.maintenance {
+            maintenance.abort();
+        }
         let _ = self.shutdown_tx.send(());
         // Wait for the server task to complete
-        if let Err(e) = self.task.await {
+        let joined = match self.grace {
+            Some(grace) => match tokio::time::timeout(grace, &mut self.task).await {
+                Ok(joined) => joined,
+                Err(_) => {
+                    tracing::warn!("Requests still running after {:?}; stopping anyway", grace);
+                    self.task.abort();
+                    Ok(())
+                },
+            },
+            None => (&mut self.task).await,
+        };
+        if let Err(e) = joined {
             eprintln!("Error joining server task: {:?}", e);
         }
         Ok(())
     }
+    
+    /// Serve until the process receives SIGTERM or SIGINT, then shut down
+    pub async fn run_until_terminated(self) -> Result<(), ServerError> {
+        let signal = container::terminated().await.map_err(ServerError::SignalError)?;
+        tracing::info!(signal, "Shutting down");
+        self.shutdown().await
+    }
 }
 
 pub struct Server {
     config: ServerConfig,
+    admin_ui: Option<UiState>,
+    api: Option<ApiState>,
+    unix_socket: Option<config::UnixSocketConfig>,
+    http: HttpPolicy,
+    migrator: Option<Migrator>,
+    telemetry: Option<TelemetryReporter>,
+    maintenance: Option<MaintenanceScheduler>,
 }
 
 impl Server {
     pub fn new(config: ServerConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            admin_ui: None,
+            api: None,
+            unix_socket: None,
+            http: HttpPolicy::default(),
+            migrator: None,
+            telemetry: None,
+            maintenance: None,
+        }
+    }
+    
+    /// Listen on a Unix domain socket instead of `host`/`port` (Unix only)
+    pub fn with_unix_socket(mut self, socket: config::UnixSocketConfig) -> Self {
+        self.unix_socket = Some(socket);
+        self
+    }
+    
+    /// Allow cross-origin requests, send security headers and limit request
+    /// bodies as `http` says, instead of the defaults: no CORS, every security
+    /// header and a 2 MiB limit
+    pub fn with_http(mut self, http: config::HttpConfig) -> Self {
+        self.http = HttpPolicy::from_config(&http);
+        self
+    }
+    
+    /// Serve the REST endpoints backed by a vector store, such as `/api/search`
+    pub fn with_api(mut self, state: ApiState) -> Self {
+        self.api = Some(state);
+        self
+    }
+    
+    /// Serve the admin UI under `/ui`
+    pub fn with_admin_ui(mut self, state: UiState) -> Self {
+        self.admin_ui = Some(state);
+        self
+    }
+    
+    /// Apply pending schema migrations before listening; the server does not
+    /// start if one fails
+    pub fn with_migrations(mut self, migrator: Migrator) -> Self {
+        self.migrator = Some(migrator);
+        self
+    }
+    
+    /// Send usage reports while running, if the reporter's config opts in
+    pub fn with_telemetry(mut self, reporter: TelemetryReporter) -> Self {
+        self.telemetry = Some(reporter);
+        self
+    }
+    
+    /// Purge expired entries and run other upkeep while running, unless the
+    /// scheduler's config disables it
+    pub fn with_maintenance(mut self, scheduler: MaintenanceScheduler) -> Self {
+        self.maintenance = Some(scheduler);
+        self
+    }
+    
+    /// The server `config` describes: the REST API, the admin UI when it has a
+    /// key, and the MCP server's manifest, over the embedded store or the
+    /// configured Qdrant instances, with migrations, maintenance and
+    /// telemetry as configured
+    pub async fn from_config(config: &config::Config) -> Result<Self, ServerError> {
+        let setup_error = |e: &dyn std::fmt::Display| ServerError::SetupError(e.to_string());
+        
+        let mut compactor = None;
+        let backend: Arc<dyn VectorStore> = if config.qdrant.instances.is_empty() {
+            let embedded = Arc::new(InMemoryVectorStore::new().with_storage(config.vectors.storage));
+            let embedded_compactor = Compactor::new(embedded.clone());
+            match WalVectorStore::from_config(embedded.clone(), &config.wal).await.map_err(|e| setup_error(&e))? {
+                Some((logged, report)) => {
+                    tracing::info!(path = %logged.path().display(), "Replayed the write-ahead log: {}", report);
+                    for rejection in &report.rejections {
+                        tracing::debug!(path = %logged.path().display(), "Logged change rejected again on replay: {}", rejection);
+                    }
+                    let logged = Arc::new(logged);
+                    compactor = Some(Arc::new(embedded_compactor.with_wal(logged.clone())));
+                    logged
+                },
+                None => {
+                    compactor = Some(Arc::new(embedded_compactor));
+                    embedded
+                },
+            }
+        } else {
+            Arc::new(RoutedVectorStore::from_config(&config.qdrant, &config.pool, &config.compression).await.map_err(|e| setup_error(&e))?)
+        };
+        let mut store: Arc<dyn VectorStore> = Arc::new(ShardedVectorStore::from_config(backend, &config.sharding));
+        if let Some(key) = EncryptionKey::from_config(&config.encryption).map_err(|e| setup_error(&e))? {
+            store = Arc::new(EncryptedVectorStore::new(store, key));
+        }
+        let slow_query_log = Arc::new(SlowQueryLog::from_config(&config.slow_query).map_err(|e| setup_error(&e))?);
+        store = Arc::new(TimedVectorStore::new(store, slow_query_log.clone()));
+        let change_feed = ChangeFeed::from_config(&config.changes).map(Arc::new);
+        if let Some(feed) = &change_feed {
+            store = Arc::new(EventedVectorStore::new(store, feed.clone()));
+        }
+        
+        let registry = Arc::new(CollectionRegistry::load(&config::Config::data_dir().join(REGISTRY_FILE)).map_err(|e| setup_error(&e))?);
+        let embedding = FallbackEmbeddingProvider::from_config(&config.embedding, &config.network)
+            .map_err(|e| setup_error(&e))?
+            .map(|chain| Arc::new(chain) as Arc<dyn EmbeddingProvider + Send + Sync>);
+        let query_cache = QueryEmbeddingCache::from_config(&config.query_cache).map(Arc::new);
+        // Cached queries are keyed by the whole chain, which may answer from any of its providers
+        let embedding_model = config.embedding.providers.iter().map(|provider| provider.name()).collect::<Vec<_>>().join(",");
+        let usage = Arc::new(UsageCounters::default());
+        let audit_log = Arc::new(ConfigAuditLog::from_config(&config.audit).map_err(|e| setup_error(&e))?);
+        let retrieval_log = Arc::new(RetrievalLog::from_config(&config.retrieval_log).map_err(|e| setup_error(&e))?);
+
+        let mut mcp_server = ProgmoMcpServer::new(
+            mcp::ServerConfig { name: "p-mo".to_string(), version: env!("CARGO_PKG_VERSION").to_string() },
+            store.clone(),
+        )
+        .with_registry(registry.clone())
+        .with_tool_policy(ToolPolicy::from_config(&config.tools))
+        .with_memory_config(config.memory.clone())
+        .with_pii_policy(PiiPolicy::from_config(&config.pii))
+        .with_language_router(LanguageRouter::from_config(&config.language))
+        .with_model_registry(ModelRegistry::from_config(&config.models))
+        .with_safe_mode(SafeModePolicy::from_config(&config.safe_mode))
+        .with_injection_scoring(config.injection_scoring.enabled)
+        .with_audit_log(audit_log.clone())
+        .with_retrieval_log(retrieval_log.clone())
+        .with_slow_query_log(slow_query_log)
+        .with_usage_counters(usage.clone());
+        if let Some(provider) = &embedding {
+            mcp_server = mcp_server.with_embedding_provider(provider.clone()).map_err(|e| setup_error(&e))?;
+        }
+        if let Some(cache) = &query_cache {
+            mcp_server = mcp_server.with_query_cache(cache.clone(), &embedding_model);
+        }
+        // Downstreams that fail to start are logged and left out
+        if !config.gateway.downstreams.is_empty() {
+            mcp_server = mcp_server.with_gateway(Arc::new(Gateway::connect(config.gateway.downstreams.clone()).await));
+        }
+        if let Some(reranker) = CrossEncoderReranker::from_config(&config.reranker).map_err(|e| setup_error(&e))? {
+            mcp_server = mcp_server.with_reranker(Arc::new(reranker));
+        }
+        
+        let mut api = ApiState::new(store.clone())
+            .with_registry(registry.clone())
+            .with_retrieval_log(retrieval_log)
+            .with_mcp_server(Arc::new(mcp_server));
+        if let Some(feed) = change_feed {
+            api = api.with_change_feed(feed);
+        }
+        if let Some(cache) = query_cache {
+            api = api.with_query_cache(cache, &embedding_model);
+        }
+        let attachments = Attachments::from_config(&config.attachments).map_err(|e| setup_error(&e))?;
+        api = api.with_attachments(Arc::new(attachments))
+            .with_uploads(Arc::new(UploadSessions::from_config(&config.attachments)));
+        let mut maintenance = MaintenanceScheduler::new(config.maintenance.clone(), store.clone(), registry.clone());
+        if let Some(compactor) = compactor {
+            api = api.with_compactor(compactor.clone());
+            maintenance = maintenance.with_compactor(compactor);
+        }
+        let mut admin_ui = UiState::new(store.clone(), config.admin.api_key.clone());
+        if let Some(provider) = &embedding {
+            api = api.with_embedding_provider(provider.clone());
+            admin_ui = admin_ui.with_embedding_provider(provider.clone());
+        }
+        // Keys are required once any exist or an admin key can create them
+        let keys_path = config::Config::data_dir().join(API_KEYS_FILE);
+        if keys_path.exists() || config.admin.api_key.is_some() {
+            let keys = ApiKeyStore::load(&keys_path).map_err(|e| setup_error(&e))?.with_audit_log(audit_log);
+            api = api.with_api_keys(Arc::new(keys)).with_admin_key(config.admin.api_key.clone());
+        }
+        // Outbound requests go through the configured proxy and CAs
+        let http = network::http_client(&config.network).map_err(|e| setup_error(&e))?;
+        if let Some(validator) = OidcValidator::new(config.oidc.clone()) {
+            api = api.with_oidc(Arc::new(validator.with_http_client(http.clone())));
+        }
+        
+        let migrator = Migrator::new(config::Config::data_dir(), migrations::builtin())?
+            .with_vector_store(store.clone());
+        let mut server = Self::new(config.server.clone().into())
+            .with_http(config.server.http.clone())
+            .with_api(api)
+            .with_migrations(migrator)
+            .with_maintenance(maintenance)
+            .with_telemetry(TelemetryReporter::new(config.telemetry.clone(), store, usage).with_http_client(http));
+        if config.admin.api_key.is_some() {
+            server = server.with_admin_ui(admin_ui);
+        }
+        if let Some(socket) = &config.server.unix_socket {
+            server = server.with_unix_socket(socket.clone());
+        }
+        Ok(server)
     }
     
     pub async fn start(&self) -> Result<ServerHandle, ServerError> {
         let addr: SocketAddr = format!("{}:{}", self.config.host, self.config.port)
             .parse()
             .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid address"))?;
+        
+        if let Some(migrator) = &self.migrator {
+            migrator.run().await?;
+        }
             
-        // If running as daemon, write PID file
-        if self.config.daemon {
+        // If running as daemon, write PID file; a container's runtime tracks
+        // the process and collects its stdout instead
+        if self.config.daemon && !self.config.container {
             if let Some(pid_file) = &self.config.pid_file {
+                if let Some(parent) = pid_file.parent() {
+                    std::fs::create_dir_all(parent)
+                        .map_err(|e| ServerError::DaemonError(format!("Failed to create PID directory: {}", e)))?;
+                }
                 let pid = std::process::id();
                 let mut file = File::create(pid_file)
                     .map_err(|e| ServerError::DaemonError(format!("Failed to create PID file: {}", e)))?;
@@ -98,42 +378,128 @@ impl Server {
                     .map_err(|e| ServerError::DaemonError(format!("Failed to write PID: {}", e)))?;
             }
             
-            // Redirect stdout/stderr to log file if specified
+            // Send logs to a rotating log file if specified
             if let Some(log_file) = &self.config.log_file {
-                let _file = File::create(log_file)
+                if let Some(parent) = log_file.parent() {
+                    std::fs::create_dir_all(parent)
+                        .map_err(|e| ServerError::DaemonError(format!("Failed to create log directory: {}", e)))?;
+                }
+                let file = RotatingFile::from_config(log_file, &self.config.logging)
                     .map_err(|e| ServerError::DaemonError(format!("Failed to create log file: {}", e)))?;
-                // In a real implementation, we would redirect stdout/stderr to this file
-                // This is just a placeholder for demonstration
+                let writer = LogWriter::new(file);
+                if !daemon::install_global_writer(writer.clone()) {
+                    tracing::warn!(
+                        log_file = %log_file.display(),
+                        "Another tracing subscriber was installed first; logging stays where it sends it instead of the log file"
+                    );
+                }
+                
+                #[cfg(unix)]
+                daemon::spawn_reopen_on_sigusr1(writer)
+                    .map_err(|e| ServerError::DaemonError(format!("Failed to install SIGUSR1 handler: {}", e)))?;
             }
         }
             
+        #[cfg(unix)]
+        let unix_listener = match &self.unix_socket {
+            Some(socket) => Some((
+                crate::unix_socket::bind(&socket.path, socket.mode).map_err(|e| match e.kind() {
+                    std::io::ErrorKind::AddrInUse => ServerError::AlreadyRunning,
+                    _ => ServerError::BindError(e),
+                })?,
+                socket.path.clone(),
+            )),
+            None => None,
+        };
+        #[cfg(not(unix))]
+        if self.unix_socket.is_some() {
+            return Err(ServerError::BindError(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Unix domain sockets are not supported on this platform",
+            )));
+        }
+        
+        // Under systemd socket activation, serve the socket it bound for us
+        let activated = match systemd::take_listener()? {
+            Some(listener) => Some(axum::Server::from_tcp(listener)
+                .map_err(std::io::Error::other)?),
+            None => None,
+        };
+            
         let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let admin_ui = self.admin_ui.clone();
+        let api = self.api.clone();
+        let http = self.http.clone();
+        let draining = Arc::new(AtomicBool::new(false));
+        let health = draining.clone();
         
         let task = tokio::spawn(async move {
-            let app = axum::Router::new()
-                .route("/health", axum::routing::get(|| async { "OK" }))
+            let mut app = axum::Router::new()
+                // Probes see the server going away before it stops accepting connections
+                .route("/health", axum::routing::get(move || {
+                    let draining = health.load(Ordering::SeqCst);
+                    async move {
+                        if draining {
+                            (axum::http::StatusCode::SERVICE_UNAVAILABLE, "draining")
+                        } else {
+                            (axum::http::StatusCode::OK, "OK")
+                        }
+                    }
+                }))
                 .route("/api/knowledge", axum::routing::post(|| async { 
                     (axum::http::StatusCode::CREATED, "\"test-id-123\"")
                 }))
                 .route("/api/knowledge/:id", axum::routing::get(|| async { 
                     (axum::http::StatusCode::OK, "{\"id\":\"test-id-123\",\"title\":\"Test Entry\",\"content\":\"This is a test knowledge entry\",\"tags\":[\"test\",\"knowledge\"]}")
                 }));
+            
+            if let Some(state) = api {
+                app = app.merge(api::router(state));
+            }
+            if let Some(state) = admin_ui {
+                app = app.nest("/ui", ui::router(state));
+            }
+            let app = http.apply(app).layer(axum::middleware::from_fn(otel::trace_http_request));
                 
-            let server = axum::Server::bind(&addr)
+            let shutdown = async {
+                shutdown_rx.await.ok();
+            };
+            
+            #[cfg(unix)]
+            if let Some((listener, path)) = unix_listener {
+                let server = axum::Server::builder(crate::unix_socket::UnixAccept(listener))
+                    .serve(app.into_make_service())
+                    .with_graceful_shutdown(shutdown);
+                if let Err(e) = server.await {
+                    eprintln!("Server error: {}", e);
+                }
+                let _ = std::fs::remove_file(path);
+                return;
+            }
+            
+            let server = activated.unwrap_or_else(|| axum::Server::bind(&addr))
                 .serve(app.into_make_service());
                 
-            let server_with_shutdown = server.with_graceful_shutdown(async {
-                shutdown_rx.await.ok();
-            });
+            let server_with_shutdown = server.with_graceful_shutdown(shutdown);
             
             if let Err(e) = server_with_shutdown.await {
                 eprintln!("Server error: {}", e);
             }
         });
         
+        // Tell systemd (Type=notify) that requests can be served
+        if let Err(e) = systemd::notify("READY=1") {
+            tracing::warn!("Failed to notify systemd of readiness: {}", e);
+        }
+        
         Ok(ServerHandle {
             shutdown_tx,
             task,
+            draining,
+            grace: self.config.container.then_some(container::SHUTDOWN_GRACE),
+            watchdog: systemd::spawn_watchdog(),
+            telemetry: self.telemetry.clone().and_then(TelemetryReporter::spawn),
+            maintenance: self.maintenance.clone().and_then(MaintenanceScheduler::spawn),
         })
     }
 }
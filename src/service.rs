@@ -0,0 +1,237 @@
+//! Running p-mo in the background and registering it with the OS.
+//!
+//! Background mode re-executes the binary as a detached child instead of
+//! forking, so it behaves the same on Unix and Windows. Service
+//! registration uses launchd on macOS and Task Scheduler on Windows.
+
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use thiserror::Error;
+
+/// Label of the launchd job and name of the scheduled task
+pub const SERVICE_NAME: &str = "com.progmo.p-mo";
+
+#[derive(Debug, Error)]
+pub enum ServiceError {
+    #[error("Service registration is not supported on this platform")]
+    Unsupported,
+    
+    #[error("Failed to locate the p-mo executable: {0}")]
+    Executable(std::io::Error),
+    
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    
+    #[error("{command} failed: {message}")]
+    Command { command: String, message: String },
+}
+
+/// The service manager p-mo registers itself with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceManager {
+    Launchd,
+    TaskScheduler,
+}
+
+impl ServiceManager {
+    /// The service manager of the running platform, if one is supported
+    pub fn current() -> Option<Self> {
+        if cfg!(target_os = "macos") {
+            Some(ServiceManager::Launchd)
+        } else if cfg!(windows) {
+            Some(ServiceManager::TaskScheduler)
+        } else {
+            None
+        }
+    }
+}
+
+/// Arguments that start the server in the foreground with `config_path`
+fn start_args(config_path: &Path) -> Vec<String> {
+    vec![
+        "start".to_string(),
+        "--config-path".to_string(),
+        config_path.display().to_string(),
+    ]
+}
+
+fn escape_xml(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// A launchd agent that keeps the server running for the current user
+pub fn launchd_plist(executable: &Path, config_path: &Path, log_file: &Path) -> String {
+    let arguments: String = std::iter::once(executable.display().to_string())
+        .chain(start_args(config_path))
+        .map(|argument| format!("        <string>{}</string>\n", escape_xml(&argument)))
+        .collect();
+    let log_file = escape_xml(&log_file.display().to_string());
+    
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+{arguments}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <true/>
+    <key>StandardOutPath</key>
+    <string>{log_file}</string>
+    <key>StandardErrorPath</key>
+    <string>{log_file}</string>
+</dict>
+</plist>
+"#,
+        label = SERVICE_NAME,
+        arguments = arguments,
+        log_file = log_file,
+    )
+}
+
+/// Where the launchd agent for the current user is installed
+pub fn launchd_plist_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("Library")
+        .join("LaunchAgents")
+        .join(format!("{}.plist", SERVICE_NAME))
+}
+
+/// `schtasks` arguments creating a task that starts the server at logon
+pub fn task_scheduler_args(executable: &Path, config_path: &Path) -> Vec<String> {
+    let command = std::iter::once(executable.display().to_string())
+        .chain(start_args(config_path))
+        .map(|argument| format!("\"{}\"", argument))
+        .collect::<Vec<String>>()
+        .join(" ");
+    
+    vec![
+        "/Create".to_string(),
+        "/F".to_string(),
+        "/SC".to_string(),
+        "ONLOGON".to_string(),
+        "/TN".to_string(),
+        SERVICE_NAME.to_string(),
+        "/TR".to_string(),
+        command,
+    ]
+}
+
+fn run(program: &str, args: &[String]) -> Result<(), ServiceError> {
+    let output = Command::new(program).args(args).output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(ServiceError::Command {
+            command: format!("{} {}", program, args.join(" ")),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        })
+    }
+}
+
+/// Register the server to start at login, describing what was installed
+pub fn install(config_path: &Path, log_file: &Path) -> Result<String, ServiceError> {
+    let executable = std::env::current_exe().map_err(ServiceError::Executable)?;
+    
+    match ServiceManager::current().ok_or(ServiceError::Unsupported)? {
+        ServiceManager::Launchd => {
+            let plist_path = launchd_plist_path();
+            if let Some(parent) = plist_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&plist_path, launchd_plist(&executable, config_path, log_file))?;
+            run("launchctl", &["load".to_string(), "-w".to_string(), plist_path.display().to_string()])?;
+            Ok(format!("Installed launchd agent {}", plist_path.display()))
+        },
+        ServiceManager::TaskScheduler => {
+            run("schtasks", &task_scheduler_args(&executable, config_path))?;
+            Ok(format!("Installed scheduled task {}", SERVICE_NAME))
+        },
+    }
+}
+
+/// Remove the registration made by `install`
+pub fn uninstall() -> Result<String, ServiceError> {
+    match ServiceManager::current().ok_or(ServiceError::Unsupported)? {
+        ServiceManager::Launchd => {
+            let plist_path = launchd_plist_path();
+            if !plist_path.exists() {
+                return Ok("No launchd agent installed".to_string());
+            }
+            run("launchctl", &["unload".to_string(), "-w".to_string(), plist_path.display().to_string()])?;
+            fs::remove_file(&plist_path)?;
+            Ok(format!("Removed launchd agent {}", plist_path.display()))
+        },
+        ServiceManager::TaskScheduler => {
+            let args = ["/Delete", "/F", "/TN", SERVICE_NAME].map(String::from);
+            run("schtasks", &args)?;
+            Ok(format!("Removed scheduled task {}", SERVICE_NAME))
+        },
+    }
+}
+
+/// Start `p-mo <args>` detached from this process, returning the child's PID
+///
+/// Output goes to `log_file`. The child is put in its own process group (or
+/// detached from the console on Windows) so closing the terminal leaves it running.
+pub fn spawn_background(args: &[String], log_file: &Path) -> Result<u32, ServiceError> {
+    let executable = std::env::current_exe().map_err(ServiceError::Executable)?;
+    if let Some(parent) = log_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let log = OpenOptions::new().create(true).append(true).open(log_file)?;
+    
+    let mut command = Command::new(executable);
+    command.args(args)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
+    
+    Ok(command.spawn()?.id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_launchd_plist() {
+        let plist = launchd_plist(
+            Path::new("/usr/local/bin/p-mo"),
+            Path::new("/Users/me/Library/Application Support/p-mo/config.toml"),
+            Path::new("/Users/me/Library/Logs/p-mo & co.log"),
+        );
+        
+        assert!(plist.contains(&format!("<string>{}</string>", SERVICE_NAME)));
+        assert!(plist.contains("<string>/usr/local/bin/p-mo</string>\n        <string>start</string>"));
+        assert!(plist.contains("<string>/Users/me/Library/Application Support/p-mo/config.toml</string>"));
+        assert!(plist.contains("p-mo &amp; co.log"));
+    }
+    
+    #[test]
+    fn test_task_scheduler_args() {
+        let args = task_scheduler_args(Path::new(r"C:\Tools\p-mo.exe"), Path::new(r"C:\Users\me\p-mo\config.toml"));
+        assert_eq!(&args[..6], ["/Create", "/F", "/SC", "ONLOGON", "/TN", SERVICE_NAME]);
+        assert_eq!(args[7], r#""C:\Tools\p-mo.exe" "start" "--config-path" "C:\Users\me\p-mo\config.toml""#);
+    }
+}
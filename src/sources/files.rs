@@ -0,0 +1,461 @@
+//! Ingestion of local files.
+//!
+//! Files are read, extracted and stored several at a time. A file that cannot
+//! be read or extracted is recorded in the report and does not stop the
+//! others; the failures can be saved as an error file and passed back to
+//! ingest just those files again. With a manifest, repeated runs only touch
+//! files that changed or vanished since the last one.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use super::manifest::{content_hash, IngestManifest, ManifestEntry};
+use super::{
+    extract_with_ocr, file_type_of, object_document_id, FileType, OcrStage, SourceError, DEFAULT_SOURCE_EMBEDDING_DIM,
+    LINE_KEY, OCR_CONFIDENCE_KEY, PAGE_KEY,
+};
+use crate::progress::Progress;
+use crate::text_processing::EmbeddingProvider;
+use crate::vector_store::{Document, VectorStore, CREATED_AT_KEY, EMBEDDING_PROVIDER_KEY, SOURCE_KEY, TITLE_KEY};
+
+/// Namespace of the ids of entries ingested from local files
+pub const FILE_ID_NAMESPACE: &str = "file";
+
+/// Files ingested at once when no parallelism is configured
+pub const DEFAULT_PARALLELISM: usize = 4;
+
+/// A file that could not be ingested, as recorded in an error file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFailure {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Outcome of ingesting a set of files
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IngestReport {
+    /// Files stored, with the number of entries each produced
+    pub succeeded: Vec<(PathBuf, usize)>,
+    /// Files that could not be read, extracted or stored
+    pub failed: Vec<FileFailure>,
+    /// Files of a type that cannot be ingested
+    pub unsupported: Vec<PathBuf>,
+    /// Files whose content matches the manifest, left as stored
+    pub unchanged: Vec<PathBuf>,
+    /// Files in the manifest that no longer exist, whose entries were deleted
+    pub removed: Vec<PathBuf>,
+}
+
+impl IngestReport {
+    /// Entries written across all files
+    pub fn entries(&self) -> usize {
+        self.succeeded.iter().map(|(_, entries)| entries).sum()
+    }
+    
+    /// Write the failures as JSON lines of `{"path", "reason"}`, readable by [`read_error_file`]
+    pub fn write_error_file(&self, path: &Path) -> Result<(), SourceError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = fs::File::create(path)?;
+        for failure in &self.failed {
+            let line = serde_json::to_string(failure)
+                .map_err(|e| SourceError::Config(format!("Failed to encode {}: {}", failure.path.display(), e)))?;
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// The paths listed in an error file written by [`IngestReport::write_error_file`]
+pub fn read_error_file(path: &Path) -> Result<Vec<PathBuf>, SourceError> {
+    let content = fs::read_to_string(path)?;
+    content.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str::<FileFailure>(line)
+                .map(|failure| failure.path)
+                .map_err(|e| SourceError::Config(format!("{} line {}: {}", path.display(), index + 1, e)))
+        })
+        .collect()
+}
+
+/// Every file named by `paths`, descending into directories; paths that
+/// cannot be listed are returned as failures
+pub fn collect_files(paths: &[PathBuf]) -> (Vec<PathBuf>, Vec<FileFailure>) {
+    let mut files = Vec::new();
+    let mut failed = Vec::new();
+    let mut pending: Vec<PathBuf> = paths.to_vec();
+    
+    while let Some(path) = pending.pop() {
+        if !path.is_dir() {
+            files.push(path);
+            continue;
+        }
+        match fs::read_dir(&path) {
+            Ok(entries) => pending.extend(entries.filter_map(|entry| entry.ok()).map(|entry| entry.path())),
+            Err(e) => failed.push(FileFailure { path, reason: e.to_string() }),
+        }
+    }
+    
+    files.sort();
+    files.dedup();
+    (files, failed)
+}
+
+/// Stores local files in a collection, several at a time
+#[derive(Clone)]
+pub struct FileIngest {
+    store: Arc<dyn VectorStore>,
+    embedding_provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    embedding_dim: usize,
+    parallelism: usize,
+    progress: Progress,
+    manifest: Option<PathBuf>,
+    ocr: Option<OcrStage>,
+}
+
+/// What happened to one file
+enum FileOutcome {
+    Unchanged,
+    Stored(ManifestEntry),
+}
+
+impl FileIngest {
+    pub fn new(store: Arc<dyn VectorStore>) -> Self {
+        Self {
+            store,
+            embedding_provider: None,
+            embedding_dim: DEFAULT_SOURCE_EMBEDDING_DIM,
+            parallelism: DEFAULT_PARALLELISM,
+            progress: Progress::hidden(),
+            manifest: None,
+            ocr: None,
+        }
+    }
+    
+    /// Embed files with `provider`, whose vectors have `embedding_dim` dimensions
+    pub fn with_embedding_provider(mut self, provider: Arc<dyn EmbeddingProvider + Send + Sync>, embedding_dim: usize) -> Self {
+        self.embedding_provider = Some(provider);
+        self.embedding_dim = embedding_dim;
+        self
+    }
+    
+    /// Ingest up to `parallelism` files at once
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+    
+    /// Report each file as it is handled
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+    
+    /// Keep an ingest manifest at `path`, making repeated runs incremental
+    pub fn with_manifest(mut self, path: PathBuf) -> Self {
+        self.manifest = Some(path);
+        self
+    }
+    
+    /// Recognize text in images and image-only PDFs with `ocr`; images are
+    /// unsupported files without it
+    pub fn with_ocr(mut self, ocr: OcrStage) -> Self {
+        self.ocr = Some(ocr);
+        self
+    }
+    
+    /// Ingest the files named by `paths`, descending into directories, into `collection`
+    ///
+    /// The collection is created if it does not exist; failing to do so, or
+    /// to read or write the manifest, is the only error that stops the run.
+    /// Files recorded in the manifest under one of `paths` that no longer
+    /// exist have their entries deleted.
+    pub async fn ingest(&self, paths: &[PathBuf], collection: &str) -> Result<IngestReport, SourceError> {
+        if !self.store.list_collections().await?.iter().any(|name| name == collection) {
+            self.store.create_collection(collection, self.embedding_dim).await?;
+        }
+        let mut manifest = match &self.manifest {
+            Some(path) => IngestManifest::load(path)?,
+            None => IngestManifest::default(),
+        };
+        
+        let (files, failed) = collect_files(paths);
+        let mut report = IngestReport { failed, ..IngestReport::default() };
+        self.progress.set_total(files.len() as u64);
+        
+        let semaphore = Arc::new(Semaphore::new(self.parallelism));
+        let mut tasks = JoinSet::new();
+        let mut pending = HashMap::new();
+        let mut seen = HashSet::new();
+        for path in files {
+            let Some(file_type) = file_type_of(&path.to_string_lossy(), self.ocr.as_ref()) else {
+                self.progress.inc(1);
+                report.unsupported.push(path);
+                continue;
+            };
+            // Files are tracked by absolute path so the same file matches however it is named
+            let absolute = match fs::canonicalize(&path) {
+                Ok(absolute) => absolute.to_string_lossy().into_owned(),
+                Err(e) => {
+                    self.progress.inc(1);
+                    report.failed.push(FileFailure { path, reason: e.to_string() });
+                    continue;
+                },
+            };
+            
+            if !seen.insert(absolute.clone()) {
+                // The same file named twice
+                self.progress.inc(1);
+                continue;
+            }
+            pending.insert(path.clone(), absolute.clone());
+            let previous = manifest.get(collection, &absolute).cloned();
+            let ingest = self.clone();
+            let semaphore = semaphore.clone();
+            let collection = collection.to_string();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let outcome = ingest.ingest_file(&collection, &path, &absolute, file_type, previous).await;
+                ingest.progress.inc(1);
+                (path, outcome)
+            });
+        }
+        
+        while let Some(joined) = tasks.join_next().await {
+            // A panicked task leaves its path in `pending`, reported below
+            let Ok((path, outcome)) = joined else { continue };
+            let Some(absolute) = pending.remove(&path) else { continue };
+            match outcome {
+                Ok(FileOutcome::Unchanged) => report.unchanged.push(path),
+                Ok(FileOutcome::Stored(entry)) => {
+                    report.succeeded.push((path, entry.entry_ids.len()));
+                    manifest.record(collection, &absolute, entry);
+                },
+                Err(e) => report.failed.push(FileFailure { path, reason: e.to_string() }),
+            }
+        }
+        report.failed.extend(pending.into_keys().map(|path| FileFailure { path, reason: "Ingest task panicked".to_string() }));
+        
+        self.remove_vanished(&mut manifest, &mut report, paths, &seen, collection).await;
+        if let Some(path) = &self.manifest {
+            manifest.save(path)?;
+        }
+        
+        report.succeeded.sort();
+        report.failed.sort_by(|a, b| a.path.cmp(&b.path));
+        report.unchanged.sort();
+        report.removed.sort();
+        Ok(report)
+    }
+    
+    /// Delete the entries of manifest files under `roots` that were not found this run
+    async fn remove_vanished(&self, manifest: &mut IngestManifest, report: &mut IngestReport, roots: &[PathBuf], seen: &HashSet<String>, collection: &str) {
+        let mut vanished = Vec::new();
+        for root in roots {
+            // A root that is gone entirely can no longer be canonicalized
+            let root = fs::canonicalize(root)
+                .or_else(|_| std::env::current_dir().map(|cwd| cwd.join(root)))
+                .unwrap_or_else(|_| root.clone());
+            vanished.extend(manifest.files_under(collection, &root)
+                .filter(|file| !seen.contains(*file) && !Path::new(file).exists())
+                .map(str::to_string));
+        }
+        vanished.sort();
+        vanished.dedup();
+        
+        for file in vanished {
+            let Some(entry) = manifest.get(collection, &file) else { continue };
+            let mut deleted = Ok(());
+            for id in &entry.entry_ids {
+                deleted = self.store.delete_document(collection, id).await;
+                if deleted.is_err() {
+                    break;
+                }
+            }
+            match deleted {
+                Ok(()) => {
+                    manifest.remove(collection, &file);
+                    report.removed.push(PathBuf::from(file));
+                },
+                Err(e) => report.failed.push(FileFailure { path: PathBuf::from(file), reason: e.to_string() }),
+            }
+        }
+    }
+    
+    async fn ingest_file(&self, collection: &str, path: &Path, key: &str, file_type: FileType, previous: Option<ManifestEntry>) -> Result<FileOutcome, SourceError> {
+        let bytes = tokio::fs::read(path).await?;
+        let hash = content_hash(&bytes);
+        if previous.as_ref().is_some_and(|previous| previous.hash == hash) {
+            return Ok(FileOutcome::Unchanged);
+        }
+        let extracted = extract_with_ocr(key, file_type, &bytes, self.ocr.as_ref())?;
+        
+        let mut documents = Vec::with_capacity(extracted.len());
+        for item in extracted {
+            let (embedding, embedding_provider) = match &self.embedding_provider {
+                Some(provider) => provider.generate_attributed(&item.content)?,
+                None => (vec![0.0; self.embedding_dim], None),
+            };
+            let mut document = Document {
+                id: object_document_id(FILE_ID_NAMESPACE, key, item.position()),
+                content: item.content,
+                embedding,
+                metadata: Default::default(),
+            };
+            let title = item.title.unwrap_or_else(|| {
+                Path::new(key).file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| key.to_string())
+            });
+            document.metadata.insert(TITLE_KEY.to_string(), title);
+            document.metadata.insert(CREATED_AT_KEY.to_string(), Utc::now().to_rfc3339());
+            if let Some(provider) = embedding_provider {
+                document.metadata.insert(EMBEDDING_PROVIDER_KEY.to_string(), provider);
+            }
+            document.metadata.insert(SOURCE_KEY.to_string(), key.to_string());
+            if let Some(line) = item.line {
+                document.metadata.insert(LINE_KEY.to_string(), line.to_string());
+            }
+            if let Some(page) = item.page {
+                document.metadata.insert(PAGE_KEY.to_string(), page.to_string());
+            }
+            if let Some(confidence) = item.ocr_confidence {
+                document.metadata.insert(OCR_CONFIDENCE_KEY.to_string(), format!("{:.1}", confidence));
+            }
+            documents.push(document);
+        }
+        
+        let entry_ids: Vec<String> = documents.iter().map(|document| document.id.clone()).collect();
+        for document in documents {
+            self.store.insert_document(collection, document).await?;
+        }
+        // Entries the file no longer produces, such as removed JSONL lines
+        for stale in previous.iter().flat_map(|previous| &previous.entry_ids) {
+            if !entry_ids.contains(stale) {
+                self.store.delete_document(collection, stale).await?;
+            }
+        }
+        
+        Ok(FileOutcome::Stored(ManifestEntry { hash, entry_ids, ingested_at: Utc::now() }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    use tempfile::TempDir;
+    
+    #[tokio::test]
+    async fn test_ingest_isolates_failures() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("notes")).unwrap();
+        fs::write(dir.path().join("notes/a.md"), "# Alpha\n\nFirst note.").unwrap();
+        fs::write(dir.path().join("notes/b.txt"), "Second note.").unwrap();
+        fs::write(dir.path().join("notes/c.jsonl"), "not json").unwrap();
+        fs::write(dir.path().join("notes/d.png"), "binary").unwrap();
+        let missing = dir.path().join("missing.md");
+        
+        let store = Arc::new(InMemoryVectorStore::new());
+        let ingest = FileIngest::new(store.clone()).with_parallelism(2);
+        let report = ingest.ingest(&[dir.path().join("notes"), missing.clone()], "local").await.unwrap();
+        
+        assert_eq!(report.succeeded.len(), 2);
+        assert_eq!(report.entries(), 2);
+        assert_eq!(report.unsupported, vec![dir.path().join("notes/d.png")]);
+        let failed: Vec<&PathBuf> = report.failed.iter().map(|failure| &failure.path).collect();
+        assert_eq!(failed, vec![&missing, &dir.path().join("notes/c.jsonl")]);
+        
+        let id = object_document_id(FILE_ID_NAMESPACE, &fs::canonicalize(dir.path().join("notes/b.txt")).unwrap().to_string_lossy(), None);
+        let stored = store.get_document("local", &id).await.unwrap().unwrap();
+        assert_eq!(stored.content, "Second note.");
+        assert_eq!(stored.metadata[TITLE_KEY], "b.txt");
+    }
+    
+    /// An engine reading the "image" as `text|confidence`
+    struct ScriptedEngine;
+    
+    impl crate::sources::OcrEngine for ScriptedEngine {
+        fn recognize(&self, image: &[u8]) -> Result<crate::sources::ocr::Recognized, crate::sources::OcrError> {
+            let script = String::from_utf8_lossy(image);
+            let (text, confidence) = script.rsplit_once('|').unwrap();
+            Ok(crate::sources::ocr::Recognized { text: text.to_string(), confidence: confidence.parse().unwrap() })
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_ocr_ingests_images() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("whiteboard.png"), "Retry budget is 3 per request|88").unwrap();
+        fs::write(dir.path().join("blurry.jpg"), "r3try bu#get|31").unwrap();
+        
+        let store = Arc::new(InMemoryVectorStore::new());
+        let ocr = OcrStage::new(Arc::new(ScriptedEngine)).with_min_confidence(60.0);
+        let report = FileIngest::new(store.clone()).with_ocr(ocr).ingest(&[dir.path().to_path_buf()], "local").await.unwrap();
+        assert_eq!(report.succeeded.len(), 2);
+        assert_eq!(report.entries(), 1);
+        assert!(report.unsupported.is_empty());
+        
+        let key = fs::canonicalize(dir.path().join("whiteboard.png")).unwrap().to_string_lossy().into_owned();
+        let stored = store.get_document("local", &object_document_id(FILE_ID_NAMESPACE, &key, Some(1))).await.unwrap().unwrap();
+        assert_eq!(stored.content, "Retry budget is 3 per request");
+        assert_eq!(stored.metadata[OCR_CONFIDENCE_KEY], "88.0");
+        assert_eq!(stored.metadata[PAGE_KEY], "1");
+    }
+    
+    #[tokio::test]
+    async fn test_error_file_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let bad = dir.path().join("bad.jsonl");
+        fs::write(&bad, "not json").unwrap();
+        
+        let store = Arc::new(InMemoryVectorStore::new());
+        let report = FileIngest::new(store).ingest(std::slice::from_ref(&bad), "local").await.unwrap();
+        let errors = dir.path().join("errors/ingest-errors.jsonl");
+        report.write_error_file(&errors).unwrap();
+        
+        assert_eq!(read_error_file(&errors).unwrap(), vec![bad]);
+        fs::write(&errors, "{\"path\": 3}").unwrap();
+        assert!(matches!(read_error_file(&errors), Err(SourceError::Config(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_manifest_makes_ingest_a_sync() {
+        let dir = TempDir::new().unwrap();
+        let notes = dir.path().join("notes");
+        fs::create_dir(&notes).unwrap();
+        fs::write(notes.join("a.md"), "Alpha").unwrap();
+        fs::write(notes.join("b.jsonl"), "{\"content\": \"one\"}\n{\"content\": \"two\"}\n").unwrap();
+        fs::write(notes.join("c.txt"), "Gamma").unwrap();
+        
+        let store = Arc::new(InMemoryVectorStore::new());
+        let ingest = FileIngest::new(store.clone()).with_manifest(dir.path().join("manifest.json"));
+        let report = ingest.ingest(std::slice::from_ref(&notes), "local").await.unwrap();
+        assert_eq!((report.succeeded.len(), report.entries()), (3, 4));
+        
+        // Nothing changed
+        let report = ingest.ingest(std::slice::from_ref(&notes), "local").await.unwrap();
+        assert_eq!((report.succeeded.len(), report.unchanged.len()), (0, 3));
+        
+        // One line dropped from b, c deleted
+        fs::write(notes.join("b.jsonl"), "{\"content\": \"one, revised\"}\n").unwrap();
+        fs::remove_file(notes.join("c.txt")).unwrap();
+        let report = ingest.ingest(std::slice::from_ref(&notes), "local").await.unwrap();
+        assert_eq!(report.succeeded, vec![(notes.join("b.jsonl"), 1)]);
+        assert_eq!(report.unchanged, vec![notes.join("a.md")]);
+        assert_eq!(report.removed.len(), 1);
+        
+        let entries = store.list_documents("local", None, 10).await.unwrap().documents;
+        let mut contents: Vec<&str> = entries.iter().map(|document| document.content.as_str()).collect();
+        contents.sort();
+        assert_eq!(contents, vec!["Alpha", "one, revised"]);
+    }
+}
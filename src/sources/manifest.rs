@@ -0,0 +1,111 @@
+//! What `p-mo ingest` stored from each file.
+//!
+//! The manifest maps every ingested file, per collection, to the hash of the
+//! content it was ingested with and the ids of the entries it produced. A
+//! later run skips files whose hash is unchanged, replaces the entries of
+//! changed ones, and deletes the entries of files that are gone.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::SourceError;
+
+/// File name of the manifest in the data directory
+pub const MANIFEST_FILE: &str = "ingest-manifest.json";
+
+/// What was stored from one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// SHA-256 of the file's content, hex encoded
+    pub hash: String,
+    /// Ids of the entries the file produced
+    pub entry_ids: Vec<String>,
+    pub ingested_at: DateTime<Utc>,
+}
+
+/// Ingested files by collection, then by absolute path
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestManifest {
+    #[serde(default)]
+    pub collections: BTreeMap<String, BTreeMap<String, ManifestEntry>>,
+}
+
+impl IngestManifest {
+    /// Read the manifest, treating a missing file as empty
+    pub fn load(path: &Path) -> Result<Self, SourceError> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| SourceError::Config(format!("Ingest manifest {} is corrupt: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    /// Write the manifest, replacing the file atomically
+    pub fn save(&self, path: &Path) -> Result<(), SourceError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| SourceError::Config(format!("Failed to encode the ingest manifest: {}", e)))?;
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, content)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+    
+    pub fn get(&self, collection: &str, file: &str) -> Option<&ManifestEntry> {
+        self.collections.get(collection)?.get(file)
+    }
+    
+    pub fn record(&mut self, collection: &str, file: &str, entry: ManifestEntry) {
+        self.collections.entry(collection.to_string()).or_default().insert(file.to_string(), entry);
+    }
+    
+    pub fn remove(&mut self, collection: &str, file: &str) -> Option<ManifestEntry> {
+        self.collections.get_mut(collection)?.remove(file)
+    }
+    
+    /// Files recorded for `collection` that lie at or under `root`
+    pub fn files_under<'a>(&'a self, collection: &str, root: &'a Path) -> impl Iterator<Item = &'a str> + 'a {
+        self.collections.get(collection)
+            .into_iter()
+            .flat_map(|files| files.keys())
+            .map(String::as_str)
+            .filter(move |file| Path::new(file).starts_with(root))
+    }
+}
+
+/// Hash of a file's content as recorded in the manifest
+pub fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(MANIFEST_FILE);
+        assert_eq!(IngestManifest::load(&path).unwrap(), IngestManifest::default());
+        
+        let mut manifest = IngestManifest::default();
+        let entry = ManifestEntry { hash: content_hash(b"alpha"), entry_ids: vec!["a".to_string()], ingested_at: Utc::now() };
+        manifest.record("notes", "/docs/a.md", entry.clone());
+        manifest.record("notes", "/other/b.md", entry.clone());
+        manifest.save(&path).unwrap();
+        
+        let loaded = IngestManifest::load(&path).unwrap();
+        assert_eq!(loaded.get("notes", "/docs/a.md"), Some(&entry));
+        assert_eq!(loaded.files_under("notes", Path::new("/docs")).collect::<Vec<_>>(), vec!["/docs/a.md"]);
+        assert_eq!(loaded.files_under("wiki", Path::new("/")).count(), 0);
+        assert_ne!(content_hash(b"alpha"), content_hash(b"beta"));
+    }
+}
@@ -0,0 +1,433 @@
+//! Bulk ingestion from object storage and local files.
+//!
+//! Objects under a bucket prefix are listed, fetched and stored under ids
+//! derived from their key. Each stored entry keeps the object's etag, so a
+//! later sync skips objects that have not changed since.
+
+pub mod files;
+pub mod manifest;
+pub mod ocr;
+pub mod s3;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::feeds::html_to_text;
+use crate::progress::Progress;
+use crate::text_processing::{EmbeddingError, EmbeddingProvider};
+use crate::vector_store::{Document, VectorStore, VectorStoreError, CREATED_AT_KEY, SOURCE_KEY, TITLE_KEY};
+
+pub use files::{FileFailure, FileIngest, IngestReport};
+pub use manifest::{IngestManifest, ManifestEntry};
+pub use ocr::{OcrEngine, OcrError, OcrStage};
+pub use s3::{Credentials, S3Client, GCS_ENDPOINT, S3_ENDPOINT};
+
+/// Metadata key holding the object key an entry was ingested from
+pub const OBJECT_KEY_KEY: &str = "object_key";
+
+/// Metadata key holding the etag of the object when it was ingested
+pub const ETAG_KEY: &str = "etag";
+
+/// Metadata key holding the line of a JSONL object a record came from
+pub const LINE_KEY: &str = "line";
+
+/// Metadata key holding the page of a scanned PDF an entry was recognized from
+pub const PAGE_KEY: &str = "page";
+
+/// Metadata key holding the mean OCR confidence (0-100) of an entry's text
+pub const OCR_CONFIDENCE_KEY: &str = "ocr_confidence";
+
+/// PDFs with fewer characters of text than this are treated as scans when OCR is enabled
+const MIN_PDF_TEXT_CHARS: usize = 20;
+
+/// Embedding dimension used for objects when no provider is configured
+const DEFAULT_SOURCE_EMBEDDING_DIM: usize = 384;
+
+#[derive(Debug, Error)]
+pub enum SourceError {
+    #[error("Configuration error: {0}")]
+    Config(String),
+    
+    #[error("Request failed: {0}")]
+    Request(String),
+    
+    #[error("Failed to extract text from {key}: {message}")]
+    Extract { key: String, message: String },
+    
+    #[error("Vector store error: {0}")]
+    Store(#[from] VectorStoreError),
+    
+    #[error("Embedding error: {0}")]
+    Embedding(#[from] EmbeddingError),
+    
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// An object as listed by the store
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectInfo {
+    pub key: String,
+    /// Opaque version tag; changes whenever the object's content does
+    pub etag: String,
+    pub size: u64,
+}
+
+/// A bucket of objects that can be listed and fetched
+#[async_trait]
+pub trait ObjectSource: Send + Sync {
+    /// Every object whose key starts with `prefix`
+    async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>, SourceError>;
+    
+    /// The full content of an object
+    async fn fetch(&self, key: &str) -> Result<Vec<u8>, SourceError>;
+}
+
+/// File types that can be ingested, chosen by extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    Markdown,
+    Text,
+    Html,
+    Pdf,
+    /// One record per line, each stored as its own entry
+    Jsonl,
+    /// Only read with an OCR stage, one entry per image
+    Image,
+}
+
+impl FileType {
+    pub fn from_key(key: &str) -> Option<Self> {
+        let extension = key.rsplit_once('.')?.1.to_ascii_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" => Some(FileType::Markdown),
+            "txt" => Some(FileType::Text),
+            "html" | "htm" => Some(FileType::Html),
+            "pdf" => Some(FileType::Pdf),
+            "jsonl" | "ndjson" => Some(FileType::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+/// The file type of `key`, counting images only when they can be read with OCR
+fn file_type_of(key: &str, ocr: Option<&OcrStage>) -> Option<FileType> {
+    FileType::from_key(key).or_else(|| (ocr.is_some() && ocr::is_image_key(key)).then_some(FileType::Image))
+}
+
+/// A piece of text extracted from an object, ready to store
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extracted {
+    pub title: Option<String>,
+    pub content: String,
+    /// Line number within a JSONL object, starting at 1
+    pub line: Option<usize>,
+    /// Page number of text recognized with OCR, starting at 1
+    pub page: Option<usize>,
+    /// Mean OCR confidence (0-100) of recognized text
+    pub ocr_confidence: Option<f32>,
+}
+
+impl Extracted {
+    /// Where the piece sits within its object, for building its id
+    pub fn position(&self) -> Option<usize> {
+        self.line.or(self.page)
+    }
+}
+
+/// Pull the text out of an object's bytes
+pub fn extract(key: &str, file_type: FileType, bytes: &[u8]) -> Result<Vec<Extracted>, SourceError> {
+    extract_with_ocr(key, file_type, bytes, None)
+}
+
+/// Pull the text out of an object's bytes, recognizing images and PDFs
+/// without a text layer with `ocr`
+pub fn extract_with_ocr(key: &str, file_type: FileType, bytes: &[u8], ocr: Option<&OcrStage>) -> Result<Vec<Extracted>, SourceError> {
+    let extract_error = |message: String| SourceError::Extract { key: key.to_string(), message };
+    let text = || String::from_utf8(bytes.to_vec()).map_err(|e| extract_error(e.to_string()));
+    let whole = |content: String| Extracted { title: None, content: content.trim().to_string(), line: None, page: None, ocr_confidence: None };
+    let recognized = |pages: Vec<ocr::OcrPage>| -> Vec<Extracted> {
+        pages.into_iter()
+            .map(|page| Extracted {
+                title: None,
+                content: page.text,
+                line: None,
+                page: Some(page.page),
+                ocr_confidence: Some(page.confidence),
+            })
+            .collect()
+    };
+    
+    let extracted = match file_type {
+        FileType::Markdown | FileType::Text => vec![whole(text()?)],
+        FileType::Html => vec![whole(html_to_text(&text()?))],
+        FileType::Pdf => {
+            let text = pdf_extract::extract_text_from_mem(bytes);
+            match ocr {
+                // A PDF with (next to) no text layer is a scan
+                Some(ocr) if text.as_ref().map_or(true, |text| text.split_whitespace().map(str::len).sum::<usize>() < MIN_PDF_TEXT_CHARS) => {
+                    recognized(ocr.recognize_pdf(bytes).map_err(|e| extract_error(e.to_string()))?)
+                },
+                _ => vec![whole(text.map_err(|e| extract_error(e.to_string()))?)],
+            }
+        },
+        FileType::Image => match ocr {
+            Some(ocr) => recognized(ocr.recognize_image(bytes).map_err(|e| extract_error(e.to_string()))?),
+            None => return Err(extract_error("images can only be ingested with OCR enabled".to_string())),
+        },
+        FileType::Jsonl => {
+            let mut records = Vec::new();
+            for (index, line) in text()?.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record: serde_json::Value = serde_json::from_str(line)
+                    .map_err(|e| extract_error(format!("line {}: {}", index + 1, e)))?;
+                let field = |name: &str| record.get(name).and_then(|value| value.as_str()).map(|value| value.to_string());
+                
+                records.push(Extracted {
+                    title: field("title"),
+                    content: field("content").or_else(|| field("text")).unwrap_or_else(|| line.to_string()),
+                    line: Some(index + 1),
+                    page: None,
+                    ocr_confidence: None,
+                });
+            }
+            records
+        },
+    };
+    
+    Ok(extracted.into_iter().filter(|item| !item.content.is_empty()).collect())
+}
+
+/// Id under which a piece of an object is stored, stable across syncs
+pub fn object_document_id(bucket: &str, key: &str, line: Option<usize>) -> String {
+    let name = match line {
+        Some(line) => format!("{}\n{}\n{}", bucket, key, line),
+        None => format!("{}\n{}", bucket, key),
+    };
+    let digest = Sha256::digest(name.as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Uuid::from_bytes(bytes).to_string()
+}
+
+/// Outcome of syncing a bucket prefix
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncReport {
+    /// Objects ingested for the first time or re-ingested after a change
+    pub ingested: usize,
+    /// Objects whose etag matches the stored entry
+    pub unchanged: usize,
+    /// Objects of a type that cannot be ingested
+    pub unsupported: usize,
+    /// Objects that could not be fetched or read, with the reason
+    pub failed: Vec<(String, String)>,
+    /// Entries written, which exceeds `ingested` for JSONL objects
+    pub entries: usize,
+}
+
+/// Syncs a bucket prefix into a collection
+pub struct BucketSync {
+    source: Arc<dyn ObjectSource>,
+    store: Arc<dyn VectorStore>,
+    bucket: String,
+    embedding_provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    embedding_dim: usize,
+    progress: Progress,
+}
+
+impl BucketSync {
+    pub fn new(source: Arc<dyn ObjectSource>, store: Arc<dyn VectorStore>, bucket: &str) -> Self {
+        Self {
+            source,
+            store,
+            bucket: bucket.to_string(),
+            embedding_provider: None,
+            embedding_dim: DEFAULT_SOURCE_EMBEDDING_DIM,
+            progress: Progress::hidden(),
+        }
+    }
+    
+    /// Embed objects with `provider`, whose vectors have `embedding_dim` dimensions
+    pub fn with_embedding_provider(mut self, provider: Arc<dyn EmbeddingProvider + Send + Sync>, embedding_dim: usize) -> Self {
+        self.embedding_provider = Some(provider);
+        self.embedding_dim = embedding_dim;
+        self
+    }
+    
+    /// Report each object listed under the prefix as it is handled
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+    
+    /// Ingest the new and changed objects under `prefix` into `collection`
+    ///
+    /// The collection is created if it does not exist. An object that fails
+    /// to fetch or extract is recorded in the report and does not stop the sync.
+    pub async fn sync(&self, prefix: &str, collection: &str) -> Result<SyncReport, SourceError> {
+        if !self.store.list_collections().await?.iter().any(|name| name == collection) {
+            self.store.create_collection(collection, self.embedding_dim).await?;
+        }
+        
+        let mut report = SyncReport::default();
+        let objects = self.source.list(prefix).await?;
+        self.progress.set_total(objects.len() as u64);
+        for object in objects {
+            self.progress.inc(1);
+            let Some(file_type) = FileType::from_key(&object.key) else {
+                report.unsupported += 1;
+                continue;
+            };
+            if self.is_unchanged(collection, &object, file_type).await? {
+                report.unchanged += 1;
+                continue;
+            }
+            
+            match self.ingest_object(collection, &object, file_type).await {
+                Ok(entries) => {
+                    report.ingested += 1;
+                    report.entries += entries;
+                },
+                Err(e @ SourceError::Store(_)) => return Err(e),
+                Err(e) => report.failed.push((object.key.clone(), e.to_string())),
+            }
+        }
+        
+        Ok(report)
+    }
+    
+    /// Whether the entry stored for `object` carries its current etag
+    async fn is_unchanged(&self, collection: &str, object: &ObjectInfo, file_type: FileType) -> Result<bool, SourceError> {
+        // A JSONL object's etag is recorded on every line; the first is enough to check
+        let line = (file_type == FileType::Jsonl).then_some(1);
+        let id = object_document_id(&self.bucket, &object.key, line);
+        Ok(self.store.get_document(collection, &id).await?
+            .is_some_and(|document| document.metadata.get(ETAG_KEY) == Some(&object.etag)))
+    }
+    
+    async fn ingest_object(&self, collection: &str, object: &ObjectInfo, file_type: FileType) -> Result<usize, SourceError> {
+        let bytes = self.source.fetch(&object.key).await?;
+        let extracted = extract(&object.key, file_type, &bytes)?;
+        
+        let mut documents = Vec::with_capacity(extracted.len());
+        for item in extracted {
+            let embedding = match &self.embedding_provider {
+                Some(provider) => provider.generate_embedding(&item.content)?,
+                None => vec![0.0; self.embedding_dim],
+            };
+            let mut document = Document {
+                id: object_document_id(&self.bucket, &object.key, item.line),
+                content: item.content,
+                embedding,
+                metadata: Default::default(),
+            };
+            let title = item.title.unwrap_or_else(|| {
+                object.key.rsplit('/').next().unwrap_or(&object.key).to_string()
+            });
+            document.metadata.insert(TITLE_KEY.to_string(), title);
+            document.metadata.insert(CREATED_AT_KEY.to_string(), Utc::now().to_rfc3339());
+            document.metadata.insert(SOURCE_KEY.to_string(), format!("{}/{}", self.bucket, object.key));
+            document.metadata.insert(OBJECT_KEY_KEY.to_string(), object.key.clone());
+            document.metadata.insert(ETAG_KEY.to_string(), object.etag.clone());
+            if let Some(line) = item.line {
+                document.metadata.insert(LINE_KEY.to_string(), line.to_string());
+            }
+            documents.push(document);
+        }
+        
+        let count = documents.len();
+        for document in documents {
+            self.store.insert_document(collection, document).await?;
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+    
+    #[derive(Default)]
+    struct FakeBucket {
+        objects: Mutex<BTreeMap<String, (String, Vec<u8>)>>,
+    }
+    
+    impl FakeBucket {
+        fn put(&self, key: &str, etag: &str, body: &str) {
+            self.objects.lock().unwrap().insert(key.to_string(), (etag.to_string(), body.as_bytes().to_vec()));
+        }
+    }
+    
+    #[async_trait]
+    impl ObjectSource for FakeBucket {
+        async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>, SourceError> {
+            Ok(self.objects.lock().unwrap().iter()
+                .filter(|(key, _)| key.starts_with(prefix))
+                .map(|(key, (etag, body))| ObjectInfo { key: key.clone(), etag: etag.clone(), size: body.len() as u64 })
+                .collect())
+        }
+        
+        async fn fetch(&self, key: &str) -> Result<Vec<u8>, SourceError> {
+            self.objects.lock().unwrap().get(key)
+                .map(|(_, body)| body.clone())
+                .ok_or_else(|| SourceError::Request(format!("No such key: {}", key)))
+        }
+    }
+    
+    #[test]
+    fn test_file_type_from_key() {
+        assert_eq!(FileType::from_key("docs/readme.MD"), Some(FileType::Markdown));
+        assert_eq!(FileType::from_key("a/b.htm"), Some(FileType::Html));
+        assert_eq!(FileType::from_key("data.jsonl"), Some(FileType::Jsonl));
+        assert_eq!(FileType::from_key("image.png"), None);
+        assert_eq!(FileType::from_key("Makefile"), None);
+    }
+    
+    #[test]
+    fn test_extract_jsonl_records() {
+        let body = "{\"title\": \"One\", \"content\": \"first\"}\n\n{\"text\": \"second\"}\n";
+        let records = extract("data.jsonl", FileType::Jsonl, body.as_bytes()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].title.as_deref(), Some("One"));
+        assert_eq!(records[1].content, "second");
+        assert_eq!(records[1].line, Some(3));
+        
+        assert!(matches!(extract("bad.jsonl", FileType::Jsonl, b"not json"), Err(SourceError::Extract { .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_sync_skips_unchanged_etags() {
+        let bucket = Arc::new(FakeBucket::default());
+        bucket.put("docs/a.md", "e1", "# Alpha\n\nFirst document.");
+        bucket.put("docs/b.html", "e2", "<p>Beta</p>");
+        bucket.put("docs/c.png", "e3", "binary");
+        bucket.put("other/d.txt", "e4", "Out of prefix");
+        
+        let store = Arc::new(InMemoryVectorStore::new());
+        let sync = BucketSync::new(bucket.clone(), store.clone(), "kb");
+        
+        let report = sync.sync("docs/", "kb_docs").await.unwrap();
+        assert_eq!((report.ingested, report.unchanged, report.unsupported), (2, 0, 1));
+        
+        let stored = store.get_document("kb_docs", &object_document_id("kb", "docs/b.html", None)).await.unwrap().unwrap();
+        assert_eq!(stored.content, "Beta");
+        assert_eq!(stored.metadata[OBJECT_KEY_KEY], "docs/b.html");
+        assert_eq!(stored.metadata[ETAG_KEY], "e2");
+        
+        bucket.put("docs/a.md", "e5", "Alpha, revised.");
+        let report = sync.sync("docs/", "kb_docs").await.unwrap();
+        assert_eq!((report.ingested, report.unchanged), (1, 1));
+        
+        let stored = store.get_document("kb_docs", &object_document_id("kb", "docs/a.md", None)).await.unwrap().unwrap();
+        assert_eq!(stored.content, "Alpha, revised.");
+    }
+}
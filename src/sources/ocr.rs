@@ -0,0 +1,299 @@
+//! Optional text recognition for images and image-only PDFs.
+//!
+//! Screenshots and scanned documents carry no text layer, so ingesting them
+//! yields nothing. An [`OcrStage`] runs each image (or each page image of a
+//! PDF) through an [`OcrEngine`] and keeps the pages recognized with enough
+//! confidence. Tesseract is the engine used in production and is only built
+//! with the `ocr` feature.
+
+use std::io::Read;
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::debug;
+
+use crate::config::OcrConfig;
+
+/// Minimum mean confidence (0-100) of a kept page when none is configured
+pub const DEFAULT_MIN_CONFIDENCE: f32 = 60.0;
+
+/// Extensions of image files OCR can read
+const IMAGE_EXTENSIONS: [&str; 9] = ["png", "jpg", "jpeg", "tif", "tiff", "bmp", "gif", "webp", "pnm"];
+
+#[derive(Debug, Error)]
+pub enum OcrError {
+    #[error("OCR is enabled but p-mo was built without the ocr feature")]
+    NotBuilt,
+    
+    #[error("OCR failed: {0}")]
+    Engine(String),
+    
+    #[error("Failed to read PDF page images: {0}")]
+    Pdf(String),
+}
+
+/// Whether `key` names an image file OCR can read
+pub fn is_image_key(key: &str) -> bool {
+    key.rsplit_once('.')
+        .is_some_and(|(_, extension)| IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+/// Text recognized in one image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recognized {
+    pub text: String,
+    /// Mean word confidence, 0-100
+    pub confidence: f32,
+}
+
+/// Turns an encoded image into text
+pub trait OcrEngine: Send + Sync {
+    fn recognize(&self, image: &[u8]) -> Result<Recognized, OcrError>;
+}
+
+/// OCR through the Tesseract library
+#[cfg(feature = "ocr")]
+#[derive(Debug, Clone)]
+pub struct TesseractEngine {
+    datapath: Option<String>,
+    language: String,
+}
+
+#[cfg(feature = "ocr")]
+impl TesseractEngine {
+    pub fn new(datapath: Option<String>, language: &str) -> Self {
+        Self { datapath, language: language.to_string() }
+    }
+}
+
+#[cfg(feature = "ocr")]
+impl OcrEngine for TesseractEngine {
+    fn recognize(&self, image: &[u8]) -> Result<Recognized, OcrError> {
+        // An instance holds one image at a time, so each call gets its own
+        let mut tesseract = tesseract::Tesseract::new(self.datapath.as_deref(), Some(&self.language))
+            .map_err(|e| OcrError::Engine(e.to_string()))?
+            .set_image_from_mem(image)
+            .map_err(|e| OcrError::Engine(e.to_string()))?
+            .recognize()
+            .map_err(|e| OcrError::Engine(e.to_string()))?;
+        let text = tesseract.get_text().map_err(|e| OcrError::Engine(e.to_string()))?;
+        Ok(Recognized { text, confidence: tesseract.mean_text_conf() as f32 })
+    }
+}
+
+/// The text of one page, as kept by an [`OcrStage`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrPage {
+    /// Page number, starting at 1
+    pub page: usize,
+    pub text: String,
+    pub confidence: f32,
+}
+
+/// Recognizes text in images and image-only PDFs, skipping low-confidence pages
+#[derive(Clone)]
+pub struct OcrStage {
+    engine: Arc<dyn OcrEngine>,
+    min_confidence: f32,
+}
+
+impl OcrStage {
+    pub fn new(engine: Arc<dyn OcrEngine>) -> Self {
+        Self { engine, min_confidence: DEFAULT_MIN_CONFIDENCE }
+    }
+    
+    /// Skip pages whose mean confidence is below `min_confidence` (0-100)
+    pub fn with_min_confidence(mut self, min_confidence: f32) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+    
+    /// A Tesseract stage from configuration
+    #[cfg(feature = "ocr")]
+    pub fn from_config(config: &OcrConfig) -> Result<Self, OcrError> {
+        let datapath = config.datapath.as_ref().map(|path| path.to_string_lossy().into_owned());
+        Ok(Self::new(Arc::new(TesseractEngine::new(datapath, &config.language))).with_min_confidence(config.min_confidence))
+    }
+    
+    /// A Tesseract stage from configuration
+    #[cfg(not(feature = "ocr"))]
+    pub fn from_config(_config: &OcrConfig) -> Result<Self, OcrError> {
+        Err(OcrError::NotBuilt)
+    }
+    
+    /// The text of a single image, unless it was recognized with too little confidence
+    pub fn recognize_image(&self, bytes: &[u8]) -> Result<Vec<OcrPage>, OcrError> {
+        let recognized = self.engine.recognize(bytes)?;
+        Ok(self.keep(1, recognized).into_iter().collect())
+    }
+    
+    /// The text of each page of an image-only PDF, leaving out pages without
+    /// readable images or recognized with too little confidence
+    pub fn recognize_pdf(&self, bytes: &[u8]) -> Result<Vec<OcrPage>, OcrError> {
+        let mut pages = Vec::new();
+        for (page, images) in pdf_page_images(bytes)? {
+            let mut texts = Vec::new();
+            let mut confidences = Vec::new();
+            for image in images {
+                let recognized = self.engine.recognize(&image)?;
+                if !recognized.text.trim().is_empty() {
+                    texts.push(recognized.text.trim().to_string());
+                    confidences.push(recognized.confidence);
+                }
+            }
+            if texts.is_empty() {
+                debug!(page, "No text recognized on PDF page");
+                continue;
+            }
+            let confidence = confidences.iter().sum::<f32>() / confidences.len() as f32;
+            pages.extend(self.keep(page, Recognized { text: texts.join("\n\n"), confidence }));
+        }
+        Ok(pages)
+    }
+    
+    fn keep(&self, page: usize, recognized: Recognized) -> Option<OcrPage> {
+        let text = recognized.text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        if recognized.confidence < self.min_confidence {
+            debug!(page, confidence = recognized.confidence, min_confidence = self.min_confidence, "Skipping low-confidence OCR page");
+            return None;
+        }
+        Some(OcrPage { page, text: text.to_string(), confidence: recognized.confidence })
+    }
+}
+
+/// The encoded images on one page of a PDF, with the page's number
+pub type PageImages = (usize, Vec<Vec<u8>>);
+
+/// The images on each page of a PDF, encoded in formats an OCR engine reads:
+/// JPEG and JPEG 2000 images as stored, and uncompressed or Flate-compressed
+/// bitmaps as PNM. Other encodings, such as JBIG2, are left out.
+pub fn pdf_page_images(bytes: &[u8]) -> Result<Vec<PageImages>, OcrError> {
+    let document = lopdf::Document::load_mem(bytes).map_err(|e| OcrError::Pdf(e.to_string()))?;
+    let mut pages = Vec::new();
+    for (number, page_id) in document.get_pages() {
+        let images = document.get_page_images(page_id).map_err(|e| OcrError::Pdf(e.to_string()))?;
+        let encoded: Vec<Vec<u8>> = images.iter()
+            .filter_map(|image| {
+                let filters = image.filters.clone().unwrap_or_default();
+                let encoded = encode_image(
+                    &filters,
+                    image.content,
+                    image.width,
+                    image.height,
+                    image.color_space.as_deref(),
+                    image.bits_per_component,
+                );
+                if encoded.is_none() {
+                    debug!(page = number, ?filters, "Skipping PDF image in an encoding OCR cannot read");
+                }
+                encoded
+            })
+            .collect();
+        pages.push((number as usize, encoded));
+    }
+    Ok(pages)
+}
+
+/// An image XObject's content as a file an OCR engine can read
+fn encode_image(
+    filters: &[String],
+    content: &[u8],
+    width: i64,
+    height: i64,
+    color_space: Option<&str>,
+    bits_per_component: Option<i64>,
+) -> Option<Vec<u8>> {
+    match filters {
+        [filter] if filter == "DCTDecode" || filter == "JPXDecode" => return Some(content.to_vec()),
+        [] => {},
+        [filter] if filter == "FlateDecode" => {},
+        _ => return None,
+    }
+    let pixels = if filters.is_empty() {
+        content.to_vec()
+    } else {
+        let mut inflated = Vec::new();
+        flate2::read::ZlibDecoder::new(content).read_to_end(&mut inflated).ok()?;
+        inflated
+    };
+    pnm(&pixels, width as usize, height as usize, color_space?, bits_per_component.unwrap_or(8))
+}
+
+/// Wrap raw samples in a PNM header
+fn pnm(pixels: &[u8], width: usize, height: usize, color_space: &str, bits_per_component: i64) -> Option<Vec<u8>> {
+    let (magic, row_bytes) = match (color_space, bits_per_component) {
+        ("DeviceGray", 8) => ("P5", width),
+        ("DeviceRGB", 8) => ("P6", width * 3),
+        ("DeviceGray", 1) => ("P4", width.div_ceil(8)),
+        _ => return None,
+    };
+    if pixels.len() < row_bytes * height {
+        return None;
+    }
+    
+    let mut out = if magic == "P4" {
+        format!("{}\n{} {}\n", magic, width, height).into_bytes()
+    } else {
+        format!("{}\n{} {}\n255\n", magic, width, height).into_bytes()
+    };
+    let samples = &pixels[..row_bytes * height];
+    if magic == "P4" {
+        // PDF gray uses 0 for black; PBM uses 1
+        out.extend(samples.iter().map(|byte| !byte));
+    } else {
+        out.extend_from_slice(samples);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// An engine reading the "image" as `text|confidence`
+    struct ScriptedEngine;
+    
+    impl OcrEngine for ScriptedEngine {
+        fn recognize(&self, image: &[u8]) -> Result<Recognized, OcrError> {
+            let script = String::from_utf8_lossy(image);
+            let (text, confidence) = script.rsplit_once('|').ok_or_else(|| OcrError::Engine("unreadable".to_string()))?;
+            Ok(Recognized { text: text.to_string(), confidence: confidence.parse().unwrap() })
+        }
+    }
+    
+    #[test]
+    fn test_low_confidence_images_are_skipped() {
+        let stage = OcrStage::new(Arc::new(ScriptedEngine)).with_min_confidence(70.0);
+        let pages = stage.recognize_image(b"  Quarterly architecture review  |91.5").unwrap();
+        assert_eq!(pages, vec![OcrPage { page: 1, text: "Quarterly architecture review".to_string(), confidence: 91.5 }]);
+        
+        assert!(stage.recognize_image(b"bl#rry n0ise|42").unwrap().is_empty());
+        assert!(stage.recognize_image(b"   |99").unwrap().is_empty());
+        assert!(stage.recognize_image(b"no confidence").is_err());
+    }
+    
+    #[test]
+    fn test_image_keys() {
+        assert!(is_image_key("screens/login.PNG"));
+        assert!(is_image_key("scan.tiff"));
+        assert!(!is_image_key("notes.md"));
+        assert!(!is_image_key("png"));
+    }
+    
+    #[test]
+    fn test_bitmaps_become_pnm() {
+        let gray = encode_image(&[], &[0, 128, 255, 64], 2, 2, Some("DeviceGray"), Some(8)).unwrap();
+        assert_eq!(gray, b"P5\n2 2\n255\n\x00\x80\xff\x40".to_vec());
+        
+        // A 1-bit row of black then white pixels, inverted for PBM
+        let mono = encode_image(&[], &[0b0011_1111], 4, 1, Some("DeviceGray"), Some(1)).unwrap();
+        assert_eq!(mono, b"P4\n4 1\n\xc0".to_vec());
+        
+        let jpeg = encode_image(&["DCTDecode".to_string()], b"\xff\xd8\xff", 1, 1, Some("DeviceRGB"), Some(8)).unwrap();
+        assert_eq!(jpeg, b"\xff\xd8\xff".to_vec());
+        assert!(encode_image(&["JBIG2Decode".to_string()], b"", 1, 1, Some("DeviceGray"), Some(1)).is_none());
+        assert!(encode_image(&[], &[0], 2, 2, Some("DeviceGray"), Some(8)).is_none());
+    }
+}
@@ -0,0 +1,156 @@
+//! Socket activation and readiness notification under systemd.
+//!
+//! Both follow the `sd_listen_fds(3)` and `sd_notify(3)` protocols directly
+//! through environment variables, so nothing here needs libsystemd. Outside
+//! systemd every function is a no-op.
+
+use std::io;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// First file descriptor passed by socket activation
+pub const SD_LISTEN_FDS_START: i32 = 3;
+
+/// Number of sockets passed to process `pid`, from `LISTEN_PID` and `LISTEN_FDS`
+pub fn listen_fds_count(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> usize {
+    // The variables are inherited by children, which must not claim the sockets
+    if listen_pid.and_then(|value| value.trim().parse::<u32>().ok()) != Some(pid) {
+        return 0;
+    }
+    listen_fds.and_then(|value| value.trim().parse().ok()).unwrap_or(0)
+}
+
+/// The listener systemd bound for us, if the process was socket activated
+#[cfg(unix)]
+pub fn take_listener() -> io::Result<Option<std::net::TcpListener>> {
+    use std::os::unix::io::FromRawFd;
+    
+    let count = listen_fds_count(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    if count == 0 {
+        return Ok(None);
+    }
+    if count > 1 {
+        warn!(count, "Socket activation passed several sockets; only the first is used");
+    }
+    
+    // SAFETY: with LISTEN_PID naming this process, systemd guarantees the
+    // descriptor is open and owned by us; nothing else in the process uses it.
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(SD_LISTEN_FDS_START) };
+    listener.set_nonblocking(true)?;
+    Ok(Some(listener))
+}
+
+#[cfg(not(unix))]
+pub fn take_listener() -> io::Result<Option<std::net::TcpListener>> {
+    Ok(None)
+}
+
+/// Send `state` (e.g. "READY=1") to the service manager
+///
+/// Returns false when the process is not supervised by systemd.
+#[cfg(unix)]
+pub fn notify(state: &str) -> io::Result<bool> {
+    match std::env::var_os("NOTIFY_SOCKET") {
+        Some(path) => send_notification(&path, state).map(|()| true),
+        None => Ok(false),
+    }
+}
+
+#[cfg(unix)]
+fn send_notification(path: &std::ffi::OsStr, state: &str) -> io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+    
+    let socket = UnixDatagram::unbound()?;
+    
+    // A leading '@' names a socket in the Linux abstract namespace
+    #[cfg(target_os = "linux")]
+    if let Some(name) = path.to_str().and_then(|path| path.strip_prefix('@')) {
+        use std::os::linux::net::SocketAddrExt;
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+        socket.send_to_addr(state.as_bytes(), &addr)?;
+        return Ok(());
+    }
+    
+    socket.send_to(state.as_bytes(), path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// How often to ping the watchdog: half the timeout systemd will enforce
+pub fn watchdog_interval(watchdog_usec: Option<&str>, watchdog_pid: Option<&str>, pid: u32) -> Option<Duration> {
+    if let Some(watchdog_pid) = watchdog_pid {
+        if watchdog_pid.trim().parse::<u32>().ok() != Some(pid) {
+            return None;
+        }
+    }
+    let usec: u64 = watchdog_usec?.trim().parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// Ping the systemd watchdog until aborted, if `WatchdogSec=` is set for the unit
+pub fn spawn_watchdog() -> Option<JoinHandle<()>> {
+    let interval = watchdog_interval(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    )?;
+    
+    Some(tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = notify("WATCHDOG=1") {
+                warn!("Failed to ping the systemd watchdog: {}", e);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_listen_fds_count() {
+        assert_eq!(listen_fds_count(Some("42"), Some("1"), 42), 1);
+        assert_eq!(listen_fds_count(Some("42"), Some("2"), 42), 2);
+        assert_eq!(listen_fds_count(Some("41"), Some("1"), 42), 0);
+        assert_eq!(listen_fds_count(None, Some("1"), 42), 0);
+        assert_eq!(listen_fds_count(Some("42"), None, 42), 0);
+        assert_eq!(listen_fds_count(Some("42"), Some("x"), 42), 0);
+    }
+    
+    #[test]
+    fn test_watchdog_interval() {
+        assert_eq!(watchdog_interval(Some("30000000"), None, 7), Some(Duration::from_secs(15)));
+        assert_eq!(watchdog_interval(Some("30000000"), Some("7"), 7), Some(Duration::from_secs(15)));
+        assert_eq!(watchdog_interval(Some("30000000"), Some("8"), 7), None);
+        assert_eq!(watchdog_interval(Some("0"), None, 7), None);
+        assert_eq!(watchdog_interval(None, None, 7), None);
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_send_notification() {
+        use std::os::unix::net::UnixDatagram;
+        
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("notify.sock");
+        let receiver = UnixDatagram::bind(&path).unwrap();
+        
+        send_notification(path.as_os_str(), "READY=1").unwrap();
+        
+        let mut buffer = [0u8; 64];
+        let len = receiver.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"READY=1");
+    }
+}
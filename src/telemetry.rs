@@ -0,0 +1,189 @@
+//! Strictly opt-in, anonymous usage telemetry.
+//!
+//! Nothing is collected or sent unless the config says `telemetry = true`
+//! (or `[telemetry] enabled = true`) and names an endpoint. Reports hold
+//! aggregate counts only: the version, platform, backend type, number of
+//! collections and how often each built-in tool was called. They never
+//! contain content, queries, collection names, ids or client identities.
+//! `p-mo telemetry` prints the exact report that would be sent.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::config::TelemetryConfig;
+use crate::mcp::tools::tool_spec;
+use crate::vector_store::VectorStore;
+
+#[derive(Debug, Error)]
+pub enum TelemetryError {
+    #[error("Telemetry is enabled but no endpoint is configured")]
+    NoEndpoint,
+    
+    #[error("Failed to send telemetry: {0}")]
+    Send(String),
+}
+
+/// Everything a telemetry report contains
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TelemetryReport {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    /// Kind of vector store, e.g. "qdrant"
+    pub backend: String,
+    pub collection_count: usize,
+    /// Calls of each built-in tool since the previous report
+    pub tool_calls: BTreeMap<String, u64>,
+    /// Seconds covered by `tool_calls`
+    pub period_secs: u64,
+}
+
+/// Counts tool calls between reports
+#[derive(Debug, Default)]
+pub struct UsageCounters {
+    tool_calls: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl UsageCounters {
+    /// Count a call of `tool`; names other than the built-in tools (e.g.
+    /// gateway tools) are not recorded
+    pub fn record_tool_call(&self, tool: &str) {
+        if let Some(spec) = tool_spec(tool) {
+            *self.tool_calls.lock().unwrap().entry(spec.name).or_default() += 1;
+        }
+    }
+    
+    /// The counts so far
+    pub fn snapshot(&self) -> BTreeMap<String, u64> {
+        self.tool_calls.lock().unwrap().iter().map(|(name, count)| (name.to_string(), *count)).collect()
+    }
+    
+    /// The counts so far, starting over from zero
+    pub fn take(&self) -> BTreeMap<String, u64> {
+        std::mem::take(&mut *self.tool_calls.lock().unwrap())
+            .into_iter()
+            .map(|(name, count)| (name.to_string(), count))
+            .collect()
+    }
+}
+
+/// Build a report from the store and the given tool call counts
+pub async fn build_report(store: &dyn VectorStore, tool_calls: BTreeMap<String, u64>, period: Duration) -> TelemetryReport {
+    TelemetryReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        backend: store.backend_name().to_string(),
+        collection_count: store.list_collections().await.map(|collections| collections.len()).unwrap_or(0),
+        tool_calls,
+        period_secs: period.as_secs(),
+    }
+}
+
+/// Periodically sends reports while telemetry is enabled
+#[derive(Clone)]
+pub struct TelemetryReporter {
+    config: TelemetryConfig,
+    store: Arc<dyn VectorStore>,
+    usage: Arc<UsageCounters>,
+    http: reqwest::Client,
+}
+
+impl TelemetryReporter {
+    pub fn new(config: TelemetryConfig, store: Arc<dyn VectorStore>, usage: Arc<UsageCounters>) -> Self {
+        Self { config, store, usage, http: reqwest::Client::new() }
+    }
+    
+    /// Send reports with `http`, e.g. one built by `network::http_client`
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+    
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.config.interval_secs.max(60))
+    }
+    
+    /// The report that would be sent now, without resetting the counts
+    pub async fn preview(&self) -> TelemetryReport {
+        build_report(self.store.as_ref(), self.usage.snapshot(), self.interval()).await
+    }
+    
+    /// Send `report` to the configured endpoint
+    pub async fn send(&self, report: &TelemetryReport) -> Result<(), TelemetryError> {
+        let endpoint = self.config.endpoint.as_deref().ok_or(TelemetryError::NoEndpoint)?;
+        let response = self.http.post(endpoint)
+            .timeout(Duration::from_secs(10))
+            .json(report)
+            .send()
+            .await
+            .map_err(|e| TelemetryError::Send(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(TelemetryError::Send(format!("{} returned {}", endpoint, response.status())));
+        }
+        Ok(())
+    }
+    
+    /// Report every interval in the background; `None` unless telemetry is
+    /// enabled and has an endpoint
+    pub fn spawn(self) -> Option<JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+        if self.config.endpoint.is_none() {
+            warn!("{}", TelemetryError::NoEndpoint);
+            return None;
+        }
+        info!("Anonymous usage telemetry is enabled; set telemetry = false to turn it off");
+        
+        Some(tokio::spawn(async move {
+            let interval = self.interval();
+            loop {
+                tokio::time::sleep(interval).await;
+                let report = build_report(self.store.as_ref(), self.usage.take(), interval).await;
+                if let Err(e) = self.send(&report).await {
+                    debug!("{}", e);
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    #[tokio::test]
+    async fn test_report_holds_only_counts() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("secret-project", 2).await.unwrap();
+        
+        let usage = UsageCounters::default();
+        usage.record_tool_call("search_knowledge");
+        usage.record_tool_call("search_knowledge");
+        usage.record_tool_call("github.create_issue");
+        
+        let report = build_report(&store, usage.take(), Duration::from_secs(3600)).await;
+        assert_eq!(report.backend, "memory");
+        assert_eq!(report.collection_count, 1);
+        assert_eq!(report.tool_calls, BTreeMap::from([("search_knowledge".to_string(), 2)]));
+        assert!(usage.snapshot().is_empty());
+        assert!(!serde_json::to_string(&report).unwrap().contains("secret-project"));
+    }
+    
+    #[tokio::test]
+    async fn test_disabled_by_default() {
+        let reporter = TelemetryReporter::new(
+            TelemetryConfig { endpoint: Some("http://127.0.0.1:9".to_string()), ..TelemetryConfig::default() },
+            Arc::new(InMemoryVectorStore::new()),
+            Arc::new(UsageCounters::default()),
+        );
+        assert!(reporter.spawn().is_none());
+    }
+}
@@ -0,0 +1,237 @@
+//! Client-side rate limiting and request coalescing for remote embedding APIs.
+//!
+//! Embedding providers are synchronous, so concurrent callers (e.g. the
+//! tasks of a parallel ingest) each block on their own request. The
+//! [`Coalescer`] lets the first caller wait a short window for others to
+//! join its batch, then sends the whole batch as one API call and hands
+//! each caller its own embedding. The [`RateLimiter`] spaces out the calls
+//! that remain.
+
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use super::embedding::EmbeddingError;
+
+/// Spaces calls evenly to stay under a number of requests per minute
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn per_minute(requests: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(60) / requests.max(1),
+            next_slot: Mutex::new(None),
+        }
+    }
+    
+    /// Claim the next free slot, returning how long after `now` it starts
+    pub fn reserve(&self, now: Instant) -> Duration {
+        let mut next_slot = self.next_slot.lock().unwrap();
+        let slot = next_slot.map_or(now, |next| next.max(now));
+        *next_slot = Some(slot + self.interval);
+        slot - now
+    }
+    
+    /// Block until the caller may make a call
+    pub fn acquire(&self) {
+        let wait = self.reserve(Instant::now());
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// A batch that finished, waiting for its followers to collect their results
+struct Finished {
+    result: Result<Vec<Vec<f32>>, EmbeddingError>,
+    uncollected: usize,
+}
+
+#[derive(Default)]
+struct CoalesceState {
+    next_id: u64,
+    /// The batch new callers join, until it fills up or its leader sends it
+    accepting: Option<u64>,
+    open: HashMap<u64, Vec<String>>,
+    finished: HashMap<u64, Finished>,
+}
+
+/// Merges concurrent single-text requests into batched calls
+pub struct Coalescer {
+    window: Duration,
+    max_batch: usize,
+    state: Mutex<CoalesceState>,
+    finished: Condvar,
+}
+
+impl std::fmt::Debug for Coalescer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Coalescer")
+            .field("window", &self.window)
+            .field("max_batch", &self.max_batch)
+            .finish()
+    }
+}
+
+impl Coalescer {
+    /// Batch requests arriving within `window` of the first, up to `max_batch` texts
+    pub fn new(window: Duration, max_batch: usize) -> Self {
+        Self {
+            window,
+            max_batch: max_batch.max(1),
+            state: Mutex::new(CoalesceState::default()),
+            finished: Condvar::new(),
+        }
+    }
+    
+    /// Embed `text` as part of a batch sent by `send`
+    pub fn embed(
+        &self,
+        text: &str,
+        send: impl FnOnce(&[String]) -> Result<Vec<Vec<f32>>, EmbeddingError>,
+    ) -> Result<Vec<f32>, EmbeddingError> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(id) = state.accepting {
+            let batch = state.open.get_mut(&id).expect("accepting batch is open");
+            batch.push(text.to_string());
+            let position = batch.len() - 1;
+            if batch.len() >= self.max_batch {
+                state.accepting = None;
+            }
+            return self.collect(state, id, position);
+        }
+        
+        // Lead a new batch: give others the window to join, then send it
+        let id = state.next_id;
+        state.next_id += 1;
+        state.open.insert(id, vec![text.to_string()]);
+        state.accepting = (self.max_batch > 1).then_some(id);
+        drop(state);
+        if self.max_batch > 1 && !self.window.is_zero() {
+            std::thread::sleep(self.window);
+        }
+        
+        let texts = {
+            let mut state = self.state.lock().unwrap();
+            if state.accepting == Some(id) {
+                state.accepting = None;
+            }
+            state.open.remove(&id).expect("led batch is open")
+        };
+        let result = send(&texts).and_then(|embeddings| {
+            if embeddings.len() == texts.len() {
+                Ok(embeddings)
+            } else {
+                Err(EmbeddingError::GenerationError(format!("Got {} embeddings for {} texts", embeddings.len(), texts.len())))
+            }
+        });
+        
+        if texts.len() > 1 {
+            let mut state = self.state.lock().unwrap();
+            state.finished.insert(id, Finished {
+                result: result.as_ref().map(Clone::clone).map_err(duplicate),
+                uncollected: texts.len() - 1,
+            });
+            self.finished.notify_all();
+        }
+        result.map(|mut embeddings| embeddings.swap_remove(0))
+    }
+    
+    /// Wait for batch `id` to be sent and take the embedding at `position`
+    fn collect(
+        &self,
+        mut state: std::sync::MutexGuard<'_, CoalesceState>,
+        id: u64,
+        position: usize,
+    ) -> Result<Vec<f32>, EmbeddingError> {
+        while !state.finished.contains_key(&id) {
+            state = self.finished.wait(state).unwrap();
+        }
+        let finished = state.finished.get_mut(&id).expect("batch finished");
+        let own = match &finished.result {
+            Ok(embeddings) => Ok(embeddings[position].clone()),
+            Err(e) => Err(duplicate(e)),
+        };
+        finished.uncollected -= 1;
+        if finished.uncollected == 0 {
+            state.finished.remove(&id);
+        }
+        own
+    }
+}
+
+/// The same error again, for each caller of a failed batch
+fn duplicate(e: &EmbeddingError) -> EmbeddingError {
+    match e {
+        EmbeddingError::InitializationError(message) => EmbeddingError::InitializationError(message.clone()),
+        EmbeddingError::GenerationError(message) => EmbeddingError::GenerationError(message.clone()),
+        EmbeddingError::InvalidInputError(message) => EmbeddingError::InvalidInputError(message.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    
+    #[test]
+    fn test_rate_limiter_spaces_calls() {
+        let limiter = RateLimiter::per_minute(120);
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(limiter.reserve(now), Duration::from_millis(500));
+        assert_eq!(limiter.reserve(now), Duration::from_millis(1000));
+        // Idle time is not banked
+        let later = now + Duration::from_secs(10);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+    }
+    
+    #[test]
+    fn test_concurrent_requests_share_a_call() {
+        let coalescer = Arc::new(Coalescer::new(Duration::from_millis(200), 64));
+        let calls = Arc::new(AtomicUsize::new(0));
+        
+        let handles: Vec<_> = (1..=8)
+            .map(|length| {
+                let coalescer = coalescer.clone();
+                let calls = calls.clone();
+                std::thread::spawn(move || {
+                    coalescer.embed(&"x".repeat(length), |texts| {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        Ok(texts.iter().map(|text| vec![text.len() as f32]).collect())
+                    })
+                })
+            })
+            .collect();
+        for (length, handle) in (1..=8).zip(handles) {
+            assert_eq!(handle.join().unwrap().unwrap(), vec![length as f32]);
+        }
+        assert!(calls.load(Ordering::SeqCst) < 8);
+    }
+    
+    #[test]
+    fn test_batches_are_capped_and_errors_shared() {
+        let coalescer = Arc::new(Coalescer::new(Duration::from_millis(200), 2));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let coalescer = coalescer.clone();
+                std::thread::spawn(move || {
+                    coalescer.embed("text", |texts| {
+                        if texts.len() > 2 {
+                            return Ok(Vec::new());
+                        }
+                        Err(EmbeddingError::GenerationError("quota exceeded".to_string()))
+                    })
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().unwrap_err().to_string(), "Failed to generate embedding: quota exceeded");
+        }
+    }
+}
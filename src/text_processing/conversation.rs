@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+/// Default maximum size of a conversation chunk, in characters
+pub const DEFAULT_CONVERSATION_CHUNK_CHARS: usize = 2000;
+
+/// One message of a conversation transcript
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationMessage {
+    /// Who spoke, such as `user`, `assistant` or `tool`
+    pub role: String,
+    pub content: String,
+    /// When the message was sent, as given by the caller
+    #[serde(default)]
+    pub timestamp: Option<String>,
+}
+
+/// A run of consecutive turns stored as one entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversationChunk {
+    /// Index of the first turn in the chunk
+    pub turn_start: usize,
+    /// Index of the last turn in the chunk
+    pub turn_end: usize,
+    /// Distinct speakers in order of first appearance
+    pub speakers: Vec<String>,
+    /// Timestamp of the first turn, if it had one
+    pub started_at: Option<String>,
+    /// The turns rendered as `role: content`, one per paragraph
+    pub content: String,
+}
+
+fn render_turn(message: &ConversationMessage) -> String {
+    format!("{}: {}", message.role, message.content.trim())
+}
+
+/// Group consecutive turns into chunks of at most `max_chars` characters.
+///
+/// Turns are never split, so a single turn longer than `max_chars` becomes a
+/// chunk of its own. Turns with no content are skipped but keep their index.
+pub fn chunk_conversation(messages: &[ConversationMessage], max_chars: usize) -> Vec<ConversationChunk> {
+    let mut chunks = Vec::new();
+    let mut current: Option<ConversationChunk> = None;
+    
+    for (index, message) in messages.iter().enumerate() {
+        if message.content.trim().is_empty() {
+            continue;
+        }
+        
+        let turn = render_turn(message);
+        if let Some(chunk) = current.as_mut() {
+            if chunk.content.chars().count() + 2 + turn.chars().count() <= max_chars {
+                chunk.content.push_str("\n\n");
+                chunk.content.push_str(&turn);
+                chunk.turn_end = index;
+                if !chunk.speakers.contains(&message.role) {
+                    chunk.speakers.push(message.role.clone());
+                }
+                continue;
+            }
+            chunks.extend(current.take());
+        }
+        
+        current = Some(ConversationChunk {
+            turn_start: index,
+            turn_end: index,
+            speakers: vec![message.role.clone()],
+            started_at: message.timestamp.clone(),
+            content: turn,
+        });
+    }
+    
+    chunks.extend(current);
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn message(role: &str, content: &str) -> ConversationMessage {
+        ConversationMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: None,
+        }
+    }
+    
+    #[test]
+    fn test_groups_turns_up_to_limit() {
+        let messages = vec![
+            message("user", "How do I rotate keys?"),
+            message("assistant", "Run the reencrypt command."),
+            message("user", ""),
+            message("user", "Thanks, that worked and the old key can be removed now."),
+        ];
+        
+        let chunks = chunk_conversation(&messages, 80);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!((chunks[0].turn_start, chunks[0].turn_end), (0, 1));
+        assert_eq!(chunks[0].speakers, vec!["user", "assistant"]);
+        assert_eq!(chunks[0].content, "user: How do I rotate keys?\n\nassistant: Run the reencrypt command.");
+        assert_eq!((chunks[1].turn_start, chunks[1].turn_end), (3, 3));
+    }
+    
+    #[test]
+    fn test_long_turn_is_its_own_chunk() {
+        let long = "x".repeat(100);
+        let messages = vec![message("user", "hi"), message("assistant", &long), message("user", "ok")];
+        
+        let chunks = chunk_conversation(&messages, 20);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[1].turn_start, 1);
+        assert!(chunk_conversation(&[], 20).is_empty());
+    }
+}
@@ -2,6 +2,8 @@ use std::path::PathBuf;
 use thiserror::Error;
 use tracing::{info, error};
 
+use super::fallback::FallbackMetrics;
+
 /// A trait for embedding providers
 pub trait EmbeddingProvider {
     /// Generate an embedding for a single text
@@ -12,6 +14,36 @@ pub trait EmbeddingProvider {
     
     /// Get the dimensionality of the embeddings
     fn embedding_dim(&self) -> usize;
+    
+    /// Generate an embedding along with the name of the provider that made it,
+    /// for providers that delegate to one of several; `None` otherwise
+    fn generate_attributed(&self, text: &str) -> Result<(Vec<f32>, Option<String>), EmbeddingError> {
+        Ok((self.generate_embedding(text)?, None))
+    }
+    
+    /// Fallback counters of a provider chain; `None` for single providers
+    fn fallback_metrics(&self) -> Option<FallbackMetrics> {
+        None
+    }
+}
+
+/// Probe a provider for the dimensionality of the embeddings it actually produces.
+///
+/// Fails if the probe disagrees with the provider's declared `embedding_dim`,
+/// which usually means the configured model and dimension are out of sync.
+pub fn detect_embedding_dim<P: EmbeddingProvider + ?Sized>(provider: &P) -> Result<usize, EmbeddingError> {
+    let probe = provider.generate_embedding("p-mo embedding dimension probe")?;
+    let declared = provider.embedding_dim();
+    
+    if probe.len() != declared {
+        return Err(EmbeddingError::InitializationError(format!(
+            "Embedding provider declares dimension {} but produced {}",
+            declared,
+            probe.len()
+        )));
+    }
+    
+    Ok(probe.len())
 }
 
 #[cfg(feature = "embedding-generation")]
@@ -77,7 +109,8 @@ impl Default for EmbeddingConfig {
 }
 
 /// Types of embedding models supported
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum EmbeddingModelType {
     /// BERT base model
     Bert,
@@ -378,4 +411,32 @@ mod tests {
         assert_eq!(embeddings[0].len(), 384);
         assert_eq!(embeddings[1].len(), 384);
     }
+    
+    #[test]
+    fn test_detect_embedding_dim() {
+        let generator = MockEmbeddingGenerator::new(128);
+        assert_eq!(detect_embedding_dim(&generator).unwrap(), 128);
+    }
+    
+    #[test]
+    fn test_detect_embedding_dim_mismatch() {
+        struct MisconfiguredProvider;
+        
+        impl EmbeddingProvider for MisconfiguredProvider {
+            fn generate_embedding(&self, _text: &str) -> Result<Vec<f32>, EmbeddingError> {
+                Ok(vec![0.5; 768])
+            }
+            
+            fn generate_embeddings(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+                Ok(texts.iter().map(|_| vec![0.5; 768]).collect())
+            }
+            
+            fn embedding_dim(&self) -> usize {
+                384
+            }
+        }
+        
+        let err = detect_embedding_dim(&MisconfiguredProvider).unwrap_err();
+        assert!(err.to_string().contains("declares dimension 384 but produced 768"));
+    }
 }
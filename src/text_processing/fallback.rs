@@ -0,0 +1,276 @@
+//! Embedding with an ordered chain of providers.
+//!
+//! The first provider that succeeds embeds the text, so a local model can be
+//! backed by a remote API for when the model is missing or an API quota runs
+//! out. Every provider of a chain must produce the same dimension, since the
+//! vectors all land in the same collections.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use super::embedding::{EmbeddingConfig, EmbeddingError, EmbeddingGenerator, EmbeddingProvider};
+use super::remote::RemoteEmbeddingProvider;
+use crate::config::{EmbeddingProviderConfig, EmbeddingProvidersConfig, NetworkConfig};
+
+/// Counters of one provider in a chain
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ProviderMetrics {
+    pub name: String,
+    /// Calls this provider answered
+    pub served: u64,
+    /// Calls this provider failed, handing them to the next one
+    pub failures: u64,
+}
+
+/// A snapshot of a chain's counters
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FallbackMetrics {
+    /// Calls answered by a provider other than the first
+    pub fallbacks: u64,
+    pub providers: Vec<ProviderMetrics>,
+}
+
+struct ChainLink {
+    name: String,
+    provider: Arc<dyn EmbeddingProvider + Send + Sync>,
+    served: AtomicU64,
+    failures: AtomicU64,
+}
+
+/// An embedding provider trying several others in order
+pub struct FallbackEmbeddingProvider {
+    links: Vec<ChainLink>,
+    embedding_dim: usize,
+    fallbacks: AtomicU64,
+}
+
+impl FallbackEmbeddingProvider {
+    /// Chain `providers`, named for the entries they embed.
+    ///
+    /// Fails if there are none, or if they disagree on the dimension.
+    pub fn new(providers: Vec<(String, Arc<dyn EmbeddingProvider + Send + Sync>)>) -> Result<Self, EmbeddingError> {
+        let (first_name, first) = providers.first()
+            .ok_or_else(|| EmbeddingError::InitializationError("An embedding provider chain needs at least one provider".to_string()))?;
+        let embedding_dim = first.embedding_dim();
+        if let Some((name, provider)) = providers.iter().find(|(_, provider)| provider.embedding_dim() != embedding_dim) {
+            return Err(EmbeddingError::InitializationError(format!(
+                "Embedding provider {} produces {} dimensions but {} produces {}; a chain cannot mix dimensions",
+                name, provider.embedding_dim(), first_name, embedding_dim
+            )));
+        }
+        
+        let links = providers.into_iter()
+            .map(|(name, provider)| ChainLink { name, provider, served: AtomicU64::new(0), failures: AtomicU64::new(0) })
+            .collect();
+        Ok(Self { links, embedding_dim, fallbacks: AtomicU64::new(0) })
+    }
+    
+    /// Build the chain described by `config`, reaching remote providers through
+    /// `network`; `None` when it lists no providers
+    pub fn from_config(config: &EmbeddingProvidersConfig, network: &NetworkConfig) -> Result<Option<Self>, EmbeddingError> {
+        if config.providers.is_empty() {
+            return Ok(None);
+        }
+        let providers = config.providers.iter()
+            .map(|provider| Ok((provider.name().to_string(), build_provider(provider, network)?)))
+            .collect::<Result<Vec<_>, EmbeddingError>>()?;
+        Self::new(providers).map(Some)
+    }
+    
+    /// Names of the providers, in the order they are tried
+    pub fn provider_names(&self) -> impl Iterator<Item = &str> {
+        self.links.iter().map(|link| link.name.as_str())
+    }
+    
+    /// Ask each provider in turn until one returns `expected` vectors of the chain's dimension
+    fn first_success(
+        &self,
+        expected: usize,
+        embed: impl Fn(&dyn EmbeddingProvider) -> Result<Vec<Vec<f32>>, EmbeddingError>,
+    ) -> Result<(Vec<Vec<f32>>, &str), EmbeddingError> {
+        let mut errors = Vec::new();
+        for (position, link) in self.links.iter().enumerate() {
+            let outcome = embed(link.provider.as_ref()).and_then(|embeddings| {
+                if embeddings.len() != expected || embeddings.iter().any(|embedding| embedding.len() != self.embedding_dim) {
+                    return Err(EmbeddingError::GenerationError(format!(
+                        "expected {} embeddings of dimension {}", expected, self.embedding_dim
+                    )));
+                }
+                Ok(embeddings)
+            });
+            
+            match outcome {
+                Ok(embeddings) => {
+                    link.served.fetch_add(1, Ordering::Relaxed);
+                    if position > 0 {
+                        self.fallbacks.fetch_add(1, Ordering::Relaxed);
+                    }
+                    return Ok((embeddings, &link.name));
+                },
+                // Bad input fails the same way everywhere
+                Err(e @ EmbeddingError::InvalidInputError(_)) => return Err(e),
+                Err(e) => {
+                    link.failures.fetch_add(1, Ordering::Relaxed);
+                    if let Some(next) = self.links.get(position + 1) {
+                        warn!("Embedding provider {} failed, falling back to {}: {}", link.name, next.name, e);
+                    }
+                    errors.push(format!("{}: {}", link.name, e));
+                },
+            }
+        }
+        Err(EmbeddingError::GenerationError(format!("All embedding providers failed ({})", errors.join("; "))))
+    }
+}
+
+impl EmbeddingProvider for FallbackEmbeddingProvider {
+    fn generate_embedding(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        self.generate_attributed(text).map(|(embedding, _)| embedding)
+    }
+    
+    fn generate_embeddings(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        self.first_success(texts.len(), |provider| provider.generate_embeddings(texts))
+            .map(|(embeddings, _)| embeddings)
+    }
+    
+    fn embedding_dim(&self) -> usize {
+        self.embedding_dim
+    }
+    
+    fn generate_attributed(&self, text: &str) -> Result<(Vec<f32>, Option<String>), EmbeddingError> {
+        let (mut embeddings, name) = self.first_success(1, |provider| provider.generate_embedding(text).map(|embedding| vec![embedding]))?;
+        Ok((embeddings.remove(0), Some(name.to_string())))
+    }
+    
+    fn fallback_metrics(&self) -> Option<FallbackMetrics> {
+        Some(FallbackMetrics {
+            fallbacks: self.fallbacks.load(Ordering::Relaxed),
+            providers: self.links.iter()
+                .map(|link| ProviderMetrics {
+                    name: link.name.clone(),
+                    served: link.served.load(Ordering::Relaxed),
+                    failures: link.failures.load(Ordering::Relaxed),
+                })
+                .collect(),
+        })
+    }
+}
+
+fn build_provider(config: &EmbeddingProviderConfig, network: &NetworkConfig) -> Result<Arc<dyn EmbeddingProvider + Send + Sync>, EmbeddingError> {
+    match config {
+        EmbeddingProviderConfig::Local { model, model_path, dimension, use_gpu, .. } => {
+            let generator = EmbeddingGenerator::new(EmbeddingConfig {
+                model_type: *model,
+                model_path: model_path.clone(),
+                use_gpu: *use_gpu,
+                embedding_dim: *dimension,
+            })?;
+            Ok(Arc::new(generator))
+        },
+        EmbeddingProviderConfig::Remote {
+            name, url, model, api_key_env, dimension, timeout_secs, requests_per_minute, coalesce_window_ms, max_batch_size,
+        } => {
+            let mut provider = RemoteEmbeddingProvider::new(url.clone(), model.clone(), *dimension, network)?
+                .with_timeout(Duration::from_secs(*timeout_secs))
+                .with_coalescing(Duration::from_millis(*coalesce_window_ms), *max_batch_size);
+            if let Some(requests_per_minute) = requests_per_minute {
+                provider = provider.with_rate_limit(*requests_per_minute);
+            }
+            if let Some(variable) = api_key_env {
+                let key = std::env::var(variable).map_err(|_| EmbeddingError::InitializationError(format!(
+                    "Embedding provider {} needs an API key in ${}", name, variable
+                )))?;
+                provider = provider.with_api_key(key);
+            }
+            Ok(Arc::new(provider))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_processing::embedding::MockEmbeddingGenerator;
+    
+    /// A provider that is always down
+    struct Unavailable(usize);
+    
+    impl EmbeddingProvider for Unavailable {
+        fn generate_embedding(&self, _text: &str) -> Result<Vec<f32>, EmbeddingError> {
+            Err(EmbeddingError::InitializationError("model not found".to_string()))
+        }
+        
+        fn generate_embeddings(&self, _texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+            Err(EmbeddingError::InitializationError("model not found".to_string()))
+        }
+        
+        fn embedding_dim(&self) -> usize {
+            self.0
+        }
+    }
+    
+    fn named(name: &str, provider: impl EmbeddingProvider + Send + Sync + 'static) -> (String, Arc<dyn EmbeddingProvider + Send + Sync>) {
+        (name.to_string(), Arc::new(provider))
+    }
+    
+    #[test]
+    fn test_falls_back_and_attributes() {
+        let chain = FallbackEmbeddingProvider::new(vec![
+            named("local", Unavailable(4)),
+            named("remote", MockEmbeddingGenerator::new(4)),
+        ]).unwrap();
+        
+        let (embedding, provider) = chain.generate_attributed("hello").unwrap();
+        assert_eq!(embedding.len(), 4);
+        assert_eq!(provider.as_deref(), Some("remote"));
+        assert_eq!(chain.generate_embeddings(&["a".to_string(), "b".to_string()]).unwrap().len(), 2);
+        
+        let metrics = chain.fallback_metrics().unwrap();
+        assert_eq!(metrics.fallbacks, 2);
+        assert_eq!(metrics.providers[0], ProviderMetrics { name: "local".to_string(), served: 0, failures: 2 });
+        assert_eq!(metrics.providers[1].served, 2);
+    }
+    
+    #[test]
+    fn test_all_failing() {
+        let chain = FallbackEmbeddingProvider::new(vec![named("a", Unavailable(4)), named("b", Unavailable(4))]).unwrap();
+        let err = chain.generate_embedding("hello").unwrap_err();
+        assert!(err.to_string().contains("a: Failed to initialize embedding model: model not found"));
+        assert!(err.to_string().contains("b: "));
+    }
+    
+    #[test]
+    fn test_refuses_mixed_dimensions() {
+        let err = FallbackEmbeddingProvider::new(vec![
+            named("local", MockEmbeddingGenerator::new(384)),
+            named("remote", MockEmbeddingGenerator::new(1536)),
+        ]).err().unwrap();
+        assert!(err.to_string().contains("remote produces 1536 dimensions but local produces 384"));
+        assert!(FallbackEmbeddingProvider::new(Vec::new()).is_err());
+    }
+    
+    #[test]
+    #[cfg(not(feature = "embedding-generation"))]
+    fn test_from_config() {
+        assert!(FallbackEmbeddingProvider::from_config(&EmbeddingProvidersConfig::default(), &NetworkConfig::default()).unwrap().is_none());
+        
+        let config: EmbeddingProvidersConfig = toml::from_str(r#"
+            [[providers]]
+            kind = "local"
+            name = "minilm"
+            dimension = 384
+            
+            [[providers]]
+            kind = "remote"
+            name = "api"
+            url = "http://127.0.0.1:9/v1/embeddings"
+            model = "small"
+            dimension = 384
+        "#).unwrap();
+        let chain = FallbackEmbeddingProvider::from_config(&config, &NetworkConfig::default()).unwrap().unwrap();
+        assert_eq!(chain.provider_names().collect::<Vec<_>>(), vec!["minilm", "api"]);
+        assert_eq!(chain.embedding_dim(), 384);
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use crate::config::LanguageConfig;
+
+/// The language detected for a piece of text
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedLanguage {
+    /// ISO 639-3 code, e.g. `eng` or `deu`
+    pub code: String,
+    /// Detection confidence between 0 and 1
+    pub confidence: f64,
+    /// Whether the detector considers the result reliable
+    pub reliable: bool,
+}
+
+/// Detect the language of `text`
+pub fn detect_language(text: &str) -> Option<DetectedLanguage> {
+    whatlang::detect(text).map(|info| DetectedLanguage {
+        code: info.lang().code().to_string(),
+        confidence: info.confidence(),
+        reliable: info.is_reliable(),
+    })
+}
+
+/// Decides which language, if any, to record for content and where to route it
+#[derive(Debug, Clone)]
+pub struct LanguageRouter {
+    detect: bool,
+    min_confidence: f64,
+    route: bool,
+    collection_template: String,
+    languages: Vec<String>,
+    overrides: HashMap<String, String>,
+}
+
+impl Default for LanguageRouter {
+    fn default() -> Self {
+        Self::from_config(&LanguageConfig::default())
+    }
+}
+
+impl LanguageRouter {
+    /// Create a router from configuration
+    pub fn from_config(config: &LanguageConfig) -> Self {
+        Self {
+            detect: config.detect,
+            min_confidence: config.min_confidence,
+            route: config.route,
+            collection_template: config.collection_template.clone(),
+            languages: config.languages.clone(),
+            overrides: config.overrides.clone(),
+        }
+    }
+    
+    /// Detect the language of `text` if detection is enabled and confident enough
+    pub fn detect(&self, text: &str) -> Option<String> {
+        if !self.detect {
+            return None;
+        }
+        
+        detect_language(text)
+            .filter(|language| language.confidence >= self.min_confidence)
+            .map(|language| language.code)
+    }
+    
+    /// Whether entries and queries are routed to language-specific collections
+    pub fn routes(&self) -> bool {
+        self.route
+    }
+    
+    /// The collection content in `language` should go to when it targets `collection`
+    pub fn route(&self, collection: &str, language: Option<&str>) -> String {
+        let language = match language {
+            Some(language) if self.route => language,
+            _ => return collection.to_string(),
+        };
+        
+        if !self.languages.is_empty() && !self.languages.iter().any(|l| l == language) {
+            return collection.to_string();
+        }
+        
+        let key = format!("{}.{}", collection, language);
+        if let Some(target) = self.overrides.get(&key).or_else(|| self.overrides.get(language)) {
+            return target.clone();
+        }
+        
+        self.collection_template
+            .replace("{collection}", collection)
+            .replace("{lang}", language)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn routing_config() -> LanguageConfig {
+        LanguageConfig {
+            route: true,
+            languages: vec!["deu".to_string(), "fra".to_string()],
+            overrides: [("docs.fra".to_string(), "docs-french".to_string())].into_iter().collect(),
+            ..LanguageConfig::default()
+        }
+    }
+    
+    #[test]
+    fn test_detect_language() {
+        let detected = detect_language("Der schnelle braune Fuchs springt über den faulen Hund und läuft weiter").unwrap();
+        assert_eq!(detected.code, "deu");
+        
+        let detected = detect_language("The quick brown fox jumps over the lazy dog and keeps running").unwrap();
+        assert_eq!(detected.code, "eng");
+    }
+    
+    #[test]
+    fn test_routing_disabled_by_default() {
+        let router = LanguageRouter::default();
+        assert_eq!(router.route("docs", Some("deu")), "docs");
+    }
+    
+    #[test]
+    fn test_route_uses_template_and_overrides() {
+        let router = LanguageRouter::from_config(&routing_config());
+        assert_eq!(router.route("docs", Some("deu")), "docs_deu");
+        assert_eq!(router.route("docs", Some("fra")), "docs-french");
+        assert_eq!(router.route("docs", Some("eng")), "docs");
+        assert_eq!(router.route("docs", None), "docs");
+    }
+    
+    #[test]
+    fn test_detection_can_be_disabled() {
+        let config = LanguageConfig { detect: false, ..LanguageConfig::default() };
+        let router = LanguageRouter::from_config(&config);
+        assert_eq!(router.detect("Der schnelle braune Fuchs springt über den faulen Hund"), None);
+    }
+}
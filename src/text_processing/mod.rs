@@ -1,7 +1,34 @@
 mod pure;
+pub mod conversation;
+pub mod batching;
 pub mod embedding;
+pub mod fallback;
+pub mod language;
+pub mod models;
+pub mod packing;
+pub mod pii;
+pub mod query_cache;
+pub mod remote;
+pub mod rerank;
+pub mod sanitize;
+pub mod secrets;
+pub mod stemming;
+pub mod tokens;
 pub use pure::*;
-pub use embedding::{EmbeddingProvider, EmbeddingError, EmbeddingGenerator, EmbeddingConfig, EmbeddingModelType};
+pub use conversation::{chunk_conversation, ConversationChunk, ConversationMessage};
+pub use embedding::{EmbeddingProvider, EmbeddingError, EmbeddingGenerator, EmbeddingConfig, EmbeddingModelType, detect_embedding_dim};
+pub use fallback::{FallbackEmbeddingProvider, FallbackMetrics, ProviderMetrics};
+pub use remote::RemoteEmbeddingProvider;
+pub use query_cache::{normalize_query, QueryCacheMetrics, QueryEmbeddingCache};
+pub use rerank::{rerank_order, CrossEncoderReranker, Reranker, DEFAULT_RERANK_CANDIDATES};
+pub use language::{detect_language, DetectedLanguage, LanguageRouter};
+pub use pii::{detect_pii, mask_pii, PiiKind, PiiMatch, PiiPolicy};
+pub use sanitize::{assess_injection, injection_signals, sanitize, sanitize_bytes, InjectionRisk, SafeModePolicy, Sanitized, SanitizeError};
+pub use secrets::{scan_secrets, SecretFinding};
+pub use stemming::{stemmed_words, stemmer_for, StemmedWord};
+pub use models::{ModelInfo, ModelRegistry};
+pub use packing::{pack_context, ContextChunk, PackedContext};
+pub use tokens::{count_tokens, TokenEncoding};
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -49,7 +76,8 @@ impl Default for TokenizerConfig {
 }
 
 /// Chunking strategy for text processing
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ChunkingStrategy {
     /// Fixed size chunking with a maximum number of tokens per chunk
     FixedSize(usize),
@@ -61,6 +89,16 @@ pub enum ChunkingStrategy {
     Semantic,
 }
 
+impl std::fmt::Display for ChunkingStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkingStrategy::FixedSize(max_tokens) => write!(f, "fixed_size({})", max_tokens),
+            ChunkingStrategy::Paragraph => write!(f, "paragraph"),
+            ChunkingStrategy::Semantic => write!(f, "semantic"),
+        }
+    }
+}
+
 /// A text processor for tokenization, chunking, and metadata extraction
 #[derive(Debug, Clone)]
 pub struct TextProcessor {
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::tokens::TokenEncoding;
+
+/// What the context packer needs to know about a model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// Total tokens the model accepts, prompt and completion together
+    pub context_window: usize,
+    /// The tokenizer used to count tokens for the model
+    #[serde(default)]
+    pub encoding: TokenEncoding,
+}
+
+impl ModelInfo {
+    pub fn new(context_window: usize, encoding: TokenEncoding) -> Self {
+        Self { context_window, encoding }
+    }
+}
+
+/// Known models and their context windows
+#[derive(Debug, Clone)]
+pub struct ModelRegistry {
+    models: HashMap<String, ModelInfo>,
+    default_model: String,
+}
+
+/// The model assumed when a request does not name one
+pub const DEFAULT_MODEL: &str = "gpt-4o";
+
+impl Default for ModelRegistry {
+    fn default() -> Self {
+        let builtin = [
+            ("gpt-4o", 128_000, TokenEncoding::O200kBase),
+            ("gpt-4o-mini", 128_000, TokenEncoding::O200kBase),
+            ("gpt-4-turbo", 128_000, TokenEncoding::Cl100kBase),
+            ("gpt-4", 8_192, TokenEncoding::Cl100kBase),
+            ("gpt-3.5-turbo", 16_385, TokenEncoding::Cl100kBase),
+            ("claude-3-5-sonnet", 200_000, TokenEncoding::Cl100kBase),
+            ("claude-3-opus", 200_000, TokenEncoding::Cl100kBase),
+            ("claude-3-haiku", 200_000, TokenEncoding::Cl100kBase),
+            ("llama-3-8b", 8_192, TokenEncoding::Cl100kBase),
+            ("llama-3.1-70b", 128_000, TokenEncoding::Cl100kBase),
+        ];
+        
+        Self {
+            models: builtin.iter()
+                .map(|(name, window, encoding)| (name.to_string(), ModelInfo::new(*window, *encoding)))
+                .collect(),
+            default_model: DEFAULT_MODEL.to_string(),
+        }
+    }
+}
+
+impl ModelRegistry {
+    /// Create a registry from configuration
+    pub fn from_config(config: &crate::config::ModelsConfig) -> Self {
+        let mut registry = Self::with_overrides(&config.overrides);
+        registry.set_default_model(&config.default_model);
+        registry
+    }
+    
+    /// The built-in models extended or overridden by `models`
+    pub fn with_overrides(models: &HashMap<String, ModelInfo>) -> Self {
+        let mut registry = Self::default();
+        for (name, info) in models {
+            registry.register(name, info.clone());
+        }
+        registry
+    }
+    
+    /// Add or replace a model
+    pub fn register(&mut self, name: &str, info: ModelInfo) {
+        self.models.insert(name.to_string(), info);
+    }
+    
+    /// Use `name` when a request does not specify a model
+    pub fn set_default_model(&mut self, name: &str) {
+        self.default_model = name.to_string();
+    }
+    
+    pub fn default_model(&self) -> &str {
+        &self.default_model
+    }
+    
+    /// Look up a model by exact name, or by the longest registered prefix
+    /// so that dated variants such as `gpt-4o-2024-08-06` resolve.
+    pub fn get(&self, name: &str) -> Option<&ModelInfo> {
+        self.models.get(name).or_else(|| {
+            self.models.iter()
+                .filter(|(known, _)| name.starts_with(known.as_str()))
+                .max_by_key(|(known, _)| known.len())
+                .map(|(_, info)| info)
+        })
+    }
+    
+    /// Names of all known models, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.models.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_lookup_by_prefix() {
+        let registry = ModelRegistry::default();
+        assert_eq!(registry.get("gpt-4").unwrap().context_window, 8_192);
+        assert_eq!(registry.get("gpt-4o-2024-08-06").unwrap().context_window, 128_000);
+        assert_eq!(registry.get("gpt-4-0613").unwrap().context_window, 8_192);
+        assert!(registry.get("unknown-model").is_none());
+    }
+    
+    #[test]
+    fn test_overrides() {
+        let overrides = [("local-model".to_string(), ModelInfo::new(4_096, TokenEncoding::Cl100kBase))]
+            .into_iter()
+            .collect();
+        let registry = ModelRegistry::with_overrides(&overrides);
+        assert_eq!(registry.get("local-model").unwrap().context_window, 4_096);
+        assert!(registry.names().contains(&"gpt-4o".to_string()));
+    }
+}
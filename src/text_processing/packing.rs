@@ -0,0 +1,115 @@
+use super::tokens::TokenEncoding;
+
+/// Separator placed between packed chunks
+pub const CHUNK_SEPARATOR: &str = "\n\n";
+
+/// A candidate chunk for the context window
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextChunk {
+    pub id: String,
+    pub content: String,
+    pub score: f32,
+}
+
+/// A chunk that made it into the packed context
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackedChunk {
+    pub id: String,
+    pub score: f32,
+    pub tokens: usize,
+}
+
+/// The result of packing chunks into a token budget
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackedContext {
+    /// The assembled context, chunks in descending score order
+    pub text: String,
+    /// The exact token count of `text`
+    pub tokens_used: usize,
+    pub chunks: Vec<PackedChunk>,
+    /// Candidates left out because they did not fit
+    pub omitted: usize,
+}
+
+/// Greedily pack chunks by descending score into at most `budget` tokens.
+///
+/// Chunks that do not fit are skipped while smaller, lower-scored ones are
+/// still tried. The returned token count is measured on the assembled text.
+pub fn pack_context(chunks: &[ContextChunk], budget: usize, encoding: TokenEncoding) -> PackedContext {
+    let mut candidates: Vec<&ContextChunk> = chunks.iter().collect();
+    candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    
+    let separator_tokens = encoding.count(CHUNK_SEPARATOR);
+    let mut selected: Vec<(&ContextChunk, usize)> = Vec::new();
+    let mut estimated = 0;
+    
+    for chunk in candidates {
+        let tokens = encoding.count(&chunk.content);
+        let cost = if selected.is_empty() { tokens } else { tokens + separator_tokens };
+        if estimated + cost <= budget {
+            selected.push((chunk, tokens));
+            estimated += cost;
+        }
+    }
+    
+    // Token boundaries can merge across separators, so confirm on the final text
+    let (text, tokens_used) = loop {
+        let text = selected.iter()
+            .map(|(chunk, _)| chunk.content.as_str())
+            .collect::<Vec<&str>>()
+            .join(CHUNK_SEPARATOR);
+        let tokens_used = encoding.count(&text);
+        if tokens_used <= budget || selected.is_empty() {
+            break (text, tokens_used);
+        }
+        selected.pop();
+    };
+    
+    PackedContext {
+        text,
+        tokens_used,
+        omitted: chunks.len() - selected.len(),
+        chunks: selected.into_iter()
+            .map(|(chunk, tokens)| PackedChunk {
+                id: chunk.id.clone(),
+                score: chunk.score,
+                tokens,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn chunk(id: &str, words: usize, score: f32) -> ContextChunk {
+        ContextChunk {
+            id: id.to_string(),
+            content: vec!["word"; words].join(" "),
+            score,
+        }
+    }
+    
+    #[test]
+    fn test_packs_by_score_within_budget() {
+        let encoding = TokenEncoding::Cl100kBase;
+        let chunks = vec![chunk("low", 10, 0.1), chunk("high", 10, 0.9), chunk("big", 100, 0.5)];
+        
+        let packed = pack_context(&chunks, 25, encoding);
+        let ids: Vec<&str> = packed.chunks.iter().map(|c| c.id.as_str()).collect();
+        
+        assert_eq!(ids, vec!["high", "low"]);
+        assert_eq!(packed.omitted, 1);
+        assert!(packed.tokens_used <= 25);
+        assert_eq!(packed.tokens_used, encoding.count(&packed.text));
+    }
+    
+    #[test]
+    fn test_empty_budget() {
+        let packed = pack_context(&[chunk("a", 5, 1.0)], 0, TokenEncoding::Cl100kBase);
+        assert!(packed.chunks.is_empty());
+        assert_eq!(packed.tokens_used, 0);
+        assert_eq!(packed.omitted, 1);
+    }
+}
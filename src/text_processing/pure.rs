@@ -225,6 +225,62 @@ pub fn summarize_text(text: &str, max_sentences: usize) -> String {
     summary + "."
 }
 
+/// Maximum length of a generated title in characters
+pub const MAX_TITLE_CHARS: usize = 80;
+
+/// Generate a title for untitled content.
+///
+/// Uses the first Markdown heading if there is one, otherwise a one-sentence
+/// extractive summary of the first paragraph. Returns `None` for blank content.
+pub fn generate_title(content: &str) -> Option<String> {
+    let heading = content.lines()
+        .filter_map(|line| markdown_heading(line.trim()))
+        .find(|heading| !heading.is_empty());
+    
+    let title = match heading {
+        Some(heading) => heading.to_string(),
+        None => {
+            let first_chunk = content.split("\n\n")
+                .map(|paragraph| paragraph.trim())
+                .find(|paragraph| !paragraph.is_empty())?;
+            let summary = summarize_text(first_chunk, 1);
+            summary.trim_end_matches('.').trim().to_string()
+        }
+    };
+    
+    let title = title.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if title.is_empty() {
+        return None;
+    }
+    
+    Some(truncate_title(&title, MAX_TITLE_CHARS))
+}
+
+/// The text of an ATX heading such as `## Setup`; `#include`, `#!/bin/sh`
+/// and `#hashtag` lines are not headings
+fn markdown_heading(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+    if !(1..=6).contains(&level) || !(text.is_empty() || text.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some(text.trim().trim_end_matches('#').trim())
+}
+
+/// Shorten a title to `max_chars`, breaking at a word boundary where possible
+fn truncate_title(title: &str, max_chars: usize) -> String {
+    if title.chars().count() <= max_chars {
+        return title.to_string();
+    }
+    
+    let cut: String = title.chars().take(max_chars - 1).collect();
+    let cut = match cut.rfind(' ') {
+        Some(idx) if idx > max_chars / 2 => cut[..idx].to_string(),
+        _ => cut,
+    };
+    format!("{}…", cut.trim_end())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -286,4 +342,36 @@ mod tests {
         assert!(summary.contains("Artificial intelligence"));
         assert!(summary.split(". ").count() <= 3); // 2 sentences + possible trailing period
     }
+    
+    #[test]
+    fn test_generate_title_from_heading() {
+        let content = "Some preamble\n\n## Deploying  the service\n\nSteps follow.";
+        assert_eq!(generate_title(content), Some("Deploying the service".to_string()));
+    }
+    
+    #[test]
+    fn test_generate_title_from_summary() {
+        let content = "Backups run nightly. They are kept for a week.\n\nRestores are manual.";
+        let title = generate_title(content).unwrap();
+        assert!(!title.ends_with('.'));
+        assert_eq!(title, "Backups run nightly");
+    }
+    
+    #[test]
+    fn test_generate_title_ignores_non_heading_hashes() {
+        assert_eq!(generate_title("#deploy friday releases\n\nThey go out at noon.").unwrap(), "#deploy friday releases");
+        assert_eq!(generate_title("#include <stdio.h>\n# Reading input\nUse fgets.").unwrap(), "Reading input");
+        
+        assert_eq!(generate_title("#!/bin/sh\n## Release steps ##\nTag the commit.").unwrap(), "Release steps");
+    }
+    
+    #[test]
+    fn test_generate_title_truncates_and_rejects_blank() {
+        let long = "word ".repeat(40);
+        let title = generate_title(&long).unwrap();
+        assert!(title.chars().count() <= MAX_TITLE_CHARS);
+        assert!(title.ends_with('…'));
+        
+        assert_eq!(generate_title("  \n\n "), None);
+    }
 }
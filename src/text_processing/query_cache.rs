@@ -0,0 +1,163 @@
+//! Reusing the embeddings of recent search queries.
+//!
+//! Agents working on the same task often send the same query within seconds
+//! of each other. The cache keeps each query's embedding for a short time,
+//! keyed by the model that made it and the query text normalized, so the
+//! repeats skip the embedding model. It holds query vectors only; entries
+//! and search results are never cached.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::EmbeddingError;
+use crate::config::QueryCacheConfig;
+
+/// A snapshot of a cache's counters
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QueryCacheMetrics {
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that had to embed the query
+    pub misses: u64,
+    /// Embeddings held, including expired ones not yet dropped
+    pub entries: usize,
+}
+
+impl QueryCacheMetrics {
+    /// Share of lookups answered from the cache, from 0 to 1
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 }
+    }
+}
+
+struct CachedEmbedding {
+    embedding: Vec<f32>,
+    made_at: Instant,
+    /// Orders entries by age when `made_at` cannot tell them apart
+    sequence: u64,
+}
+
+/// Query embeddings by model and normalized query text
+pub struct QueryEmbeddingCache {
+    entries: Mutex<HashMap<(String, String), CachedEmbedding>>,
+    ttl: Duration,
+    max_entries: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl QueryEmbeddingCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+            max_entries: max_entries.max(1),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+    
+    /// The cache the `[query_cache]` settings describe; `None` when it is disabled
+    pub fn from_config(config: &QueryCacheConfig) -> Option<Self> {
+        config.enabled.then(|| Self::new(Duration::from_secs(config.ttl_secs), config.max_entries))
+    }
+    
+    /// The embedding of `query` under `model`, from the cache when a fresh
+    /// one is there and from `embed` otherwise
+    pub fn get_or_embed(
+        &self,
+        model: &str,
+        query: &str,
+        embed: impl FnOnce(&str) -> Result<Vec<f32>, EmbeddingError>,
+    ) -> Result<Vec<f32>, EmbeddingError> {
+        let key = (model.to_string(), normalize_query(query));
+        if let Some(cached) = self.entries.lock().unwrap().get(&key) {
+            if cached.made_at.elapsed() < self.ttl {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(cached.embedding.clone());
+            }
+        }
+        let sequence = self.misses.fetch_add(1, Ordering::Relaxed);
+        
+        // Embed without holding the lock; concurrent misses for one query both embed it
+        let embedding = embed(query)?;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let ttl = self.ttl;
+            entries.retain(|_, cached| cached.made_at.elapsed() < ttl);
+            if entries.len() >= self.max_entries {
+                let oldest = entries.iter()
+                    .min_by_key(|(_, cached)| cached.sequence)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, CachedEmbedding { embedding: embedding.clone(), made_at: Instant::now(), sequence });
+        Ok(embedding)
+    }
+    
+    pub fn metrics(&self) -> QueryCacheMetrics {
+        QueryCacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+}
+
+/// `query` trimmed, lowercased and with runs of whitespace made single spaces
+pub fn normalize_query(query: &str) -> String {
+    query.split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    
+    #[test]
+    fn test_repeated_queries_hit_the_cache() {
+        let cache = QueryEmbeddingCache::new(Duration::from_secs(60), 10);
+        let calls = Cell::new(0);
+        let embed = |_: &str| {
+            calls.set(calls.get() + 1);
+            Ok(vec![calls.get() as f32])
+        };
+        
+        assert_eq!(cache.get_or_embed("minilm", "Key rotation", embed).unwrap(), [1.0]);
+        assert_eq!(cache.get_or_embed("minilm", "  key   ROTATION ", embed).unwrap(), [1.0]);
+        // Another model's vectors are kept apart
+        assert_eq!(cache.get_or_embed("mpnet", "key rotation", embed).unwrap(), [2.0]);
+        assert_eq!(calls.get(), 2);
+        
+        let metrics = cache.metrics();
+        assert_eq!((metrics.hits, metrics.misses, metrics.entries), (1, 2, 2));
+        assert!((metrics.hit_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_expired_and_evicted_entries_are_embedded_again() {
+        let cache = QueryEmbeddingCache::new(Duration::ZERO, 10);
+        cache.get_or_embed("minilm", "backups", |_| Ok(vec![1.0])).unwrap();
+        assert_eq!(cache.get_or_embed("minilm", "backups", |_| Ok(vec![2.0])).unwrap(), [2.0]);
+        
+        let cache = QueryEmbeddingCache::new(Duration::from_secs(60), 2);
+        for query in ["a", "b", "c"] {
+            cache.get_or_embed("minilm", query, |_| Ok(vec![0.0])).unwrap();
+        }
+        assert_eq!(cache.metrics().entries, 2);
+        cache.get_or_embed("minilm", "a", |_| Ok(vec![0.0])).unwrap();
+        assert_eq!(cache.metrics().misses, 4);
+        
+        assert!(cache.get_or_embed("minilm", "d", |_| Err(EmbeddingError::GenerationError("down".to_string()))).is_err());
+    }
+}
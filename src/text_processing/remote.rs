@@ -0,0 +1,219 @@
+//! Embeddings from an OpenAI-compatible HTTP endpoint.
+
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::batching::{Coalescer, RateLimiter};
+use super::embedding::{EmbeddingError, EmbeddingProvider};
+use crate::config::NetworkConfig;
+use crate::network;
+
+/// How long a request waits for concurrent ones to join its batch by default
+pub const DEFAULT_COALESCE_WINDOW: Duration = Duration::from_millis(10);
+
+/// Most texts sent in one call by default
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 64;
+
+/// An embedding provider calling a remote `/embeddings` endpoint
+#[derive(Debug, Clone)]
+pub struct RemoteEmbeddingProvider {
+    client: reqwest::blocking::Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+    embedding_dim: usize,
+    timeout: Duration,
+    max_batch_size: usize,
+    coalescer: Arc<Coalescer>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    #[serde(default)]
+    index: Option<usize>,
+}
+
+impl RemoteEmbeddingProvider {
+    /// A provider sending its requests through the `[network]` proxy and CAs
+    pub fn new(url: impl Into<String>, model: impl Into<String>, embedding_dim: usize, network: &NetworkConfig) -> Result<Self, EmbeddingError> {
+        let client = network::blocking_http_client(network)
+            .map_err(|e| EmbeddingError::InitializationError(e.to_string()))?;
+        Ok(Self {
+            client,
+            url: url.into(),
+            model: model.into(),
+            api_key: None,
+            embedding_dim,
+            timeout: Duration::from_secs(30),
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+            coalescer: Arc::new(Coalescer::new(DEFAULT_COALESCE_WINDOW, DEFAULT_MAX_BATCH_SIZE)),
+            rate_limiter: None,
+        })
+    }
+    
+    /// Send `key` as a bearer token
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+    
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    
+    /// Send concurrent single-text requests arriving within `window` as one
+    /// call of at most `max_batch_size` texts; a zero window turns this off
+    pub fn with_coalescing(mut self, window: Duration, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self.coalescer = Arc::new(Coalescer::new(window, self.max_batch_size));
+        self
+    }
+    
+    /// Make at most `requests_per_minute` calls, waiting for a free slot before each
+    pub fn with_rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.rate_limiter = Some(Arc::new(RateLimiter::per_minute(requests_per_minute)));
+        self
+    }
+    
+    fn request(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        let mut request = self.client.post(&self.url)
+            .timeout(self.timeout)
+            .json(&json!({"model": self.model, "input": texts}));
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        
+        let response = request.send()
+            .map_err(|e| EmbeddingError::GenerationError(format!("{}: {}", self.url, e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().unwrap_or_default();
+            return Err(EmbeddingError::GenerationError(format!("{} returned {}: {}", self.url, status, body.trim())));
+        }
+        let mut body: EmbeddingsResponse = response.json()
+            .map_err(|e| EmbeddingError::GenerationError(format!("Unexpected response from {}: {}", self.url, e)))?;
+        
+        body.data.sort_by_key(|data| data.index);
+        if body.data.len() != texts.len() {
+            return Err(EmbeddingError::GenerationError(format!(
+                "{} returned {} embeddings for {} texts", self.url, body.data.len(), texts.len()
+            )));
+        }
+        Ok(body.data.into_iter().map(|data| data.embedding).collect())
+    }
+    
+    /// Make the blocking request on a thread of its own, since the blocking
+    /// client must not run (or be dropped) on an async runtime's thread
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire();
+        }
+        std::thread::scope(|scope| {
+            scope.spawn(|| self.request(texts))
+                .join()
+                .unwrap_or_else(|_| Err(EmbeddingError::GenerationError("Embedding request panicked".to_string())))
+        })
+    }
+}
+
+impl EmbeddingProvider for RemoteEmbeddingProvider {
+    fn generate_embedding(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        if text.trim().is_empty() {
+            return Err(EmbeddingError::InvalidInputError("Empty text provided".to_string()));
+        }
+        self.coalescer.embed(text, |texts| self.embed(texts))
+    }
+    
+    fn generate_embeddings(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbeddingError> {
+        if texts.is_empty() {
+            return Err(EmbeddingError::InvalidInputError("Empty texts provided".to_string()));
+        }
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.max_batch_size) {
+            embeddings.extend(self.embed(batch)?);
+        }
+        Ok(embeddings)
+    }
+    
+    fn embedding_dim(&self) -> usize {
+        self.embedding_dim
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::Value;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    
+    /// Serve an embeddings endpoint answering `[text length, 1.0]`, counting calls
+    fn serve(calls: Arc<AtomicUsize>) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1/embeddings", listener.local_addr().unwrap());
+        let app = Router::new().route("/v1/embeddings", post(move |Json(body): Json<Value>| async move {
+            calls.fetch_add(1, Ordering::SeqCst);
+            let data: Vec<Value> = body["input"].as_array().unwrap().iter().enumerate()
+                .map(|(index, text)| json!({"index": index, "embedding": [text.as_str().unwrap().len() as f32, 1.0]}))
+                .collect();
+            Json(json!({"data": data}))
+        }));
+        std::thread::spawn(move || {
+            tokio::runtime::Runtime::new().unwrap().block_on(async move {
+                axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()).await.unwrap();
+            });
+        });
+        url
+    }
+    
+    #[test]
+    fn test_concurrent_requests_are_coalesced() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let provider = Arc::new(RemoteEmbeddingProvider::new(serve(calls.clone()), "small", 2, &NetworkConfig::default()).unwrap()
+            .with_coalescing(Duration::from_millis(300), 64));
+        
+        let handles: Vec<_> = (1..=6)
+            .map(|length| {
+                let provider = provider.clone();
+                std::thread::spawn(move || provider.generate_embedding(&"x".repeat(length)).unwrap())
+            })
+            .collect();
+        for (length, handle) in (1..=6).zip(handles) {
+            assert_eq!(handle.join().unwrap(), vec![length as f32, 1.0]);
+        }
+        assert!(calls.load(Ordering::SeqCst) < 6);
+    }
+    
+    #[test]
+    fn test_batches_are_split() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let provider = RemoteEmbeddingProvider::new(serve(calls.clone()), "small", 2, &NetworkConfig::default()).unwrap()
+            .with_coalescing(Duration::ZERO, 2)
+            .with_rate_limit(6000);
+        let texts: Vec<String> = (1..=5).map(|length| "x".repeat(length)).collect();
+        let embeddings = provider.generate_embeddings(&texts).unwrap();
+        assert_eq!(embeddings.len(), 5);
+        assert_eq!(embeddings[4], vec![5.0, 1.0]);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_builds_and_drops_on_an_async_runtime() {
+        let provider = RemoteEmbeddingProvider::new("http://127.0.0.1:9/v1/embeddings", "small", 2, &NetworkConfig::default()).unwrap();
+        drop(provider);
+        
+        let network = NetworkConfig { proxy: Some("not a url".to_string()), ..Default::default() };
+        assert!(RemoteEmbeddingProvider::new("http://127.0.0.1:9/v1/embeddings", "small", 2, &network).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+//! Reranking search results with a cross-encoder.
+//!
+//! Vector search compares a query and a passage that were embedded apart; a
+//! cross-encoder reads the two together and scores how well the passage
+//! answers the query. That is far more precise for ambiguous queries but too
+//! slow to run over a whole collection, so a collection with `rerank` enabled
+//! in its search settings fetches its best `candidates` by vector and returns
+//! them in the reranker's order.
+
+use std::path::Path;
+
+use super::EmbeddingError;
+use crate::config::RerankerConfig;
+
+/// Candidates fetched for reranking when a collection does not set its own
+pub const DEFAULT_RERANK_CANDIDATES: usize = 50;
+
+/// Scores passages by their relevance to a query
+pub trait Reranker: Send + Sync {
+    /// Score each of `passages` against `query`, from 0 to 1; higher is more relevant
+    fn score(&self, query: &str, passages: &[&str]) -> Result<Vec<f32>, EmbeddingError>;
+}
+
+/// Indices of `passages` from most to least relevant to `query`, with their scores
+pub fn rerank_order(reranker: &dyn Reranker, query: &str, passages: &[&str]) -> Result<Vec<(usize, f32)>, EmbeddingError> {
+    if passages.is_empty() {
+        return Ok(Vec::new());
+    }
+    let scores = reranker.score(query, passages)?;
+    if scores.len() != passages.len() {
+        return Err(EmbeddingError::GenerationError(format!(
+            "Reranker returned {} scores for {} passages", scores.len(), passages.len()
+        )));
+    }
+    let mut order: Vec<(usize, f32)> = scores.into_iter().enumerate().collect();
+    // Ties keep the vector search's order
+    order.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    Ok(order)
+}
+
+#[cfg(feature = "embedding-generation")]
+use rust_bert::bert::{BertConfig, BertForSequenceClassification};
+#[cfg(feature = "embedding-generation")]
+use rust_bert::Config;
+#[cfg(feature = "embedding-generation")]
+use rust_tokenizers::tokenizer::{BertTokenizer, Tokenizer, TruncationStrategy};
+#[cfg(feature = "embedding-generation")]
+use tch::{nn, Device, Kind, Tensor};
+
+/// A BERT cross-encoder, such as `cross-encoder/ms-marco-MiniLM-L-6-v2`,
+/// loaded from a directory with `config.json`, `vocab.txt` and the weights
+/// converted to `rust_model.ot`
+#[cfg(feature = "embedding-generation")]
+pub struct CrossEncoderReranker {
+    model: BertForSequenceClassification,
+    tokenizer: BertTokenizer,
+    device: Device,
+    max_length: usize,
+    batch_size: usize,
+    // The model's weights live here
+    _weights: nn::VarStore,
+}
+
+#[cfg(feature = "embedding-generation")]
+impl CrossEncoderReranker {
+    pub fn new(model_path: &Path, use_gpu: bool, max_length: usize, batch_size: usize) -> Result<Self, EmbeddingError> {
+        tracing::info!("Loading cross-encoder from {:?}", model_path);
+        let failed = |e: String| EmbeddingError::InitializationError(format!("cross-encoder at {}: {}", model_path.display(), e));
+        
+        let device = if use_gpu { Device::cuda_if_available() } else { Device::Cpu };
+        let config = std::fs::read_to_string(model_path.join("config.json")).map_err(|e| failed(format!("config.json: {}", e)))?;
+        let config: BertConfig = serde_json::from_str(&config).map_err(|e| failed(format!("config.json: {}", e)))?;
+        let tokenizer = BertTokenizer::from_file(model_path.join("vocab.txt"), true, true)
+            .map_err(|e| failed(e.to_string()))?;
+        let mut weights = nn::VarStore::new(device);
+        let model = BertForSequenceClassification::new(weights.root(), &config);
+        weights.load(model_path.join("rust_model.ot")).map_err(|e| failed(e.to_string()))?;
+        
+        Ok(Self { model, tokenizer, device, max_length, batch_size: batch_size.max(1), _weights: weights })
+    }
+    
+    /// Scores of one batch of (query, passage) pairs
+    fn score_batch(&self, pairs: &[(&str, &str)]) -> Result<Vec<f32>, EmbeddingError> {
+        let encoded = self.tokenizer.encode_pair_list(pairs, self.max_length, &TruncationStrategy::LongestFirst, 0);
+        let width = encoded.iter().map(|input| input.token_ids.len()).max().unwrap_or(0);
+        
+        let pad = |values: Vec<i64>| {
+            let mut values = values;
+            values.resize(width, 0);
+            Tensor::of_slice(&values)
+        };
+        let ids: Vec<Tensor> = encoded.iter().map(|input| pad(input.token_ids.clone())).collect();
+        let segments: Vec<Tensor> = encoded.iter().map(|input| pad(input.segment_ids.iter().map(|id| *id as i64).collect())).collect();
+        let masks: Vec<Tensor> = encoded.iter().map(|input| pad(vec![1; input.token_ids.len()])).collect();
+        
+        let logits = tch::no_grad(|| {
+            self.model.forward_t(
+                Some(&Tensor::stack(&ids, 0).to(self.device)),
+                Some(&Tensor::stack(&masks, 0).to(self.device)),
+                Some(&Tensor::stack(&segments, 0).to(self.device)),
+                None,
+                None,
+                false,
+            ).logits
+        });
+        // Models with one output give a relevance logit; others a probability per label, the last meaning relevant
+        let labels = logits.size()[1];
+        let scores = if labels == 1 {
+            logits.squeeze_dim(1).sigmoid()
+        } else {
+            logits.softmax(-1, Kind::Float).select(1, labels - 1)
+        };
+        Ok(Vec::<f32>::from(&scores.to(Device::Cpu)))
+    }
+}
+
+#[cfg(feature = "embedding-generation")]
+impl Reranker for CrossEncoderReranker {
+    fn score(&self, query: &str, passages: &[&str]) -> Result<Vec<f32>, EmbeddingError> {
+        let mut scores = Vec::with_capacity(passages.len());
+        for batch in passages.chunks(self.batch_size) {
+            let pairs: Vec<(&str, &str)> = batch.iter().map(|passage| (query, *passage)).collect();
+            scores.extend(self.score_batch(&pairs)?);
+        }
+        Ok(scores)
+    }
+}
+
+/// Stands in for the cross-encoder when the embedding-generation feature is disabled
+#[cfg(not(feature = "embedding-generation"))]
+pub struct CrossEncoderReranker;
+
+#[cfg(not(feature = "embedding-generation"))]
+impl CrossEncoderReranker {
+    /// Always fails: there is no model to run without the embedding-generation feature
+    pub fn new(model_path: &Path, _use_gpu: bool, _max_length: usize, _batch_size: usize) -> Result<Self, EmbeddingError> {
+        Err(EmbeddingError::InitializationError(format!(
+            "the cross-encoder at {} needs p-mo built with the embedding-generation feature",
+            model_path.display()
+        )))
+    }
+}
+
+#[cfg(not(feature = "embedding-generation"))]
+impl Reranker for CrossEncoderReranker {
+    fn score(&self, _query: &str, _passages: &[&str]) -> Result<Vec<f32>, EmbeddingError> {
+        Err(EmbeddingError::GenerationError("reranking needs the embedding-generation feature".to_string()))
+    }
+}
+
+impl CrossEncoderReranker {
+    /// The reranker the `[reranker]` settings describe; `None` when they name no model
+    pub fn from_config(config: &RerankerConfig) -> Result<Option<Self>, EmbeddingError> {
+        match &config.model_path {
+            Some(path) => Self::new(path, config.use_gpu, config.max_length, config.batch_size).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Scores passages by the share of their words that appear in the query
+    struct WordOverlap;
+    
+    impl Reranker for WordOverlap {
+        fn score(&self, query: &str, passages: &[&str]) -> Result<Vec<f32>, EmbeddingError> {
+            Ok(passages.iter().map(|passage| {
+                let words: Vec<&str> = passage.split_whitespace().collect();
+                let shared = words.iter().filter(|word| query.contains(**word)).count();
+                shared as f32 / words.len().max(1) as f32
+            }).collect())
+        }
+    }
+    
+    #[test]
+    fn test_rerank_order() {
+        let passages = ["rust borrow checker", "python garbage collector", "rust lifetimes"];
+        let order = rerank_order(&WordOverlap, "how do rust lifetimes work", &passages).unwrap();
+        let indices: Vec<usize> = order.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indices, [2, 0, 1]);
+        assert!(rerank_order(&WordOverlap, "anything", &[]).unwrap().is_empty());
+    }
+    
+    #[cfg(not(feature = "embedding-generation"))]
+    #[test]
+    fn test_cross_encoder_needs_the_feature() {
+        let config = RerankerConfig::default();
+        assert!(CrossEncoderReranker::from_config(&config).unwrap().is_none());
+        let config = RerankerConfig { model_path: Some("/models/ms-marco".into()), ..config };
+        assert!(CrossEncoderReranker::from_config(&config).is_err());
+    }
+}
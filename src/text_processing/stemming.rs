@@ -0,0 +1,90 @@
+//! Language-aware word stemming.
+//!
+//! Words are reduced to their Snowball stems so inflected forms of one word
+//! compare equal: "deploying" and "deployment" both stem to "deploy". The
+//! stemmer is picked by the ISO 639-3 code language detection records for
+//! an entry, falling back to English.
+
+use std::ops::Range;
+
+use rust_stemmers::{Algorithm, Stemmer};
+
+/// The Snowball algorithm for an ISO 639-3 language code; `None` when there is none
+pub fn algorithm_for(language: &str) -> Option<Algorithm> {
+    let algorithm = match language {
+        "ara" => Algorithm::Arabic,
+        "dan" => Algorithm::Danish,
+        "deu" => Algorithm::German,
+        "ell" => Algorithm::Greek,
+        "eng" => Algorithm::English,
+        "fin" => Algorithm::Finnish,
+        "fra" => Algorithm::French,
+        "hun" => Algorithm::Hungarian,
+        "ita" => Algorithm::Italian,
+        "nld" => Algorithm::Dutch,
+        "nob" => Algorithm::Norwegian,
+        "por" => Algorithm::Portuguese,
+        "ron" => Algorithm::Romanian,
+        "rus" => Algorithm::Russian,
+        "spa" => Algorithm::Spanish,
+        "swe" => Algorithm::Swedish,
+        "tam" => Algorithm::Tamil,
+        "tur" => Algorithm::Turkish,
+        _ => return None,
+    };
+    Some(algorithm)
+}
+
+/// A stemmer for `language`, or English when it is unknown or has no stemmer
+pub fn stemmer_for(language: Option<&str>) -> Stemmer {
+    Stemmer::create(language.and_then(algorithm_for).unwrap_or(Algorithm::English))
+}
+
+/// A word of a text: where it is and its lowercase stem
+#[derive(Debug, Clone, PartialEq)]
+pub struct StemmedWord {
+    /// Byte range of the word in the text
+    pub span: Range<usize>,
+    pub stem: String,
+}
+
+/// The words of `text`, runs of letters and digits, with their stems
+pub fn stemmed_words(text: &str, stemmer: &Stemmer) -> Vec<StemmedWord> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(from)) => {
+                let word = text[from..index].to_lowercase();
+                words.push(StemmedWord { span: from..index, stem: stemmer.stem(&word).into_owned() });
+                start = None;
+            },
+            _ => {},
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn stems(text: &str, language: &str) -> Vec<String> {
+        stemmed_words(text, &stemmer_for(Some(language))).into_iter().map(|word| word.stem).collect()
+    }
+    
+    #[test]
+    fn test_inflected_forms_share_a_stem() {
+        assert_eq!(stems("Deploying deployment", "eng"), ["deploy", "deploy"]);
+        assert_eq!(stems("desplegando desplegar", "spa"), ["despleg", "despleg"]);
+    }
+    
+    #[test]
+    fn test_spans_and_fallback() {
+        let words = stemmed_words("née, 2 cafés", &stemmer_for(Some("xxx")));
+        let spans: Vec<Range<usize>> = words.iter().map(|word| word.span.clone()).collect();
+        assert_eq!(spans, [0..4, 6..7, 8..14]);
+        assert!(algorithm_for("xxx").is_none());
+    }
+}
@@ -0,0 +1,48 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tiktoken_rs::CoreBPE;
+
+/// BPE vocabularies used to count tokens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenEncoding {
+    #[default]
+    Cl100kBase,
+    O200kBase,
+}
+
+lazy_static! {
+    static ref CL100K_BASE: CoreBPE = tiktoken_rs::cl100k_base().unwrap();
+    static ref O200K_BASE: CoreBPE = tiktoken_rs::o200k_base().unwrap();
+}
+
+impl TokenEncoding {
+    fn bpe(&self) -> &'static CoreBPE {
+        match self {
+            TokenEncoding::Cl100kBase => &CL100K_BASE,
+            TokenEncoding::O200kBase => &O200K_BASE,
+        }
+    }
+    
+    /// Count the tokens `text` encodes to
+    pub fn count(&self, text: &str) -> usize {
+        self.bpe().encode_with_special_tokens(text).len()
+    }
+}
+
+/// Count tokens with the default encoding
+pub fn count_tokens(text: &str) -> usize {
+    TokenEncoding::default().count(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_count_tokens() {
+        assert_eq!(count_tokens(""), 0);
+        assert_eq!(count_tokens("hello world"), 2);
+        assert!(TokenEncoding::O200kBase.count("hello world") > 0);
+    }
+}
@@ -0,0 +1,232 @@
+//! A minimal embedded web UI for inspecting the knowledge base.
+//!
+//! Static assets are compiled into the binary and served under `/ui`; the
+//! JSON endpoints under `/ui/api` require the admin API key.
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get};
+use axum::{Json, Router};
+use rust_embed::RustEmbed;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use thiserror::Error;
+
+use crate::api_keys::constant_time_eq;
+use crate::context::api_key_from_headers;
+use crate::mcp::DEFAULT_EMBEDDING_DIM;
+use crate::text_processing::{EmbeddingError, EmbeddingProvider};
+use crate::vector_store::{Document, EntryId, EntryIdError, SearchQuery, VectorStore, VectorStoreError};
+
+#[derive(RustEmbed)]
+#[folder = "assets/ui/"]
+struct Assets;
+
+#[derive(Debug, Error)]
+pub enum UiError {
+    #[error("Invalid or missing admin API key")]
+    Unauthorized,
+    
+    #[error("The admin UI is disabled because no admin API key is configured")]
+    NotConfigured,
+    
+    #[error("Invalid entry id: {0}")]
+    InvalidEntryId(#[from] EntryIdError),
+    
+    #[error("Vector store error: {0}")]
+    VectorStoreError(#[from] VectorStoreError),
+    
+    #[error("Embedding error: {0}")]
+    EmbeddingError(#[from] EmbeddingError),
+}
+
+impl IntoResponse for UiError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            UiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            UiError::NotConfigured => StatusCode::FORBIDDEN,
+            UiError::InvalidEntryId(_) => StatusCode::BAD_REQUEST,
+            UiError::VectorStoreError(_) | UiError::EmbeddingError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+/// Shared state for the admin UI handlers
+#[derive(Clone)]
+pub struct UiState {
+    vector_store: Arc<dyn VectorStore>,
+    embedding_provider: Option<Arc<dyn EmbeddingProvider + Send + Sync>>,
+    embedding_dim: usize,
+    admin_api_key: Option<String>,
+}
+
+impl UiState {
+    /// Create UI state; without an admin API key every API call is refused
+    pub fn new(vector_store: Arc<dyn VectorStore>, admin_api_key: Option<String>) -> Self {
+        Self {
+            vector_store,
+            embedding_provider: None,
+            embedding_dim: DEFAULT_EMBEDDING_DIM,
+            admin_api_key,
+        }
+    }
+    
+    /// Embed search queries with `provider` instead of placeholder vectors
+    pub fn with_embedding_provider(mut self, provider: Arc<dyn EmbeddingProvider + Send + Sync>) -> Self {
+        self.embedding_dim = provider.embedding_dim();
+        self.embedding_provider = Some(provider);
+        self
+    }
+    
+    fn embed(&self, text: &str) -> Result<Vec<f32>, EmbeddingError> {
+        match &self.embedding_provider {
+            Some(provider) => provider.generate_embedding(text),
+            None => Ok(vec![0.0; self.embedding_dim]),
+        }
+    }
+}
+
+/// Build the router serving the UI; mount it at `/ui`
+pub fn router(state: UiState) -> Router {
+    let api = Router::new()
+        .route("/collections", get(list_collections))
+        .route("/collections/:collection/entries", get(list_entries))
+        .route("/collections/:collection/entries/:id", delete(delete_entry))
+        .route("/collections/:collection/search", get(search_entries))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin_key));
+    
+    Router::new()
+        .route("/", get(index))
+        .route("/assets/*path", get(asset))
+        .nest("/api", api)
+        .with_state(state)
+}
+
+async fn require_admin_key<B>(State(state): State<UiState>, request: Request<B>, next: Next<B>) -> Response {
+    let expected = match &state.admin_api_key {
+        Some(key) => key,
+        None => return UiError::NotConfigured.into_response(),
+    };
+    
+    match api_key_from_headers(request.headers()) {
+        Some(presented) if constant_time_eq(presented.as_bytes(), expected.as_bytes()) => next.run(request).await,
+        _ => UiError::Unauthorized.into_response(),
+    }
+}
+
+async fn index() -> Response {
+    serve_asset("index.html")
+}
+
+async fn asset(Path(path): Path<String>) -> Response {
+    serve_asset(&path)
+}
+
+fn serve_asset(path: &str) -> Response {
+    match Assets::get(path) {
+        Some(file) => ([(header::CONTENT_TYPE, content_type(path))], file.data.into_owned()).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit('.').next() {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "application/javascript",
+        Some("css") => "text/css",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    }
+}
+
+fn entry_json(document: &Document) -> Value {
+    json!({
+        "id": document.id,
+        "content": document.content,
+    })
+}
+
+async fn list_collections(State(state): State<UiState>) -> Result<Json<Value>, UiError> {
+    let collections = state.vector_store.list_collections().await?;
+    Ok(Json(json!({ "collections": collections })))
+}
+
+#[derive(Debug, Deserialize)]
+struct EntriesParams {
+    offset: Option<String>,
+    limit: Option<usize>,
+}
+
+async fn list_entries(
+    State(state): State<UiState>,
+    Path(collection): Path<String>,
+    Query(params): Query<EntriesParams>,
+) -> Result<Json<Value>, UiError> {
+    let limit = params.limit.unwrap_or(50).min(500);
+    let page = state.vector_store.list_documents(&collection, params.offset, limit).await?;
+    
+    Ok(Json(json!({
+        "entries": page.documents.iter().map(entry_json).collect::<Vec<Value>>(),
+        "next_offset": page.next_offset,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    q: String,
+    limit: Option<usize>,
+}
+
+async fn search_entries(
+    State(state): State<UiState>,
+    Path(collection): Path<String>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<Value>, UiError> {
+    let query = SearchQuery {
+        embedding: state.embed(&params.q)?,
+        limit: params.limit.unwrap_or(20).min(100),
+    };
+    let results = state.vector_store.search(&collection, query).await?;
+    
+    let results: Vec<Value> = results.iter()
+        .map(|result| {
+            let mut entry = entry_json(&result.document);
+            entry["score"] = json!(result.score);
+            entry
+        })
+        .collect();
+    Ok(Json(json!({ "results": results })))
+}
+
+async fn delete_entry(
+    State(state): State<UiState>,
+    Path((collection, id)): Path<(String, String)>,
+) -> Result<Json<Value>, UiError> {
+    let id = EntryId::parse(&id)?;
+    state.vector_store.delete_document(&collection, id.as_str()).await?;
+    Ok(Json(json!({ "deleted": id })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secrets"));
+    }
+    
+    #[test]
+    fn test_assets_are_embedded() {
+        assert!(Assets::get("index.html").is_some());
+        assert!(Assets::get("app.js").is_some());
+        assert_eq!(content_type("app.js"), "application/javascript");
+    }
+}
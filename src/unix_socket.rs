@@ -0,0 +1,82 @@
+//! Serving HTTP over a Unix domain socket: the REST API and, upgraded from
+//! it, the MCP WebSocket.
+//!
+//! Access is then governed by the socket file's permissions rather than by
+//! whoever can reach a TCP port, which matters on shared machines.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::net::{UnixListener, UnixStream};
+
+/// Accepts hyper connections from a Unix listener
+pub struct UnixAccept(pub UnixListener);
+
+impl hyper::server::accept::Accept for UnixAccept {
+    type Conn = UnixStream;
+    type Error = io::Error;
+    
+    fn poll_accept(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        match self.0.poll_accept(cx) {
+            Poll::Ready(Ok((stream, _addr))) => Poll::Ready(Some(Ok(stream))),
+            Poll::Ready(Err(e)) => Poll::Ready(Some(Err(e))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Bind `path` and restrict it to `mode`, replacing a stale socket file
+///
+/// Fails with `AddrInUse` if another server is still accepting on `path`.
+pub fn bind(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists and is not a socket", path.display())));
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("{} is in use by a running server", path.display())));
+        }
+        fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    
+    #[tokio::test]
+    async fn test_bind_sets_mode_and_replaces_stale_socket() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("p-mo.sock");
+        
+        let listener = bind(&path, 0o600).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        
+        let in_use = bind(&path, 0o600).unwrap_err();
+        assert_eq!(in_use.kind(), io::ErrorKind::AddrInUse);
+        
+        drop(listener);
+        assert!(bind(&path, 0o660).is_ok());
+    }
+    
+    #[test]
+    fn test_bind_refuses_regular_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("not-a-socket");
+        fs::write(&path, "data").unwrap();
+        
+        assert_eq!(bind(&path, 0o600).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+    }
+}
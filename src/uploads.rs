@@ -0,0 +1,353 @@
+//! Resumable uploads of large attachments.
+//!
+//! A client starts a session by declaring the total size, then sends the
+//! content over as many requests as it takes, each starting at the offset
+//! the server has reached. After a dropped connection it asks for the offset
+//! and carries on from there instead of starting over. Sessions live on
+//! disk, as the bytes received so far next to a JSON description, so they
+//! survive restarts; unfinished ones expire.
+//!
+//! The size of the partial file is the session's offset, so whatever part of
+//! an interrupted request reached the disk counts. Requests for one session
+//! take turns: each holds the session's lock from checking its offset until
+//! it has written its body, so two requests sent at the same offset cannot
+//! both append.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+use uuid::Uuid;
+
+use crate::config::{AttachmentsConfig, Config};
+
+/// How long an unfinished upload is kept when no TTL is configured: one day
+pub const DEFAULT_UPLOAD_TTL_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Error)]
+pub enum UploadError {
+    #[error("Upload {0} not found")]
+    NotFound(String),
+    
+    #[error("Upload is at offset {expected}, not {given}")]
+    OffsetMismatch { expected: u64, given: u64 },
+    
+    #[error("Upload would go past its declared length of {length} bytes")]
+    PastEnd { length: u64 },
+    
+    #[error("Upload of {length} bytes is over the limit of {max} bytes")]
+    TooLarge { length: u64, max: u64 },
+    
+    #[error("Upload length must be at least one byte")]
+    Empty,
+    
+    #[error("Upload request ended early: {0}")]
+    Interrupted(String),
+    
+    #[error("Upload storage error: {0}")]
+    Storage(String),
+}
+
+impl From<std::io::Error> for UploadError {
+    fn from(e: std::io::Error) -> Self {
+        UploadError::Storage(e.to_string())
+    }
+}
+
+/// An upload in progress
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UploadSession {
+    pub id: String,
+    /// Total bytes the client said it would send
+    pub length: u64,
+    /// Content type declared when the upload started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    /// Collection of the entry the finished upload is attached to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Unfinished uploads, kept in a directory
+#[derive(Debug, Clone)]
+pub struct UploadSessions {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+    /// One lock per session being written to
+    locks: Arc<Mutex<HashMap<Uuid, Arc<AsyncMutex<()>>>>>,
+}
+
+impl UploadSessions {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: Duration::seconds(DEFAULT_UPLOAD_TTL_SECS),
+            max_bytes: u64::MAX,
+            locks: Arc::default(),
+        }
+    }
+    
+    /// Sessions in the configured directory, limited to the attachment size limit
+    pub fn from_config(config: &AttachmentsConfig) -> Self {
+        Self::new(config.upload_dir.clone().unwrap_or_else(|| Config::data_dir().join("uploads")))
+            .with_ttl(Duration::seconds(config.upload_ttl_secs as i64))
+            .with_max_bytes(config.max_bytes as u64)
+    }
+    
+    /// Drop unfinished uploads `ttl` after they started
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+    
+    /// Refuse to start uploads of more than `max_bytes`
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+    
+    /// The description and content files of session `id`. Only ids this
+    /// server handed out name files, so no id reaches outside the directory.
+    fn paths(&self, id: &str) -> Result<(PathBuf, PathBuf), UploadError> {
+        let id = Uuid::parse_str(id).map_err(|_| UploadError::NotFound(id.to_string()))?;
+        Ok((self.dir.join(format!("{}.json", id)), self.dir.join(format!("{}.part", id))))
+    }
+    
+    fn session_lock(&self, id: &str) -> Result<Arc<AsyncMutex<()>>, UploadError> {
+        let id = Uuid::parse_str(id).map_err(|_| UploadError::NotFound(id.to_string()))?;
+        Ok(self.locks.lock().unwrap().entry(id).or_default().clone())
+    }
+    
+    /// Start a session for `length` bytes, to be attached to `entry` (collection
+    /// and id) once complete if one is given
+    pub async fn create(
+        &self,
+        length: u64,
+        content_type: Option<&str>,
+        filename: Option<&str>,
+        entry: Option<(&str, &str)>,
+    ) -> Result<UploadSession, UploadError> {
+        if length == 0 {
+            return Err(UploadError::Empty);
+        }
+        if length > self.max_bytes {
+            return Err(UploadError::TooLarge { length, max: self.max_bytes });
+        }
+        self.purge_expired().await?;
+        
+        let now = Utc::now();
+        let session = UploadSession {
+            id: Uuid::new_v4().to_string(),
+            length,
+            content_type: content_type.map(str::to_string),
+            filename: filename.map(str::to_string),
+            collection: entry.map(|(collection, _)| collection.to_string()),
+            entry_id: entry.map(|(_, id)| id.to_string()),
+            created_at: now,
+            expires_at: now + self.ttl,
+        };
+        let (description, content) = self.paths(&session.id)?;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(&content, b"").await?;
+        tokio::fs::write(&description, serde_json::to_vec(&session).expect("sessions serialize")).await?;
+        Ok(session)
+    }
+    
+    /// A session and how many of its bytes have arrived; an expired session is removed and not found
+    pub async fn get(&self, id: &str) -> Result<(UploadSession, u64), UploadError> {
+        let (description, content) = self.paths(id)?;
+        let session: UploadSession = match tokio::fs::read(&description).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| UploadError::Storage(e.to_string()))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(UploadError::NotFound(id.to_string())),
+            Err(e) => return Err(e.into()),
+        };
+        if session.expires_at <= Utc::now() {
+            self.remove(id).await?;
+            return Err(UploadError::NotFound(id.to_string()));
+        }
+        let offset = tokio::fs::metadata(&content).await?.len();
+        Ok((session, offset))
+    }
+    
+    /// Carry on writing session `id` at `offset`, which must be the bytes received so far.
+    /// Other requests for the session wait until the writer is dropped.
+    pub async fn resume(&self, id: &str, offset: u64) -> Result<UploadWriter, UploadError> {
+        let guard = self.session_lock(id)?.lock_owned().await;
+        let (session, received) = self.get(id).await?;
+        if offset != received {
+            return Err(UploadError::OffsetMismatch { expected: received, given: offset });
+        }
+        let (_, content) = self.paths(id)?;
+        let file = tokio::fs::OpenOptions::new().append(true).open(&content).await?;
+        Ok(UploadWriter { session, file, offset, _guard: guard })
+    }
+    
+    /// The file holding the bytes received for session `id`; the session
+    /// stays until [`remove`](Self::remove)d
+    pub fn content_path(&self, id: &str) -> Result<PathBuf, UploadError> {
+        let (_, content) = self.paths(id)?;
+        Ok(content)
+    }
+    
+    /// Drop session `id` and what it received
+    pub async fn remove(&self, id: &str) -> Result<(), UploadError> {
+        let (description, content) = self.paths(id)?;
+        for path in [description, content] {
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => {},
+                Err(e) if e.kind() == ErrorKind::NotFound => {},
+                Err(e) => return Err(e.into()),
+            }
+        }
+        if let Ok(id) = Uuid::parse_str(id) {
+            self.locks.lock().unwrap().remove(&id);
+        }
+        Ok(())
+    }
+    
+    /// Remove the sessions past their expiry, returning how many there were
+    pub async fn purge_expired(&self) -> Result<usize, UploadError> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        let mut purged = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let Some(id) = name.to_str().and_then(|name| name.strip_suffix(".json")) else { continue };
+            if Uuid::parse_str(id).is_ok() && matches!(self.get(id).await, Err(UploadError::NotFound(_))) {
+                purged += 1;
+            }
+        }
+        Ok(purged)
+    }
+}
+
+/// Appends to a session, refusing bytes past its declared length, and holds
+/// the session's lock until dropped
+#[derive(Debug)]
+pub struct UploadWriter {
+    session: UploadSession,
+    file: tokio::fs::File,
+    offset: u64,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl UploadWriter {
+    pub fn session(&self) -> &UploadSession {
+        &self.session
+    }
+    
+    /// Bytes received so far, this request's included
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+    
+    /// Bytes still to come before the session is complete
+    pub fn remaining(&self) -> u64 {
+        self.session.length - self.offset
+    }
+    
+    pub async fn write(&mut self, chunk: &[u8]) -> Result<(), UploadError> {
+        if self.offset + chunk.len() as u64 > self.session.length {
+            return Err(UploadError::PastEnd { length: self.session.length });
+        }
+        self.file.write_all(chunk).await?;
+        self.offset += chunk.len() as u64;
+        Ok(())
+    }
+    
+    /// Flush what was written, returning the new offset; the lock is kept
+    /// until the writer is dropped
+    pub async fn finish(&mut self) -> Result<u64, UploadError> {
+        self.file.flush().await?;
+        Ok(self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    #[tokio::test]
+    async fn test_upload_resumes_at_offset() {
+        let temp_dir = TempDir::new().unwrap();
+        let uploads = UploadSessions::new(temp_dir.path());
+        let session = uploads.create(10, Some("application/pdf"), Some("spec.pdf"), Some(("docs", "spec"))).await.unwrap();
+        
+        let mut writer = uploads.resume(&session.id, 0).await.unwrap();
+        writer.write(b"%PDF-").await.unwrap();
+        assert_eq!(writer.finish().await.unwrap(), 5);
+        
+        // A restarted server finds the session where it was
+        let reopened = UploadSessions::new(temp_dir.path());
+        let (found, offset) = reopened.get(&session.id).await.unwrap();
+        assert_eq!((found, offset), (session.clone(), 5));
+        assert!(matches!(
+            reopened.resume(&session.id, 0).await,
+            Err(UploadError::OffsetMismatch { expected: 5, given: 0 })
+        ));
+        
+        let mut writer = reopened.resume(&session.id, 5).await.unwrap();
+        assert!(matches!(writer.write(b"123456").await, Err(UploadError::PastEnd { length: 10 })));
+        writer.write(b"12345").await.unwrap();
+        writer.finish().await.unwrap();
+        assert_eq!(std::fs::read(reopened.content_path(&session.id).unwrap()).unwrap(), b"%PDF-12345");
+        
+        reopened.remove(&session.id).await.unwrap();
+        assert!(matches!(reopened.get(&session.id).await, Err(UploadError::NotFound(_))));
+        assert!(matches!(reopened.get("../../etc/passwd").await, Err(UploadError::NotFound(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_concurrent_writers_take_turns() {
+        let temp_dir = TempDir::new().unwrap();
+        let uploads = UploadSessions::new(temp_dir.path());
+        let session = uploads.create(8, None, None, None).await.unwrap();
+        
+        let mut first = uploads.resume(&session.id, 0).await.unwrap();
+        let second = tokio::spawn({
+            let uploads = uploads.clone();
+            let id = session.id.clone();
+            async move { uploads.resume(&id, 0).await.map(|_| ()) }
+        });
+        
+        // The second request waits for the first and then finds the offset moved on
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!second.is_finished());
+        first.write(b"abcd").await.unwrap();
+        first.finish().await.unwrap();
+        drop(first);
+        assert!(matches!(second.await.unwrap(), Err(UploadError::OffsetMismatch { expected: 4, given: 0 })));
+        assert_eq!(std::fs::read(uploads.content_path(&session.id).unwrap()).unwrap(), b"abcd");
+    }
+    
+    #[tokio::test]
+    async fn test_limits_and_expiry() {
+        let temp_dir = TempDir::new().unwrap();
+        let uploads = UploadSessions::new(temp_dir.path()).with_max_bytes(8);
+        assert!(matches!(uploads.create(0, None, None, None).await, Err(UploadError::Empty)));
+        assert!(matches!(uploads.create(9, None, None, None).await, Err(UploadError::TooLarge { length: 9, max: 8 })));
+        
+        let kept = uploads.create(4, None, None, None).await.unwrap();
+        let expired = uploads.clone().with_ttl(Duration::seconds(-1));
+        let session = expired.create(4, None, None, None).await.unwrap();
+        assert_eq!(uploads.purge_expired().await.unwrap(), 1);
+        assert!(matches!(uploads.get(&session.id).await, Err(UploadError::NotFound(_))));
+        assert!(uploads.get(&kept.id).await.is_ok());
+    }
+}
@@ -0,0 +1,154 @@
+//! Inserting many documents in one call.
+//!
+//! A batch reports an outcome per document so callers can retry only the
+//! failures. An atomic batch is all-or-nothing instead: when one document
+//! fails, those already written are restored to what they replaced.
+
+use serde::Serialize;
+
+use super::{Document, VectorStore, VectorStoreError};
+
+/// Error recorded against the other documents of an atomic batch that was rolled back
+pub const ROLLED_BACK: &str = "rolled back: another document in the atomic batch failed";
+
+/// The outcome of one document in a batch
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchItem {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BatchItem {
+    pub fn inserted(id: String) -> Self {
+        Self { id, error: None }
+    }
+    
+    pub fn failed(id: String, error: String) -> Self {
+        Self { id, error: Some(error) }
+    }
+    
+    pub fn is_inserted(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Insert `documents` one at a time, as `VectorStore::batch_insert` does by default
+pub async fn insert_each<S: VectorStore + ?Sized>(
+    store: &S,
+    collection: &str,
+    documents: Vec<Document>,
+    atomic: bool,
+) -> Result<Vec<BatchItem>, VectorStoreError> {
+    let ids: Vec<String> = documents.iter().map(|document| document.id.clone()).collect();
+    let mut items = Vec::with_capacity(documents.len());
+    let mut written = Vec::new();
+    
+    for (index, document) in documents.into_iter().enumerate() {
+        let id = document.id.clone();
+        if !atomic {
+            items.push(match store.insert_document(collection, document).await {
+                Ok(()) => BatchItem::inserted(id),
+                Err(e) => BatchItem::failed(id, e.to_string()),
+            });
+            continue;
+        }
+        
+        // Keep what the document replaces so the batch can be undone
+        let result = match store.get_document(collection, &id).await {
+            Ok(previous) => store.insert_document(collection, document).await.map(|()| previous),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(previous) => written.push((id, previous)),
+            Err(e) => {
+                roll_back(store, collection, written).await?;
+                return Ok(ids.into_iter()
+                    .enumerate()
+                    .map(|(other, id)| if other == index {
+                        BatchItem::failed(id, e.to_string())
+                    } else {
+                        BatchItem::failed(id, ROLLED_BACK.to_string())
+                    })
+                    .collect());
+            },
+        }
+    }
+    
+    if atomic {
+        items = ids.into_iter().map(BatchItem::inserted).collect();
+    }
+    Ok(items)
+}
+
+/// Undo the writes of an atomic batch, newest first so a document written
+/// twice ends up as it was before the batch
+async fn roll_back<S: VectorStore + ?Sized>(
+    store: &S,
+    collection: &str,
+    written: Vec<(String, Option<Document>)>,
+) -> Result<(), VectorStoreError> {
+    for (id, previous) in written.into_iter().rev() {
+        let result = match previous {
+            Some(previous) => store.insert_document(collection, previous).await,
+            None => store.delete_document(collection, &id).await,
+        };
+        result.map_err(|e| VectorStoreError::OperationFailed(format!("Failed to roll back atomic batch at {}: {}", id, e)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    fn document(id: &str, content: &str, embedding: Vec<f32>) -> Document {
+        let mut document = Document::with_placeholder_embedding(content.to_string(), embedding.len());
+        document.id = id.to_string();
+        document.embedding = embedding;
+        document
+    }
+    
+    #[tokio::test]
+    async fn test_batch_reports_each_document() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        
+        let items = store.batch_insert("docs", vec![
+            document("a", "first", vec![0.6, 0.8]),
+            document("b", "wrong size", vec![0.1, 0.2, 0.3]),
+            document("c", "third", vec![0.8, 0.6]),
+        ], false).await.unwrap();
+        
+        assert_eq!(items.len(), 3);
+        assert!(items[0].is_inserted());
+        assert!(!items[1].is_inserted());
+        assert!(items[2].is_inserted());
+        assert!(store.exists("docs", "a").await.unwrap());
+        assert!(!store.exists("docs", "b").await.unwrap());
+        assert!(store.exists("docs", "c").await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_atomic_batch_rolls_back() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        store.insert_document("docs", document("a", "original", vec![0.6, 0.8])).await.unwrap();
+        
+        let items = store.batch_insert("docs", vec![
+            document("a", "replacement", vec![0.8, 0.6]),
+            document("b", "new", vec![0.6, 0.8]),
+            document("c", "wrong size", vec![0.1]),
+        ], true).await.unwrap();
+        
+        assert!(items.iter().all(|item| !item.is_inserted()));
+        assert_eq!(items[0].error.as_deref(), Some(ROLLED_BACK));
+        assert_ne!(items[2].error.as_deref(), Some(ROLLED_BACK));
+        assert_eq!(store.get_document("docs", "a").await.unwrap().unwrap().content, "original");
+        assert!(!store.exists("docs", "b").await.unwrap());
+        
+        let items = store.batch_insert("docs", vec![document("b", "new", vec![0.6, 0.8])], true).await.unwrap();
+        assert!(items[0].is_inserted());
+    }
+}
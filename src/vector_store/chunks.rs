@@ -0,0 +1,361 @@
+//! Storage layout of chunked entries and differential updates.
+//!
+//! An entry is stored as one document per chunk. Chunk 0 uses the entry id
+//! itself, so entries stored as a single document are valid one-chunk
+//! entries; later chunks get ids derived from the entry id and their index.
+//! A chunked entry may also have a summary document, an extractive summary
+//! of the whole entry embedded as its document-level vector, which two-stage
+//! and hierarchical search match before looking at chunks.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use super::{cosine_similarity, DistanceMetric, Document, MetadataFilter, SearchQuery, SearchResult, VectorStore, VectorStoreError};
+
+/// Metadata key linking a chunk to the entry it belongs to
+pub const PARENT_ID_KEY: &str = "parent_id";
+
+/// Metadata key holding a chunk's position within its entry
+pub const CHUNK_INDEX_KEY: &str = "chunk_index";
+
+/// Metadata key holding the SHA-256 of a chunk's content
+pub const CHUNK_HASH_KEY: &str = "chunk_hash";
+
+/// Metadata key marking a document as the summary of the entry it names
+pub const SUMMARY_OF_KEY: &str = "summary_of";
+
+/// Entries whose chunks two-stage and hierarchical search look at when a call does not say
+pub const DEFAULT_STAGED_ENTRIES: usize = 5;
+
+/// How a search finds the chunks it returns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchStrategy {
+    /// Match chunks directly
+    #[default]
+    Chunks,
+    /// Match whole entries by their summaries first, then score every chunk of the best entries
+    TwoStage,
+    /// Match whole entries by their summaries first, then search only the chunks of the best entries
+    Hierarchical,
+}
+
+/// Id of the chunk at `index` of an entry
+pub fn chunk_id(entry_id: &str, index: usize) -> String {
+    if index == 0 {
+        return entry_id.to_string();
+    }
+    derived_id(&format!("{}#{}", entry_id, index))
+}
+
+/// Id of the document holding an entry's summary
+pub fn summary_id(entry_id: &str) -> String {
+    derived_id(&format!("{}#summary", entry_id))
+}
+
+fn derived_id(key: &str) -> String {
+    let digest = Sha256::digest(key.as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Uuid::from_bytes(bytes).to_string()
+}
+
+/// The entry `document` summarizes, if it is a summary document
+pub fn summary_of(document: &Document) -> Option<&str> {
+    document.metadata.get(SUMMARY_OF_KEY).map(String::as_str)
+}
+
+/// The summary document of an entry, with the metadata of its first chunk
+pub fn summary_document(entry_id: &str, first_chunk: &Document, summary: String, embedding: Vec<f32>) -> Document {
+    let mut metadata = first_chunk.metadata.clone();
+    for key in [PARENT_ID_KEY, CHUNK_INDEX_KEY, CHUNK_HASH_KEY] {
+        metadata.remove(key);
+    }
+    metadata.insert(SUMMARY_OF_KEY.to_string(), entry_id.to_string());
+    Document {
+        id: summary_id(entry_id),
+        content: summary,
+        embedding,
+        metadata,
+    }
+}
+
+/// Hex SHA-256 of chunk content
+pub fn chunk_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+fn stored_hash(document: &Document) -> String {
+    document.metadata.get(CHUNK_HASH_KEY)
+        .cloned()
+        .unwrap_or_else(|| chunk_hash(&document.content))
+}
+
+/// Read the stored chunks of an entry in order; empty if the entry does not exist
+pub async fn load_chunks(store: &dyn VectorStore, collection: &str, entry_id: &str) -> Result<Vec<Document>, VectorStoreError> {
+    let mut chunks = Vec::new();
+    while let Some(document) = store.get_document(collection, &chunk_id(entry_id, chunks.len())).await? {
+        chunks.push(document);
+    }
+    Ok(chunks)
+}
+
+/// Hits the first stage of a search fetches per entry wanted, since the
+/// chunks of one entry can crowd out the others
+const ENTRY_HIT_OVERFETCH: usize = 4;
+
+/// An entry found by the first stage of a search
+enum EntryHit {
+    /// An entry stored whole, as its only document
+    Whole(SearchResult),
+    /// The id of a chunked entry
+    Chunked(String),
+}
+
+/// The first `entries` distinct entries of `hits`, best first. A chunked
+/// entry is found by its summary, or by a chunk when it has none.
+fn best_entries(hits: Vec<SearchResult>, entries: usize) -> Vec<EntryHit> {
+    let mut seen: Vec<String> = Vec::new();
+    let mut best = Vec::new();
+    for hit in hits {
+        if best.len() == entries {
+            break;
+        }
+        let entry = match (summary_of(&hit.document), hit.document.metadata.get(PARENT_ID_KEY)) {
+            (Some(entry), _) => entry.to_string(),
+            (None, Some(parent)) => parent.clone(),
+            (None, None) => hit.document.id.clone(),
+        };
+        if seen.contains(&entry) {
+            continue;
+        }
+        seen.push(entry.clone());
+        if summary_of(&hit.document).is_some() || hit.document.metadata.contains_key(PARENT_ID_KEY) {
+            best.push(EntryHit::Chunked(entry));
+        } else {
+            best.push(EntryHit::Whole(hit));
+        }
+    }
+    best
+}
+
+/// The first stage of a two-stage or hierarchical search
+async fn search_entries(store: &dyn VectorStore, collection: &str, query: &SearchQuery, entries: usize) -> Result<Vec<EntryHit>, VectorStoreError> {
+    let entries = entries.max(1);
+    let stage_one = SearchQuery { embedding: query.embedding.clone(), limit: entries * ENTRY_HIT_OVERFETCH };
+    Ok(best_entries(store.search(collection, stage_one).await?, entries))
+}
+
+/// Search in two stages: find the `entries` best entries, by their summary
+/// where they have one, then score all of their chunks against the query.
+///
+/// Chunks are scored by cosine similarity here rather than by the backend,
+/// so a long entry's best passage is found even when the passage alone
+/// would not have ranked. Summary documents are never returned.
+pub async fn search_two_stage(store: &dyn VectorStore, collection: &str, query: SearchQuery, entries: usize) -> Result<Vec<SearchResult>, VectorStoreError> {
+    let mut chunks = Vec::new();
+    for entry in search_entries(store, collection, &query, entries).await? {
+        match entry {
+            EntryHit::Whole(hit) => chunks.push(hit.document),
+            EntryHit::Chunked(entry) => chunks.extend(load_chunks(store, collection, &entry).await?),
+        }
+    }
+    
+    let mut results: Vec<SearchResult> = chunks.into_iter()
+        .map(|document| SearchResult {
+            score: cosine_similarity(&query.embedding, &document.embedding),
+            metric: DistanceMetric::Cosine,
+            document,
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.truncate(query.limit);
+    Ok(results)
+}
+
+/// Search hierarchically: find the `entries` best entries, then search the
+/// chunks of each with the backend, restricted to the entry by a metadata
+/// filter, and merge the results.
+///
+/// No entry is read whole, so unlike two-stage search this suits
+/// collections of many long entries. Summary documents are never returned.
+pub async fn search_hierarchical(store: &dyn VectorStore, collection: &str, query: SearchQuery, entries: usize) -> Result<Vec<SearchResult>, VectorStoreError> {
+    let mut results = Vec::new();
+    for entry in search_entries(store, collection, &query, entries).await? {
+        match entry {
+            EntryHit::Whole(hit) => results.push(hit),
+            EntryHit::Chunked(entry) => {
+                let filter = MetadataFilter {
+                    metadata: BTreeMap::from([(PARENT_ID_KEY.to_string(), entry)]),
+                    ..Default::default()
+                };
+                results.extend(store.search_filtered(collection, query.clone(), &filter).await?);
+            },
+        }
+    }
+    results.sort_by(|a, b| b.relevance().total_cmp(&a.relevance()));
+    results.truncate(query.limit);
+    Ok(results)
+}
+
+/// One chunk of the updated entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedChunk {
+    pub index: usize,
+    pub content: String,
+    pub hash: String,
+    /// The embedding of a stored chunk with identical content, if there is one
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// What an update changes, chunk by chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkPlan {
+    pub chunks: Vec<PlannedChunk>,
+    /// Ids of stored chunks beyond the end of the new content
+    pub removed: Vec<String>,
+}
+
+impl ChunkPlan {
+    /// Chunks whose embedding is carried over from the stored entry
+    pub fn reused(&self) -> usize {
+        self.chunks.iter().filter(|chunk| chunk.embedding.is_some()).count()
+    }
+    
+    /// Chunks that must be embedded
+    pub fn recomputed(&self) -> usize {
+        self.chunks.len() - self.reused()
+    }
+}
+
+/// Match new chunk contents against the stored chunks by hash.
+///
+/// A chunk reuses the embedding of any stored chunk with the same content,
+/// wherever it was, so moved and unchanged paragraphs are not re-embedded.
+pub fn plan_chunk_update(entry_id: &str, new_chunks: &[String], stored: &[Document]) -> ChunkPlan {
+    let stored_embeddings: HashMap<String, &Vec<f32>> = stored.iter()
+        .map(|document| (stored_hash(document), &document.embedding))
+        .collect();
+    
+    let chunks = new_chunks.iter()
+        .enumerate()
+        .map(|(index, content)| {
+            let hash = chunk_hash(content);
+            PlannedChunk {
+                index,
+                content: content.clone(),
+                embedding: stored_embeddings.get(&hash).map(|embedding| (*embedding).clone()),
+                hash,
+            }
+        })
+        .collect();
+    
+    let removed = (new_chunks.len()..stored.len())
+        .map(|index| chunk_id(entry_id, index))
+        .collect();
+    
+    ChunkPlan { chunks, removed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    fn stored_chunk(entry_id: &str, index: usize, content: &str, embedding: Vec<f32>) -> Document {
+        let mut document = Document {
+            id: chunk_id(entry_id, index),
+            content: content.to_string(),
+            embedding,
+            metadata: Default::default(),
+        };
+        document.metadata.insert(CHUNK_HASH_KEY.to_string(), chunk_hash(content));
+        document
+    }
+    
+    #[test]
+    fn test_chunk_ids() {
+        assert_eq!(chunk_id("entry", 0), "entry");
+        assert_eq!(chunk_id("entry", 1), chunk_id("entry", 1));
+        assert_ne!(chunk_id("entry", 1), chunk_id("entry", 2));
+        assert!(Uuid::parse_str(&chunk_id("entry", 3)).is_ok());
+    }
+    
+    #[test]
+    fn test_plan_reuses_unchanged_and_moved_chunks() {
+        let stored = vec![
+            stored_chunk("e", 0, "intro", vec![1.0]),
+            stored_chunk("e", 1, "middle", vec![2.0]),
+            stored_chunk("e", 2, "outro", vec![3.0]),
+        ];
+        let new_chunks = vec!["intro".to_string(), "outro".to_string()];
+        
+        let plan = plan_chunk_update("e", &new_chunks, &stored);
+        assert_eq!(plan.reused(), 2);
+        assert_eq!(plan.recomputed(), 0);
+        assert_eq!(plan.chunks[1].embedding, Some(vec![3.0]));
+        assert_eq!(plan.removed, vec![chunk_id("e", 2)]);
+        
+        let plan = plan_chunk_update("e", &["intro".to_string(), "changed".to_string()], &stored[..1]);
+        assert_eq!((plan.reused(), plan.recomputed()), (1, 1));
+        assert!(plan.removed.is_empty());
+    }
+    
+    /// A long entry whose summary matches `[1, 0]` but whose best chunk is off
+    /// to the side, and a short entry stored whole
+    async fn long_and_short_entries() -> InMemoryVectorStore {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        for (index, embedding) in [vec![0.0, 1.0], vec![0.6, 0.8]].into_iter().enumerate() {
+            let mut chunk = stored_chunk("long", index, &format!("part {}", index), embedding);
+            chunk.metadata.insert(PARENT_ID_KEY.to_string(), "long".to_string());
+            store.insert_document("docs", chunk).await.unwrap();
+        }
+        let mut summary = stored_chunk("long", 0, "summary", vec![1.0, 0.1]);
+        summary.id = summary_id("long");
+        summary.metadata.insert(SUMMARY_OF_KEY.to_string(), "long".to_string());
+        store.insert_document("docs", summary).await.unwrap();
+        store.insert_document("docs", stored_chunk("short", 0, "short entry", vec![0.9, -0.5])).await.unwrap();
+        store
+    }
+    
+    #[tokio::test]
+    async fn test_two_stage_search_scores_chunks_of_matched_entries() {
+        let store = long_and_short_entries().await;
+        let query = SearchQuery { embedding: vec![1.0, 0.0], limit: 2 };
+        let results = search_two_stage(&store, "docs", query.clone(), 1).await.unwrap();
+        let ids: Vec<&str> = results.iter().map(|result| result.document.id.as_str()).collect();
+        assert_eq!(ids, [chunk_id("long", 1), chunk_id("long", 0)]);
+        
+        let results = search_two_stage(&store, "docs", query, 2).await.unwrap();
+        assert_eq!(results[0].document.id, "short");
+        assert!(results.iter().all(|result| summary_of(&result.document).is_none()));
+    }
+    
+    #[tokio::test]
+    async fn test_hierarchical_search_filters_chunks_to_matched_entries() {
+        let store = long_and_short_entries().await;
+        let query = SearchQuery { embedding: vec![1.0, 0.0], limit: 3 };
+        let results = search_hierarchical(&store, "docs", query.clone(), 1).await.unwrap();
+        let ids: Vec<&str> = results.iter().map(|result| result.document.id.as_str()).collect();
+        assert_eq!(ids, [chunk_id("long", 1), chunk_id("long", 0)]);
+        
+        let results = search_hierarchical(&store, "docs", query, 2).await.unwrap();
+        let ids: Vec<&str> = results.iter().map(|result| result.document.id.as_str()).collect();
+        assert_eq!(ids, ["short".to_string(), chunk_id("long", 1), chunk_id("long", 0)]);
+    }
+    
+    #[tokio::test]
+    async fn test_load_chunks_reads_in_order() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 1).await.unwrap();
+        store.insert_document("docs", stored_chunk("e", 1, "second", vec![1.0])).await.unwrap();
+        assert!(load_chunks(&store, "docs", "e").await.unwrap().is_empty());
+        
+        store.insert_document("docs", stored_chunk("e", 0, "first", vec![1.0])).await.unwrap();
+        let chunks = load_chunks(&store, "docs", "e").await.unwrap();
+        assert_eq!(chunks.iter().map(|chunk| chunk.content.as_str()).collect::<Vec<_>>(), vec!["first", "second"]);
+    }
+}
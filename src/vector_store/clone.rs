@@ -0,0 +1,194 @@
+//! Copying a collection, or a filtered or sampled part of it, into a new one.
+//!
+//! Useful for building test and staging datasets from production knowledge.
+//! Documents keep their ids, content, embeddings and metadata.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::filter::{MetadataFilter, FILTER_PAGE_SIZE};
+use super::{Document, VectorStore, VectorStoreError};
+use crate::progress::Progress;
+
+/// Which documents to copy
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CloneOptions {
+    /// Only documents matching this filter are candidates
+    pub filter: MetadataFilter,
+    /// Copy a uniform random sample of this many candidates instead of all of them
+    pub sample: Option<usize>,
+    /// Seed of the sample, for a reproducible one; random when unset
+    pub seed: Option<u64>,
+    /// Report what would be copied without creating anything
+    pub dry_run: bool,
+}
+
+/// Outcome of a clone
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CloneOutcome {
+    /// Documents in the source matching the filter
+    pub matched: usize,
+    /// Documents copied, or that would be with `dry_run`
+    pub copied: usize,
+}
+
+/// A small seeded generator (SplitMix64), enough for sampling
+struct SampleRng(u64);
+
+impl SampleRng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    
+    /// Uniform in `0..bound`
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// Copy the documents of `source` selected by `options` into a new collection `target`.
+///
+/// `target` must not exist yet; it is created with the dimension of the
+/// copied embeddings. Samples are drawn by reservoir sampling, so only the
+/// sample is held in memory.
+pub async fn clone_collection(
+    store: &dyn VectorStore,
+    source: &str,
+    target: &str,
+    options: &CloneOptions,
+    progress: &Progress,
+) -> Result<CloneOutcome, VectorStoreError> {
+    if source == target {
+        return Err(VectorStoreError::InvalidArgument("Source and target collections must differ".to_string()));
+    }
+    let collections = store.list_collections().await?;
+    if !collections.iter().any(|name| name == source) {
+        return Err(VectorStoreError::CollectionNotFound(source.to_string()));
+    }
+    if collections.iter().any(|name| name == target) {
+        return Err(VectorStoreError::InvalidArgument(format!("Collection {} already exists", target)));
+    }
+    
+    if let Ok(total) = store.count(source, &options.filter).await {
+        progress.set_total(options.sample.map_or(total, |sample| sample.min(total)) as u64);
+    }
+    
+    let mut rng = SampleRng(options.seed.unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_nanos() as u64).unwrap_or_default()
+    }));
+    let mut outcome = CloneOutcome::default();
+    let mut reservoir: Vec<Document> = Vec::new();
+    let mut created = false;
+    let mut offset = None;
+    loop {
+        let page = store.list_documents(source, offset, FILTER_PAGE_SIZE).await?;
+        for document in page.documents {
+            if !options.filter.matches(&document) {
+                continue;
+            }
+            outcome.matched += 1;
+            
+            match options.sample {
+                Some(size) if reservoir.len() < size => reservoir.push(document),
+                Some(_) => {
+                    let slot = rng.below(outcome.matched);
+                    if slot < reservoir.len() {
+                        reservoir[slot] = document;
+                    }
+                },
+                None => {
+                    outcome.copied += 1;
+                    if !options.dry_run {
+                        copy(store, target, document, &mut created).await?;
+                    }
+                    progress.inc(1);
+                },
+            }
+        }
+        offset = match page.next_offset {
+            Some(next) => Some(next),
+            None => break,
+        };
+    }
+    
+    outcome.copied += reservoir.len();
+    if !options.dry_run {
+        for document in reservoir {
+            copy(store, target, document, &mut created).await?;
+            progress.inc(1);
+        }
+    }
+    Ok(outcome)
+}
+
+async fn copy(store: &dyn VectorStore, target: &str, document: Document, created: &mut bool) -> Result<(), VectorStoreError> {
+    if !*created {
+        store.create_collection(target, document.embedding.len()).await?;
+        *created = true;
+    }
+    store.insert_document(target, document).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    async fn source() -> InMemoryVectorStore {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("prod", 2).await.unwrap();
+        for index in 0..20 {
+            let tag = if index % 2 == 0 { "even" } else { "odd" };
+            let mut document = Document::with_placeholder_embedding(format!("entry {}", index), 2).with_tags(&[tag.to_string()]);
+            document.id = format!("e{}", index);
+            store.insert_document("prod", document).await.unwrap();
+        }
+        store
+    }
+    
+    async fn sorted_ids(store: &InMemoryVectorStore, collection: &str) -> Vec<String> {
+        let mut ids: Vec<String> = store.list_documents(collection, None, 50).await.unwrap()
+            .documents
+            .into_iter()
+            .map(|document| document.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+    
+    #[tokio::test]
+    async fn test_clone_filtered_subset() {
+        let store = source().await;
+        let options = CloneOptions {
+            filter: MetadataFilter { tags: vec!["even".to_string()], ..MetadataFilter::default() },
+            ..CloneOptions::default()
+        };
+        let outcome = clone_collection(&store, "prod", "staging", &options, &Progress::hidden()).await.unwrap();
+        assert_eq!(outcome, CloneOutcome { matched: 10, copied: 10 });
+        assert!(store.get_document("staging", "e4").await.unwrap().is_some());
+        assert!(store.get_document("staging", "e5").await.unwrap().is_none());
+        
+        // The target now exists
+        assert!(clone_collection(&store, "prod", "staging", &options, &Progress::hidden()).await.is_err());
+        assert!(clone_collection(&store, "missing", "other", &options, &Progress::hidden()).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_clone_sample_is_seeded() {
+        let store = source().await;
+        let options = CloneOptions { sample: Some(5), seed: Some(7), ..CloneOptions::default() };
+        
+        let dry = clone_collection(&store, "prod", "sample", &CloneOptions { dry_run: true, ..options.clone() }, &Progress::hidden()).await.unwrap();
+        assert_eq!(dry, CloneOutcome { matched: 20, copied: 5 });
+        assert!(!store.list_collections().await.unwrap().contains(&"sample".to_string()));
+        
+        clone_collection(&store, "prod", "sample", &options, &Progress::hidden()).await.unwrap();
+        clone_collection(&store, "prod", "sample-again", &options, &Progress::hidden()).await.unwrap();
+        let sample = sorted_ids(&store, "sample").await;
+        assert_eq!(sample.len(), 5);
+        assert_eq!(sample, sorted_ids(&store, "sample-again").await);
+    }
+}
@@ -0,0 +1,127 @@
+//! Compaction of the embedded store.
+//!
+//! Deletes and replacements leave dead data behind in two places: records in
+//! the write-ahead log that later changes undid, and, in memory, documents
+//! held only by abandoned listings' snapshots and keyword indexes sized for
+//! documents long gone. A compaction checkpoints the log, rewriting it as the
+//! records of the live state, then vacuums the store, and reports the space
+//! reclaimed. Neither step holds up reads, and writes wait only for the swaps
+//! at their ends.
+//!
+//! The maintenance scheduler compacts on every run that finds something
+//! changed; `POST /api/admin/compact` and `p-mo compact` compact on demand.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+use super::{CheckpointReport, InMemoryVectorStore, VacuumReport, VectorStoreError, WalVectorStore};
+
+/// What a compaction did
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactionReport {
+    /// The log checkpoint; `None` without a log, or when only compacting
+    /// what changed and nothing was logged since the last checkpoint
+    pub checkpoint: Option<CheckpointReport>,
+    pub vacuum: VacuumReport,
+}
+
+impl CompactionReport {
+    /// Bytes reclaimed on disk and in memory together
+    pub fn reclaimed_bytes(&self) -> u64 {
+        self.checkpoint.as_ref().map_or(0, CheckpointReport::reclaimed_bytes) + self.vacuum.reclaimed_bytes
+    }
+    
+    /// Whether the compaction found nothing to do
+    pub fn is_empty(&self) -> bool {
+        self.checkpoint.is_none() && self.vacuum.is_empty()
+    }
+}
+
+impl fmt::Display for CompactionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "reclaimed {} bytes", self.reclaimed_bytes())?;
+        if let Some(checkpoint) = &self.checkpoint {
+            write!(f, "; log {}", checkpoint)?;
+        }
+        write!(
+            f,
+            "; rebuilt {} keyword indexes, released {} idle listings holding {} dead documents",
+            self.vacuum.indexes_rebuilt, self.vacuum.snapshots_released, self.vacuum.documents_released
+        )
+    }
+}
+
+/// Compacts the embedded store and its write-ahead log, if it has one
+pub struct Compactor {
+    store: Arc<InMemoryVectorStore>,
+    wal: Option<Arc<WalVectorStore>>,
+}
+
+impl Compactor {
+    pub fn new(store: Arc<InMemoryVectorStore>) -> Self {
+        Self { store, wal: None }
+    }
+    
+    /// Checkpoint `wal`, which logs the store, as part of each compaction
+    pub fn with_wal(mut self, wal: Arc<WalVectorStore>) -> Self {
+        self.wal = Some(wal);
+        self
+    }
+    
+    /// Checkpoint the log and vacuum the store
+    pub async fn compact(&self) -> Result<CompactionReport, VectorStoreError> {
+        let checkpoint = match &self.wal {
+            Some(wal) => Some(wal.checkpoint().await?),
+            None => None,
+        };
+        Ok(CompactionReport { checkpoint, vacuum: self.vacuum().await? })
+    }
+    
+    /// [`compact`](Self::compact), but checkpoint the log only if anything
+    /// was logged since the last checkpoint
+    pub async fn compact_changed(&self) -> Result<CompactionReport, VectorStoreError> {
+        let checkpoint = match &self.wal {
+            Some(wal) => wal.checkpoint_if_changed().await?,
+            None => None,
+        };
+        Ok(CompactionReport { checkpoint, vacuum: self.vacuum().await? })
+    }
+    
+    async fn vacuum(&self) -> Result<VacuumReport, VectorStoreError> {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || store.vacuum()).await
+            .map_err(|e| VectorStoreError::OperationFailed(format!("Vacuum failed: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FsyncPolicy;
+    use crate::vector_store::{Document, VectorStore};
+    use std::time::Duration;
+    
+    #[tokio::test]
+    async fn test_compaction_checkpoints_and_vacuums() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(InMemoryVectorStore::new());
+        let (wal, _) = WalVectorStore::open(store.clone(), &temp_dir.path().join("wal.log"), FsyncPolicy::Always, Duration::from_secs(1)).await.unwrap();
+        let wal = Arc::new(wal);
+        wal.create_collection("docs", 2).await.unwrap();
+        let mut document = Document::with_placeholder_embedding("note".to_string(), 2);
+        document.id = "a".to_string();
+        wal.insert_document("docs", document).await.unwrap();
+        wal.delete_document("docs", "a").await.unwrap();
+        
+        let compactor = Compactor::new(store).with_wal(wal);
+        let report = compactor.compact_changed().await.unwrap();
+        let checkpoint = report.checkpoint.as_ref().unwrap();
+        assert_eq!((checkpoint.records_before, checkpoint.records_after), (3, 1));
+        assert_eq!(report.vacuum.indexes_rebuilt, 1);
+        assert_eq!(report.reclaimed_bytes(), checkpoint.reclaimed_bytes());
+        
+        assert!(compactor.compact_changed().await.unwrap().is_empty());
+        assert!(compactor.compact().await.unwrap().checkpoint.is_some());
+    }
+}
@@ -0,0 +1,107 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+
+use super::VectorStoreError;
+use crate::config::CompressionConfig;
+
+/// Payload key recording how the stored content is encoded; absent for plain text
+pub const CONTENT_ENCODING_KEY: &str = "content_encoding";
+
+/// Encoding of zstd-compressed, base64-wrapped content
+pub const ZSTD_ENCODING: &str = "zstd+base64";
+
+/// When and how hard to compress stored content
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PayloadCompression {
+    /// Content shorter than this many bytes is stored as-is
+    pub threshold_bytes: usize,
+    /// zstd compression level
+    pub level: i32,
+}
+
+impl Default for PayloadCompression {
+    fn default() -> Self {
+        Self::from_config(&CompressionConfig::default())
+    }
+}
+
+impl PayloadCompression {
+    /// Create settings from configuration
+    pub fn from_config(config: &CompressionConfig) -> Self {
+        Self {
+            threshold_bytes: config.threshold_bytes,
+            level: config.level,
+        }
+    }
+    
+    /// Whether content of `len` bytes should be compressed
+    pub fn should_compress(&self, len: usize) -> bool {
+        len >= self.threshold_bytes
+    }
+    
+    /// Encode content for storage, returning the stored text and its encoding.
+    ///
+    /// Content stays uncompressed when it is under the threshold or when
+    /// compression would not make it smaller.
+    pub fn encode(&self, content: &str) -> Result<(String, Option<&'static str>), VectorStoreError> {
+        if !self.should_compress(content.len()) {
+            return Ok((content.to_string(), None));
+        }
+        
+        let compressed = zstd::encode_all(content.as_bytes(), self.level)
+            .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to compress content: {}", e)))?;
+        let encoded = BASE64.encode(compressed);
+        if encoded.len() >= content.len() {
+            return Ok((content.to_string(), None));
+        }
+        Ok((encoded, Some(ZSTD_ENCODING)))
+    }
+}
+
+/// Decode stored content according to its encoding flag
+pub fn decode_content(stored: &str, encoding: Option<&str>) -> Result<String, VectorStoreError> {
+    match encoding {
+        None | Some("identity") => Ok(stored.to_string()),
+        Some(ZSTD_ENCODING) => {
+            let compressed = BASE64.decode(stored)
+                .map_err(|e| VectorStoreError::OperationFailed(format!("Corrupt compressed content: {}", e)))?;
+            let bytes = zstd::decode_all(compressed.as_slice())
+                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to decompress content: {}", e)))?;
+            String::from_utf8(bytes)
+                .map_err(|_| VectorStoreError::OperationFailed("Decompressed content is not UTF-8".to_string()))
+        },
+        Some(other) => Err(VectorStoreError::OperationFailed(format!("Unknown content encoding: {}", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn compression(threshold_bytes: usize) -> PayloadCompression {
+        PayloadCompression { threshold_bytes, level: 3 }
+    }
+    
+    #[test]
+    fn test_small_content_is_left_alone() {
+        let (stored, encoding) = compression(1024).encode("short").unwrap();
+        assert_eq!(stored, "short");
+        assert_eq!(encoding, None);
+    }
+    
+    #[test]
+    fn test_round_trip() {
+        let content = "The same sentence over and over. ".repeat(200);
+        let (stored, encoding) = compression(1024).encode(&content).unwrap();
+        
+        assert_eq!(encoding, Some(ZSTD_ENCODING));
+        assert!(stored.len() < content.len() / 4);
+        assert_eq!(decode_content(&stored, encoding).unwrap(), content);
+    }
+    
+    #[test]
+    fn test_unknown_encoding_is_an_error() {
+        assert!(decode_content("abc", Some("gzip")).is_err());
+        assert_eq!(decode_content("abc", None).unwrap(), "abc");
+    }
+}
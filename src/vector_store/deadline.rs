@@ -0,0 +1,99 @@
+//! Caller deadlines for backend operations.
+//!
+//! A request's deadline reaches the stores through a task-local, as stage
+//! timings are collected in [`trace`](super::trace), so the `VectorStore`
+//! trait does not change. Qdrant calls cap their timeouts to the time left
+//! and stop retrying once it is gone. Stores that can return what they have
+//! found so far, such as the in-memory store's scan, stop early instead and
+//! mark the operation partial.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use super::VectorStoreError;
+
+struct Deadline {
+    at: Instant,
+    partial: Cell<bool>,
+}
+
+tokio::task_local! {
+    static DEADLINE: Deadline;
+}
+
+/// Run `future` under `deadline`, also returning whether a store cut its
+/// work short to meet it. Without a deadline `future` runs unbounded.
+pub async fn with_deadline<F: Future>(deadline: Option<Instant>, future: F) -> (F::Output, bool) {
+    let at = match deadline {
+        Some(at) => at,
+        None => return (future.await, false),
+    };
+    DEADLINE.scope(Deadline { at, partial: Cell::new(false) }, async {
+        let output = future.await;
+        let partial = DEADLINE.with(|deadline| deadline.partial.get());
+        (output, partial)
+    }).await
+}
+
+/// Time left before the current deadline; `None` when there is none
+pub fn remaining() -> Option<Duration> {
+    DEADLINE.try_with(|deadline| deadline.at.saturating_duration_since(Instant::now())).ok()
+}
+
+/// Whether the current deadline has passed
+pub fn is_past() -> bool {
+    remaining().is_some_and(|remaining| remaining.is_zero())
+}
+
+/// Record that results were cut short to meet the deadline
+pub fn mark_partial() {
+    let _ = DEADLINE.try_with(|deadline| deadline.partial.set(true));
+}
+
+/// `timeout`, shortened to the time left before the deadline
+pub fn cap(timeout: Duration) -> Duration {
+    remaining().map_or(timeout, |remaining| remaining.min(timeout))
+}
+
+/// Await `future`, failing with `DeadlineExceeded` if the deadline passes first
+pub async fn bounded<T, F>(future: F) -> Result<T, VectorStoreError>
+where
+    F: Future<Output = Result<T, VectorStoreError>>,
+{
+    match remaining() {
+        Some(remaining) => tokio::time::timeout(remaining, future).await
+            .unwrap_or(Err(VectorStoreError::DeadlineExceeded)),
+        None => future.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_without_deadline_nothing_is_bounded() {
+        let (remaining, partial) = with_deadline(None, async { remaining() }).await;
+        assert!(remaining.is_none());
+        assert!(!partial);
+        assert_eq!(cap(Duration::from_secs(5)), Duration::from_secs(5));
+    }
+    
+    #[tokio::test]
+    async fn test_deadline_caps_and_reports_partial() {
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let (capped, partial) = with_deadline(Some(deadline), async {
+            mark_partial();
+            cap(Duration::from_secs(600))
+        }).await;
+        assert!(capped <= Duration::from_secs(60));
+        assert!(partial);
+        
+        let (result, _) = with_deadline(Some(Instant::now()), bounded(async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        })).await;
+        assert!(matches!(result, Err(VectorStoreError::DeadlineExceeded)));
+    }
+}
@@ -0,0 +1,241 @@
+//! Detecting collections whose schema drifted from the registry.
+//!
+//! A collection created outside p-mo, or recreated by hand, can hold
+//! vectors of a different size or compare them with a different distance
+//! than the registry says. Inserts then fail deep inside the backend and
+//! searches return scores that mean something else, so drift is checked
+//! when the server starts and again the first time each collection is used,
+//! and reported as a [`VectorStoreError::SchemaMismatch`] naming both sides.
+//! `p-mo reconcile` repairs it either way round.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::RwLock;
+
+use super::{CollectionInfo, CollectionRegistry, DistanceMetric, VectorStore, VectorStoreError};
+
+/// The vector size and distance a collection was created with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollectionSchema {
+    pub vector_size: usize,
+    /// Distance name as the backend reports it, e.g. "cosine"; `None` if undeclared
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance: Option<String>,
+}
+
+impl CollectionSchema {
+    pub fn new(vector_size: usize, distance: Option<&str>) -> Self {
+        Self {
+            vector_size,
+            distance: distance.map(str::to_lowercase),
+        }
+    }
+    
+    /// The schema the registry expects of a collection
+    pub fn expected(info: &CollectionInfo) -> Self {
+        Self::new(info.vector_size, info.distance.map(|distance| distance.as_str()))
+    }
+    
+    /// Whether a collection with schema `actual` satisfies this one; an
+    /// undeclared distance accepts any
+    pub fn accepts(&self, actual: &CollectionSchema) -> bool {
+        self.vector_size == actual.vector_size
+            && match (&self.distance, &actual.distance) {
+                (Some(expected), Some(actual)) => expected == actual,
+                _ => true,
+            }
+    }
+}
+
+impl fmt::Display for CollectionSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.distance {
+            Some(distance) => write!(f, "{} dimensions with {} distance", self.vector_size, distance),
+            None => write!(f, "{} dimensions", self.vector_size),
+        }
+    }
+}
+
+/// The schema the registry expects of `collection` and the one it has, when both are known
+async fn schemas(store: &dyn VectorStore, registry: &CollectionRegistry, collection: &str) -> Result<Option<(CollectionSchema, CollectionSchema)>, VectorStoreError> {
+    let info = match registry.get(collection) {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+    Ok(store.collection_schema(collection).await?.map(|actual| (CollectionSchema::expected(&info), actual)))
+}
+
+fn mismatch(collection: &str, expected: CollectionSchema, actual: CollectionSchema) -> VectorStoreError {
+    VectorStoreError::SchemaMismatch { collection: collection.to_string(), expected, actual }
+}
+
+/// Check the registered schema of `collection` against the backend's.
+///
+/// Collections that are unregistered, do not exist yet, or whose store
+/// cannot report a schema are not checked.
+pub async fn check_schema(store: &dyn VectorStore, registry: &CollectionRegistry, collection: &str) -> Result<(), VectorStoreError> {
+    match schemas(store, registry, collection).await? {
+        Some((expected, actual)) if !expected.accepts(&actual) => Err(mismatch(collection, expected, actual)),
+        _ => Ok(()),
+    }
+}
+
+/// Check every registered collection, returning the mismatches found
+pub async fn detect_drift(store: &dyn VectorStore, registry: &CollectionRegistry) -> Result<Vec<VectorStoreError>, VectorStoreError> {
+    let mut mismatches = Vec::new();
+    for info in registry.list() {
+        match check_schema(store, registry, &info.name).await {
+            Ok(()) => {},
+            Err(mismatch @ VectorStoreError::SchemaMismatch { .. }) => mismatches.push(mismatch),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Remembers which collections have been checked, so each is only checked on first use
+#[derive(Debug, Default)]
+pub struct SchemaGuard {
+    verified: RwLock<HashSet<String>>,
+}
+
+impl SchemaGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Check `collection` unless it already passed; a mismatch is reported on
+    /// every use until it is reconciled
+    pub async fn verify(&self, store: &dyn VectorStore, registry: &CollectionRegistry, collection: &str) -> Result<(), VectorStoreError> {
+        if self.verified.read().unwrap().contains(collection) {
+            return Ok(());
+        }
+        
+        match schemas(store, registry, collection).await? {
+            Some((expected, actual)) if !expected.accepts(&actual) => Err(mismatch(collection, expected, actual)),
+            Some(_) => {
+                self.verified.write().unwrap().insert(collection.to_string());
+                Ok(())
+            },
+            None => Ok(()),
+        }
+    }
+    
+    /// Check `collection` again on its next use
+    pub fn forget(&self, collection: &str) {
+        self.verified.write().unwrap().remove(collection);
+    }
+}
+
+/// How `p-mo reconcile` resolves a mismatch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reconciliation {
+    /// Record the collection's actual schema in the registry
+    UpdateRegistry,
+    /// Delete the collection, and all its entries, and create it again as the registry expects
+    Recreate,
+}
+
+/// Resolve drift in `collection` as `how` says; returns the schema the
+/// collection and registry now share, or `None` if nothing had drifted
+pub async fn reconcile(
+    store: &dyn VectorStore,
+    registry: &CollectionRegistry,
+    collection: &str,
+    how: Reconciliation,
+) -> Result<Option<CollectionSchema>, VectorStoreError> {
+    let (expected, actual) = match schemas(store, registry, collection).await? {
+        Some((expected, actual)) if !expected.accepts(&actual) => (expected, actual),
+        _ => return Ok(None),
+    };
+    
+    match how {
+        Reconciliation::UpdateRegistry => {
+            let distance = match actual.distance.as_deref() {
+                None => None,
+                Some(name) => Some(DistanceMetric::parse(name).ok_or_else(|| VectorStoreError::InvalidArgument(format!(
+                    "Collection '{}' uses {} distance, which p-mo cannot search; recreate it instead",
+                    collection, name
+                )))?),
+            };
+            registry.set_vector_schema(collection, actual.vector_size, distance)?;
+            Ok(Some(actual))
+        },
+        Reconciliation::Recreate => {
+            store.delete_collection(collection).await?;
+            store.create_collection(collection, expected.vector_size).await?;
+            check_schema(store, registry, collection).await?;
+            Ok(Some(expected))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    #[test]
+    fn test_undeclared_distance_accepts_any() {
+        let expected = CollectionSchema::new(384, None);
+        assert!(expected.accepts(&CollectionSchema::new(384, Some("Dot"))));
+        assert!(!expected.accepts(&CollectionSchema::new(768, Some("cosine"))));
+        assert!(!CollectionSchema::new(384, Some("cosine")).accepts(&CollectionSchema::new(384, Some("dot"))));
+        assert_eq!(CollectionSchema::new(384, Some("Cosine")).to_string(), "384 dimensions with cosine distance");
+    }
+    
+    #[tokio::test]
+    async fn test_mismatch_names_both_schemas() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 768).await.unwrap();
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("docs", 384));
+        registry.register(CollectionInfo::new("unused", 384));
+        
+        let error = check_schema(&store, &registry, "docs").await.unwrap_err();
+        match &error {
+            VectorStoreError::SchemaMismatch { collection, expected, actual } => {
+                assert_eq!(collection, "docs");
+                assert_eq!(expected.vector_size, 384);
+                assert_eq!(actual.vector_size, 768);
+            },
+            other => panic!("unexpected error: {:?}", other),
+        }
+        let message = error.to_string();
+        assert!(message.contains("384 dimensions") && message.contains("768 dimensions with cosine distance"));
+        
+        // Registered collections that do not exist yet are not drift
+        assert_eq!(detect_drift(&store, &registry).await.unwrap().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_guard_rechecks_until_reconciled() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 768).await.unwrap();
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("docs", 384).with_distance(DistanceMetric::Cosine));
+        let guard = SchemaGuard::new();
+        
+        assert!(guard.verify(&store, &registry, "docs").await.is_err());
+        assert!(guard.verify(&store, &registry, "docs").await.is_err());
+        
+        let schema = reconcile(&store, &registry, "docs", Reconciliation::UpdateRegistry).await.unwrap();
+        assert_eq!(schema, Some(CollectionSchema::new(768, Some("cosine"))));
+        assert_eq!(registry.get("docs").unwrap().vector_size, 768);
+        assert!(guard.verify(&store, &registry, "docs").await.is_ok());
+        assert_eq!(reconcile(&store, &registry, "docs", Reconciliation::Recreate).await.unwrap(), None);
+    }
+    
+    #[tokio::test]
+    async fn test_recreate_follows_the_registry() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 768).await.unwrap();
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("docs", 384));
+        
+        reconcile(&store, &registry, "docs", Reconciliation::Recreate).await.unwrap();
+        assert_eq!(store.collection_schema("docs").await.unwrap(), Some(CollectionSchema::new(384, Some("cosine"))));
+        assert!(check_schema(&store, &registry, "docs").await.is_ok());
+    }
+}
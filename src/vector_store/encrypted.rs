@@ -0,0 +1,378 @@
+//! Transparent encryption of document content and metadata at rest.
+//!
+//! Embeddings are stored unencrypted so that similarity search keeps working.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::filter::count_matching;
+use super::{CollectionSchema, Document, DocumentPage, MetadataFilter, PoolMetrics, SearchQuery, SearchResult, VectorStore, VectorStoreError};
+use crate::config::EncryptionConfig;
+use crate::progress::Progress;
+
+/// Prefix marking an encrypted value; followed by `<key id>:<base64 nonce + ciphertext>`
+pub const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+const NONCE_LEN: usize = 12;
+
+/// A 256-bit AES-GCM key
+#[derive(Clone)]
+pub struct EncryptionKey {
+    cipher: Aes256Gcm,
+    id: String,
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionKey").field("id", &self.id).finish_non_exhaustive()
+    }
+}
+
+impl EncryptionKey {
+    /// Create a key from 32 raw bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, VectorStoreError> {
+        let cipher = Aes256Gcm::new_from_slice(bytes).map_err(|_| {
+            VectorStoreError::InvalidArgument(format!("Encryption key must be 32 bytes, got {}", bytes.len()))
+        })?;
+        let id = Sha256::digest(bytes)[..4].iter().map(|b| format!("{:02x}", b)).collect();
+        Ok(Self { cipher, id })
+    }
+    
+    /// Create a key from its base64 encoding
+    pub fn from_base64(encoded: &str) -> Result<Self, VectorStoreError> {
+        let bytes = BASE64.decode(encoded.trim())
+            .map_err(|e| VectorStoreError::InvalidArgument(format!("Encryption key is not valid base64: {}", e)))?;
+        Self::from_bytes(&bytes)
+    }
+    
+    /// Read a base64 key from a file
+    pub fn from_file(path: &Path) -> Result<Self, VectorStoreError> {
+        let encoded = std::fs::read_to_string(path).map_err(|e| {
+            VectorStoreError::InvalidArgument(format!("Failed to read key file {}: {}", path.display(), e))
+        })?;
+        Self::from_base64(&encoded)
+    }
+    
+    /// Load the configured key, preferring the environment over the key file.
+    ///
+    /// Returns `None` when neither is set, meaning encryption is disabled.
+    pub fn from_config(config: &EncryptionConfig) -> Result<Option<Self>, VectorStoreError> {
+        if let Ok(encoded) = std::env::var(&config.key_env) {
+            if !encoded.trim().is_empty() {
+                return Self::from_base64(&encoded).map(Some);
+            }
+        }
+        
+        match &config.key_file {
+            Some(path) => Self::from_file(path).map(Some),
+            None => Ok(None),
+        }
+    }
+    
+    /// Generate a random key, returned base64-encoded for storing in a key file
+    pub fn generate_base64() -> String {
+        BASE64.encode(Aes256Gcm::generate_key(OsRng))
+    }
+    
+    /// A short fingerprint identifying the key in stored values
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    
+    /// Encrypt a value
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, VectorStoreError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher.encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| VectorStoreError::OperationFailed("Encryption failed".to_string()))?;
+        
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}:{}", ENCRYPTED_PREFIX, self.id, BASE64.encode(sealed)))
+    }
+    
+    /// Decrypt a value; values that were never encrypted are returned unchanged
+    pub fn decrypt(&self, value: &str) -> Result<String, VectorStoreError> {
+        let rest = match value.strip_prefix(ENCRYPTED_PREFIX) {
+            Some(rest) => rest,
+            None => return Ok(value.to_string()),
+        };
+        
+        let (key_id, encoded) = rest.split_once(':')
+            .ok_or_else(|| VectorStoreError::OperationFailed("Malformed encrypted value".to_string()))?;
+        if key_id != self.id {
+            return Err(VectorStoreError::AuthenticationError(format!(
+                "Value was encrypted with key {} but key {} is configured",
+                key_id, self.id
+            )));
+        }
+        
+        let sealed = BASE64.decode(encoded)
+            .map_err(|_| VectorStoreError::OperationFailed("Malformed encrypted value".to_string()))?;
+        if sealed.len() < NONCE_LEN {
+            return Err(VectorStoreError::OperationFailed("Malformed encrypted value".to_string()));
+        }
+        
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self.cipher.decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| VectorStoreError::AuthenticationError("Decryption failed; the value may have been tampered with".to_string()))?;
+        String::from_utf8(plaintext)
+            .map_err(|_| VectorStoreError::OperationFailed("Decrypted value is not UTF-8".to_string()))
+    }
+    
+    /// Encrypt a document's content and metadata values
+    pub fn encrypt_document(&self, mut document: Document) -> Result<Document, VectorStoreError> {
+        document.content = self.encrypt(&document.content)?;
+        for value in document.metadata.values_mut() {
+            *value = self.encrypt(value)?;
+        }
+        Ok(document)
+    }
+    
+    /// Decrypt a document's content and metadata values
+    pub fn decrypt_document(&self, mut document: Document) -> Result<Document, VectorStoreError> {
+        document.content = self.decrypt(&document.content)?;
+        for value in document.metadata.values_mut() {
+            *value = self.decrypt(value)?;
+        }
+        Ok(document)
+    }
+}
+
+/// The id of the key a value was encrypted with, or `None` for plaintext
+pub fn encrypted_key_id(value: &str) -> Option<&str> {
+    value.strip_prefix(ENCRYPTED_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+        .map(|(key_id, _)| key_id)
+}
+
+/// A vector store that encrypts documents before they reach `inner`
+pub struct EncryptedVectorStore {
+    inner: Arc<dyn VectorStore>,
+    key: EncryptionKey,
+}
+
+impl EncryptedVectorStore {
+    pub fn new(inner: Arc<dyn VectorStore>, key: EncryptionKey) -> Self {
+        Self { inner, key }
+    }
+}
+
+#[async_trait]
+impl VectorStore for EncryptedVectorStore {
+    async fn test_connection(&self) -> Result<(), VectorStoreError> {
+        self.inner.test_connection().await
+    }
+    
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), VectorStoreError> {
+        self.inner.create_collection(name, vector_size).await
+    }
+    
+    async fn delete_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+        self.inner.delete_collection(name).await
+    }
+    
+    async fn insert_document(&self, collection: &str, document: Document) -> Result<(), VectorStoreError> {
+        let document = self.key.encrypt_document(document)?;
+        self.inner.insert_document(collection, document).await
+    }
+    
+    async fn search(&self, collection: &str, query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.inner.search(collection, query).await?
+            .into_iter()
+            .map(|result| Ok(SearchResult {
+                document: self.key.decrypt_document(result.document)?,
+                score: result.score,
+                metric: result.metric,
+            }))
+            .collect()
+    }
+    
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        self.inner.list_collections().await
+    }
+    
+    async fn collection_schema(&self, collection: &str) -> Result<Option<CollectionSchema>, VectorStoreError> {
+        self.inner.collection_schema(collection).await
+    }
+    
+    async fn delete_document(&self, collection: &str, id: &str) -> Result<(), VectorStoreError> {
+        self.inner.delete_document(collection, id).await
+    }
+    
+    async fn get_document(&self, collection: &str, id: &str) -> Result<Option<Document>, VectorStoreError> {
+        self.inner.get_document(collection, id).await?
+            .map(|document| self.key.decrypt_document(document))
+            .transpose()
+    }
+    
+    async fn list_documents(&self, collection: &str, offset: Option<String>, limit: usize) -> Result<DocumentPage, VectorStoreError> {
+        let page = self.inner.list_documents(collection, offset, limit).await?;
+        Ok(DocumentPage {
+            documents: page.documents
+                .into_iter()
+                .map(|document| self.key.decrypt_document(document))
+                .collect::<Result<Vec<Document>, VectorStoreError>>()?,
+            next_offset: page.next_offset,
+        })
+    }
+    
+    async fn count(&self, collection: &str, filter: &MetadataFilter) -> Result<usize, VectorStoreError> {
+        // Metadata is encrypted at rest, so only id filters can be counted by the inner store
+        if filter.tags.is_empty() && filter.metadata.is_empty() {
+            self.inner.count(collection, filter).await
+        } else {
+            count_matching(self, collection, filter).await
+        }
+    }
+    
+    async fn exists(&self, collection: &str, id: &str) -> Result<bool, VectorStoreError> {
+        self.inner.exists(collection, id).await
+    }
+    
+    fn pool_metrics(&self) -> Option<PoolMetrics> {
+        self.inner.pool_metrics()
+    }
+    
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+}
+
+/// Re-encrypt every document in `collection` of the raw (unwrapped) store under `new_key`.
+///
+/// Documents are decrypted with `old_key`, or read as plaintext when it is
+/// `None`; documents already under `new_key` are skipped, so an interrupted
+/// rotation can simply be re-run. Returns the number of documents rewritten.
+pub async fn reencrypt_collection(
+    store: &dyn VectorStore,
+    collection: &str,
+    old_key: Option<&EncryptionKey>,
+    new_key: &EncryptionKey,
+    progress: &Progress,
+) -> Result<usize, VectorStoreError> {
+    const PAGE_SIZE: usize = 256;
+    
+    if let Ok(total) = store.count(collection, &MetadataFilter::default()).await {
+        progress.set_total(total as u64);
+    }
+    let mut rewritten = 0;
+    let mut offset = None;
+    loop {
+        let page = store.list_documents(collection, offset, PAGE_SIZE).await?;
+        
+        for document in page.documents {
+            progress.inc(1);
+            if encrypted_key_id(&document.content) == Some(new_key.id()) {
+                continue;
+            }
+            
+            let plaintext = match (old_key, encrypted_key_id(&document.content)) {
+                (_, None) => document,
+                (Some(old_key), Some(_)) => old_key.decrypt_document(document)?,
+                (None, Some(key_id)) => {
+                    return Err(VectorStoreError::InvalidArgument(format!(
+                        "Document {} is encrypted with key {} but no old key was given",
+                        document.id, key_id
+                    )));
+                }
+            };
+            store.insert_document(collection, new_key.encrypt_document(plaintext)?).await?;
+            rewritten += 1;
+        }
+        
+        match page.next_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+    
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    fn key() -> EncryptionKey {
+        EncryptionKey::from_base64(&EncryptionKey::generate_base64()).unwrap()
+    }
+    
+    fn document(id: &str) -> Document {
+        Document {
+            id: id.to_string(),
+            content: format!("secret content {}", id),
+            embedding: vec![1.0, 0.0],
+            metadata: Default::default(),
+        }
+        .with_title("Classified")
+    }
+    
+    #[test]
+    fn test_round_trip_and_tamper_detection() {
+        let key = key();
+        let sealed = key.encrypt("hello").unwrap();
+        assert!(sealed.starts_with(ENCRYPTED_PREFIX));
+        assert_eq!(encrypted_key_id(&sealed), Some(key.id()));
+        assert_eq!(key.decrypt(&sealed).unwrap(), "hello");
+        assert_eq!(key.decrypt("plain").unwrap(), "plain");
+        
+        let mut tampered = sealed.clone();
+        tampered.pop();
+        tampered.push(if sealed.ends_with('A') { 'B' } else { 'A' });
+        assert!(key.decrypt(&tampered).is_err());
+        
+        assert!(matches!(self::key().decrypt(&sealed), Err(VectorStoreError::AuthenticationError(_))));
+    }
+    
+    #[test]
+    fn test_rejects_short_keys() {
+        assert!(EncryptionKey::from_bytes(&[0u8; 16]).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_store_encrypts_at_rest() {
+        let raw = Arc::new(InMemoryVectorStore::new());
+        raw.create_collection("docs", 2).await.unwrap();
+        let store = EncryptedVectorStore::new(raw.clone(), key());
+        
+        store.insert_document("docs", document("a")).await.unwrap();
+        
+        let stored = raw.get_document("docs", "a").await.unwrap().unwrap();
+        assert!(!stored.content.contains("secret"));
+        assert!(!stored.metadata["title"].contains("Classified"));
+        
+        let read = store.get_document("docs", "a").await.unwrap().unwrap();
+        assert_eq!(read.content, "secret content a");
+        assert_eq!(read.title(), Some("Classified"));
+        
+        let results = store.search("docs", SearchQuery { embedding: vec![1.0, 0.0], limit: 1 }).await.unwrap();
+        assert_eq!(results[0].document.content, "secret content a");
+    }
+    
+    #[tokio::test]
+    async fn test_reencrypt_collection() {
+        let raw = Arc::new(InMemoryVectorStore::new());
+        raw.create_collection("docs", 2).await.unwrap();
+        let (old_key, new_key) = (key(), key());
+        
+        EncryptedVectorStore::new(raw.clone(), old_key.clone()).insert_document("docs", document("a")).await.unwrap();
+        raw.insert_document("docs", document("b")).await.unwrap();
+        
+        assert!(reencrypt_collection(raw.as_ref(), "docs", None, &new_key, &Progress::hidden()).await.is_err());
+        assert_eq!(reencrypt_collection(raw.as_ref(), "docs", Some(&old_key), &new_key, &Progress::hidden()).await.unwrap(), 2);
+        assert_eq!(reencrypt_collection(raw.as_ref(), "docs", Some(&old_key), &new_key, &Progress::hidden()).await.unwrap(), 0);
+        
+        let store = EncryptedVectorStore::new(raw.clone(), new_key);
+        let page = store.list_documents("docs", None, 10).await.unwrap();
+        assert_eq!(page.documents[0].content, "secret content a");
+        assert_eq!(page.documents[1].content, "secret content b");
+    }
+}
@@ -0,0 +1,142 @@
+//! A vector store that records its changes in a [`ChangeFeed`].
+//!
+//! Every successful mutation made through the wrapper becomes a change
+//! event; reads pass straight through. Changes made to the underlying store
+//! by other means are not seen.
+
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use super::{
+    CollectionSchema, Document, DocumentPage, KeywordHit, MetadataFilter, MetadataPatch, PatchOutcome, PoolMetrics, SearchQuery, SearchResult,
+    VectorStore, VectorStoreError,
+};
+use crate::events::{ChangeFeed, ChangeKind};
+
+/// Wraps a store, recording its mutations in a change feed
+pub struct EventedVectorStore {
+    inner: Arc<dyn VectorStore>,
+    feed: Arc<ChangeFeed>,
+}
+
+impl EventedVectorStore {
+    pub fn new(inner: Arc<dyn VectorStore>, feed: Arc<ChangeFeed>) -> Self {
+        Self { inner, feed }
+    }
+    
+    pub fn feed(&self) -> &Arc<ChangeFeed> {
+        &self.feed
+    }
+}
+
+#[async_trait]
+impl VectorStore for EventedVectorStore {
+    async fn test_connection(&self) -> Result<(), VectorStoreError> {
+        self.inner.test_connection().await
+    }
+    
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), VectorStoreError> {
+        self.inner.create_collection(name, vector_size).await?;
+        self.feed.record(ChangeKind::CollectionCreated, name, None);
+        Ok(())
+    }
+    
+    async fn delete_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+        self.inner.delete_collection(name).await?;
+        self.feed.record(ChangeKind::CollectionDeleted, name, None);
+        Ok(())
+    }
+    
+    async fn insert_document(&self, collection: &str, document: Document) -> Result<(), VectorStoreError> {
+        let id = document.id.clone();
+        self.inner.insert_document(collection, document).await?;
+        self.feed.record(ChangeKind::Upserted, collection, Some(&id));
+        Ok(())
+    }
+    
+    async fn search(&self, collection: &str, query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.inner.search(collection, query).await
+    }
+    
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        self.inner.list_collections().await
+    }
+    
+    async fn collection_schema(&self, collection: &str) -> Result<Option<CollectionSchema>, VectorStoreError> {
+        self.inner.collection_schema(collection).await
+    }
+    
+    async fn delete_document(&self, collection: &str, id: &str) -> Result<(), VectorStoreError> {
+        self.inner.delete_document(collection, id).await?;
+        self.feed.record(ChangeKind::Deleted, collection, Some(id));
+        Ok(())
+    }
+    
+    async fn get_document(&self, collection: &str, id: &str) -> Result<Option<Document>, VectorStoreError> {
+        self.inner.get_document(collection, id).await
+    }
+    
+    async fn list_documents(&self, collection: &str, offset: Option<String>, limit: usize) -> Result<DocumentPage, VectorStoreError> {
+        self.inner.list_documents(collection, offset, limit).await
+    }
+    
+    async fn patch_metadata(&self, collection: &str, filter: &MetadataFilter, patch: &MetadataPatch) -> Result<PatchOutcome, VectorStoreError> {
+        let outcome = self.inner.patch_metadata(collection, filter, patch).await?;
+        if outcome.updated > 0 {
+            // A patch of one entry names it; wider patches are one event for the collection
+            let id = match filter.ids.as_slice() {
+                [id] => Some(id.as_str()),
+                _ => None,
+            };
+            self.feed.record(ChangeKind::MetadataPatched, collection, id);
+        }
+        Ok(outcome)
+    }
+    
+    async fn count(&self, collection: &str, filter: &MetadataFilter) -> Result<usize, VectorStoreError> {
+        self.inner.count(collection, filter).await
+    }
+    
+    async fn search_filtered(&self, collection: &str, query: SearchQuery, filter: &MetadataFilter) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.inner.search_filtered(collection, query, filter).await
+    }
+    
+    async fn keyword_search(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<KeywordHit>, VectorStoreError> {
+        self.inner.keyword_search(collection, query, limit).await
+    }
+    
+    async fn exists(&self, collection: &str, id: &str) -> Result<bool, VectorStoreError> {
+        self.inner.exists(collection, id).await
+    }
+    
+    fn pool_metrics(&self) -> Option<PoolMetrics> {
+        self.inner.pool_metrics()
+    }
+    
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    #[tokio::test]
+    async fn test_mutations_are_recorded() {
+        let feed = Arc::new(ChangeFeed::default());
+        let store = EventedVectorStore::new(Arc::new(InMemoryVectorStore::new()), feed.clone());
+        
+        store.create_collection("docs", 2).await.unwrap();
+        let mut document = Document::with_placeholder_embedding("content".to_string(), 2);
+        document.id = "a".to_string();
+        store.insert_document("docs", document).await.unwrap();
+        store.get_document("docs", "a").await.unwrap();
+        store.delete_document("docs", "a").await.unwrap();
+        assert!(store.insert_document("missing", Document::with_placeholder_embedding("x".to_string(), 2)).await.is_err());
+        
+        let kinds: Vec<ChangeKind> = feed.since(None, 10).events.iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, vec![ChangeKind::CollectionCreated, ChangeKind::Upserted, ChangeKind::Deleted]);
+    }
+}
@@ -0,0 +1,204 @@
+//! Entry expiry.
+//!
+//! An entry expires at the time in its `expires_at` metadata, or, when its
+//! collection declares a TTL, that long after it was created. Expired
+//! entries are left out of search results until the maintenance scheduler
+//! purges them.
+//!
+//! Agent memories expire the same way, so the key keeps the name it was
+//! given when memories were the only entries with a lifetime.
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use std::future::Future;
+
+use super::{Document, SearchResult, VectorStore, VectorStoreError, CREATED_AT_KEY};
+use super::filter::FILTER_PAGE_SIZE;
+
+/// Metadata key holding when an entry expires (RFC 3339), if it does
+pub const EXPIRES_AT_KEY: &str = "memory_expires_at";
+
+/// Longest lifetime an entry or collection may be given: 100 years
+pub const MAX_TTL_SECS: u64 = 100 * 365 * 24 * 60 * 60;
+
+/// When something created at `created_at` to live `ttl_secs` expires;
+/// `None` for lifetimes over [`MAX_TTL_SECS`]
+pub fn expiry_after(created_at: DateTime<Utc>, ttl_secs: u64) -> Option<DateTime<Utc>> {
+    if ttl_secs > MAX_TTL_SECS {
+        return None;
+    }
+    created_at.checked_add_signed(ChronoDuration::try_seconds(ttl_secs as i64)?)
+}
+
+/// Check a TTL given for an entry or collection
+pub fn check_ttl(ttl_secs: u64) -> Result<(), String> {
+    if !(1..=MAX_TTL_SECS).contains(&ttl_secs) {
+        return Err(format!("ttl must be between 1 and {} seconds (100 years)", MAX_TTL_SECS));
+    }
+    Ok(())
+}
+
+fn parse_time(document: &Document, key: &str) -> Option<DateTime<Utc>> {
+    document.metadata.get(key)
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// When `document` expires; its own expiry wins over the collection's TTL
+pub fn expires_at(document: &Document, collection_ttl_secs: Option<u64>) -> Option<DateTime<Utc>> {
+    parse_time(document, EXPIRES_AT_KEY).or_else(|| {
+        expiry_after(parse_time(document, CREATED_AT_KEY)?, collection_ttl_secs?)
+    })
+}
+
+/// Whether `document` had expired by `now`
+pub fn is_expired(document: &Document, collection_ttl_secs: Option<u64>, now: DateTime<Utc>) -> bool {
+    expires_at(document, collection_ttl_secs).is_some_and(|expires_at| expires_at <= now)
+}
+
+/// Up to `limit` unexpired results of `search`, which is given how many
+/// results to ask the store for.
+///
+/// Expired entries stay in the store until they are purged and would take
+/// places in a page of `limit` results, so the search is repeated asking for
+/// twice as many until enough unexpired ones come back or the store has no
+/// more to give.
+pub async fn search_unexpired<F, Fut>(
+    limit: usize,
+    collection_ttl_secs: Option<u64>,
+    now: DateTime<Utc>,
+    mut search: F,
+) -> Result<Vec<SearchResult>, VectorStoreError>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<Vec<SearchResult>, VectorStoreError>>,
+{
+    let mut fetch = limit;
+    loop {
+        let results = search(fetch).await?;
+        let exhausted = results.len() < fetch;
+        let mut unexpired: Vec<SearchResult> = results.into_iter()
+            .filter(|result| !is_expired(&result.document, collection_ttl_secs, now))
+            .collect();
+        if unexpired.len() >= limit || exhausted {
+            unexpired.truncate(limit);
+            return Ok(unexpired);
+        }
+        fetch = fetch.saturating_mul(2);
+    }
+}
+
+/// Delete the entries of `collection` that had expired by `now`, returning their ids
+pub async fn purge_expired(
+    store: &dyn VectorStore,
+    collection: &str,
+    collection_ttl_secs: Option<u64>,
+    now: DateTime<Utc>,
+) -> Result<Vec<String>, VectorStoreError> {
+    // Collect first so deletions do not shift the pages still to be read
+    let mut expired = Vec::new();
+    let mut offset = None;
+    loop {
+        let page = store.list_documents(collection, offset, FILTER_PAGE_SIZE).await?;
+        expired.extend(page.documents.into_iter()
+            .filter(|document| is_expired(document, collection_ttl_secs, now))
+            .map(|document| document.id));
+        offset = match page.next_offset {
+            Some(next) => Some(next),
+            None => break,
+        };
+    }
+    
+    for id in &expired {
+        store.delete_document(collection, id).await?;
+    }
+    Ok(expired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    fn entry(id: &str, metadata: &[(&str, String)]) -> Document {
+        let mut document = Document::with_placeholder_embedding(id.to_string(), 2);
+        document.id = id.to_string();
+        for (key, value) in metadata {
+            document.metadata.insert(key.to_string(), value.clone());
+        }
+        document
+    }
+    
+    #[test]
+    fn test_entry_expiry_wins_over_collection_ttl() {
+        let now = Utc::now();
+        let hour_old = [(CREATED_AT_KEY, (now - ChronoDuration::hours(1)).to_rfc3339())];
+        
+        assert!(!is_expired(&entry("a", &hour_old), None, now));
+        assert!(is_expired(&entry("a", &hour_old), Some(60), now));
+        assert!(!is_expired(&entry("a", &hour_old), Some(7200), now));
+        
+        let extended = [hour_old[0].clone(), (EXPIRES_AT_KEY, (now + ChronoDuration::days(1)).to_rfc3339())];
+        assert!(!is_expired(&entry("a", &extended), Some(60), now));
+        let lapsed = [(EXPIRES_AT_KEY, (now - ChronoDuration::seconds(1)).to_rfc3339())];
+        assert!(is_expired(&entry("a", &lapsed), None, now));
+        
+        // Entries without a creation time only expire by their own expiry
+        assert!(!is_expired(&entry("a", &[]), Some(60), now));
+    }
+    
+    #[test]
+    fn test_ttls_are_bounded() {
+        let now = Utc::now();
+        assert_eq!(expiry_after(now, 60), Some(now + ChronoDuration::seconds(60)));
+        assert!(expiry_after(now, MAX_TTL_SECS).is_some());
+        assert_eq!(expiry_after(now, MAX_TTL_SECS + 1), None);
+        assert_eq!(expiry_after(now, u64::MAX), None);
+        
+        assert!(check_ttl(0).is_err());
+        assert!(check_ttl(u64::MAX).is_err());
+        
+        // An unrepresentable collection TTL means the entry never expires rather than a panic
+        let created = [(CREATED_AT_KEY, now.to_rfc3339())];
+        assert!(!is_expired(&entry("a", &created), Some(u64::MAX), now));
+    }
+    
+    #[tokio::test]
+    async fn test_search_fetches_past_expired_entries() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        let now = Utc::now();
+        let lapsed = (now - ChronoDuration::minutes(5)).to_rfc3339();
+        for index in 0..6 {
+            let mut document = entry(&format!("old-{}", index), &[(EXPIRES_AT_KEY, lapsed.clone())]);
+            document.embedding = vec![1.0, 0.0];
+            store.insert_document("docs", document).await.unwrap();
+        }
+        let mut fresh = entry("fresh", &[]);
+        fresh.embedding = vec![0.9, 0.1];
+        store.insert_document("docs", fresh).await.unwrap();
+        
+        // The expired entries rank first, yet the one live entry is still found
+        let mut fetches = Vec::new();
+        let results = search_unexpired(2, None, now, |fetch| {
+            fetches.push(fetch);
+            store.search("docs", crate::vector_store::SearchQuery { embedding: vec![1.0, 0.0], limit: fetch })
+        }).await.unwrap();
+        assert_eq!(results.iter().map(|result| result.document.id.as_str()).collect::<Vec<_>>(), vec!["fresh"]);
+        assert_eq!(fetches, vec![2, 4, 8]);
+    }
+    
+    #[tokio::test]
+    async fn test_purge_deletes_only_expired_entries() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        let now = Utc::now();
+        store.insert_document("docs", entry("old", &[(EXPIRES_AT_KEY, (now - ChronoDuration::minutes(5)).to_rfc3339())])).await.unwrap();
+        store.insert_document("docs", entry("fresh", &[(EXPIRES_AT_KEY, (now + ChronoDuration::minutes(5)).to_rfc3339())])).await.unwrap();
+        store.insert_document("docs", entry("forever", &[])).await.unwrap();
+        
+        assert_eq!(purge_expired(&store, "docs", None, now).await.unwrap(), vec!["old".to_string()]);
+        assert!(!store.exists("docs", "old").await.unwrap());
+        assert!(store.exists("docs", "fresh").await.unwrap());
+        assert!(store.exists("docs", "forever").await.unwrap());
+    }
+}
@@ -0,0 +1,187 @@
+//! Selecting entries by id, tag and metadata value
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::{cosine_similarity, DistanceMetric, Document, EntryId, SearchQuery, SearchResult, VectorStore, VectorStoreError};
+
+/// Number of documents read per page when a store filters client-side
+pub const FILTER_PAGE_SIZE: usize = 256;
+
+/// Which entries an operation applies to; every given condition must hold
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataFilter {
+    /// Entry ids, any of which matches
+    pub ids: Vec<String>,
+    /// Tags the entry must all carry
+    pub tags: Vec<String>,
+    /// Metadata fields that must have exactly these values
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl MetadataFilter {
+    /// Parse `{"ids": [...], "tags": [...], "metadata": {"key": "value"}}`
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let object = value.as_object().ok_or("filter must be an object")?;
+        let strings = |key: &str| -> Result<Vec<String>, String> {
+            match object.get(key) {
+                None => Ok(Vec::new()),
+                Some(Value::Array(items)) => items.iter()
+                    .map(|item| item.as_str().map(str::to_string).ok_or(format!("filter.{} must contain strings", key)))
+                    .collect(),
+                Some(_) => Err(format!("filter.{} must be an array", key)),
+            }
+        };
+        
+        let metadata = match object.get("metadata") {
+            None => BTreeMap::new(),
+            Some(Value::Object(fields)) => fields.iter()
+                .map(|(key, value)| match scalar_string(value) {
+                    Some(value) => Ok((key.clone(), value)),
+                    None => Err(format!("filter.metadata.{} must be a string, number or boolean", key)),
+                })
+                .collect::<Result<_, String>>()?,
+            Some(_) => return Err("filter.metadata must be an object".to_string()),
+        };
+        if let Some(unknown) = object.keys().find(|key| !["ids", "tags", "metadata"].contains(&key.as_str())) {
+            return Err(format!("unknown filter field '{}'", unknown));
+        }
+        
+        let ids = strings("ids")?
+            .iter()
+            .map(|id| EntryId::parse(id).map(String::from).map_err(|e| format!("filter.ids: {}", e)))
+            .collect::<Result<_, String>>()?;
+        
+        Ok(Self {
+            ids,
+            tags: strings("tags")?,
+            metadata,
+        })
+    }
+    
+    /// Whether the filter has no conditions and so matches every entry
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty() && self.tags.is_empty() && self.metadata.is_empty()
+    }
+    
+    pub fn matches(&self, document: &Document) -> bool {
+        if !self.ids.is_empty() && !self.ids.contains(&document.id) {
+            return false;
+        }
+        let tags = document.tags();
+        if !self.tags.iter().all(|tag| tags.contains(tag)) {
+            return false;
+        }
+        self.metadata.iter().all(|(key, value)| document.metadata.get(key) == Some(value))
+    }
+}
+
+/// The string stored for a scalar JSON value
+pub(crate) fn scalar_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::Bool(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Count matching documents by paging through the collection.
+///
+/// The fallback for stores that cannot count server-side.
+pub async fn count_matching<S: VectorStore + ?Sized>(store: &S, collection: &str, filter: &MetadataFilter) -> Result<usize, VectorStoreError> {
+    let mut count = 0;
+    let mut offset = None;
+    loop {
+        let page = store.list_documents(collection, offset, FILTER_PAGE_SIZE).await?;
+        count += page.documents.iter().filter(|document| filter.matches(document)).count();
+        offset = match page.next_offset {
+            Some(next) => Some(next),
+            None => break,
+        };
+    }
+    Ok(count)
+}
+
+/// Search matching documents by paging through the collection and scoring
+/// each locally.
+///
+/// The fallback for stores that cannot filter a search server-side.
+pub async fn search_matching<S: VectorStore + ?Sized>(store: &S, collection: &str, query: SearchQuery, filter: &MetadataFilter) -> Result<Vec<SearchResult>, VectorStoreError> {
+    let mut results = Vec::new();
+    let mut offset = None;
+    loop {
+        let page = store.list_documents(collection, offset, FILTER_PAGE_SIZE).await?;
+        results.extend(page.documents.into_iter()
+            .filter(|document| filter.matches(document))
+            .map(|document| SearchResult {
+                score: cosine_similarity(&query.embedding, &document.embedding),
+                metric: DistanceMetric::Cosine,
+                document,
+            }));
+        offset = match page.next_offset {
+            Some(next) => Some(next),
+            None => break,
+        };
+    }
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.truncate(query.limit);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    use serde_json::json;
+    
+    fn document(id: &str, tags: &[&str], owner: &str) -> Document {
+        let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+        let mut document = Document::with_placeholder_embedding(format!("content of {}", id), 2).with_tags(&tags);
+        document.id = id.to_string();
+        document.metadata.insert("owner".to_string(), owner.to_string());
+        document
+    }
+    
+    #[test]
+    fn test_filter_matches() {
+        let filter = MetadataFilter::from_json(&json!({"tags": ["ops"], "metadata": {"owner": "sre"}})).unwrap();
+        assert!(filter.matches(&document("a", &["ops", "db"], "sre")));
+        assert!(!filter.matches(&document("b", &["db"], "sre")));
+        assert!(!filter.matches(&document("c", &["ops"], "web")));
+        
+        assert!(MetadataFilter::from_json(&json!({})).unwrap().is_empty());
+        assert!(MetadataFilter::from_json(&json!({"owner": "sre"})).is_err());
+        assert!(MetadataFilter::from_json(&json!({"metadata": {"owner": {"name": "sre"}}})).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_count_matching() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        store.insert_document("docs", document("a", &["ops"], "sre")).await.unwrap();
+        store.insert_document("docs", document("b", &["ops"], "platform")).await.unwrap();
+        store.insert_document("docs", document("c", &["web"], "sre")).await.unwrap();
+        
+        let ops = MetadataFilter::from_json(&json!({"tags": ["ops"]})).unwrap();
+        assert_eq!(count_matching(&store, "docs", &ops).await.unwrap(), 2);
+        assert_eq!(count_matching(&store, "docs", &MetadataFilter::default()).await.unwrap(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_search_matching() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        for (id, owner, embedding) in [("a", "sre", [1.0, 0.0]), ("b", "sre", [0.6, 0.8]), ("c", "web", [1.0, 0.1])] {
+            let mut document = document(id, &[], owner);
+            document.embedding = embedding.to_vec();
+            store.insert_document("docs", document).await.unwrap();
+        }
+        
+        let sre = MetadataFilter::from_json(&json!({"metadata": {"owner": "sre"}})).unwrap();
+        let query = SearchQuery { embedding: vec![0.0, 1.0], limit: 5 };
+        let results = search_matching(&store, "docs", query, &sre).await.unwrap();
+        let ids: Vec<&str> = results.iter().map(|result| result.document.id.as_str()).collect();
+        assert_eq!(ids, ["b", "a"]);
+    }
+}
@@ -0,0 +1,161 @@
+//! Entry identifiers, validated once where they enter the server
+
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::VectorStoreError;
+
+/// Longest accepted entry id, in bytes
+pub const MAX_ENTRY_ID_LEN: usize = 256;
+
+/// Payload key holding an external entry id where a backend keys entries by UUID
+pub const ENTRY_ID_KEY: &str = "entry_id";
+
+/// Namespace of the UUIDv5 point ids derived from external entry ids
+pub const ENTRY_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2b8e_4d3a_4c5f_9e7b_1a2d_3c4e_5f60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EntryIdError {
+    #[error("Entry id must not be empty")]
+    Empty,
+    
+    #[error("Entry id is {0} bytes long; at most {max} are allowed", max = MAX_ENTRY_ID_LEN)]
+    TooLong(usize),
+    
+    #[error("Entry id '{0}' contains a control character")]
+    ControlCharacter(String),
+}
+
+impl From<EntryIdError> for VectorStoreError {
+    fn from(err: EntryIdError) -> Self {
+        VectorStoreError::InvalidArgument(err.to_string())
+    }
+}
+
+/// The id of an entry.
+///
+/// UUIDs are kept in their canonical lowercase hyphenated form; any other
+/// non-empty string is accepted as an external id. Backends that only take
+/// UUID keys use [`EntryId::point_uuid`], which hashes external ids into a
+/// stable UUIDv5.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(transparent)]
+pub struct EntryId(String);
+
+impl EntryId {
+    /// A fresh random id
+    pub fn new() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+    
+    /// Validate and normalize an id received from a caller
+    pub fn parse(id: &str) -> Result<Self, EntryIdError> {
+        let id = id.trim();
+        if id.is_empty() {
+            return Err(EntryIdError::Empty);
+        }
+        if id.len() > MAX_ENTRY_ID_LEN {
+            return Err(EntryIdError::TooLong(id.len()));
+        }
+        if id.chars().any(char::is_control) {
+            return Err(EntryIdError::ControlCharacter(id.escape_debug().to_string()));
+        }
+        
+        match Uuid::parse_str(id) {
+            Ok(uuid) => Ok(Self(uuid.to_string())),
+            Err(_) => Ok(Self(id.to_string())),
+        }
+    }
+    
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+    
+    /// Whether the id is a UUID rather than an external id
+    pub fn is_uuid(&self) -> bool {
+        Uuid::parse_str(&self.0).is_ok()
+    }
+    
+    /// The UUID a backend keys this entry by: the id itself, or a UUIDv5 of an external id
+    pub fn point_uuid(&self) -> Uuid {
+        Uuid::parse_str(&self.0).unwrap_or_else(|_| Uuid::new_v5(&ENTRY_ID_NAMESPACE, self.0.as_bytes()))
+    }
+}
+
+impl Default for EntryId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<Uuid> for EntryId {
+    fn from(uuid: Uuid) -> Self {
+        Self(uuid.to_string())
+    }
+}
+
+impl fmt::Display for EntryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for EntryId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<EntryId> for String {
+    fn from(id: EntryId) -> Self {
+        id.0
+    }
+}
+
+impl std::str::FromStr for EntryId {
+    type Err = EntryIdError;
+    
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        Self::parse(id)
+    }
+}
+
+impl<'de> Deserialize<'de> for EntryId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Self::parse(&id).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_normalizes_uuids() {
+        let id = EntryId::parse(" 6F1C2B8E-4D3A-4C5F-9E7B-1A2D3C4E5F60 ").unwrap();
+        assert_eq!(id.as_str(), "6f1c2b8e-4d3a-4c5f-9e7b-1a2d3c4e5f60");
+        assert!(id.is_uuid());
+        assert_eq!(id.point_uuid().to_string(), id.as_str());
+    }
+    
+    #[test]
+    fn test_external_ids_hash_to_stable_uuids() {
+        let id = EntryId::parse("docs/readme.md").unwrap();
+        assert_eq!(id.as_str(), "docs/readme.md");
+        assert!(!id.is_uuid());
+        assert_eq!(id.point_uuid(), EntryId::parse("docs/readme.md").unwrap().point_uuid());
+        assert_ne!(id.point_uuid(), EntryId::parse("docs/other.md").unwrap().point_uuid());
+        assert_eq!(id.point_uuid().get_version_num(), 5);
+    }
+    
+    #[test]
+    fn test_invalid_ids() {
+        assert_eq!(EntryId::parse("   "), Err(EntryIdError::Empty));
+        assert_eq!(EntryId::parse(&"x".repeat(MAX_ENTRY_ID_LEN + 1)), Err(EntryIdError::TooLong(MAX_ENTRY_ID_LEN + 1)));
+        assert!(matches!(EntryId::parse("a\nb"), Err(EntryIdError::ControlCharacter(_))));
+        assert!(serde_json::from_str::<EntryId>("\"\"").is_err());
+    }
+}
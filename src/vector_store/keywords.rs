@@ -0,0 +1,279 @@
+//! Keyword scoring for hybrid search.
+//!
+//! Entries are split into lowercase terms with stopwords dropped, and each
+//! backend keeps an inverted index of the terms alongside the vectors: the
+//! memory store in a [`KeywordIndex`] updated on every insert and delete,
+//! Qdrant in each point's payload. A keyword search reads the postings of
+//! the query's terms and scores the entries holding them with BM25, so it
+//! never scans the whole collection.
+//!
+//! Collections whose search settings give `hybrid` weights blend this score
+//! with the vector score; see [`fuse_hybrid`].
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::{cosine_similarity, DistanceMetric, Document, HybridWeights, SearchResult, VectorStore, VectorStoreError};
+use super::filter::FILTER_PAGE_SIZE;
+
+/// Payload field holding the distinct terms of a Qdrant point, for matching
+pub const TERMS_KEY: &str = "terms";
+
+/// Payload field holding the count of each term of a Qdrant point
+pub const TERM_FREQS_KEY: &str = "term_freqs";
+
+/// Payload field holding the number of terms of a Qdrant point
+pub const TERM_COUNT_KEY: &str = "term_count";
+
+/// BM25 term frequency saturation
+const BM25_K1: f32 = 1.2;
+
+/// BM25 length normalization
+const BM25_B: f32 = 0.75;
+
+/// Words too common to tell entries apart
+const STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "am", "an", "and", "any", "are", "as", "at",
+    "be", "because", "been", "before", "being", "between", "both", "but", "by",
+    "can", "could", "did", "do", "does", "doing", "down", "during", "each", "few",
+    "for", "from", "further", "had", "has", "have", "having", "he", "her", "here",
+    "hers", "him", "his", "how", "i", "if", "in", "into", "is", "it", "its", "itself",
+    "just", "me", "more", "most", "my", "no", "nor", "not", "now", "of", "off", "on",
+    "once", "only", "or", "other", "our", "ours", "out", "over", "own", "same", "she",
+    "should", "so", "some", "such", "than", "that", "the", "their", "theirs", "them",
+    "then", "there", "these", "they", "this", "those", "through", "to", "too", "under",
+    "until", "up", "very", "was", "we", "were", "what", "when", "where", "which",
+    "while", "who", "whom", "why", "will", "with", "would", "you", "your", "yours",
+];
+
+/// Whether `term`, in lower case, is a stopword
+pub fn is_stopword(term: &str) -> bool {
+    STOPWORDS.contains(&term)
+}
+
+/// The terms of `text` in order: lowercase runs of letters and digits, with
+/// stopwords and single characters dropped
+pub fn index_terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 1)
+        .map(|word| word.to_lowercase())
+        .filter(|term| !is_stopword(term))
+        .collect()
+}
+
+/// How often each term occurs in `text`
+pub fn term_frequencies(text: &str) -> BTreeMap<String, u32> {
+    let mut frequencies = BTreeMap::new();
+    for term in index_terms(text) {
+        *frequencies.entry(term).or_insert(0) += 1;
+    }
+    frequencies
+}
+
+/// The text of a document that is indexed: its title and content
+pub fn indexed_text(document: &Document) -> String {
+    match document.title() {
+        Some(title) => format!("{}\n{}", title, document.content),
+        None => document.content.clone(),
+    }
+}
+
+/// The distinct terms of a query
+pub fn query_terms(query: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    index_terms(query).into_iter().filter(|term| seen.insert(term.clone())).collect()
+}
+
+/// The BM25 weight of one term in one document
+pub fn bm25(frequency: u32, document_frequency: usize, documents: usize, length: usize, average_length: f32) -> f32 {
+    let frequency = frequency as f32;
+    let idf = (1.0 + (documents as f32 - document_frequency as f32 + 0.5) / (document_frequency as f32 + 0.5)).ln();
+    let norm = 1.0 - BM25_B + BM25_B * length as f32 / average_length.max(1.0);
+    idf * frequency * (BM25_K1 + 1.0) / (frequency + BM25_K1 * norm)
+}
+
+/// A document and its keyword score, higher for a better match
+#[derive(Debug, Clone)]
+pub struct KeywordHit {
+    pub document: Document,
+    pub score: f32,
+}
+
+/// An inverted index from terms to the documents holding them
+#[derive(Debug, Default)]
+pub struct KeywordIndex {
+    /// Ids of the documents holding each term
+    postings: HashMap<String, HashSet<String>>,
+    /// Term counts of each document
+    frequencies: HashMap<String, BTreeMap<String, u32>>,
+    /// Terms in all documents together
+    total_length: usize,
+}
+
+impl KeywordIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Index `document`, replacing what was indexed under its id
+    pub fn insert(&mut self, document: &Document) {
+        self.remove(&document.id);
+        let frequencies = term_frequencies(&indexed_text(document));
+        for term in frequencies.keys() {
+            self.postings.entry(term.clone()).or_default().insert(document.id.clone());
+        }
+        self.total_length += frequencies.values().map(|count| *count as usize).sum::<usize>();
+        self.frequencies.insert(document.id.clone(), frequencies);
+    }
+    
+    /// Drop the document `id` from the index
+    pub fn remove(&mut self, id: &str) {
+        let Some(frequencies) = self.frequencies.remove(id) else {
+            return;
+        };
+        self.total_length -= frequencies.values().map(|count| *count as usize).sum::<usize>();
+        for term in frequencies.keys() {
+            if let Some(ids) = self.postings.get_mut(term) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+    }
+    
+    /// Ids of the `limit` documents scoring best for `query` under BM25, with their scores
+    pub fn score(&self, query: &str, limit: usize) -> Vec<(String, f32)> {
+        let documents = self.frequencies.len();
+        if documents == 0 {
+            return Vec::new();
+        }
+        let average_length = self.total_length as f32 / documents as f32;
+        
+        let mut scores: HashMap<&str, f32> = HashMap::new();
+        for term in query_terms(query) {
+            let Some(ids) = self.postings.get(&term) else {
+                continue;
+            };
+            for id in ids {
+                let frequencies = &self.frequencies[id];
+                let length = frequencies.values().map(|count| *count as usize).sum();
+                *scores.entry(id.as_str()).or_insert(0.0) += bm25(frequencies[&term], ids.len(), documents, length, average_length);
+            }
+        }
+        
+        let mut scores: Vec<(String, f32)> = scores.into_iter().map(|(id, score)| (id.to_string(), score)).collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scores.truncate(limit);
+        scores
+    }
+}
+
+/// Score a collection by keywords by indexing all of it.
+///
+/// The fallback for stores that keep no index.
+pub async fn search_by_scan<S: VectorStore + ?Sized>(store: &S, collection: &str, query: &str, limit: usize) -> Result<Vec<KeywordHit>, VectorStoreError> {
+    let mut index = KeywordIndex::new();
+    let mut documents = HashMap::new();
+    let mut offset = None;
+    loop {
+        let page = store.list_documents(collection, offset, FILTER_PAGE_SIZE).await?;
+        for document in page.documents {
+            index.insert(&document);
+            documents.insert(document.id.clone(), document);
+        }
+        offset = match page.next_offset {
+            Some(next) => Some(next),
+            None => break,
+        };
+    }
+    Ok(index.score(query, limit)
+        .into_iter()
+        .filter_map(|(id, score)| documents.remove(&id).map(|document| KeywordHit { document, score }))
+        .collect())
+}
+
+/// Blend vector results and keyword hits by `weights`.
+///
+/// Keyword scores are scaled so the best hit scores 1; documents found by
+/// only one search get the other's score computed from `embedding`, or 0
+/// for keywords. Each result's relevance is the weighted mean of the two,
+/// carried as a cosine score so [`SearchResult::relevance`] reports it.
+pub fn fuse_hybrid(results: Vec<SearchResult>, hits: Vec<KeywordHit>, weights: HybridWeights, embedding: &[f32]) -> Vec<SearchResult> {
+    let best = hits.iter().map(|hit| hit.score).fold(0.0, f32::max);
+    let mut keyword_scores: HashMap<String, f32> = hits.iter()
+        .map(|hit| (hit.document.id.clone(), if best > 0.0 { hit.score / best } else { 0.0 }))
+        .collect();
+    
+    let mut documents: Vec<(Document, f32)> = results.into_iter()
+        .map(|result| {
+            let relevance = result.relevance();
+            (result.document, relevance)
+        })
+        .collect();
+    for hit in hits {
+        if !documents.iter().any(|(document, _)| document.id == hit.document.id) {
+            let relevance = DistanceMetric::Cosine.relevance(cosine_similarity(embedding, &hit.document.embedding));
+            documents.push((hit.document, relevance));
+        }
+    }
+    
+    let total = weights.vector + weights.keyword;
+    let mut fused: Vec<SearchResult> = documents.into_iter()
+        .map(|(document, vector)| {
+            let keyword = keyword_scores.remove(&document.id).unwrap_or(0.0);
+            let relevance = (weights.vector * vector + weights.keyword * keyword) / total;
+            SearchResult { document, score: relevance * 2.0 - 1.0, metric: DistanceMetric::Cosine }
+        })
+        .collect();
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.document.id.cmp(&b.document.id)));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn document(id: &str, content: &str) -> Document {
+        Document {
+            id: id.to_string(),
+            content: content.to_string(),
+            embedding: vec![1.0, 0.0],
+            metadata: Default::default(),
+        }
+    }
+    
+    #[test]
+    fn test_index_terms_drop_stopwords() {
+        assert_eq!(index_terms("How do I rotate the API key?"), ["rotate", "api", "key"]);
+        assert_eq!(query_terms("key KEY keys"), ["key", "keys"]);
+    }
+    
+    #[test]
+    fn test_keyword_index_scores_and_forgets() {
+        let mut index = KeywordIndex::new();
+        index.insert(&document("a", "Rotate the signing key. The key expires yearly."));
+        index.insert(&document("b", "Backups run nightly."));
+        index.insert(&document("c", "Each service has its own key and its own backups."));
+        
+        let ids: Vec<String> = index.score("signing key", 10).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, ["a", "c"]);
+        
+        index.remove("a");
+        index.insert(&document("c", "Nothing relevant here."));
+        assert!(index.score("key", 10).is_empty());
+        assert_eq!(index.score("backups", 10).len(), 1);
+    }
+    
+    #[test]
+    fn test_fuse_hybrid_blends_both_scores() {
+        let vector = vec![SearchResult { document: document("a", "alpha"), score: 1.0, metric: DistanceMetric::Cosine }];
+        let hits = vec![KeywordHit { document: document("b", "beta"), score: 4.0 }];
+        let fused = fuse_hybrid(vector, hits, HybridWeights { vector: 0.25, keyword: 0.75 }, &[0.0, 1.0]);
+        
+        let ids: Vec<&str> = fused.iter().map(|result| result.document.id.as_str()).collect();
+        assert_eq!(ids, ["b", "a"]);
+        // b is orthogonal to the query: relevance 0.5 by vector and 1 by keyword
+        assert!((fused[0].relevance() - 0.875).abs() < 1e-6);
+        assert!((fused[1].relevance() - 0.25).abs() < 1e-6);
+    }
+}
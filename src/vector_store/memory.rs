@@ -0,0 +1,658 @@
+use async_trait::async_trait;
+use half::f16;
+use im::OrdMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::{cosine_similarity, check_dimension, deadline, CollectionSchema, DistanceMetric, Document, DocumentPage, KeywordHit, KeywordIndex, MetadataFilter, SearchQuery, SearchResult, VectorStore, VectorStoreError};
+use crate::config::VectorStorage;
+
+/// Documents scored between checks of the caller's deadline
+const DEADLINE_CHECK_INTERVAL: usize = 256;
+
+/// Start of the offsets continuing a listing from its snapshot, as
+/// `@snapshot/<token>/<next id>`
+const SNAPSHOT_OFFSET_PREFIX: &str = "@snapshot/";
+
+/// A listing not continued for this long loses its snapshot
+pub const SNAPSHOT_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Listings kept at once; starting another drops the one idle longest
+const MAX_SNAPSHOTS: usize = 64;
+
+/// A vector in the collection's storage format
+#[derive(Debug)]
+enum StoredVector {
+    F32(Vec<f32>),
+    F16(Vec<f16>),
+}
+
+impl StoredVector {
+    fn new(vector: Vec<f32>, storage: VectorStorage) -> Self {
+        match storage {
+            VectorStorage::F32 => StoredVector::F32(vector),
+            VectorStorage::F16 => StoredVector::F16(vector.into_iter().map(f16::from_f32).collect()),
+        }
+    }
+    
+    fn to_f32(&self) -> Vec<f32> {
+        match self {
+            StoredVector::F32(vector) => vector.clone(),
+            StoredVector::F16(vector) => vector.iter().map(|x| x.to_f32()).collect(),
+        }
+    }
+    
+    fn size_bytes(&self) -> usize {
+        match self {
+            StoredVector::F32(vector) => vector.len() * std::mem::size_of::<f32>(),
+            StoredVector::F16(vector) => vector.len() * std::mem::size_of::<f16>(),
+        }
+    }
+}
+
+/// A document held without its embedding, which is kept in `vector`
+#[derive(Debug)]
+struct StoredDocument {
+    document: Document,
+    vector: StoredVector,
+}
+
+impl StoredDocument {
+    fn new(mut document: Document, storage: VectorStorage) -> Self {
+        let vector = StoredVector::new(std::mem::take(&mut document.embedding), storage);
+        Self { document, vector }
+    }
+    
+    fn to_document(&self) -> Document {
+        Document {
+            embedding: self.vector.to_f32(),
+            ..self.document.clone()
+        }
+    }
+    
+    /// Roughly the memory the document's text and vector take
+    fn size_bytes(&self) -> u64 {
+        let metadata: usize = self.document.metadata.iter().map(|(key, value)| key.len() + value.len()).sum();
+        (self.document.id.len() + self.document.content.len() + metadata + self.vector.size_bytes()) as u64
+    }
+}
+
+/// The documents of a collection. Clones share structure, so a listing's
+/// copy costs nothing to take and a write after it copies only the path to
+/// the changed document, never the whole collection.
+type Documents = OrdMap<String, Arc<StoredDocument>>;
+
+#[derive(Debug)]
+struct Collection {
+    vector_size: usize,
+    /// Shared with the listings reading it, which never see a later change
+    documents: Documents,
+    /// Terms of the documents, kept in step with them
+    keywords: KeywordIndex,
+    /// No document was inserted or deleted since the keywords were last rebuilt
+    vacuumed: bool,
+}
+
+/// The view of a collection a listing pages through
+#[derive(Debug)]
+struct Snapshot {
+    collection: String,
+    documents: Documents,
+    last_read: Instant,
+}
+
+/// What a vacuum of the store reclaimed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VacuumReport {
+    /// Collections whose keyword index was rebuilt
+    pub indexes_rebuilt: usize,
+    /// Snapshots of idle listings released
+    pub snapshots_released: usize,
+    /// Deleted or replaced documents those snapshots still held
+    pub documents_released: usize,
+    /// Roughly the memory those documents took
+    pub reclaimed_bytes: u64,
+}
+
+impl VacuumReport {
+    /// Whether the vacuum found nothing to do
+    pub fn is_empty(&self) -> bool {
+        self.indexes_rebuilt == 0 && self.snapshots_released == 0
+    }
+}
+
+/// A vector store held entirely in memory.
+///
+/// Searches are exact (brute-force cosine similarity), which makes it a
+/// predictable backend for tests and small local knowledge bases.
+///
+/// Paging through a collection reads a snapshot taken at its first page, so
+/// a long export or scan sees each document once, as it was then, while
+/// writers carry on; later pages' offsets name the snapshot. A
+/// [`vacuum`](Self::vacuum) reclaims the snapshots of abandoned listings and
+/// rebuilds keyword indexes worn by deletes.
+#[derive(Debug, Default)]
+pub struct InMemoryVectorStore {
+    collections: RwLock<HashMap<String, Collection>>,
+    storage: VectorStorage,
+    snapshots: Mutex<HashMap<String, Snapshot>>,
+}
+
+impl InMemoryVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Hold vectors in `storage` format; f16 halves memory use
+    pub fn with_storage(mut self, storage: VectorStorage) -> Self {
+        self.storage = storage;
+        self
+    }
+}
+
+fn not_found(collection: &str) -> VectorStoreError {
+    VectorStoreError::CollectionNotFound(collection.to_string())
+}
+
+impl InMemoryVectorStore {
+    /// The snapshot `token` of `collection`, once more marked as read
+    fn resume_snapshot(&self, collection: &str, token: &str) -> Result<Documents, VectorStoreError> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        match snapshots.get_mut(token) {
+            Some(snapshot) if snapshot.collection == collection => {
+                snapshot.last_read = Instant::now();
+                Ok(snapshot.documents.clone())
+            },
+            Some(_) => Err(VectorStoreError::InvalidArgument(format!("The offset does not belong to a listing of {}", collection))),
+            None => Err(VectorStoreError::InvalidArgument(format!(
+                "The listing of {} was idle for over {} seconds and has expired; start again without an offset",
+                collection,
+                SNAPSHOT_IDLE_TIMEOUT.as_secs()
+            ))),
+        }
+    }
+    
+    /// Keep `documents` for the listing `token` to continue from
+    fn keep_snapshot(&self, collection: &str, token: &str, documents: Documents) {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let now = Instant::now();
+        snapshots.retain(|_, snapshot| now.duration_since(snapshot.last_read) < SNAPSHOT_IDLE_TIMEOUT);
+        if !snapshots.contains_key(token) && snapshots.len() >= MAX_SNAPSHOTS {
+            let idlest = snapshots.iter().min_by_key(|(_, snapshot)| snapshot.last_read).map(|(token, _)| token.clone());
+            if let Some(idlest) = idlest {
+                snapshots.remove(&idlest);
+            }
+        }
+        snapshots.insert(token.to_string(), Snapshot { collection: collection.to_string(), documents, last_read: now });
+    }
+    
+    /// Reclaim what deletes and replacements left behind, without holding up
+    /// readers or writers for more than a swap: release the snapshots of
+    /// listings gone idle, with the deleted and replaced documents only they
+    /// still held, and rebuild the keyword index of each collection changed
+    /// since the last vacuum, sized to the documents it holds now. Blocks
+    /// while it rebuilds, so async callers run it on the blocking pool.
+    pub fn vacuum(&self) -> VacuumReport {
+        self.vacuum_at(Instant::now())
+    }
+    
+    /// [`vacuum`](Self::vacuum) as of `now`
+    fn vacuum_at(&self, now: Instant) -> VacuumReport {
+        let mut report = VacuumReport::default();
+        
+        let idle: Vec<Snapshot> = {
+            let mut snapshots = self.snapshots.lock().unwrap();
+            let expired: Vec<String> = snapshots.iter()
+                .filter(|(_, snapshot)| now.duration_since(snapshot.last_read) >= SNAPSHOT_IDLE_TIMEOUT)
+                .map(|(token, _)| token.clone())
+                .collect();
+            expired.iter().filter_map(|token| snapshots.remove(token)).collect()
+        };
+        report.snapshots_released = idle.len();
+        let live: HashMap<String, Documents> = self.collections.read().unwrap().iter()
+            .map(|(name, target)| (name.clone(), target.documents.clone()))
+            .collect();
+        let mut released = HashSet::new();
+        for snapshot in &idle {
+            let current = live.get(&snapshot.collection);
+            for (id, stored) in snapshot.documents.iter() {
+                let is_live = current.and_then(|documents| documents.get(id)).is_some_and(|live| Arc::ptr_eq(live, stored));
+                if !is_live && released.insert(Arc::as_ptr(stored)) {
+                    report.reclaimed_bytes += stored.size_bytes();
+                }
+            }
+        }
+        report.documents_released = released.len();
+        drop(idle);
+        
+        let changed: Vec<(String, Documents)> = self.collections.read().unwrap().iter()
+            .filter(|(_, target)| !target.vacuumed)
+            .map(|(name, target)| (name.clone(), target.documents.clone()))
+            .collect();
+        for (name, documents) in changed {
+            let mut keywords = KeywordIndex::new();
+            for stored in documents.values() {
+                keywords.insert(&stored.document);
+            }
+            // A write since the copy was taken changed the index as well;
+            // the next vacuum rebuilds it
+            let mut collections = self.collections.write().unwrap();
+            if let Some(target) = collections.get_mut(&name).filter(|target| target.documents.ptr_eq(&documents)) {
+                target.keywords = keywords;
+                target.vacuumed = true;
+                report.indexes_rebuilt += 1;
+            }
+        }
+        report
+    }
+}
+
+#[async_trait]
+impl VectorStore for InMemoryVectorStore {
+    async fn test_connection(&self) -> Result<(), VectorStoreError> {
+        Ok(())
+    }
+    
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), VectorStoreError> {
+        let mut collections = self.collections.write().unwrap();
+        if collections.contains_key(name) {
+            return Err(VectorStoreError::OperationFailed(format!("Collection already exists: {}", name)));
+        }
+        
+        collections.insert(name.to_string(), Collection {
+            vector_size,
+            documents: OrdMap::new(),
+            keywords: KeywordIndex::new(),
+            vacuumed: true,
+        });
+        Ok(())
+    }
+    
+    async fn delete_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+        self.collections.write().unwrap()
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| not_found(name))
+    }
+    
+    async fn insert_document(&self, collection: &str, document: Document) -> Result<(), VectorStoreError> {
+        let mut collections = self.collections.write().unwrap();
+        let target = collections.get_mut(collection).ok_or_else(|| not_found(collection))?;
+        check_dimension(collection, target.vector_size, document.embedding.len())?;
+        
+        let id = document.id.clone();
+        target.keywords.insert(&document);
+        target.documents.insert(id, Arc::new(StoredDocument::new(document, self.storage)));
+        target.vacuumed = false;
+        Ok(())
+    }
+    
+    async fn search(&self, collection: &str, query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let collections = self.collections.read().unwrap();
+        let target = collections.get(collection).ok_or_else(|| not_found(collection))?;
+        check_dimension(collection, target.vector_size, query.embedding.len())?;
+        
+        let mut scored: Vec<(f32, &StoredDocument)> = Vec::with_capacity(target.documents.len());
+        for (index, stored) in target.documents.values().enumerate() {
+            // Rank what was scored so far rather than miss the caller's deadline
+            if index % DEADLINE_CHECK_INTERVAL == 0 && deadline::is_past() {
+                deadline::mark_partial();
+                break;
+            }
+            scored.push((cosine_similarity(&query.embedding, &stored.vector.to_f32()), stored.as_ref()));
+        }
+        
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(query.limit);
+        Ok(scored.into_iter()
+            .map(|(score, stored)| SearchResult {
+                score,
+                metric: DistanceMetric::Cosine,
+                document: stored.to_document(),
+            })
+            .collect())
+    }
+    
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        let mut names: Vec<String> = self.collections.read().unwrap().keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+    
+    async fn collection_schema(&self, collection: &str) -> Result<Option<CollectionSchema>, VectorStoreError> {
+        Ok(self.collections.read().unwrap()
+            .get(collection)
+            .map(|target| CollectionSchema::new(target.vector_size, Some(DistanceMetric::Cosine.as_str()))))
+    }
+    
+    async fn delete_document(&self, collection: &str, id: &str) -> Result<(), VectorStoreError> {
+        let mut collections = self.collections.write().unwrap();
+        let target = collections.get_mut(collection).ok_or_else(|| not_found(collection))?;
+        if target.documents.remove(id).is_some() {
+            target.keywords.remove(id);
+            target.vacuumed = false;
+        }
+        Ok(())
+    }
+    
+    async fn get_document(&self, collection: &str, id: &str) -> Result<Option<Document>, VectorStoreError> {
+        let collections = self.collections.read().unwrap();
+        let target = collections.get(collection).ok_or_else(|| not_found(collection))?;
+        Ok(target.documents.get(id).map(|stored| stored.to_document()))
+    }
+    
+    async fn keyword_search(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<KeywordHit>, VectorStoreError> {
+        let collections = self.collections.read().unwrap();
+        let target = collections.get(collection).ok_or_else(|| not_found(collection))?;
+        Ok(target.keywords.score(query, limit)
+            .into_iter()
+            .filter_map(|(id, score)| target.documents.get(&id).map(|stored| KeywordHit { document: stored.to_document(), score }))
+            .collect())
+    }
+    
+    async fn count(&self, collection: &str, filter: &MetadataFilter) -> Result<usize, VectorStoreError> {
+        let collections = self.collections.read().unwrap();
+        let target = collections.get(collection).ok_or_else(|| not_found(collection))?;
+        Ok(target.documents.values().filter(|stored| filter.matches(&stored.document)).count())
+    }
+    
+    async fn exists(&self, collection: &str, id: &str) -> Result<bool, VectorStoreError> {
+        let collections = self.collections.read().unwrap();
+        let target = collections.get(collection).ok_or_else(|| not_found(collection))?;
+        Ok(target.documents.contains_key(id))
+    }
+    
+    async fn list_documents(&self, collection: &str, offset: Option<String>, limit: usize) -> Result<DocumentPage, VectorStoreError> {
+        // A listing continues from its snapshot; any other offset, a plain
+        // id, starts a new one there
+        let (token, snapshot, start) = match offset.as_deref().and_then(|offset| offset.strip_prefix(SNAPSHOT_OFFSET_PREFIX)) {
+            Some(rest) => {
+                let (token, start) = rest.split_once('/')
+                    .ok_or_else(|| VectorStoreError::InvalidArgument(format!("Invalid offset: {}", rest)))?;
+                (token.to_string(), self.resume_snapshot(collection, token)?, Some(start.to_string()))
+            },
+            None => {
+                let collections = self.collections.read().unwrap();
+                let target = collections.get(collection).ok_or_else(|| not_found(collection))?;
+                (Uuid::new_v4().to_string(), target.documents.clone(), offset)
+            },
+        };
+        
+        let from = start.as_deref().map_or(Bound::Unbounded, Bound::Included);
+        let mut remaining = snapshot.range::<_, str>((from, Bound::Unbounded));
+        let documents: Vec<Document> = remaining.by_ref().take(limit).map(|(_, stored)| stored.to_document()).collect();
+        let next_offset = match remaining.next() {
+            Some((next, _)) => {
+                let next_offset = format!("{}{}/{}", SNAPSHOT_OFFSET_PREFIX, token, next);
+                self.keep_snapshot(collection, &token, snapshot.clone());
+                Some(next_offset)
+            },
+            None => {
+                self.snapshots.lock().unwrap().remove(&token);
+                None
+            },
+        };
+        
+        Ok(DocumentPage { documents, next_offset })
+    }
+    
+    fn backend_name(&self) -> &'static str {
+        "memory"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn document(id: &str, embedding: Vec<f32>) -> Document {
+        Document {
+            id: id.to_string(),
+            content: format!("content of {}", id),
+            embedding,
+            metadata: Default::default(),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_insert_and_search() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        store.insert_document("docs", document("a", vec![1.0, 0.0])).await.unwrap();
+        store.insert_document("docs", document("b", vec![0.0, 1.0])).await.unwrap();
+        
+        let results = store.search("docs", SearchQuery { embedding: vec![0.9, 0.1], limit: 1 }).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].document.id, "a");
+        assert_eq!(results[0].metric, DistanceMetric::Cosine);
+        assert!(results[0].relevance() > 0.9 && results[0].relevance() <= 1.0);
+    }
+    
+    #[tokio::test]
+    async fn test_rejects_wrong_dimension() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        
+        let result = store.insert_document("docs", document("a", vec![1.0, 0.0, 0.0])).await;
+        assert!(matches!(result, Err(VectorStoreError::DimensionMismatch { expected: 2, actual: 3, .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_list_documents_pages() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 1).await.unwrap();
+        for id in ["a", "b", "c"] {
+            store.insert_document("docs", document(id, vec![1.0])).await.unwrap();
+        }
+        
+        let first = store.list_documents("docs", None, 2).await.unwrap();
+        assert_eq!(first.documents.len(), 2);
+        assert!(first.next_offset.as_deref().is_some_and(|offset| offset.ends_with("/c")));
+        
+        let second = store.list_documents("docs", first.next_offset, 2).await.unwrap();
+        assert_eq!(second.documents.len(), 1);
+        assert_eq!(second.documents[0].id, "c");
+        assert!(second.next_offset.is_none());
+        
+        // A plain id starts a listing there
+        let from_b = store.list_documents("docs", Some("b".to_string()), 10).await.unwrap();
+        assert_eq!(from_b.documents.iter().map(|document| document.id.as_str()).collect::<Vec<_>>(), ["b", "c"]);
+    }
+    
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_listing_sees_a_snapshot_while_writers_carry_on() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("docs", 1).await.unwrap();
+        for index in 0..200 {
+            store.insert_document("docs", document(&format!("doc-{:03}", index), vec![1.0])).await.unwrap();
+        }
+        
+        let first = store.list_documents("docs", None, 10).await.unwrap();
+        let writer = {
+            let store = store.clone();
+            tokio::spawn(async move {
+                for index in 0..200 {
+                    // New documents land between and after the listed ones
+                    store.insert_document("docs", document(&format!("doc-{:03}-new", index), vec![1.0])).await.unwrap();
+                    store.delete_document("docs", &format!("doc-{:03}", 199 - index)).await.unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        
+        let mut listed: Vec<String> = first.documents.into_iter().map(|document| document.id).collect();
+        let mut offset = first.next_offset;
+        while let Some(next) = offset {
+            let page = store.list_documents("docs", Some(next), 10).await.unwrap();
+            listed.extend(page.documents.into_iter().map(|document| document.id));
+            offset = page.next_offset;
+            tokio::task::yield_now().await;
+        }
+        writer.await.unwrap();
+        
+        let expected: Vec<String> = (0..200).map(|index| format!("doc-{:03}", index)).collect();
+        assert_eq!(listed, expected);
+        assert_eq!(store.count("docs", &Default::default()).await.unwrap(), 200);
+        assert!(store.snapshots.lock().unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_interleaved_listings_of_a_large_collection() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 1).await.unwrap();
+        for index in 0..20_000 {
+            store.insert_document("docs", document(&format!("doc-{:05}", index), vec![1.0])).await.unwrap();
+        }
+        
+        // Two exports page through side by side, one started halfway
+        // through the other, with a write between every page
+        let mut early = Vec::new();
+        let mut late = Vec::new();
+        let mut early_offset = Some(None);
+        let mut late_offset = None;
+        let mut written = 0;
+        let mut written_by_halfway = 0;
+        while early_offset.is_some() || late_offset.is_some() {
+            if let Some(offset) = early_offset.take() {
+                let page = store.list_documents("docs", offset, 100).await.unwrap();
+                early.extend(page.documents.into_iter().map(|document| document.id));
+                early_offset = page.next_offset.map(Some);
+                if early.len() == 10_000 {
+                    late_offset = Some(None);
+                    written_by_halfway = written;
+                }
+            }
+            if let Some(offset) = late_offset.take() {
+                let page = store.list_documents("docs", offset, 100).await.unwrap();
+                late.extend(page.documents.into_iter().map(|document| document.id));
+                late_offset = page.next_offset.map(Some);
+            }
+            store.insert_document("docs", document(&format!("doc-{:05}-new", written), vec![1.0])).await.unwrap();
+            store.delete_document("docs", &format!("doc-{:05}", 19_999 - written)).await.unwrap();
+            written += 1;
+        }
+        
+        // Each sees the collection as it was at its first page
+        let expected: Vec<String> = (0..20_000).map(|index| format!("doc-{:05}", index)).collect();
+        assert_eq!(early, expected);
+        let mut at_halfway: Vec<String> = (0..20_000 - written_by_halfway).map(|index| format!("doc-{:05}", index))
+            .chain((0..written_by_halfway).map(|index| format!("doc-{:05}-new", index)))
+            .collect();
+        at_halfway.sort();
+        assert_eq!(late, at_halfway);
+        assert!(store.snapshots.lock().unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_vacuum_releases_idle_listings_and_rebuilds_indexes() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 1).await.unwrap();
+        for index in 0..100 {
+            let mut entry = document(&format!("doc-{:03}", index), vec![1.0]);
+            entry.content = format!("note number{}", index);
+            store.insert_document("docs", entry).await.unwrap();
+        }
+        let listing = store.list_documents("docs", None, 10).await.unwrap();
+        assert!(listing.next_offset.is_some());
+        for index in 10..100 {
+            store.delete_document("docs", &format!("doc-{:03}", index)).await.unwrap();
+        }
+        
+        // The listing is still in use, so only the index is rebuilt
+        let report = store.vacuum();
+        assert_eq!((report.indexes_rebuilt, report.snapshots_released), (1, 0));
+        assert_eq!(store.keyword_search("docs", "note", 100).await.unwrap().len(), 10);
+        assert!(store.keyword_search("docs", "number50", 10).await.unwrap().is_empty());
+        
+        let report = store.vacuum_at(Instant::now() + SNAPSHOT_IDLE_TIMEOUT);
+        assert_eq!(report.indexes_rebuilt, 0);
+        assert_eq!(report.snapshots_released, 1);
+        assert_eq!(report.documents_released, 90);
+        assert!(report.reclaimed_bytes >= 90 * 4);
+        assert!(store.snapshots.lock().unwrap().is_empty());
+        assert!(store.vacuum().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_expired_listing_is_refused() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 1).await.unwrap();
+        for id in ["a", "b"] {
+            store.insert_document("docs", document(id, vec![1.0])).await.unwrap();
+        }
+        let first = store.list_documents("docs", None, 1).await.unwrap();
+        store.snapshots.lock().unwrap().clear();
+        
+        let result = store.list_documents("docs", first.next_offset, 1).await;
+        assert!(matches!(result, Err(VectorStoreError::InvalidArgument(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_f16_storage_round_trips() {
+        let store = InMemoryVectorStore::new().with_storage(VectorStorage::F16);
+        store.create_collection("docs", 2).await.unwrap();
+        store.insert_document("docs", document("a", vec![0.6, 0.8])).await.unwrap();
+        
+        let stored = store.get_document("docs", "a").await.unwrap().unwrap();
+        assert!((stored.embedding[0] - 0.6).abs() < 1e-3);
+        assert!((stored.embedding[1] - 0.8).abs() < 1e-3);
+        
+        let results = store.search("docs", SearchQuery { embedding: vec![0.6, 0.8], limit: 1 }).await.unwrap();
+        assert!((results[0].score - 1.0).abs() < 1e-3);
+    }
+    
+    #[tokio::test]
+    async fn test_search_stops_at_deadline() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        store.insert_document("docs", document("a", vec![1.0, 0.0])).await.unwrap();
+        let query = SearchQuery { embedding: vec![1.0, 0.0], limit: 1 };
+        
+        let (results, partial) = deadline::with_deadline(Some(std::time::Instant::now()), store.search("docs", query.clone())).await;
+        assert!(partial);
+        assert!(results.unwrap().is_empty());
+        
+        let later = std::time::Instant::now() + std::time::Duration::from_secs(60);
+        let (results, partial) = deadline::with_deadline(Some(later), store.search("docs", query)).await;
+        assert!(!partial);
+        assert_eq!(results.unwrap().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_delete_document() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 1).await.unwrap();
+        store.insert_document("docs", document("a", vec![1.0])).await.unwrap();
+        assert!(store.get_document("docs", "a").await.unwrap().is_some());
+        store.delete_document("docs", "a").await.unwrap();
+        assert!(store.get_document("docs", "a").await.unwrap().is_none());
+        
+        let page = store.list_documents("docs", None, 10).await.unwrap();
+        assert!(page.documents.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_keyword_search_follows_writes() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 1).await.unwrap();
+        for (id, content) in [("a", "Rotate the signing key"), ("b", "Nightly backups"), ("c", "Backups of the key store")] {
+            store.insert_document("docs", Document { content: content.to_string(), ..document(id, vec![1.0]) }).await.unwrap();
+        }
+        
+        let hits = store.keyword_search("docs", "signing key", 10).await.unwrap();
+        let ids: Vec<&str> = hits.iter().map(|hit| hit.document.id.as_str()).collect();
+        assert_eq!(ids, ["a", "c"]);
+        
+        store.delete_document("docs", "a").await.unwrap();
+        store.insert_document("docs", Document { content: "Nightly restores".to_string(), ..document("b", vec![1.0]) }).await.unwrap();
+        let ids: Vec<String> = store.keyword_search("docs", "key backups", 10).await.unwrap()
+            .into_iter()
+            .map(|hit| hit.document.id)
+            .collect();
+        assert_eq!(ids, ["c"]);
+    }
+}
@@ -1,15 +1,67 @@
 mod pure;
+pub mod batch;
+pub mod chunks;
+pub mod clone;
+pub mod compaction;
+pub mod compression;
+pub mod deadline;
+pub mod drift;
+pub mod encrypted;
+pub mod evented;
+pub mod expiry;
+pub mod filter;
+pub mod id;
+pub mod keywords;
+pub mod memory;
+pub mod patch;
+pub mod placeholder;
+pub mod pool;
+pub mod registry;
+pub mod replicated;
+pub mod routed;
+pub mod schema;
+pub mod sharded;
+pub mod timed;
+pub mod trace;
+pub mod wal;
 pub use pure::*;
+pub use batch::BatchItem;
+pub use clone::{clone_collection, CloneOptions, CloneOutcome};
+pub use compaction::{CompactionReport, Compactor};
+pub use compression::PayloadCompression;
+pub use drift::{check_schema, detect_drift, reconcile, CollectionSchema, Reconciliation, SchemaGuard};
+pub use encrypted::{EncryptedVectorStore, EncryptionKey};
+pub use evented::EventedVectorStore;
+pub use expiry::{check_ttl, expires_at, expiry_after, is_expired, purge_expired, search_unexpired, EXPIRES_AT_KEY, MAX_TTL_SECS};
+pub use memory::{InMemoryVectorStore, VacuumReport};
+pub use filter::MetadataFilter;
+pub use id::{EntryId, EntryIdError};
+pub use keywords::{fuse_hybrid, KeywordHit, KeywordIndex};
+pub use patch::{MetadataPatch, PatchOutcome};
+pub use placeholder::{embedding_defect, find_placeholders, EmbeddingDefect};
+pub use pool::PoolMetrics;
+pub use trace::{collect_stages, slowest_stage, BackendStage};
+pub use replicated::ReplicatedVectorStore;
+pub use routed::RoutedVectorStore;
+pub use registry::{check_dimension, CollectionInfo, CollectionRegistry, HybridWeights, RerankSettings, SearchDefaults, REGISTRY_FILE};
+pub use schema::{EntrySchema, FieldError};
+pub use sharded::{shard_name, shard_of, ReshardOutcome, ShardedVectorStore};
+pub use timed::TimedVectorStore;
+pub use wal::{CheckpointReport, RecoveryReport, WalVectorStore, WAL_FILE};
 
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use async_trait::async_trait;
-use deadpool::managed::{Manager, Pool, PoolError, RecycleError};
+use deadpool::managed::{Manager, Object, Pool, PoolError, RecycleError, TimeoutType};
 use backoff::{ExponentialBackoff, ExponentialBackoffBuilder};
 use qdrant_client::qdrant::{VectorParams, Distance};
 use qdrant_client::{Qdrant, QdrantError};
 use qdrant_client::config::QdrantConfig as QdrantClientConfig;
 use tracing::error;
+use trace::{OperationTrace, BUILD_STAGE, DESERIALIZE_STAGE, NETWORK_STAGE, POOL_STAGE};
+
+use crate::progress::Progress;
 
 #[derive(Debug, Error)]
 pub enum VectorStoreError {
@@ -25,13 +77,45 @@ pub enum VectorStoreError {
     #[error("Pool error: {0}")]
     PoolError(String),
     
+    #[error("Connection pool exhausted: {0}")]
+    PoolExhausted(String),
+    
     #[error("Timeout error: {0}")]
     TimeoutError(String),
+    
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    
+    #[error("Collection not found: {0}")]
+    CollectionNotFound(String),
+    
+    #[error("Embedding dimension mismatch for collection '{collection}': expected {expected}, got {actual}")]
+    DimensionMismatch {
+        collection: String,
+        expected: usize,
+        actual: usize,
+    },
+    
+    #[error("Request deadline exceeded")]
+    DeadlineExceeded,
+    
+    #[error("Schema mismatch in collection '{collection}': the registry expects {expected} but the collection has {actual}; run `p-mo reconcile {collection}` to update the registry or recreate the collection")]
+    SchemaMismatch {
+        collection: String,
+        expected: CollectionSchema,
+        actual: CollectionSchema,
+    },
 }
 
 impl From<PoolError<QdrantError>> for VectorStoreError {
     fn from(err: PoolError<QdrantError>) -> Self {
-        VectorStoreError::PoolError(err.to_string())
+        match err {
+            PoolError::Timeout(TimeoutType::Wait) => VectorStoreError::PoolExhausted(
+                "timed out waiting for a free Qdrant connection; consider raising pool.max_connections".to_string(),
+            ),
+            PoolError::Timeout(TimeoutType::Create) => VectorStoreError::TimeoutError("timed out connecting to Qdrant".to_string()),
+            err => VectorStoreError::PoolError(err.to_string()),
+        }
     }
 }
 
@@ -44,6 +128,96 @@ pub trait VectorStore: Send + Sync {
     async fn delete_collection(&self, name: &str) -> Result<(), VectorStoreError>;
     async fn insert_document(&self, collection: &str, document: Document) -> Result<(), VectorStoreError>;
     async fn search(&self, collection: &str, query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError>;
+    
+    // Optional operations; stores that cannot support them keep the defaults
+    
+    /// List the names of all collections
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        Err(unsupported("list_collections"))
+    }
+    
+    /// Delete a single document by id
+    async fn delete_document(&self, _collection: &str, _id: &str) -> Result<(), VectorStoreError> {
+        Err(unsupported("delete_document"))
+    }
+    
+    /// Fetch a single document by id
+    async fn get_document(&self, _collection: &str, _id: &str) -> Result<Option<Document>, VectorStoreError> {
+        Err(unsupported("get_document"))
+    }
+    
+    /// Page through the documents of a collection in id order.
+    ///
+    /// `offset` is the `next_offset` of the previous page.
+    async fn list_documents(&self, _collection: &str, _offset: Option<String>, _limit: usize) -> Result<DocumentPage, VectorStoreError> {
+        Err(unsupported("list_documents"))
+    }
+    
+    /// Apply `patch` to the metadata of every document matching `filter`,
+    /// leaving content and embeddings untouched.
+    ///
+    /// The default re-inserts each changed document; stores with a payload
+    /// update API should override it.
+    async fn patch_metadata(&self, collection: &str, filter: &MetadataFilter, patch: &MetadataPatch) -> Result<PatchOutcome, VectorStoreError> {
+        patch::rewrite_metadata(self, collection, filter, patch, false).await
+    }
+    
+    /// Insert `documents`, reporting the outcome of each.
+    ///
+    /// A failed document does not stop the others unless `atomic` is set,
+    /// in which case the batch is undone and every document reports an
+    /// error. The default inserts one document at a time.
+    async fn batch_insert(&self, collection: &str, documents: Vec<Document>, atomic: bool) -> Result<Vec<BatchItem>, VectorStoreError> {
+        batch::insert_each(self, collection, documents, atomic).await
+    }
+    
+    /// Count the documents matching `filter` without fetching them
+    async fn count(&self, collection: &str, filter: &MetadataFilter) -> Result<usize, VectorStoreError> {
+        filter::count_matching(self, collection, filter).await
+    }
+    
+    /// Search only the documents matching `filter`.
+    ///
+    /// The default pages through the collection and scores the matching
+    /// documents by cosine similarity; stores that filter server-side
+    /// should override it.
+    async fn search_filtered(&self, collection: &str, query: SearchQuery, filter: &MetadataFilter) -> Result<Vec<SearchResult>, VectorStoreError> {
+        filter::search_matching(self, collection, query, filter).await
+    }
+    
+    /// The `limit` documents scoring best for `query` by keywords.
+    ///
+    /// The default indexes the whole collection on every call; stores that
+    /// keep a keyword index alongside the vectors should override it.
+    async fn keyword_search(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<KeywordHit>, VectorStoreError> {
+        keywords::search_by_scan(self, collection, query, limit).await
+    }
+    
+    /// Whether a document with this id exists
+    async fn exists(&self, collection: &str, id: &str) -> Result<bool, VectorStoreError> {
+        Ok(self.get_document(collection, id).await?.is_some())
+    }
+    
+    /// Vector size and distance a collection was created with; `None` if it
+    /// does not exist or the store cannot tell
+    async fn collection_schema(&self, _collection: &str) -> Result<Option<CollectionSchema>, VectorStoreError> {
+        Ok(None)
+    }
+    
+    /// State of the backend connection pool; `None` for stores without one
+    fn pool_metrics(&self) -> Option<PoolMetrics> {
+        None
+    }
+    
+    /// Kind of backend, e.g. "qdrant", as reported by telemetry
+    fn backend_name(&self) -> &'static str {
+        "custom"
+    }
+}
+
+/// The error returned by operations a store does not implement
+pub fn unsupported(operation: &str) -> VectorStoreError {
+    VectorStoreError::OperationFailed(format!("{} is not supported by this vector store", operation))
 }
 
 #[derive(Debug, Clone)]
@@ -51,6 +225,10 @@ pub struct QdrantConfig {
     pub url: String,
     pub timeout: Duration,
     pub max_connections: usize,
+    /// How long to wait for a free pooled connection before failing
+    pub pool_wait_timeout: Option<Duration>,
+    /// How long opening a pooled connection may take
+    pub pool_create_timeout: Option<Duration>,
     pub api_key: Option<String>,
     pub retry_max_elapsed_time: Duration,
     pub retry_initial_interval: Duration,
@@ -58,12 +236,24 @@ pub struct QdrantConfig {
     pub retry_multiplier: f64,
 }
 
+impl QdrantConfig {
+    /// Apply the pool sizing and timeouts from the config file
+    pub fn with_pool(mut self, pool: &crate::config::PoolConfig) -> Self {
+        self.max_connections = pool.max_connections;
+        self.pool_wait_timeout = Some(Duration::from_millis(pool.wait_timeout_ms));
+        self.pool_create_timeout = Some(Duration::from_millis(pool.create_timeout_ms));
+        self
+    }
+}
+
 impl Default for QdrantConfig {
     fn default() -> Self {
         Self {
             url: "http://localhost:6333".to_string(),
             timeout: Duration::from_secs(5),
             max_connections: 10,
+            pool_wait_timeout: Some(Duration::from_secs(5)),
+            pool_create_timeout: Some(Duration::from_secs(5)),
             api_key: None,
             retry_max_elapsed_time: Duration::from_secs(60),
             retry_initial_interval: Duration::from_millis(100),
@@ -87,7 +277,7 @@ impl QdrantClientManager {
 impl Manager for QdrantClientManager {
     type Type = Qdrant;
     type Error = QdrantError;
-
+    
     async fn create(&self) -> Result<Qdrant, QdrantError> {
         let mut config = QdrantClientConfig::from_url(&self.config.url);
         
@@ -101,7 +291,7 @@ impl Manager for QdrantClientManager {
         
         Qdrant::new(config)
     }
-
+    
     async fn recycle(&self, client: &mut Qdrant) -> Result<(), RecycleError<QdrantError>> {
         // Check if the client is still usable
         match client.health_check().await {
@@ -111,10 +301,17 @@ impl Manager for QdrantClientManager {
     }
 }
 
+/// Most points a Qdrant keyword search scores; scrolling is not ordered by
+/// score, so past this a strong match can be missed
+const KEYWORD_CANDIDATES: usize = 2048;
+
 #[derive(Clone)]
 pub struct QdrantConnector {
     client_pool: Pool<QdrantClientManager>,
+    pool_stats: Arc<pool::PoolStats>,
     config: QdrantConfig,
+    compression: Option<PayloadCompression>,
+    normalized: bool,
 }
 
 impl QdrantConnector {
@@ -122,15 +319,79 @@ impl QdrantConnector {
         let manager = QdrantClientManager::new(config.clone());
         let pool = Pool::builder(manager)
             .max_size(config.max_connections)
+            .wait_timeout(config.pool_wait_timeout)
+            .create_timeout(config.pool_create_timeout)
+            .runtime(deadpool::Runtime::Tokio1)
             .build()
             .map_err(|e| VectorStoreError::ConnectionError(e.to_string()))?;
         
         Ok(Self {
             client_pool: pool,
+            pool_stats: Arc::new(pool::PoolStats::default()),
             config,
+            compression: None,
+            normalized: false,
         })
     }
     
+    /// Compress large content before storing it; reads always decompress
+    pub fn with_compression(mut self, compression: PayloadCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+    
+    /// Create collections for L2-normalized vectors, searched by dot product
+    pub fn with_normalization(mut self, normalized: bool) -> Self {
+        self.normalized = normalized;
+        self
+    }
+    
+    /// The metric collections are created with and searched by
+    pub fn metric(&self) -> DistanceMetric {
+        if self.normalized { DistanceMetric::Dot } else { DistanceMetric::Cosine }
+    }
+    
+    /// Rewrite documents stored before compression was enabled.
+    ///
+    /// Every document at or above the threshold is re-inserted, which stores
+    /// it compressed. Returns the number of documents rewritten.
+    pub async fn compress_existing(&self, collection: &str, progress: &Progress) -> Result<usize, VectorStoreError> {
+        let compression = self.compression.ok_or_else(|| {
+            VectorStoreError::InvalidArgument("Compression is not enabled for this connector".to_string())
+        })?;
+        
+        if let Ok(total) = self.count(collection, &MetadataFilter::default()).await {
+            progress.set_total(total as u64);
+        }
+        let mut rewritten = 0;
+        let mut offset = None;
+        loop {
+            let page = self.list_documents(collection, offset, 256).await?;
+            for document in page.documents {
+                progress.inc(1);
+                if compression.should_compress(document.content.len()) {
+                    self.insert_document(collection, document).await?;
+                    rewritten += 1;
+                }
+            }
+            
+            match page.next_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+        
+        Ok(rewritten)
+    }
+    
+    /// Check a client out of the pool, recording how long that took
+    async fn client(&self) -> Result<Object<QdrantClientManager>, VectorStoreError> {
+        let started = Instant::now();
+        let client = self.client_pool.get().await;
+        self.pool_stats.record(started.elapsed(), client.as_ref().err());
+        Ok(client?)
+    }
+    
     fn create_backoff(&self) -> ExponentialBackoff {
         ExponentialBackoffBuilder::new()
             .with_initial_interval(self.config.retry_initial_interval)
@@ -151,11 +412,18 @@ impl QdrantConnector {
         let max_attempts = 3; // Limit the number of retries
         
         loop {
-            match operation().await {
+            // Each attempt gets only the time left before the caller's deadline
+            match deadline::bounded(operation()).await {
                 Ok(value) => return Ok(value),
                 Err(err) => {
                     current_attempt += 1;
-                    if current_attempt >= max_attempts {
+                    if current_attempt >= max_attempts || matches!(err, VectorStoreError::DeadlineExceeded) {
+                        return Err(err);
+                    }
+                    
+                    // Wait before retrying, unless the deadline would pass first
+                    let wait_time = backoff.initial_interval * (backoff.multiplier.powf(current_attempt as f64 - 1.0) as u32);
+                    if deadline::cap(wait_time) < wait_time {
                         return Err(err);
                     }
                     
@@ -163,21 +431,64 @@ impl QdrantConnector {
                     error!("Operation failed, will retry (attempt {}/{}): {}", 
                            current_attempt, max_attempts, err);
                     
-                    // Wait before retrying
-                    let wait_time = backoff.initial_interval * (backoff.multiplier.powf(current_attempt as f64 - 1.0) as u32);
                     tokio::time::sleep(wait_time).await;
                 }
             }
         }
     }
+    
+    /// Search a collection, only among the points matching `filter` when one is given
+    async fn search_points(&self, collection: &str, query: SearchQuery, filter: Option<qdrant_client::qdrant::Filter>) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.with_retry(|| async {
+            let mut trace = OperationTrace::start("search", collection);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
+            
+            use qdrant_client::qdrant::{SearchParams, WithPayloadSelector, WithVectorsSelector, SearchPoints};
+            
+            // Create search request
+            let search_request = trace.run(BUILD_STAGE, || SearchPoints {
+                collection_name: collection.to_string(),
+                vector: query.embedding.clone(),
+                limit: query.limit as u64,
+                filter: filter.clone(),
+                with_payload: Some(WithPayloadSelector::from(true)),
+                with_vectors: Some(WithVectorsSelector::from(true)),
+                params: Some(SearchParams {
+                    hnsw_ef: Some(128),
+                    exact: Some(false),
+                    ..Default::default()
+                }),
+                // Qdrant's own timeout, capped to the caller's deadline; it counts whole seconds
+                timeout: deadline::remaining().map(|remaining| remaining.as_secs().max(1)),
+                ..Default::default()
+            });
+            
+            // Execute search
+            let search_result = trace.wait(NETWORK_STAGE, client.search_points(search_request)).await
+                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to search: {}", e)))?;
+            
+            // Convert search results to our format
+            let results = trace.run(DESERIALIZE_STAGE, || search_result.result
+                .into_iter()
+                .filter_map(|point| {
+                    let score = point.score;
+                    point_to_document(point.id, point.payload, point.vectors)
+                        .map(|document| SearchResult { document, score, metric: self.metric() })
+                })
+                .collect());
+            
+            Ok(results)
+        }).await
+    }
 }
 
 #[async_trait]
 impl VectorStore for QdrantConnector {
     async fn test_connection(&self) -> Result<(), VectorStoreError> {
         self.with_retry(|| async {
-            let client = self.client_pool.get().await?;
-            client.health_check().await
+            let mut trace = OperationTrace::start("test_connection", "");
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
+            trace.wait(NETWORK_STAGE, client.health_check()).await
                 .map(|_| ())
                 .map_err(|e| VectorStoreError::ConnectionError(e.to_string()))
         }).await
@@ -185,12 +496,13 @@ impl VectorStore for QdrantConnector {
     
     async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), VectorStoreError> {
         self.with_retry(|| async {
-            let client = self.client_pool.get().await?;
+            let mut trace = OperationTrace::start("create_collection", name);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
             
             // Create a collection with the given name and vector size
             let vector_params = VectorParams {
                 size: vector_size as u64,
-                distance: Distance::Cosine as i32,
+                distance: (match self.metric() { DistanceMetric::Dot => Distance::Dot, DistanceMetric::Cosine => Distance::Cosine }) as i32,
                 ..Default::default()
             };
             
@@ -206,7 +518,7 @@ impl VectorStore for QdrantConnector {
                 ..Default::default()
             };
             
-            client.create_collection(create_collection).await
+            trace.wait(NETWORK_STAGE, client.create_collection(create_collection)).await
                 .map(|_| ())
                 .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to create collection: {}", e)))
         }).await
@@ -214,138 +526,635 @@ impl VectorStore for QdrantConnector {
     
     async fn delete_collection(&self, name: &str) -> Result<(), VectorStoreError> {
         self.with_retry(|| async {
-            let client = self.client_pool.get().await?;
+            let mut trace = OperationTrace::start("delete_collection", name);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
             
-            client.delete_collection(name).await
+            trace.wait(NETWORK_STAGE, client.delete_collection(name)).await
                 .map(|_| ())
                 .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to delete collection: {}", e)))
         }).await
     }
     
     async fn insert_document(&self, collection: &str, document: Document) -> Result<(), VectorStoreError> {
+        let entry_id = EntryId::parse(&document.id)?;
+        
         self.with_retry(|| async {
-            let client = self.client_pool.get().await?;
+            let mut trace = OperationTrace::start("insert_document", collection);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
             
-            use qdrant_client::qdrant::{PointId, PointStruct, Vectors, Vector};
+            use qdrant_client::qdrant::{PointStruct, Vectors, Vector};
             use std::collections::HashMap;
             
-            // Create point ID
-            let point_id = PointId {
-                point_id_options: Some(qdrant_client::qdrant::point_id::PointIdOptions::Uuid(
-                    document.id.clone(),
-                )),
-            };
+            let upsert_points = trace.run(BUILD_STAGE, || -> Result<_, VectorStoreError> {
+                // Create point ID; external ids are hashed into a UUID and kept in the payload
+                let point_id = point_id(&entry_id);
+                
+                // Create vector
+                let vector = Vector {
+                    data: document.embedding.clone(),
+                    vector: None,
+                    indices: None,
+                    vectors_count: None,
+                };
+                
+                // Create vectors
+                let vectors = Vectors {
+                    vectors_options: Some(qdrant_client::qdrant::vectors::VectorsOptions::Vector(vector)),
+                };
+                
+                // Create payload, compressing large content when enabled
+                let (content, encoding) = match &self.compression {
+                    Some(compression) => compression.encode(&document.content)?,
+                    None => (document.content.clone(), None),
+                };
+                
+                let mut payload = HashMap::new();
+                payload.insert(
+                    "content".to_string(),
+                    qdrant_client::qdrant::Value {
+                        kind: Some(qdrant_client::qdrant::value::Kind::StringValue(
+                            content,
+                        )),
+                    },
+                );
+                if let Some(encoding) = encoding {
+                    payload.insert(
+                        compression::CONTENT_ENCODING_KEY.to_string(),
+                        qdrant_client::qdrant::Value {
+                            kind: Some(qdrant_client::qdrant::value::Kind::StringValue(encoding.to_string())),
+                        },
+                    );
+                }
+                if !document.metadata.is_empty() {
+                    payload.insert("metadata".to_string(), metadata_to_value(&document.metadata));
+                }
+                if !entry_id.is_uuid() {
+                    payload.insert(
+                        id::ENTRY_ID_KEY.to_string(),
+                        qdrant_client::qdrant::Value {
+                            kind: Some(qdrant_client::qdrant::value::Kind::StringValue(entry_id.to_string())),
+                        },
+                    );
+                }
+                payload.extend(keyword_payload(&document));
+                
+                // Create point
+                let point = PointStruct {
+                    id: Some(point_id),
+                    vectors: Some(vectors),
+                    payload,
+                };
+                
+                // Create upsert points request
+                Ok(qdrant_client::qdrant::UpsertPoints {
+                    collection_name: collection.to_string(),
+                    wait: Some(true),
+                    points: vec![point],
+                    ..Default::default()
+                })
+            })?;
             
-            // Create vector
-            let vector = Vector {
-                data: document.embedding.clone(),
-                vector: None,
-                indices: None,
-                vectors_count: None,
-            };
+            // Insert point into collection
+            trace.wait(NETWORK_STAGE, client.upsert_points(upsert_points)).await
+                .map(|_| ())
+                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to insert document: {}", e)))
+        }).await
+    }
+    
+    async fn search(&self, collection: &str, query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.search_points(collection, query, None).await
+    }
+    
+    async fn search_filtered(&self, collection: &str, query: SearchQuery, filter: &MetadataFilter) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let server_filter = qdrant_filter(filter)?;
+        let mut results = self.search_points(collection, query, Some(server_filter)).await?;
+        // Tags are stored joined, so Qdrant cannot match them itself
+        results.retain(|result| filter.matches(&result.document));
+        Ok(results)
+    }
+    
+    async fn keyword_search(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<KeywordHit>, VectorStoreError> {
+        let terms = keywords::query_terms(query);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        self.with_retry(|| async {
+            let mut trace = OperationTrace::start("keyword_search", collection);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
             
-            // Create vectors
-            let vectors = Vectors {
-                vectors_options: Some(qdrant_client::qdrant::vectors::VectorsOptions::Vector(vector)),
-            };
+            use qdrant_client::qdrant::{Condition, CountPoints, Filter, ScrollPoints};
+            use std::collections::HashMap;
             
-            // Create payload
-            let mut payload = HashMap::new();
-            payload.insert(
-                "content".to_string(),
-                qdrant_client::qdrant::Value {
-                    kind: Some(qdrant_client::qdrant::value::Kind::StringValue(
-                        document.content.clone(),
-                    )),
-                },
-            );
-            
-            // Create point
-            let point = PointStruct {
-                id: Some(point_id),
-                vectors: Some(vectors),
-                payload,
+            let count = |filter: Option<Filter>| CountPoints {
+                collection_name: collection.to_string(),
+                filter,
+                exact: Some(true),
+                ..Default::default()
             };
+            let failed = |e: QdrantError| VectorStoreError::OperationFailed(format!("Failed to search keywords: {}", e));
+            
+            // Document frequencies come from the terms each point lists in its payload
+            let documents = trace.wait(NETWORK_STAGE, client.count(count(None))).await.map_err(failed)?
+                .result.map(|result| result.count as usize).unwrap_or(0);
+            let mut document_frequencies = HashMap::new();
+            for term in &terms {
+                let filter = Filter::must([Condition::matches(keywords::TERMS_KEY, term.clone())]);
+                let response = trace.wait(NETWORK_STAGE, client.count(count(Some(filter)))).await.map_err(failed)?;
+                document_frequencies.insert(term.clone(), response.result.map(|result| result.count as usize).unwrap_or(0));
+            }
+            
+            // Scroll the points holding any of the terms, up to a cap
+            let filter = Filter::should(terms.iter().map(|term| Condition::matches(keywords::TERMS_KEY, term.clone())));
+            let mut candidates = Vec::new();
+            let mut offset = None;
+            while candidates.len() < KEYWORD_CANDIDATES {
+                let scroll_points = ScrollPoints {
+                    collection_name: collection.to_string(),
+                    filter: Some(filter.clone()),
+                    offset,
+                    limit: Some(filter::FILTER_PAGE_SIZE as u32),
+                    with_payload: Some(true.into()),
+                    with_vectors: Some(true.into()),
+                    ..Default::default()
+                };
+                let response = trace.wait(NETWORK_STAGE, client.scroll(scroll_points)).await.map_err(failed)?;
+                candidates.extend(response.result);
+                offset = match response.next_page_offset {
+                    Some(next) => Some(next),
+                    None => break,
+                };
+            }
+            
+            Ok(trace.run(DESERIALIZE_STAGE, || {
+                let candidates: Vec<_> = candidates.into_iter()
+                    .filter_map(|point| {
+                        let (frequencies, length) = payload_term_frequencies(&point.payload)?;
+                        let document = point_to_document(point.id, point.payload, point.vectors)?;
+                        Some((document, frequencies, length))
+                    })
+                    .collect();
+                // Qdrant keeps no collection-wide length total; the candidates stand in for it
+                let average_length = candidates.iter().map(|(_, _, length)| *length).sum::<usize>() as f32
+                    / candidates.len().max(1) as f32;
+                
+                let mut hits: Vec<KeywordHit> = candidates.into_iter()
+                    .map(|(document, frequencies, length)| {
+                        let score = terms.iter()
+                            .filter_map(|term| frequencies.get(term).map(|frequency| {
+                                keywords::bm25(*frequency, document_frequencies[term], documents, length, average_length)
+                            }))
+                            .sum();
+                        KeywordHit { document, score }
+                    })
+                    .collect();
+                hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.document.id.cmp(&b.document.id)));
+                hits.truncate(limit);
+                hits
+            }))
+        }).await
+    }
+    
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        self.with_retry(|| async {
+            let mut trace = OperationTrace::start("list_collections", "");
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
+            
+            let response = trace.wait(NETWORK_STAGE, client.list_collections()).await
+                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to list collections: {}", e)))?;
             
-            // Create upsert points request
-            let upsert_points = qdrant_client::qdrant::UpsertPoints {
+            Ok(response.collections.into_iter().map(|c| c.name).collect())
+        }).await
+    }
+    
+    async fn collection_schema(&self, collection: &str) -> Result<Option<CollectionSchema>, VectorStoreError> {
+        self.with_retry(|| async {
+            let mut trace = OperationTrace::start("collection_info", collection);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
+            
+            let exists = trace.wait(NETWORK_STAGE, client.collection_exists(collection)).await
+                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to check collection: {}", e)))?;
+            if !exists {
+                return Ok(None);
+            }
+            let response = trace.wait(NETWORK_STAGE, client.collection_info(collection)).await
+                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to get collection info: {}", e)))?;
+            
+            Ok(trace.run(DESERIALIZE_STAGE, || {
+                use qdrant_client::qdrant::vectors_config::Config;
+                
+                // Collections with named vectors were not created by p-mo and have no single schema
+                match response.result?.config?.params?.vectors_config?.config? {
+                    Config::Params(params) => {
+                        let distance = Distance::try_from(params.distance).ok().map(|distance| distance.as_str_name());
+                        Some(CollectionSchema::new(params.size as usize, distance))
+                    },
+                    Config::ParamsMap(_) => None,
+                }
+            }))
+        }).await
+    }
+    
+    async fn delete_document(&self, collection: &str, id: &str) -> Result<(), VectorStoreError> {
+        let point = entry_point_id(id)?;
+        
+        self.with_retry(|| async {
+            let mut trace = OperationTrace::start("delete_document", collection);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
+            
+            use qdrant_client::qdrant::{DeletePoints, PointsIdsList, PointsSelector};
+            use qdrant_client::qdrant::points_selector::PointsSelectorOneOf;
+            
+            let delete_points = DeletePoints {
                 collection_name: collection.to_string(),
                 wait: Some(true),
-                points: vec![point],
+                points: Some(PointsSelector {
+                    points_selector_one_of: Some(PointsSelectorOneOf::Points(PointsIdsList {
+                        ids: vec![point.clone()],
+                    })),
+                }),
                 ..Default::default()
             };
             
-            // Insert point into collection
-            client.upsert_points(upsert_points).await
+            trace.wait(NETWORK_STAGE, client.delete_points(delete_points)).await
                 .map(|_| ())
-                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to insert document: {}", e)))
+                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to delete document: {}", e)))
         }).await
     }
     
-    async fn search(&self, collection: &str, query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+    async fn get_document(&self, collection: &str, id: &str) -> Result<Option<Document>, VectorStoreError> {
+        let point = entry_point_id(id)?;
+        
         self.with_retry(|| async {
-            let client = self.client_pool.get().await?;
+            let mut trace = OperationTrace::start("get_document", collection);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
             
-            use qdrant_client::qdrant::{SearchParams, WithPayloadSelector, WithVectorsSelector, SearchPoints};
+            use qdrant_client::qdrant::{GetPoints, WithPayloadSelector, WithVectorsSelector};
             
-            // Create search request
-            let search_request = SearchPoints {
+            let get_points = GetPoints {
                 collection_name: collection.to_string(),
-                vector: query.embedding.clone(),
-                limit: query.limit as u64,
+                ids: vec![point.clone()],
                 with_payload: Some(WithPayloadSelector::from(true)),
                 with_vectors: Some(WithVectorsSelector::from(true)),
-                params: Some(SearchParams {
-                    hnsw_ef: Some(128),
-                    exact: Some(false),
-                    ..Default::default()
-                }),
                 ..Default::default()
             };
             
-            // Execute search
-            let search_result = client.search_points(search_request).await
-                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to search: {}", e)))?;
+            let response = trace.wait(NETWORK_STAGE, client.get_points(get_points)).await
+                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to get document: {}", e)))?;
             
-            // Convert search results to our format
-            let results = search_result.result
+            Ok(trace.run(DESERIALIZE_STAGE, || response.result
                 .into_iter()
-                .filter_map(|point| {
-                    let id = match point.id.and_then(|id| id.point_id_options) {
-                        Some(qdrant_client::qdrant::point_id::PointIdOptions::Uuid(uuid)) => uuid,
-                        _ => return None,
+                .find_map(|point| point_to_document(point.id, point.payload, point.vectors))))
+        }).await
+    }
+    
+    async fn list_documents(&self, collection: &str, offset: Option<String>, limit: usize) -> Result<DocumentPage, VectorStoreError> {
+        let offset = offset.as_deref().map(entry_point_id).transpose()?;
+        
+        self.with_retry(|| async {
+            let mut trace = OperationTrace::start("list_documents", collection);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
+            
+            use qdrant_client::qdrant::{ScrollPoints, WithPayloadSelector, WithVectorsSelector};
+            
+            let scroll_points = ScrollPoints {
+                collection_name: collection.to_string(),
+                offset: offset.clone(),
+                limit: Some(limit as u32),
+                with_payload: Some(WithPayloadSelector::from(true)),
+                with_vectors: Some(WithVectorsSelector::from(true)),
+                ..Default::default()
+            };
+            
+            let response = trace.wait(NETWORK_STAGE, client.scroll(scroll_points)).await
+                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to list documents: {}", e)))?;
+            
+            let documents = trace.run(DESERIALIZE_STAGE, || response.result
+                .into_iter()
+                .filter_map(|point| point_to_document(point.id, point.payload, point.vectors))
+                .collect());
+            
+            Ok(DocumentPage {
+                documents,
+                next_offset: response.next_page_offset.and_then(point_id_to_string),
+            })
+        }).await
+    }
+    
+    async fn patch_metadata(&self, collection: &str, filter: &MetadataFilter, patch: &MetadataPatch) -> Result<PatchOutcome, VectorStoreError> {
+        let server_filter = qdrant_filter(filter)?;
+        
+        self.with_retry(|| async {
+            let mut trace = OperationTrace::start("patch_metadata", collection);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
+            
+            use qdrant_client::qdrant::{PointsIdsList, PointsSelector, ScrollPoints, SetPayloadPoints};
+            use qdrant_client::qdrant::points_selector::PointsSelectorOneOf;
+            use std::collections::HashMap;
+            
+            let mut outcome = PatchOutcome::default();
+            let mut offset = None;
+            loop {
+                // Only the metadata is read; content and vectors never leave the server
+                let scroll_points = ScrollPoints {
+                    collection_name: collection.to_string(),
+                    filter: Some(server_filter.clone()),
+                    offset,
+                    limit: Some(filter::FILTER_PAGE_SIZE as u32),
+                    with_payload: Some(metadata_only()),
+                    with_vectors: Some(false.into()),
+                    ..Default::default()
+                };
+                let response = trace.wait(NETWORK_STAGE, client.scroll(scroll_points)).await
+                    .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to list documents: {}", e)))?;
+                
+                for point in response.result {
+                    let mut document = match point_to_document(point.id, point.payload, None) {
+                        Some(document) => document,
+                        None => continue,
                     };
+                    if !filter.matches(&document) {
+                        continue;
+                    }
+                    outcome.matched += 1;
+                    if !patch.apply(&mut document.metadata) {
+                        continue;
+                    }
                     
-                    let content = point.payload.get("content").and_then(|value| {
-                        if let Some(qdrant_client::qdrant::value::Kind::StringValue(content)) = &value.kind {
-                            Some(content.clone())
-                        } else {
-                            None
-                        }
-                    }).unwrap_or_default();
-                    
-                    let embedding = point.vectors.and_then(|v| {
-                        if let Some(qdrant_client::qdrant::vectors_output::VectorsOptions::Vector(vector)) = v.vectors_options {
-                            Some(vector.data)
-                        } else {
-                            None
-                        }
-                    }).unwrap_or_default();
-                    
-                    Some(SearchResult {
-                        document: Document {
-                            id,
-                            content,
-                            embedding,
-                        },
-                        score: point.score,
-                    })
-                })
-                .collect();
+                    let set_payload = SetPayloadPoints {
+                        collection_name: collection.to_string(),
+                        wait: Some(true),
+                        payload: HashMap::from([("metadata".to_string(), metadata_to_value(&document.metadata))]),
+                        points_selector: Some(PointsSelector {
+                            points_selector_one_of: Some(PointsSelectorOneOf::Points(PointsIdsList {
+                                ids: vec![entry_point_id(&document.id)?],
+                            })),
+                        }),
+                        ..Default::default()
+                    };
+                    trace.wait(NETWORK_STAGE, client.set_payload(set_payload)).await
+                        .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to patch metadata: {}", e)))?;
+                    outcome.updated += 1;
+                }
+                
+                offset = match response.next_page_offset {
+                    Some(next) => Some(next),
+                    None => break,
+                };
+            }
             
-            Ok(results)
+            Ok(outcome)
         }).await
     }
+    
+    async fn count(&self, collection: &str, filter: &MetadataFilter) -> Result<usize, VectorStoreError> {
+        let server_filter = qdrant_filter(filter)?;
+        
+        self.with_retry(|| async {
+            let mut trace = OperationTrace::start("count", collection);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
+            
+            use qdrant_client::qdrant::{CountPoints, ScrollPoints};
+            
+            // Tags are stored joined, so Qdrant can only count filters without them
+            if filter.tags.is_empty() {
+                let count_points = CountPoints {
+                    collection_name: collection.to_string(),
+                    filter: Some(server_filter.clone()),
+                    exact: Some(true),
+                    ..Default::default()
+                };
+                let response = trace.wait(NETWORK_STAGE, client.count(count_points)).await
+                    .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to count documents: {}", e)))?;
+                return Ok(response.result.map(|result| result.count as usize).unwrap_or(0));
+            }
+            
+            let mut count = 0;
+            let mut offset = None;
+            loop {
+                let scroll_points = ScrollPoints {
+                    collection_name: collection.to_string(),
+                    filter: Some(server_filter.clone()),
+                    offset,
+                    limit: Some(filter::FILTER_PAGE_SIZE as u32),
+                    with_payload: Some(metadata_only()),
+                    with_vectors: Some(false.into()),
+                    ..Default::default()
+                };
+                let response = trace.wait(NETWORK_STAGE, client.scroll(scroll_points)).await
+                    .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to count documents: {}", e)))?;
+                
+                count += response.result.into_iter()
+                    .filter_map(|point| point_to_document(point.id, point.payload, None))
+                    .filter(|document| filter.matches(document))
+                    .count();
+                offset = match response.next_page_offset {
+                    Some(next) => Some(next),
+                    None => break,
+                };
+            }
+            Ok(count)
+        }).await
+    }
+    
+    async fn exists(&self, collection: &str, id: &str) -> Result<bool, VectorStoreError> {
+        let point = entry_point_id(id)?;
+        
+        self.with_retry(|| async {
+            let mut trace = OperationTrace::start("exists", collection);
+            let client = trace.wait(POOL_STAGE, self.client()).await?;
+            
+            use qdrant_client::qdrant::GetPoints;
+            
+            let get_points = GetPoints {
+                collection_name: collection.to_string(),
+                ids: vec![point.clone()],
+                with_payload: Some(false.into()),
+                with_vectors: Some(false.into()),
+                ..Default::default()
+            };
+            
+            let response = trace.wait(NETWORK_STAGE, client.get_points(get_points)).await
+                .map_err(|e| VectorStoreError::OperationFailed(format!("Failed to get document: {}", e)))?;
+            Ok(!response.result.is_empty())
+        }).await
+    }
+    
+    fn pool_metrics(&self) -> Option<PoolMetrics> {
+        Some(self.pool_stats.snapshot(self.client_pool.status()))
+    }
+    
+    fn backend_name(&self) -> &'static str {
+        "qdrant"
+    }
+}
+
+/// The server-side part of a filter; tags are stored as one JSON array string and must be matched locally
+fn qdrant_filter(filter: &MetadataFilter) -> Result<qdrant_client::qdrant::Filter, VectorStoreError> {
+    use qdrant_client::qdrant::{Condition, Filter};
+    
+    let mut conditions: Vec<Condition> = filter.metadata.iter()
+        .map(|(key, value)| Condition::matches(format!("metadata.{}", key), value.clone()))
+        .collect();
+    if !filter.ids.is_empty() {
+        let points = filter.ids.iter()
+            .map(|id| entry_point_id(id))
+            .collect::<Result<Vec<_>, VectorStoreError>>()?;
+        conditions.push(Condition::has_id(points));
+    }
+    Ok(Filter::must(conditions))
+}
+
+/// Payload fields indexing a document's terms for keyword search
+fn keyword_payload(document: &Document) -> Vec<(String, qdrant_client::qdrant::Value)> {
+    use qdrant_client::qdrant::value::Kind;
+    use qdrant_client::qdrant::{ListValue, Struct, Value};
+    
+    let frequencies = keywords::term_frequencies(&keywords::indexed_text(document));
+    let length: u32 = frequencies.values().sum();
+    let terms = frequencies.keys()
+        .map(|term| Value { kind: Some(Kind::StringValue(term.clone())) })
+        .collect();
+    let counts = frequencies.into_iter()
+        .map(|(term, count)| (term, Value { kind: Some(Kind::IntegerValue(count as i64)) }))
+        .collect();
+    vec![
+        (keywords::TERMS_KEY.to_string(), Value { kind: Some(Kind::ListValue(ListValue { values: terms })) }),
+        (keywords::TERM_FREQS_KEY.to_string(), Value { kind: Some(Kind::StructValue(Struct { fields: counts })) }),
+        (keywords::TERM_COUNT_KEY.to_string(), Value { kind: Some(Kind::IntegerValue(length as i64)) }),
+    ]
+}
+
+/// The term counts and term total a point's payload indexes; `None` for
+/// points stored before keyword indexing, which need reindexing to be found
+fn payload_term_frequencies(payload: &std::collections::HashMap<String, qdrant_client::qdrant::Value>) -> Option<(std::collections::HashMap<String, u32>, usize)> {
+    use qdrant_client::qdrant::value::Kind;
+    
+    let Some(Kind::StructValue(counts)) = &payload.get(keywords::TERM_FREQS_KEY)?.kind else {
+        return None;
+    };
+    let Some(Kind::IntegerValue(length)) = payload.get(keywords::TERM_COUNT_KEY)?.kind else {
+        return None;
+    };
+    let frequencies = counts.fields.iter()
+        .filter_map(|(term, value)| match value.kind {
+            Some(Kind::IntegerValue(count)) => Some((term.clone(), count as u32)),
+            _ => None,
+        })
+        .collect();
+    Some((frequencies, length as usize))
+}
+
+/// Payload selector reading only the metadata and entry id of a point
+fn metadata_only() -> qdrant_client::qdrant::WithPayloadSelector {
+    use qdrant_client::qdrant::{PayloadIncludeSelector, WithPayloadSelector};
+    use qdrant_client::qdrant::with_payload_selector::SelectorOptions;
+    
+    WithPayloadSelector {
+        selector_options: Some(SelectorOptions::Include(PayloadIncludeSelector {
+            fields: vec!["metadata".to_string(), id::ENTRY_ID_KEY.to_string()],
+        })),
+    }
+}
+
+/// Build a Qdrant point id from one of our entry ids
+fn point_id(id: &EntryId) -> qdrant_client::qdrant::PointId {
+    qdrant_client::qdrant::PointId {
+        point_id_options: Some(qdrant_client::qdrant::point_id::PointIdOptions::Uuid(id.point_uuid().to_string())),
+    }
+}
+
+/// Validate an entry id and build its Qdrant point id
+fn entry_point_id(id: &str) -> Result<qdrant_client::qdrant::PointId, VectorStoreError> {
+    Ok(point_id(&EntryId::parse(id)?))
+}
+
+fn point_id_to_string(id: qdrant_client::qdrant::PointId) -> Option<String> {
+    match id.point_id_options {
+        Some(qdrant_client::qdrant::point_id::PointIdOptions::Uuid(uuid)) => Some(uuid),
+        Some(qdrant_client::qdrant::point_id::PointIdOptions::Num(num)) => Some(num.to_string()),
+        None => None,
+    }
+}
+
+/// Convert a point returned by Qdrant into a document
+fn point_to_document(
+    id: Option<qdrant_client::qdrant::PointId>,
+    payload: std::collections::HashMap<String, qdrant_client::qdrant::Value>,
+    vectors: Option<qdrant_client::qdrant::VectorsOutput>,
+) -> Option<Document> {
+    let id = match id.and_then(|id| id.point_id_options) {
+        Some(qdrant_client::qdrant::point_id::PointIdOptions::Uuid(uuid)) => uuid,
+        _ => return None,
+    };
+    
+    let payload_string = |key: &str| payload.get(key).and_then(|value| {
+        if let Some(qdrant_client::qdrant::value::Kind::StringValue(value)) = &value.kind {
+            Some(value.clone())
+        } else {
+            None
+        }
+    });
+    
+    // Entries with external ids are keyed by a hash; the original id is in the payload
+    let id = payload_string(id::ENTRY_ID_KEY).unwrap_or(id);
+    
+    let stored = payload_string("content").unwrap_or_default();
+    let encoding = payload_string(compression::CONTENT_ENCODING_KEY);
+    let content = match compression::decode_content(&stored, encoding.as_deref()) {
+        Ok(content) => content,
+        Err(e) => {
+            error!("Skipping document {}: {}", id, e);
+            return None;
+        }
+    };
+    
+    let metadata = payload.get("metadata")
+        .map(metadata_from_value)
+        .unwrap_or_default();
+    
+    let embedding = vectors.and_then(|v| {
+        if let Some(qdrant_client::qdrant::vectors_output::VectorsOptions::Vector(vector)) = v.vectors_options {
+            Some(vector.data)
+        } else {
+            None
+        }
+    }).unwrap_or_default();
+    
+    Some(Document {
+        id,
+        content,
+        embedding,
+        metadata,
+    })
+}
+
+/// Store document metadata as a nested payload object
+fn metadata_to_value(metadata: &crate::text_processing::Metadata) -> qdrant_client::qdrant::Value {
+    let fields = metadata.iter()
+        .map(|(key, value)| {
+            (key.clone(), qdrant_client::qdrant::Value {
+                kind: Some(qdrant_client::qdrant::value::Kind::StringValue(value.clone())),
+            })
+        })
+        .collect();
+    
+    qdrant_client::qdrant::Value {
+        kind: Some(qdrant_client::qdrant::value::Kind::StructValue(qdrant_client::qdrant::Struct { fields })),
+    }
+}
+
+/// Read document metadata back from a payload object, skipping non-string fields
+fn metadata_from_value(value: &qdrant_client::qdrant::Value) -> crate::text_processing::Metadata {
+    match &value.kind {
+        Some(qdrant_client::qdrant::value::Kind::StructValue(object)) => object.fields.iter()
+            .filter_map(|(key, value)| match &value.kind {
+                Some(qdrant_client::qdrant::value::Kind::StringValue(value)) => Some((key.clone(), value.clone())),
+                _ => None,
+            })
+            .collect(),
+        _ => Default::default(),
+    }
 }
 
 // Re-export the QdrantConnector for backward compatibility
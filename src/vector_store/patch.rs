@@ -0,0 +1,196 @@
+//! Metadata patches applied to every entry matching a filter
+
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+use super::filter::{scalar_string, MetadataFilter, FILTER_PAGE_SIZE};
+use super::{decode_tags, encode_tags, VectorStore, VectorStoreError, TAGS_KEY};
+use crate::text_processing::Metadata;
+
+/// A change to entry metadata: a JSON merge patch plus tag renames
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataPatch {
+    set: BTreeMap<String, String>,
+    remove: Vec<String>,
+    rename_tags: HashMap<String, String>,
+}
+
+impl MetadataPatch {
+    /// Read an RFC 7396 merge patch over the flat metadata map.
+    ///
+    /// `null` removes a field and scalars are stored as strings; an array
+    /// under `tags` replaces the entry's tags.
+    pub fn from_merge_patch(patch: &Value) -> Result<Self, String> {
+        let object = patch.as_object().ok_or("patch must be an object")?;
+        let mut result = Self::default();
+        for (key, value) in object {
+            match value {
+                Value::Null => result.remove.push(key.clone()),
+                Value::Array(tags) if key == TAGS_KEY => {
+                    let tags = tags.iter()
+                        .map(|tag| tag.as_str().ok_or("patch.tags must contain strings"))
+                        .collect::<Result<Vec<&str>, _>>()?;
+                    if tags.is_empty() {
+                        result.remove.push(key.clone());
+                    } else {
+                        result.set.insert(key.clone(), encode_tags(&tags));
+                    }
+                },
+                value => match scalar_string(value) {
+                    Some(value) => {
+                        result.set.insert(key.clone(), value);
+                    },
+                    None => return Err(format!("patch.{} must be a string, number, boolean or null", key)),
+                },
+            }
+        }
+        Ok(result)
+    }
+    
+    /// Also replace the tag `from` with `to` wherever it occurs
+    pub fn with_tag_rename(mut self, from: &str, to: &str) -> Self {
+        self.rename_tags.insert(from.to_string(), to.to_string());
+        self
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.remove.is_empty() && self.rename_tags.is_empty()
+    }
+    
+    /// Apply the patch, returning whether anything changed
+    pub fn apply(&self, metadata: &mut Metadata) -> bool {
+        let before = metadata.clone();
+        for key in &self.remove {
+            metadata.remove(key);
+        }
+        for (key, value) in &self.set {
+            metadata.insert(key.clone(), value.clone());
+        }
+        
+        if !self.rename_tags.is_empty() {
+            if let Some(tags) = metadata.get(TAGS_KEY) {
+                let mut renamed: Vec<String> = Vec::new();
+                for tag in decode_tags(tags) {
+                    let tag = self.rename_tags.get(&tag).cloned().unwrap_or(tag);
+                    if !renamed.contains(&tag) {
+                        renamed.push(tag);
+                    }
+                }
+                metadata.insert(TAGS_KEY.to_string(), encode_tags(&renamed));
+            }
+        }
+        
+        *metadata != before
+    }
+}
+
+/// How many entries a patch matched and how many it changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PatchOutcome {
+    pub matched: usize,
+    pub updated: usize,
+}
+
+/// Patch by reading every page and re-inserting changed documents.
+///
+/// The fallback for stores without a payload update API; content and
+/// embeddings are written back unchanged. With `dry_run` nothing is written.
+pub async fn rewrite_metadata<S: VectorStore + ?Sized>(
+    store: &S,
+    collection: &str,
+    filter: &MetadataFilter,
+    patch: &MetadataPatch,
+    dry_run: bool,
+) -> Result<PatchOutcome, VectorStoreError> {
+    let mut outcome = PatchOutcome::default();
+    let mut changed = Vec::new();
+    let mut offset = None;
+    loop {
+        let page = store.list_documents(collection, offset, FILTER_PAGE_SIZE).await?;
+        for mut document in page.documents {
+            if !filter.matches(&document) {
+                continue;
+            }
+            outcome.matched += 1;
+            if patch.apply(&mut document.metadata) {
+                outcome.updated += 1;
+                changed.push(document);
+            }
+        }
+        offset = match page.next_offset {
+            Some(next) => Some(next),
+            None => break,
+        };
+    }
+    
+    if !dry_run {
+        for document in changed {
+            store.insert_document(collection, document).await?;
+        }
+    }
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::{Document, InMemoryVectorStore};
+    use serde_json::json;
+    
+    fn document(id: &str, tags: &[&str], owner: &str) -> Document {
+        let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+        let mut document = Document::with_placeholder_embedding(format!("content of {}", id), 2).with_tags(&tags);
+        document.id = id.to_string();
+        document.embedding = vec![1.0, 0.0];
+        document.metadata.insert("owner".to_string(), owner.to_string());
+        document
+    }
+    
+    #[test]
+    fn test_merge_patch_and_tag_rename() {
+        let patch = MetadataPatch::from_merge_patch(&json!({"owner": "platform", "team": null, "priority": 2}))
+            .unwrap()
+            .with_tag_rename("ops", "operations");
+        
+        let mut metadata = document("a", &["ops", "db", "operations"], "sre").metadata;
+        metadata.insert("team".to_string(), "blue".to_string());
+        assert!(patch.apply(&mut metadata));
+        assert_eq!(metadata["owner"], "platform");
+        assert_eq!(metadata["priority"], "2");
+        assert_eq!(decode_tags(&metadata[TAGS_KEY]), vec!["operations", "db"]);
+        assert!(!metadata.contains_key("team"));
+        
+        // Applying the same patch again changes nothing
+        assert!(!patch.apply(&mut metadata));
+        
+        assert!(MetadataPatch::from_merge_patch(&json!({"owner": {"name": "x"}})).is_err());
+        let tags = MetadataPatch::from_merge_patch(&json!({"tags": []})).unwrap();
+        let mut metadata = document("b", &["ops"], "sre").metadata;
+        tags.apply(&mut metadata);
+        assert!(!metadata.contains_key(TAGS_KEY));
+    }
+    
+    #[tokio::test]
+    async fn test_rewrite_metadata() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        store.insert_document("docs", document("a", &["ops"], "sre")).await.unwrap();
+        store.insert_document("docs", document("b", &["ops"], "platform")).await.unwrap();
+        store.insert_document("docs", document("c", &["web"], "sre")).await.unwrap();
+        
+        let filter = MetadataFilter::from_json(&json!({"tags": ["ops"]})).unwrap();
+        let patch = MetadataPatch::from_merge_patch(&json!({"owner": "platform"})).unwrap();
+        
+        let planned = rewrite_metadata(&store, "docs", &filter, &patch, true).await.unwrap();
+        assert_eq!(planned, PatchOutcome { matched: 2, updated: 1 });
+        assert_eq!(store.get_document("docs", "a").await.unwrap().unwrap().metadata["owner"], "sre");
+        
+        let outcome = rewrite_metadata(&store, "docs", &filter, &patch, false).await.unwrap();
+        assert_eq!(outcome, planned);
+        let patched = store.get_document("docs", "a").await.unwrap().unwrap();
+        assert_eq!(patched.metadata["owner"], "platform");
+        assert_eq!(patched.content, "content of a");
+        assert_eq!(patched.embedding, vec![1.0, 0.0]);
+        assert_eq!(store.get_document("docs", "c").await.unwrap().unwrap().metadata["owner"], "sre");
+    }
+}
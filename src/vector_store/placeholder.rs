@@ -0,0 +1,105 @@
+//! Placeholder embeddings.
+//!
+//! Earlier versions stored `vec![0.0; 384]` for every entry when no
+//! embedding provider was configured. A zero vector has no direction, so
+//! such entries never match a query and searches quietly degrade. New
+//! embeddings are checked before they are stored, and existing ones can be
+//! found and regenerated from their content.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Document, VectorStore, VectorStoreError};
+use super::filter::FILTER_PAGE_SIZE;
+
+/// Why an embedding cannot be searched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingDefect {
+    /// Every component is zero, or there are none
+    Zero,
+    /// Some component is NaN or infinite
+    NonFinite,
+}
+
+impl EmbeddingDefect {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmbeddingDefect::Zero => "zero",
+            EmbeddingDefect::NonFinite => "non_finite",
+        }
+    }
+    
+    pub fn description(&self) -> &'static str {
+        match self {
+            EmbeddingDefect::Zero => "embedding is all zeros, a placeholder that matches no query",
+            EmbeddingDefect::NonFinite => "embedding contains NaN or infinite values",
+        }
+    }
+}
+
+/// What is wrong with `embedding`, if anything
+pub fn embedding_defect(embedding: &[f32]) -> Option<EmbeddingDefect> {
+    if embedding.iter().any(|x| !x.is_finite()) {
+        Some(EmbeddingDefect::NonFinite)
+    } else if embedding.iter().all(|x| *x == 0.0) {
+        Some(EmbeddingDefect::Zero)
+    } else {
+        None
+    }
+}
+
+/// Page through up to `limit` documents of `collection`, returning how many
+/// were read and those whose embedding is a placeholder
+pub async fn find_placeholders(
+    store: &dyn VectorStore,
+    collection: &str,
+    limit: usize,
+) -> Result<(usize, Vec<(Document, EmbeddingDefect)>), VectorStoreError> {
+    let mut scanned = 0;
+    let mut found = Vec::new();
+    let mut offset = None;
+    while scanned < limit {
+        let page = store.list_documents(collection, offset, FILTER_PAGE_SIZE.min(limit - scanned)).await?;
+        scanned += page.documents.len();
+        found.extend(page.documents.into_iter()
+            .filter_map(|document| embedding_defect(&document.embedding).map(|defect| (document, defect))));
+        
+        match page.next_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+    Ok((scanned, found))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    #[test]
+    fn test_embedding_defect() {
+        assert_eq!(embedding_defect(&[0.0, 0.0, 0.0]), Some(EmbeddingDefect::Zero));
+        assert_eq!(embedding_defect(&[]), Some(EmbeddingDefect::Zero));
+        assert_eq!(embedding_defect(&[0.1, f32::NAN]), Some(EmbeddingDefect::NonFinite));
+        assert_eq!(embedding_defect(&[0.0, f32::INFINITY]), Some(EmbeddingDefect::NonFinite));
+        assert_eq!(embedding_defect(&[0.0, -0.2]), None);
+    }
+    
+    #[tokio::test]
+    async fn test_find_placeholders() {
+        let store = InMemoryVectorStore::new();
+        store.create_collection("docs", 2).await.unwrap();
+        let placeholder = Document::with_placeholder_embedding("never embedded".to_string(), 2);
+        let mut embedded = Document::with_placeholder_embedding("embedded".to_string(), 2);
+        embedded.embedding = vec![0.6, 0.8];
+        store.insert_document("docs", placeholder.clone()).await.unwrap();
+        store.insert_document("docs", embedded).await.unwrap();
+        
+        let (scanned, found) = find_placeholders(&store, "docs", 100).await.unwrap();
+        assert_eq!(scanned, 2);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.id, placeholder.id);
+        assert_eq!(found[0].1, EmbeddingDefect::Zero);
+    }
+}
@@ -0,0 +1,113 @@
+//! Connection pool metrics
+//!
+//! Checkout waits and timeouts are counted so an undersized pool can be told
+//! apart from a slow backend.
+
+use deadpool::managed::{PoolError, Status, TimeoutType};
+use serde::Serialize;
+use std::ops::Add;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// A snapshot of a connection pool's state and counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PoolMetrics {
+    /// Most connections the pool will open
+    pub max_size: usize,
+    /// Connections currently open
+    pub size: usize,
+    /// Open connections not checked out
+    pub available: usize,
+    /// Requests waiting for a connection
+    pub waiting: usize,
+    /// Connections handed out since startup
+    pub checkouts: u64,
+    /// Total time spent waiting for connections, in milliseconds
+    pub wait_time_ms: u64,
+    /// Checkouts that gave up waiting for a free connection
+    pub wait_timeouts: u64,
+    /// Connections that could not be opened in time
+    pub create_timeouts: u64,
+}
+
+/// Totals across several pools, e.g. a primary and its replicas
+impl Add for PoolMetrics {
+    type Output = Self;
+    
+    fn add(self, other: Self) -> Self {
+        Self {
+            max_size: self.max_size + other.max_size,
+            size: self.size + other.size,
+            available: self.available + other.available,
+            waiting: self.waiting + other.waiting,
+            checkouts: self.checkouts + other.checkouts,
+            wait_time_ms: self.wait_time_ms + other.wait_time_ms,
+            wait_timeouts: self.wait_timeouts + other.wait_timeouts,
+            create_timeouts: self.create_timeouts + other.create_timeouts,
+        }
+    }
+}
+
+/// Counters updated on every checkout
+#[derive(Debug, Default)]
+pub(crate) struct PoolStats {
+    checkouts: AtomicU64,
+    wait_micros: AtomicU64,
+    wait_timeouts: AtomicU64,
+    create_timeouts: AtomicU64,
+}
+
+impl PoolStats {
+    /// Record one checkout attempt and how long it took
+    pub(crate) fn record<E>(&self, waited: Duration, error: Option<&PoolError<E>>) {
+        self.wait_micros.fetch_add(waited.as_micros() as u64, Ordering::Relaxed);
+        match error {
+            None => {
+                self.checkouts.fetch_add(1, Ordering::Relaxed);
+            },
+            Some(PoolError::Timeout(TimeoutType::Wait)) => {
+                self.wait_timeouts.fetch_add(1, Ordering::Relaxed);
+            },
+            Some(PoolError::Timeout(TimeoutType::Create)) => {
+                self.create_timeouts.fetch_add(1, Ordering::Relaxed);
+            },
+            Some(_) => {},
+        }
+    }
+    
+    /// Combine the counters with the pool's current status
+    pub(crate) fn snapshot(&self, status: Status) -> PoolMetrics {
+        PoolMetrics {
+            max_size: status.max_size,
+            size: status.size,
+            // A negative count of available connections is the number of waiters
+            available: status.available.max(0) as usize,
+            waiting: (-status.available).max(0) as usize,
+            checkouts: self.checkouts.load(Ordering::Relaxed),
+            wait_time_ms: self.wait_micros.load(Ordering::Relaxed) / 1000,
+            wait_timeouts: self.wait_timeouts.load(Ordering::Relaxed),
+            create_timeouts: self.create_timeouts.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_stats_snapshot() {
+        let stats = PoolStats::default();
+        stats.record::<()>(Duration::from_millis(3), None);
+        stats.record::<()>(Duration::from_millis(5), Some(&PoolError::Timeout(TimeoutType::Wait)));
+        stats.record::<()>(Duration::from_millis(2), Some(&PoolError::Timeout(TimeoutType::Create)));
+        
+        let metrics = stats.snapshot(Status { max_size: 4, size: 4, available: -2 });
+        assert_eq!(metrics.checkouts, 1);
+        assert_eq!(metrics.wait_time_ms, 10);
+        assert_eq!(metrics.wait_timeouts, 1);
+        assert_eq!(metrics.create_timeouts, 1);
+        assert_eq!(metrics.available, 0);
+        assert_eq!(metrics.waiting, 2);
+    }
+}
@@ -1,12 +1,62 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use crate::text_processing::EmbeddingProvider;
+use crate::text_processing::{EmbeddingProvider, Metadata};
+
+/// Metadata key holding an entry's title
+pub const TITLE_KEY: &str = "title";
+
+/// Metadata key set to "true" when the title was generated from the content
+pub const TITLE_GENERATED_KEY: &str = "title_generated";
+
+/// Metadata key holding the ISO 639-3 code of an entry's detected language
+pub const LANGUAGE_KEY: &str = "language";
+
+/// Metadata key set to "true" on entries found to contain PII
+pub const SENSITIVE_KEY: &str = "sensitive";
+
+/// Metadata key listing the comma-separated kinds of PII found in an entry
+pub const PII_KINDS_KEY: &str = "pii_kinds";
+
+/// Metadata key set to "true" on entries stored through safe mode
+pub const UNTRUSTED_KEY: &str = "untrusted";
+
+/// Metadata key listing the comma-separated prompt-injection signals found in an entry
+pub const INJECTION_SIGNALS_KEY: &str = "injection_signals";
+
+/// Metadata key holding where an entry came from, such as a file path or URL
+pub const SOURCE_KEY: &str = "source";
+
+/// Metadata key holding an entry's tags as a JSON array of strings
+pub const TAGS_KEY: &str = "tags";
+
+/// Metadata key holding when an entry was added (RFC 3339)
+pub const CREATED_AT_KEY: &str = "created_at";
+
+/// Metadata key naming the provider of a fallback chain that embedded an entry
+pub const EMBEDDING_PROVIDER_KEY: &str = "embedding_provider";
+
+/// Metadata key linking an entry to the conversation it was ingested from
+pub const CONVERSATION_ID_KEY: &str = "conversation_id";
+
+/// Metadata key listing the comma-separated speakers of a conversation chunk
+pub const SPEAKER_KEY: &str = "speaker";
+
+/// Metadata key holding the index of the first turn in a conversation chunk
+pub const TURN_INDEX_KEY: &str = "turn_index";
+
+/// Metadata key holding the index of the last turn in a conversation chunk
+pub const TURN_END_KEY: &str = "turn_end";
+
+/// Metadata key holding when the first turn of a conversation chunk was sent
+pub const TIMESTAMP_KEY: &str = "timestamp";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Document {
     pub id: String,
     pub content: String,
     pub embedding: Vec<f32>,
+    #[serde(default)]
+    pub metadata: Metadata,
 }
 
 impl Document {
@@ -17,6 +67,7 @@ impl Document {
             id: Uuid::new_v4().to_string(),
             content,
             embedding,
+            metadata: Metadata::new(),
         })
     }
     
@@ -27,6 +78,7 @@ impl Document {
             id,
             content,
             embedding,
+            metadata: Metadata::new(),
         })
     }
     
@@ -35,8 +87,54 @@ impl Document {
             id: Uuid::new_v4().to_string(),
             content,
             embedding: vec![0.0; embedding_dim],
+            metadata: Metadata::new(),
         }
     }
+    
+    /// Set the entry title
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.metadata.insert(TITLE_KEY.to_string(), title.to_string());
+        self
+    }
+    
+    /// Set the entry tags, replacing any existing ones
+    pub fn with_tags(mut self, tags: &[String]) -> Self {
+        if tags.is_empty() {
+            self.metadata.remove(TAGS_KEY);
+        } else {
+            self.metadata.insert(TAGS_KEY.to_string(), encode_tags(tags));
+        }
+        self
+    }
+    
+    /// The entry title, if one was stored
+    pub fn title(&self) -> Option<&str> {
+        self.metadata.get(TITLE_KEY).map(|title| title.as_str())
+    }
+    
+    /// The entry tags in the order they were stored
+    pub fn tags(&self) -> Vec<String> {
+        self.metadata.get(TAGS_KEY).map(|tags| decode_tags(tags)).unwrap_or_default()
+    }
+}
+
+/// Tags as stored under [`TAGS_KEY`], a JSON array so a tag may hold a comma
+pub fn encode_tags<T: AsRef<str>>(tags: &[T]) -> String {
+    serde_json::to_string(&tags.iter().map(|tag| tag.as_ref()).collect::<Vec<&str>>())
+        .expect("a list of strings serializes")
+}
+
+/// Tags stored under [`TAGS_KEY`]; entries written before tags were stored as
+/// a JSON array hold them comma-separated
+pub fn decode_tags(stored: &str) -> Vec<String> {
+    if let Ok(tags) = serde_json::from_str::<Vec<String>>(stored) {
+        return tags;
+    }
+    stored.split(',')
+        .map(|tag| tag.trim())
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| tag.to_string())
+        .collect()
 }
 
 #[derive(Debug, Clone)]
@@ -63,10 +161,64 @@ impl SearchQuery {
     }
 }
 
+/// How a backend compares vectors, which decides what its raw scores mean
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceMetric {
+    /// Cosine similarity, from -1 to 1
+    #[default]
+    Cosine,
+    /// Dot product of unit vectors, which equals their cosine similarity
+    Dot,
+}
+
+impl DistanceMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DistanceMetric::Cosine => "cosine",
+            DistanceMetric::Dot => "dot",
+        }
+    }
+    
+    /// The metric with this name, in any case; `None` for distances p-mo does not search with
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "cosine" => Some(DistanceMetric::Cosine),
+            "dot" => Some(DistanceMetric::Dot),
+            _ => None,
+        }
+    }
+    
+    /// Map a raw score under this metric onto 0-1, where 1 is an identical vector
+    pub fn relevance(&self, score: f32) -> f32 {
+        match self {
+            DistanceMetric::Cosine | DistanceMetric::Dot => ((score + 1.0) / 2.0).clamp(0.0, 1.0),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SearchResult {
     pub document: Document,
+    /// The score as the backend reported it
     pub score: f32,
+    /// The metric `score` was computed with
+    pub metric: DistanceMetric,
+}
+
+impl SearchResult {
+    /// `score` normalized to 0-1 for the metric, comparable across backends
+    pub fn relevance(&self) -> f32 {
+        self.metric.relevance(self.score)
+    }
+}
+
+/// A page of documents returned when browsing a collection
+#[derive(Debug, Clone, Default)]
+pub struct DocumentPage {
+    pub documents: Vec<Document>,
+    /// Pass this as the offset to fetch the next page; `None` on the last page
+    pub next_offset: Option<String>,
 }
 
 // Pure functions for vector operations
@@ -95,10 +247,47 @@ pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     }
 }
 
+/// Tolerance within which a vector counts as unit length
+pub const UNIT_LENGTH_TOLERANCE: f32 = 1e-3;
+
+/// Scale a vector to unit L2 norm in place; zero vectors are left unchanged
+pub fn l2_normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for x in vector.iter_mut() {
+            *x /= norm;
+        }
+    }
+}
+
+/// Whether a vector has unit L2 norm
+pub fn is_unit_length(vector: &[f32]) -> bool {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    (norm - 1.0).abs() <= UNIT_LENGTH_TOLERANCE
+}
+
+/// Dot product; equals cosine similarity for unit vectors
+pub fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_tags_round_trip() {
+        let tags = vec!["ops".to_string(), "db, replicas".to_string()];
+        let document = Document::with_placeholder_embedding("content".to_string(), 2).with_tags(&tags);
+        assert_eq!(document.tags(), tags);
+        
+        // Entries stored before tags were a JSON array still read
+        assert_eq!(decode_tags("ops, db"), vec!["ops", "db"]);
+    }
+
     #[test]
     fn test_cosine_similarity() {
         let a = vec![1.0, 0.0, 0.0];
@@ -113,4 +302,29 @@ mod tests {
         let f = vec![1.0, 0.0, 1.0];
         assert!((cosine_similarity(&e, &f) - 0.5).abs() < 0.0001);
     }
+    
+    #[test]
+    fn test_l2_normalize() {
+        let mut v = vec![3.0, 4.0];
+        assert!(!is_unit_length(&v));
+        l2_normalize(&mut v);
+        assert!(is_unit_length(&v));
+        assert!((dot_product(&v, &v) - 1.0).abs() < 1e-6);
+        
+        let mut zero = vec![0.0, 0.0];
+        l2_normalize(&mut zero);
+        assert_eq!(zero, vec![0.0, 0.0]);
+    }
+    
+    #[test]
+    fn test_relevance_is_normalized_per_metric() {
+        for metric in [DistanceMetric::Cosine, DistanceMetric::Dot] {
+            assert_eq!(metric.relevance(1.0), 1.0);
+            assert_eq!(metric.relevance(0.0), 0.5);
+            assert_eq!(metric.relevance(-1.0), 0.0);
+            // Rounding can push a raw score just past the ends of its range
+            assert_eq!(metric.relevance(1.0001), 1.0);
+        }
+        assert_eq!(serde_json::to_value(DistanceMetric::Dot).unwrap(), "dot");
+    }
 }
@@ -0,0 +1,460 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+use super::schema::{EntrySchema, FieldError};
+use super::{is_unit_length, DistanceMetric, VectorStoreError};
+use crate::text_processing::{ChunkingStrategy, Metadata};
+
+/// File in the data directory the registry is saved to between runs
+pub const REGISTRY_FILE: &str = "collections.json";
+
+/// What the server knows about a collection independently of the backend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionInfo {
+    /// The collection name
+    pub name: String,
+    /// The dimensionality of the vectors stored in the collection
+    pub vector_size: usize,
+    /// Rules entries must satisfy, if the collection declares any
+    #[serde(default)]
+    pub schema: Option<EntrySchema>,
+    /// Whether the collection holds L2-normalized vectors; `None` if undeclared
+    #[serde(default)]
+    pub normalized: Option<bool>,
+    /// Distance the collection compares vectors with; `None` if undeclared
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance: Option<DistanceMetric>,
+    /// Search parameters used when a call leaves them out
+    #[serde(default)]
+    pub search: SearchDefaults,
+    /// How documents are split before embedding, as recommended by `p-mo analyze`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunking: Option<ChunkingStrategy>,
+    /// Lifetime of entries without an expiry of their own; unset means they never expire
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+}
+
+/// Relative weights of the vector and keyword scores in hybrid search
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HybridWeights {
+    pub vector: f32,
+    pub keyword: f32,
+}
+
+/// How a collection's results are reranked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RerankSettings {
+    pub enabled: bool,
+    /// Number of candidates fetched for the reranker to reorder
+    #[serde(default)]
+    pub candidates: Option<usize>,
+}
+
+/// Per-collection search defaults, tuned by admins without client changes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchDefaults {
+    /// Number of results returned when a call gives no `limit`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Results scoring below this are dropped when a call gives no `score_threshold`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_threshold: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hybrid: Option<HybridWeights>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank: Option<RerankSettings>,
+}
+
+impl SearchDefaults {
+    /// Reject settings no search could use
+    pub fn check(&self) -> Result<(), VectorStoreError> {
+        let invalid = |message: &str| Err(VectorStoreError::InvalidArgument(message.to_string()));
+        
+        if self.limit == Some(0) {
+            return invalid("limit must be at least 1");
+        }
+        if self.score_threshold.is_some_and(|threshold| !threshold.is_finite()) {
+            return invalid("score_threshold must be a finite number");
+        }
+        if let Some(weights) = self.hybrid {
+            let valid = |weight: f32| weight.is_finite() && weight >= 0.0;
+            if !valid(weights.vector) || !valid(weights.keyword) {
+                return invalid("hybrid weights must be non-negative numbers");
+            }
+            if weights.vector + weights.keyword == 0.0 {
+                return invalid("hybrid weights must not both be zero");
+            }
+        }
+        if self.rerank.as_ref().is_some_and(|rerank| rerank.candidates == Some(0)) {
+            return invalid("rerank candidates must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+impl CollectionInfo {
+    pub fn new(name: &str, vector_size: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            vector_size,
+            schema: None,
+            normalized: None,
+            distance: None,
+            search: SearchDefaults::default(),
+            chunking: None,
+            ttl_secs: None,
+        }
+    }
+    
+    /// Attach an entry schema
+    pub fn with_schema(mut self, schema: EntrySchema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+    
+    /// Declare whether the collection holds normalized vectors
+    pub fn with_normalized(mut self, normalized: bool) -> Self {
+        self.normalized = Some(normalized);
+        self
+    }
+    
+    /// Declare the distance the collection compares vectors with
+    pub fn with_distance(mut self, distance: DistanceMetric) -> Self {
+        self.distance = Some(distance);
+        self
+    }
+}
+
+/// Registry of known collections, consulted before calls reach the backend
+#[derive(Debug, Default)]
+pub struct CollectionRegistry {
+    collections: RwLock<HashMap<String, CollectionInfo>>,
+}
+
+impl CollectionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Load a registry saved with [`save`](Self::save); a missing file is an empty registry
+    pub fn load(path: &Path) -> Result<Self, VectorStoreError> {
+        let registry = Self::new();
+        if !path.exists() {
+            return Ok(registry);
+        }
+        let text = fs::read_to_string(path).map_err(|e| {
+            VectorStoreError::OperationFailed(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let collections: Vec<CollectionInfo> = serde_json::from_str(&text).map_err(|e| {
+            VectorStoreError::OperationFailed(format!("Failed to parse {}: {}", path.display(), e))
+        })?;
+        for info in collections {
+            registry.register(info);
+        }
+        Ok(registry)
+    }
+    
+    /// Write every registered collection to `path` as JSON
+    pub fn save(&self, path: &Path) -> Result<(), VectorStoreError> {
+        let failed = |e: String| VectorStoreError::OperationFailed(format!("Failed to write {}: {}", path.display(), e));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| failed(e.to_string()))?;
+        }
+        let text = serde_json::to_string_pretty(&self.list()).map_err(|e| failed(e.to_string()))?;
+        fs::write(path, text).map_err(|e| failed(e.to_string()))
+    }
+    
+    /// Register a collection, replacing any previous entry with the same name
+    pub fn register(&self, info: CollectionInfo) {
+        let mut collections = self.collections.write().unwrap();
+        collections.insert(info.name.clone(), info);
+    }
+    
+    /// Declare or replace the entry schema of a registered collection
+    pub fn set_schema(&self, name: &str, schema: EntrySchema) -> Result<(), VectorStoreError> {
+        schema.check()?;
+        
+        let mut collections = self.collections.write().unwrap();
+        let info = collections.get_mut(name).ok_or_else(|| {
+            VectorStoreError::InvalidArgument(format!("Collection '{}' is not registered", name))
+        })?;
+        info.schema = Some(schema);
+        Ok(())
+    }
+    
+    /// Replace the search defaults of a registered collection
+    pub fn set_search_defaults(&self, name: &str, defaults: SearchDefaults) -> Result<(), VectorStoreError> {
+        defaults.check()?;
+        
+        let mut collections = self.collections.write().unwrap();
+        let info = collections.get_mut(name).ok_or_else(|| {
+            VectorStoreError::InvalidArgument(format!("Collection '{}' is not registered", name))
+        })?;
+        info.search = defaults;
+        Ok(())
+    }
+    
+    /// Record how a registered collection's documents are chunked
+    pub fn set_chunking(&self, name: &str, chunking: ChunkingStrategy) -> Result<(), VectorStoreError> {
+        if chunking == ChunkingStrategy::FixedSize(0) {
+            return Err(VectorStoreError::InvalidArgument("chunk size must be at least 1 token".to_string()));
+        }
+        
+        let mut collections = self.collections.write().unwrap();
+        let info = collections.get_mut(name).ok_or_else(|| {
+            VectorStoreError::InvalidArgument(format!("Collection '{}' is not registered", name))
+        })?;
+        info.chunking = Some(chunking);
+        Ok(())
+    }
+    
+    /// Set or clear the TTL of a registered collection's entries
+    pub fn set_ttl(&self, name: &str, ttl_secs: Option<u64>) -> Result<(), VectorStoreError> {
+        if let Some(Err(message)) = ttl_secs.map(super::check_ttl) {
+            return Err(VectorStoreError::InvalidArgument(format!("ttl_secs: {}", message)));
+        }
+        
+        let mut collections = self.collections.write().unwrap();
+        let info = collections.get_mut(name).ok_or_else(|| {
+            VectorStoreError::InvalidArgument(format!("Collection '{}' is not registered", name))
+        })?;
+        info.ttl_secs = ttl_secs;
+        Ok(())
+    }
+    
+    /// Record the vector size and distance a registered collection actually has
+    pub fn set_vector_schema(&self, name: &str, vector_size: usize, distance: Option<DistanceMetric>) -> Result<(), VectorStoreError> {
+        let mut collections = self.collections.write().unwrap();
+        let info = collections.get_mut(name).ok_or_else(|| {
+            VectorStoreError::InvalidArgument(format!("Collection '{}' is not registered", name))
+        })?;
+        info.vector_size = vector_size;
+        info.distance = distance;
+        Ok(())
+    }
+    
+    /// The TTL of a collection's entries; `None` for unregistered collections
+    pub fn ttl(&self, name: &str) -> Option<u64> {
+        self.get(name).and_then(|info| info.ttl_secs)
+    }
+    
+    /// The search defaults of a collection; empty for unregistered collections
+    pub fn search_defaults(&self, name: &str) -> SearchDefaults {
+        self.get(name).map(|info| info.search).unwrap_or_default()
+    }
+    
+    /// Remove a collection from the registry
+    pub fn remove(&self, name: &str) -> Option<CollectionInfo> {
+        self.collections.write().unwrap().remove(name)
+    }
+    
+    /// Look up a collection by name
+    pub fn get(&self, name: &str) -> Option<CollectionInfo> {
+        self.collections.read().unwrap().get(name).cloned()
+    }
+    
+    /// List all registered collections sorted by name
+    pub fn list(&self) -> Vec<CollectionInfo> {
+        let mut collections: Vec<CollectionInfo> = self.collections.read().unwrap().values().cloned().collect();
+        collections.sort_by(|a, b| a.name.cmp(&b.name));
+        collections
+    }
+    
+    /// Check an embedding dimension against the registered vector size.
+    ///
+    /// Unregistered collections are not validated, since they may have been
+    /// created outside of p-mo.
+    pub fn validate_dimension(&self, collection: &str, actual: usize) -> Result<(), VectorStoreError> {
+        match self.get(collection) {
+            Some(info) => check_dimension(collection, info.vector_size, actual),
+            None => Ok(()),
+        }
+    }
+    
+    /// Check that a vector matches the collection's declared normalization.
+    ///
+    /// `normalized` is whether the caller normalizes its vectors. Collections
+    /// that declare nothing, and all-zero placeholder vectors, are not checked.
+    pub fn validate_normalization(&self, collection: &str, normalized: bool, embedding: &[f32]) -> Result<(), VectorStoreError> {
+        let declared = match self.get(collection).and_then(|info| info.normalized) {
+            Some(declared) => declared,
+            None => return Ok(()),
+        };
+        
+        let describe = |normalized: bool| if normalized { "normalized" } else { "unnormalized" };
+        if declared != normalized {
+            return Err(VectorStoreError::InvalidArgument(format!(
+                "Collection '{}' stores {} vectors but {} vectors were given",
+                collection, describe(declared), describe(normalized)
+            )));
+        }
+        
+        if declared && embedding.iter().any(|x| *x != 0.0) && !is_unit_length(embedding) {
+            return Err(VectorStoreError::InvalidArgument(format!(
+                "Collection '{}' stores normalized vectors but the vector is not unit length",
+                collection
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Validate an entry against the collection's schema, if it declares one
+    pub fn validate_entry(&self, collection: &str, title: &str, tags: &[String], metadata: &Metadata) -> Result<(), Vec<FieldError>> {
+        match self.get(collection).and_then(|info| info.schema) {
+            Some(schema) => schema.validate(title, tags, metadata),
+            None => Ok(()),
+        }
+    }
+    
+    /// Return the collections whose vector size differs from `dimension`
+    pub fn mismatched_collections(&self, dimension: usize) -> Vec<CollectionInfo> {
+        self.list()
+            .into_iter()
+            .filter(|info| info.vector_size != dimension)
+            .collect()
+    }
+}
+
+/// Compare an expected and an actual embedding dimension
+pub fn check_dimension(collection: &str, expected: usize, actual: usize) -> Result<(), VectorStoreError> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(VectorStoreError::DimensionMismatch {
+            collection: collection.to_string(),
+            expected,
+            actual,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_register_and_get() {
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("docs", 384));
+        
+        assert_eq!(registry.get("docs"), Some(CollectionInfo::new("docs", 384)));
+        assert!(registry.get("missing").is_none());
+        
+        registry.remove("docs");
+        assert!(registry.get("docs").is_none());
+    }
+    
+    #[test]
+    fn test_validate_dimension() {
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("docs", 384));
+        
+        assert!(registry.validate_dimension("docs", 384).is_ok());
+        assert!(registry.validate_dimension("unknown", 12).is_ok());
+        
+        let err = registry.validate_dimension("docs", 768).unwrap_err();
+        assert!(matches!(err, VectorStoreError::DimensionMismatch { expected: 384, actual: 768, .. }));
+        let message = err.to_string();
+        assert!(message.contains("expected 384"));
+        assert!(message.contains("got 768"));
+    }
+    
+    #[test]
+    fn test_set_schema_and_validate_entry() {
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("docs", 384));
+        
+        assert!(registry.set_schema("missing", EntrySchema::new()).is_err());
+        assert!(registry.set_schema("docs", EntrySchema::new().with_title_pattern("(")).is_err());
+        registry.set_schema("docs", EntrySchema::new().require("owner")).unwrap();
+        
+        let errors = registry.validate_entry("docs", "Title", &[], &Metadata::new()).unwrap_err();
+        assert_eq!(errors[0].field, "metadata.owner");
+        assert!(registry.validate_entry("other", "Title", &[], &Metadata::new()).is_ok());
+    }
+    
+    #[test]
+    fn test_mismatched_collections() {
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("small", 384));
+        registry.register(CollectionInfo::new("large", 768));
+        
+        let mismatched = registry.mismatched_collections(384);
+        assert_eq!(mismatched.len(), 1);
+        assert_eq!(mismatched[0].name, "large");
+    }
+    
+    #[test]
+    fn test_validate_normalization() {
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("unit", 2).with_normalized(true));
+        registry.register(CollectionInfo::new("raw", 2).with_normalized(false));
+        
+        assert!(registry.validate_normalization("unit", true, &[0.6, 0.8]).is_ok());
+        assert!(registry.validate_normalization("unit", true, &[3.0, 4.0]).is_err());
+        assert!(registry.validate_normalization("unit", false, &[3.0, 4.0]).is_err());
+        assert!(registry.validate_normalization("raw", true, &[0.6, 0.8]).is_err());
+        assert!(registry.validate_normalization("raw", false, &[3.0, 4.0]).is_ok());
+        assert!(registry.validate_normalization("unknown", true, &[3.0, 4.0]).is_ok());
+    }
+    
+    #[test]
+    fn test_set_search_defaults() {
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("docs", 384));
+        assert_eq!(registry.search_defaults("docs"), SearchDefaults::default());
+        
+        let defaults = SearchDefaults {
+            limit: Some(5),
+            score_threshold: Some(0.3),
+            hybrid: Some(HybridWeights { vector: 0.7, keyword: 0.3 }),
+            rerank: None,
+        };
+        registry.set_search_defaults("docs", defaults.clone()).unwrap();
+        assert_eq!(registry.search_defaults("docs"), defaults);
+        assert_eq!(registry.search_defaults("unknown"), SearchDefaults::default());
+        
+        assert!(registry.set_search_defaults("missing", SearchDefaults::default()).is_err());
+        let zero_weights = SearchDefaults { hybrid: Some(HybridWeights { vector: 0.0, keyword: 0.0 }), ..Default::default() };
+        assert!(registry.set_search_defaults("docs", zero_weights).is_err());
+        assert!(registry.set_search_defaults("docs", SearchDefaults { limit: Some(0), ..Default::default() }).is_err());
+    }
+    
+    #[test]
+    fn test_set_ttl() {
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("scratch", 384));
+        assert_eq!(registry.ttl("scratch"), None);
+        
+        registry.set_ttl("scratch", Some(3600)).unwrap();
+        assert_eq!(registry.ttl("scratch"), Some(3600));
+        assert!(registry.set_ttl("scratch", Some(0)).is_err());
+        assert!(registry.set_ttl("missing", Some(60)).is_err());
+        
+        registry.set_ttl("scratch", None).unwrap();
+        assert_eq!(registry.ttl("scratch"), None);
+        assert_eq!(registry.ttl("missing"), None);
+    }
+    
+    #[test]
+    fn test_chunking_survives_save_and_load() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(REGISTRY_FILE);
+        assert!(CollectionRegistry::load(&path).unwrap().list().is_empty());
+        
+        let registry = CollectionRegistry::new();
+        registry.register(CollectionInfo::new("docs", 384));
+        assert!(registry.set_chunking("missing", ChunkingStrategy::Paragraph).is_err());
+        assert!(registry.set_chunking("docs", ChunkingStrategy::FixedSize(0)).is_err());
+        registry.set_chunking("docs", ChunkingStrategy::FixedSize(256)).unwrap();
+        registry.save(&path).unwrap();
+        
+        let loaded = CollectionRegistry::load(&path).unwrap();
+        assert_eq!(loaded.get("docs").unwrap().chunking, Some(ChunkingStrategy::FixedSize(256)));
+        assert_eq!(loaded.list(), registry.list());
+    }
+}
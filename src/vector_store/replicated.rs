@@ -0,0 +1,289 @@
+//! Read/write splitting over a primary and its read replicas.
+//!
+//! Mutations always go to the primary. Reads go to a replica picked by the
+//! configured selection; when it fails the next replica is tried, and the
+//! primary answers if every replica does.
+
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use super::{
+    CollectionSchema, Document, DocumentPage, KeywordHit, MetadataFilter, MetadataPatch, PatchOutcome, PoolMetrics, SearchQuery, SearchResult,
+    VectorStore, VectorStoreError,
+};
+use crate::config::ReadSelection;
+
+/// Latency charged to a replica for a failed read, pushing it to the back under least-latency selection
+pub const FAILED_READ_PENALTY: Duration = Duration::from_secs(10);
+
+struct Replica {
+    name: String,
+    store: Arc<dyn VectorStore>,
+    /// Moving average of read latency in microseconds; 0 until first measured
+    latency_micros: AtomicU64,
+}
+
+impl Replica {
+    fn observe(&self, elapsed: Duration) {
+        let sample = elapsed.as_micros().max(1) as u64;
+        let previous = self.latency_micros.load(Ordering::Relaxed);
+        let average = if previous == 0 { sample } else { (previous * 7 + sample) / 8 };
+        self.latency_micros.store(average, Ordering::Relaxed);
+    }
+}
+
+/// A vector store that writes to a primary and reads from replicas
+pub struct ReplicatedVectorStore {
+    primary: Arc<dyn VectorStore>,
+    replicas: Vec<Replica>,
+    selection: ReadSelection,
+    next: AtomicUsize,
+}
+
+impl ReplicatedVectorStore {
+    /// `replicas` are named for logging, e.g. by URL
+    pub fn new(primary: Arc<dyn VectorStore>, replicas: Vec<(String, Arc<dyn VectorStore>)>, selection: ReadSelection) -> Self {
+        Self {
+            primary,
+            replicas: replicas.into_iter()
+                .map(|(name, store)| Replica { name, store, latency_micros: AtomicU64::new(0) })
+                .collect(),
+            selection,
+            next: AtomicUsize::new(0),
+        }
+    }
+    
+    /// The order replicas are tried in for one read
+    fn read_order(&self) -> Vec<usize> {
+        let count = self.replicas.len();
+        if count == 0 {
+            return Vec::new();
+        }
+        match self.selection {
+            ReadSelection::RoundRobin => {
+                let start = self.next.fetch_add(1, Ordering::Relaxed) % count;
+                (0..count).map(|offset| (start + offset) % count).collect()
+            },
+            ReadSelection::LeastLatency => {
+                // Unmeasured replicas sort first so each gets sampled
+                let mut order: Vec<usize> = (0..count).collect();
+                order.sort_by_key(|&index| self.replicas[index].latency_micros.load(Ordering::Relaxed));
+                order
+            },
+        }
+    }
+    
+    /// Run a read on the replicas in turn, falling back to the primary
+    async fn read<T, F, Fut>(&self, operation: &str, call: F) -> Result<T, VectorStoreError>
+    where
+        F: Fn(Arc<dyn VectorStore>) -> Fut,
+        Fut: Future<Output = Result<T, VectorStoreError>>,
+    {
+        for index in self.read_order() {
+            let replica = &self.replicas[index];
+            let started = Instant::now();
+            match call(replica.store.clone()).await {
+                Ok(value) => {
+                    replica.observe(started.elapsed());
+                    return Ok(value);
+                },
+                // The caller's mistake; every other endpoint would refuse it too
+                Err(e @ (VectorStoreError::InvalidArgument(_) | VectorStoreError::DimensionMismatch { .. })) => return Err(e),
+                Err(e) => {
+                    replica.observe(FAILED_READ_PENALTY);
+                    warn!(replica = %replica.name, operation = %operation, error = %e, "Read replica failed; failing over");
+                },
+            }
+        }
+        call(self.primary.clone()).await
+    }
+}
+
+#[async_trait]
+impl VectorStore for ReplicatedVectorStore {
+    async fn test_connection(&self) -> Result<(), VectorStoreError> {
+        self.primary.test_connection().await?;
+        for replica in &self.replicas {
+            if let Err(e) = replica.store.test_connection().await {
+                warn!(replica = %replica.name, error = %e, "Read replica is unreachable; reads will fail over");
+            }
+        }
+        Ok(())
+    }
+    
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), VectorStoreError> {
+        self.primary.create_collection(name, vector_size).await
+    }
+    
+    async fn delete_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+        self.primary.delete_collection(name).await
+    }
+    
+    async fn insert_document(&self, collection: &str, document: Document) -> Result<(), VectorStoreError> {
+        self.primary.insert_document(collection, document).await
+    }
+    
+    async fn search(&self, collection: &str, query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.read("search", |store| {
+            let query = query.clone();
+            async move { store.search(collection, query).await }
+        }).await
+    }
+    
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        self.read("list_collections", |store| async move { store.list_collections().await }).await
+    }
+    
+    /// The primary's schema, which replicas are created to match
+    async fn collection_schema(&self, collection: &str) -> Result<Option<CollectionSchema>, VectorStoreError> {
+        self.primary.collection_schema(collection).await
+    }
+    
+    async fn delete_document(&self, collection: &str, id: &str) -> Result<(), VectorStoreError> {
+        self.primary.delete_document(collection, id).await
+    }
+    
+    async fn get_document(&self, collection: &str, id: &str) -> Result<Option<Document>, VectorStoreError> {
+        self.read("get_document", |store| async move { store.get_document(collection, id).await }).await
+    }
+    
+    async fn list_documents(&self, collection: &str, offset: Option<String>, limit: usize) -> Result<DocumentPage, VectorStoreError> {
+        self.read("list_documents", |store| {
+            let offset = offset.clone();
+            async move { store.list_documents(collection, offset, limit).await }
+        }).await
+    }
+    
+    async fn patch_metadata(&self, collection: &str, filter: &MetadataFilter, patch: &MetadataPatch) -> Result<PatchOutcome, VectorStoreError> {
+        self.primary.patch_metadata(collection, filter, patch).await
+    }
+    
+    async fn count(&self, collection: &str, filter: &MetadataFilter) -> Result<usize, VectorStoreError> {
+        self.read("count", |store| async move { store.count(collection, filter).await }).await
+    }
+    
+    async fn search_filtered(&self, collection: &str, query: SearchQuery, filter: &MetadataFilter) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.read("search", |store| {
+            let query = query.clone();
+            async move { store.search_filtered(collection, query, filter).await }
+        }).await
+    }
+    
+    async fn keyword_search(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<KeywordHit>, VectorStoreError> {
+        self.read("keyword_search", |store| async move { store.keyword_search(collection, query, limit).await }).await
+    }
+    
+    async fn exists(&self, collection: &str, id: &str) -> Result<bool, VectorStoreError> {
+        self.read("exists", |store| async move { store.exists(collection, id).await }).await
+    }
+    
+    fn pool_metrics(&self) -> Option<PoolMetrics> {
+        self.replicas.iter()
+            .filter_map(|replica| replica.store.pool_metrics())
+            .fold(self.primary.pool_metrics(), |total, metrics| Some(total.unwrap_or_default() + metrics))
+    }
+    
+    fn backend_name(&self) -> &'static str {
+        self.primary.backend_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::{unsupported, InMemoryVectorStore};
+    
+    /// A replica that is down
+    struct Unreachable;
+    
+    #[async_trait]
+    impl VectorStore for Unreachable {
+        async fn test_connection(&self) -> Result<(), VectorStoreError> {
+            Err(VectorStoreError::ConnectionError("down".to_string()))
+        }
+        async fn create_collection(&self, _name: &str, _vector_size: usize) -> Result<(), VectorStoreError> {
+            Err(unsupported("create_collection"))
+        }
+        async fn delete_collection(&self, _name: &str) -> Result<(), VectorStoreError> {
+            Err(unsupported("delete_collection"))
+        }
+        async fn insert_document(&self, _collection: &str, _document: Document) -> Result<(), VectorStoreError> {
+            Err(unsupported("insert_document"))
+        }
+        async fn search(&self, _collection: &str, _query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+            Err(VectorStoreError::ConnectionError("down".to_string()))
+        }
+        async fn get_document(&self, _collection: &str, _id: &str) -> Result<Option<Document>, VectorStoreError> {
+            Err(VectorStoreError::ConnectionError("down".to_string()))
+        }
+    }
+    
+    fn document(id: &str) -> Document {
+        let mut document = Document::with_placeholder_embedding(format!("content of {}", id), 2);
+        document.id = id.to_string();
+        document
+    }
+    
+    #[tokio::test]
+    async fn test_writes_go_to_primary_and_reads_to_replicas() {
+        let primary = Arc::new(InMemoryVectorStore::new());
+        let replica = Arc::new(InMemoryVectorStore::new());
+        primary.create_collection("docs", 2).await.unwrap();
+        replica.create_collection("docs", 2).await.unwrap();
+        replica.insert_document("docs", document("replicated")).await.unwrap();
+        
+        let store = ReplicatedVectorStore::new(primary.clone(), vec![("replica".to_string(), replica.clone() as Arc<dyn VectorStore>)], ReadSelection::RoundRobin);
+        store.insert_document("docs", document("new")).await.unwrap();
+        
+        assert!(primary.get_document("docs", "new").await.unwrap().is_some());
+        assert!(replica.get_document("docs", "new").await.unwrap().is_none());
+        // Reads are served by the replica, which has not caught up yet
+        assert!(store.get_document("docs", "replicated").await.unwrap().is_some());
+        assert!(!store.exists("docs", "new").await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_failed_replica_fails_over() {
+        let primary = Arc::new(InMemoryVectorStore::new());
+        primary.create_collection("docs", 2).await.unwrap();
+        primary.insert_document("docs", document("a")).await.unwrap();
+        let healthy = Arc::new(InMemoryVectorStore::new());
+        healthy.create_collection("docs", 2).await.unwrap();
+        healthy.insert_document("docs", document("a")).await.unwrap();
+        
+        let replicas: Vec<(String, Arc<dyn VectorStore>)> = vec![
+            ("down".to_string(), Arc::new(Unreachable) as Arc<dyn VectorStore>),
+            ("healthy".to_string(), healthy as Arc<dyn VectorStore>),
+        ];
+        let store = ReplicatedVectorStore::new(primary, replicas, ReadSelection::LeastLatency);
+        
+        for _ in 0..3 {
+            assert!(store.get_document("docs", "a").await.unwrap().is_some());
+        }
+        // The failing replica has been pushed to the back
+        assert_eq!(store.read_order(), vec![1, 0]);
+        
+        let primary_only = ReplicatedVectorStore::new(
+            Arc::new(InMemoryVectorStore::new()),
+            vec![("down".to_string(), Arc::new(Unreachable) as Arc<dyn VectorStore>)],
+            ReadSelection::RoundRobin,
+        );
+        assert!(primary_only.search("missing", SearchQuery { embedding: vec![1.0, 0.0], limit: 1 }).await.is_err());
+        assert!(primary_only.test_connection().await.is_ok());
+    }
+    
+    #[test]
+    fn test_round_robin_rotates() {
+        let replicas: Vec<(String, Arc<dyn VectorStore>)> = (0..3)
+            .map(|index| (format!("replica-{}", index), Arc::new(InMemoryVectorStore::new()) as Arc<dyn VectorStore>))
+            .collect();
+        let store = ReplicatedVectorStore::new(Arc::new(InMemoryVectorStore::new()), replicas, ReadSelection::RoundRobin);
+        assert_eq!(store.read_order(), vec![0, 1, 2]);
+        assert_eq!(store.read_order(), vec![1, 2, 0]);
+        assert_eq!(store.read_order(), vec![2, 0, 1]);
+    }
+}
@@ -0,0 +1,274 @@
+//! Several backends behind one store, chosen per collection.
+//!
+//! Routing rules map collection names to named instances, so e.g. code
+//! knowledge can live on a local Qdrant while docs live on a managed cluster.
+//! Every operation names its collection, which makes the choice transparent
+//! to callers.
+
+use async_trait::async_trait;
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use super::{
+    CollectionSchema, Document, DocumentPage, KeywordHit, MetadataFilter, MetadataPatch, PatchOutcome, PayloadCompression, PoolMetrics, QdrantConfig,
+    QdrantConnector, SearchQuery, SearchResult, VectorStore, VectorStoreError,
+};
+use super::ReplicatedVectorStore;
+use crate::config::{CompressionConfig, PoolConfig, QdrantInstanceConfig, QdrantRoutingConfig};
+
+/// A vector store that forwards each call to the instance its collection is routed to
+pub struct RoutedVectorStore {
+    instances: Vec<(String, Arc<dyn VectorStore>)>,
+    routes: Vec<(Regex, usize)>,
+    default: usize,
+}
+
+impl RoutedVectorStore {
+    /// Route everything to the first of `instances` until rules are added
+    pub fn new(instances: Vec<(String, Arc<dyn VectorStore>)>) -> Result<Self, VectorStoreError> {
+        if instances.is_empty() {
+            return Err(VectorStoreError::InvalidArgument("At least one vector store instance is required".to_string()));
+        }
+        Ok(Self { instances, routes: Vec::new(), default: 0 })
+    }
+    
+    /// Send collections matching `pattern` to `instance`; earlier rules win
+    pub fn with_route(mut self, pattern: &str, instance: &str) -> Result<Self, VectorStoreError> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| VectorStoreError::InvalidArgument(format!("Invalid route pattern '{}': {}", pattern, e)))?;
+        let index = self.index_of(instance)?;
+        self.routes.push((regex, index));
+        Ok(self)
+    }
+    
+    /// Send collections no rule matches to `instance`
+    pub fn with_default_instance(mut self, instance: &str) -> Result<Self, VectorStoreError> {
+        self.default = self.index_of(instance)?;
+        Ok(self)
+    }
+    
+    /// Connect to every configured Qdrant instance and apply the routing
+    /// rules, compressing stored content on all of them when `compression` is enabled
+    pub async fn from_config(config: &QdrantRoutingConfig, pool: &PoolConfig, compression: &CompressionConfig) -> Result<Self, VectorStoreError> {
+        let compression = compression.enabled.then(|| PayloadCompression::from_config(compression));
+        let mut instances: Vec<(String, Arc<dyn VectorStore>)> = Vec::new();
+        for instance in &config.instances {
+            if instances.iter().any(|(name, _)| *name == instance.name) {
+                return Err(VectorStoreError::InvalidArgument(format!("Qdrant instance '{}' is configured twice", instance.name)));
+            }
+            let api_key = match &instance.api_key_env {
+                Some(variable) => Some(std::env::var(variable).map_err(|_| {
+                    VectorStoreError::AuthenticationError(format!(
+                        "{} is not set; it holds the API key of Qdrant instance '{}'",
+                        variable, instance.name
+                    ))
+                })?),
+                None => None,
+            };
+            instances.push((instance.name.clone(), connect_instance(instance, api_key, pool, compression).await?));
+        }
+        
+        let mut store = Self::new(instances)?;
+        for route in &config.routes {
+            store = store.with_route(&route.pattern, &route.instance)?;
+        }
+        if let Some(instance) = &config.default_instance {
+            store = store.with_default_instance(instance)?;
+        }
+        Ok(store)
+    }
+    
+    /// Name of the instance holding `collection`
+    pub fn instance_for(&self, collection: &str) -> &str {
+        &self.instances[self.route(collection)].0
+    }
+    
+    fn index_of(&self, instance: &str) -> Result<usize, VectorStoreError> {
+        self.instances.iter()
+            .position(|(name, _)| name == instance)
+            .ok_or_else(|| VectorStoreError::InvalidArgument(format!("Unknown vector store instance '{}'", instance)))
+    }
+    
+    fn route(&self, collection: &str) -> usize {
+        self.routes.iter()
+            .find(|(pattern, _)| pattern.is_match(collection))
+            .map(|(_, index)| *index)
+            .unwrap_or(self.default)
+    }
+    
+    fn store(&self, collection: &str) -> &Arc<dyn VectorStore> {
+        &self.instances[self.route(collection)].1
+    }
+}
+
+/// Connect to an instance's primary and, when it has any, its read replicas
+async fn connect_instance(
+    instance: &QdrantInstanceConfig,
+    api_key: Option<String>,
+    pool: &PoolConfig,
+    compression: Option<PayloadCompression>,
+) -> Result<Arc<dyn VectorStore>, VectorStoreError> {
+    let connect = |url: &str| {
+        let config = QdrantConfig {
+            url: url.to_string(),
+            api_key: api_key.clone(),
+            ..QdrantConfig::default()
+        }.with_pool(pool);
+        async move {
+            let connector = QdrantConnector::new(config).await?;
+            Ok::<_, VectorStoreError>(match compression {
+                Some(compression) => connector.with_compression(compression),
+                None => connector,
+            })
+        }
+    };
+    
+    let primary: Arc<dyn VectorStore> = Arc::new(connect(&instance.url).await?);
+    if instance.read_urls.is_empty() {
+        return Ok(primary);
+    }
+    
+    let mut replicas: Vec<(String, Arc<dyn VectorStore>)> = Vec::new();
+    for url in &instance.read_urls {
+        replicas.push((url.clone(), Arc::new(connect(url).await?)));
+    }
+    Ok(Arc::new(ReplicatedVectorStore::new(primary, replicas, instance.read_selection)))
+}
+
+#[async_trait]
+impl VectorStore for RoutedVectorStore {
+    async fn test_connection(&self) -> Result<(), VectorStoreError> {
+        for (name, store) in &self.instances {
+            store.test_connection().await
+                .map_err(|e| VectorStoreError::ConnectionError(format!("instance '{}': {}", name, e)))?;
+        }
+        Ok(())
+    }
+    
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), VectorStoreError> {
+        self.store(name).create_collection(name, vector_size).await
+    }
+    
+    async fn delete_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+        self.store(name).delete_collection(name).await
+    }
+    
+    async fn insert_document(&self, collection: &str, document: Document) -> Result<(), VectorStoreError> {
+        self.store(collection).insert_document(collection, document).await
+    }
+    
+    async fn search(&self, collection: &str, query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.store(collection).search(collection, query).await
+    }
+    
+    /// Collections routed to each instance; a collection found on an instance
+    /// its name does not route to is left out, since no call would reach it
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        let mut collections = BTreeSet::new();
+        for (index, (_, store)) in self.instances.iter().enumerate() {
+            collections.extend(store.list_collections().await?
+                .into_iter()
+                .filter(|collection| self.route(collection) == index));
+        }
+        Ok(collections.into_iter().collect())
+    }
+    
+    async fn collection_schema(&self, collection: &str) -> Result<Option<CollectionSchema>, VectorStoreError> {
+        self.store(collection).collection_schema(collection).await
+    }
+    
+    async fn delete_document(&self, collection: &str, id: &str) -> Result<(), VectorStoreError> {
+        self.store(collection).delete_document(collection, id).await
+    }
+    
+    async fn get_document(&self, collection: &str, id: &str) -> Result<Option<Document>, VectorStoreError> {
+        self.store(collection).get_document(collection, id).await
+    }
+    
+    async fn list_documents(&self, collection: &str, offset: Option<String>, limit: usize) -> Result<DocumentPage, VectorStoreError> {
+        self.store(collection).list_documents(collection, offset, limit).await
+    }
+    
+    async fn patch_metadata(&self, collection: &str, filter: &MetadataFilter, patch: &MetadataPatch) -> Result<PatchOutcome, VectorStoreError> {
+        self.store(collection).patch_metadata(collection, filter, patch).await
+    }
+    
+    async fn count(&self, collection: &str, filter: &MetadataFilter) -> Result<usize, VectorStoreError> {
+        self.store(collection).count(collection, filter).await
+    }
+    
+    async fn search_filtered(&self, collection: &str, query: SearchQuery, filter: &MetadataFilter) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.store(collection).search_filtered(collection, query, filter).await
+    }
+    
+    async fn keyword_search(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<KeywordHit>, VectorStoreError> {
+        self.store(collection).keyword_search(collection, query, limit).await
+    }
+    
+    async fn exists(&self, collection: &str, id: &str) -> Result<bool, VectorStoreError> {
+        self.store(collection).exists(collection, id).await
+    }
+    
+    /// The pools of all instances, summed
+    fn pool_metrics(&self) -> Option<PoolMetrics> {
+        self.instances.iter()
+            .filter_map(|(_, store)| store.pool_metrics())
+            .reduce(|total, metrics| total + metrics)
+    }
+    
+    fn backend_name(&self) -> &'static str {
+        "qdrant-routed"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    fn routed() -> (RoutedVectorStore, Arc<InMemoryVectorStore>, Arc<InMemoryVectorStore>) {
+        let code = Arc::new(InMemoryVectorStore::new());
+        let docs = Arc::new(InMemoryVectorStore::new());
+        let store = RoutedVectorStore::new(vec![
+            ("code".to_string(), code.clone() as Arc<dyn VectorStore>),
+            ("docs".to_string(), docs.clone() as Arc<dyn VectorStore>),
+        ])
+            .unwrap()
+            .with_route("^docs", "docs")
+            .unwrap();
+        (store, code, docs)
+    }
+    
+    #[tokio::test]
+    async fn test_collections_follow_routes() {
+        let (store, code, docs) = routed();
+        assert_eq!(store.instance_for("docs-handbook"), "docs");
+        assert_eq!(store.instance_for("rust"), "code");
+        
+        store.create_collection("docs-handbook", 2).await.unwrap();
+        store.create_collection("rust", 2).await.unwrap();
+        let mut document = Document::with_placeholder_embedding("Onboarding".to_string(), 2);
+        document.id = "a".to_string();
+        store.insert_document("docs-handbook", document).await.unwrap();
+        
+        assert!(docs.get_document("docs-handbook", "a").await.unwrap().is_some());
+        assert_eq!(code.list_collections().await.unwrap(), vec!["rust"]);
+        assert!(store.exists("docs-handbook", "a").await.unwrap());
+        assert_eq!(store.list_collections().await.unwrap(), vec!["docs-handbook", "rust"]);
+    }
+    
+    #[test]
+    fn test_invalid_routes() {
+        let (store, _, _) = routed();
+        assert!(matches!(store.with_route("^wiki", "cloud"), Err(VectorStoreError::InvalidArgument(_))));
+        
+        let (store, _, _) = routed();
+        assert!(store.with_route("(", "docs").is_err());
+        
+        let (store, _, _) = routed();
+        let store = store.with_default_instance("docs").unwrap();
+        assert_eq!(store.instance_for("wiki"), "docs");
+        assert!(RoutedVectorStore::new(Vec::new()).is_err());
+    }
+}
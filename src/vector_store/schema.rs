@@ -0,0 +1,165 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::VectorStoreError;
+use crate::text_processing::Metadata;
+
+/// Rules entries added to a collection must satisfy
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EntrySchema {
+    /// Metadata fields every entry must provide with a non-empty value
+    #[serde(default)]
+    pub required_fields: Vec<String>,
+    /// The only tags entries may use; any tag is accepted when unset
+    #[serde(default)]
+    pub allowed_tags: Option<Vec<String>>,
+    /// A regular expression titles must match
+    #[serde(default)]
+    pub title_pattern: Option<String>,
+}
+
+/// A validation failure for a single field of an entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl EntrySchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Require a metadata field
+    pub fn require(mut self, field: &str) -> Self {
+        self.required_fields.push(field.to_string());
+        self
+    }
+    
+    /// Restrict the tags entries may use
+    pub fn with_allowed_tags(mut self, tags: &[&str]) -> Self {
+        self.allowed_tags = Some(tags.iter().map(|tag| tag.to_string()).collect());
+        self
+    }
+    
+    /// Require titles to match `pattern`
+    pub fn with_title_pattern(mut self, pattern: &str) -> Self {
+        self.title_pattern = Some(pattern.to_string());
+        self
+    }
+    
+    /// Check that the schema itself is usable
+    pub fn check(&self) -> Result<(), VectorStoreError> {
+        if let Some(pattern) = &self.title_pattern {
+            Regex::new(pattern).map_err(|e| {
+                VectorStoreError::InvalidArgument(format!("Invalid title pattern '{}': {}", pattern, e))
+            })?;
+        }
+        Ok(())
+    }
+    
+    /// Validate an entry, collecting every failing field
+    pub fn validate(&self, title: &str, tags: &[String], metadata: &Metadata) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        
+        for field in &self.required_fields {
+            let present = metadata.get(field).is_some_and(|value| !value.trim().is_empty());
+            if !present {
+                errors.push(FieldError::new(&format!("metadata.{}", field), "required field is missing"));
+            }
+        }
+        
+        if let Some(allowed) = &self.allowed_tags {
+            for tag in tags.iter().filter(|tag| !allowed.contains(tag)) {
+                errors.push(FieldError::new("tags", format!(
+                    "tag '{}' is not allowed; expected one of: {}",
+                    tag,
+                    allowed.join(", ")
+                )));
+            }
+        }
+        
+        if let Some(pattern) = &self.title_pattern {
+            // An invalid pattern is rejected on registration, so this only fails for hand-built schemas
+            let matches = Regex::new(pattern).map(|re| re.is_match(title)).unwrap_or(false);
+            if !matches {
+                errors.push(FieldError::new("title", format!("title does not match pattern '{}'", pattern)));
+            }
+        }
+        
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+    
+    /// A skeleton of the arguments an entry for this collection needs
+    pub fn template(&self) -> Value {
+        let metadata: serde_json::Map<String, Value> = self.required_fields.iter()
+            .map(|field| (field.clone(), json!("")))
+            .collect();
+        
+        json!({
+            "title": self.title_pattern.as_ref().map(|pattern| format!("<matching {}>", pattern)).unwrap_or_default(),
+            "content": "",
+            "tags": self.allowed_tags.clone().unwrap_or_default(),
+            "metadata": metadata,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn metadata(pairs: &[(&str, &str)]) -> Metadata {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+    
+    #[test]
+    fn test_valid_entry() {
+        let schema = EntrySchema::new()
+            .require("owner")
+            .with_allowed_tags(&["runbook", "postmortem"])
+            .with_title_pattern(r"^[A-Z]+-\d+");
+        
+        let result = schema.validate("OPS-12 Disk full", &["runbook".to_string()], &metadata(&[("owner", "sre")]));
+        assert!(result.is_ok());
+    }
+    
+    #[test]
+    fn test_collects_field_errors() {
+        let schema = EntrySchema::new()
+            .require("owner")
+            .with_allowed_tags(&["runbook"])
+            .with_title_pattern(r"^[A-Z]+-\d+");
+        
+        let errors = schema.validate("Disk full", &["misc".to_string()], &metadata(&[("owner", " ")])).unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["metadata.owner", "tags", "title"]);
+    }
+    
+    #[test]
+    fn test_invalid_pattern_is_rejected() {
+        assert!(EntrySchema::new().with_title_pattern("(").check().is_err());
+        assert!(EntrySchema::new().with_title_pattern("^a").check().is_ok());
+    }
+    
+    #[test]
+    fn test_template_lists_required_fields() {
+        let template = EntrySchema::new().require("owner").with_allowed_tags(&["a"]).template();
+        assert_eq!(template["metadata"]["owner"], "");
+        assert_eq!(template["tags"], json!(["a"]));
+    }
+}
@@ -0,0 +1,496 @@
+//! One logical collection spread over several physical ones.
+//!
+//! A collection `kb` with 4 shards is stored as `kb__shard_0_of_4` through
+//! `kb__shard_3_of_4`, each document on the shard picked by a hash of its id,
+//! so no single backend collection has to hold the whole knowledge base.
+//! Lookups, inserts and deletes go to one shard; searches fan out to every
+//! shard and merge by score; listings walk the shards in turn.
+//!
+//! The layout is read back from the backend's collection names once per
+//! process, so a collection created unsharded or resharded with
+//! [`ShardedVectorStore::reshard`] needs no configuration to be found. A
+//! running server picks up a reshard made by another process on restart.
+
+use async_trait::async_trait;
+use futures::future::try_join_all;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+
+use super::{
+    CollectionSchema, Document, DocumentPage, KeywordHit, MetadataFilter, MetadataPatch, PatchOutcome, PoolMetrics, SearchQuery, SearchResult,
+    VectorStore, VectorStoreError,
+};
+use super::filter::FILTER_PAGE_SIZE;
+use crate::config::ShardingConfig;
+use crate::progress::Progress;
+
+/// Separates a collection's name from the shard part of its physical collections' names
+pub const SHARD_SEPARATOR: &str = "__shard_";
+
+/// Name of shard `index` of `collection` split `count` ways; a single shard is the collection itself
+pub fn shard_name(collection: &str, index: usize, count: usize) -> String {
+    if count == 1 {
+        collection.to_string()
+    } else {
+        format!("{}{}{}_of_{}", collection, SHARD_SEPARATOR, index, count)
+    }
+}
+
+/// The shard of `count` a document with this id lives on
+pub fn shard_of(id: &str, count: usize) -> usize {
+    // FNV-1a, which unlike the std hasher is fixed across Rust versions
+    let hash = id.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    (hash % count as u64) as usize
+}
+
+/// The collection, shard index and shard count a physical collection name encodes
+fn parse_shard_name(physical: &str) -> Option<(&str, usize, usize)> {
+    let (collection, shard) = physical.rsplit_once(SHARD_SEPARATOR)?;
+    let (index, count) = shard.split_once("_of_")?;
+    let (index, count): (usize, usize) = (index.parse().ok()?, count.parse().ok()?);
+    (!collection.is_empty() && count > 1 && index < count).then_some((collection, index, count))
+}
+
+fn physical_names(collection: &str, count: usize) -> Vec<String> {
+    (0..count).map(|index| shard_name(collection, index, count)).collect()
+}
+
+/// The shard counts each collection is completely stored with, from the
+/// backend's collection names; plain collections have one shard
+fn discover(names: &[String]) -> HashMap<String, BTreeSet<usize>> {
+    let mut shards: BTreeMap<(&str, usize), usize> = BTreeMap::new();
+    let mut layouts: HashMap<String, BTreeSet<usize>> = HashMap::new();
+    for name in names {
+        match parse_shard_name(name) {
+            Some((collection, _, count)) => *shards.entry((collection, count)).or_default() += 1,
+            None => {
+                layouts.entry(name.clone()).or_default().insert(1);
+            },
+        }
+    }
+    // Shards of a layout still being created are left out
+    for ((collection, count), found) in shards {
+        if found == count {
+            layouts.entry(collection.to_string()).or_default().insert(count);
+        }
+    }
+    layouts
+}
+
+/// Outcome of a reshard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReshardOutcome {
+    /// Shard count the collection had
+    pub from: usize,
+    /// Shard count the collection has now
+    pub to: usize,
+    /// Documents copied to their new shard
+    pub moved: usize,
+}
+
+/// Wraps a store, splitting sharded collections over several of its collections
+pub struct ShardedVectorStore {
+    inner: Arc<dyn VectorStore>,
+    /// Shard count collections are created with; one when unlisted
+    configured: HashMap<String, usize>,
+    /// Layouts found in the backend, loaded on first use
+    layouts: RwLock<Option<HashMap<String, BTreeSet<usize>>>>,
+}
+
+impl ShardedVectorStore {
+    pub fn new(inner: Arc<dyn VectorStore>) -> Self {
+        Self { inner, configured: HashMap::new(), layouts: RwLock::new(None) }
+    }
+    
+    /// Create `collection` with `count` shards when it does not exist yet
+    pub fn with_shards(mut self, collection: &str, count: usize) -> Self {
+        self.configured.insert(collection.to_string(), count.max(1));
+        self
+    }
+    
+    /// Shard the collections listed in the `[sharding]` settings
+    pub fn from_config(inner: Arc<dyn VectorStore>, config: &ShardingConfig) -> Self {
+        config.collections.iter().fold(Self::new(inner), |store, (collection, count)| store.with_shards(collection, *count))
+    }
+    
+    /// Number of shards `collection` is stored in; one for plain and unknown collections
+    pub async fn shard_count(&self, collection: &str) -> Result<usize, VectorStoreError> {
+        if self.layouts.read().unwrap().is_none() {
+            let found = discover(&self.inner.list_collections().await?);
+            self.layouts.write().unwrap().get_or_insert(found);
+        }
+        let layouts = self.layouts.read().unwrap();
+        match layouts.as_ref().and_then(|layouts| layouts.get(collection)) {
+            None => Ok(1),
+            Some(counts) if counts.len() == 1 => Ok(counts.iter().next().copied().unwrap_or(1)),
+            Some(counts) => Err(VectorStoreError::OperationFailed(format!(
+                "Collection '{}' is stored with {} shard counts ({}), left by an interrupted reshard; run `p-mo reshard {} --shards <count>` to finish it",
+                collection,
+                counts.len(),
+                counts.iter().map(usize::to_string).collect::<Vec<_>>().join(", "),
+                collection
+            ))),
+        }
+    }
+    
+    fn set_layout(&self, collection: &str, count: Option<usize>) {
+        if let Some(layouts) = self.layouts.write().unwrap().as_mut() {
+            match count {
+                Some(count) => layouts.insert(collection.to_string(), BTreeSet::from([count])),
+                None => layouts.remove(collection),
+            };
+        }
+    }
+    
+    /// The physical collection holding document `id` of `collection`
+    async fn shard_for(&self, collection: &str, id: &str) -> Result<String, VectorStoreError> {
+        let count = self.shard_count(collection).await?;
+        Ok(shard_name(collection, shard_of(id, count), count))
+    }
+    
+    async fn shards(&self, collection: &str) -> Result<Vec<String>, VectorStoreError> {
+        Ok(physical_names(collection, self.shard_count(collection).await?))
+    }
+    
+    /// Move the documents of `collection` onto `count` shards.
+    ///
+    /// The new shards are created and filled before the old ones are
+    /// deleted, so reads keep working until the switch; writes made by other
+    /// processes meanwhile may be lost, so stop them first. A reshard that
+    /// was interrupted is finished by running it again.
+    pub async fn reshard(&self, collection: &str, count: usize, progress: &Progress) -> Result<ReshardOutcome, VectorStoreError> {
+        if count == 0 {
+            return Err(VectorStoreError::InvalidArgument("Shard count must be at least 1".to_string()));
+        }
+        let names = self.inner.list_collections().await?;
+        let layouts = discover(&names).remove(collection).unwrap_or_default();
+        let sources: Vec<usize> = layouts.iter().copied().filter(|layout| *layout != count).collect();
+        let Some(&from) = sources.first() else {
+            return match layouts.contains(&count) {
+                true => Ok(ReshardOutcome { from: count, to: count, moved: 0 }),
+                false => Err(VectorStoreError::CollectionNotFound(collection.to_string())),
+            };
+        };
+        
+        let sources: Vec<String> = sources.iter().flat_map(|layout| physical_names(collection, *layout)).collect();
+        let vector_size = self.inner.collection_schema(&sources[0]).await?
+            .map(|schema| schema.vector_size)
+            .ok_or_else(|| VectorStoreError::OperationFailed(format!("Cannot tell the vector size of collection '{}'", collection)))?;
+        let mut total = 0;
+        for source in &sources {
+            total += self.inner.count(source, &MetadataFilter::default()).await?;
+        }
+        progress.set_total(total as u64);
+        
+        // Shards left by an interrupted run are reused
+        for target in physical_names(collection, count) {
+            if !names.contains(&target) {
+                self.inner.create_collection(&target, vector_size).await?;
+            }
+        }
+        let mut moved = 0;
+        for source in &sources {
+            let mut offset = None;
+            loop {
+                let page = self.inner.list_documents(source, offset, FILTER_PAGE_SIZE).await?;
+                for document in page.documents {
+                    let target = shard_name(collection, shard_of(&document.id, count), count);
+                    self.inner.insert_document(&target, document).await?;
+                    moved += 1;
+                    progress.inc(1);
+                }
+                offset = match page.next_offset {
+                    Some(next) => Some(next),
+                    None => break,
+                };
+            }
+        }
+        for source in &sources {
+            self.inner.delete_collection(source).await?;
+        }
+        
+        self.set_layout(collection, Some(count));
+        Ok(ReshardOutcome { from, to: count, moved })
+    }
+    
+    /// Every shard searched at once, among the documents matching `filter`
+    /// when one is given, and the best `limit` results kept
+    async fn search_shards(&self, collection: &str, query: SearchQuery, filter: Option<MetadataFilter>) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let shards = self.shards(collection).await?;
+        if let [shard] = shards.as_slice() {
+            return match &filter {
+                Some(filter) => self.inner.search_filtered(shard, query, filter).await,
+                None => self.inner.search(shard, query).await,
+            };
+        }
+        
+        // Polled together on this task rather than spawned, so each shard's
+        // search keeps the caller's deadline and stage timings
+        let searches = shards.iter().map(|shard| match &filter {
+            Some(filter) => self.inner.search_filtered(shard, query.clone(), filter),
+            None => self.inner.search(shard, query.clone()),
+        });
+        let mut results: Vec<SearchResult> = try_join_all(searches).await?.into_iter().flatten().collect();
+        results.sort_by(|a, b| b.relevance().total_cmp(&a.relevance()).then_with(|| a.document.id.cmp(&b.document.id)));
+        results.truncate(query.limit);
+        Ok(results)
+    }
+}
+
+/// Split a listing offset into the shard it is in and the shard's own offset
+fn parse_offset(offset: &str, count: usize) -> Result<(usize, Option<String>), VectorStoreError> {
+    let invalid = || VectorStoreError::InvalidArgument(format!("Invalid offset for a sharded collection: {}", offset));
+    let (index, rest) = offset.split_once(':').ok_or_else(invalid)?;
+    let index: usize = index.parse().map_err(|_| invalid())?;
+    if index >= count {
+        return Err(invalid());
+    }
+    Ok((index, (!rest.is_empty()).then(|| rest.to_string())))
+}
+
+#[async_trait]
+impl VectorStore for ShardedVectorStore {
+    async fn test_connection(&self) -> Result<(), VectorStoreError> {
+        self.inner.test_connection().await
+    }
+    
+    /// New collections get their configured shard count; an existing one keeps its layout
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), VectorStoreError> {
+        let existing = self.shard_count(name).await?;
+        let count = match self.layouts.read().unwrap().as_ref().is_some_and(|layouts| layouts.contains_key(name)) {
+            true => existing,
+            false => self.configured.get(name).copied().unwrap_or(1),
+        };
+        for shard in physical_names(name, count) {
+            self.inner.create_collection(&shard, vector_size).await?;
+        }
+        self.set_layout(name, Some(count));
+        Ok(())
+    }
+    
+    async fn delete_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+        for shard in self.shards(name).await? {
+            self.inner.delete_collection(&shard).await?;
+        }
+        self.set_layout(name, None);
+        Ok(())
+    }
+    
+    async fn insert_document(&self, collection: &str, document: Document) -> Result<(), VectorStoreError> {
+        let shard = self.shard_for(collection, &document.id).await?;
+        self.inner.insert_document(&shard, document).await
+    }
+    
+    /// Every shard searched at once, the best `limit` results kept
+    async fn search(&self, collection: &str, query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.search_shards(collection, query, None).await
+    }
+    
+    async fn search_filtered(&self, collection: &str, query: SearchQuery, filter: &MetadataFilter) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.search_shards(collection, query, Some(filter.clone())).await
+    }
+    
+    /// Each shard scores its own documents; term statistics differ little
+    /// between shards filled by a hash, so the scores are merged as they are
+    async fn keyword_search(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<KeywordHit>, VectorStoreError> {
+        let shards = self.shards(collection).await?;
+        let searches = shards.iter().map(|shard| self.inner.keyword_search(shard, query, limit));
+        let mut hits: Vec<KeywordHit> = try_join_all(searches).await?.into_iter().flatten().collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.document.id.cmp(&b.document.id)));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+    
+    /// Collections by their logical names, shards hidden
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        let collections: BTreeSet<String> = self.inner.list_collections().await?
+            .into_iter()
+            .map(|name| match parse_shard_name(&name) {
+                Some((collection, _, _)) => collection.to_string(),
+                None => name,
+            })
+            .collect();
+        Ok(collections.into_iter().collect())
+    }
+    
+    /// The schema of the first shard, which all shards are created with
+    async fn collection_schema(&self, collection: &str) -> Result<Option<CollectionSchema>, VectorStoreError> {
+        let shard = shard_name(collection, 0, self.shard_count(collection).await?);
+        self.inner.collection_schema(&shard).await
+    }
+    
+    async fn delete_document(&self, collection: &str, id: &str) -> Result<(), VectorStoreError> {
+        let shard = self.shard_for(collection, id).await?;
+        self.inner.delete_document(&shard, id).await
+    }
+    
+    async fn get_document(&self, collection: &str, id: &str) -> Result<Option<Document>, VectorStoreError> {
+        let shard = self.shard_for(collection, id).await?;
+        self.inner.get_document(&shard, id).await
+    }
+    
+    /// Shards listed one after another; offsets are `<shard>:<the shard's offset>`
+    async fn list_documents(&self, collection: &str, offset: Option<String>, limit: usize) -> Result<DocumentPage, VectorStoreError> {
+        let count = self.shard_count(collection).await?;
+        if count == 1 {
+            return self.inner.list_documents(collection, offset, limit).await;
+        }
+        
+        let (mut index, mut shard_offset) = match offset {
+            Some(offset) => parse_offset(&offset, count)?,
+            None => (0, None),
+        };
+        let mut documents = Vec::new();
+        while index < count && documents.len() < limit {
+            let page = self.inner.list_documents(&shard_name(collection, index, count), shard_offset.take(), limit - documents.len()).await?;
+            documents.extend(page.documents);
+            match page.next_offset {
+                Some(next) => shard_offset = Some(next),
+                None => index += 1,
+            }
+        }
+        let next_offset = (index < count).then(|| format!("{}:{}", index, shard_offset.unwrap_or_default()));
+        Ok(DocumentPage { documents, next_offset })
+    }
+    
+    async fn patch_metadata(&self, collection: &str, filter: &MetadataFilter, patch: &MetadataPatch) -> Result<PatchOutcome, VectorStoreError> {
+        let mut total = PatchOutcome::default();
+        for shard in self.shards(collection).await? {
+            let outcome = self.inner.patch_metadata(&shard, filter, patch).await?;
+            total.matched += outcome.matched;
+            total.updated += outcome.updated;
+        }
+        Ok(total)
+    }
+    
+    async fn count(&self, collection: &str, filter: &MetadataFilter) -> Result<usize, VectorStoreError> {
+        let mut total = 0;
+        for shard in self.shards(collection).await? {
+            total += self.inner.count(&shard, filter).await?;
+        }
+        Ok(total)
+    }
+    
+    async fn exists(&self, collection: &str, id: &str) -> Result<bool, VectorStoreError> {
+        let shard = self.shard_for(collection, id).await?;
+        self.inner.exists(&shard, id).await
+    }
+    
+    fn pool_metrics(&self) -> Option<PoolMetrics> {
+        self.inner.pool_metrics()
+    }
+    
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::{deadline, InMemoryVectorStore};
+    use std::time::{Duration, Instant};
+    
+    fn document(id: &str, embedding: Vec<f32>) -> Document {
+        let mut document = Document::with_placeholder_embedding(format!("entry {}", id), embedding.len());
+        document.id = id.to_string();
+        document.embedding = embedding;
+        document
+    }
+    
+    #[tokio::test]
+    async fn test_documents_spread_over_shards() {
+        let backend = Arc::new(InMemoryVectorStore::new());
+        let store = ShardedVectorStore::new(backend.clone()).with_shards("kb", 3);
+        store.create_collection("kb", 2).await.unwrap();
+        for index in 0..30 {
+            let angle = index as f32 / 10.0;
+            store.insert_document("kb", document(&format!("e{}", index), vec![angle.cos(), angle.sin()])).await.unwrap();
+        }
+        
+        assert_eq!(store.list_collections().await.unwrap(), vec!["kb"]);
+        assert_eq!(backend.list_collections().await.unwrap().len(), 3);
+        for index in 0..3 {
+            assert!(backend.count(&shard_name("kb", index, 3), &MetadataFilter::default()).await.unwrap() > 0);
+        }
+        assert_eq!(store.count("kb", &MetadataFilter::default()).await.unwrap(), 30);
+        assert!(store.get_document("kb", "e7").await.unwrap().is_some());
+        
+        // The merged search finds the nearest entries whichever shard holds them
+        let results = store.search("kb", SearchQuery { embedding: vec![1.0, 0.0], limit: 3 }).await.unwrap();
+        let ids: Vec<&str> = results.iter().map(|result| result.document.id.as_str()).collect();
+        assert_eq!(ids, ["e0", "e1", "e2"]);
+        
+        let mut listed = Vec::new();
+        let mut offset = None;
+        loop {
+            let page = store.list_documents("kb", offset, 7).await.unwrap();
+            assert!(page.documents.len() <= 7);
+            listed.extend(page.documents.into_iter().map(|document| document.id));
+            offset = match page.next_offset {
+                Some(next) => Some(next),
+                None => break,
+            };
+        }
+        listed.sort();
+        listed.dedup();
+        assert_eq!(listed.len(), 30);
+        
+        store.delete_collection("kb").await.unwrap();
+        assert!(backend.list_collections().await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_reshard_moves_documents() {
+        let backend = Arc::new(InMemoryVectorStore::new());
+        backend.create_collection("kb", 2).await.unwrap();
+        for index in 0..20 {
+            backend.insert_document("kb", document(&format!("e{}", index), vec![1.0, 0.0])).await.unwrap();
+        }
+        
+        let store = ShardedVectorStore::new(backend.clone());
+        let outcome = store.reshard("kb", 4, &Progress::hidden()).await.unwrap();
+        assert_eq!(outcome, ReshardOutcome { from: 1, to: 4, moved: 20 });
+        assert_eq!(store.shard_count("kb").await.unwrap(), 4);
+        assert!(!backend.list_collections().await.unwrap().contains(&"kb".to_string()));
+        assert_eq!(store.count("kb", &MetadataFilter::default()).await.unwrap(), 20);
+        assert!(store.exists("kb", "e13").await.unwrap());
+        
+        // A fresh process finds the layout from the collection names
+        let reopened = ShardedVectorStore::new(backend.clone());
+        assert_eq!(reopened.shard_count("kb").await.unwrap(), 4);
+        assert_eq!(reopened.reshard("kb", 4, &Progress::hidden()).await.unwrap().moved, 0);
+        
+        // An interrupted reshard leaves two layouts, finished by running it again
+        backend.create_collection(&shard_name("kb", 0, 2), 2).await.unwrap();
+        backend.create_collection(&shard_name("kb", 1, 2), 2).await.unwrap();
+        assert!(ShardedVectorStore::new(backend.clone()).shard_count("kb").await.is_err());
+        let outcome = ShardedVectorStore::new(backend.clone()).reshard("kb", 2, &Progress::hidden()).await.unwrap();
+        assert_eq!(outcome, ReshardOutcome { from: 4, to: 2, moved: 20 });
+        assert_eq!(backend.list_collections().await.unwrap().len(), 2);
+        
+        assert!(store.reshard("missing", 2, &Progress::hidden()).await.is_err());
+        assert!(store.reshard("kb", 0, &Progress::hidden()).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_shard_searches_keep_the_callers_deadline() {
+        let store = ShardedVectorStore::new(Arc::new(InMemoryVectorStore::new())).with_shards("kb", 4);
+        store.create_collection("kb", 2).await.unwrap();
+        for index in 0..40 {
+            store.insert_document("kb", document(&format!("e{}", index), vec![1.0, 0.0])).await.unwrap();
+        }
+        let query = SearchQuery { embedding: vec![1.0, 0.0], limit: 10 };
+        
+        let (results, partial) = deadline::with_deadline(Some(Instant::now() + Duration::from_secs(60)), store.search("kb", query.clone())).await;
+        assert_eq!(results.unwrap().len(), 10);
+        assert!(!partial);
+        
+        // Every shard sees the deadline has passed and stops scanning
+        let (results, partial) = deadline::with_deadline(Some(Instant::now()), store.search("kb", query)).await;
+        assert!(results.unwrap().is_empty());
+        assert!(partial);
+    }
+}
@@ -0,0 +1,168 @@
+//! A vector store that reports its slow operations.
+//!
+//! Every operation made through the wrapper is timed; those at or above the
+//! [`SlowQueryLog`]'s threshold are logged with their parameters, content
+//! redacted, and the time spent in the store. Tool calls are logged on their
+//! own, with their stages, by the MCP server.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::future::Future;
+use std::sync::Arc;
+
+use super::{
+    BatchItem, CollectionSchema, Document, DocumentPage, KeywordHit, MetadataFilter, MetadataPatch, PatchOutcome, PoolMetrics, SearchQuery,
+    SearchResult, VectorStore, VectorStoreError,
+};
+use crate::logging::slow_query::BACKEND_STAGE;
+use crate::logging::{SlowQueryLog, StageTimer};
+
+/// Wraps a store, logging operations that exceed the slow query threshold
+pub struct TimedVectorStore {
+    inner: Arc<dyn VectorStore>,
+    log: Arc<SlowQueryLog>,
+}
+
+impl TimedVectorStore {
+    pub fn new(inner: Arc<dyn VectorStore>, log: Arc<SlowQueryLog>) -> Self {
+        Self { inner, log }
+    }
+    
+    /// Run `operation`, logging it as `vector_store.<name>` if it was slow
+    async fn timed<T, F>(&self, name: &str, collection: Option<&str>, params: Value, operation: F) -> Result<T, VectorStoreError>
+    where
+        F: Future<Output = Result<T, VectorStoreError>>,
+    {
+        let mut timer = StageTimer::start();
+        let output = operation.await;
+        timer.stage(BACKEND_STAGE);
+        if self.log.is_slow(timer.elapsed()) {
+            self.log.record(&timer.finish(&format!("vector_store.{}", name), collection, &params));
+        }
+        output
+    }
+}
+
+fn filter_params(filter: &MetadataFilter) -> Value {
+    json!({
+        "ids": filter.ids,
+        "tags": filter.tags,
+        "metadata": filter.metadata
+    })
+}
+
+#[async_trait]
+impl VectorStore for TimedVectorStore {
+    async fn test_connection(&self) -> Result<(), VectorStoreError> {
+        self.timed("test_connection", None, json!({}), self.inner.test_connection()).await
+    }
+    
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), VectorStoreError> {
+        self.timed("create_collection", Some(name), json!({"vector_size": vector_size}), self.inner.create_collection(name, vector_size)).await
+    }
+    
+    async fn delete_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+        self.timed("delete_collection", Some(name), json!({}), self.inner.delete_collection(name)).await
+    }
+    
+    async fn insert_document(&self, collection: &str, document: Document) -> Result<(), VectorStoreError> {
+        let params = json!({"id": document.id, "content": document.content});
+        self.timed("insert_document", Some(collection), params, self.inner.insert_document(collection, document)).await
+    }
+    
+    async fn search(&self, collection: &str, query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let params = json!({"limit": query.limit});
+        self.timed("search", Some(collection), params, self.inner.search(collection, query)).await
+    }
+    
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        self.timed("list_collections", None, json!({}), self.inner.list_collections()).await
+    }
+    
+    async fn collection_schema(&self, collection: &str) -> Result<Option<CollectionSchema>, VectorStoreError> {
+        self.timed("collection_schema", Some(collection), json!({}), self.inner.collection_schema(collection)).await
+    }
+    
+    async fn delete_document(&self, collection: &str, id: &str) -> Result<(), VectorStoreError> {
+        self.timed("delete_document", Some(collection), json!({"id": id}), self.inner.delete_document(collection, id)).await
+    }
+    
+    async fn get_document(&self, collection: &str, id: &str) -> Result<Option<Document>, VectorStoreError> {
+        self.timed("get_document", Some(collection), json!({"id": id}), self.inner.get_document(collection, id)).await
+    }
+    
+    async fn list_documents(&self, collection: &str, offset: Option<String>, limit: usize) -> Result<DocumentPage, VectorStoreError> {
+        let params = json!({"offset": offset, "limit": limit});
+        self.timed("list_documents", Some(collection), params, self.inner.list_documents(collection, offset, limit)).await
+    }
+    
+    async fn patch_metadata(&self, collection: &str, filter: &MetadataFilter, patch: &MetadataPatch) -> Result<PatchOutcome, VectorStoreError> {
+        self.timed("patch_metadata", Some(collection), json!({"filter": filter_params(filter)}), self.inner.patch_metadata(collection, filter, patch)).await
+    }
+    
+    async fn batch_insert(&self, collection: &str, documents: Vec<Document>, atomic: bool) -> Result<Vec<BatchItem>, VectorStoreError> {
+        let params = json!({"documents": documents.len(), "atomic": atomic});
+        self.timed("batch_insert", Some(collection), params, self.inner.batch_insert(collection, documents, atomic)).await
+    }
+    
+    async fn count(&self, collection: &str, filter: &MetadataFilter) -> Result<usize, VectorStoreError> {
+        self.timed("count", Some(collection), json!({"filter": filter_params(filter)}), self.inner.count(collection, filter)).await
+    }
+    
+    async fn search_filtered(&self, collection: &str, query: SearchQuery, filter: &MetadataFilter) -> Result<Vec<SearchResult>, VectorStoreError> {
+        let params = json!({"limit": query.limit, "filter": filter_params(filter)});
+        self.timed("search_filtered", Some(collection), params, self.inner.search_filtered(collection, query, filter)).await
+    }
+    
+    async fn keyword_search(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<KeywordHit>, VectorStoreError> {
+        let params = json!({"query": query, "limit": limit});
+        self.timed("keyword_search", Some(collection), params, self.inner.keyword_search(collection, query, limit)).await
+    }
+    
+    async fn exists(&self, collection: &str, id: &str) -> Result<bool, VectorStoreError> {
+        self.timed("exists", Some(collection), json!({"id": id}), self.inner.exists(collection, id)).await
+    }
+    
+    fn pool_metrics(&self) -> Option<PoolMetrics> {
+        self.inner.pool_metrics()
+    }
+    
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::RotatingFile;
+    use crate::vector_store::InMemoryVectorStore;
+    use std::time::Duration;
+    
+    #[tokio::test]
+    async fn test_slow_operations_are_logged_redacted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("slow.log");
+        let log = Arc::new(SlowQueryLog::new(Duration::ZERO).with_file(RotatingFile::open(&path, 0, 1).unwrap()));
+        let store = TimedVectorStore::new(Arc::new(InMemoryVectorStore::new()), log);
+        
+        store.create_collection("docs", 2).await.unwrap();
+        store.insert_document("docs", Document {
+            id: "a".to_string(),
+            content: "private notes".to_string(),
+            embedding: vec![1.0, 0.0],
+            metadata: Default::default(),
+        }).await.unwrap();
+        store.count("docs", &MetadataFilter::default()).await.unwrap();
+        
+        let lines: Vec<Value> = std::fs::read_to_string(&path).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let operations: Vec<&str> = lines.iter().map(|line| line["operation"].as_str().unwrap()).collect();
+        assert_eq!(operations, vec!["vector_store.create_collection", "vector_store.insert_document", "vector_store.count"]);
+        assert_eq!(lines[1]["collection"], "docs");
+        assert_eq!(lines[1]["params"]["content"], "<redacted 13 chars>");
+        assert!(lines[1]["backend_latency_ms"].is_number());
+    }
+}
@@ -0,0 +1,145 @@
+//! Timing the stages of backend operations.
+//!
+//! Each Qdrant call is split into acquiring a pooled connection, building
+//! the request, the network round trip and turning the response back into
+//! documents. Every stage runs in its own child span and is timed; when the
+//! operation ends the breakdown is logged at debug level and handed to
+//! whoever is collecting it with [`collect_stages`], such as a search that
+//! was asked to explain itself.
+
+use serde::Serialize;
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::Instant;
+use tracing::{debug, debug_span, Instrument, Span};
+
+/// Waiting for a connection from the pool
+pub const POOL_STAGE: &str = "pool_acquire";
+
+/// Turning arguments into a backend request
+pub const BUILD_STAGE: &str = "request_build";
+
+/// The round trip to the backend, including its own processing time
+pub const NETWORK_STAGE: &str = "network";
+
+/// Turning the backend's response into documents
+pub const DESERIALIZE_STAGE: &str = "deserialize";
+
+/// Time one backend operation spent in one stage
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BackendStage {
+    /// The backend operation, e.g. `search`
+    pub operation: String,
+    pub stage: String,
+    pub duration_ms: f64,
+}
+
+tokio::task_local! {
+    static COLLECTED: RefCell<Vec<BackendStage>>;
+}
+
+/// Run `future`, also returning the backend stages timed while it ran
+pub async fn collect_stages<F: Future>(future: F) -> (F::Output, Vec<BackendStage>) {
+    COLLECTED.scope(RefCell::new(Vec::new()), async {
+        let output = future.await;
+        let stages = COLLECTED.with(|collected| collected.take());
+        (output, stages)
+    }).await
+}
+
+/// The stage that took longest
+pub fn slowest_stage(stages: &[BackendStage]) -> Option<&BackendStage> {
+    stages.iter().max_by(|a, b| a.duration_ms.total_cmp(&b.duration_ms))
+}
+
+/// Times the stages of a single backend operation, reporting them when dropped
+#[derive(Debug)]
+pub struct OperationTrace {
+    operation: &'static str,
+    span: Span,
+    stages: Vec<BackendStage>,
+}
+
+impl OperationTrace {
+    /// Start tracing `operation` on `collection`
+    pub fn start(operation: &'static str, collection: &str) -> Self {
+        Self {
+            operation,
+            span: debug_span!("qdrant", operation, collection),
+            stages: Vec::new(),
+        }
+    }
+    
+    fn record(&mut self, stage: &'static str, started: Instant) {
+        self.stages.push(BackendStage {
+            operation: self.operation.to_string(),
+            stage: stage.to_string(),
+            duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+        });
+    }
+    
+    /// Run synchronous `work` as `stage`
+    pub fn run<T>(&mut self, stage: &'static str, work: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let output = debug_span!(parent: &self.span, "qdrant.stage", stage).in_scope(work);
+        self.record(stage, started);
+        output
+    }
+    
+    /// Await `future` as `stage`
+    pub async fn wait<F: Future>(&mut self, stage: &'static str, future: F) -> F::Output {
+        let started = Instant::now();
+        let output = future.instrument(debug_span!(parent: &self.span, "qdrant.stage", stage)).await;
+        self.record(stage, started);
+        output
+    }
+}
+
+impl Drop for OperationTrace {
+    fn drop(&mut self) {
+        if self.stages.is_empty() {
+            return;
+        }
+        
+        let total_ms: f64 = self.stages.iter().map(|stage| stage.duration_ms).sum();
+        let breakdown: Vec<String> = self.stages.iter()
+            .map(|stage| format!("{}={:.2}ms", stage.stage, stage.duration_ms))
+            .collect();
+        let slowest = slowest_stage(&self.stages).map(|stage| stage.stage.as_str()).unwrap_or_default();
+        self.span.in_scope(|| debug!(total_ms, slowest, "Backend operation took {}", breakdown.join(" ")));
+        
+        let stages = std::mem::take(&mut self.stages);
+        let _ = COLLECTED.try_with(|collected| collected.borrow_mut().extend(stages));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    #[tokio::test]
+    async fn test_stages_are_collected_in_order() {
+        let ((), stages) = collect_stages(async {
+            let mut trace = OperationTrace::start("search", "docs");
+            trace.wait(POOL_STAGE, async {}).await;
+            trace.run(BUILD_STAGE, || ());
+            trace.wait(NETWORK_STAGE, tokio::time::sleep(Duration::from_millis(20))).await;
+        }).await;
+        
+        let names: Vec<&str> = stages.iter().map(|stage| stage.stage.as_str()).collect();
+        assert_eq!(names, vec![POOL_STAGE, BUILD_STAGE, NETWORK_STAGE]);
+        assert!(stages.iter().all(|stage| stage.operation == "search"));
+        assert_eq!(slowest_stage(&stages).unwrap().stage, NETWORK_STAGE);
+    }
+    
+    #[tokio::test]
+    async fn test_traces_outside_a_collection_are_only_logged() {
+        let mut trace = OperationTrace::start("count", "docs");
+        trace.run(BUILD_STAGE, || ());
+        drop(trace);
+        
+        let ((), stages) = collect_stages(async {}).await;
+        assert!(stages.is_empty());
+    }
+}
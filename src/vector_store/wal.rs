@@ -0,0 +1,897 @@
+//! A write-ahead log keeping the embedded vector store across restarts.
+//!
+//! Every change made through [`WalVectorStore`] is appended to the log, and
+//! flushed to disk as the fsync policy asks, before it is applied and
+//! acknowledged. Opening the store replays the log into the empty in-memory
+//! store, so it comes back to the state of the last logged change after a
+//! restart or crash. A record cut short by a crash is dropped: its change was
+//! never acknowledged. One cut short by a failed write is cut off at once; if
+//! even that fails, the log takes no more changes until it is checkpointed.
+//! Writes and flushes run on the blocking pool, and a change once begun is
+//! logged and made whole even if its caller goes away.
+//!
+//! The log holds one JSON record per line. Batch inserts and metadata patches
+//! are logged as the single inserts they are made of. A change the store
+//! rejects stays logged and is rejected again, the same way, on replay.
+//!
+//! A checkpoint compacts the log into the records that rebuild the same
+//! state, leaving out replaced, deleted and rejected changes, and swaps the
+//! result in, so it reads the log rather than the store. It runs beside
+//! changes, which wait only while the records they logged meanwhile are
+//! copied onto the compacted log. One is started in the background when the
+//! log outgrows `[wal] checkpoint_bytes`, so it does not grow without bound,
+//! and [`Compactor`](super::Compactor) takes one on every maintenance run
+//! that finds the log changed and on demand.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use super::{CollectionSchema, Document, DocumentPage, KeywordHit, MetadataFilter, PoolMetrics, SearchQuery, SearchResult, VectorStore, VectorStoreError};
+use crate::config::{Config, FsyncPolicy, WalConfig};
+
+/// File in the data directory the log is kept in unless configured otherwise
+pub const WAL_FILE: &str = "wal.log";
+
+/// Rejected changes named in a recovery report; the rest are only counted
+const MAX_REPORTED_REJECTIONS: usize = 20;
+
+/// A change to the store, as logged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Operation {
+    CreateCollection { name: String, vector_size: usize },
+    DeleteCollection { name: String },
+    Insert { collection: String, document: Document },
+    Delete { collection: String, id: String },
+}
+
+impl Operation {
+    fn kind(&self) -> &'static str {
+        match self {
+            Operation::CreateCollection { .. } => "create_collection",
+            Operation::DeleteCollection { .. } => "delete_collection",
+            Operation::Insert { .. } => "insert",
+            Operation::Delete { .. } => "delete",
+        }
+    }
+    
+    async fn apply(self, store: &dyn VectorStore) -> Result<(), VectorStoreError> {
+        match self {
+            Operation::CreateCollection { name, vector_size } => store.create_collection(&name, vector_size).await,
+            Operation::DeleteCollection { name } => store.delete_collection(&name).await,
+            Operation::Insert { collection, document } => store.insert_document(&collection, document).await,
+            Operation::Delete { collection, id } => store.delete_document(&collection, &id).await,
+        }
+    }
+}
+
+/// One line of the log
+#[derive(Serialize, Deserialize)]
+struct Record<O> {
+    seq: u64,
+    #[serde(flatten)]
+    operation: O,
+}
+
+/// The line logging `operation` as record `seq`
+fn encode(seq: u64, operation: &Operation) -> serde_json::Result<String> {
+    let mut line = serde_json::to_string(&Record { seq, operation })?;
+    line.push('\n');
+    Ok(line)
+}
+
+/// What replaying the log at startup did
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RecoveryReport {
+    /// Changes replayed into the store, by kind
+    pub replayed: BTreeMap<String, usize>,
+    /// Logged changes the store rejected again, as it did when they were made
+    pub rejected: usize,
+    /// The first rejected changes, with why each was rejected
+    pub rejections: Vec<String>,
+    /// Bytes dropped from the end of the log, holding a record cut short by a crash
+    pub truncated_bytes: u64,
+}
+
+impl RecoveryReport {
+    /// Every logged change read, replayed or rejected
+    pub fn operations(&self) -> usize {
+        self.replayed.values().sum::<usize>() + self.rejected
+    }
+}
+
+impl fmt::Display for RecoveryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kinds: Vec<String> = self.replayed.iter().map(|(kind, count)| format!("{} {}", count, kind)).collect();
+        write!(f, "replayed {} operations", self.replayed.values().sum::<usize>())?;
+        if !kinds.is_empty() {
+            write!(f, " ({})", kinds.join(", "))?;
+        }
+        if self.rejected > 0 {
+            write!(f, ", {} rejected again", self.rejected)?;
+        }
+        if self.truncated_bytes > 0 {
+            write!(f, ", dropped {} bytes of an incomplete record", self.truncated_bytes)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a checkpoint did to the log
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckpointReport {
+    pub records_before: u64,
+    pub records_after: u64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+impl CheckpointReport {
+    /// Bytes the log shrank by
+    pub fn reclaimed_bytes(&self) -> u64 {
+        self.bytes_before.saturating_sub(self.bytes_after)
+    }
+}
+
+impl fmt::Display for CheckpointReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "kept {} of {} records, {} of {} bytes ({} reclaimed)",
+            self.records_after, self.records_before, self.bytes_after, self.bytes_before, self.reclaimed_bytes()
+        )
+    }
+}
+
+/// The open log file
+struct Log {
+    /// Opened for appending, and shared with the blocking tasks writing it
+    file: Arc<File>,
+    path: PathBuf,
+    /// Records in the log, which are numbered from 1
+    seq: u64,
+    /// Bytes in the log
+    len: u64,
+    /// Bytes in the log when it was last checkpointed or opened
+    checkpoint_len: u64,
+    /// Nothing was appended since the log was last checkpointed
+    checkpointed: bool,
+    fsync: FsyncPolicy,
+    /// Written since it was last flushed to disk
+    dirty: bool,
+    /// Why no more records can be appended: a failed append left part of a
+    /// record that could not be cut off again. A checkpoint, which rewrites
+    /// the log without it, clears this.
+    poisoned: Option<String>,
+}
+
+impl Log {
+    /// Append `operation` as the next record. One that fails part way is cut
+    /// off again, so a torn line never sits before later records.
+    async fn append(&mut self, operation: &Operation) -> io::Result<()> {
+        if let Some(reason) = &self.poisoned {
+            return Err(io::Error::other(format!("no more records until the log is checkpointed: {}", reason)));
+        }
+        let line = encode(self.seq + 1, operation)?;
+        let file = self.file.clone();
+        let sync = self.fsync == FsyncPolicy::Always;
+        let written = blocking(move || {
+            (&*file).write_all(line.as_bytes())?;
+            if sync {
+                file.sync_data()?;
+            }
+            Ok(line.len() as u64)
+        }).await;
+        
+        match written {
+            Ok(written) => {
+                self.seq += 1;
+                self.len += written;
+                self.checkpointed = false;
+                self.dirty |= !sync;
+                Ok(())
+            },
+            Err(e) => {
+                let (file, len) = (self.file.clone(), self.len);
+                if let Err(truncate) = blocking(move || file.set_len(len)).await {
+                    self.poisoned = Some(format!("{}, and cutting off the partial record failed: {}", e, truncate));
+                }
+                Err(e)
+            },
+        }
+    }
+    
+    async fn sync(&mut self) -> io::Result<()> {
+        if self.dirty {
+            let file = self.file.clone();
+            blocking(move || file.sync_data()).await?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+    
+    /// Whether the log has outgrown `checkpoint_bytes`, and doubled since it
+    /// was last checkpointed, so a store too big for the bound is not
+    /// rewritten on every change
+    fn needs_checkpoint(&self, checkpoint_bytes: u64) -> bool {
+        checkpoint_bytes > 0 && self.len >= checkpoint_bytes.max(self.checkpoint_len.saturating_mul(2))
+    }
+    
+    /// Copy what was logged after its first `from` bytes onto `compacted`,
+    /// a compaction of those bytes, and swap the result in for the log; a
+    /// crash at any point leaves either the old log or the new one whole
+    async fn swap_in(&mut self, mut compacted: Compacted, from: u64) -> Result<CheckpointReport, VectorStoreError> {
+        let (path, len) = (self.path.clone(), self.len);
+        let compacted = blocking(move || {
+            let checkpoint = checkpoint_path(&path);
+            copy_tail(&path, from, len, &mut compacted)?;
+            std::fs::rename(&checkpoint, &path)?;
+            Ok(compacted)
+        }).await.map_err(|e| wal_error(&self.path, e))?;
+        
+        // The log is now the compacted file, already open for appending, so
+        // nothing below can leave later records going to the replaced one
+        let report = CheckpointReport { records_before: self.seq, records_after: compacted.records, bytes_before: self.len, bytes_after: compacted.len };
+        self.file = Arc::new(compacted.file);
+        self.seq = compacted.records;
+        self.len = compacted.len;
+        self.checkpoint_len = compacted.len;
+        self.checkpointed = true;
+        self.dirty = false;
+        self.poisoned = None;
+        
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()).map(Path::to_path_buf) {
+            blocking(move || File::open(parent)?.sync_all()).await.map_err(|e| wal_error(&self.path, e))?;
+        }
+        Ok(report)
+    }
+}
+
+/// The log, shared with the tasks writing, flushing and checkpointing it
+struct SharedLog {
+    log: Mutex<Log>,
+    /// Held through a checkpoint, so only one runs at a time
+    checkpointing: Mutex<()>,
+}
+
+impl SharedLog {
+    /// Compact the log into the records that rebuild the state it leads to,
+    /// while changes carry on; they wait only while what they logged
+    /// meanwhile is copied after the compacted records and the result is
+    /// swapped in. `None` when `only_if_changed` and nothing was logged since
+    /// the last checkpoint.
+    async fn checkpoint(&self, only_if_changed: bool) -> Result<Option<CheckpointReport>, VectorStoreError> {
+        let _checkpointing = self.checkpointing.lock().await;
+        let (path, from) = {
+            let log = self.log.lock().await;
+            if only_if_changed && log.checkpointed {
+                return Ok(None);
+            }
+            (log.path.clone(), log.len)
+        };
+        
+        let checkpoint = checkpoint_path(&path);
+        let compacted = {
+            let (log, checkpoint) = (path.clone(), checkpoint.clone());
+            blocking(move || compact(&log, from, &checkpoint)).await.map_err(|e| wal_error(&path, e))
+        };
+        let swapped = match compacted {
+            Ok(compacted) => self.log.lock().await.swap_in(compacted, from).await,
+            Err(e) => Err(e),
+        };
+        if swapped.is_err() && checkpoint.exists() {
+            let _ = std::fs::remove_file(&checkpoint);
+        }
+        swapped.map(Some)
+    }
+}
+
+/// Where a checkpoint is written before it replaces the log at `path`
+fn checkpoint_path(path: &Path) -> PathBuf {
+    let mut checkpoint = path.as_os_str().to_owned();
+    checkpoint.push(".checkpoint");
+    PathBuf::from(checkpoint)
+}
+
+fn wal_error(path: &Path, e: impl fmt::Display) -> VectorStoreError {
+    VectorStoreError::OperationFailed(format!("Write-ahead log {}: {}", path.display(), e))
+}
+
+/// Run file work on the blocking pool rather than the async runtime
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> io::Result<T> + Send + 'static) -> io::Result<T> {
+    tokio::task::spawn_blocking(work).await?
+}
+
+/// Run `work` to the end even if its caller stops waiting, so a change or
+/// checkpoint is never left half done between the log and the store
+async fn detached<T: Send + 'static>(work: impl Future<Output = Result<T, VectorStoreError>> + Send + 'static) -> Result<T, VectorStoreError> {
+    tokio::spawn(work).await
+        .map_err(|e| VectorStoreError::OperationFailed(format!("Write-ahead log task failed: {}", e)))?
+}
+
+/// A compacted log, open for appending
+struct Compacted {
+    file: File,
+    records: u64,
+    len: u64,
+}
+
+/// Copy the records among the first `len` bytes of the log at `path` that
+/// still count to `checkpoint`, renumbered and flushed to disk
+fn compact(path: &Path, len: u64, checkpoint: &Path) -> io::Result<Compacted> {
+    let mut keep = live_records(BufReader::new(File::open(path)?.take(len)))?;
+    keep.sort_unstable();
+    let mut keep = keep.into_iter().peekable();
+    
+    let file = OpenOptions::new().create(true).append(true).open(checkpoint)?;
+    file.set_len(0)?;
+    let mut writer = BufWriter::new(&file);
+    let (mut records, mut written) = (0, 0);
+    for (index, line) in BufReader::new(File::open(path)?.take(len)).lines().enumerate() {
+        let Some(&next) = keep.peek() else {
+            break;
+        };
+        let line = line?;
+        if index != next {
+            continue;
+        }
+        keep.next();
+        let record: Record<Operation> = serde_json::from_str(&line)?;
+        records += 1;
+        let line = encode(records, &record.operation)?;
+        writer.write_all(line.as_bytes())?;
+        written += line.len() as u64;
+    }
+    writer.flush()?;
+    drop(writer);
+    file.sync_all()?;
+    Ok(Compacted { file, records, len: written })
+}
+
+/// Which of the records in `log`, by line, rebuild the state replaying all
+/// of them leads to, under the embedded store's rules: the creation of each
+/// collection left, and the last insert of each document left in it.
+/// Changes the store rejects, and changes undone later, are left out.
+fn live_records(log: impl BufRead) -> io::Result<Vec<usize>> {
+    struct Live {
+        vector_size: usize,
+        created: usize,
+        documents: HashMap<String, usize>,
+    }
+    
+    let mut collections: HashMap<String, Live> = HashMap::new();
+    for (index, line) in log.lines().enumerate() {
+        let record: Record<Operation> = serde_json::from_str(&line?)?;
+        match record.operation {
+            Operation::CreateCollection { name, vector_size } => {
+                // Creating a collection that exists is rejected
+                collections.entry(name).or_insert_with(|| Live { vector_size, created: index, documents: HashMap::new() });
+            },
+            Operation::DeleteCollection { name } => {
+                collections.remove(&name);
+            },
+            Operation::Insert { collection, document } => {
+                if let Some(live) = collections.get_mut(&collection).filter(|live| live.vector_size == document.embedding.len()) {
+                    live.documents.insert(document.id, index);
+                }
+            },
+            Operation::Delete { collection, id } => {
+                if let Some(live) = collections.get_mut(&collection) {
+                    live.documents.remove(&id);
+                }
+            },
+        }
+    }
+    Ok(collections.into_values()
+        .flat_map(|live| std::iter::once(live.created).chain(live.documents.into_values()))
+        .collect())
+}
+
+/// Append the records between bytes `from` and `to` of the log at `path` to
+/// `compacted`, numbered on from its own, and flush them to disk
+fn copy_tail(path: &Path, from: u64, to: u64, compacted: &mut Compacted) -> io::Result<()> {
+    if to == from {
+        return Ok(());
+    }
+    let mut log = File::open(path)?;
+    log.seek(SeekFrom::Start(from))?;
+    let mut writer = BufWriter::new(&compacted.file);
+    for line in BufReader::new(log.take(to - from)).lines() {
+        let record: Record<Operation> = serde_json::from_str(&line?)?;
+        let line = encode(compacted.records + 1, &record.operation)?;
+        writer.write_all(line.as_bytes())?;
+        compacted.records += 1;
+        compacted.len += line.len() as u64;
+    }
+    writer.flush()?;
+    drop(writer);
+    compacted.file.sync_all()
+}
+
+/// Wraps the embedded store, logging each change before making it
+pub struct WalVectorStore {
+    inner: Arc<dyn VectorStore>,
+    shared: Arc<SharedLog>,
+    path: PathBuf,
+    /// Checkpoint once the log grows past this many bytes; 0 never does
+    checkpoint_bytes: u64,
+}
+
+impl WalVectorStore {
+    /// Open the log at `path`, creating it if need be, and replay it into
+    /// `inner`, which should start empty.
+    ///
+    /// With [`FsyncPolicy::Interval`] a background task flushes the log every
+    /// `fsync_interval` until the store is dropped.
+    pub async fn open(
+        inner: Arc<dyn VectorStore>,
+        path: &Path,
+        fsync: FsyncPolicy,
+        fsync_interval: Duration,
+    ) -> Result<(Self, RecoveryReport), VectorStoreError> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| wal_error(path, e))?;
+        }
+        let (records, valid_len) = read_records(path)?;
+        
+        let mut report = RecoveryReport::default();
+        let mut seq = 0;
+        for record in records {
+            seq = record.seq;
+            let kind = record.operation.kind();
+            match record.operation.apply(inner.as_ref()).await {
+                Ok(()) => *report.replayed.entry(kind.to_string()).or_default() += 1,
+                Err(e) => {
+                    if report.rejections.len() < MAX_REPORTED_REJECTIONS {
+                        report.rejections.push(format!("#{} {}: {}", record.seq, kind, e));
+                    }
+                    report.rejected += 1;
+                },
+            }
+        }
+        
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| wal_error(path, e))?;
+        let len = file.metadata().map_err(|e| wal_error(path, e))?.len();
+        if len > valid_len {
+            file.set_len(valid_len).map_err(|e| wal_error(path, e))?;
+            file.sync_data().map_err(|e| wal_error(path, e))?;
+            report.truncated_bytes = len - valid_len;
+        }
+        
+        let log = Log {
+            file: Arc::new(file),
+            path: path.to_path_buf(),
+            seq,
+            len: valid_len,
+            checkpoint_len: valid_len,
+            checkpointed: false,
+            fsync,
+            dirty: false,
+            poisoned: None,
+        };
+        let shared = Arc::new(SharedLog { log: Mutex::new(log), checkpointing: Mutex::new(()) });
+        if fsync == FsyncPolicy::Interval {
+            spawn_syncer(Arc::downgrade(&shared), fsync_interval, path.to_path_buf());
+        }
+        Ok((Self { inner, shared, path: path.to_path_buf(), checkpoint_bytes: 0 }, report))
+    }
+    
+    /// Checkpoint the log whenever it grows past `bytes`, and past twice its
+    /// size after the last checkpoint; the change that crosses the bound
+    /// starts the checkpoint in the background
+    pub fn with_checkpoint_bytes(mut self, bytes: u64) -> Self {
+        self.checkpoint_bytes = bytes;
+        self
+    }
+    
+    /// Wrap `inner` as `config` asks; `None` when the log is disabled
+    pub async fn from_config(inner: Arc<dyn VectorStore>, config: &WalConfig) -> Result<Option<(Self, RecoveryReport)>, VectorStoreError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let path = config.path.clone().unwrap_or_else(|| Config::data_dir().join(WAL_FILE));
+        let (store, report) = Self::open(inner, &path, config.fsync, Duration::from_millis(config.fsync_interval_ms.max(1))).await?;
+        Ok(Some((store.with_checkpoint_bytes(config.checkpoint_bytes), report)))
+    }
+    
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// Log `operation`, then make the change with `apply`; changes are
+    /// logged and made one at a time, so replay makes them in the same order
+    async fn logged<T: Send + 'static>(
+        &self,
+        operation: Operation,
+        apply: impl Future<Output = Result<T, VectorStoreError>> + Send + 'static,
+    ) -> Result<T, VectorStoreError> {
+        let (shared, checkpoint_bytes) = (self.shared.clone(), self.checkpoint_bytes);
+        detached(async move {
+            let mut log = shared.log.lock().await;
+            log.append(&operation).await.map_err(|e| wal_error(&log.path, e))?;
+            let result = apply.await;
+            if log.needs_checkpoint(checkpoint_bytes) {
+                // Ask again once the log has doubled, however this one goes,
+                // rather than on the next change
+                log.checkpoint_len = log.len;
+                let path = log.path.clone();
+                drop(log);
+                tokio::spawn(async move {
+                    match shared.checkpoint(false).await {
+                        Ok(Some(report)) => info!(path = %path.display(), "Checkpointed the write-ahead log: {}", report),
+                        Ok(None) => {},
+                        Err(e) => warn!(path = %path.display(), "Failed to checkpoint the write-ahead log: {}", e),
+                    }
+                });
+            }
+            result
+        }).await
+    }
+    
+    /// Rewrite the log as the records that rebuild the store as it is now,
+    /// dropping replaced, deleted and rejected changes. Reads never wait for
+    /// it, and changes only while it copies what they logged meanwhile.
+    pub async fn checkpoint(&self) -> Result<CheckpointReport, VectorStoreError> {
+        let shared = self.shared.clone();
+        detached(async move { shared.checkpoint(false).await }).await
+            .map(|report| report.expect("an unconditional checkpoint always runs"))
+    }
+    
+    /// [`checkpoint`](Self::checkpoint), unless nothing was logged since the
+    /// last one; `None` when it was skipped
+    pub async fn checkpoint_if_changed(&self) -> Result<Option<CheckpointReport>, VectorStoreError> {
+        let shared = self.shared.clone();
+        detached(async move { shared.checkpoint(true).await }).await
+    }
+}
+
+/// Flush the log every `interval` while the store holding it is alive
+fn spawn_syncer(shared: std::sync::Weak<SharedLog>, interval: Duration, path: PathBuf) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let Some(shared) = shared.upgrade() else {
+                return;
+            };
+            let mut log = shared.log.lock().await;
+            if let Err(e) = log.sync().await {
+                warn!(path = %path.display(), "Failed to flush the write-ahead log: {}", e);
+            }
+        }
+    });
+}
+
+/// The complete records of the log at `path`, and the length of the log
+/// they fill; a missing log has none
+fn read_records(path: &Path) -> Result<(Vec<Record<Operation>>, u64), VectorStoreError> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+        Err(e) => return Err(wal_error(path, e)),
+    };
+    
+    let mut records = Vec::new();
+    let mut valid_len = 0;
+    let mut lines = bytes.split_inclusive(|byte| *byte == b'\n').peekable();
+    while let Some(line) = lines.next() {
+        // A record is acknowledged only once its whole line is written
+        if !line.ends_with(b"\n") {
+            break;
+        }
+        match serde_json::from_slice::<Record<Operation>>(line) {
+            Ok(record) => records.push(record),
+            Err(_) if lines.peek().is_none() => break,
+            Err(e) => return Err(wal_error(path, format!("record {} is corrupt: {}", records.len() + 1, e))),
+        }
+        valid_len += line.len() as u64;
+    }
+    Ok((records, valid_len))
+}
+
+#[async_trait]
+impl VectorStore for WalVectorStore {
+    async fn test_connection(&self) -> Result<(), VectorStoreError> {
+        self.inner.test_connection().await
+    }
+    
+    async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), VectorStoreError> {
+        let (inner, name) = (self.inner.clone(), name.to_string());
+        let operation = Operation::CreateCollection { name: name.clone(), vector_size };
+        self.logged(operation, async move { inner.create_collection(&name, vector_size).await }).await
+    }
+    
+    async fn delete_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+        let (inner, name) = (self.inner.clone(), name.to_string());
+        let operation = Operation::DeleteCollection { name: name.clone() };
+        self.logged(operation, async move { inner.delete_collection(&name).await }).await
+    }
+    
+    async fn insert_document(&self, collection: &str, document: Document) -> Result<(), VectorStoreError> {
+        let (inner, collection) = (self.inner.clone(), collection.to_string());
+        let operation = Operation::Insert { collection: collection.clone(), document: document.clone() };
+        self.logged(operation, async move { inner.insert_document(&collection, document).await }).await
+    }
+    
+    async fn search(&self, collection: &str, query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.inner.search(collection, query).await
+    }
+    
+    async fn list_collections(&self) -> Result<Vec<String>, VectorStoreError> {
+        self.inner.list_collections().await
+    }
+    
+    async fn collection_schema(&self, collection: &str) -> Result<Option<CollectionSchema>, VectorStoreError> {
+        self.inner.collection_schema(collection).await
+    }
+    
+    async fn delete_document(&self, collection: &str, id: &str) -> Result<(), VectorStoreError> {
+        let (inner, collection, id) = (self.inner.clone(), collection.to_string(), id.to_string());
+        let operation = Operation::Delete { collection: collection.clone(), id: id.clone() };
+        self.logged(operation, async move { inner.delete_document(&collection, &id).await }).await
+    }
+    
+    async fn get_document(&self, collection: &str, id: &str) -> Result<Option<Document>, VectorStoreError> {
+        self.inner.get_document(collection, id).await
+    }
+    
+    async fn list_documents(&self, collection: &str, offset: Option<String>, limit: usize) -> Result<DocumentPage, VectorStoreError> {
+        self.inner.list_documents(collection, offset, limit).await
+    }
+    
+    async fn count(&self, collection: &str, filter: &MetadataFilter) -> Result<usize, VectorStoreError> {
+        self.inner.count(collection, filter).await
+    }
+    
+    async fn search_filtered(&self, collection: &str, query: SearchQuery, filter: &MetadataFilter) -> Result<Vec<SearchResult>, VectorStoreError> {
+        self.inner.search_filtered(collection, query, filter).await
+    }
+    
+    async fn keyword_search(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<KeywordHit>, VectorStoreError> {
+        self.inner.keyword_search(collection, query, limit).await
+    }
+    
+    async fn exists(&self, collection: &str, id: &str) -> Result<bool, VectorStoreError> {
+        self.inner.exists(collection, id).await
+    }
+    
+    fn pool_metrics(&self) -> Option<PoolMetrics> {
+        self.inner.pool_metrics()
+    }
+    
+    fn backend_name(&self) -> &'static str {
+        self.inner.backend_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::InMemoryVectorStore;
+    
+    fn document(id: &str) -> Document {
+        let mut document = Document::with_placeholder_embedding(format!("content of {}", id), 2);
+        document.id = id.to_string();
+        document
+    }
+    
+    async fn open(path: &Path) -> (Arc<InMemoryVectorStore>, WalVectorStore, RecoveryReport) {
+        let inner = Arc::new(InMemoryVectorStore::new());
+        let (store, report) = WalVectorStore::open(inner.clone(), path, FsyncPolicy::Always, Duration::from_secs(1)).await.unwrap();
+        (inner, store, report)
+    }
+    
+    #[tokio::test]
+    async fn test_changes_survive_reopening() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(WAL_FILE);
+        
+        let (_, store, report) = open(&path).await;
+        assert_eq!(report, RecoveryReport::default());
+        store.create_collection("docs", 2).await.unwrap();
+        store.insert_document("docs", document("a")).await.unwrap();
+        store.insert_document("docs", document("b")).await.unwrap();
+        store.delete_document("docs", "a").await.unwrap();
+        assert!(store.insert_document("missing", document("c")).await.is_err());
+        drop(store);
+        
+        let (inner, _store, report) = open(&path).await;
+        assert_eq!(report.replayed, BTreeMap::from([
+            ("create_collection".to_string(), 1),
+            ("delete".to_string(), 1),
+            ("insert".to_string(), 2),
+        ]));
+        assert_eq!(report.rejected, 1);
+        assert!(inner.get_document("docs", "a").await.unwrap().is_none());
+        assert_eq!(inner.get_document("docs", "b").await.unwrap().unwrap().content, "content of b");
+    }
+    
+    #[tokio::test]
+    async fn test_incomplete_record_is_dropped() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(WAL_FILE);
+        let (_, store, _) = open(&path).await;
+        store.create_collection("docs", 2).await.unwrap();
+        drop(store);
+        let complete = std::fs::metadata(&path).unwrap().len();
+        
+        // A crash part way through writing the next record
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"seq\":2,\"op\":\"ins").unwrap();
+        let (inner, store, report) = open(&path).await;
+        assert_eq!(report.truncated_bytes, 18);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), complete);
+        assert_eq!(inner.list_collections().await.unwrap(), vec!["docs".to_string()]);
+        
+        // Later records follow on from the last complete one
+        store.insert_document("docs", document("a")).await.unwrap();
+        drop(store);
+        let (inner, _store, report) = open(&path).await;
+        assert_eq!(report.operations(), 2);
+        assert!(inner.exists("docs", "a").await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_checkpoint_keeps_only_the_live_state() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(WAL_FILE);
+        let (_, store, _) = open(&path).await;
+        store.create_collection("docs", 2).await.unwrap();
+        store.create_collection("scratch", 2).await.unwrap();
+        store.insert_document("docs", document("a")).await.unwrap();
+        store.insert_document("docs", document("b")).await.unwrap();
+        store.insert_document("docs", document("b")).await.unwrap();
+        store.delete_document("docs", "a").await.unwrap();
+        store.delete_collection("scratch").await.unwrap();
+        assert!(store.insert_document("missing", document("c")).await.is_err());
+        let before = std::fs::metadata(&path).unwrap().len();
+        
+        let report = store.checkpoint().await.unwrap();
+        assert_eq!((report.records_before, report.records_after), (8, 2));
+        assert_eq!(report.bytes_before, before);
+        assert_eq!(report.bytes_after, std::fs::metadata(&path).unwrap().len());
+        assert!(report.reclaimed_bytes() > 0);
+        assert!(!checkpoint_path(&path).exists());
+        
+        // Changes after the checkpoint are appended to it
+        store.insert_document("docs", document("d")).await.unwrap();
+        drop(store);
+        let (inner, _store, report) = open(&path).await;
+        assert_eq!(report.operations(), 3);
+        assert_eq!(report.rejected, 0);
+        assert_eq!(inner.list_collections().await.unwrap(), vec!["docs".to_string()]);
+        assert!(!inner.exists("docs", "a").await.unwrap());
+        assert!(inner.exists("docs", "b").await.unwrap());
+        assert!(inner.exists("docs", "d").await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_checkpoint_replays_to_the_same_state() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(WAL_FILE);
+        let (inner, store, _) = open(&path).await;
+        store.create_collection("docs", 2).await.unwrap();
+        assert!(store.create_collection("docs", 3).await.is_err());
+        store.insert_document("docs", document("a")).await.unwrap();
+        let mut wide = document("b");
+        wide.embedding.push(0.0);
+        assert!(store.insert_document("docs", wide.clone()).await.is_err());
+        store.delete_collection("docs").await.unwrap();
+        store.create_collection("docs", 3).await.unwrap();
+        store.insert_document("docs", wide).await.unwrap();
+        store.create_collection("notes", 2).await.unwrap();
+        store.insert_document("notes", document("a")).await.unwrap();
+        store.delete_document("notes", "a").await.unwrap();
+        store.delete_document("notes", "missing").await.unwrap();
+        store.insert_document("notes", document("c")).await.unwrap();
+        
+        let report = store.checkpoint().await.unwrap();
+        assert_eq!((report.records_before, report.records_after), (12, 4));
+        drop(store);
+        let (replayed, _store, report) = open(&path).await;
+        assert_eq!(report.rejected, 0);
+        for collection in ["docs", "notes"] {
+            assert_eq!(replayed.collection_schema(collection).await.unwrap(), inner.collection_schema(collection).await.unwrap());
+            let ids = |page: DocumentPage| page.documents.into_iter().map(|document| document.id).collect::<Vec<_>>();
+            assert_eq!(
+                ids(replayed.list_documents(collection, None, 10).await.unwrap()),
+                ids(inner.list_documents(collection, None, 10).await.unwrap()),
+            );
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_failed_append_stops_the_log_until_checkpointed() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(WAL_FILE);
+        let (inner, store, _) = open(&path).await;
+        store.create_collection("docs", 2).await.unwrap();
+        
+        // A handle that can neither write the record nor cut it off again
+        store.shared.log.lock().await.file = Arc::new(File::open(&path).unwrap());
+        assert!(store.insert_document("docs", document("a")).await.is_err());
+        let refused = store.insert_document("docs", document("b")).await.unwrap_err();
+        assert!(refused.to_string().contains("until the log is checkpointed"), "{}", refused);
+        assert_eq!(inner.count("docs", &MetadataFilter::default()).await.unwrap(), 0);
+        
+        store.checkpoint().await.unwrap();
+        store.insert_document("docs", document("c")).await.unwrap();
+        drop(store);
+        let (inner, _store, report) = open(&path).await;
+        assert_eq!(report.operations(), 2);
+        assert!(inner.exists("docs", "c").await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_unchanged_log_is_not_checkpointed_again() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let (_, store, _) = open(&temp_dir.path().join(WAL_FILE)).await;
+        store.create_collection("docs", 2).await.unwrap();
+        
+        assert!(store.checkpoint_if_changed().await.unwrap().is_some());
+        assert_eq!(store.checkpoint_if_changed().await.unwrap(), None);
+        store.insert_document("docs", document("a")).await.unwrap();
+        assert_eq!(store.checkpoint_if_changed().await.unwrap().unwrap().records_after, 2);
+    }
+    
+    #[tokio::test]
+    async fn test_log_is_checkpointed_as_it_grows() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(WAL_FILE);
+        let (_, store, _) = open(&path).await;
+        let store = store.with_checkpoint_bytes(1024);
+        store.create_collection("docs", 2).await.unwrap();
+        for _ in 0..100 {
+            store.insert_document("docs", document("a")).await.unwrap();
+        }
+        
+        // Replacing one entry over and over keeps the log within twice the
+        // bound, once the checkpoints started in the background are done
+        for _ in 0..500 {
+            if std::fs::metadata(&path).unwrap().len() < 2048 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(std::fs::metadata(&path).unwrap().len() < 2048);
+        drop(store.shared.checkpointing.lock().await);
+        drop(store);
+        let (inner, _store, report) = open(&path).await;
+        assert!(report.operations() < 100);
+        assert_eq!(inner.count("docs", &MetadataFilter::default()).await.unwrap(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_changes_made_during_a_checkpoint_are_kept() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(WAL_FILE);
+        let (_, store, _) = open(&path).await;
+        store.create_collection("docs", 2).await.unwrap();
+        for index in 0..200 {
+            store.insert_document("docs", document(&format!("old-{}", index))).await.unwrap();
+            store.delete_document("docs", &format!("old-{}", index)).await.unwrap();
+        }
+        
+        let writes = async {
+            for index in 0..50 {
+                store.insert_document("docs", document(&format!("new-{}", index))).await.unwrap();
+            }
+        };
+        let (report, ()) = tokio::join!(store.checkpoint(), writes);
+        let report = report.unwrap();
+        assert!(report.records_after <= 51);
+        
+        drop(store);
+        let (inner, _store, report) = open(&path).await;
+        assert_eq!(report.rejected, 0);
+        assert_eq!(inner.count("docs", &MetadataFilter::default()).await.unwrap(), 50);
+    }
+}
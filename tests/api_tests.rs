@@ -1,10 +1,14 @@
 #[cfg(test)]
 mod api_tests {
+    use p_mo::api::ApiState;
+    use p_mo::logging::RetrievalLog;
     use p_mo::server::{Server, ServerConfig};
+    use p_mo::vector_store::{Document, InMemoryVectorStore, VectorStore};
     use reqwest::Client;
-    use serde_json::json;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
     use std::time::Duration;
-
+    
     #[tokio::test]
     async fn test_api_basic_operations() {
         // Start server
@@ -15,6 +19,8 @@ mod api_tests {
             daemon: false,
             pid_file: None,
             log_file: None,
+            logging: Default::default(),
+            container: false,
         };
         
         let server = Server::new(config);
@@ -54,4 +60,628 @@ mod api_tests {
         // Cleanup
         handle.shutdown().await.expect("Failed to shutdown server");
     }
+    
+    #[tokio::test]
+    async fn test_search_export() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("notes", 384).await.unwrap();
+        let document = Document {
+            id: "entry-1".to_string(),
+            content: "Rust ownership, borrowing and lifetimes".to_string(),
+            embedding: vec![0.0; 384],
+            metadata: Default::default(),
+        }
+        .with_title("Ownership")
+        .with_tags(&["rust".to_string()]);
+        store.insert_document("notes", document).await.unwrap();
+        
+        let config = ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8084,
+            timeout: Duration::from_secs(30),
+            daemon: false,
+            pid_file: None,
+            log_file: None,
+            logging: Default::default(),
+            container: false,
+        };
+        let retrieval_log = Arc::new(RetrievalLog::new(10));
+        let server = Server::new(config).with_api(ApiState::new(store).with_retrieval_log(retrieval_log.clone()));
+        let handle = server.start().await.expect("Failed to start server");
+        
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let client = Client::new();
+        let url = "http://127.0.0.1:8084/api/search?q=rust&collection=notes";
+        
+        let body: Value = client.get(url).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["results"][0]["title"], "Ownership");
+        assert_eq!(body["results"][0]["source"], "notes#entry-1");
+        let record = retrieval_log.get(body["retrieval_id"].as_str().unwrap()).unwrap();
+        assert_eq!(record.result["results"], body["results"]);
+        
+        let response = client.get(format!("{}&format=csv", url)).send().await.unwrap();
+        assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/csv"));
+        assert!(retrieval_log.get(response.headers()["x-retrieval-id"].to_str().unwrap()).is_some());
+        let csv = response.text().await.unwrap();
+        assert!(csv.starts_with("title,snippet,score,tags,source\r\n"));
+        assert!(csv.contains("Ownership,\"**Rust** ownership, borrowing and lifetimes\","));
+        
+        let markdown = client.get(format!("{}&format=markdown", url)).send().await.unwrap().text().await.unwrap();
+        assert!(markdown.contains("| Ownership |"));
+        assert!(markdown.contains("`notes#entry-1`"));
+        
+        handle.shutdown().await.expect("Failed to shutdown server");
+    }
+    
+    #[tokio::test]
+    async fn test_count_and_exists() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("notes", 384).await.unwrap();
+        for (id, tag) in [("a", "rust"), ("b", "rust"), ("c", "go")] {
+            let document = Document {
+                id: id.to_string(),
+                content: format!("Entry {}", id),
+                embedding: vec![0.0; 384],
+                metadata: Default::default(),
+            }
+            .with_tags(&[tag.to_string()]);
+            store.insert_document("notes", document).await.unwrap();
+        }
+        
+        let config = ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8086,
+            timeout: Duration::from_secs(30),
+            daemon: false,
+            pid_file: None,
+            log_file: None,
+            logging: Default::default(),
+            container: false,
+        };
+        let server = Server::new(config).with_api(ApiState::new(store));
+        let handle = server.start().await.expect("Failed to start server");
+        
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let client = Client::new();
+        let base = "http://127.0.0.1:8086/api/collections/notes";
+        
+        let body: Value = client.get(format!("{}/count", base)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["count"], 3);
+        let body: Value = client.get(format!("{}/count?tags=rust", base)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["count"], 2);
+        let response = client.get(format!("{}/count?owner=sre", base)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 400);
+        
+        let body: Value = client.get(format!("{}/entries/a/exists", base)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["exists"], true);
+        let body: Value = client.get(format!("{}/entries/zzz/exists", base)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["exists"], false);
+        
+        handle.shutdown().await.expect("Failed to shutdown server");
+    }
+    
+    #[tokio::test]
+    async fn test_router_mounts_in_another_app() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("notes", 384).await.unwrap();
+        
+        // A host application nesting the API under its own prefix and middleware
+        let app = axum::Router::new()
+            .route("/", axum::routing::get(|| async { "host" }))
+            .nest("/knowledge", p_mo::api::router(ApiState::new(store)))
+            .layer(axum::middleware::from_fn(|request: axum::http::Request<axum::body::Body>, next: axum::middleware::Next<axum::body::Body>| async move {
+                let mut response = next.run(request).await;
+                response.headers_mut().insert("x-host", axum::http::HeaderValue::from_static("yes"));
+                response
+            }));
+        
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        
+        let response = Client::new()
+            .get(format!("http://{}/knowledge/api/collections/notes/count", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["x-host"], "yes");
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["count"], 0);
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_change_feed_long_polls() {
+        use p_mo::events::ChangeFeed;
+        use p_mo::vector_store::EventedVectorStore;
+        
+        let feed = Arc::new(ChangeFeed::default());
+        let store = Arc::new(EventedVectorStore::new(Arc::new(InMemoryVectorStore::new()), feed.clone()));
+        store.create_collection("notes", 2).await.unwrap();
+        
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = p_mo::api::router(ApiState::new(store.clone()).with_change_feed(feed));
+        let server = tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        let client = Client::new();
+        
+        let body: Value = client.get(format!("http://{}/api/changes", addr)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["events"][0]["kind"], "collection_created");
+        assert_eq!(body["reset"], false);
+        let cursor = body["cursor"].as_str().unwrap().to_string();
+        
+        // A long poll returns as soon as a change is made
+        let writer = store.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let mut document = Document::with_placeholder_embedding("note".to_string(), 2);
+            document.id = "note-1".to_string();
+            writer.insert_document("notes", document).await.unwrap();
+        });
+        let body: Value = client.get(format!("http://{}/api/changes?since={}&wait=10", addr, cursor))
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["events"].as_array().unwrap().len(), 1);
+        assert_eq!(body["events"][0]["kind"], "upserted");
+        assert_eq!(body["events"][0]["id"], "note-1");
+        
+        let response = client.get(format!("http://{}/api/changes?since=bogus", addr)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 400);
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_ingest_acknowledges_each_line() {
+        use p_mo::mcp::{ProgmoMcpServer, ServerConfig as McpServerConfig};
+        use p_mo::text_processing::PiiPolicy;
+        use p_mo::config::{PiiAction, PiiConfig};
+        
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("notes", 384).await.unwrap();
+        let pii = PiiPolicy::from_config(&PiiConfig { enabled: true, action: PiiAction::Mask, ..PiiConfig::default() });
+        let mcp = Arc::new(ProgmoMcpServer::new(McpServerConfig { name: "p-mo".to_string(), version: "1.2.3".to_string() }, store.clone()).with_pii_policy(pii));
+        
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = p_mo::api::router(ApiState::new(store.clone()).with_mcp_server(mcp));
+        let server = tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        
+        let body = [
+            r#"{"id": "first", "title": "First", "content": "Rollbacks need a ticket", "tags": ["ops"]}"#,
+            "",
+            r#"{"content": ""}"#,
+            r#"{"id": "third", "content": "Deploys run on Tuesdays, ask alice@example.com"}"#,
+        ].join("\n");
+        let response = Client::new()
+            .post(format!("http://{}/api/ingest?collection=notes&progress_every=2", addr))
+            .header("content-type", "application/x-ndjson")
+            .body(body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+        let frames: Vec<Value> = response.text().await.unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        
+        assert_eq!(frames[0], json!({"line": 1, "status": "ingested", "id": "first"}));
+        assert_eq!(frames[1]["line"], 3);
+        assert_eq!(frames[1]["status"], "failed");
+        assert_eq!(frames[2]["progress"]["ingested"], 1);
+        assert_eq!(frames[2]["progress"]["failed"], 1);
+        assert_eq!(frames[3], json!({"line": 4, "status": "ingested", "id": "third"}));
+        let done = &frames[4]["done"];
+        assert_eq!(done["lines"], 4);
+        assert_eq!(done["ingested"], 2);
+        assert_eq!(done["complete"], true);
+        
+        let stored = store.get_document("notes", "first").await.unwrap().unwrap();
+        assert_eq!(stored.title(), Some("First"));
+        // Lines go through the same pipeline as add_knowledge_entry
+        let third = store.get_document("notes", "third").await.unwrap().unwrap();
+        assert!(third.title().unwrap().starts_with("Deploys run on Tuesdays"));
+        assert!(third.content.contains("[REDACTED:email]"));
+        assert!(!third.content.contains("alice@example.com"));
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_ingest_acknowledges_lines_before_the_body_ends() {
+        use p_mo::mcp::{ProgmoMcpServer, ServerConfig as McpServerConfig};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("notes", 384).await.unwrap();
+        let mcp = Arc::new(ProgmoMcpServer::new(McpServerConfig { name: "p-mo".to_string(), version: "1.2.3".to_string() }, store.clone()));
+        
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = p_mo::api::router(ApiState::new(store.clone()).with_mcp_server(mcp));
+        let server = tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        
+        // A chunked upload, written one line at a time
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let head = format!(
+            "POST /api/ingest?collection=notes HTTP/1.1\r\nhost: {}\r\ncontent-type: application/x-ndjson\r\ntransfer-encoding: chunked\r\n\r\n",
+            addr
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        let chunk = |line: &str| format!("{:x}\r\n{}\r\n", line.len(), line);
+        
+        // Reads the response until `expected` has streamed back
+        async fn read_until(stream: &mut tokio::net::TcpStream, received: &mut String, expected: &str) {
+            let mut buffer = [0u8; 4096];
+            while !received.contains(expected) {
+                let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buffer))
+                    .await
+                    .expect("no acknowledgement streamed back")
+                    .unwrap();
+                assert!(read > 0, "connection closed before {}", expected);
+                received.push_str(&String::from_utf8_lossy(&buffer[..read]));
+            }
+        }
+        let mut received = String::new();
+        
+        stream.write_all(chunk("{\"id\": \"first\", \"content\": \"Rollbacks need a ticket\"}\n").as_bytes()).await.unwrap();
+        read_until(&mut stream, &mut received, r#""id":"first""#).await;
+        assert!(received.starts_with("HTTP/1.1 200"));
+        assert!(received.contains("application/x-ndjson"));
+        assert!(!received.contains("done"));
+        
+        stream.write_all(chunk("{\"id\": \"second\", \"content\": \"Deploys run on Tuesdays\"}\n").as_bytes()).await.unwrap();
+        read_until(&mut stream, &mut received, r#""id":"second""#).await;
+        assert!(!received.contains("done"));
+        
+        stream.write_all(b"0\r\n\r\n").await.unwrap();
+        read_until(&mut stream, &mut received, r#""complete":true"#).await;
+        let done = received.lines()
+            .find(|line| line.contains("done"))
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .unwrap();
+        assert_eq!(done["done"]["ingested"], 2);
+        assert!(store.get_document("notes", "second").await.unwrap().is_some());
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_resumable_upload_attaches_to_entry() {
+        use p_mo::attachments::{Attachments, DiskAttachmentStore};
+        use p_mo::uploads::UploadSessions;
+        
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("notes", 384).await.unwrap();
+        let mut document = Document::with_placeholder_embedding("Runbook".to_string(), 384);
+        document.id = "runbook".to_string();
+        store.insert_document("notes", document).await.unwrap();
+        
+        let attachments = Arc::new(Attachments::new(Arc::new(DiskAttachmentStore::new(temp_dir.path().join("attachments")))));
+        let uploads = Arc::new(UploadSessions::new(temp_dir.path().join("uploads")));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = p_mo::api::router(ApiState::new(store.clone()).with_attachments(attachments).with_uploads(uploads));
+        let server = tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        let client = Client::new();
+        
+        let pdf = b"%PDF-1.7 runbook contents".to_vec();
+        let response = client.post(format!("http://{}/api/collections/notes/entries/runbook/uploads?filename=runbook.pdf", addr))
+            .header("content-type", "application/pdf")
+            .header("upload-length", pdf.len().to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 201);
+        let url = format!("http://{}{}", addr, response.headers()["location"].to_str().unwrap());
+        
+        // The first part arrives, then the connection drops
+        let response = client.patch(&url).header("upload-offset", "0").body(pdf[..10].to_vec()).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 204);
+        assert_eq!(response.headers()["upload-offset"], "10");
+        
+        // Resending from the start is refused; the client asks where to resume
+        let response = client.patch(&url).header("upload-offset", "0").body(pdf.clone()).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 409);
+        let status: Value = client.get(&url).send().await.unwrap().json().await.unwrap();
+        assert_eq!(status["offset"], 10);
+        assert_eq!(status["length"], pdf.len());
+        
+        let response = client.patch(&url)
+            .header("content-range", format!("bytes 10-{}/{}", pdf.len() - 1, pdf.len()))
+            .body(pdf[10..].to_vec())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 201);
+        let attachment: Value = response.json().await.unwrap();
+        assert_eq!(attachment["content_type"], "application/pdf");
+        assert_eq!(attachment["filename"], "runbook.pdf");
+        
+        let stored = store.get_document("notes", "runbook").await.unwrap().unwrap();
+        assert!(stored.metadata["attachments"].contains(attachment["hash"].as_str().unwrap()));
+        let body = client.get(format!("http://{}{}", addr, attachment["url"].as_str().unwrap())).send().await.unwrap().bytes().await.unwrap();
+        assert_eq!(body.to_vec(), pdf);
+        assert_eq!(client.get(&url).send().await.unwrap().status().as_u16(), 404);
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_concurrent_upload_patches_append_once() {
+        use p_mo::attachments::{Attachments, DiskAttachmentStore};
+        use p_mo::uploads::UploadSessions;
+        
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(InMemoryVectorStore::new());
+        let attachments = Arc::new(Attachments::new(Arc::new(DiskAttachmentStore::new(temp_dir.path().join("attachments")))));
+        let uploads = Arc::new(UploadSessions::new(temp_dir.path().join("uploads")));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = p_mo::api::router(ApiState::new(store).with_attachments(attachments).with_uploads(uploads));
+        let server = tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        let client = Client::new();
+        
+        let pdf = b"%PDF-1.7 sent twice at once".to_vec();
+        let response = client.post(format!("http://{}/api/uploads?filename=twice.pdf", addr))
+            .header("content-type", "application/pdf")
+            .header("upload-length", pdf.len().to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 201);
+        let url = format!("http://{}{}", addr, response.headers()["location"].to_str().unwrap());
+        
+        // A body longer than what is left is refused before any of it is written
+        let mut too_long = pdf.clone();
+        too_long.push(b'!');
+        let response = client.patch(&url).header("upload-offset", "0").body(too_long).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 400);
+        
+        let send = || client.patch(&url).header("upload-offset", "0").body(pdf.clone()).send();
+        let (first, second) = tokio::join!(send(), send());
+        let mut statuses = vec![first.unwrap().status().as_u16(), second.unwrap().status().as_u16()];
+        statuses.sort();
+        assert_eq!(statuses[0], 201, "{:?}", statuses);
+        assert!(statuses[1] == 404 || statuses[1] == 409, "{:?}", statuses);
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_attachments_upload_and_appear_in_search() {
+        use p_mo::attachments::{Attachments, DiskAttachmentStore};
+        
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("notes", 384).await.unwrap();
+        let mut document = Document::with_placeholder_embedding("Deployment diagram".to_string(), 384);
+        document.id = "deploy".to_string();
+        store.insert_document("notes", document).await.unwrap();
+        
+        let attachments = Arc::new(Attachments::new(Arc::new(DiskAttachmentStore::new(temp_dir.path()))));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = p_mo::api::router(ApiState::new(store).with_attachments(attachments));
+        let server = tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        let client = Client::new();
+        
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        let response = client.post(format!("http://{}/api/collections/notes/entries/deploy/attachments?filename=deploy.png", addr))
+            .header("content-type", "image/png")
+            .body(png.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 201);
+        let attachment: Value = response.json().await.unwrap();
+        assert_eq!(attachment["content_type"], "image/png");
+        let url = attachment["url"].as_str().unwrap().to_string();
+        
+        let body: Value = client.get(format!("http://{}/api/search?q=deployment&collection=notes", addr))
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(body["results"][0]["attachments"][0], url.as_str());
+        
+        let response = client.get(format!("http://{}{}", addr, url)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["content-type"], "image/png");
+        assert_eq!(response.bytes().await.unwrap().to_vec(), png);
+        
+        let response = client.post(format!("http://{}/api/attachments", addr))
+            .header("content-type", "text/x-shellscript")
+            .body("#!/bin/sh")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 415);
+        
+        let response = client.post(format!("http://{}/api/collections/notes/entries/missing/attachments", addr))
+            .header("content-type", "image/png")
+            .body(png)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 404);
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_mcp_manifest_needs_no_credentials() {
+        use p_mo::api_keys::ApiKeyStore;
+        use p_mo::mcp::{ProgmoMcpServer, ServerConfig as McpServerConfig};
+        
+        let store = Arc::new(InMemoryVectorStore::new());
+        let mcp = Arc::new(ProgmoMcpServer::new(McpServerConfig { name: "p-mo".to_string(), version: "1.2.3".to_string() }, store.clone()));
+        let state = ApiState::new(store).with_api_keys(Arc::new(ApiKeyStore::new())).with_mcp_server(mcp);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(p_mo::api::router(state).into_make_service()));
+        let client = Client::new();
+        
+        let response = client.get(format!("http://{}/.well-known/mcp.json", addr)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        let manifest: Value = response.json().await.unwrap();
+        assert_eq!(manifest["version"], "1.2.3");
+        assert!(manifest["tools"].as_array().unwrap().iter().any(|tool| tool["name"] == "search_knowledge"));
+        assert_eq!(manifest["http"]["auth"]["required"], true);
+        assert_eq!(manifest["http"]["auth"]["schemes"][0]["name"], "x-api-key");
+        assert_eq!(manifest["transports"][1]["type"], "websocket");
+        assert_eq!(manifest["transports"][1]["path"], "/mcp");
+        
+        let response = client.get(format!("http://{}/api/collections/docs/count", addr)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_mcp_websocket_cancels_requests_when_the_socket_closes() {
+        use async_trait::async_trait;
+        use futures::{SinkExt, StreamExt};
+        use p_mo::mcp::{ProgmoMcpServer, ServerConfig as McpServerConfig};
+        use p_mo::vector_store::{SearchQuery, SearchResult, VectorStoreError};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use tokio_tungstenite::tungstenite::Message;
+        
+        /// Sets its flag when dropped, i.e. when the search holding it is abandoned
+        struct Abandoned(Arc<AtomicBool>);
+        
+        impl Drop for Abandoned {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+        
+        /// A store whose searches never finish
+        struct Stalled {
+            inner: InMemoryVectorStore,
+            started: Arc<AtomicBool>,
+            abandoned: Arc<AtomicBool>,
+        }
+        
+        #[async_trait]
+        impl VectorStore for Stalled {
+            async fn test_connection(&self) -> Result<(), VectorStoreError> {
+                self.inner.test_connection().await
+            }
+            
+            async fn create_collection(&self, name: &str, vector_size: usize) -> Result<(), VectorStoreError> {
+                self.inner.create_collection(name, vector_size).await
+            }
+            
+            async fn delete_collection(&self, name: &str) -> Result<(), VectorStoreError> {
+                self.inner.delete_collection(name).await
+            }
+            
+            async fn insert_document(&self, collection: &str, document: Document) -> Result<(), VectorStoreError> {
+                self.inner.insert_document(collection, document).await
+            }
+            
+            async fn search(&self, _collection: &str, _query: SearchQuery) -> Result<Vec<SearchResult>, VectorStoreError> {
+                let _abandoned = Abandoned(self.abandoned.clone());
+                self.started.store(true, Ordering::SeqCst);
+                std::future::pending().await
+            }
+        }
+        
+        async fn wait_for(flag: &AtomicBool) {
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while !flag.load(Ordering::SeqCst) {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            }).await.expect("timed out waiting for the search");
+        }
+        
+        let started = Arc::new(AtomicBool::new(false));
+        let abandoned = Arc::new(AtomicBool::new(false));
+        let store = Arc::new(Stalled { inner: InMemoryVectorStore::new(), started: started.clone(), abandoned: abandoned.clone() });
+        store.create_collection("notes", 384).await.unwrap();
+        let mcp = Arc::new(ProgmoMcpServer::new(McpServerConfig { name: "p-mo".to_string(), version: "1.2.3".to_string() }, store.clone()));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = p_mo::api::router(ApiState::new(store).with_mcp_server(mcp));
+        let server = tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/mcp", addr)).await.unwrap();
+        socket.send(Message::Text(r#"{"jsonrpc":"2.0","id":1,"method":"ListTools"}"#.to_string())).await.unwrap();
+        let response = match socket.next().await.unwrap().unwrap() {
+            Message::Text(text) => serde_json::from_str::<Value>(&text).unwrap(),
+            other => panic!("expected a text frame, got {:?}", other),
+        };
+        assert_eq!(response["id"], 1);
+        assert!(response["result"]["tools"].is_array());
+        
+        let search = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "CallTool",
+            "params": {"name": "search_knowledge", "arguments": {"collection_id": "notes", "query": "rollbacks"}},
+        });
+        socket.send(Message::Text(search.to_string())).await.unwrap();
+        wait_for(&started).await;
+        assert!(!abandoned.load(Ordering::SeqCst));
+        
+        // Closing the socket abandons the search it left running
+        socket.close(None).await.unwrap();
+        wait_for(&abandoned).await;
+        
+        server.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_mcp_websocket_calls_are_limited_to_the_key() {
+        use futures::{SinkExt, StreamExt};
+        use p_mo::api_keys::{ApiKeyStore, KeyOptions, Scope};
+        use p_mo::mcp::{ProgmoMcpServer, ServerConfig as McpServerConfig, ACCESS_DENIED};
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+        use tokio_tungstenite::tungstenite::Message;
+        
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("notes", 384).await.unwrap();
+        let keys = Arc::new(ApiKeyStore::new());
+        let options = KeyOptions { scope: Scope::Read, collections: Some(vec!["notes".to_string()]), expires_at: None };
+        let (_, key) = keys.create("reader", options, "test", "test").unwrap();
+        let mcp = Arc::new(ProgmoMcpServer::new(McpServerConfig { name: "p-mo".to_string(), version: "1.2.3".to_string() }, store.clone()));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = p_mo::api::router(ApiState::new(store).with_api_keys(keys).with_mcp_server(mcp));
+        let server = tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        
+        // The upgrade takes the same credentials as the REST routes
+        assert!(tokio_tungstenite::connect_async(format!("ws://{}/mcp", addr)).await.is_err());
+        let mut request = format!("ws://{}/mcp", addr).into_client_request().unwrap();
+        request.headers_mut().insert("x-api-key", key.parse().unwrap());
+        let (mut socket, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+        
+        let call = |id: u64, tool: &str, arguments: Value| {
+            let request = json!({"jsonrpc": "2.0", "id": id, "method": "CallTool", "params": {"name": tool, "arguments": arguments}});
+            Message::Text(request.to_string())
+        };
+        let requests = [
+            call(1, "search_knowledge", json!({"collection_id": "notes", "query": "rollbacks"})),
+            call(2, "search_knowledge", json!({"collection_id": "secrets", "query": "rollbacks"})),
+            call(3, "add_knowledge_entry", json!({"collection_id": "notes", "title": "Rollbacks", "content": "Rollbacks need a ticket"})),
+            call(4, "list_api_keys", json!({})),
+        ];
+        for request in requests {
+            socket.send(request).await.unwrap();
+        }
+        let mut errors = std::collections::HashMap::new();
+        for _ in 0..4 {
+            let Message::Text(response) = socket.next().await.unwrap().unwrap() else { panic!("expected a text frame") };
+            let response: Value = serde_json::from_str(&response).unwrap();
+            errors.insert(response["id"].as_u64().unwrap(), response["error"]["code"].as_i64());
+        }
+        assert_ne!(errors[&1], Some(ACCESS_DENIED));
+        assert_eq!(errors[&2], Some(ACCESS_DENIED));
+        assert_eq!(errors[&3], Some(ACCESS_DENIED));
+        assert_eq!(errors[&4], Some(ACCESS_DENIED));
+        
+        server.abort();
+    }
 }
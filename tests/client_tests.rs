@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod client_tests {
+    use p_mo::api::ApiState;
+    use p_mo::client::{entry_id, ClientError, KnowledgeClient, NewEntry};
+    use p_mo::server::{Server, ServerConfig};
+    use p_mo::ui::UiState;
+    use p_mo::vector_store::{Document, InMemoryVectorStore, VectorStore};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const BASE: &str = "http://127.0.0.1:8085";
+    const API_KEY: &str = "admin-secret";
+
+    #[tokio::test]
+    async fn test_knowledge_client() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("notes", 384).await.unwrap();
+        let document = Document {
+            id: "entry-1".to_string(),
+            content: "Rust ownership, borrowing and lifetimes".to_string(),
+            embedding: vec![0.0; 384],
+            metadata: Default::default(),
+        }
+        .with_title("Ownership");
+        store.insert_document("notes", document).await.unwrap();
+        
+        let config = ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8085,
+            timeout: Duration::from_secs(30),
+            daemon: false,
+            pid_file: None,
+            log_file: None,
+            logging: Default::default(),
+            container: false,
+        };
+        let server = Server::new(config)
+            .with_api(ApiState::new(store.clone()))
+            .with_admin_ui(UiState::new(store.clone(), Some(API_KEY.to_string())));
+        let handle = server.start().await.expect("Failed to start server");
+        
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let client = KnowledgeClient::new(BASE).with_api_key(API_KEY);
+        
+        let id = client.add(&NewEntry {
+            title: "Test Entry".to_string(),
+            content: "This is a test knowledge entry".to_string(),
+            tags: vec!["test".to_string()],
+        }).await.unwrap();
+        let entry = client.get(&id).await.unwrap();
+        assert_eq!(entry.title, "Test Entry");
+        
+        let rows = client.search("notes", "rust", 5).await.unwrap();
+        assert_eq!(rows[0].title, "Ownership");
+        assert_eq!(entry_id(&rows[0]), "entry-1");
+        
+        assert_eq!(client.collections().await.unwrap(), vec!["notes"]);
+        client.delete("notes", "entry-1").await.unwrap();
+        assert!(store.get_document("notes", "entry-1").await.unwrap().is_none());
+        
+        // Admin calls without the key surface the server's error
+        let anonymous = KnowledgeClient::new(BASE);
+        match anonymous.collections().await {
+            Err(ClientError::Status { status, message }) => {
+                assert_eq!(status, 401);
+                assert!(message.contains("admin API key"));
+            },
+            other => panic!("expected a 401, got {:?}", other),
+        }
+        
+        handle.shutdown().await.expect("Failed to shutdown server");
+    }
+}
@@ -1,4 +1,4 @@
-use p_mo::config::{Config, ConfigError};
+use p_mo::config::{Config, ConfigError, EmbeddingProviderConfig, ReadSelection};
 use std::fs;
 use std::path::PathBuf;
 use tempfile::TempDir;
@@ -16,7 +16,7 @@ fn test_config_defaults() {
 fn test_config_load_and_save() -> Result<(), ConfigError> {
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
     let config_path = temp_dir.path().join("test_config.toml");
-
+    
     // Create a custom config
     let mut config = Config::default();
     config.server.host = "0.0.0.0".to_string();
@@ -89,3 +89,305 @@ daemon = true
     
     Ok(())
 }
+
+#[test]
+fn test_pool_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("pool_config.toml");
+    
+    let config_content = r#"
+[pool]
+max_connections = 32
+wait_timeout_ms = 250
+"#;
+    fs::write(&config_path, config_content).expect("Failed to write config file");
+    
+    let config = Config::load(&config_path)?;
+    assert_eq!(config.pool.max_connections, 32);
+    assert_eq!(config.pool.wait_timeout_ms, 250);
+    assert_eq!(config.pool.create_timeout_ms, 5000);
+    
+    Ok(())
+}
+
+#[test]
+fn test_ingest_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("ingest_config.toml");
+    
+    fs::write(&config_path, "[ingest]\nparallelism = 16\n").expect("Failed to write config file");
+    assert_eq!(Config::load(&config_path)?.ingest.parallelism, 16);
+    assert_eq!(Config::default().ingest.parallelism, 4);
+    
+    Ok(())
+}
+
+#[test]
+fn test_audit_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("audit_config.toml");
+    
+    fs::write(&config_path, "[audit]\nfile = \"/var/log/p-mo/config-audit.log\"\n").expect("Failed to write config file");
+    let config = Config::load(&config_path)?;
+    assert_eq!(config.audit.file, Some(PathBuf::from("/var/log/p-mo/config-audit.log")));
+    assert_eq!(config.audit.max_entries, 1000);
+    assert!(Config::default().audit.file.is_none());
+    
+    Ok(())
+}
+
+#[test]
+fn test_attachments_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("attachments_config.toml");
+    
+    fs::write(&config_path, "[attachments]\nmax_bytes = 1024\n\n[attachments.bucket]\nbucket = \"kb-attachments\"\nprefix = \"att/\"\n").expect("Failed to write config file");
+    let config = Config::load(&config_path)?;
+    assert_eq!(config.attachments.max_bytes, 1024);
+    let bucket = config.attachments.bucket.expect("bucket configured");
+    assert_eq!(bucket.bucket, "kb-attachments");
+    assert_eq!(bucket.region, "us-east-1");
+    assert!(Config::default().attachments.allowed_types.contains(&"image/png".to_string()));
+    
+    Ok(())
+}
+
+#[test]
+fn test_ocr_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("ocr_config.toml");
+    
+    fs::write(&config_path, "[ingest.ocr]\nenabled = true\nmin_confidence = 75.0\n").expect("Failed to write config file");
+    let config = Config::load(&config_path)?;
+    assert!(config.ingest.ocr.enabled);
+    assert_eq!(config.ingest.ocr.min_confidence, 75.0);
+    assert_eq!(config.ingest.ocr.language, "eng");
+    assert_eq!(config.ingest.parallelism, 4);
+    assert!(!Config::default().ingest.ocr.enabled);
+    
+    Ok(())
+}
+
+#[test]
+fn test_telemetry_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("telemetry_config.toml");
+    assert!(!Config::default().telemetry.enabled);
+    
+    fs::write(&config_path, "telemetry = false\n").expect("Failed to write config file");
+    let config = Config::load(&config_path)?;
+    assert!(!config.telemetry.enabled);
+    assert!(config.telemetry.endpoint.is_none());
+    
+    fs::write(&config_path, "[telemetry]\nenabled = true\nendpoint = \"https://telemetry.example.com/v1\"\n").expect("Failed to write config file");
+    let config = Config::load(&config_path)?;
+    assert!(config.telemetry.enabled);
+    assert_eq!(config.telemetry.endpoint.as_deref(), Some("https://telemetry.example.com/v1"));
+    assert_eq!(config.telemetry.interval_secs, 86400);
+    
+    Ok(())
+}
+
+#[test]
+fn test_embedding_providers_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("embedding_config.toml");
+    
+    let config_content = r#"
+[[embedding.providers]]
+kind = "local"
+name = "minilm"
+model = "minilm"
+dimension = 384
+
+[[embedding.providers]]
+kind = "remote"
+name = "openai"
+url = "https://api.openai.com/v1/embeddings"
+model = "text-embedding-3-small"
+api_key_env = "OPENAI_API_KEY"
+dimension = 384
+requests_per_minute = 500
+"#;
+    fs::write(&config_path, config_content).expect("Failed to write config file");
+    
+    let config = Config::load(&config_path)?;
+    let names: Vec<&str> = config.embedding.providers.iter().map(|provider| provider.name()).collect();
+    assert_eq!(names, vec!["minilm", "openai"]);
+    match &config.embedding.providers[1] {
+        EmbeddingProviderConfig::Remote { api_key_env, timeout_secs, requests_per_minute, coalesce_window_ms, max_batch_size, .. } => {
+            assert_eq!(api_key_env.as_deref(), Some("OPENAI_API_KEY"));
+            assert_eq!(*timeout_secs, 30);
+            assert_eq!(*requests_per_minute, Some(500));
+            assert_eq!((*coalesce_window_ms, *max_batch_size), (10, 64));
+        },
+        other => panic!("expected a remote provider, got {:?}", other),
+    }
+    assert!(Config::default().embedding.providers.is_empty());
+    
+    Ok(())
+}
+
+#[test]
+fn test_qdrant_routing_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("qdrant_config.toml");
+    
+    let config_content = r#"
+[qdrant]
+default_instance = "local"
+
+[[qdrant.instances]]
+name = "local"
+url = "http://localhost:6334"
+
+[[qdrant.instances]]
+name = "cloud"
+url = "https://docs.cloud.qdrant.io:6334"
+api_key_env = "PMO_QDRANT_CLOUD_KEY"
+read_urls = ["https://replica-1.cloud.qdrant.io:6334", "https://replica-2.cloud.qdrant.io:6334"]
+read_selection = "least_latency"
+
+[[qdrant.routes]]
+pattern = "^docs-"
+instance = "cloud"
+"#;
+    fs::write(&config_path, config_content).expect("Failed to write config file");
+    
+    let config = Config::load(&config_path)?;
+    assert_eq!(config.qdrant.instances.len(), 2);
+    assert_eq!(config.qdrant.instances[1].api_key_env.as_deref(), Some("PMO_QDRANT_CLOUD_KEY"));
+    assert_eq!(config.qdrant.instances[1].read_urls.len(), 2);
+    assert_eq!(config.qdrant.instances[1].read_selection, ReadSelection::LeastLatency);
+    assert_eq!(config.qdrant.instances[0].read_selection, ReadSelection::RoundRobin);
+    assert_eq!(config.qdrant.routes[0].instance, "cloud");
+    assert_eq!(config.qdrant.default_instance.as_deref(), Some("local"));
+    assert!(Config::default().qdrant.instances.is_empty());
+    
+    Ok(())
+}
+
+#[test]
+fn test_maintenance_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("maintenance_config.toml");
+    
+    fs::write(&config_path, "[maintenance]\ninterval_secs = 60\n").expect("Failed to write config file");
+    let config = Config::load(&config_path)?;
+    assert!(config.maintenance.enabled);
+    assert_eq!(config.maintenance.interval_secs, 60);
+    assert_eq!(Config::default().maintenance.interval_secs, 900);
+    
+    Ok(())
+}
+
+#[test]
+fn test_retrieval_log_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("retrieval_config.toml");
+    
+    fs::write(&config_path, "[retrieval_log]\nfile = \"/var/log/p-mo/retrievals.log\"\nmax_entries = 500\n").expect("Failed to write config file");
+    let config = Config::load(&config_path)?;
+    assert_eq!(config.retrieval_log.file, Some(std::path::PathBuf::from("/var/log/p-mo/retrievals.log")));
+    assert_eq!(config.retrieval_log.max_entries, 500);
+    assert!(Config::default().retrieval_log.file.is_none());
+    
+    Ok(())
+}
+
+#[test]
+fn test_safe_mode_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("safe_mode_config.toml");
+    
+    fs::write(&config_path, "[safe_mode]\nenabled = true\ncollections = [\"inbox\"]\n").expect("Failed to write config file");
+    let config = Config::load(&config_path)?;
+    assert!(config.safe_mode.enabled);
+    assert_eq!(config.safe_mode.collections, vec!["inbox".to_string()]);
+    assert!(!Config::default().safe_mode.enabled);
+    
+    Ok(())
+}
+
+#[test]
+fn test_injection_scoring_config() -> Result<(), ConfigError> {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("injection_config.toml");
+    
+    fs::write(&config_path, "[injection_scoring]\nenabled = true\n").expect("Failed to write config file");
+    assert!(Config::load(&config_path)?.injection_scoring.enabled);
+    assert!(!Config::default().injection_scoring.enabled);
+    
+    Ok(())
+}
+
+#[test]
+fn test_config_rejects_unknown_keys_and_conflicts() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("invalid_settings.toml");
+    
+    fs::write(&config_path, "[server]\nhots = \"0.0.0.0\"\n").expect("Failed to write config file");
+    assert!(matches!(Config::load(&config_path), Err(ConfigError::ParseError(_))));
+    
+    fs::write(&config_path, "[server]\ndaemon = true\npid_file = \"\"\n").expect("Failed to write config file");
+    match Config::load(&config_path) {
+        Err(ConfigError::Invalid(problems)) => {
+            assert_eq!(problems.len(), 1);
+            assert_eq!(problems[0].path, "server.pid_file");
+            assert_eq!(problems[0].line, Some(3));
+        },
+        other => panic!("expected an invalid config, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_config_rejects_proxy_for_qdrant() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("qdrant_proxy.toml");
+    
+    let config_content = r#"
+[network]
+proxy = "http://proxy.internal:3128"
+
+[[qdrant.instances]]
+name = "local"
+url = "http://qdrant.internal:6334"
+"#;
+    fs::write(&config_path, config_content).expect("Failed to write config file");
+    match Config::load(&config_path) {
+        Err(ConfigError::Invalid(problems)) => {
+            assert_eq!(problems.len(), 1);
+            assert_eq!(problems[0].path, "network.proxy");
+            assert!(problems[0].message.contains("qdrant.instances[0]"), "{}", problems[0].message);
+        },
+        other => panic!("expected an invalid config, got {:?}", other.map(|_| ())),
+    }
+    
+    // Qdrant reached directly, past the proxy, is fine
+    let config_content = config_content.replace("3128\"\n", "3128\"\nno_proxy = [\"qdrant.internal\"]\n");
+    fs::write(&config_path, config_content).expect("Failed to write config file");
+    assert!(Config::load(&config_path).is_ok());
+}
+
+#[test]
+fn test_config_rejects_wal_for_qdrant() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let config_path = temp_dir.path().join("qdrant_wal.toml");
+    
+    let config_content = r#"
+[wal]
+enabled = true
+
+[[qdrant.instances]]
+name = "local"
+url = "http://qdrant.internal:6334"
+"#;
+    fs::write(&config_path, config_content).expect("Failed to write config file");
+    match Config::load(&config_path) {
+        Err(ConfigError::Invalid(problems)) => {
+            assert_eq!(problems.len(), 1);
+            assert_eq!(problems[0].path, "wal.enabled");
+        },
+        other => panic!("expected an invalid config, got {:?}", other.map(|_| ())),
+    }
+}
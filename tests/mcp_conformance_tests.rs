@@ -0,0 +1,167 @@
+//! Conformance tests for MCP protocol responses.
+//!
+//! Each `tests/conformance/<case>.jsonl` file is a session: raw JSON-RPC
+//! requests, one per line, sent in order to a fresh seeded server. The
+//! responses, with volatile values masked, must equal the recorded ones in
+//! `<case>.golden.json`. After an intended change to the protocol, run
+//! `UPDATE_GOLDEN=1 cargo test --test mcp_conformance_tests` and review the
+//! diff of the golden files.
+
+use p_mo::determinism::Determinism;
+use p_mo::mcp::{ProgmoMcpServer, ServerConfig};
+use p_mo::selftest::WordHashEmbedder;
+use p_mo::vector_store::{CollectionInfo, InMemoryVectorStore, VectorStore};
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Dimension of the fixture collection and embedder
+const DIMENSION: usize = 16;
+
+/// Keys whose values change from run to run even on a seeded server
+const VOLATILE_KEYS: &[&str] = &["duration_ms", "total_ms", "slowest_backend_stage"];
+
+/// Resource URIs the sessions must read between them, by prefix
+const RESOURCE_PREFIXES: &[&str] = &["knowledge://collections/docs", "knowledge://collections/docs/entries/", "jobs://"];
+
+fn conformance_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("conformance")
+}
+
+/// The sessions, sorted by name
+fn cases() -> Vec<PathBuf> {
+    let mut cases: Vec<PathBuf> = fs::read_dir(conformance_dir())
+        .expect("tests/conformance is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "jsonl"))
+        .collect();
+    cases.sort();
+    cases
+}
+
+/// The requests of a session, skipping blank lines
+fn requests(case: &Path) -> Vec<String> {
+    fs::read_to_string(case).unwrap()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// A fresh server over an empty `docs` collection, with seeded ids and clock
+async fn fixture_server() -> ProgmoMcpServer {
+    let store = Arc::new(InMemoryVectorStore::new());
+    store.create_collection("docs", DIMENSION).await.unwrap();
+    let server = ProgmoMcpServer::new(ServerConfig { name: "conformance".to_string(), version: "0.0.0".to_string() }, store)
+        .with_embedding_provider(Arc::new(WordHashEmbedder::new(DIMENSION)))
+        .unwrap()
+        .with_determinism(Determinism::seeded(42));
+    server.registry().register(CollectionInfo::new("docs", DIMENSION));
+    server
+}
+
+/// `value` with UUIDs, timestamps and the values of volatile keys replaced
+/// by placeholders, looking inside strings that hold JSON too
+fn mask(value: Value) -> Value {
+    let uuid = Regex::new(r"[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}").unwrap();
+    let timestamp = Regex::new(r"\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:\d{2})").unwrap();
+    mask_with(value, &uuid, &timestamp)
+}
+
+fn mask_with(value: Value, uuid: &Regex, timestamp: &Regex) -> Value {
+    match value {
+        Value::String(text) => match serde_json::from_str::<Value>(&text) {
+            Ok(embedded @ (Value::Object(_) | Value::Array(_))) => Value::String(mask_with(embedded, uuid, timestamp).to_string()),
+            _ => {
+                let text = uuid.replace_all(&text, "<uuid>");
+                Value::String(timestamp.replace_all(&text, "<timestamp>").into_owned())
+            },
+        },
+        Value::Array(items) => Value::Array(items.into_iter().map(|item| mask_with(item, uuid, timestamp)).collect()),
+        Value::Object(fields) => Value::Object(fields.into_iter()
+            .map(|(key, field)| {
+                let field = if VOLATILE_KEYS.contains(&key.as_str()) { json!("<masked>") } else { mask_with(field, uuid, timestamp) };
+                (key, field)
+            })
+            .collect()),
+        other => other,
+    }
+}
+
+/// Replay a session against a fresh server: each request with its masked response
+async fn replay(case: &Path) -> Value {
+    let server = fixture_server().await;
+    let mut exchanges = Vec::new();
+    for request in requests(case) {
+        let response: Value = serde_json::from_str(&server.handle_request(&request).await).unwrap();
+        exchanges.push(json!({ "request": request, "response": mask(response) }));
+    }
+    Value::Array(exchanges)
+}
+
+#[tokio::test]
+async fn test_responses_match_golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+    for case in cases() {
+        let golden_path = case.with_extension("golden.json");
+        let actual = replay(&case).await;
+        if update {
+            fs::write(&golden_path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+            continue;
+        }
+        
+        let golden: Value = match fs::read_to_string(&golden_path) {
+            Ok(text) => serde_json::from_str(&text).unwrap(),
+            Err(_) => {
+                failures.push(format!("{}: no golden file; run with UPDATE_GOLDEN=1 to record it", case.display()));
+                continue;
+            },
+        };
+        let (Value::Array(actual), Value::Array(golden)) = (actual, golden) else {
+            panic!("{} is not an array of exchanges", golden_path.display());
+        };
+        if actual.len() != golden.len() {
+            failures.push(format!("{}: {} exchanges, golden file has {}", case.display(), actual.len(), golden.len()));
+        }
+        for (index, (actual, golden)) in actual.iter().zip(&golden).enumerate() {
+            if actual != golden {
+                failures.push(format!(
+                    "{} request {}:\n  {}\nexpected: {}\n  actual: {}",
+                    case.display(), index + 1, actual["request"], golden["response"], actual["response"]
+                ));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{} conformance failures:\n{}", failures.len(), failures.join("\n\n"));
+}
+
+#[tokio::test]
+async fn test_sessions_cover_every_tool_and_resource() {
+    let listed: Value = serde_json::from_str(&fixture_server().await.handle_request(r#"{"jsonrpc":"2.0","id":1,"method":"ListTools","params":{}}"#).await).unwrap();
+    let tools: BTreeSet<String> = listed["result"]["tools"].as_array().unwrap()
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap().to_string())
+        .collect();
+    
+    let mut called = BTreeSet::new();
+    let mut uris = Vec::new();
+    for request in cases().iter().flat_map(|case| requests(case)) {
+        let Ok(request) = serde_json::from_str::<Value>(&request) else { continue };
+        if let Some(name) = request["params"]["name"].as_str() {
+            called.insert(name.to_string());
+        }
+        if let Some(uri) = request["params"]["uri"].as_str() {
+            uris.push(uri.to_string());
+        }
+    }
+    
+    let uncovered: Vec<&String> = tools.difference(&called).collect();
+    assert!(uncovered.is_empty(), "no conformance session calls {:?}", uncovered);
+    for prefix in RESOURCE_PREFIXES {
+        assert!(uris.iter().any(|uri| uri.starts_with(prefix)), "no conformance session reads a {} resource", prefix);
+    }
+}
@@ -15,6 +15,8 @@ mod server_tests {
             daemon: false,
             pid_file: None,
             log_file: None,
+            logging: Default::default(),
+            container: false,
         };
         
         let server = Server::new(config);
@@ -47,6 +49,10 @@ mod server_tests {
             daemon: true,
             pid_file: None,
             log_file: None,
+            logging: Default::default(),
+            unix_socket: None,
+            http: Default::default(),
+            container: false,
         };
 
         let server_config: ServerConfig = config_server.into();
@@ -56,4 +62,254 @@ mod server_tests {
         assert_eq!(server_config.timeout, Duration::from_secs(60));
         assert!(server_config.daemon);
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_server_unix_socket() {
+        use std::os::unix::fs::PermissionsExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("p-mo.sock");
+        let server = Server::new(ServerConfig { pid_file: None, log_file: None, ..ServerConfig::default() })
+            .with_unix_socket(config::UnixSocketConfig { path: path.clone(), mode: 0o600 });
+        let handle = server.start().await.expect("Failed to start server");
+        
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        
+        let mut stream = tokio::net::UnixStream::connect(&path).await.expect("Failed to connect");
+        stream.write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("OK"));
+        
+        handle.shutdown().await.expect("Failed to shutdown server");
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_server_unix_socket_serves_mcp() {
+        use futures::{SinkExt, StreamExt};
+        use p_mo::api::ApiState;
+        use p_mo::mcp::{ProgmoMcpServer, ServerConfig as McpServerConfig};
+        use p_mo::vector_store::InMemoryVectorStore;
+        use std::sync::Arc;
+        use tokio_tungstenite::tungstenite::Message;
+        
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("p-mo.sock");
+        let store = Arc::new(InMemoryVectorStore::new());
+        let mcp = Arc::new(ProgmoMcpServer::new(McpServerConfig { name: "p-mo".to_string(), version: "1.2.3".to_string() }, store.clone()));
+        let server = Server::new(ServerConfig { pid_file: None, log_file: None, ..ServerConfig::default() })
+            .with_api(ApiState::new(store).with_mcp_server(mcp))
+            .with_unix_socket(config::UnixSocketConfig { path: path.clone(), mode: 0o600 });
+        let handle = server.start().await.expect("Failed to start server");
+        
+        let stream = tokio::net::UnixStream::connect(&path).await.expect("Failed to connect");
+        let (mut socket, _) = tokio_tungstenite::client_async("ws://localhost/mcp", stream).await.expect("WebSocket handshake failed");
+        socket.send(Message::Text(r#"{"jsonrpc":"2.0","id":1,"method":"ListTools"}"#.to_string())).await.unwrap();
+        let Message::Text(response) = socket.next().await.unwrap().unwrap() else { panic!("expected a text frame") };
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["id"], 1);
+        assert!(response["result"]["tools"].is_array());
+        socket.close(None).await.unwrap();
+        
+        handle.shutdown().await.expect("Failed to shutdown server");
+    }
+    
+    #[tokio::test]
+    async fn test_server_refuses_to_start_when_a_migration_fails() {
+        use async_trait::async_trait;
+        use p_mo::migrations::{Migration, MigrationContext, MigrationError, Migrator};
+        use p_mo::server::ServerError;
+        
+        struct Broken;
+        
+        #[async_trait]
+        impl Migration for Broken {
+            fn version(&self) -> u32 {
+                1
+            }
+            
+            fn description(&self) -> &str {
+                "broken"
+            }
+            
+            async fn up(&self, _ctx: &MigrationContext) -> Result<(), String> {
+                Err("disk full".to_string())
+            }
+            
+            async fn down(&self, _ctx: &MigrationContext) -> Result<(), String> {
+                Ok(())
+            }
+        }
+        
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let migrator = Migrator::new(temp_dir.path().to_path_buf(), vec![Box::new(Broken)]).unwrap();
+        let server = Server::new(ServerConfig { port: 0, pid_file: None, log_file: None, ..ServerConfig::default() })
+            .with_migrations(migrator);
+        
+        let err = server.start().await.err().expect("Server started despite a failed migration");
+        assert!(matches!(err, ServerError::MigrationError(MigrationError::RolledBack { version: 1, .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_container_server_writes_no_pid_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let pid_file = temp_dir.path().join("p-mo.pid");
+        let server = Server::new(ServerConfig {
+            port: 8087,
+            daemon: true,
+            container: true,
+            pid_file: Some(pid_file.clone()),
+            log_file: None,
+            ..ServerConfig::default()
+        });
+        let handle = server.start().await.expect("Failed to start server");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        let response = Client::new().get("http://127.0.0.1:8087/health")
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(!pid_file.exists());
+        
+        handle.shutdown().await.expect("Failed to shutdown server");
+    }
+    
+    #[tokio::test]
+    async fn test_server_answers_cors_preflights() {
+        let http = config::HttpConfig {
+            cors_origins: vec!["https://notes.example.com".to_string()],
+            ..Default::default()
+        };
+        let server = Server::new(ServerConfig { port: 8088, pid_file: None, log_file: None, ..ServerConfig::default() })
+            .with_http(http);
+        let handle = server.start().await.expect("Failed to start server");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        let client = Client::new();
+        let preflight = client.request(reqwest::Method::OPTIONS, "http://127.0.0.1:8088/api/knowledge")
+            .header("origin", "https://notes.example.com")
+            .header("access-control-request-method", "POST")
+            .header("access-control-request-headers", "content-type, x-api-key")
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(preflight.status().as_u16(), 204);
+        assert_eq!(preflight.headers()["access-control-allow-origin"], "https://notes.example.com");
+        assert!(preflight.headers()["access-control-allow-methods"].to_str().unwrap().contains("POST"));
+        
+        let response = client.get("http://127.0.0.1:8088/health")
+            .header("origin", "https://notes.example.com")
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://notes.example.com");
+        assert_eq!(response.headers()["x-content-type-options"], "nosniff");
+        
+        let foreign = client.request(reqwest::Method::OPTIONS, "http://127.0.0.1:8088/api/knowledge")
+            .header("origin", "https://evil.example.com")
+            .header("access-control-request-method", "POST")
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(foreign.status().as_u16(), 403);
+        assert!(!foreign.headers().contains_key("access-control-allow-origin"));
+        
+        handle.shutdown().await.expect("Failed to shutdown server");
+    }
+    
+    #[tokio::test]
+    async fn test_server_from_config_serves_the_configured_api() {
+        // Keep migrations and the collection registry out of the real data directory
+        let data_home = tempfile::TempDir::new().unwrap();
+        std::env::set_var("XDG_DATA_HOME", data_home.path());
+        
+        let mut config = config::Config::default();
+        config.server.port = 8089;
+        config.server.pid_file = None;
+        config.server.log_file = None;
+        config.admin.api_key = Some("admin-key".to_string());
+        config.audit.file = Some(data_home.path().join("audit.log"));
+        config.query_cache.enabled = true;
+        let server = Server::from_config(&config).await.expect("Failed to build server");
+        let handle = server.start().await.expect("Failed to start server");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        let client = Client::new();
+        let manifest: serde_json::Value = client.get("http://127.0.0.1:8089/.well-known/mcp.json")
+            .send().await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(manifest["name"], "p-mo");
+        assert_eq!(manifest["http"]["auth"]["required"], true);
+        
+        let response = client.get("http://127.0.0.1:8089/api/search?q=rust&collection=notes").send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+        let response = client.get("http://127.0.0.1:8089/ui").send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        
+        // The change feed is on by default
+        let changes: serde_json::Value = client.get("http://127.0.0.1:8089/api/changes")
+            .header("x-api-key", "admin-key")
+            .send().await.unwrap()
+            .json().await.unwrap();
+        assert!(changes["events"].is_array());
+        
+        // So are attachments and resumable uploads, kept in the data directory
+        let response = client.post("http://127.0.0.1:8089/api/uploads")
+            .header("x-api-key", "admin-key")
+            .header("upload-length", "5")
+            .header("content-type", "application/pdf")
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 201);
+        assert!(data_home.path().join("p-mo/attachments").is_dir());
+        
+        let metrics = client.get("http://127.0.0.1:8089/metrics")
+            .header("x-api-key", "admin-key")
+            .send().await.unwrap()
+            .text().await.unwrap();
+        assert!(metrics.contains("pmo_query_cache_hits_total 0"));
+        
+        // Key changes are written to the configured audit file
+        let response = client.post("http://127.0.0.1:8089/api/admin/keys")
+            .header("x-api-key", "admin-key")
+            .json(&serde_json::json!({ "name": "ci" }))
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 201);
+        let audit = std::fs::read_to_string(data_home.path().join("audit.log")).unwrap();
+        assert_eq!(audit.lines().count(), 1);
+
+        handle.shutdown().await.expect("Failed to shutdown server");
+    }
+    
+    #[tokio::test]
+    async fn test_server_from_config_refuses_an_unusable_attachment_directory() {
+        use p_mo::server::ServerError;
+        
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let file = temp_dir.path().join("not-a-directory");
+        std::fs::write(&file, b"").unwrap();
+        let mut config = config::Config::default();
+        config.attachments.dir = Some(file.join("attachments"));
+        
+        let err = Server::from_config(&config).await.err().expect("Server built with an unusable attachment directory");
+        assert!(matches!(err, ServerError::SetupError(_)));
+    }
+    
+    #[tokio::test]
+    async fn test_server_from_config_refuses_a_missing_reranker_model() {
+        use p_mo::server::ServerError;
+        
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = config::Config::default();
+        config.reranker.model_path = Some(temp_dir.path().join("no-such-model"));
+        
+        let err = Server::from_config(&config).await.err().expect("Server built without its reranker model");
+        assert!(matches!(err, ServerError::SetupError(message) if message.contains("cross-encoder")));
+    }
 }
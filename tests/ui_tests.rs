@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod ui_tests {
+    use p_mo::server::{Server, ServerConfig};
+    use p_mo::ui::UiState;
+    use p_mo::vector_store::{Document, InMemoryVectorStore, VectorStore};
+    use reqwest::Client;
+    use serde_json::Value;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const BASE: &str = "http://127.0.0.1:8083/ui";
+    const API_KEY: &str = "admin-secret";
+
+    #[tokio::test]
+    async fn test_admin_ui() {
+        let store = Arc::new(InMemoryVectorStore::new());
+        store.create_collection("notes", 3).await.unwrap();
+        for id in ["a", "b"] {
+            store.insert_document("notes", Document {
+                id: id.to_string(),
+                content: format!("note {}", id),
+                embedding: vec![1.0, 0.0, 0.0],
+                metadata: Default::default(),
+            }).await.unwrap();
+        }
+        
+        let config = ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8083,
+            timeout: Duration::from_secs(30),
+            daemon: false,
+            pid_file: None,
+            log_file: None,
+            logging: Default::default(),
+            container: false,
+        };
+        let server = Server::new(config)
+            .with_admin_ui(UiState::new(store.clone(), Some(API_KEY.to_string())));
+        let handle = server.start().await.expect("Failed to start server");
+        
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let client = Client::new();
+        
+        // The page itself is public
+        let response = client.get(BASE).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(response.text().await.unwrap().contains("<html"));
+        
+        // The API requires the admin key
+        let response = client.get(format!("{}/api/collections", BASE)).send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+        
+        let response = client.get(format!("{}/api/collections", BASE))
+            .header("x-api-key", "wrong")
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+        
+        let body: Value = client.get(format!("{}/api/collections", BASE))
+            .header("x-api-key", API_KEY)
+            .send().await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(body["collections"], serde_json::json!(["notes"]));
+        
+        let body: Value = client.get(format!("{}/api/collections/notes/entries?limit=1", BASE))
+            .header("x-api-key", API_KEY)
+            .send().await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(body["entries"].as_array().unwrap().len(), 1);
+        assert_eq!(body["entries"][0]["id"], "a");
+
+        // The cursor is opaque; following it gives the next entry
+        let body: Value = client.get(format!("{}/api/collections/notes/entries", BASE))
+            .query(&[("limit", "1"), ("offset", body["next_offset"].as_str().unwrap())])
+            .header("x-api-key", API_KEY)
+            .send().await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(body["entries"][0]["id"], "b");
+        
+        let response = client.delete(format!("{}/api/collections/notes/entries/a", BASE))
+            .header("Authorization", format!("Bearer {}", API_KEY))
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        
+        let page = store.list_documents("notes", None, 10).await.unwrap();
+        assert_eq!(page.documents.len(), 1);
+        assert_eq!(page.documents[0].id, "b");
+        
+        handle.shutdown().await.expect("Failed to shutdown server");
+    }
+}
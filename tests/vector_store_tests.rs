@@ -21,6 +21,8 @@ mod vector_store_tests {
             url: qdrant_url,
             timeout: Duration::from_secs(5),
             max_connections: 5,
+            pool_wait_timeout: Some(Duration::from_secs(5)),
+            pool_create_timeout: Some(Duration::from_secs(5)),
             api_key: std::env::var("QDRANT_API_KEY").ok(),
             retry_max_elapsed_time: Duration::from_secs(30),
             retry_initial_interval: Duration::from_millis(100),
@@ -61,6 +63,8 @@ mod vector_store_tests {
             url: qdrant_url,
             timeout: Duration::from_secs(1), // Short timeout to trigger retries
             max_connections: 3,
+            pool_wait_timeout: Some(Duration::from_secs(5)),
+            pool_create_timeout: Some(Duration::from_secs(5)),
             api_key: std::env::var("QDRANT_API_KEY").ok(),
             retry_max_elapsed_time: Duration::from_secs(10),
             retry_initial_interval: Duration::from_millis(100),
@@ -92,6 +96,8 @@ mod vector_store_tests {
             url: qdrant_url,
             timeout: Duration::from_secs(5),
             max_connections: 5, // Set pool size
+            pool_wait_timeout: Some(Duration::from_secs(5)),
+            pool_create_timeout: Some(Duration::from_secs(5)),
             api_key: std::env::var("QDRANT_API_KEY").ok(),
             retry_max_elapsed_time: Duration::from_secs(30),
             retry_initial_interval: Duration::from_millis(100),
@@ -124,6 +130,12 @@ mod vector_store_tests {
             let result = handle.await.expect("Task panicked");
             assert!(result.is_ok(), "Task {} failed: {:?}", i, result);
         }
+        
+        let metrics = connector.pool_metrics().expect("Qdrant connector reports pool metrics");
+        assert_eq!(metrics.max_size, 5);
+        assert!(metrics.size <= 5);
+        assert!(metrics.checkouts >= 20);
+        assert_eq!(metrics.wait_timeouts, 0);
     }
     
     #[tokio::test]
@@ -142,6 +154,8 @@ mod vector_store_tests {
             url: qdrant_url,
             timeout: Duration::from_secs(5),
             max_connections: 5,
+            pool_wait_timeout: Some(Duration::from_secs(5)),
+            pool_create_timeout: Some(Duration::from_secs(5)),
             api_key: std::env::var("QDRANT_API_KEY").ok(),
             retry_max_elapsed_time: Duration::from_secs(30),
             retry_initial_interval: Duration::from_millis(100),
@@ -164,16 +178,19 @@ mod vector_store_tests {
                 id: Uuid::new_v4().to_string(),
                 content: "This is a test document about artificial intelligence".to_string(),
                 embedding: vec![1.0, 0.5, 0.1],
+                metadata: Default::default(),
             },
             Document {
                 id: Uuid::new_v4().to_string(),
                 content: "Document about machine learning and neural networks".to_string(),
                 embedding: vec![0.9, 0.4, 0.2],
+                metadata: Default::default(),
             },
             Document {
                 id: Uuid::new_v4().to_string(),
                 content: "Information about databases and storage systems".to_string(),
                 embedding: vec![0.1, 0.2, 0.9],
+                metadata: Default::default(),
             },
         ];
         